@@ -91,7 +91,7 @@ pub trait ImageApi: Send + Sync {
         name: &str,
         force: bool,
         noprune: bool,
-    ) -> Box<dyn Future<Item = Vec<ImageDeleteResponseItem>, Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = Vec<ImageDeleteResponseItem>, Error = Error<serde_json::Value>> + Send>;
     fn image_get(
         &self,
         name: &str,
@@ -109,13 +109,16 @@ pub trait ImageApi: Send + Sync {
     fn image_inspect(
         &self,
         name: &str,
-    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>> + Send>;
     fn image_list(
         &self,
         all: bool,
         filters: &str,
         digests: bool,
-    ) -> Box<dyn Future<Item = Vec<crate::models::ImageSummary>, Error = Error<serde_json::Value>>>;
+    ) -> Box<
+        dyn Future<Item = Vec<crate::models::ImageSummary>, Error = Error<serde_json::Value>>
+            + Send,
+    >;
     fn image_load(
         &self,
         images_tarball: Vec<u8>,
@@ -471,7 +474,7 @@ where
         name: &str,
         force: bool,
         noprune: bool,
-    ) -> Box<dyn Future<Item = Vec<ImageDeleteResponseItem>, Error = Error<serde_json::Value>>>
+    ) -> Box<dyn Future<Item = Vec<ImageDeleteResponseItem>, Error = Error<serde_json::Value>> + Send>
     {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
@@ -683,7 +686,7 @@ where
     fn image_inspect(
         &self,
         name: &str,
-    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>> + Send> {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::GET;
@@ -735,8 +738,10 @@ where
         all: bool,
         filters: &str,
         digests: bool,
-    ) -> Box<dyn Future<Item = Vec<crate::models::ImageSummary>, Error = Error<serde_json::Value>>>
-    {
+    ) -> Box<
+        dyn Future<Item = Vec<crate::models::ImageSummary>, Error = Error<serde_json::Value>>
+            + Send,
+    > {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::GET;
@@ -36,9 +36,9 @@ use serde_json::Value;
 
 #[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize, Clone)]
 pub struct HostConfig {
-    // /// An integer value representing this container's relative CPU weight versus other containers.
-    // #[serde(rename = "CpuShares", skip_serializing_if = "Option::is_none")]
-    // cpu_shares: Option<i32>,
+    /// An integer value representing this container's relative CPU weight versus other containers.
+    #[serde(rename = "CpuShares", skip_serializing_if = "Option::is_none")]
+    cpu_shares: Option<i32>,
     /// Memory limit in bytes.
     #[serde(rename = "Memory", skip_serializing_if = "Option::is_none")]
     memory: Option<i64>,
@@ -105,9 +105,9 @@ pub struct HostConfig {
     // /// Memory soft limit in bytes.
     // #[serde(rename = "MemoryReservation", skip_serializing_if = "Option::is_none")]
     // memory_reservation: Option<i64>,
-    // /// Total memory limit (memory + swap). Set as `-1` to enable unlimited swap.
-    // #[serde(rename = "MemorySwap", skip_serializing_if = "Option::is_none")]
-    // memory_swap: Option<i64>,
+    /// Total memory limit (memory + swap). Set as `-1` to enable unlimited swap.
+    #[serde(rename = "MemorySwap", skip_serializing_if = "Option::is_none")]
+    memory_swap: Option<i64>,
     // /// Tune a container's memory swappiness behavior. Accepts an integer between 0 and 100.
     // #[serde(rename = "MemorySwappiness", skip_serializing_if = "Option::is_none")]
     // memory_swappiness: Option<i64>,
@@ -117,9 +117,9 @@ pub struct HostConfig {
     // /// Disable OOM Killer for the container.
     // #[serde(rename = "OomKillDisable", skip_serializing_if = "Option::is_none")]
     // oom_kill_disable: Option<bool>,
-    // /// Tune a container's pids limit. Set -1 for unlimited.
-    // #[serde(rename = "PidsLimit", skip_serializing_if = "Option::is_none")]
-    // pids_limit: Option<i64>,
+    /// Tune a container's pids limit. Set -1 for unlimited.
+    #[serde(rename = "PidsLimit", skip_serializing_if = "Option::is_none")]
+    pids_limit: Option<i64>,
     // /// A list of resource limits to set in the container. For example: `{\"Name\": \"nofile\", \"Soft\": 1024, \"Hard\": 2048}`\"
     // #[serde(rename = "Ulimits", skip_serializing_if = "Option::is_none")]
     // ulimits: Option<Vec<crate::models::ResourcesUlimits>>,
@@ -247,7 +247,7 @@ impl HostConfig {
     /// Container configuration that depends on the host we are running on
     pub fn new() -> Self {
         HostConfig {
-            // cpu_shares: None,
+            cpu_shares: None,
             memory: None,
             // cgroup_parent: None,
             // blkio_weight: None,
@@ -267,11 +267,11 @@ impl HostConfig {
             // disk_quota: None,
             // kernel_memory: None,
             // memory_reservation: None,
-            // memory_swap: None,
+            memory_swap: None,
             // memory_swappiness: None,
             // nano_cp_us: None,
             // oom_kill_disable: None,
-            // pids_limit: None,
+            pids_limit: None,
             // ulimits: None,
             // cpu_count: None,
             // cpu_percent: None,
@@ -316,22 +316,22 @@ impl HostConfig {
         }
     }
 
-    // pub fn set_cpu_shares(&mut self, cpu_shares: i32) {
-    //     self.cpu_shares = Some(cpu_shares);
-    // }
+    pub fn set_cpu_shares(&mut self, cpu_shares: i32) {
+        self.cpu_shares = Some(cpu_shares);
+    }
 
-    // pub fn with_cpu_shares(mut self, cpu_shares: i32) -> Self {
-    //     self.cpu_shares = Some(cpu_shares);
-    //     self
-    // }
+    pub fn with_cpu_shares(mut self, cpu_shares: i32) -> Self {
+        self.cpu_shares = Some(cpu_shares);
+        self
+    }
 
-    // pub fn cpu_shares(&self) -> Option<i32> {
-    //     self.cpu_shares
-    // }
+    pub fn cpu_shares(&self) -> Option<i32> {
+        self.cpu_shares
+    }
 
-    // pub fn reset_cpu_shares(&mut self) {
-    //     self.cpu_shares = None;
-    // }
+    pub fn reset_cpu_shares(&mut self) {
+        self.cpu_shares = None;
+    }
 
     pub fn set_memory(&mut self, memory: i64) {
         self.memory = Some(memory);
@@ -686,22 +686,22 @@ impl HostConfig {
     //     self.memory_reservation = None;
     // }
 
-    // pub fn set_memory_swap(&mut self, memory_swap: i64) {
-    //     self.memory_swap = Some(memory_swap);
-    // }
+    pub fn set_memory_swap(&mut self, memory_swap: i64) {
+        self.memory_swap = Some(memory_swap);
+    }
 
-    // pub fn with_memory_swap(mut self, memory_swap: i64) -> Self {
-    //     self.memory_swap = Some(memory_swap);
-    //     self
-    // }
+    pub fn with_memory_swap(mut self, memory_swap: i64) -> Self {
+        self.memory_swap = Some(memory_swap);
+        self
+    }
 
-    // pub fn memory_swap(&self) -> Option<i64> {
-    //     self.memory_swap
-    // }
+    pub fn memory_swap(&self) -> Option<i64> {
+        self.memory_swap
+    }
 
-    // pub fn reset_memory_swap(&mut self) {
-    //     self.memory_swap = None;
-    // }
+    pub fn reset_memory_swap(&mut self) {
+        self.memory_swap = None;
+    }
 
     // pub fn set_memory_swappiness(&mut self, memory_swappiness: i64) {
     //     self.memory_swappiness = Some(memory_swappiness);
@@ -754,22 +754,22 @@ impl HostConfig {
     //     self.oom_kill_disable = None;
     // }
 
-    // pub fn set_pids_limit(&mut self, pids_limit: i64) {
-    //     self.pids_limit = Some(pids_limit);
-    // }
+    pub fn set_pids_limit(&mut self, pids_limit: i64) {
+        self.pids_limit = Some(pids_limit);
+    }
 
-    // pub fn with_pids_limit(mut self, pids_limit: i64) -> Self {
-    //     self.pids_limit = Some(pids_limit);
-    //     self
-    // }
+    pub fn with_pids_limit(mut self, pids_limit: i64) -> Self {
+        self.pids_limit = Some(pids_limit);
+        self
+    }
 
-    // pub fn pids_limit(&self) -> Option<i64> {
-    //     self.pids_limit
-    // }
+    pub fn pids_limit(&self) -> Option<i64> {
+        self.pids_limit
+    }
 
-    // pub fn reset_pids_limit(&mut self) {
-    //     self.pids_limit = None;
-    // }
+    pub fn reset_pids_limit(&mut self) {
+        self.pids_limit = None;
+    }
 
     // pub fn set_ulimits(&mut self, ulimits: Vec<crate::models::ResourcesUlimits>) {
     //     self.ulimits = Some(ulimits);
@@ -53,8 +53,8 @@ pub struct Mount {
     // bind_options: Option<crate::models::MountBindOptions>,
     // #[serde(rename = "VolumeOptions", skip_serializing_if = "Option::is_none")]
     // volume_options: Option<crate::models::MountVolumeOptions>,
-    // #[serde(rename = "TmpfsOptions", skip_serializing_if = "Option::is_none")]
-    // tmpfs_options: Option<crate::models::MountTmpfsOptions>,
+    #[serde(rename = "TmpfsOptions", skip_serializing_if = "Option::is_none")]
+    tmpfs_options: Option<crate::models::MountTmpfsOptions>,
     #[serde(flatten)]
     other_properties: std::collections::HashMap<String, serde_json::Value>,
 }
@@ -69,7 +69,7 @@ impl Mount {
             // consistency: None,
             // bind_options: None,
             // volume_options: None,
-            // tmpfs_options: None,
+            tmpfs_options: None,
             other_properties: Default::default(),
         }
     }
@@ -196,20 +196,20 @@ impl Mount {
     //     self.volume_options = None;
     // }
 
-    // pub fn set_tmpfs_options(&mut self, tmpfs_options: crate::models::MountTmpfsOptions) {
-    //     self.tmpfs_options = Some(tmpfs_options);
-    // }
+    pub fn set_tmpfs_options(&mut self, tmpfs_options: crate::models::MountTmpfsOptions) {
+        self.tmpfs_options = Some(tmpfs_options);
+    }
 
-    // pub fn with_tmpfs_options(mut self, tmpfs_options: crate::models::MountTmpfsOptions) -> Self {
-    //     self.tmpfs_options = Some(tmpfs_options);
-    //     self
-    // }
+    pub fn with_tmpfs_options(mut self, tmpfs_options: crate::models::MountTmpfsOptions) -> Self {
+        self.tmpfs_options = Some(tmpfs_options);
+        self
+    }
 
-    // pub fn tmpfs_options(&self) -> Option<&crate::models::MountTmpfsOptions> {
-    //     self.tmpfs_options.as_ref()
-    // }
+    pub fn tmpfs_options(&self) -> Option<&crate::models::MountTmpfsOptions> {
+        self.tmpfs_options.as_ref()
+    }
 
-    // pub fn reset_tmpfs_options(&mut self) {
-    //     self.tmpfs_options = None;
-    // }
+    pub fn reset_tmpfs_options(&mut self) {
+        self.tmpfs_options = None;
+    }
 }
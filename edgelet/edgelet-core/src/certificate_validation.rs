@@ -0,0 +1,454 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::os::raw::c_int;
+
+use chrono::{DateTime, NaiveDateTime, ParseResult, Utc};
+use failure::ResultExt;
+use foreign_types::ForeignTypeRef;
+use openssl::asn1::Asn1TimeRef;
+use openssl::nid::Nid;
+use openssl::x509::{X509Ref, X509VerifyResult, X509};
+
+use crate::crl::{CrlChecker, CrlFetcher};
+use crate::error::{Error, ErrorKind, InvalidCertificateReason, Result};
+
+/// Validates a certificate chain against a trusted root.
+///
+/// `chain` is ordered from the leaf certificate up to the certificate closest to the root.
+/// Every certificate must be within its validity period and be issued by the certificate
+/// that follows it, and the last certificate must be issued by `trusted_root`. The chain
+/// may end with `trusted_root` itself.
+///
+/// A certificate is issued by another if its issuer name is the other's subject name, it is
+/// signed by the other's key, and the other is a CA, i.e. its basicConstraints extension
+/// allows it to issue certificates and its keyUsage extension, if any, includes keyCertSign.
+///
+/// Once the chain is found to be valid, `crl_checker` checks that none of its certificates has
+/// been revoked.
+pub fn validate_certificate_chain<F>(
+    chain: &[X509],
+    trusted_root: &X509,
+    crl_checker: &CrlChecker<F>,
+) -> Result<()>
+where
+    F: CrlFetcher,
+{
+    validate_certificate_chain_at(chain, trusted_root, crl_checker, Utc::now())
+}
+
+/// Like `validate_certificate_chain`, but with the chain and trusted roots as PEM.
+///
+/// The chain is accepted if it validates against any of the certificates in
+/// `trust_bundle`.
+pub fn validate_certificate_chain_pem<F>(
+    chain: &[u8],
+    trust_bundle: &[u8],
+    crl_checker: &CrlChecker<F>,
+) -> Result<()>
+where
+    F: CrlFetcher,
+{
+    let chain = X509::stack_from_pem(chain).context(ErrorKind::CertificateChainValidation)?;
+    let roots =
+        X509::stack_from_pem(trust_bundle).context(ErrorKind::CertificateChainValidation)?;
+
+    let mut result = Err(Error::from(ErrorKind::InvalidCertificate(
+        chain.len().saturating_sub(1),
+        InvalidCertificateReason::Untrusted,
+    )));
+    for root in &roots {
+        result = validate_certificate_chain(&chain, root, crl_checker);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Validates that `cert` is valid for `expected_hostname`, following RFC 6125.
+///
+/// The DNS names in the certificate's subjectAltName extension are checked first, and the
+/// common name is only checked if the certificate has no DNS names. A wildcard is only allowed as
+/// the whole left-most label of a name, and matches exactly one label.
+pub fn validate_san(cert: &X509, expected_hostname: &str) -> Result<()> {
+    let hostname = expected_hostname.trim_end_matches('.');
+
+    let dns_names: Vec<String> = cert
+        .subject_alt_names()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.dnsname().map(ToOwned::to_owned))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let is_valid = if dns_names.is_empty() {
+        cert.subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .filter_map(|entry| entry.data().as_utf8().ok())
+            .any(|common_name| hostname_matches(&common_name, hostname))
+    } else {
+        dns_names
+            .iter()
+            .any(|dns_name| hostname_matches(dns_name, hostname))
+    };
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::from(ErrorKind::CertificateHostnameMismatch(
+            expected_hostname.to_string(),
+        )))
+    }
+}
+
+fn hostname_matches(pattern: &str, hostname: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.');
+
+    if pattern.starts_with("*.") {
+        let suffix = &pattern[2..];
+
+        // the wildcard matches exactly one label, and is not allowed directly in front of a
+        // top-level domain
+        if !suffix.contains('.') {
+            return false;
+        }
+        match hostname.find('.') {
+            Some(index) if index > 0 => hostname[index + 1..].eq_ignore_ascii_case(suffix),
+            _ => false,
+        }
+    } else {
+        pattern.eq_ignore_ascii_case(hostname)
+    }
+}
+
+fn validate_certificate_chain_at<F>(
+    chain: &[X509],
+    trusted_root: &X509,
+    crl_checker: &CrlChecker<F>,
+    now: DateTime<Utc>,
+) -> Result<()>
+where
+    F: CrlFetcher,
+{
+    if chain.is_empty() {
+        return Err(Error::from(ErrorKind::CertificateChainEmpty));
+    }
+
+    for (index, cert) in chain.iter().enumerate() {
+        check_validity_period(index, cert, now)?;
+
+        let issuer = chain.get(index + 1).unwrap_or(trusted_root);
+        let reason = if index + 1 == chain.len() {
+            InvalidCertificateReason::Untrusted
+        } else {
+            InvalidCertificateReason::Signature
+        };
+        if issuer.issued(cert) != X509VerifyResult::OK {
+            return Err(Error::from(ErrorKind::InvalidCertificate(index, reason)));
+        }
+        if !is_ca(issuer) {
+            return Err(Error::from(ErrorKind::InvalidCertificate(
+                index + 1,
+                InvalidCertificateReason::NotCa,
+            )));
+        }
+        let issuer_key = issuer
+            .public_key()
+            .context(ErrorKind::CertificateChainValidation)?;
+        if !cert
+            .verify(&issuer_key)
+            .context(ErrorKind::CertificateChainValidation)?
+        {
+            return Err(Error::from(ErrorKind::InvalidCertificate(index, reason)));
+        }
+    }
+
+    check_validity_period(chain.len(), trusted_root, now)?;
+
+    crl_checker.check_chain_at(chain, trusted_root, now)
+}
+
+extern "C" {
+    fn X509_check_ca(x: *mut openssl_sys::X509) -> c_int;
+}
+
+/// Whether the certificate may issue other certificates, the way OpenSSL's own verification
+/// decides it. The openssl crate doesn't expose the basicConstraints and keyUsage extensions.
+fn is_ca(cert: &X509Ref) -> bool {
+    // X509_check_ca only reads the certificate, which is valid for the lifetime of `cert`
+    unsafe { X509_check_ca(cert.as_ptr()) != 0 }
+}
+
+fn check_validity_period(index: usize, cert: &X509, now: DateTime<Utc>) -> Result<()> {
+    let not_before =
+        parse_openssl_time(cert.not_before()).context(ErrorKind::CertificateChainValidation)?;
+    let not_after =
+        parse_openssl_time(cert.not_after()).context(ErrorKind::CertificateChainValidation)?;
+
+    if now < not_before {
+        Err(Error::from(ErrorKind::InvalidCertificate(
+            index,
+            InvalidCertificateReason::NotYetValid(not_before),
+        )))
+    } else if now > not_after {
+        Err(Error::from(ErrorKind::InvalidCertificate(
+            index,
+            InvalidCertificateReason::Expired(not_after),
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts a time read from a certificate, such as its `not_after`, to a `DateTime`.
+pub fn parse_openssl_time(time: &Asn1TimeRef) -> ParseResult<DateTime<Utc>> {
+    // openssl::asn1::Asn1TimeRef does not expose any way to convert the ASN1_TIME to a Rust-friendly type
+    //
+    // Its Display impl uses ASN1_TIME_print, so we convert it into a String and parse it back
+    // into a chrono::DateTime<chrono::Utc>
+    let time = NaiveDateTime::parse_from_str(&time.to_string(), "%b %e %H:%M:%S %Y GMT")?;
+    Ok(DateTime::<Utc>::from_utc(time, Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use chrono::Duration;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::x509::extension::BasicConstraints;
+    use openssl::x509::{X509Builder, X509Name, X509NameBuilder};
+    use url::Url;
+
+    use crate::crl::tests::{cert, checker, CA, REVOKED, VALID};
+
+    /// For chains without CRL distribution points, which are never checked.
+    fn no_crls() -> CrlChecker<impl CrlFetcher> {
+        CrlChecker::new(|url: &Url| -> Result<Vec<u8>> {
+            panic!("Unexpected download of the CRL at {}", url)
+        })
+    }
+
+    fn cert_builder(
+        common_name: &str,
+        key: &PKey<Private>,
+        validity_days: u32,
+        ca: bool,
+    ) -> X509Builder {
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name(common_name)).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(validity_days).unwrap())
+            .unwrap();
+        let mut basic_constraints = BasicConstraints::new();
+        if ca {
+            basic_constraints.ca();
+        }
+        builder
+            .append_extension(basic_constraints.build().unwrap())
+            .unwrap();
+        builder
+    }
+
+    fn name(common_name: &str) -> X509Name {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        name.build()
+    }
+
+    fn make_cert(
+        common_name: &str,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        validity_days: u32,
+    ) -> (X509, PKey<Private>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut builder = cert_builder(common_name, &key, validity_days, true);
+
+        match issuer {
+            Some((issuer_cert, issuer_key)) => {
+                builder.set_issuer_name(issuer_cert.subject_name()).unwrap();
+                builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.set_issuer_name(&name(common_name)).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        (builder.build(), key)
+    }
+
+    #[test]
+    fn valid_chain_succeeds() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (intermediate, intermediate_key) =
+            make_cert("intermediate", Some((&root, &root_key)), 90);
+        let (leaf, _) = make_cert("leaf", Some((&intermediate, &intermediate_key)), 30);
+
+        validate_certificate_chain(&[leaf.clone(), intermediate.clone()], &root, &no_crls())
+            .unwrap();
+        validate_certificate_chain(&[leaf, intermediate, root.clone()], &root, &no_crls()).unwrap();
+    }
+
+    #[test]
+    fn empty_chain_fails() {
+        let (root, _) = make_cert("root", None, 365);
+
+        let err = validate_certificate_chain(&[], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::CertificateChainEmpty => (),
+            kind => panic!("Expected `CertificateChainEmpty` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn chain_signed_by_other_root_fails() {
+        let (root, _) = make_cert("root", None, 365);
+        let (other_root, other_root_key) = make_cert("other root", None, 365);
+        let (leaf, _) = make_cert("leaf", Some((&other_root, &other_root_key)), 30);
+
+        let err = validate_certificate_chain(&[leaf], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Untrusted) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn broken_chain_fails() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (intermediate, _) = make_cert("intermediate", Some((&root, &root_key)), 90);
+        let (leaf, _) = make_cert("leaf", Some((&root, &root_key)), 30);
+
+        let err = validate_certificate_chain(&[leaf, intermediate], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Signature) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn non_ca_issuer_fails() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let intermediate_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut builder = cert_builder("intermediate", &intermediate_key, 90, false);
+        builder.set_issuer_name(root.subject_name()).unwrap();
+        builder.sign(&root_key, MessageDigest::sha256()).unwrap();
+        let intermediate = builder.build();
+        let (leaf, _) = make_cert("leaf", Some((&intermediate, &intermediate_key)), 30);
+
+        let err = validate_certificate_chain(&[leaf, intermediate], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(1, InvalidCertificateReason::NotCa) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn issuer_name_mismatch_fails() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (intermediate, _) = make_cert("intermediate", Some((&root, &root_key)), 90);
+        let leaf_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut builder = cert_builder("leaf", &leaf_key, 30, false);
+        builder.set_issuer_name(&name("someone else")).unwrap();
+        builder.sign(&root_key, MessageDigest::sha256()).unwrap();
+        let leaf = builder.build();
+
+        // signed by the root, but not issued by it by name
+        let err = validate_certificate_chain(&[leaf.clone()], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Untrusted) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+        let err = validate_certificate_chain(&[leaf, intermediate], &root, &no_crls()).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Signature) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn expired_certificate_fails() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (leaf, _) = make_cert("leaf", Some((&root, &root_key)), 30);
+
+        let err = validate_certificate_chain_at(
+            &[leaf],
+            &root,
+            &no_crls(),
+            Utc::now() + Duration::days(60),
+        )
+        .unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Expired(_)) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn not_yet_valid_certificate_fails() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (leaf, _) = make_cert("leaf", Some((&root, &root_key)), 30);
+
+        let err = validate_certificate_chain_at(
+            &[leaf],
+            &root,
+            &no_crls(),
+            Utc::now() - Duration::days(1),
+        )
+        .unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::NotYetValid(_)) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn pem_chain_validates_against_any_root_in_bundle() {
+        let (root, root_key) = make_cert("root", None, 365);
+        let (other_root, _) = make_cert("other root", None, 365);
+        let (leaf, _) = make_cert("leaf", Some((&root, &root_key)), 30);
+
+        let mut trust_bundle = other_root.to_pem().unwrap();
+        trust_bundle.extend(root.to_pem().unwrap());
+
+        validate_certificate_chain_pem(&leaf.to_pem().unwrap(), &trust_bundle, &no_crls()).unwrap();
+        validate_certificate_chain_pem(
+            &leaf.to_pem().unwrap(),
+            &other_root.to_pem().unwrap(),
+            &no_crls(),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn revoked_certificate_fails() {
+        let checker = checker(Arc::new(AtomicUsize::new(0)));
+        let ca = cert(CA);
+
+        validate_certificate_chain(&[cert(VALID)], &ca, &checker).unwrap();
+
+        let err = validate_certificate_chain(&[cert(REVOKED)], &ca, &checker).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Revoked) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+}
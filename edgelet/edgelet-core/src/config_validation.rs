@@ -0,0 +1,543 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Up-front checks of the settings read from config.yaml.
+//!
+//! Most settings are only used well after startup, so without these checks a mistake in
+//! config.yaml would only surface once the setting is first used, often as an error that doesn't
+//! name the setting at all.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use openssl::x509::X509;
+use url::Url;
+
+use crate::error::Error;
+use crate::settings::{
+    AttestationMethod, ManualAuthMethod, ProvisioningType, RuntimeSettings,
+    DEFAULT_CONNECTION_STRING,
+};
+use crate::UNIX_SCHEME;
+
+const CONNECT_SCHEMES: &[&str] = &["http", "https", UNIX_SCHEME];
+const LISTEN_SCHEMES: &[&str] = &["fd", "http", "https", UNIX_SCHEME];
+
+/// A problem with a single setting in config.yaml.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    setting: &'static str,
+    message: String,
+}
+
+impl ConfigError {
+    pub fn new(setting: &'static str, message: String) -> Self {
+        ConfigError { setting, message }
+    }
+
+    /// The name of the setting, e.g. `listen.management_uri`.
+    pub fn setting(&self) -> &'static str {
+        self.setting
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.setting, self.message)
+    }
+}
+
+/// Checks all the settings and returns every problem found, so that they can all be fixed at
+/// once. An empty list means that the settings are valid.
+pub fn validate_config<S>(settings: &S) -> Vec<ConfigError>
+where
+    S: RuntimeSettings,
+{
+    let mut errors = vec![];
+
+    if settings.hostname().trim().is_empty() {
+        errors.push(ConfigError::new(
+            "hostname",
+            "must not be empty".to_string(),
+        ));
+    }
+    if settings.agent().name().trim().is_empty() {
+        errors.push(ConfigError::new(
+            "agent.name",
+            "must not be empty".to_string(),
+        ));
+    }
+
+    validate_provisioning(settings, &mut errors);
+
+    validate_uri(
+        "connect.management_uri",
+        settings.connect().management_uri(),
+        CONNECT_SCHEMES,
+        &mut errors,
+    );
+    validate_uri(
+        "connect.workload_uri",
+        settings.connect().workload_uri(),
+        CONNECT_SCHEMES,
+        &mut errors,
+    );
+    validate_uri(
+        "listen.management_uri",
+        settings.listen().management_uri(),
+        LISTEN_SCHEMES,
+        &mut errors,
+    );
+    validate_uri(
+        "listen.workload_uri",
+        settings.listen().workload_uri(),
+        LISTEN_SCHEMES,
+        &mut errors,
+    );
+
+    if settings.homedir().is_file() {
+        errors.push(ConfigError::new(
+            "homedir",
+            format!(
+                "{} is a file, not a directory",
+                settings.homedir().display()
+            ),
+        ));
+    }
+
+    if let Some(device_cert) = settings.certificates().device_cert() {
+        validate_certificate_file(
+            "certificates.device_ca_cert",
+            device_cert.device_ca_cert(),
+            &mut errors,
+        );
+        validate_file(
+            "certificates.device_ca_pk",
+            device_cert.device_ca_pk(),
+            &mut errors,
+        );
+        validate_certificate_file(
+            "certificates.trusted_ca_certs",
+            device_cert.trusted_ca_certs(),
+            &mut errors,
+        );
+    }
+
+    if settings.certificates().edge_ca_validity_seconds() == Some(0) {
+        errors.push(ConfigError::new(
+            "certificates.edge_ca_validity_days",
+            "must be greater than 0".to_string(),
+        ));
+    }
+    if settings.certificates().renewal_threshold_percent() > 100 {
+        errors.push(ConfigError::new(
+            "certificates.renewal_threshold_percent",
+            "must be at most 100".to_string(),
+        ));
+    }
+
+    validate_est(settings, &mut errors);
+    validate_scep(settings, &mut errors);
+
+    if let Some(endpoint) = settings.tracing().otlp_endpoint() {
+        validate_uri(
+            "tracing.otlp_endpoint",
+            endpoint,
+            &["http", "https"],
+            &mut errors,
+        );
+    }
+
+    errors
+}
+
+fn validate_provisioning<S>(settings: &S, errors: &mut Vec<ConfigError>)
+where
+    S: RuntimeSettings,
+{
+    match settings.provisioning().provisioning_type() {
+        ProvisioningType::Manual(manual) => match manual.authentication_method() {
+            ManualAuthMethod::DeviceConnectionString(cs) => {
+                if cs.device_connection_string() == DEFAULT_CONNECTION_STRING {
+                    errors.push(ConfigError::new(
+                        "provisioning.device_connection_string",
+                        "has not been set to the connection string of the device".to_string(),
+                    ));
+                } else if let Err(err) = cs.parse_device_connection_string() {
+                    errors.push(ConfigError::new(
+                        "provisioning.device_connection_string",
+                        causes(&err),
+                    ));
+                }
+            }
+            ManualAuthMethod::X509(x509) => {
+                if x509.iothub_hostname().trim().is_empty() {
+                    errors.push(ConfigError::new(
+                        "provisioning.authentication.iothub_hostname",
+                        "must not be empty".to_string(),
+                    ));
+                }
+                if x509.device_id().trim().is_empty() {
+                    errors.push(ConfigError::new(
+                        "provisioning.authentication.device_id",
+                        "must not be empty".to_string(),
+                    ));
+                }
+                validate_certificate_file(
+                    "provisioning.authentication.identity_cert",
+                    x509.identity_cert(),
+                    errors,
+                );
+                validate_file(
+                    "provisioning.authentication.identity_pk",
+                    x509.identity_pk(),
+                    errors,
+                );
+            }
+        },
+        ProvisioningType::Dps(dps) => {
+            validate_uri(
+                "provisioning.global_endpoint",
+                dps.global_endpoint(),
+                &["https"],
+                errors,
+            );
+            if dps.scope_id().trim().is_empty() {
+                errors.push(ConfigError::new(
+                    "provisioning.scope_id",
+                    "must not be empty".to_string(),
+                ));
+            }
+            match dps.attestation() {
+                AttestationMethod::Tpm(tpm) => {
+                    if tpm.registration_id().trim().is_empty() {
+                        errors.push(ConfigError::new(
+                            "provisioning.attestation.registration_id",
+                            "must not be empty".to_string(),
+                        ));
+                    }
+                }
+                AttestationMethod::SymmetricKey(key) => {
+                    if key.registration_id().trim().is_empty() {
+                        errors.push(ConfigError::new(
+                            "provisioning.attestation.registration_id",
+                            "must not be empty".to_string(),
+                        ));
+                    }
+                    if base64::decode(key.symmetric_key()).is_err() {
+                        errors.push(ConfigError::new(
+                            "provisioning.attestation.symmetric_key",
+                            "is not valid base64".to_string(),
+                        ));
+                    }
+                }
+                AttestationMethod::Fido2(fido2) => {
+                    if fido2.registration_id().trim().is_empty() {
+                        errors.push(ConfigError::new(
+                            "provisioning.attestation.registration_id",
+                            "must not be empty".to_string(),
+                        ));
+                    }
+                }
+                AttestationMethod::X509(x509) => {
+                    validate_certificate_file(
+                        "provisioning.attestation.identity_cert",
+                        x509.identity_cert(),
+                        errors,
+                    );
+                    validate_file(
+                        "provisioning.attestation.identity_pk",
+                        x509.identity_pk(),
+                        errors,
+                    );
+                }
+            }
+        }
+        ProvisioningType::External(external) => {
+            validate_uri(
+                "provisioning.endpoint",
+                external.endpoint(),
+                CONNECT_SCHEMES,
+                errors,
+            );
+        }
+    }
+}
+
+fn validate_uri(setting: &'static str, uri: &Url, schemes: &[&str], errors: &mut Vec<ConfigError>) {
+    if !schemes.contains(&uri.scheme()) {
+        errors.push(ConfigError::new(
+            setting,
+            format!(
+                "{} has unsupported scheme {:?}, expected one of {}",
+                uri,
+                uri.scheme(),
+                schemes.join(", ")
+            ),
+        ));
+        return;
+    }
+
+    match uri.scheme() {
+        "http" | "https" => {
+            if uri.host_str().map_or(true, str::is_empty) {
+                errors.push(ConfigError::new(setting, format!("{} has no host", uri)));
+            }
+            // The URL parser already rejects ports outside of the u16 range
+            if uri.port() == Some(0) {
+                errors.push(ConfigError::new(
+                    setting,
+                    format!("{} has port 0, expected a port between 1 and 65535", uri),
+                ));
+            }
+        }
+        "fd" => {
+            if uri
+                .host_str()
+                .map_or(true, |fd| fd.parse::<usize>().is_err())
+            {
+                errors.push(ConfigError::new(
+                    setting,
+                    format!("{} does not name a file descriptor", uri),
+                ));
+            }
+        }
+        _ => {
+            if uri.path().is_empty() || uri.path() == "/" {
+                errors.push(ConfigError::new(
+                    setting,
+                    format!("{} does not name a socket path", uri),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_est<S>(settings: &S, errors: &mut Vec<ConfigError>)
+where
+    S: RuntimeSettings,
+{
+    let certificates = settings.certificates();
+    let identity_cert = certificates.est_identity_cert().transpose();
+    let identity_pk = certificates.est_identity_pk().transpose();
+
+    if let Some(url) = certificates.est_url() {
+        // the client authenticates with its identity certificate, which needs TLS
+        validate_uri("certificates.est_url", url, &["https"], errors);
+
+        if certificates.scep_url().is_some() {
+            errors.push(ConfigError::new(
+                "certificates.est_url",
+                "cannot be used together with certificates.scep_url".to_string(),
+            ));
+        }
+
+        // the EST server authenticates the daemon over mutual TLS
+        match identity_cert {
+            Some(path) => validate_certificate_file("certificates.est_identity_cert", path, errors),
+            None => errors.push(ConfigError::new(
+                "certificates.est_identity_cert",
+                "is required with certificates.est_url".to_string(),
+            )),
+        }
+        match identity_pk {
+            Some(path) => {
+                validate_file("certificates.est_identity_pk", path, errors);
+            }
+            None => errors.push(ConfigError::new(
+                "certificates.est_identity_pk",
+                "is required with certificates.est_url".to_string(),
+            )),
+        }
+    } else {
+        if identity_cert.is_some() {
+            errors.push(ConfigError::new(
+                "certificates.est_identity_cert",
+                "is only used with certificates.est_url".to_string(),
+            ));
+        }
+        if identity_pk.is_some() {
+            errors.push(ConfigError::new(
+                "certificates.est_identity_pk",
+                "is only used with certificates.est_url".to_string(),
+            ));
+        }
+    }
+}
+
+fn validate_scep<S>(settings: &S, errors: &mut Vec<ConfigError>)
+where
+    S: RuntimeSettings,
+{
+    let certificates = settings.certificates();
+
+    if let Some(url) = certificates.scep_url() {
+        validate_uri("certificates.scep_url", url, &["http", "https"], errors);
+
+        // without TLS, the fingerprint is the only thing that authenticates the CA
+        if url.scheme() == "http" && certificates.scep_ca_fingerprint().is_none() {
+            errors.push(ConfigError::new(
+                "certificates.scep_url",
+                format!(
+                    "{} uses plain http, which requires certificates.scep_ca_fingerprint",
+                    url
+                ),
+            ));
+        }
+    } else {
+        if certificates.scep_challenge_password().is_some() {
+            errors.push(ConfigError::new(
+                "certificates.scep_challenge_password",
+                "is only used with certificates.scep_url".to_string(),
+            ));
+        }
+        if certificates.scep_ca_fingerprint().is_some() {
+            errors.push(ConfigError::new(
+                "certificates.scep_ca_fingerprint",
+                "is only used with certificates.scep_url".to_string(),
+            ));
+        }
+    }
+
+    if let Some(fingerprint) = certificates.scep_ca_fingerprint() {
+        let digits: Vec<char> = fingerprint.chars().filter(|c| *c != ':').collect();
+        if !digits.iter().all(char::is_ascii_hexdigit) || ![40, 64].contains(&digits.len()) {
+            errors.push(ConfigError::new(
+                "certificates.scep_ca_fingerprint",
+                format!(
+                    "{:?} is not a SHA-256 or SHA-1 fingerprint in hex",
+                    fingerprint
+                ),
+            ));
+        }
+    }
+}
+
+fn validate_file(
+    setting: &'static str,
+    path: Result<PathBuf, Error>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<PathBuf> {
+    let path = match path {
+        Ok(path) => path,
+        Err(err) => {
+            errors.push(ConfigError::new(setting, causes(&err)));
+            return None;
+        }
+    };
+
+    if path.is_file() {
+        Some(path)
+    } else {
+        errors.push(ConfigError::new(setting, not_a_file(&path)));
+        None
+    }
+}
+
+fn validate_certificate_file(
+    setting: &'static str,
+    path: Result<PathBuf, Error>,
+    errors: &mut Vec<ConfigError>,
+) {
+    let path = match validate_file(setting, path, errors) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let result = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|pem| X509::stack_from_pem(&pem).map_err(|err| err.to_string()));
+    match result {
+        Ok(ref certs) if !certs.is_empty() => (),
+        Ok(_) => errors.push(ConfigError::new(
+            setting,
+            format!("{} does not contain any PEM certificates", path.display()),
+        )),
+        Err(err) => errors.push(ConfigError::new(
+            setting,
+            format!("{} could not be read as PEM: {}", path.display(), err),
+        )),
+    }
+}
+
+fn not_a_file(path: &Path) -> String {
+    if path.exists() {
+        format!("{} is not a file", path.display())
+    } else {
+        format!("{} does not exist", path.display())
+    }
+}
+
+fn causes(err: &Error) -> String {
+    let mut message = err.to_string();
+    for cause in <dyn failure::Fail>::iter_causes(err) {
+        message.push_str(&format!(": {}", cause));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri_errors(uri: &str, schemes: &[&str]) -> Vec<ConfigError> {
+        let mut errors = vec![];
+        validate_uri("test", &Url::parse(uri).unwrap(), schemes, &mut errors);
+        errors
+    }
+
+    #[test]
+    fn supported_uris_are_valid() {
+        for uri in &[
+            "http://localhost:15580",
+            "https://0.0.0.0:15581",
+            "unix:///var/run/iotedge/mgmt.sock",
+            "fd://3",
+        ] {
+            assert_eq!(Vec::<ConfigError>::new(), uri_errors(uri, LISTEN_SCHEMES));
+        }
+    }
+
+    #[test]
+    fn unsupported_scheme_is_invalid() {
+        let errors = uri_errors("ftp://localhost:21", CONNECT_SCHEMES);
+
+        assert_eq!(1, errors.len());
+        assert_eq!("test", errors[0].setting());
+        assert!(errors[0].message().contains("unsupported scheme \"ftp\""));
+    }
+
+    #[test]
+    fn port_zero_is_invalid() {
+        let errors = uri_errors("http://localhost:0", CONNECT_SCHEMES);
+
+        assert_eq!(1, errors.len());
+        assert!(errors[0].message().contains("port 0"));
+    }
+
+    #[test]
+    fn fd_must_be_a_number() {
+        let errors = uri_errors("fd://mgmt", LISTEN_SCHEMES);
+
+        assert_eq!(1, errors.len());
+        assert!(errors[0].message().contains("file descriptor"));
+    }
+
+    #[test]
+    fn missing_file_is_invalid() {
+        let mut errors = vec![];
+
+        validate_certificate_file(
+            "test",
+            Ok(PathBuf::from("/does/not/exist.pem")),
+            &mut errors,
+        );
+
+        assert_eq!(1, errors.len());
+        assert!(errors[0].message().ends_with("does not exist"));
+    }
+}
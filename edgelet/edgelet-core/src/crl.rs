@@ -0,0 +1,554 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Revocation checking of certificate chains against certificate revocation lists (RFC 5280).
+//!
+//! The CRLs are found through the CRL distribution points extension of every certificate, and
+//! are cached until their `nextUpdate` time.
+
+use std::collections::{HashMap, HashSet};
+use std::str;
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use failure::ResultExt;
+use log::warn;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::sign::Verifier;
+use openssl::x509::X509;
+use url::Url;
+
+use crate::der::{
+    self, TAG_BIT_STRING, TAG_BOOLEAN, TAG_CONTEXT_0, TAG_CONTEXT_3, TAG_GENERALIZED_TIME,
+    TAG_INTEGER, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE, TAG_UTC_TIME,
+};
+use crate::error::{Error, ErrorKind, InvalidCertificateReason, Result};
+
+const TAG_ISSUER_UNIQUE_ID: u8 = 0x81;
+const TAG_SUBJECT_UNIQUE_ID: u8 = 0x82;
+const TAG_URI: u8 = 0x86;
+
+/// 2.5.29.31
+const CRL_DISTRIBUTION_POINTS_OID: &[u8] = &[0x55, 0x1d, 0x1f];
+
+/// The CRL signature algorithms that are supported, by OID
+const SIGNATURE_ALGORITHMS: &[(&[u8], fn() -> MessageDigest)] = &[
+    // sha1WithRSAEncryption
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x05],
+        MessageDigest::sha1,
+    ),
+    // sha256WithRSAEncryption
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b],
+        MessageDigest::sha256,
+    ),
+    // sha384WithRSAEncryption
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c],
+        MessageDigest::sha384,
+    ),
+    // sha512WithRSAEncryption
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d],
+        MessageDigest::sha512,
+    ),
+    // ecdsa-with-SHA256
+    (
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02],
+        MessageDigest::sha256,
+    ),
+    // ecdsa-with-SHA384
+    (
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03],
+        MessageDigest::sha384,
+    ),
+    // ecdsa-with-SHA512
+    (
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04],
+        MessageDigest::sha512,
+    ),
+];
+
+/// Downloads the CRL published at a CRL distribution point, as DER or PEM.
+pub trait CrlFetcher {
+    fn fetch(&self, url: &Url) -> Result<Vec<u8>>;
+}
+
+impl<F> CrlFetcher for F
+where
+    F: Fn(&Url) -> Result<Vec<u8>>,
+{
+    fn fetch(&self, url: &Url) -> Result<Vec<u8>> {
+        (self)(url)
+    }
+}
+
+#[derive(Debug)]
+struct Crl {
+    /// Serial numbers of the revoked certificates, as big-endian bytes without leading zeros
+    revoked: HashSet<Vec<u8>>,
+    next_update: Option<DateTime<Utc>>,
+}
+
+impl Crl {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        // a CRL without a nextUpdate time can't be cached
+        self.next_update
+            .map_or(false, |next_update| now < next_update)
+    }
+}
+
+pub struct CrlChecker<F> {
+    fetcher: F,
+    cache: Mutex<HashMap<Url, Crl>>,
+}
+
+impl<F> CrlChecker<F>
+where
+    F: CrlFetcher,
+{
+    pub fn new(fetcher: F) -> Self {
+        CrlChecker {
+            fetcher,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks that no certificate in `chain` has been revoked.
+    ///
+    /// `chain` is ordered as for `validate_certificate_chain`, and the CRL of every certificate
+    /// must be signed by the certificate that follows it, or by `trusted_root` for the last one.
+    /// Certificates without a CRL distribution point are not checked. If a certificate has
+    /// several distribution points, the first one whose CRL can be downloaded and verified is
+    /// used. If none can, a warning is logged and the certificate is not treated as revoked, so
+    /// that an unreachable distribution point doesn't keep the daemon from starting.
+    pub fn check_chain(&self, chain: &[X509], trusted_root: &X509) -> Result<()> {
+        self.check_chain_at(chain, trusted_root, Utc::now())
+    }
+
+    pub(crate) fn check_chain_at(
+        &self,
+        chain: &[X509],
+        trusted_root: &X509,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        for (index, cert) in chain.iter().enumerate() {
+            let issuer = chain.get(index + 1).unwrap_or(trusted_root);
+            self.check_certificate(index, cert, issuer, now)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_certificate(
+        &self,
+        index: usize,
+        cert: &X509,
+        issuer: &X509,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let urls = crl_distribution_points(cert)?;
+        if urls.is_empty() {
+            return Ok(());
+        }
+
+        let serial_number = cert
+            .serial_number()
+            .to_bn()
+            .context(ErrorKind::CertificateRevocationCheck)?
+            .to_vec();
+
+        // The lock is not held while downloading, so that a slow distribution point doesn't
+        // block the checks of other chains.
+        let mut last_err = None;
+        for url in urls {
+            let cached = self
+                .cache
+                .lock()
+                .expect("Unable to lock the CRL cache mutex")
+                .get(&url)
+                .filter(|crl| crl.is_fresh(now))
+                .map(|crl| crl.revoked.contains(&serial_number));
+
+            let is_revoked = match cached {
+                Some(is_revoked) => is_revoked,
+                None => match self
+                    .fetcher
+                    .fetch(&url)
+                    .and_then(|crl| parse_crl(&crl, issuer))
+                {
+                    Ok(crl) => {
+                        let is_revoked = crl.revoked.contains(&serial_number);
+                        self.cache
+                            .lock()
+                            .expect("Unable to lock the CRL cache mutex")
+                            .insert(url, crl);
+                        is_revoked
+                    }
+                    Err(err) => {
+                        last_err = Some((url, err));
+                        continue;
+                    }
+                },
+            };
+
+            return if is_revoked {
+                Err(Error::from(ErrorKind::InvalidCertificate(
+                    index,
+                    InvalidCertificateReason::Revoked,
+                )))
+            } else {
+                Ok(())
+            };
+        }
+
+        if let Some((url, err)) = last_err {
+            warn!(
+                "Could not check the revocation of certificate {} of the chain with the CRL at {}: {}",
+                index, url, err
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Returns the URIs in the full names of the CRL distribution points of `cert`.
+pub fn crl_distribution_points(cert: &X509) -> Result<Vec<Url>> {
+    let der = cert
+        .to_der()
+        .context(ErrorKind::CertificateRevocationCheck)?;
+
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+    let (_, certificate, _) = read(&der, TAG_SEQUENCE)?;
+    let (_, tbs_certificate, _) = read(certificate, TAG_SEQUENCE)?;
+
+    let (_, tbs_certificate) = read_optional(tbs_certificate, TAG_CONTEXT_0)?; // version
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_INTEGER)?; // serialNumber
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_SEQUENCE)?; // signature
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_SEQUENCE)?; // issuer
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_SEQUENCE)?; // validity
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_SEQUENCE)?; // subject
+    let (_, _, tbs_certificate) = read(tbs_certificate, TAG_SEQUENCE)?; // subjectPublicKeyInfo
+    let (_, tbs_certificate) = read_optional(tbs_certificate, TAG_ISSUER_UNIQUE_ID)?;
+    let (_, tbs_certificate) = read_optional(tbs_certificate, TAG_SUBJECT_UNIQUE_ID)?;
+    let extensions = match read_optional(tbs_certificate, TAG_CONTEXT_3)? {
+        (Some(extensions), _) => extensions,
+        (None, _) => return Ok(vec![]),
+    };
+
+    let mut urls = vec![];
+
+    let (_, mut extensions, _) = read(extensions, TAG_SEQUENCE)?;
+    while !extensions.is_empty() {
+        // Extension ::= SEQUENCE { extnID OID, critical BOOLEAN DEFAULT FALSE, extnValue OCTET STRING }
+        let (_, extension, rest) = read(extensions, TAG_SEQUENCE)?;
+        extensions = rest;
+        let (_, oid, extension) = read(extension, TAG_OID)?;
+        if oid != CRL_DISTRIBUTION_POINTS_OID {
+            continue;
+        }
+        let (_, extension) = read_optional(extension, TAG_BOOLEAN)?;
+        let (_, value, _) = read(extension, TAG_OCTET_STRING)?;
+
+        // CRLDistributionPoints ::= SEQUENCE OF DistributionPoint
+        // DistributionPoint ::= SEQUENCE { distributionPoint [0] DistributionPointName OPTIONAL, ... }
+        // DistributionPointName ::= CHOICE { fullName [0] GeneralNames, ... }
+        let (_, mut distribution_points, _) = read(value, TAG_SEQUENCE)?;
+        while !distribution_points.is_empty() {
+            let (_, distribution_point, rest) = read(distribution_points, TAG_SEQUENCE)?;
+            distribution_points = rest;
+            let full_name = match read_optional(distribution_point, TAG_CONTEXT_0)? {
+                (Some(name), _) => read_optional(name, TAG_CONTEXT_0)?.0,
+                (None, _) => None,
+            };
+
+            let mut general_names = full_name.unwrap_or_default();
+            while !general_names.is_empty() {
+                let (tag, general_name, rest) = read_any(general_names)?;
+                general_names = rest;
+                if tag == TAG_URI {
+                    if let Some(url) = str::from_utf8(general_name)
+                        .ok()
+                        .and_then(|url| Url::parse(url).ok())
+                    {
+                        urls.push(url);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(urls)
+}
+
+fn parse_crl(crl: &[u8], issuer: &X509) -> Result<Crl> {
+    let der = pem_to_der(crl)?;
+
+    // CertificateList ::= SEQUENCE { tbsCertList, signatureAlgorithm, signatureValue BIT STRING }
+    let (_, certificate_list, _) = read(&der, TAG_SEQUENCE)?;
+    let (tbs_cert_list_der, tbs_cert_list, certificate_list) =
+        read(certificate_list, TAG_SEQUENCE)?;
+    let (_, signature_algorithm, certificate_list) = read(certificate_list, TAG_SEQUENCE)?;
+    let (_, signature, _) = read(certificate_list, TAG_BIT_STRING)?;
+
+    verify_signature(tbs_cert_list_der, signature_algorithm, signature, issuer)?;
+
+    // TBSCertList ::= SEQUENCE { version INTEGER OPTIONAL, signature, issuer, thisUpdate,
+    //                            nextUpdate OPTIONAL, revokedCertificates OPTIONAL, ... }
+    let (_, tbs_cert_list) = read_optional(tbs_cert_list, TAG_INTEGER)?; // version
+    let (_, _, tbs_cert_list) = read(tbs_cert_list, TAG_SEQUENCE)?; // signature
+    let (_, _, tbs_cert_list) = read(tbs_cert_list, TAG_SEQUENCE)?; // issuer
+    let (_, tbs_cert_list) = read_time(tbs_cert_list)?; // thisUpdate
+    let (next_update, tbs_cert_list) = match tbs_cert_list.first() {
+        Some(&TAG_UTC_TIME) | Some(&TAG_GENERALIZED_TIME) => {
+            let (next_update, rest) = read_time(tbs_cert_list)?;
+            (Some(next_update), rest)
+        }
+        _ => (None, tbs_cert_list),
+    };
+
+    let mut revoked = HashSet::new();
+    if let (Some(mut revoked_certificates), _) = read_optional(tbs_cert_list, TAG_SEQUENCE)? {
+        while !revoked_certificates.is_empty() {
+            // SEQUENCE { userCertificate INTEGER, revocationDate Time, crlEntryExtensions OPTIONAL }
+            let (_, entry, rest) = read(revoked_certificates, TAG_SEQUENCE)?;
+            revoked_certificates = rest;
+            let (_, serial_number, _) = read(entry, TAG_INTEGER)?;
+            let serial_number =
+                BigNum::from_slice(serial_number).context(ErrorKind::CertificateRevocationCheck)?;
+            revoked.insert(serial_number.to_vec());
+        }
+    }
+
+    Ok(Crl {
+        revoked,
+        next_update,
+    })
+}
+
+fn verify_signature(
+    tbs_cert_list: &[u8],
+    signature_algorithm: &[u8],
+    signature: &[u8],
+    issuer: &X509,
+) -> Result<()> {
+    let (_, algorithm, _) = read(signature_algorithm, TAG_OID)?;
+    let digest = SIGNATURE_ALGORITHMS
+        .iter()
+        .find(|(oid, _)| *oid == algorithm)
+        .map(|(_, digest)| digest())
+        .ok_or(ErrorKind::CertificateRevocationCheck)?;
+
+    // the first byte of a BIT STRING is the number of unused bits in the last byte
+    let signature = match signature.split_first() {
+        Some((&0, signature)) => signature,
+        _ => return Err(Error::from(ErrorKind::CertificateRevocationCheck)),
+    };
+
+    let issuer_key = issuer
+        .public_key()
+        .context(ErrorKind::CertificateRevocationCheck)?;
+    let mut verifier =
+        Verifier::new(digest, &issuer_key).context(ErrorKind::CertificateRevocationCheck)?;
+    verifier
+        .update(tbs_cert_list)
+        .context(ErrorKind::CertificateRevocationCheck)?;
+    if verifier
+        .verify(signature)
+        .context(ErrorKind::CertificateRevocationCheck)?
+    {
+        Ok(())
+    } else {
+        Err(Error::from(ErrorKind::CertificateRevocationCheck))
+    }
+}
+
+/// Reads the `UTCTime` or `GeneralizedTime` at the start of `data`, and returns it and the bytes
+/// that follow it.
+fn read_time(data: &[u8]) -> Result<(DateTime<Utc>, &[u8])> {
+    let (tag, time, rest) = read_any(data)?;
+    let time = str::from_utf8(time).context(ErrorKind::CertificateRevocationCheck)?;
+
+    let time = match tag {
+        // UTCTime has a two-digit year, which is in 1950..=2049
+        TAG_UTC_TIME => {
+            let century = match time.get(..2).and_then(|year| year.parse::<u8>().ok()) {
+                Some(year) if year >= 50 => "19",
+                Some(_) => "20",
+                None => return Err(Error::from(ErrorKind::CertificateRevocationCheck)),
+            };
+            format!("{}{}", century, time)
+        }
+        TAG_GENERALIZED_TIME => time.to_string(),
+        _ => return Err(Error::from(ErrorKind::CertificateRevocationCheck)),
+    };
+
+    let time = NaiveDateTime::parse_from_str(&time, "%Y%m%d%H%M%SZ")
+        .context(ErrorKind::CertificateRevocationCheck)?;
+    Ok((DateTime::<Utc>::from_utc(time, Utc), rest))
+}
+
+fn pem_to_der(crl: &[u8]) -> Result<Vec<u8>> {
+    if !crl.starts_with(b"-----BEGIN") {
+        return Ok(crl.to_vec());
+    }
+
+    let crl = str::from_utf8(crl).context(ErrorKind::CertificateRevocationCheck)?;
+    let base64: String = crl
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = base64::decode(&base64).context(ErrorKind::CertificateRevocationCheck)?;
+    Ok(der)
+}
+
+fn read(data: &[u8], tag: u8) -> Result<(&[u8], &[u8], &[u8])> {
+    Ok(der::read(data, tag).ok_or(ErrorKind::CertificateRevocationCheck)?)
+}
+
+fn read_optional(data: &[u8], tag: u8) -> Result<(Option<&[u8]>, &[u8])> {
+    Ok(der::read_optional(data, tag).ok_or(ErrorKind::CertificateRevocationCheck)?)
+}
+
+fn read_any(data: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    Ok(der::read_any(data).ok_or(ErrorKind::CertificateRevocationCheck)?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use chrono::Duration;
+
+    // A CA, two certificates it issued with the CRL distribution point
+    // http://crl.example.com/test-ca.crl, and the CA's CRL, in which "revoked" is revoked.
+    // The CRL's nextUpdate is 2036-10-11.
+    pub(crate) const CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBgDCCASegAwIBAgIUbVqfsDqY++mnWdnyKLr10eSO8G8wCgYIKoZIzj0EAwIw
+FjEUMBIGA1UEAwwLY3JsLXRlc3QtY2EwHhcNMjYxMDE0MDUxNzE4WhcNMzYxMDEx
+MDUxNzE4WjAWMRQwEgYDVQQDDAtjcmwtdGVzdC1jYTBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABLxcWk2ofRvx5iPfZzYfIa4BnCIQRniK02XAfCKO1ShXuNTGKbZi
+NeQIeOQC4rXBFzhKE95im9hfj0yV+nCt9ICjUzBRMB0GA1UdDgQWBBTg7ieX+6gL
+UNKlHM1mVIDj4E5bUTAfBgNVHSMEGDAWgBTg7ieX+6gLUNKlHM1mVIDj4E5bUTAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0cAMEQCIGd7/LibH0R7QeModGms
+kCtWrkASMbzSPK8b9QYgt+5vAiBdYJKtR5Xie43iBqZl8fGWKCg/ZeGRtkajmNOI
+suP2Nw==
+-----END CERTIFICATE-----
+";
+
+    pub(crate) const REVOKED: &str = "-----BEGIN CERTIFICATE-----
+MIIBmzCCAUKgAwIBAgICEAAwCgYIKoZIzj0EAwIwFjEUMBIGA1UEAwwLY3JsLXRl
+c3QtY2EwHhcNMjYxMDE0MDUxNzE4WhcNMzYxMDExMDUxNzE4WjASMRAwDgYDVQQD
+DAdyZXZva2VkMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEZ09OvwPV8kt4Invj
+Gyy8rObRjMFPrsstXAccC5i81Fr9Z+6FdP4srPoAJkAkJaAQgbzxWkAZBMDwJBjn
+DLaS1KOBgzCBgDAJBgNVHRMEAjAAMDMGA1UdHwQsMCowKKAmoCSGImh0dHA6Ly9j
+cmwuZXhhbXBsZS5jb20vdGVzdC1jYS5jcmwwHQYDVR0OBBYEFAWmiqjZ0yCj0/G1
+RCYVZayjGiodMB8GA1UdIwQYMBaAFODuJ5f7qAtQ0qUczWZUgOPgTltRMAoGCCqG
+SM49BAMCA0cAMEQCIDbLa+ZNeiVuQSspWQdHtEO0Iyoj56+RsLdnLfeimAqwAiBI
+ytqeJJNfjTaLc0pQ1h7GiBMtuxoauDqUHgqvmfDfiA==
+-----END CERTIFICATE-----
+";
+
+    pub(crate) const VALID: &str = "-----BEGIN CERTIFICATE-----
+MIIBmjCCAUCgAwIBAgICEAEwCgYIKoZIzj0EAwIwFjEUMBIGA1UEAwwLY3JsLXRl
+c3QtY2EwHhcNMjYxMDE0MDUxNzE4WhcNMzYxMDExMDUxNzE4WjAQMQ4wDAYDVQQD
+DAV2YWxpZDBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABMQbrv0ZYM8pH+MqDxDO
+70Le9Q5cS+NEtDeTc4Ea8B5o3i38g6nRA3Pexl/nGKZjZDlXWEDZrqK97A5QnK0c
+sGmjgYMwgYAwCQYDVR0TBAIwADAzBgNVHR8ELDAqMCigJqAkhiJodHRwOi8vY3Js
+LmV4YW1wbGUuY29tL3Rlc3QtY2EuY3JsMB0GA1UdDgQWBBR3v25ZSxHcP+Ch3Htr
+0+Uj6llwHzAfBgNVHSMEGDAWgBTg7ieX+6gLUNKlHM1mVIDj4E5bUTAKBggqhkjO
+PQQDAgNIADBFAiEA/NqdBZTPAKeKUFatY0HecJ91MWDokm63R9Hlc4zGI2ACIDZQ
+gHXM3ErUkzUyE7iDnKU7eIcA3Spb3LbfRD2RNHdc
+-----END CERTIFICATE-----
+";
+
+    const CRL: &str = "-----BEGIN X509 CRL-----
+MIHFMG0CAQEwCgYIKoZIzj0EAwIwFjEUMBIGA1UEAwwLY3JsLXRlc3QtY2EXDTI2
+MTAxNDA1MTcxOFoXDTM2MTAxMTA1MTcxOFowFTATAgIQABcNMjYxMDE0MDUxNzE4
+WqAPMA0wCwYDVR0UBAQCAhAAMAoGCCqGSM49BAMCA0gAMEUCIQCKgtMue8Ir2AmF
+ImDnLD+Rz8p3+OFgoVNbrJ+EGTRtDQIgfE2bZbaz5TrE7sDFuBXQRdLaReotzAoG
+njI846UgNd8=
+-----END X509 CRL-----
+";
+
+    const CRL_URL: &str = "http://crl.example.com/test-ca.crl";
+
+    pub(crate) fn cert(pem: &str) -> X509 {
+        X509::from_pem(pem.as_bytes()).unwrap()
+    }
+
+    pub(crate) fn checker(fetches: Arc<AtomicUsize>) -> CrlChecker<impl CrlFetcher> {
+        CrlChecker::new(move |url: &Url| -> Result<Vec<u8>> {
+            assert_eq!(CRL_URL, url.as_str());
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(CRL.as_bytes().to_vec())
+        })
+    }
+
+    #[test]
+    fn distribution_points_are_read() {
+        assert_eq!(
+            vec![Url::parse(CRL_URL).unwrap()],
+            crl_distribution_points(&cert(VALID)).unwrap()
+        );
+        assert!(crl_distribution_points(&cert(CA)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn revoked_certificate_fails() {
+        let checker = checker(Arc::new(AtomicUsize::new(0)));
+
+        let err = checker
+            .check_chain(&[cert(REVOKED)], &cert(CA))
+            .unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidCertificate(0, InvalidCertificateReason::Revoked) => (),
+            kind => panic!("Expected `InvalidCertificate` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn crl_is_cached_until_next_update() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let checker = checker(fetches.clone());
+        let ca = cert(CA);
+
+        checker.check_chain(&[cert(VALID)], &ca).unwrap();
+        checker
+            .check_chain(&[cert(VALID), ca.clone()], &ca)
+            .unwrap();
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+
+        let after_next_update = Utc::now() + Duration::days(3650 * 2);
+        checker
+            .check_chain_at(&[cert(VALID)], &ca, after_next_update)
+            .unwrap();
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn crl_with_bad_signature_is_not_used() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let checker = checker(fetches.clone());
+
+        // the CRL is not signed by the key of "valid", so it neither revokes the certificate
+        // nor gets cached
+        checker.check_chain(&[cert(REVOKED)], &cert(VALID)).unwrap();
+        checker.check_chain(&[cert(REVOKED)], &cert(VALID)).unwrap();
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unavailable_crl_is_skipped() {
+        let checker = CrlChecker::new(|_: &Url| -> Result<Vec<u8>> {
+            Err(Error::from(ErrorKind::CertificateRevocationCheck))
+        });
+
+        checker.check_chain(&[cert(REVOKED)], &cert(CA)).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Just enough DER to read and write the X.509, CRL and PKCS#7 structures that openssl doesn't
+//! expose.
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+/// `[0] IMPLICIT` of a primitive type
+pub const TAG_CONTEXT_0_PRIMITIVE: u8 = 0x80;
+/// `[0] EXPLICIT`, or `[0] IMPLICIT` of a constructed type
+pub const TAG_CONTEXT_0: u8 = 0xa0;
+pub const TAG_CONTEXT_1: u8 = 0xa1;
+pub const TAG_CONTEXT_3: u8 = 0xa3;
+
+/// Reads the DER element at the start of `data`, and returns its tag, its contents and the bytes
+/// that follow it.
+pub fn read_any(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first_len, rest) = rest.split_first()?;
+
+    let (len, rest) = if first_len < 0x80 {
+        (usize::from(first_len), rest)
+    } else {
+        // long form; the indefinite form (0x80) is not allowed in DER
+        let num_bytes = usize::from(first_len & 0x7f);
+        if num_bytes == 0 || num_bytes > 4 || rest.len() < num_bytes {
+            return None;
+        }
+        let len = rest[..num_bytes]
+            .iter()
+            .fold(0_usize, |len, &b| (len << 8) | usize::from(b));
+        (len, &rest[num_bytes..])
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// Reads a DER element with the given tag from the start of `data`, and returns the whole
+/// element, its contents and the bytes that follow it.
+pub fn read(data: &[u8], expected_tag: u8) -> Option<(&[u8], &[u8], &[u8])> {
+    if data.first() != Some(&expected_tag) {
+        return None;
+    }
+
+    let (_, contents, rest) = read_any(data)?;
+    Some((&data[..data.len() - rest.len()], contents, rest))
+}
+
+/// Like `read`, but returns `None` as the element instead of failing if `data` doesn't start with
+/// an element with the given tag.
+pub fn read_optional(data: &[u8], tag: u8) -> Option<(Option<&[u8]>, &[u8])> {
+    if data.first() == Some(&tag) {
+        let (_, contents, rest) = read(data, tag)?;
+        Some((Some(contents), rest))
+    } else {
+        Some((None, data))
+    }
+}
+
+/// Encodes a DER element with the given tag and contents.
+pub fn write(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let len = contents.len();
+    let mut element = vec![tag];
+    if len < 0x80 {
+        #[allow(clippy::cast_possible_truncation)]
+        element.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .iter()
+            .cloned()
+            .skip_while(|&b| b == 0)
+            .collect();
+        #[allow(clippy::cast_possible_truncation)]
+        element.push(0x80 | len_bytes.len() as u8);
+        element.extend(len_bytes);
+    }
+    element.extend_from_slice(contents);
+    element
+}
+
+/// Encodes a SEQUENCE, SET or other constructed element made of the given encoded elements.
+pub fn write_all(tag: u8, elements: &[&[u8]]) -> Vec<u8> {
+    write(tag, &elements.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_then_read_round_trips() {
+        for len in &[0, 1, 0x7f, 0x80, 0xff, 0x100, 0x1_0000] {
+            let contents = vec![0x5a; *len];
+            let element = write(TAG_OCTET_STRING, &contents);
+
+            let (whole, read_contents, rest) = read(&element, TAG_OCTET_STRING).unwrap();
+            assert_eq!(&element[..], whole);
+            assert_eq!(&contents[..], read_contents);
+            assert!(rest.is_empty());
+        }
+
+        assert_eq!(
+            vec![0x04, 0x81, 0x80],
+            write(TAG_OCTET_STRING, &[0; 0x80])[..3].to_vec()
+        );
+        assert!(read(&write(TAG_SET, &[]), TAG_SEQUENCE).is_none());
+        assert!(read_any(&[TAG_SEQUENCE, 0x80]).is_none());
+    }
+}
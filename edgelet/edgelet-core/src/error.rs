@@ -3,6 +3,7 @@
 use std::fmt;
 use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
 use failure::{Backtrace, Context, Fail};
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -19,6 +20,12 @@ pub enum ErrorKind {
     #[fail(display = "Identity error")]
     Certificate,
 
+    #[fail(display = "The certificate chain is empty")]
+    CertificateChainEmpty,
+
+    #[fail(display = "An error occurred validating the certificate chain")]
+    CertificateChainValidation,
+
     #[fail(display = "An error occurred obtaining the certificate contents")]
     CertificateContent,
 
@@ -37,6 +44,15 @@ pub enum ErrorKind {
     #[fail(display = "An error occurred obtaining the certificate's key")]
     CertificateKey,
 
+    #[fail(display = "The certificate is not valid for host name {:?}", _0)]
+    CertificateHostnameMismatch(String),
+
+    #[fail(display = "Could not check certificate expiry")]
+    CertificateExpiryCheck,
+
+    #[fail(display = "Could not check whether the certificate has been revoked")]
+    CertificateRevocationCheck,
+
     #[fail(
         display = "The Connection String is empty. Please update the config.yaml and provide the IoTHub connection information."
     )]
@@ -68,6 +84,12 @@ pub enum ErrorKind {
     )]
     EdgeRuntimeIdentityNotFound,
 
+    #[fail(
+        display = "Edge runtime module has been restarted {} times and will not be restarted again.",
+        _0
+    )]
+    EdgeRuntimeRestartLimitReached(u32),
+
     #[fail(display = "The timer that checks the edge runtime status encountered an error.")]
     EdgeRuntimeStatusCheckerTimer,
 
@@ -80,6 +102,9 @@ pub enum ErrorKind {
     #[fail(display = "Invalid image pull policy configuration {:?}", _0)]
     InvalidImagePullPolicy(String),
 
+    #[fail(display = "Certificate {} in the chain is invalid: {}", _0, _1)]
+    InvalidCertificate(usize, InvalidCertificateReason),
+
     #[fail(display = "Invalid or unsupported certificate issuer.")]
     InvalidIssuer,
 
@@ -89,6 +114,9 @@ pub enum ErrorKind {
     #[fail(display = "Invalid module name {:?}", _0)]
     InvalidModuleName(String),
 
+    #[fail(display = "Invalid module token: {}", _0)]
+    InvalidModuleToken(InvalidModuleTokenReason),
+
     #[fail(display = "Invalid module type {:?}", _0)]
     InvalidModuleType(String),
 
@@ -107,6 +135,9 @@ pub enum ErrorKind {
     #[fail(display = "Invalid URL {:?}", _0)]
     InvalidUrl(String),
 
+    #[fail(display = "Invalid workload capability {:?}", _0)]
+    InvalidWorkloadCapability(String),
+
     #[fail(display = "An error occurred in the key store.")]
     KeyStore,
 
@@ -119,12 +150,24 @@ pub enum ErrorKind {
     #[fail(display = "A module runtime error occurred.")]
     ModuleRuntime,
 
+    #[fail(display = "Secret {:?} not found", _0)]
+    SecretNotFound(String),
+
+    #[fail(display = "An error occurred in the secret store.")]
+    SecretStore,
+
     #[fail(display = "Signing error occurred.")]
     Sign,
 
     #[fail(display = "Signing error occurred. Invalid key length: {}", _0)]
     SignInvalidKeyLength(usize),
 
+    #[fail(display = "Could not run module {:?}", _0)]
+    SpawnModule(String),
+
+    #[fail(display = "An error occurred in the twin cache.")]
+    TwinCache,
+
     #[fail(
         display = "URI {} is unsupported for '{}'. Please check the config.yaml file.",
         _0, _1
@@ -138,6 +181,62 @@ pub enum ErrorKind {
     UnsupportedSettingsFileUri(String, &'static str),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidCertificateReason {
+    Expired(DateTime<Utc>),
+    NotCa,
+    NotYetValid(DateTime<Utc>),
+    Revoked,
+    Signature,
+    Untrusted,
+}
+
+impl Display for InvalidCertificateReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidCertificateReason::Expired(not_after) => {
+                write!(f, "the certificate expired at {}", not_after)
+            }
+            InvalidCertificateReason::NotCa => write!(
+                f,
+                "the certificate is not a CA, but issued the previous certificate in the chain"
+            ),
+            InvalidCertificateReason::NotYetValid(not_before) => {
+                write!(f, "the certificate is not valid before {}", not_before)
+            }
+            InvalidCertificateReason::Revoked => write!(f, "the certificate has been revoked"),
+            InvalidCertificateReason::Signature => write!(
+                f,
+                "the certificate is not issued by the next certificate in the chain"
+            ),
+            InvalidCertificateReason::Untrusted => {
+                write!(f, "the certificate is not issued by a trusted root")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidModuleTokenReason {
+    Expired(DateTime<Utc>),
+    Malformed,
+    Signature,
+}
+
+impl Display for InvalidModuleTokenReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidModuleTokenReason::Expired(expiry) => {
+                write!(f, "the token expired at {}", expiry)
+            }
+            InvalidModuleTokenReason::Malformed => write!(f, "the token is not a valid JWT"),
+            InvalidModuleTokenReason::Signature => {
+                write!(f, "the token is not signed by the device key")
+            }
+        }
+    }
+}
+
 impl Fail for Error {
     fn cause(&self) -> Option<&dyn Fail> {
         self.inner.cause()
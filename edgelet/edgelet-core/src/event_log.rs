@@ -0,0 +1,280 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chrono::prelude::*;
+use futures::sync::mpsc::{self, Receiver, Sender};
+
+/// This is the number of events the log keeps by default, across all modules.
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// This is the number of events buffered for each subscriber. A subscriber that falls this far
+/// behind misses events until it catches up.
+const SUBSCRIBER_BUFFER: usize = 100;
+
+/// A module lifecycle event.
+#[derive(Clone, Debug, PartialEq, serde_derive::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ModuleEvent {
+    Created,
+    Started,
+    Stopped {
+        #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i64>,
+        #[serde(rename = "statusDescription", skip_serializing_if = "Option::is_none")]
+        status_description: Option<String>,
+    },
+    /// The module was restarted. `attempt` counts the consecutive restarts by the watchdog, and
+    /// is 0 for restarts requested through the management API.
+    Restarted {
+        attempt: u32,
+    },
+    Removed,
+    /// The tag of the module's image now refers to the manifest `digest` in its registry.
+    ImageUpdateAvailable {
+        image: String,
+        digest: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, serde_derive::Serialize)]
+pub struct EventLogEntry {
+    timestamp: DateTime<Utc>,
+    #[serde(rename = "moduleId")]
+    module_id: String,
+    event: ModuleEvent,
+}
+
+impl EventLogEntry {
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn module_id(&self) -> &str {
+        &self.module_id
+    }
+
+    pub fn event(&self) -> &ModuleEvent {
+        &self.event
+    }
+}
+
+/// A bounded, in-memory log of module lifecycle events. Once the log is full, recording an
+/// event drops the oldest one.
+///
+/// Clones share the same underlying log.
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    entries: Arc<Mutex<VecDeque<EventLogEntry>>>,
+    capacity: usize,
+    subscribers: Arc<Mutex<Vec<Sender<EventLogEntry>>>>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        EventLog {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            subscribers: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn record(&self, module_id: &str, event: ModuleEvent) {
+        let entry = EventLogEntry {
+            timestamp: Utc::now(),
+            module_id: module_id.to_string(),
+            event,
+        };
+
+        {
+            let mut subscribers = self
+                .subscribers
+                .lock()
+                .expect("Unable to lock the event log subscribers mutex");
+            let live = subscribers
+                .drain(..)
+                .filter_map(|mut subscriber| match subscriber.try_send(entry.clone()) {
+                    Err(ref err) if err.is_disconnected() => None,
+                    _ => Some(subscriber),
+                })
+                .collect();
+            *subscribers = live;
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("Unable to lock the event log mutex");
+        while entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+
+        entries.push_back(entry);
+    }
+
+    /// Returns a stream of the events recorded from now on. The stream ends once the log and all
+    /// its clones are dropped.
+    pub fn subscribe(&self) -> Receiver<EventLogEntry> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+        self.subscribers
+            .lock()
+            .expect("Unable to lock the event log subscribers mutex")
+            .push(sender);
+        receiver
+    }
+
+    /// Returns up to `limit` of the most recent events for the given module, oldest first.
+    pub fn history(&self, module_id: &str, limit: usize) -> Vec<EventLogEntry> {
+        let entries = self
+            .entries
+            .lock()
+            .expect("Unable to lock the event log mutex");
+
+        let mut history: Vec<_> = entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.module_id == module_id)
+            .take(limit)
+            .cloned()
+            .collect();
+        history.reverse();
+        history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Stream;
+
+    use super::*;
+
+    #[test]
+    fn history_is_filtered_by_module() {
+        let log = EventLog::default();
+        log.record("m1", ModuleEvent::Created);
+        log.record("m2", ModuleEvent::Created);
+        log.record("m1", ModuleEvent::Started);
+
+        let history: Vec<_> = log
+            .history("m1", 100)
+            .into_iter()
+            .map(|entry| entry.event().clone())
+            .collect();
+
+        assert_eq!(vec![ModuleEvent::Created, ModuleEvent::Started], history);
+    }
+
+    #[test]
+    fn history_returns_most_recent_events() {
+        let log = EventLog::default();
+        for attempt in 0..5 {
+            log.record("m1", ModuleEvent::Restarted { attempt });
+        }
+
+        let history: Vec<_> = log
+            .history("m1", 2)
+            .into_iter()
+            .map(|entry| entry.event().clone())
+            .collect();
+
+        assert_eq!(
+            vec![
+                ModuleEvent::Restarted { attempt: 3 },
+                ModuleEvent::Restarted { attempt: 4 },
+            ],
+            history
+        );
+    }
+
+    #[test]
+    fn oldest_events_are_dropped_when_full() {
+        let log = EventLog::new(2);
+        log.record("m1", ModuleEvent::Created);
+        log.record("m1", ModuleEvent::Started);
+        log.record("m1", ModuleEvent::Restarted { attempt: 0 });
+
+        let history: Vec<_> = log
+            .history("m1", 100)
+            .into_iter()
+            .map(|entry| entry.event().clone())
+            .collect();
+
+        assert_eq!(
+            vec![ModuleEvent::Started, ModuleEvent::Restarted { attempt: 0 }],
+            history
+        );
+    }
+
+    #[test]
+    fn subscribers_receive_new_events() {
+        let log = EventLog::new(0);
+        log.record("m1", ModuleEvent::Created);
+        let subscriber = log.subscribe();
+        log.record("m1", ModuleEvent::Started);
+        log.record("m2", ModuleEvent::Started);
+        drop(log);
+
+        let events: Vec<_> = subscriber
+            .wait()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.module_id().to_string(), entry.event().clone())
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("m1".to_string(), ModuleEvent::Started),
+                ("m2".to_string(), ModuleEvent::Started),
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn dropped_subscribers_are_removed() {
+        let log = EventLog::default();
+        drop(log.subscribe());
+
+        log.record("m1", ModuleEvent::Created);
+
+        assert!(log.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn image_update_event_ser() {
+        let event = ModuleEvent::ImageUpdateAvailable {
+            image: "registry:5000/module1:1.0".to_string(),
+            digest: "sha256:1234".to_string(),
+        };
+
+        assert_eq!(
+            r#"{"type":"imageUpdateAvailable","image":"registry:5000/module1:1.0","digest":"sha256:1234"}"#,
+            serde_json::to_string(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn event_ser() {
+        let event = ModuleEvent::Stopped {
+            exit_code: Some(137),
+            status_description: Some("OOMKilled".to_string()),
+        };
+
+        assert_eq!(
+            r#"{"type":"stopped","exitCode":137,"statusDescription":"OOMKilled"}"#,
+            serde_json::to_string(&event).unwrap()
+        );
+    }
+}
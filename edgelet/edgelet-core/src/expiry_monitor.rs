@@ -0,0 +1,153 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use failure::ResultExt;
+use futures::{Future, Stream};
+use log::{info, warn, Level};
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use tokio::timer::Interval;
+
+use edgelet_utils::log_failure;
+
+use crate::certificate_validation::parse_openssl_time;
+use crate::crypto::{Certificate, GetTrustBundle};
+use crate::error::{ErrorKind, Result};
+
+/// This is the default frequency with which the monitor checks the trust bundle.
+const DEFAULT_INTERVAL_SECS: u64 = 60 * 60;
+
+/// A certificate that was found to expire within the monitor's threshold.
+#[derive(Clone, Debug)]
+pub struct ExpiringCertificate {
+    subject: String,
+    not_after: DateTime<Utc>,
+    remaining: chrono::Duration,
+}
+
+impl ExpiringCertificate {
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn not_after(&self) -> DateTime<Utc> {
+        self.not_after
+    }
+
+    /// The time left until `not_after`. This is negative if the certificate has already expired.
+    pub fn remaining(&self) -> chrono::Duration {
+        self.remaining
+    }
+}
+
+/// Periodically reads the trust bundle that the workload API serves to modules and logs a
+/// warning for every certificate in it that expires within `threshold`.
+pub struct CertificateExpiryMonitor<T> {
+    trust_bundle: T,
+    threshold: Duration,
+    interval: Duration,
+}
+
+impl<T> CertificateExpiryMonitor<T> {
+    pub fn new(trust_bundle: T, threshold: Duration) -> Self {
+        CertificateExpiryMonitor {
+            trust_bundle,
+            threshold,
+            interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<T> CertificateExpiryMonitor<T>
+where
+    T: GetTrustBundle,
+{
+    /// Reads the trust bundle once and returns the certificates that expire within the threshold,
+    /// logging a warning for each of them.
+    pub fn check(&self) -> Result<Vec<ExpiringCertificate>> {
+        let trust_bundle = self
+            .trust_bundle
+            .get_trust_bundle()
+            .and_then(|cert| cert.pem())
+            .context(ErrorKind::CertificateExpiryCheck)?;
+        let expiring = expiring_certificates(trust_bundle.as_ref(), self.threshold, Utc::now())?;
+
+        for cert in &expiring {
+            warn!(
+                "Certificate {} expires at {} ({} hours remaining), which is within the {} hour \
+                 warning threshold",
+                cert.subject,
+                cert.not_after,
+                cert.remaining.num_hours(),
+                self.threshold.as_secs() / 3600,
+            );
+        }
+
+        Ok(expiring)
+    }
+
+    /// Runs `check` on every interval until `shutdown_signal` completes. Failures to check are
+    /// logged and do not stop the monitor.
+    pub fn run_until<F>(self, shutdown_signal: F) -> impl Future<Item = (), Error = ()>
+    where
+        F: Future<Item = (), Error = ()>,
+    {
+        info!(
+            "Starting certificate expiry monitor with {} second frequency...",
+            self.interval.as_secs()
+        );
+
+        let monitor = Interval::new(Instant::now(), self.interval)
+            .map_err(|err| warn!("The certificate expiry monitor timer failed: {}", err))
+            .for_each(move |_| {
+                if let Err(err) = self.check() {
+                    warn!("Error in certificate expiry monitor:");
+                    log_failure(Level::Warn, &err);
+                }
+                Ok(())
+            });
+
+        shutdown_signal.select(monitor).then(|_| Ok(()))
+    }
+}
+
+fn expiring_certificates(
+    trust_bundle: &[u8],
+    threshold: Duration,
+    now: DateTime<Utc>,
+) -> Result<Vec<ExpiringCertificate>> {
+    let threshold =
+        chrono::Duration::from_std(threshold).context(ErrorKind::CertificateExpiryCheck)?;
+    let certs = X509::stack_from_pem(trust_bundle).context(ErrorKind::CertificateExpiryCheck)?;
+
+    let mut expiring = vec![];
+    for cert in certs {
+        let not_after =
+            parse_openssl_time(cert.not_after()).context(ErrorKind::CertificateExpiryCheck)?;
+        let remaining = not_after.signed_duration_since(now);
+        if remaining <= threshold {
+            expiring.push(ExpiringCertificate {
+                subject: common_name(&cert),
+                not_after,
+                remaining,
+            });
+        }
+    }
+
+    Ok(expiring)
+}
+
+fn common_name(cert: &X509) -> String {
+    cert.subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|entry| entry.data().as_utf8().ok())
+        .map_or_else(|| "<unknown>".to_string(), |cn| cn.to_string())
+}
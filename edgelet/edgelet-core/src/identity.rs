@@ -84,12 +84,22 @@ pub trait IdentityManager {
     fn delete(&mut self, id: IdentitySpec) -> Self::DeleteFuture;
 }
 
+/// Fetches module twins from IoT Hub.
+pub trait GetModuleTwin {
+    type Error: Fail;
+    type GetTwinFuture: Future<Item = Option<serde_json::Value>, Error = Self::Error> + Send;
+
+    /// Resolves to `None` if the module doesn't exist.
+    fn get_twin(&self, module_id: &str) -> Self::GetTwinFuture;
+}
+
 // Useful for error contexts
 #[derive(Clone, Debug)]
 pub enum IdentityOperation {
     CreateIdentity(String),
     DeleteIdentity(String),
     GetIdentity(String),
+    GetTwin(String),
     ListIdentities,
     UpdateIdentity(String),
 }
@@ -104,6 +114,7 @@ impl fmt::Display for IdentityOperation {
                 write!(f, "Could not delete identity {}", name)
             }
             IdentityOperation::GetIdentity(name) => write!(f, "Could not get identity {}", name),
+            IdentityOperation::GetTwin(name) => write!(f, "Could not get twin of module {}", name),
             IdentityOperation::ListIdentities => write!(f, "Could not list identities"),
             IdentityOperation::UpdateIdentity(name) => {
                 write!(f, "Could not update identity {}", name)
@@ -19,45 +19,75 @@ use url::Url;
 mod authentication;
 mod authorization;
 mod certificate_properties;
+mod certificate_validation;
+mod config_validation;
+mod crl;
 pub mod crypto;
+pub mod der;
 mod error;
+mod event_log;
+mod expiry_monitor;
 mod identity;
 mod logs;
 mod module;
+pub mod module_token;
 mod network;
+mod secret;
 mod settings;
+mod spawn;
+mod twin;
 pub mod watchdog;
 pub mod workload;
 
 pub use authentication::Authenticator;
 pub use authorization::{AuthId, ModuleId, Policy};
 pub use certificate_properties::{CertificateIssuer, CertificateProperties, CertificateType};
+pub use certificate_validation::{
+    parse_openssl_time, validate_certificate_chain, validate_certificate_chain_pem, validate_san,
+};
+pub use config_validation::{validate_config, ConfigError};
+pub use crl::{crl_distribution_points, CrlChecker, CrlFetcher};
 pub use crypto::{
     Certificate, CreateCertificate, Decrypt, Encrypt, GetDeviceIdentityCertificate, GetHsmVersion,
     GetIssuerAlias, GetTrustBundle, KeyBytes, KeyIdentity, KeyStore, MakeRandom,
     MasterEncryptionKey, PrivateKey, Signature, IOTEDGED_CA_ALIAS,
 };
-pub use error::{Error, ErrorKind};
-pub use identity::{AuthType, Identity, IdentityManager, IdentityOperation, IdentitySpec};
-pub use logs::{Chunked, LogChunk, LogDecode};
+pub use error::{Error, ErrorKind, InvalidCertificateReason, InvalidModuleTokenReason};
+pub use event_log::{EventLog, EventLogEntry, ModuleEvent};
+pub use expiry_monitor::{CertificateExpiryMonitor, ExpiringCertificate};
+pub use identity::{
+    AuthType, GetModuleTwin, Identity, IdentityManager, IdentityOperation, IdentitySpec,
+};
+pub use logs::{Chunked, LogChunk, LogDecode, LogsReader};
 pub use module::{
-    DiskInfo, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module, ModuleOperation,
-    ModuleRegistry, ModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState, ModuleSpec,
-    ModuleStatus, ModuleTop, ProvisioningResult, RegistryOperation, RuntimeOperation, SystemInfo,
-    SystemResources,
+    is_host_path_allowed, DiskInfo, ImagePullPolicy, ImageSizeInfo, LogOptions, LogTail,
+    MakeModuleRuntime, Module, ModuleOperation, ModuleRegistry, ModuleRuntime,
+    ModuleRuntimeErrorReason, ModuleRuntimeState, ModuleSpec, ModuleStatus, ModuleTop,
+    ProvisioningResult, PruneReport, RegistryOperation, ResourceLimits, RestartPolicy,
+    RuntimeOperation, SignatureSource, SignatureVerificationConfig, SystemInfo, SystemResources,
+    TopResult, VolumeMount, VolumeSource, WorkloadCapability,
 };
 pub use network::{Ipam, IpamConfig, MobyNetwork, Network};
+pub use secret::SecretStore;
 pub use settings::{
-    AttestationMethod, Certificates, Connect, Dps, External, Listen, Manual, ManualAuthMethod,
-    ManualDeviceConnectionString, ManualX509Auth, Protocol, Provisioning, ProvisioningType,
-    RetryLimit, RuntimeSettings, Settings, SymmetricKeyAttestationInfo, TpmAttestationInfo,
+    AttestationMethod, CallerRateLimits, Certificates, Connect, Dps, External,
+    Fido2AttestationInfo, Listen, ManagementAuthSettings, Manual, ManualAuthMethod,
+    ManualDeviceConnectionString, ManualX509Auth, MetricsSettings, Pkcs11Settings, Protocol,
+    Provisioning, ProvisioningType, RateLimit, RateLimitSettings, RetryLimit, RuntimeSettings,
+    RuntimeType, Settings, SymmetricKeyAttestationInfo, TpmAttestationInfo, TracingSettings,
     WatchdogSettings, X509AttestationInfo,
 };
+pub use spawn::{SpawnModule, SpawnOutput};
+pub use twin::{CachedTwin, TwinCache};
 pub use workload::WorkloadConfig;
 
 /// This is the default auto generated certificate life
 pub const DEFAULT_AUTO_GENERATED_CA_LIFETIME_DAYS: u16 = 90;
 
+/// This is the default percentage of the Edge CA's validity that may remain before it is
+/// regenerated
+pub const DEFAULT_EDGE_CA_RENEWAL_THRESHOLD_PERCENT: u8 = 20;
+
 lazy_static! {
     static ref VERSION: &'static str =
         option_env!("VERSION").unwrap_or_else(|| include_str!("../../version.txt").trim());
@@ -4,7 +4,9 @@ use std::cmp;
 use std::io;
 
 use bytes::{Buf, BufMut, Bytes, BytesMut, IntoBuf};
+use failure::Fail;
 use futures::prelude::*;
+use futures::stream::MapErr;
 use futures::try_ready;
 use tokio::codec::length_delimited;
 use tokio::codec::FramedRead;
@@ -169,13 +171,95 @@ where
 {
 }
 
+/// Reads the raw log stream of a module as it is produced.
+///
+/// This wraps the future returned by [`ModuleRuntime::logs`] so that callers can tail a running
+/// module with [`AsyncRead`] instead of waiting for the stream to end. Dropping the reader stops
+/// tailing. The bytes read still carry the stream headers and can be parsed with [`LogDecode`].
+///
+/// [`ModuleRuntime::logs`]: trait.ModuleRuntime.html#tymethod.logs
+pub struct LogsReader<F, S>
+where
+    F: Future<Item = S, Error = S::Error>,
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+    S::Error: Fail,
+{
+    state: LogsReaderState<F, S>,
+}
+
+enum LogsReaderState<F, S>
+where
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+{
+    Connecting(F),
+    Reading(Chunked<MapErr<S, fn(S::Error) -> io::Error>, S::Item>),
+}
+
+impl<F, S> LogsReader<F, S>
+where
+    F: Future<Item = S, Error = S::Error>,
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+    S::Error: Fail,
+{
+    pub fn new(logs: F) -> Self {
+        LogsReader {
+            state: LogsReaderState::Connecting(logs),
+        }
+    }
+}
+
+impl<F, S> io::Read for LogsReader<F, S>
+where
+    F: Future<Item = S, Error = S::Error>,
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+    S::Error: Fail,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let logs = match self.state {
+                LogsReaderState::Connecting(ref mut logs) => match logs.poll() {
+                    Ok(Async::Ready(logs)) => logs,
+                    Ok(Async::NotReady) => return Err(io::Error::from(io::ErrorKind::WouldBlock)),
+                    Err(err) => return Err(to_io_error(err)),
+                },
+                LogsReaderState::Reading(ref mut chunked) => return chunked.read(buf),
+            };
+
+            self.state = LogsReaderState::Reading(Chunked::new(
+                logs.map_err(to_io_error as fn(S::Error) -> io::Error),
+            ));
+        }
+    }
+}
+
+impl<F, S> AsyncRead for LogsReader<F, S>
+where
+    F: Future<Item = S, Error = S::Error>,
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+    S::Error: Fail,
+{
+}
+
+fn to_io_error<E: Fail>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.compat())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::io::Read;
 
-    use futures::stream::iter_ok;
+    use futures::future;
+    use futures::stream::{iter_ok, IterOk};
+    use futures::sync::oneshot;
+
+    use crate::error::{Error, ErrorKind};
 
     #[test]
     fn smoke_test() {
@@ -219,4 +303,49 @@ mod tests {
         }
         assert_eq!(b"Roses are red violets are blue", read_buffer);
     }
+
+    #[test]
+    fn logs_reader_reads_stream() {
+        let chunks = vec![&b"Roses are"[..], &b" red"[..]];
+        let logs = future::ok(iter_ok::<Vec<&[u8]>, Error>(chunks));
+
+        let mut reader = LogsReader::new(logs);
+        let read_buffer = &mut [0_u8; 13];
+        reader.read_exact(read_buffer).unwrap();
+
+        assert_eq!(b"Roses are red", read_buffer);
+    }
+
+    #[test]
+    fn logs_reader_waits_for_logs() {
+        let chunks = vec![&b"Roses are red"[..]];
+        let (tx, rx) = oneshot::channel();
+        let logs = rx.map_err(|_| Error::from(ErrorKind::ModuleRuntime));
+
+        let mut reader = LogsReader::new(logs);
+        let read_buffer = &mut [0_u8; 13];
+        let err = future::lazy(|| reader.read(read_buffer))
+            .wait()
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+
+        tx.send(iter_ok::<Vec<&[u8]>, Error>(chunks)).unwrap();
+        future::lazy(|| reader.read_exact(read_buffer))
+            .wait()
+            .unwrap();
+
+        assert_eq!(b"Roses are red", read_buffer);
+    }
+
+    #[test]
+    fn logs_reader_returns_logs_error() {
+        let logs = future::err::<IterOk<std::vec::IntoIter<&[u8]>, Error>, _>(Error::from(
+            ErrorKind::ModuleRuntime,
+        ));
+
+        let mut reader = LogsReader::new(logs);
+        let err = reader.read(&mut [0_u8; 1]).unwrap_err();
+
+        assert_eq!(io::ErrorKind::Other, err.kind());
+    }
 }
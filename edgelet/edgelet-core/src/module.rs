@@ -3,6 +3,8 @@
 use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 use std::result::Result as StdResult;
 use std::str::FromStr;
 use std::string::ToString;
@@ -12,11 +14,14 @@ use chrono::prelude::*;
 use failure::{Fail, ResultExt};
 use futures::{Future, Stream};
 use serde_json;
+use url::Url;
 
 use edgelet_utils::{ensure_not_empty_with_context, serialize_ordered};
 
 use crate::error::{Error, ErrorKind, Result};
+use crate::logs::LogsReader;
 use crate::settings::RuntimeSettings;
+use crate::spawn::SpawnModule;
 use crate::GetTrustBundle;
 
 #[derive(Clone, Copy, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
@@ -150,6 +155,25 @@ pub struct ModuleSpec<T> {
     #[serde(default)]
     #[serde(rename = "imagePullPolicy")]
     image_pull_policy: ImagePullPolicy,
+    #[serde(default)]
+    #[serde(rename = "resourceLimits")]
+    resource_limits: ResourceLimits,
+    #[serde(default)]
+    #[serde(rename = "restartPolicy")]
+    restart_policy: RestartPolicy,
+    #[serde(default)]
+    #[serde(rename = "logLevel", skip_serializing_if = "Option::is_none")]
+    log_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    capabilities: Option<Vec<WorkloadCapability>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<VolumeMount>,
+    #[serde(default)]
+    #[serde(
+        rename = "imageSignatureVerification",
+        skip_serializing_if = "Option::is_none"
+    )]
+    image_signature_verification: Option<SignatureVerificationConfig>,
 }
 
 impl<T> Clone for ModuleSpec<T>
@@ -163,6 +187,12 @@ where
             config: self.config.clone(),
             env: self.env.clone(),
             image_pull_policy: self.image_pull_policy,
+            resource_limits: self.resource_limits,
+            restart_policy: self.restart_policy,
+            log_level: self.log_level.clone(),
+            capabilities: self.capabilities.clone(),
+            volumes: self.volumes.clone(),
+            image_signature_verification: self.image_signature_verification.clone(),
         }
     }
 }
@@ -184,6 +214,12 @@ impl<T> ModuleSpec<T> {
             config,
             env,
             image_pull_policy,
+            resource_limits: ResourceLimits::default(),
+            restart_policy: RestartPolicy::default(),
+            log_level: None,
+            capabilities: None,
+            volumes: Vec::new(),
+            image_signature_verification: None,
         })
     }
 
@@ -243,6 +279,403 @@ impl<T> ModuleSpec<T> {
         self.image_pull_policy = image_pull_policy;
         self
     }
+
+    pub fn resource_limits(&self) -> &ResourceLimits {
+        &self.resource_limits
+    }
+
+    pub fn with_resource_limits(mut self, resource_limits: ResourceLimits) -> Self {
+        self.resource_limits = resource_limits;
+        self
+    }
+
+    pub fn restart_policy(&self) -> &RestartPolicy {
+        &self.restart_policy
+    }
+
+    pub fn with_restart_policy(mut self, restart_policy: RestartPolicy) -> Self {
+        self.restart_policy = restart_policy;
+        self
+    }
+
+    /// The verbosity the module should log at. The runtime hands it to the module in the
+    /// `RuntimeLogLevel` environment variable.
+    pub fn log_level(&self) -> Option<&str> {
+        self.log_level.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn with_log_level(mut self, log_level: Option<String>) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// The workload API operations the module may call. `None` allows all of them.
+    pub fn capabilities(&self) -> Option<&[WorkloadCapability]> {
+        self.capabilities.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Option<Vec<WorkloadCapability>>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn volumes(&self) -> &[VolumeMount] {
+        &self.volumes
+    }
+
+    pub fn with_volumes(mut self, volumes: Vec<VolumeMount>) -> Self {
+        self.volumes = volumes;
+        self
+    }
+
+    /// Where to find the signature of the module's image. When set, the module isn't created
+    /// unless its image has a valid signature.
+    pub fn image_signature_verification(&self) -> Option<&SignatureVerificationConfig> {
+        self.image_signature_verification.as_ref()
+    }
+
+    pub fn with_image_signature_verification(
+        mut self,
+        image_signature_verification: Option<SignatureVerificationConfig>,
+    ) -> Self {
+        self.image_signature_verification = image_signature_verification;
+        self
+    }
+}
+
+/// Where the signature of a module's image is published, and the key it must have been made with.
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+pub struct SignatureVerificationConfig {
+    source: SignatureSource,
+    #[serde(rename = "publicKey")]
+    public_key: String,
+}
+
+impl SignatureVerificationConfig {
+    pub fn new(source: SignatureSource, public_key: String) -> Self {
+        SignatureVerificationConfig { source, public_key }
+    }
+
+    pub fn source(&self) -> &SignatureSource {
+        &self.source
+    }
+
+    /// The PEM of the public key the image must be signed with, or of a certificate for it.
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+}
+
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureSource {
+    /// A TUF repository, such as a Notary server, whose targets metadata lists the digest of each
+    /// signed tag.
+    Tuf(#[serde(with = "url_serde")] Url),
+    /// A sigstore Rekor transparency log that has an entry for the signed digest of the image.
+    Rekor(#[serde(with = "url_serde")] Url),
+}
+
+/// Storage mounted into a module's container at `target`.
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+pub struct VolumeMount {
+    source: VolumeSource,
+    target: String,
+    #[serde(default, rename = "readOnly")]
+    read_only: bool,
+}
+
+impl VolumeMount {
+    pub fn new(source: VolumeSource, target: String) -> Self {
+        VolumeMount {
+            source,
+            target,
+            read_only: false,
+        }
+    }
+
+    pub fn source(&self) -> &VolumeSource {
+        &self.source
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+}
+
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VolumeSource {
+    /// A volume managed by the container runtime, created on first use.
+    NamedVolume(String),
+    /// A directory or file on the host.
+    HostPath(PathBuf),
+    /// An in-memory filesystem that is discarded when the container stops.
+    Tmpfs {
+        #[serde(rename = "sizeBytes")]
+        size_bytes: u64,
+    },
+}
+
+impl VolumeSource {
+    /// Whether this is a host path that lies under one of `allowed_prefixes`.
+    ///
+    /// Paths that are relative or contain `..` components are never allowed, since they could
+    /// otherwise escape the prefix. Sources other than host paths are always allowed.
+    pub fn is_allowed(&self, allowed_prefixes: &[PathBuf]) -> bool {
+        match self {
+            VolumeSource::HostPath(path) => is_host_path_allowed(path, allowed_prefixes),
+            VolumeSource::NamedVolume(_) | VolumeSource::Tmpfs { .. } => true,
+        }
+    }
+}
+
+/// Whether the host path `path` lies under one of `allowed_prefixes`, with the same rules as
+/// [`VolumeSource::is_allowed`].
+///
+/// Symlinks are resolved first, so a link under an allowed prefix can't point outside of it.
+pub fn is_host_path_allowed(path: &Path, allowed_prefixes: &[PathBuf]) -> bool {
+    if !path.is_absolute()
+        || path
+            .components()
+            .any(|component| component == Component::ParentDir)
+    {
+        return false;
+    }
+
+    let path = resolve_symlinks(path);
+    allowed_prefixes
+        .iter()
+        .any(|prefix| path.starts_with(resolve_symlinks(prefix)))
+}
+
+/// Resolves the symlinks in `path`. Docker creates host directories that don't exist yet, so only
+/// the part of the path that exists is resolved and the rest is appended as is.
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            return missing
+                .iter()
+                .rev()
+                .fold(resolved, |resolved, name| resolved.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// An operation of the workload API that a module can be allowed to call.
+#[derive(Clone, Copy, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkloadCapability {
+    Sign,
+    Encrypt,
+    Decrypt,
+    GetCertificate,
+}
+
+impl fmt::Display for WorkloadCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkloadCapability::Sign => write!(f, "sign"),
+            WorkloadCapability::Encrypt => write!(f, "encrypt"),
+            WorkloadCapability::Decrypt => write!(f, "decrypt"),
+            WorkloadCapability::GetCertificate => write!(f, "getCertificate"),
+        }
+    }
+}
+
+impl FromStr for WorkloadCapability {
+    type Err = Error;
+
+    fn from_str(s: &str) -> StdResult<WorkloadCapability, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sign" => Ok(WorkloadCapability::Sign),
+            "encrypt" => Ok(WorkloadCapability::Encrypt),
+            "decrypt" => Ok(WorkloadCapability::Decrypt),
+            "getcertificate" => Ok(WorkloadCapability::GetCertificate),
+            _ => Err(Error::from(ErrorKind::InvalidWorkloadCapability(
+                s.to_string(),
+            ))),
+        }
+    }
+}
+
+/// CPU and memory constraints applied to a module's container.
+///
+/// The field names match Docker's `HostConfig` so that the limits can be read straight out of a
+/// module's `createOptions`.
+#[derive(
+    Clone, Copy, Debug, Default, serde_derive::Deserialize, PartialEq, serde_derive::Serialize,
+)]
+pub struct ResourceLimits {
+    #[serde(rename = "CpuShares", skip_serializing_if = "Option::is_none")]
+    cpu_shares: Option<u64>,
+    #[serde(rename = "Memory", skip_serializing_if = "Option::is_none")]
+    memory_bytes: Option<u64>,
+    #[serde(rename = "MemorySwap", skip_serializing_if = "Option::is_none")]
+    memory_swap_bytes: Option<i64>,
+    #[serde(rename = "PidsLimit", skip_serializing_if = "Option::is_none")]
+    pids_limit: Option<i64>,
+}
+
+impl ResourceLimits {
+    pub fn new() -> Self {
+        ResourceLimits::default()
+    }
+
+    pub fn cpu_shares(&self) -> Option<u64> {
+        self.cpu_shares
+    }
+
+    pub fn with_cpu_shares(mut self, cpu_shares: u64) -> Self {
+        self.cpu_shares = Some(cpu_shares);
+        self
+    }
+
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.memory_bytes
+    }
+
+    pub fn with_memory_bytes(mut self, memory_bytes: u64) -> Self {
+        self.memory_bytes = Some(memory_bytes);
+        self
+    }
+
+    /// Total memory limit (memory + swap). `-1` means unlimited swap.
+    pub fn memory_swap_bytes(&self) -> Option<i64> {
+        self.memory_swap_bytes
+    }
+
+    pub fn with_memory_swap_bytes(mut self, memory_swap_bytes: i64) -> Self {
+        self.memory_swap_bytes = Some(memory_swap_bytes);
+        self
+    }
+
+    /// Maximum number of processes in the container. `-1` means unlimited.
+    pub fn pids_limit(&self) -> Option<i64> {
+        self.pids_limit
+    }
+
+    pub fn with_pids_limit(mut self, pids_limit: i64) -> Self {
+        self.pids_limit = Some(pids_limit);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+}
+
+/// Controls how long the watchdog waits before restarting a module that has stopped.
+///
+/// The delay before the n-th consecutive restart is `initial_delay * backoff_factor^n`, clamped
+/// to `max_delay`.
+#[derive(Clone, Copy, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    #[serde(rename = "maxRestarts", skip_serializing_if = "Option::is_none")]
+    max_restarts: Option<u32>,
+    #[serde(rename = "initialDelaySecs", with = "duration_secs")]
+    initial_delay: Duration,
+    #[serde(rename = "backoffFactor")]
+    backoff_factor: f64,
+    #[serde(rename = "maxDelaySecs", with = "duration_secs")]
+    max_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_restarts: None,
+            initial_delay: Duration::from_secs(1),
+            backoff_factor: 2.0,
+            max_delay: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn new(
+        max_restarts: Option<u32>,
+        initial_delay: Duration,
+        backoff_factor: f64,
+        max_delay: Duration,
+    ) -> Self {
+        RestartPolicy {
+            max_restarts,
+            initial_delay,
+            backoff_factor,
+            max_delay,
+        }
+    }
+
+    /// The number of consecutive restarts after which the module is no longer restarted.
+    /// `None` means there is no limit.
+    pub fn max_restarts(&self) -> Option<u32> {
+        self.max_restarts
+    }
+
+    pub fn initial_delay(&self) -> Duration {
+        self.initial_delay
+    }
+
+    pub fn backoff_factor(&self) -> f64 {
+        self.backoff_factor
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Returns the delay before the restart with the given zero-based attempt number.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let max_delay = self.max_delay.as_secs_f64();
+        let delay = self.initial_delay.as_secs_f64() * self.backoff_factor.powf(f64::from(attempt));
+
+        // also guards against NaN and infinity from a bad backoff factor
+        if delay >= 0.0 && delay < max_delay {
+            Duration::from_secs_f64(delay)
+        } else {
+            self.max_delay
+        }
+    }
+}
+
+mod duration_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -335,6 +768,11 @@ pub trait Module {
     fn type_(&self) -> &str;
     fn config(&self) -> &Self::Config;
     fn runtime_state(&self) -> Self::RuntimeStateFuture;
+
+    /// The workload API operations the module may call, or `None` if it may call all of them.
+    fn capabilities(&self) -> Option<&[WorkloadCapability]> {
+        None
+    }
 }
 
 pub trait ModuleRegistry {
@@ -383,6 +821,10 @@ impl SystemInfo {
 pub struct SystemResources {
     host_uptime: u64,
     process_uptime: u64,
+    cpu_cores: usize,
+    cpu_usage_percent: f64,
+    memory_total_kb: u64,
+    memory_available_kb: u64,
     used_cpu: f64,
     used_ram: u64,
     total_ram: u64,
@@ -403,6 +845,10 @@ impl SystemResources {
         SystemResources {
             host_uptime,
             process_uptime,
+            cpu_cores: 0,
+            cpu_usage_percent: 0.0,
+            memory_total_kb: 0,
+            memory_available_kb: 0,
             used_cpu,
             used_ram,
             total_ram,
@@ -410,6 +856,26 @@ impl SystemResources {
             docker_stats,
         }
     }
+
+    pub fn with_cpu_cores(mut self, cpu_cores: usize) -> Self {
+        self.cpu_cores = cpu_cores;
+        self
+    }
+
+    /// Host CPU usage across all cores, from 0 to 100.
+    pub fn with_cpu_usage_percent(mut self, cpu_usage_percent: f64) -> Self {
+        self.cpu_usage_percent = cpu_usage_percent;
+        self
+    }
+
+    /// Total and available host memory in KiB. Available memory is what can be given to new
+    /// processes without swapping, like `MemAvailable` in `/proc/meminfo`, which includes
+    /// reclaimable caches and so is usually more than the free memory.
+    pub fn with_memory_kb(mut self, memory_total_kb: u64, memory_available_kb: u64) -> Self {
+        self.memory_total_kb = memory_total_kb;
+        self.memory_available_kb = memory_available_kb;
+        self
+    }
 }
 
 #[derive(Debug, serde_derive::Serialize)]
@@ -461,6 +927,100 @@ impl ModuleTop {
     }
 }
 
+/// The processes running inside a module, as reported by the runtime.
+#[derive(Clone, Debug, Default, PartialEq, serde_derive::Serialize)]
+pub struct TopResult {
+    /// The column titles of the process table. Example: ["UID", "PID", "PPID", "C", "STIME", "TTY", "TIME", "CMD"]
+    titles: Vec<String>,
+    /// One row per process, with one value per column title.
+    processes: Vec<Vec<String>>,
+}
+
+impl TopResult {
+    pub fn new(titles: Vec<String>, processes: Vec<Vec<String>>) -> Self {
+        TopResult { titles, processes }
+    }
+
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+
+    pub fn processes(&self) -> &[Vec<String>] {
+        &self.processes
+    }
+
+    /// The IDs of the processes, read from the `PID` column. Rows without a valid PID are
+    /// skipped.
+    pub fn process_ids(&self) -> Vec<i32> {
+        let pid_index = match self.titles.iter().position(|title| title == "PID") {
+            Some(pid_index) => pid_index,
+            None => return Vec::new(),
+        };
+        self.processes
+            .iter()
+            .filter_map(|process| process.get(pid_index)?.parse().ok())
+            .collect()
+    }
+}
+
+/// The size of an image on disk, as reported by the runtime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde_derive::Serialize)]
+pub struct ImageSizeInfo {
+    /// The size of the image's own layers. Docker reports this as the image's `Size`.
+    compressed_bytes: u64,
+    /// The size of the image together with the layers it shares with its parent images. Docker
+    /// reports this as the image's `VirtualSize`.
+    uncompressed_bytes: u64,
+    layers: usize,
+}
+
+impl ImageSizeInfo {
+    pub fn new(compressed_bytes: u64, uncompressed_bytes: u64, layers: usize) -> Self {
+        ImageSizeInfo {
+            compressed_bytes,
+            uncompressed_bytes,
+            layers,
+        }
+    }
+
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.uncompressed_bytes
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+}
+
+/// The images removed by `ModuleRuntime::prune_images`.
+#[derive(Clone, Debug, Default, PartialEq, serde_derive::Serialize)]
+pub struct PruneReport {
+    images_deleted: Vec<String>,
+    /// The sum of the sizes of the removed images, as reported by the runtime.
+    space_reclaimed: u64,
+}
+
+impl PruneReport {
+    pub fn new(images_deleted: Vec<String>, space_reclaimed: u64) -> Self {
+        PruneReport {
+            images_deleted,
+            space_reclaimed,
+        }
+    }
+
+    pub fn images_deleted(&self) -> &[String] {
+        &self.images_deleted
+    }
+
+    pub fn space_reclaimed(&self) -> u64 {
+        self.space_reclaimed
+    }
+}
+
 pub trait ProvisioningResult {
     fn device_id(&self) -> &str;
     fn hub_name(&self) -> &str;
@@ -502,7 +1062,10 @@ pub trait ModuleRuntime: Sized {
     type StopFuture: Future<Item = (), Error = Self::Error> + Send;
     type SystemInfoFuture: Future<Item = SystemInfo, Error = Self::Error> + Send;
     type SystemResourcesFuture: Future<Item = SystemResources, Error = Self::Error> + Send;
+    type TopFuture: Future<Item = TopResult, Error = Self::Error> + Send;
     type RemoveAllFuture: Future<Item = (), Error = Self::Error> + Send;
+    type ImageSizeFuture: Future<Item = ImageSizeInfo, Error = Self::Error> + Send;
+    type PruneImagesFuture: Future<Item = PruneReport, Error = Self::Error> + Send;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture;
     fn get(&self, id: &str) -> Self::GetFuture;
@@ -512,11 +1075,36 @@ pub trait ModuleRuntime: Sized {
     fn remove(&self, id: &str) -> Self::RemoveFuture;
     fn system_info(&self) -> Self::SystemInfoFuture;
     fn system_resources(&self) -> Self::SystemResourcesFuture;
+    fn top(&self, id: &str) -> Self::TopFuture;
     fn list(&self) -> Self::ListFuture;
     fn list_with_details(&self) -> Self::ListWithDetailsStream;
     fn logs(&self, id: &str, options: &LogOptions) -> Self::LogsFuture;
     fn registry(&self) -> &Self::ModuleRegistry;
     fn remove_all(&self) -> Self::RemoveAllFuture;
+    fn image_size(&self, image: &str) -> Self::ImageSizeFuture;
+
+    /// Removes the images that aren't referred to by any of `keep`. Images that are still used by
+    /// a container are left in place.
+    fn prune_images(&self, keep: &[String]) -> Self::PruneImagesFuture;
+
+    /// Like `logs`, but returns an `AsyncRead` that yields the log stream as it is produced.
+    fn logs_stream(
+        &self,
+        id: &str,
+        options: &LogOptions,
+    ) -> LogsReader<Self::LogsFuture, Self::Logs> {
+        LogsReader::new(self.logs(id, options))
+    }
+
+    /// Creates and starts a module, and yields its output until it exits. See [`SpawnModule`].
+    ///
+    /// [`SpawnModule`]: struct.SpawnModule.html
+    fn spawn_module(&self, module: ModuleSpec<Self::Config>) -> SpawnModule<Self>
+    where
+        Self: Clone,
+    {
+        SpawnModule::new(self.clone(), module)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -559,10 +1147,13 @@ impl fmt::Display for RegistryOperation {
 #[derive(Clone, Debug, PartialEq)]
 pub enum RuntimeOperation {
     CreateModule(String),
+    ExecModule(String),
     GetModule(String),
+    GetImageSize(String),
     GetModuleLogs(String),
     Init,
     ListModules,
+    PruneImages,
     RemoveModule(String),
     RestartModule(String),
     StartModule(String),
@@ -576,12 +1167,19 @@ impl fmt::Display for RuntimeOperation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RuntimeOperation::CreateModule(name) => write!(f, "Could not create module {}", name),
+            RuntimeOperation::ExecModule(name) => {
+                write!(f, "Could not execute command in module {}", name)
+            }
             RuntimeOperation::GetModule(name) => write!(f, "Could not get module {}", name),
+            RuntimeOperation::GetImageSize(image) => {
+                write!(f, "Could not get the size of image {}", image)
+            }
             RuntimeOperation::GetModuleLogs(name) => {
                 write!(f, "Could not get logs for module {}", name)
             }
             RuntimeOperation::Init => write!(f, "Could not initialize module runtime"),
             RuntimeOperation::ListModules => write!(f, "Could not list modules"),
+            RuntimeOperation::PruneImages => write!(f, "Could not prune images"),
             RuntimeOperation::RemoveModule(name) => write!(f, "Could not remove module {}", name),
             RuntimeOperation::RestartModule(name) => write!(f, "Could not restart module {}", name),
             RuntimeOperation::StartModule(name) => write!(f, "Could not start module {}", name),
@@ -761,4 +1359,166 @@ mod tests {
             current_value_architecture_type
         );
     }
+
+    #[test]
+    fn restart_policy_delay_backs_off() {
+        let policy = RestartPolicy::new(None, Duration::from_secs(1), 2.0, Duration::from_secs(60));
+
+        assert_eq!(Duration::from_secs(1), policy.delay(0));
+        assert_eq!(Duration::from_secs(2), policy.delay(1));
+        assert_eq!(Duration::from_secs(32), policy.delay(5));
+        assert_eq!(Duration::from_secs(60), policy.delay(6));
+        assert_eq!(Duration::from_secs(60), policy.delay(u32::max_value()));
+    }
+
+    #[test]
+    fn restart_policy_deser() {
+        let policy: RestartPolicy = serde_json::from_str(
+            r#"{"maxRestarts": 5, "initialDelaySecs": 10, "backoffFactor": 1.5, "maxDelaySecs": 120}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            RestartPolicy::new(
+                Some(5),
+                Duration::from_secs(10),
+                1.5,
+                Duration::from_secs(120)
+            ),
+            policy
+        );
+    }
+
+    #[test]
+    fn restart_policy_deser_defaults() {
+        let policy: RestartPolicy = serde_json::from_str(r#"{"maxRestarts": 3}"#).unwrap();
+
+        assert_eq!(Some(3), policy.max_restarts());
+        assert_eq!(
+            RestartPolicy::default().initial_delay(),
+            policy.initial_delay()
+        );
+        assert_eq!(RestartPolicy::default().max_delay(), policy.max_delay());
+    }
+
+    #[test]
+    fn workload_capability_from_str_ignores_case() {
+        assert_eq!(
+            WorkloadCapability::GetCertificate,
+            WorkloadCapability::from_str("getCertificate").unwrap()
+        );
+        assert_eq!(
+            WorkloadCapability::Sign,
+            WorkloadCapability::from_str("SIGN").unwrap()
+        );
+        assert!(WorkloadCapability::from_str("getTrustBundle").is_err());
+    }
+
+    #[test]
+    fn module_spec_capabilities_deser() {
+        let spec: ModuleSpec<String> = serde_json::from_str(
+            r#"{"name": "m1", "type": "docker", "config": "", "capabilities": ["sign", "getCertificate"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(&[WorkloadCapability::Sign, WorkloadCapability::GetCertificate][..]),
+            spec.capabilities()
+        );
+    }
+
+    #[test]
+    fn module_spec_volumes_deser() {
+        let spec: ModuleSpec<String> = serde_json::from_str(
+            r#"{"name": "m1", "type": "docker", "config": "", "volumes": [
+                {"source": {"namedVolume": "data"}, "target": "/data"},
+                {"source": {"hostPath": "/var/lib/m1"}, "target": "/cfg", "readOnly": true},
+                {"source": {"tmpfs": {"sizeBytes": 1024}}, "target": "/tmp"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &[
+                VolumeMount::new(
+                    VolumeSource::NamedVolume("data".to_string()),
+                    "/data".to_string()
+                ),
+                VolumeMount::new(
+                    VolumeSource::HostPath(PathBuf::from("/var/lib/m1")),
+                    "/cfg".to_string()
+                )
+                .with_read_only(true),
+                VolumeMount::new(VolumeSource::Tmpfs { size_bytes: 1024 }, "/tmp".to_string()),
+            ][..],
+            spec.volumes()
+        );
+    }
+
+    #[test]
+    fn module_spec_image_signature_verification_deser() {
+        let spec: ModuleSpec<String> = serde_json::from_str(
+            r#"{"name": "m1", "type": "docker", "config": "",
+                "imageSignatureVerification": {
+                    "source": {"rekor": "https://rekor.sigstore.dev"},
+                    "publicKey": "key"
+                }}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(&SignatureVerificationConfig::new(
+                SignatureSource::Rekor(Url::parse("https://rekor.sigstore.dev").unwrap()),
+                "key".to_string()
+            )),
+            spec.image_signature_verification()
+        );
+    }
+
+    #[test]
+    fn volume_source_host_path_must_be_under_allowed_prefix() {
+        let allowed = vec![PathBuf::from("/var/lib/modules")];
+        let host_path = |path: &str| VolumeSource::HostPath(PathBuf::from(path));
+
+        assert!(host_path("/var/lib/modules/m1").is_allowed(&allowed));
+        assert!(!host_path("/var/lib/modules-other").is_allowed(&allowed));
+        assert!(!host_path("/var/lib/modules/../../../etc").is_allowed(&allowed));
+        assert!(!host_path("var/lib/modules/m1").is_allowed(&allowed));
+        assert!(!host_path("/etc").is_allowed(&[]));
+        assert!(VolumeSource::NamedVolume("data".to_string()).is_allowed(&[]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn volume_source_host_path_symlinks_are_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let allowed_dir = dir.path().join("modules");
+        fs::create_dir(&allowed_dir).unwrap();
+        std::os::unix::fs::symlink("/etc", allowed_dir.join("etc")).unwrap();
+        std::os::unix::fs::symlink(&allowed_dir, dir.path().join("link")).unwrap();
+        let allowed = vec![allowed_dir.clone()];
+        let host_path = |path: PathBuf| VolumeSource::HostPath(path);
+
+        assert!(!host_path(allowed_dir.join("etc")).is_allowed(&allowed));
+        assert!(!host_path(allowed_dir.join("etc").join("missing")).is_allowed(&allowed));
+        assert!(host_path(allowed_dir.join("m1").join("data")).is_allowed(&allowed));
+        assert!(host_path(dir.path().join("link").join("m1")).is_allowed(&allowed));
+    }
+
+    #[test]
+    fn top_result_process_ids_are_read_from_pid_column() {
+        let top = TopResult::new(
+            vec!["UID".to_string(), "PID".to_string(), "CMD".to_string()],
+            vec![
+                vec!["root".to_string(), "1".to_string(), "/bin/sh".to_string()],
+                vec!["root".to_string(), "?".to_string(), "ps".to_string()],
+                vec!["root".to_string()],
+                vec!["root".to_string(), "42".to_string(), "sleep".to_string()],
+            ],
+        );
+        assert_eq!(vec![1, 42], top.process_ids());
+
+        let top = TopResult::new(vec!["CMD".to_string()], vec![vec!["1".to_string()]]);
+        assert!(top.process_ids().is_empty());
+    }
 }
@@ -0,0 +1,180 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Tokens that identify a module to the management API.
+//!
+//! A token is a JWT signed with HMAC-SHA256 using a key derived from the device key. Its subject
+//! is the module ID and it expires after the lifetime configured in the management auth settings.
+
+use base64;
+use chrono::{DateTime, TimeZone, Utc};
+use consistenttime::ct_u8_slice_eq;
+use failure::ResultExt;
+use serde_json;
+
+use crate::authorization::ModuleId;
+use crate::crypto::{Sign, Signature, SignatureAlgorithm};
+use crate::error::{Error, ErrorKind, InvalidModuleTokenReason};
+
+/// The name of the device key that module tokens are signed with.
+pub const MODULE_TOKEN_KEY_NAME: &str = "module-token";
+
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+#[derive(Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct Claims {
+    sub: String,
+    exp: i64,
+}
+
+pub fn create_module_token<K: Sign>(
+    key: &K,
+    module_id: &str,
+    expiry: DateTime<Utc>,
+) -> Result<String, Error> {
+    let claims = Claims {
+        sub: module_id.to_string(),
+        exp: expiry.timestamp(),
+    };
+    let claims = serde_json::to_vec(&claims).context(ErrorKind::Sign)?;
+
+    let message = format!(
+        "{}.{}",
+        base64::encode_config(HEADER, base64::URL_SAFE_NO_PAD),
+        base64::encode_config(&claims, base64::URL_SAFE_NO_PAD),
+    );
+    let signature = key
+        .sign(SignatureAlgorithm::HMACSHA256, message.as_bytes())
+        .context(ErrorKind::Sign)?;
+
+    Ok(format!(
+        "{}.{}",
+        message,
+        base64::encode_config(signature.as_bytes(), base64::URL_SAFE_NO_PAD)
+    ))
+}
+
+/// Checks the signature and expiry of `token` and returns the module it was issued to.
+pub fn validate_module_token<K: Sign>(
+    key: &K,
+    token: &str,
+    now: DateTime<Utc>,
+) -> Result<ModuleId, Error> {
+    let malformed = || ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Malformed);
+
+    let split = token.rfind('.').ok_or_else(malformed)?;
+    let (message, signature) = (&token[..split], &token[split + 1..]);
+    let mut parts = message.split('.');
+    let (header, claims) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(claims), None) => (header, claims),
+        _ => return Err(malformed().into()),
+    };
+
+    let header = base64::decode_config(header, base64::URL_SAFE_NO_PAD).context(malformed())?;
+    if header != HEADER.as_bytes() {
+        return Err(malformed().into());
+    }
+
+    let signature =
+        base64::decode_config(signature, base64::URL_SAFE_NO_PAD).context(malformed())?;
+    let expected = key
+        .sign(SignatureAlgorithm::HMACSHA256, message.as_bytes())
+        .context(ErrorKind::Sign)?;
+    if !ct_u8_slice_eq(expected.as_bytes(), &signature) {
+        return Err(ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Signature).into());
+    }
+
+    let claims = base64::decode_config(claims, base64::URL_SAFE_NO_PAD).context(malformed())?;
+    let claims: Claims = serde_json::from_slice(&claims).context(malformed())?;
+    let expiry = Utc.timestamp(claims.exp, 0);
+    if expiry <= now {
+        return Err(
+            ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Expired(expiry)).into(),
+        );
+    }
+
+    Ok(ModuleId::from(claims.sub))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::crypto::MemoryKey;
+
+    #[test]
+    fn token_round_trip() {
+        let key = MemoryKey::new("key");
+        let now = Utc::now();
+
+        let token = create_module_token(&key, "m1", now + Duration::hours(1)).unwrap();
+
+        assert_eq!(2, token.matches('.').count());
+        let module_id = validate_module_token(&key, &token, now).unwrap();
+        assert_eq!(module_id, "m1");
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let key = MemoryKey::new("key");
+        let now = Utc::now();
+
+        let token = create_module_token(&key, "m1", now - Duration::seconds(1)).unwrap();
+
+        let err = validate_module_token(&key, &token, now).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Expired(_)) => (),
+            kind => panic!("expected token to be expired but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn token_signed_with_other_key_is_rejected() {
+        let now = Utc::now();
+
+        let token =
+            create_module_token(&MemoryKey::new("other"), "m1", now + Duration::hours(1)).unwrap();
+
+        let err = validate_module_token(&MemoryKey::new("key"), &token, now).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Signature) => (),
+            kind => panic!("expected bad signature but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn tampered_claims_are_rejected() {
+        let key = MemoryKey::new("key");
+        let now = Utc::now();
+        let token = create_module_token(&key, "m1", now + Duration::hours(1)).unwrap();
+
+        let parts: Vec<_> = token.split('.').collect();
+        let claims = base64::encode_config(
+            &format!(
+                r#"{{"sub":"edgeAgent","exp":{}}}"#,
+                (now + Duration::hours(1)).timestamp()
+            ),
+            base64::URL_SAFE_NO_PAD,
+        );
+        let tampered = format!("{}.{}.{}", parts[0], claims, parts[2]);
+
+        let err = validate_module_token(&key, &tampered, now).unwrap_err();
+        match err.kind() {
+            ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Signature) => (),
+            kind => panic!("expected bad signature but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn malformed_token_is_rejected() {
+        let key = MemoryKey::new("key");
+
+        for token in &["", "abc", "a.b", "a.b.c.d"] {
+            let err = validate_module_token(&key, token, Utc::now()).unwrap_err();
+            match err.kind() {
+                ErrorKind::InvalidModuleToken(InvalidModuleTokenReason::Malformed) => (),
+                kind => panic!("expected {:?} to be malformed but got {:?}", token, kind),
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use failure::{Fail, ResultExt};
+use openssl::rand::rand_bytes;
+
+use crate::crypto::{Decrypt, Encrypt};
+use crate::error::{Error, ErrorKind};
+
+/// This is the size of the initialization vector generated for each encryption.
+const IV_SIZE: usize = 16;
+
+/// Prefixes every secret key identity. The workload API derives its key identities from the
+/// module name and the genid of the request path, which are always valid UTF-8, so it can never
+/// produce an identity starting with this byte.
+const KEY_ID_PREFIX: u8 = 0xff;
+
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+struct StoredSecret {
+    #[serde(rename = "keyVersion")]
+    key_version: u32,
+    #[serde(rename = "initializationVector")]
+    initialization_vector: String,
+    ciphertext: String,
+}
+
+/// Secret values of modules, encrypted by the HSM and persisted to a JSON file.
+///
+/// Each secret is encrypted under a key identity made of the module ID, the secret name and a key
+/// version, so a module's secrets can only be decrypted for that module. Rotating a secret
+/// re-encrypts it under the next key version.
+///
+/// Clones share the same underlying store.
+#[derive(Clone)]
+pub struct SecretStore<H> {
+    hsm: H,
+    path: PathBuf,
+    secrets: Arc<Mutex<BTreeMap<String, StoredSecret>>>,
+}
+
+impl<H> SecretStore<H>
+where
+    H: Decrypt + Encrypt,
+{
+    /// Opens the store persisted at `path`. The file is created by the first write.
+    pub fn new(hsm: H, path: PathBuf) -> Result<Self, Error> {
+        let secrets = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).context(ErrorKind::SecretStore)?,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(Error::from(err.context(ErrorKind::SecretStore))),
+        };
+
+        Ok(SecretStore {
+            hsm,
+            path,
+            secrets: Arc::new(Mutex::new(secrets)),
+        })
+    }
+
+    /// Stores `value` as the secret `name` of the module, replacing any previous value.
+    pub fn set(&self, module_id: &str, name: &str, value: &[u8]) -> Result<(), Error> {
+        let mut secrets = self
+            .secrets
+            .lock()
+            .expect("Unable to lock the secret store mutex");
+
+        let id = secret_id(module_id, name);
+        let key_version = secrets.get(&id).map_or(1, |secret| secret.key_version);
+        let secret = self.encrypt(&id, key_version, value)?;
+        secrets.insert(id, secret);
+
+        persist(&self.path, &secrets)
+    }
+
+    pub fn get(&self, module_id: &str, name: &str) -> Result<Vec<u8>, Error> {
+        let secrets = self
+            .secrets
+            .lock()
+            .expect("Unable to lock the secret store mutex");
+
+        let id = secret_id(module_id, name);
+        let secret = secrets
+            .get(&id)
+            .ok_or_else(|| ErrorKind::SecretNotFound(name.to_string()))?;
+        self.decrypt(&id, secret)
+    }
+
+    /// Re-encrypts the secret `name` of the module under a new key version, and returns that
+    /// version.
+    pub fn rotate(&self, module_id: &str, name: &str) -> Result<u32, Error> {
+        let mut secrets = self
+            .secrets
+            .lock()
+            .expect("Unable to lock the secret store mutex");
+
+        let id = secret_id(module_id, name);
+        let secret = secrets
+            .get(&id)
+            .ok_or_else(|| ErrorKind::SecretNotFound(name.to_string()))?;
+        let value = self.decrypt(&id, secret)?;
+        let key_version = secret.key_version + 1;
+        let secret = self.encrypt(&id, key_version, &value)?;
+        secrets.insert(id, secret);
+
+        persist(&self.path, &secrets)?;
+        Ok(key_version)
+    }
+
+    fn encrypt(&self, id: &str, key_version: u32, value: &[u8]) -> Result<StoredSecret, Error> {
+        let mut initialization_vector = [0; IV_SIZE];
+        rand_bytes(&mut initialization_vector).context(ErrorKind::MakeRandom)?;
+
+        let ciphertext = self
+            .hsm
+            .encrypt(&key_id(id, key_version), value, &initialization_vector)
+            .context(ErrorKind::SecretStore)?;
+
+        Ok(StoredSecret {
+            key_version,
+            initialization_vector: base64::encode(&initialization_vector),
+            ciphertext: base64::encode(ciphertext.as_ref()),
+        })
+    }
+
+    fn decrypt(&self, id: &str, secret: &StoredSecret) -> Result<Vec<u8>, Error> {
+        let initialization_vector =
+            base64::decode(&secret.initialization_vector).context(ErrorKind::SecretStore)?;
+        let ciphertext = base64::decode(&secret.ciphertext).context(ErrorKind::SecretStore)?;
+
+        let plaintext = self
+            .hsm
+            .decrypt(
+                &key_id(id, secret.key_version),
+                &ciphertext,
+                &initialization_vector,
+            )
+            .context(ErrorKind::SecretStore)?;
+        Ok(plaintext.as_ref().to_vec())
+    }
+}
+
+fn secret_id(module_id: &str, name: &str) -> String {
+    format!("{}/{}", module_id, name)
+}
+
+fn key_id(secret_id: &str, key_version: u32) -> Vec<u8> {
+    let mut key_id = vec![KEY_ID_PREFIX];
+    key_id.extend_from_slice(format!("secret/{}/v{}", secret_id, key_version).as_bytes());
+    key_id
+}
+
+/// Writes the store to a temporary file first so that a crash can't leave it half-written.
+fn persist(path: &Path, secrets: &BTreeMap<String, StoredSecret>) -> Result<(), Error> {
+    let contents = serde_json::to_vec(secrets).context(ErrorKind::SecretStore)?;
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, contents).context(ErrorKind::SecretStore)?;
+    fs::rename(&temp_path, path).context(ErrorKind::SecretStore)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::env;
+    use std::rc::Rc;
+    use std::str;
+
+    /// "Encrypts" by XOR-ing the plaintext with the key identity, so that decrypting with a
+    /// different identity gives a different result.
+    #[derive(Clone, Default)]
+    struct TestHsm {
+        key_ids: Rc<RefCell<Vec<Vec<u8>>>>,
+    }
+
+    impl TestHsm {
+        fn xor(&self, client_id: &[u8], data: &[u8]) -> Vec<u8> {
+            self.key_ids.borrow_mut().push(client_id.to_vec());
+            data.iter()
+                .zip(client_id.iter().cycle())
+                .map(|(byte, key)| byte ^ key)
+                .collect()
+        }
+    }
+
+    impl Encrypt for TestHsm {
+        type Buffer = Vec<u8>;
+
+        fn encrypt(
+            &self,
+            client_id: &[u8],
+            plaintext: &[u8],
+            _initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, Error> {
+            Ok(self.xor(client_id, plaintext))
+        }
+    }
+
+    impl Decrypt for TestHsm {
+        type Buffer = Vec<u8>;
+
+        fn decrypt(
+            &self,
+            client_id: &[u8],
+            ciphertext: &[u8],
+            _initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, Error> {
+            Ok(self.xor(client_id, ciphertext))
+        }
+    }
+
+    fn store_path(test_name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("edgelet-core-{}-secrets.json", test_name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn set_then_get_returns_value() {
+        let path = store_path("set-get");
+        let store = SecretStore::new(TestHsm::default(), path.clone()).unwrap();
+
+        store.set("m1", "api-key", b"hunter2").unwrap();
+
+        assert_eq!(b"hunter2".to_vec(), store.get("m1", "api-key").unwrap());
+        match store.get("m2", "api-key").unwrap_err().kind() {
+            ErrorKind::SecretNotFound(name) if name == "api-key" => (),
+            kind => panic!("Expected `SecretNotFound` but got {:?}", kind),
+        }
+
+        let persisted = fs::read_to_string(&path).unwrap();
+        assert!(!persisted.contains("hunter2"));
+
+        let reopened = SecretStore::new(TestHsm::default(), path.clone()).unwrap();
+        assert_eq!(b"hunter2".to_vec(), reopened.get("m1", "api-key").unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rotate_re_encrypts_under_new_key_version() {
+        let path = store_path("rotate");
+        let hsm = TestHsm::default();
+        let store = SecretStore::new(hsm.clone(), path.clone()).unwrap();
+
+        store.set("m1", "api-key", b"hunter2").unwrap();
+        assert_eq!(2, store.rotate("m1", "api-key").unwrap());
+        assert_eq!(b"hunter2".to_vec(), store.get("m1", "api-key").unwrap());
+
+        assert_eq!(
+            vec![
+                b"\xffsecret/m1/api-key/v1".to_vec(),
+                b"\xffsecret/m1/api-key/v1".to_vec(),
+                b"\xffsecret/m1/api-key/v2".to_vec(),
+                b"\xffsecret/m1/api-key/v2".to_vec(),
+            ],
+            *hsm.key_ids.borrow()
+        );
+        match store.rotate("m1", "db-password").unwrap_err().kind() {
+            ErrorKind::SecretNotFound(name) if name == "db-password" => (),
+            kind => panic!("Expected `SecretNotFound` but got {:?}", kind),
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn key_ids_are_unreachable_from_the_workload_api() {
+        let path = store_path("key-ids");
+        let hsm = TestHsm::default();
+        let store = SecretStore::new(hsm.clone(), path.clone()).unwrap();
+
+        store.set("m1", "api-key", b"hunter2").unwrap();
+
+        // The workload API encrypts for its callers under `{module}{genid}`, so the module
+        // `secret` with the genid `/m1/api-key/v1` must not reach the key of this secret.
+        let key_ids = hsm.key_ids.borrow();
+        assert!(str::from_utf8(&key_ids[0]).is_err());
+        assert_ne!(b"secret/m1/api-key/v1".to_vec(), key_ids[0]);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,7 +1,9 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
@@ -13,7 +15,7 @@ use url_serde;
 use crate::crypto::MemoryKey;
 use crate::error::{Error, ErrorKind};
 use crate::module::ModuleSpec;
-use crate::DEFAULT_AUTO_GENERATED_CA_LIFETIME_DAYS;
+use crate::{DEFAULT_AUTO_GENERATED_CA_LIFETIME_DAYS, DEFAULT_EDGE_CA_RENEWAL_THRESHOLD_PERCENT};
 
 const DEVICEID_KEY: &str = "DeviceId";
 const HOSTNAME_KEY: &str = "HostName";
@@ -233,6 +235,7 @@ pub enum AttestationMethod {
     #[serde(rename = "symmetric_key")]
     SymmetricKey(SymmetricKeyAttestationInfo),
     X509(X509AttestationInfo),
+    Fido2(Fido2AttestationInfo),
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -318,6 +321,35 @@ impl X509AttestationInfo {
     }
 }
 
+/// Experimental attestation with a FIDO2 authenticator. `global_endpoint` must be an enrollment
+/// service that accepts FIDO2 attestation statements, since DPS itself doesn't.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub struct Fido2AttestationInfo {
+    registration_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relying_party_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pin: Option<String>,
+}
+
+impl Fido2AttestationInfo {
+    pub fn registration_id(&self) -> &str {
+        &self.registration_id
+    }
+
+    /// The relying party that the credential is created for. Defaults to the host of the
+    /// enrollment service.
+    pub fn relying_party_id(&self) -> Option<&str> {
+        self.relying_party_id.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The PIN of the authenticator, if it has one set.
+    pub fn pin(&self) -> Option<&str> {
+        self.pin.as_ref().map(AsRef::as_ref)
+    }
+}
+
 #[derive(Clone, Debug, serde_derive::Serialize)]
 pub struct Dps {
     #[serde(with = "url_serde")]
@@ -529,6 +561,19 @@ pub struct Certificates {
     device_cert: Option<DeviceCertificate>,
     #[serde(default = "default_auto_generated_ca_lifetime_days")]
     auto_generated_ca_lifetime_days: u16,
+    #[serde(default, with = "url_serde")]
+    ct_log_url: Option<Url>,
+    edge_ca_validity_days: Option<u16>,
+    #[serde(default = "default_renewal_threshold_percent")]
+    renewal_threshold_percent: u8,
+    #[serde(default, with = "url_serde")]
+    est_url: Option<Url>,
+    est_identity_cert: Option<String>,
+    est_identity_pk: Option<String>,
+    #[serde(default, with = "url_serde")]
+    scep_url: Option<Url>,
+    scep_challenge_password: Option<String>,
+    scep_ca_fingerprint: Option<String>,
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -542,6 +587,10 @@ fn default_auto_generated_ca_lifetime_days() -> u16 {
     DEFAULT_AUTO_GENERATED_CA_LIFETIME_DAYS
 }
 
+fn default_renewal_threshold_percent() -> u8 {
+    DEFAULT_EDGE_CA_RENEWAL_THRESHOLD_PERCENT
+}
+
 fn is_supported_uri(uri: &Url) -> bool {
     if uri.scheme() == "file" && uri.port().is_none() && uri.query().is_none() {
         if let Some(host) = uri.host_str() {
@@ -643,6 +692,57 @@ impl Certificates {
         // Convert days to seconds (86,400 seconds per day)
         u64::from(self.auto_generated_ca_lifetime_days) * 86_400
     }
+
+    pub fn ct_log_url(&self) -> Option<&Url> {
+        self.ct_log_url.as_ref()
+    }
+
+    /// How long the Edge CA is issued for. `None` issues it for as long as the device CA is valid.
+    pub fn edge_ca_validity_seconds(&self) -> Option<u64> {
+        self.edge_ca_validity_days
+            .map(|days| u64::from(days) * 86_400)
+    }
+
+    /// The percentage of the Edge CA's validity that may remain before it is regenerated.
+    pub fn renewal_threshold_percent(&self) -> u8 {
+        self.renewal_threshold_percent
+    }
+
+    /// The https URL of an EST server, usually ending in `/.well-known/est`, that issues the
+    /// module certificates requested through the workload API instead of the HSM.
+    pub fn est_url(&self) -> Option<&Url> {
+        self.est_url.as_ref()
+    }
+
+    /// The certificate that authenticates the daemon to the EST server.
+    pub fn est_identity_cert(&self) -> Result<Option<PathBuf>, Error> {
+        self.est_identity_cert
+            .as_ref()
+            .map(|path| convert_to_path(path, "certificates.est_identity_cert"))
+            .transpose()
+    }
+
+    pub fn est_identity_pk(&self) -> Result<Option<PathBuf>, Error> {
+        self.est_identity_pk
+            .as_ref()
+            .map(|path| convert_to_path(path, "certificates.est_identity_pk"))
+            .transpose()
+    }
+
+    /// The URL of a SCEP server that issues the module certificates requested through the
+    /// workload API, as an alternative to `est_url`.
+    pub fn scep_url(&self) -> Option<&Url> {
+        self.scep_url.as_ref()
+    }
+
+    pub fn scep_challenge_password(&self) -> Option<&str> {
+        self.scep_challenge_password.as_ref().map(String::as_str)
+    }
+
+    /// The SHA-256 or SHA-1 fingerprint, in hex, that the SCEP server's CA certificate must have.
+    pub fn scep_ca_fingerprint(&self) -> Option<&str> {
+        self.scep_ca_fingerprint.as_ref().map(String::as_str)
+    }
 }
 
 #[derive(Clone, Copy, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -679,6 +779,277 @@ impl WatchdogSettings {
     }
 }
 
+/// The module runtime that `config.yaml` selects with its top-level `runtime` setting.
+#[derive(Clone, Copy, Debug, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeType {
+    Docker,
+    Podman,
+    Runc,
+}
+
+impl Default for RuntimeType {
+    fn default() -> Self {
+        RuntimeType::Docker
+    }
+}
+
+/// The rate at which a single caller may call an endpoint of the management API. Calls are
+/// allowed in bursts of up to `burst`, after which they're allowed at `requests_per_second`.
+#[derive(Clone, Copy, Debug, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct RateLimit {
+    requests_per_second: f64,
+    burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        RateLimit {
+            requests_per_second,
+            burst,
+        }
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+/// Limits that apply to a single caller, in place of the limits for every caller.
+#[derive(Clone, Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct CallerRateLimits {
+    #[serde(default)]
+    default: Option<RateLimit>,
+    #[serde(default)]
+    endpoints: HashMap<String, RateLimit>,
+}
+
+impl CallerRateLimits {
+    pub fn new(default: Option<RateLimit>, endpoints: HashMap<String, RateLimit>) -> Self {
+        CallerRateLimits { default, endpoints }
+    }
+}
+
+#[derive(Clone, Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    default: Option<RateLimit>,
+    #[serde(default)]
+    endpoints: HashMap<String, RateLimit>,
+    #[serde(default)]
+    callers: HashMap<String, CallerRateLimits>,
+}
+
+impl RateLimitSettings {
+    pub fn new(default: Option<RateLimit>, endpoints: HashMap<String, RateLimit>) -> Self {
+        RateLimitSettings {
+            default,
+            endpoints,
+            callers: HashMap::new(),
+        }
+    }
+
+    pub fn with_caller(mut self, caller: String, limits: CallerRateLimits) -> Self {
+        self.callers.insert(caller, limits);
+        self
+    }
+
+    /// Returns whether no limits are configured at all.
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.endpoints.is_empty() && self.callers.is_empty()
+    }
+
+    /// Returns the limit for the endpoint with the given name when called by `caller`, which is
+    /// the name of the calling module if it could be identified.
+    ///
+    /// The limits configured for the caller take precedence over the ones for every caller: the
+    /// caller's limit for the endpoint comes first, then the caller's default limit, then the
+    /// endpoint's limit and then the default limit. Endpoints without a limit are not rate
+    /// limited.
+    pub fn limit(&self, endpoint: &str, caller: Option<&str>) -> Option<RateLimit> {
+        let caller = caller.and_then(|caller| self.callers.get(caller));
+        caller
+            .and_then(|caller| caller.endpoints.get(endpoint).copied().or(caller.default))
+            .or_else(|| self.endpoints.get(endpoint).copied())
+            .or(self.default)
+    }
+}
+
+/// Settings for authorizing management API calls with module tokens. Tokens are issued by the
+/// workload API and are valid for `token_lifetime_secs`.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct ManagementAuthSettings {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_token_lifetime_secs")]
+    token_lifetime_secs: u64,
+}
+
+fn default_token_lifetime_secs() -> u64 {
+    3600
+}
+
+impl Default for ManagementAuthSettings {
+    fn default() -> Self {
+        ManagementAuthSettings {
+            enabled: false,
+            token_lifetime_secs: default_token_lifetime_secs(),
+        }
+    }
+}
+
+impl ManagementAuthSettings {
+    pub fn new(enabled: bool, token_lifetime_secs: u64) -> Self {
+        ManagementAuthSettings {
+            enabled,
+            token_lifetime_secs,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn token_lifetime_secs(&self) -> u64 {
+        self.token_lifetime_secs
+    }
+}
+
+/// Settings for the Prometheus metrics served on `GET /metrics` of the management API. Metrics
+/// are only served if the daemon was built with the `prometheus` feature.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct MetricsSettings {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_metrics_allowed_ips")]
+    allowed_ips: Vec<IpAddr>,
+}
+
+fn default_metrics_allowed_ips() -> Vec<IpAddr> {
+    vec![
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ]
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        MetricsSettings {
+            enabled: false,
+            allowed_ips: default_metrics_allowed_ips(),
+        }
+    }
+}
+
+impl MetricsSettings {
+    pub fn new(enabled: bool, allowed_ips: Vec<IpAddr>) -> Self {
+        MetricsSettings {
+            enabled,
+            allowed_ips,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The addresses that may scrape the metrics over TCP. Callers connected over a Unix socket
+    /// are always allowed, since they already have access to the socket file.
+    pub fn allowed_ips(&self) -> &[IpAddr] {
+        &self.allowed_ips
+    }
+}
+
+/// Settings for exporting traces of management API requests. Traces are only exported if the
+/// daemon was built with the `opentelemetry` feature and an OTLP endpoint is configured.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct TracingSettings {
+    #[serde(default, with = "url_serde")]
+    otlp_endpoint: Option<Url>,
+    #[serde(default = "default_tracing_service_name")]
+    service_name: String,
+}
+
+fn default_tracing_service_name() -> String {
+    "iotedged".to_string()
+}
+
+impl Default for TracingSettings {
+    fn default() -> Self {
+        TracingSettings {
+            otlp_endpoint: None,
+            service_name: default_tracing_service_name(),
+        }
+    }
+}
+
+impl TracingSettings {
+    pub fn new(otlp_endpoint: Option<Url>, service_name: String) -> Self {
+        TracingSettings {
+            otlp_endpoint,
+            service_name,
+        }
+    }
+
+    /// The OTLP/HTTP traces endpoint spans are posted to, e.g.
+    /// `http://localhost:4318/v1/traces`.
+    pub fn otlp_endpoint(&self) -> Option<&Url> {
+        self.otlp_endpoint.as_ref()
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+}
+
+/// Settings for the PKCS#11 token that holds the device's key pair. The key pair is generated
+/// on the token at startup if it doesn't exist yet. The token is only used if the daemon was built
+/// with the `pkcs11` feature.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct Pkcs11Settings {
+    lib_path: PathBuf,
+    token_label: String,
+    pin: String,
+    #[serde(default = "default_pkcs11_key_label")]
+    key_label: String,
+}
+
+fn default_pkcs11_key_label() -> String {
+    "iotedge-device".to_string()
+}
+
+impl Pkcs11Settings {
+    pub fn new(lib_path: PathBuf, token_label: String, pin: String, key_label: String) -> Self {
+        Pkcs11Settings {
+            lib_path,
+            token_label,
+            pin,
+            key_label,
+        }
+    }
+
+    /// The path of the PKCS#11 library, e.g. `/usr/lib/softhsm/libsofthsm2.so`.
+    pub fn lib_path(&self) -> &Path {
+        &self.lib_path
+    }
+
+    pub fn token_label(&self) -> &str {
+        &self.token_label
+    }
+
+    pub fn pin(&self) -> &str {
+        &self.pin
+    }
+
+    pub fn key_label(&self) -> &str {
+        &self.key_label
+    }
+}
+
 pub trait RuntimeSettings {
     type Config;
 
@@ -691,6 +1062,12 @@ pub trait RuntimeSettings {
     fn homedir(&self) -> &Path;
     fn certificates(&self) -> &Certificates;
     fn watchdog(&self) -> &WatchdogSettings;
+    fn rate_limits(&self) -> &RateLimitSettings;
+    fn management_auth(&self) -> &ManagementAuthSettings;
+    fn metrics(&self) -> &MetricsSettings;
+    fn tracing(&self) -> &TracingSettings;
+    fn pkcs11(&self) -> Option<&Pkcs11Settings>;
+    fn twin_cache_dir(&self) -> Option<&Path>;
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -704,6 +1081,16 @@ pub struct Settings<T> {
     certificates: Option<Certificates>,
     #[serde(default)]
     watchdog: WatchdogSettings,
+    #[serde(default)]
+    rate_limits: RateLimitSettings,
+    #[serde(default)]
+    management_auth: ManagementAuthSettings,
+    #[serde(default)]
+    metrics: MetricsSettings,
+    #[serde(default)]
+    tracing: TracingSettings,
+    pkcs11: Option<Pkcs11Settings>,
+    twin_cache_dir: Option<PathBuf>,
 }
 
 impl<T> RuntimeSettings for Settings<T>
@@ -746,6 +1133,15 @@ where
             None => &Certificates {
                 device_cert: None,
                 auto_generated_ca_lifetime_days: DEFAULT_AUTO_GENERATED_CA_LIFETIME_DAYS,
+                ct_log_url: None,
+                edge_ca_validity_days: None,
+                renewal_threshold_percent: DEFAULT_EDGE_CA_RENEWAL_THRESHOLD_PERCENT,
+                est_url: None,
+                est_identity_cert: None,
+                est_identity_pk: None,
+                scep_url: None,
+                scep_challenge_password: None,
+                scep_ca_fingerprint: None,
             },
             Some(c) => c,
         }
@@ -754,6 +1150,30 @@ where
     fn watchdog(&self) -> &WatchdogSettings {
         &self.watchdog
     }
+
+    fn rate_limits(&self) -> &RateLimitSettings {
+        &self.rate_limits
+    }
+
+    fn management_auth(&self) -> &ManagementAuthSettings {
+        &self.management_auth
+    }
+
+    fn metrics(&self) -> &MetricsSettings {
+        &self.metrics
+    }
+
+    fn tracing(&self) -> &TracingSettings {
+        &self.tracing
+    }
+
+    fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+        self.pkcs11.as_ref()
+    }
+
+    fn twin_cache_dir(&self) -> Option<&Path> {
+        self.twin_cache_dir.as_ref().map(AsRef::as_ref)
+    }
 }
 
 #[cfg(test)]
@@ -949,4 +1369,46 @@ mod tests {
             Err(format!("Unsupported TLS protocol version: {}", value))
         )
     }
+
+    #[test]
+    fn rate_limit_prefers_limits_of_the_caller() {
+        let settings: RateLimitSettings = serde_json::from_value(serde_json::json!({
+            "default": { "requests_per_second": 10.0, "burst": 20 },
+            "endpoints": {
+                "CreateModule": { "requests_per_second": 1.0, "burst": 5 },
+            },
+            "callers": {
+                "edgeAgent": {
+                    "default": { "requests_per_second": 100.0, "burst": 100 },
+                },
+                "chatty": {
+                    "endpoints": {
+                        "ListModules": { "requests_per_second": 0.1, "burst": 1 },
+                    },
+                },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            Some(RateLimit::new(100.0, 100)),
+            settings.limit("CreateModule", Some("edgeAgent"))
+        );
+        assert_eq!(
+            Some(RateLimit::new(0.1, 1)),
+            settings.limit("ListModules", Some("chatty"))
+        );
+        assert_eq!(
+            Some(RateLimit::new(1.0, 5)),
+            settings.limit("CreateModule", Some("chatty"))
+        );
+        assert_eq!(
+            Some(RateLimit::new(10.0, 20)),
+            settings.limit("ListModules", None)
+        );
+        assert!(RateLimitSettings::default()
+            .limit("ListModules", None)
+            .is_none());
+        assert!(RateLimitSettings::default().is_empty());
+    }
 }
@@ -0,0 +1,429 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::mem;
+
+use failure::Fail;
+use futures::prelude::*;
+
+use crate::error::{Error, ErrorKind};
+use crate::logs::{LogChunk, LogDecode, LogsReader};
+use crate::module::{LogOptions, ModuleRuntime, ModuleSpec};
+
+/// An item yielded by [`SpawnModule`].
+#[derive(Debug, PartialEq)]
+pub enum SpawnOutput {
+    Log(LogChunk),
+
+    /// The module has exited and been removed. This is always the last item of the stream.
+    Exited(Option<i64>),
+}
+
+/// Runs a module to completion, like `docker run --rm`.
+///
+/// The module is created and started, and its stdout and stderr are yielded as they are
+/// produced. Once the log stream ends the module is stopped, its exit code is read and it is
+/// removed. If any operation fails, the module is removed before the error is returned.
+///
+/// The output is parsed with [`LogDecode`], so the module must not be created with a TTY.
+/// Dropping the stream before it ends leaves the module behind.
+pub struct SpawnModule<R>
+where
+    R: ModuleRuntime,
+{
+    runtime: R,
+    name: String,
+    state: State<R>,
+}
+
+enum State<R>
+where
+    R: ModuleRuntime,
+{
+    Creating(R::CreateFuture),
+    Starting(R::StartFuture),
+    Streaming(LogDecode<LogsReader<R::LogsFuture, R::Logs>>),
+    Stopping(R::StopFuture),
+    Inspecting(R::GetFuture),
+    Removing(R::RemoveFuture, Result<Option<i64>, Error>),
+    Done,
+}
+
+impl<R> SpawnModule<R>
+where
+    R: ModuleRuntime,
+{
+    pub fn new(runtime: R, module: ModuleSpec<R::Config>) -> Self {
+        let name = module.name().to_string();
+        let state = State::Creating(runtime.create(module));
+        SpawnModule {
+            runtime,
+            name,
+            state,
+        }
+    }
+}
+
+impl<R> Stream for SpawnModule<R>
+where
+    R: ModuleRuntime,
+{
+    type Item = SpawnOutput;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            let (next_state, result) = match self.state {
+                State::Creating(ref mut create) => match create.poll() {
+                    Ok(Async::Ready(())) => (State::Starting(self.runtime.start(&self.name)), None),
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => (State::Done, Some(Err(spawn_error(err, &self.name)))),
+                },
+
+                State::Starting(ref mut start) => match start.poll() {
+                    Ok(Async::Ready(())) => {
+                        let options = LogOptions::new().with_follow(true);
+                        let logs = LogsReader::new(self.runtime.logs(&self.name, &options));
+                        (State::Streaming(LogDecode::new(logs)), None)
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => (
+                        State::Removing(
+                            self.runtime.remove(&self.name),
+                            Err(spawn_error(err, &self.name)),
+                        ),
+                        None,
+                    ),
+                },
+
+                State::Streaming(ref mut logs) => match logs.poll() {
+                    Ok(Async::Ready(Some(chunk))) => {
+                        return Ok(Async::Ready(Some(SpawnOutput::Log(chunk))))
+                    }
+                    Ok(Async::Ready(None)) => {
+                        (State::Stopping(self.runtime.stop(&self.name, None)), None)
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => (
+                        State::Removing(
+                            self.runtime.remove(&self.name),
+                            Err(spawn_error(err, &self.name)),
+                        ),
+                        None,
+                    ),
+                },
+
+                // The module has usually exited by the time its log stream ends, in which case
+                // stopping it fails. That doesn't prevent reading its exit code, so the error is
+                // ignored.
+                State::Stopping(ref mut stop) => match stop.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(())) | Err(_) => {
+                        (State::Inspecting(self.runtime.get(&self.name)), None)
+                    }
+                },
+
+                State::Inspecting(ref mut get) => match get.poll() {
+                    Ok(Async::Ready((_, state))) => (
+                        State::Removing(self.runtime.remove(&self.name), Ok(state.exit_code())),
+                        None,
+                    ),
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => (
+                        State::Removing(
+                            self.runtime.remove(&self.name),
+                            Err(spawn_error(err, &self.name)),
+                        ),
+                        None,
+                    ),
+                },
+
+                State::Removing(ref mut remove, ref mut outcome) => {
+                    let removed = match remove.poll() {
+                        Ok(Async::Ready(())) => Ok(()),
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Err(err) => Err(spawn_error(err, &self.name)),
+                    };
+
+                    // An earlier error takes precedence over an error removing the module.
+                    let result = match (mem::replace(outcome, Ok(None)), removed) {
+                        (Ok(exit_code), Ok(())) => {
+                            Ok(Async::Ready(Some(SpawnOutput::Exited(exit_code))))
+                        }
+                        (Ok(_), Err(err)) | (Err(err), _) => Err(err),
+                    };
+                    (State::Done, Some(result))
+                }
+
+                State::Done => return Ok(Async::Ready(None)),
+            };
+
+            self.state = next_state;
+            if let Some(result) = result {
+                return result;
+            }
+        }
+    }
+}
+
+fn spawn_error<E: Fail>(err: E, name: &str) -> Error {
+    Error::from(err.context(ErrorKind::SpawnModule(name.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures::future::{self, FutureResult};
+    use futures::stream::{self, Empty, IterOk};
+
+    use bytes::Bytes;
+
+    use crate::module::{
+        ImagePullPolicy, ImageSizeInfo, Module, ModuleRegistry, ModuleRuntimeState, PruneReport,
+        SystemInfo, SystemResources, TopResult,
+    };
+
+    #[derive(Clone, Debug)]
+    struct TestModule;
+
+    impl Module for TestModule {
+        type Config = ();
+        type Error = Error;
+        type RuntimeStateFuture = FutureResult<ModuleRuntimeState, Self::Error>;
+
+        fn name(&self) -> &str {
+            "test-module"
+        }
+
+        fn type_(&self) -> &str {
+            "test"
+        }
+
+        fn config(&self) -> &Self::Config {
+            &()
+        }
+
+        fn runtime_state(&self) -> Self::RuntimeStateFuture {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestRuntime {
+        calls: Arc<Mutex<Vec<String>>>,
+        fail_start: bool,
+        logs: Vec<Vec<u8>>,
+        exit_code: Option<i64>,
+    }
+
+    impl TestRuntime {
+        fn new(logs: Vec<Vec<u8>>, exit_code: Option<i64>) -> Self {
+            TestRuntime {
+                calls: Arc::new(Mutex::new(vec![])),
+                fail_start: false,
+                logs,
+                exit_code,
+            }
+        }
+
+        fn with_fail_start(mut self) -> Self {
+            self.fail_start = true;
+            self
+        }
+
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl ModuleRegistry for TestRuntime {
+        type Error = Error;
+        type PullFuture = FutureResult<(), Self::Error>;
+        type RemoveFuture = FutureResult<(), Self::Error>;
+        type Config = ();
+
+        fn pull(&self, _config: &Self::Config) -> Self::PullFuture {
+            unimplemented!()
+        }
+
+        fn remove(&self, _name: &str) -> Self::RemoveFuture {
+            unimplemented!()
+        }
+    }
+
+    impl ModuleRuntime for TestRuntime {
+        type Error = Error;
+        type Config = ();
+        type Module = TestModule;
+        type ModuleRegistry = Self;
+        type Chunk = Vec<u8>;
+        type Logs = IterOk<std::vec::IntoIter<Vec<u8>>, Self::Error>;
+
+        type CreateFuture = FutureResult<(), Self::Error>;
+        type GetFuture = FutureResult<(Self::Module, ModuleRuntimeState), Self::Error>;
+        type ListFuture = FutureResult<Vec<Self::Module>, Self::Error>;
+        type ListWithDetailsStream = Empty<(Self::Module, ModuleRuntimeState), Self::Error>;
+        type LogsFuture = FutureResult<Self::Logs, Self::Error>;
+        type RemoveFuture = FutureResult<(), Self::Error>;
+        type RestartFuture = FutureResult<(), Self::Error>;
+        type StartFuture = FutureResult<(), Self::Error>;
+        type StopFuture = FutureResult<(), Self::Error>;
+        type SystemInfoFuture = FutureResult<SystemInfo, Self::Error>;
+        type SystemResourcesFuture = FutureResult<SystemResources, Self::Error>;
+        type TopFuture = FutureResult<TopResult, Self::Error>;
+        type RemoveAllFuture = FutureResult<(), Self::Error>;
+        type ImageSizeFuture = FutureResult<ImageSizeInfo, Self::Error>;
+        type PruneImagesFuture = FutureResult<PruneReport, Self::Error>;
+
+        fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
+            self.record(format!("create {}", module.name()));
+            future::ok(())
+        }
+
+        fn get(&self, id: &str) -> Self::GetFuture {
+            self.record(format!("get {}", id));
+            let state = ModuleRuntimeState::default().with_exit_code(self.exit_code);
+            future::ok((TestModule, state))
+        }
+
+        fn start(&self, id: &str) -> Self::StartFuture {
+            self.record(format!("start {}", id));
+            if self.fail_start {
+                future::err(Error::from(ErrorKind::ModuleRuntime))
+            } else {
+                future::ok(())
+            }
+        }
+
+        fn stop(&self, id: &str, _wait_before_kill: Option<Duration>) -> Self::StopFuture {
+            self.record(format!("stop {}", id));
+            future::ok(())
+        }
+
+        fn restart(&self, _id: &str) -> Self::RestartFuture {
+            unimplemented!()
+        }
+
+        fn remove(&self, id: &str) -> Self::RemoveFuture {
+            self.record(format!("remove {}", id));
+            future::ok(())
+        }
+
+        fn system_info(&self) -> Self::SystemInfoFuture {
+            unimplemented!()
+        }
+
+        fn system_resources(&self) -> Self::SystemResourcesFuture {
+            unimplemented!()
+        }
+
+        fn top(&self, _id: &str) -> Self::TopFuture {
+            unimplemented!()
+        }
+
+        fn list(&self) -> Self::ListFuture {
+            unimplemented!()
+        }
+
+        fn list_with_details(&self) -> Self::ListWithDetailsStream {
+            unimplemented!()
+        }
+
+        fn logs(&self, id: &str, options: &LogOptions) -> Self::LogsFuture {
+            self.record(format!("logs {} follow={}", id, options.follow()));
+            future::ok(stream::iter_ok(self.logs.clone()))
+        }
+
+        fn registry(&self) -> &Self::ModuleRegistry {
+            self
+        }
+
+        fn remove_all(&self) -> Self::RemoveAllFuture {
+            unimplemented!()
+        }
+
+        fn image_size(&self, _image: &str) -> Self::ImageSizeFuture {
+            unimplemented!()
+        }
+
+        fn prune_images(&self, _keep: &[String]) -> Self::PruneImagesFuture {
+            unimplemented!()
+        }
+    }
+
+    fn module_spec() -> ModuleSpec<()> {
+        ModuleSpec::new(
+            "job".to_string(),
+            "test".to_string(),
+            (),
+            HashMap::new(),
+            ImagePullPolicy::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn spawn_module_yields_output_then_exit_code() {
+        let logs = vec![
+            vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, b'h', b'e'],
+            b"llo".to_vec(),
+            vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04],
+            b"oops".to_vec(),
+        ];
+        let runtime = TestRuntime::new(logs, Some(3));
+
+        let output = runtime
+            .spawn_module(module_spec())
+            .collect()
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                SpawnOutput::Log(LogChunk::Stdout(Bytes::from("hello"))),
+                SpawnOutput::Log(LogChunk::Stderr(Bytes::from("oops"))),
+                SpawnOutput::Exited(Some(3)),
+            ],
+            output
+        );
+        assert_eq!(
+            vec![
+                "create job",
+                "start job",
+                "logs job follow=true",
+                "stop job",
+                "get job",
+                "remove job",
+            ],
+            runtime.calls()
+        );
+    }
+
+    #[test]
+    fn spawn_module_removes_module_when_start_fails() {
+        let runtime = TestRuntime::new(vec![], None).with_fail_start();
+
+        let err = runtime
+            .spawn_module(module_spec())
+            .collect()
+            .wait()
+            .unwrap_err();
+
+        match err.kind() {
+            ErrorKind::SpawnModule(name) => assert_eq!("job", name),
+            kind => panic!("unexpected error kind {:?}", kind),
+        }
+        assert_eq!(
+            vec!["create job", "start job", "remove job"],
+            runtime.calls()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use failure::{Fail, ResultExt};
+use serde_json::Value;
+
+use crate::error::{Error, ErrorKind};
+
+/// A module twin as it was last fetched from IoT Hub.
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
+pub struct CachedTwin {
+    #[serde(rename = "lastUpdated")]
+    last_updated: DateTime<Utc>,
+    twin: Value,
+}
+
+impl CachedTwin {
+    pub fn last_updated(&self) -> DateTime<Utc> {
+        self.last_updated
+    }
+
+    pub fn twin(&self) -> &Value {
+        &self.twin
+    }
+
+    pub fn into_twin(self) -> Value {
+        self.twin
+    }
+}
+
+/// Keeps the last known twin of each module on disk, one JSON file per module, so that it can
+/// still be served while IoT Hub is unreachable.
+#[derive(Clone, Debug)]
+pub struct TwinCache {
+    dir: PathBuf,
+}
+
+impl TwinCache {
+    /// Caches twins in `dir`. The directory is created by the first write.
+    pub fn new(dir: PathBuf) -> Self {
+        TwinCache { dir }
+    }
+
+    /// Replaces the cached twin of the module, and returns the cache entry that was written.
+    pub fn put(&self, module_id: &str, twin: Value) -> Result<CachedTwin, Error> {
+        let cached = CachedTwin {
+            last_updated: Utc::now(),
+            twin,
+        };
+
+        fs::create_dir_all(&self.dir).context(ErrorKind::TwinCache)?;
+        let contents = serde_json::to_vec(&cached).context(ErrorKind::TwinCache)?;
+        let path = self.path(module_id);
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, contents).context(ErrorKind::TwinCache)?;
+        fs::rename(&temp_path, path).context(ErrorKind::TwinCache)?;
+
+        Ok(cached)
+    }
+
+    /// Returns `None` if no twin has been cached for the module yet.
+    pub fn get(&self, module_id: &str) -> Result<Option<CachedTwin>, Error> {
+        match fs::read(self.path(module_id)) {
+            Ok(contents) => Ok(Some(
+                serde_json::from_slice(&contents).context(ErrorKind::TwinCache)?,
+            )),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::from(err.context(ErrorKind::TwinCache))),
+        }
+    }
+
+    /// Module IDs may contain characters that aren't valid in file names, so they're encoded.
+    fn path(&self, module_id: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}.json",
+            base64::encode_config(module_id, base64::URL_SAFE_NO_PAD)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use serde_json::json;
+
+    #[test]
+    fn put_then_get_returns_twin() {
+        let dir = env::temp_dir().join("edgelet-core-twin-cache");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = TwinCache::new(dir.clone());
+
+        assert_eq!(None, cache.get("$edgeAgent").unwrap());
+
+        let twin = json!({
+            "moduleId": "$edgeAgent",
+            "properties": {
+                "desired": { "$version": 2 },
+                "reported": { "$version": 1 }
+            }
+        });
+        let written = cache.put("$edgeAgent", twin.clone()).unwrap();
+
+        let cached = cache.get("$edgeAgent").unwrap().unwrap();
+        assert_eq!(written, cached);
+        assert_eq!(&twin, cached.twin());
+        assert_eq!(None, cache.get("m1").unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::cmp::Ordering;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use failure::Fail;
@@ -8,15 +9,16 @@ use futures::future::{self, Either, FutureResult};
 use futures::Future;
 use log::{info, warn, Level};
 use tokio::prelude::*;
-use tokio::timer::Interval;
+use tokio::timer::{Delay, Interval};
 
 use edgelet_utils::log_failure;
 
 use crate::error::{Error, ErrorKind};
+use crate::event_log::{EventLog, ModuleEvent};
 use crate::identity::{Identity, IdentityManager, IdentitySpec};
 use crate::module::{
     ImagePullPolicy, Module, ModuleRegistry, ModuleRuntime, ModuleRuntimeErrorReason, ModuleSpec,
-    ModuleStatus,
+    ModuleStatus, RestartPolicy,
 };
 use crate::settings::RetryLimit;
 
@@ -29,10 +31,14 @@ const MODULE_GENERATIONID: &str = "IOTEDGE_MODULEGENERATIONID";
 /// This is the frequency with which the watchdog checks for the status of the edge runtime module.
 const WATCHDOG_FREQUENCY_SECS: u64 = 60;
 
+/// This is how long the edge runtime module has to stay up after a restart for its restart count to be reset.
+const RESTART_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
 pub struct Watchdog<M, I> {
     runtime: M,
     id_mgr: I,
     max_retries: RetryLimit,
+    event_log: EventLog,
 }
 
 impl<M, I> Watchdog<M, I>
@@ -47,9 +53,16 @@ where
             runtime,
             id_mgr,
             max_retries,
+            event_log: EventLog::default(),
         }
     }
 
+    /// Sets the log that the watchdog records the edge runtime module's lifecycle events to.
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
+    }
+
     // Start the edge runtime module (EdgeAgent). This also updates the identity of the module (module_id)
     // to make sure it is configured for the right authentication type (sas token)
     // spec.name = edgeAgent / module_id = $edgeAgent
@@ -68,8 +81,10 @@ where
         let id_mgr = self.id_mgr;
         let module_id = module_id.to_string();
         let max_retries = self.max_retries;
+        let event_log = self.event_log;
+        let event_log_copy = event_log.clone();
 
-        let watchdog = start_watchdog(runtime, id_mgr, spec, module_id, max_retries);
+        let watchdog = start_watchdog(runtime, id_mgr, spec, module_id, max_retries, event_log);
 
         // Swallow any errors from shutdown_signal
         let shutdown_signal = shutdown_signal.then(|_| Ok(()));
@@ -80,7 +95,7 @@ where
         shutdown_signal
             .select(watchdog)
             .then(move |result| match result {
-                Ok(((), _)) => Ok(stop_runtime(&runtime_copy, &name)),
+                Ok(((), _)) => Ok(stop_runtime(&runtime_copy, &name, &event_log_copy)),
                 Err((err, _)) => Err(err),
             })
             .flatten()
@@ -88,15 +103,30 @@ where
 }
 
 // Stop EdgeAgent
-fn stop_runtime<M>(runtime: &M, name: &str) -> impl Future<Item = (), Error = Error>
+fn stop_runtime<M>(
+    runtime: &M,
+    name: &str,
+    event_log: &EventLog,
+) -> impl Future<Item = (), Error = Error>
 where
     M: 'static + ModuleRuntime + Clone,
     for<'r> &'r <M as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
     <M::Module as Module>::Config: Clone,
 {
     info!("Stopping edge runtime module {}", name);
+    let name = name.to_string();
+    let event_log = event_log.clone();
     runtime
-        .stop(name, Some(EDGE_RUNTIME_STOP_TIME))
+        .stop(&name, Some(EDGE_RUNTIME_STOP_TIME))
+        .map(move |_| {
+            event_log.record(
+                &name,
+                ModuleEvent::Stopped {
+                    exit_code: None,
+                    status_description: None,
+                },
+            );
+        })
         .or_else(|err| match (&err).into() {
             ModuleRuntimeErrorReason::NotFound => Ok(()),
             _ => Err(Error::from(err.context(ErrorKind::ModuleRuntime))),
@@ -110,6 +140,7 @@ pub fn start_watchdog<M, I>(
     spec: ModuleSpec<<M::Module as Module>::Config>,
     module_id: String,
     max_retries: RetryLimit,
+    event_log: EventLog,
 ) -> impl Future<Item = (), Error = Error>
 where
     M: 'static + ModuleRuntime + Clone,
@@ -121,6 +152,8 @@ where
         WATCHDOG_FREQUENCY_SECS
     );
 
+    let backoff = Arc::new(Mutex::new(RestartBackoff::new(*spec.restart_policy())));
+
     Interval::new(Instant::now(), Duration::from_secs(WATCHDOG_FREQUENCY_SECS))
         .map_err(|err| Error::from(err.context(ErrorKind::EdgeRuntimeStatusCheckerTimer)))
         .and_then(move |_| {
@@ -130,6 +163,8 @@ where
                 id_mgr.clone(),
                 spec.clone(),
                 module_id.clone(),
+                backoff.clone(),
+                event_log.clone(),
             )
             .and_then(|_| future::ok(None))
             .or_else(|e| {
@@ -158,6 +193,8 @@ fn check_runtime<M, I>(
     id_mgr: I,
     spec: ModuleSpec<<M::Module as Module>::Config>,
     module_id: String,
+    backoff: Arc<Mutex<RestartBackoff>>,
+    event_log: EventLog,
 ) -> impl Future<Item = (), Error = Error>
 where
     M: 'static + ModuleRuntime + Clone,
@@ -174,28 +211,104 @@ where
         })
         .and_then(move |state| match state {
             Some(state) => {
+                let mut backoff = backoff
+                    .lock()
+                    .expect("Unable to lock the restart backoff mutex");
+
                 let res = if *state.status() == ModuleStatus::Running {
                     info!("Edge runtime is running.");
+                    backoff.running(Instant::now());
                     future::Either::A(future::ok(()))
                 } else {
-                    info!(
-                        "Edge runtime status is {}, starting module now...",
-                        *state.status(),
+                    let status_description = state.status_description().map(ToString::to_string);
+                    event_log.record(
+                        &module,
+                        ModuleEvent::Stopped {
+                            exit_code: state.exit_code(),
+                            status_description,
+                        },
                     );
-                    future::Either::B(
-                        runtime
-                            .start(&module)
-                            .map_err(|e| Error::from(e.context(ErrorKind::ModuleRuntime))),
-                    )
+
+                    let attempt = backoff.attempts();
+                    let delay = backoff.restart(Instant::now());
+                    future::Either::B(future::result(delay).and_then(move |delay| {
+                        info!(
+                            "Edge runtime status is {}, starting module in {} seconds...",
+                            *state.status(),
+                            delay.as_secs(),
+                        );
+                        Delay::new(Instant::now() + delay)
+                            .map_err(|e| {
+                                Error::from(e.context(ErrorKind::EdgeRuntimeStatusCheckerTimer))
+                            })
+                            .and_then(move |_| {
+                                runtime
+                                    .start(&module)
+                                    .map(move |_| {
+                                        event_log
+                                            .record(&module, ModuleEvent::Restarted { attempt })
+                                    })
+                                    .map_err(|e| Error::from(e.context(ErrorKind::ModuleRuntime)))
+                            })
+                    }))
                 };
                 Either::A(res)
             }
 
-            None => Either::B(create_and_start(runtime, &id_mgr, spec, module_id)),
+            None => Either::B(create_and_start(
+                runtime, &id_mgr, spec, module_id, event_log,
+            )),
         })
         .map(|_| ())
 }
 
+// Tracks consecutive restarts of the edge runtime module so that they can be backed off.
+struct RestartBackoff {
+    policy: RestartPolicy,
+    attempts: u32,
+    last_restart: Option<Instant>,
+}
+
+impl RestartBackoff {
+    fn new(policy: RestartPolicy) -> Self {
+        RestartBackoff {
+            policy,
+            attempts: 0,
+            last_restart: None,
+        }
+    }
+
+    fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Resets the restart count once the module has stayed up for the grace period.
+    fn running(&mut self, now: Instant) {
+        if let Some(last_restart) = self.last_restart {
+            if now.saturating_duration_since(last_restart) >= RESTART_GRACE_PERIOD {
+                self.attempts = 0;
+                self.last_restart = None;
+            }
+        }
+    }
+
+    // Records a restart and returns how long to wait before performing it.
+    fn restart(&mut self, now: Instant) -> Result<Duration, Error> {
+        if let Some(max_restarts) = self.policy.max_restarts() {
+            if self.attempts >= max_restarts {
+                return Err(Error::from(ErrorKind::EdgeRuntimeRestartLimitReached(
+                    self.attempts,
+                )));
+            }
+        }
+
+        let delay = self.policy.delay(self.attempts);
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_restart = Some(now + delay);
+        Ok(delay)
+    }
+}
+
 // Gets the edge runtime module, if it exists.
 fn get_edge_runtime_mod<M>(
     runtime: &M,
@@ -247,6 +360,7 @@ fn create_and_start<M, I>(
     id_mgr: &I,
     spec: ModuleSpec<<M::Module as Module>::Config>,
     module_id: String,
+    event_log: EventLog,
 ) -> impl Future<Item = (), Error = Error>
 where
     M: 'static + ModuleRuntime + Clone,
@@ -272,9 +386,16 @@ where
             ImagePullPolicy::OnCreate => Either::B(runtime.registry().pull(spec.clone().config())),
         };
 
+        let event_log_copy = event_log.clone();
+        let module_name_copy = module_name.clone();
+
         pull_future
             .and_then(move |_| runtime.create(spec))
-            .and_then(move |_| runtime_copy.start(&module_name))
+            .and_then(move |_| {
+                event_log.record(&module_name, ModuleEvent::Created);
+                runtime_copy.start(&module_name)
+            })
+            .map(move |_| event_log_copy.record(&module_name_copy, ModuleEvent::Started))
             .map_err(|e| Error::from(e.context(ErrorKind::ModuleRuntime)))
     })
 }
@@ -523,4 +644,52 @@ mod tests {
                 .auth_type
         );
     }
+
+    #[test]
+    fn restart_backoff_increases_delay() {
+        let policy = RestartPolicy::new(None, Duration::from_secs(1), 2.0, Duration::from_secs(5));
+        let mut backoff = RestartBackoff::new(policy);
+        let now = Instant::now();
+
+        assert_eq!(Duration::from_secs(1), backoff.restart(now).unwrap());
+        assert_eq!(Duration::from_secs(2), backoff.restart(now).unwrap());
+        assert_eq!(Duration::from_secs(4), backoff.restart(now).unwrap());
+        assert_eq!(Duration::from_secs(5), backoff.restart(now).unwrap());
+    }
+
+    #[test]
+    fn restart_backoff_stops_at_max_restarts() {
+        let policy =
+            RestartPolicy::new(Some(2), Duration::from_secs(1), 2.0, Duration::from_secs(5));
+        let mut backoff = RestartBackoff::new(policy);
+        let now = Instant::now();
+
+        assert!(backoff.restart(now).is_ok());
+        assert!(backoff.restart(now).is_ok());
+        match backoff.restart(now) {
+            Ok(_) => panic!("expected restart limit to be reached"),
+            Err(err) => match err.kind() {
+                ErrorKind::EdgeRuntimeRestartLimitReached(2) => (),
+                kind => panic!("unexpected error kind {:?}", kind),
+            },
+        }
+    }
+
+    #[test]
+    fn restart_backoff_resets_after_grace_period() {
+        let policy =
+            RestartPolicy::new(Some(2), Duration::from_secs(1), 2.0, Duration::from_secs(5));
+        let mut backoff = RestartBackoff::new(policy);
+        let now = Instant::now();
+
+        backoff.restart(now).unwrap();
+        backoff.restart(now).unwrap();
+
+        // still within the grace period, so the count is kept
+        backoff.running(now + Duration::from_secs(60));
+        assert!(backoff.restart(now).is_err());
+
+        backoff.running(now + RESTART_GRACE_PERIOD + Duration::from_secs(60));
+        assert_eq!(Duration::from_secs(1), backoff.restart(now).unwrap());
+    }
 }
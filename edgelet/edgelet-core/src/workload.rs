@@ -1,5 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use url::Url;
+
 use crate::certificate_properties::CertificateType;
 
 /// Trait to obtain configuration data needed by any implementation of the workload interface
@@ -8,4 +10,10 @@ pub trait WorkloadConfig {
     fn iot_hub_name(&self) -> &str;
     fn device_id(&self) -> &str;
     fn get_cert_max_duration(&self, cert_type: CertificateType) -> i64;
+
+    /// The certificate transparency log that newly issued module server certificates are
+    /// submitted to, if any.
+    fn ct_log_url(&self) -> Option<&Url> {
+        None
+    }
 }
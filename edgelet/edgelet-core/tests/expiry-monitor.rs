@@ -0,0 +1,58 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+
+use std::time::Duration;
+
+use edgelet_core::CertificateExpiryMonitor;
+use edgelet_test_utils::cert::{TestCa, TestCert, TestCertUsage};
+use edgelet_test_utils::crypto::TestHsm;
+
+fn check_trust_bundle(trust_bundle: Vec<u8>, threshold: Duration) -> Vec<String> {
+    let hsm = TestHsm::default().with_cert(TestCert::default().with_cert(trust_bundle));
+    let monitor = CertificateExpiryMonitor::new(hsm, threshold);
+
+    monitor
+        .check()
+        .unwrap()
+        .iter()
+        .map(|cert| cert.subject().to_string())
+        .collect()
+}
+
+fn issue(ca: &TestCa, common_name: &str, validity: chrono::Duration) -> Vec<u8> {
+    ca.issue_valid_for(common_name, TestCertUsage::Server, validity)
+        .get_leaf_certificate()
+}
+
+#[test]
+fn warns_for_certificate_within_threshold() {
+    let ca = TestCa::new("root");
+    let mut trust_bundle = issue(&ca, "expiring", chrono::Duration::hours(23));
+    trust_bundle.extend(issue(&ca, "fresh", chrono::Duration::days(90)));
+
+    let expiring = check_trust_bundle(trust_bundle, Duration::from_secs(24 * 60 * 60));
+
+    assert_eq!(vec!["expiring".to_string()], expiring);
+}
+
+#[test]
+fn does_not_warn_for_certificate_outside_threshold() {
+    let ca = TestCa::new("root");
+    let trust_bundle = issue(&ca, "expiring", chrono::Duration::hours(23));
+
+    let expiring = check_trust_bundle(trust_bundle, Duration::from_secs(22 * 60 * 60));
+
+    assert!(expiring.is_empty());
+}
+
+#[test]
+fn fails_if_trust_bundle_is_unavailable() {
+    let monitor = CertificateExpiryMonitor::new(
+        TestHsm::default().with_fail_call(true),
+        Duration::from_secs(24 * 60 * 60),
+    );
+
+    assert!(monitor.check().is_err());
+}
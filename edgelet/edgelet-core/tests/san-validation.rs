@@ -0,0 +1,54 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+
+use openssl::x509::X509;
+
+use edgelet_core::{validate_san, ErrorKind};
+use edgelet_test_utils::cert::TestCa;
+
+fn cert_with_dns_names(common_name: &str, dns_names: &[&str]) -> X509 {
+    let cert = TestCa::new("root").issue_with_dns_names(common_name, dns_names);
+    X509::from_pem(&cert.get_leaf_certificate()).unwrap()
+}
+
+fn assert_hostname_mismatch(cert: &X509, hostname: &str) {
+    let err = validate_san(cert, hostname).unwrap_err();
+    match err.kind() {
+        ErrorKind::CertificateHostnameMismatch(h) if h == hostname => (),
+        kind => panic!("Expected `CertificateHostnameMismatch` but got {:?}", kind),
+    }
+}
+
+#[test]
+fn san_dns_name_matches() {
+    let cert = cert_with_dns_names(
+        "ignored.example.com",
+        &["hub.azure-devices.net", "other.example.com"],
+    );
+
+    validate_san(&cert, "hub.azure-devices.net").unwrap();
+    validate_san(&cert, "HUB.Azure-Devices.net.").unwrap();
+    validate_san(&cert, "other.example.com").unwrap();
+    assert_hostname_mismatch(&cert, "ignored.example.com");
+    assert_hostname_mismatch(&cert, "evil.azure-devices.net");
+}
+
+#[test]
+fn san_wildcard_matches_single_label() {
+    let cert = cert_with_dns_names("hub", &["*.azure-devices.net", "*.com"]);
+
+    validate_san(&cert, "myhub.azure-devices.net").unwrap();
+    assert_hostname_mismatch(&cert, "azure-devices.net");
+    assert_hostname_mismatch(&cert, "a.myhub.azure-devices.net");
+    assert_hostname_mismatch(&cert, "example.com");
+}
+
+#[test]
+fn common_name_is_used_without_sans() {
+    let cert = cert_with_dns_names("myhub.azure-devices.net", &[]);
+
+    validate_san(&cert, "myhub.azure-devices.net").unwrap();
+    assert_hostname_mismatch(&cert, "otherhub.azure-devices.net");
+}
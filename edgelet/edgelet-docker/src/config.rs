@@ -1,5 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::fmt;
+
 use failure::ResultExt;
 
 use docker::models::{AuthConfig, ContainerCreateBody};
@@ -7,7 +9,7 @@ use edgelet_utils::{ensure_not_empty_with_context, serde_clone};
 
 use crate::error::{ErrorKind, Result};
 
-#[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize, Clone)]
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct DockerConfig {
     image: String,
@@ -82,6 +84,22 @@ impl DockerConfig {
     }
 }
 
+// The registry password is left out so that module specs can be logged.
+impl fmt::Debug for DockerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let auth = self
+            .auth
+            .as_ref()
+            .map(|auth| auth.clone().with_password("<redacted>".to_string()));
+        f.debug_struct("DockerConfig")
+            .field("image", &self.image)
+            .field("image_id", &self.image_id)
+            .field("create_options", &self.create_options)
+            .field("auth", &auth)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
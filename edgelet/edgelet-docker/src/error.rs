@@ -2,6 +2,7 @@
 
 use std::fmt;
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use failure::{Backtrace, Context, Fail};
 use hyper::StatusCode;
@@ -56,6 +57,15 @@ pub enum ErrorKind {
     #[fail(display = "{}", _0)]
     FormattedDockerRuntime(String),
 
+    #[fail(display = "Host path {:?} is not in an allowed location", _0)]
+    HostPathNotAllowed(PathBuf),
+
+    #[fail(display = "Could not verify the signature of image {}", _0)]
+    ImageSignature(String),
+
+    #[fail(display = "Could not check image {} for updates", _0)]
+    ImageUpdateCheck(String),
+
     #[fail(display = "Could not initialize module runtime")]
     Initialization,
 
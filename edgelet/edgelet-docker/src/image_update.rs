@@ -0,0 +1,211 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use failure::{Fail, ResultExt};
+use futures::prelude::*;
+use futures::stream;
+use hyper::Uri;
+use log::{debug, info, warn, Level};
+use tokio::timer::Interval;
+
+use edgelet_core::{EventLog, Module, ModuleEvent, ModuleRuntime, RuntimeOperation};
+use edgelet_http::client::ClientImpl;
+use edgelet_http::oci::latest_digest;
+use edgelet_http::MaybeProxyClient;
+use edgelet_utils::log_failure;
+
+use crate::config::DockerConfig;
+use crate::error::{Error, ErrorKind, Result};
+
+/// How often registries are asked for the latest digests of module images by default.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically asks the registry of each module image that is pinned to a tag which manifest
+/// the tag refers to, and records an `ImageUpdateAvailable` event for the modules using the image
+/// when that changes. Images referenced by digest never change, so they aren't checked.
+///
+/// Not every runtime records the digest of the manifest that an image was pulled from, so the
+/// first digest seen for an image is taken to be the one its modules run.
+pub struct ImageUpdateChecker<M, C = MaybeProxyClient> {
+    runtime: M,
+    client: Arc<C>,
+    event_log: EventLog,
+    interval: Duration,
+    digests: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl<M> ImageUpdateChecker<M> {
+    pub fn from_proxy(runtime: M, event_log: EventLog, proxy_uri: Option<Uri>) -> Result<Self> {
+        let client =
+            MaybeProxyClient::new(proxy_uri, None, None).context(ErrorKind::Initialization)?;
+        Ok(ImageUpdateChecker::new(runtime, client, event_log))
+    }
+}
+
+impl<M, C> ImageUpdateChecker<M, C> {
+    pub fn new(runtime: M, client: C, event_log: EventLog) -> Self {
+        ImageUpdateChecker {
+            runtime,
+            client: Arc::new(client),
+            event_log,
+            interval: DEFAULT_CHECK_INTERVAL,
+            digests: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<M, C> ImageUpdateChecker<M, C>
+where
+    M: 'static + ModuleRuntime<Config = DockerConfig> + Send,
+    C: 'static + ClientImpl,
+{
+    /// Checks the module images once per interval, starting now, until `shutdown_signal`
+    /// completes.
+    pub fn run_until<F>(self, shutdown_signal: F) -> impl Future<Item = (), Error = ()> + Send
+    where
+        F: Future<Item = (), Error = ()> + Send + 'static,
+    {
+        let checks = Interval::new(Instant::now(), self.interval)
+            .map_err(|err| warn!("The image update timer failed: {}", err))
+            .for_each(move |_| {
+                self.check().then(|result| {
+                    if let Err(err) = result {
+                        log_failure(Level::Warn, &err);
+                    }
+                    Ok(())
+                })
+            });
+
+        shutdown_signal.select(checks).then(|_| Ok(()))
+    }
+
+    /// Asks the registries for the latest digest of each module image that is pinned to a tag.
+    /// Images that can't be checked are skipped, since registries that need credentials don't
+    /// answer anonymous requests.
+    pub fn check(&self) -> impl Future<Item = (), Error = Error> + Send {
+        debug!("Checking module images for updates...");
+
+        let client = self.client.clone();
+        let event_log = self.event_log.clone();
+        let digests = self.digests.clone();
+
+        self.runtime
+            .list()
+            .map_err(|err| {
+                Error::from(err.context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules)))
+            })
+            .and_then(move |modules| {
+                let images = pinned_images(
+                    modules
+                        .iter()
+                        .map(|module| (module.name(), module.config().image())),
+                );
+                // images that no module uses anymore are forgotten
+                digests
+                    .lock()
+                    .expect("Unable to lock the image digests mutex")
+                    .retain(|image, _| images.contains_key(image));
+
+                stream::iter_ok(images).for_each(move |(image, modules)| {
+                    let event_log = event_log.clone();
+                    let digests = digests.clone();
+                    latest_digest(client.clone(), &image).then(move |result| {
+                        match result {
+                            Ok(digest) => {
+                                let updated = record_digest(
+                                    &mut digests
+                                        .lock()
+                                        .expect("Unable to lock the image digests mutex"),
+                                    &image,
+                                    &digest,
+                                );
+                                if updated {
+                                    info!("A newer image is available for {}: {}", image, digest);
+                                    for module in modules {
+                                        event_log.record(
+                                            &module,
+                                            ModuleEvent::ImageUpdateAvailable {
+                                                image: image.clone(),
+                                                digest: digest.clone(),
+                                            },
+                                        );
+                                    }
+                                }
+                            }
+                            Err(err) => log_failure(
+                                Level::Debug,
+                                &Error::from(err.context(ErrorKind::ImageUpdateCheck(image))),
+                            ),
+                        }
+                        Ok(())
+                    })
+                })
+            })
+    }
+}
+
+/// The images pinned to a tag among `modules`, which are pairs of module names and images, with
+/// the names of the modules that use each one.
+fn pinned_images<'a>(
+    modules: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut images = BTreeMap::new();
+    for (name, image) in modules {
+        if !image.contains('@') {
+            images
+                .entry(image.to_string())
+                .or_insert_with(Vec::new)
+                .push(name.to_string());
+        }
+    }
+    images
+}
+
+/// Remembers `digest` as the latest one for `image`, and returns whether the one remembered
+/// before it was different.
+fn record_digest(digests: &mut HashMap<String, String>, image: &str, digest: &str) -> bool {
+    match digests.insert(image.to_string(), digest.to_string()) {
+        Some(previous) => previous != digest,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_images_skips_digest_references() {
+        let images = pinned_images(vec![
+            ("edgeAgent", "mcr.microsoft.com/azureiotedge-agent:1.0"),
+            ("module1", "registry:5000/module:1.0"),
+            ("module2", "registry:5000/module:1.0"),
+            ("module3", "registry:5000/module@sha256:1234"),
+        ]);
+
+        assert_eq!(2, images.len());
+        assert_eq!(
+            vec!["module1".to_string(), "module2".to_string()],
+            images["registry:5000/module:1.0"]
+        );
+        assert!(!images.contains_key("registry:5000/module@sha256:1234"));
+    }
+
+    #[test]
+    fn record_digest_reports_changes_after_first_digest() {
+        let mut digests = HashMap::new();
+
+        assert!(!record_digest(&mut digests, "module:1.0", "sha256:1111"));
+        assert!(!record_digest(&mut digests, "module:1.0", "sha256:1111"));
+        assert!(record_digest(&mut digests, "module:1.0", "sha256:2222"));
+        assert!(!record_digest(&mut digests, "module:1.0", "sha256:2222"));
+    }
+}
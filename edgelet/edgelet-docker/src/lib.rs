@@ -13,12 +13,16 @@
 mod client;
 mod config;
 mod error;
+mod image_update;
 mod module;
+mod podman;
 mod runtime;
 mod settings;
 
 pub use crate::config::DockerConfig;
 pub use error::{Error, ErrorKind};
+pub use image_update::ImageUpdateChecker;
 pub use module::{DockerModule, MODULE_TYPE};
+pub use podman::PodmanModuleRuntime;
 pub use runtime::DockerModuleRuntime;
 pub use settings::{LoadSettingsError, Settings, DEFAULTS};
@@ -10,6 +10,7 @@ use hyper::client::connect::Connect;
 use docker::models::{InlineResponse2001, InlineResponse200State};
 use edgelet_core::{
     Module, ModuleOperation, ModuleRuntimeState, ModuleStatus, ModuleTop, RuntimeOperation,
+    WorkloadCapability,
 };
 use edgelet_utils::ensure_not_empty_with_context;
 
@@ -26,6 +27,7 @@ pub struct DockerModule<C: Connect> {
     client: DockerClient<C>,
     name: String,
     config: DockerConfig,
+    capabilities: Option<Vec<WorkloadCapability>>,
 }
 
 impl<C> std::fmt::Debug for DockerModule<C>
@@ -45,8 +47,14 @@ impl<C: 'static + Connect> DockerModule<C> {
             client,
             name,
             config,
+            capabilities: None,
         })
     }
+
+    pub fn with_capabilities(mut self, capabilities: Option<Vec<WorkloadCapability>>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
 }
 
 pub trait DockerModuleTop {
@@ -202,6 +210,10 @@ impl<C: 'static + Connect> Module for DockerModule<C> {
                 }),
         )
     }
+
+    fn capabilities(&self) -> Option<&[WorkloadCapability]> {
+        self.capabilities.as_ref().map(AsRef::as_ref)
+    }
 }
 
 #[cfg(test)]
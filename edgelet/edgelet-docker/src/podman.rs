@@ -0,0 +1,813 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Podman serves a Docker-compatible API next to its own `libpod` API, so most operations on
+//! modules go through [`DockerModuleRuntime`] unchanged. Containers are created through `libpod`
+//! instead, from a Podman container spec that the createOptions of modules are converted to,
+//! since that is the route Podman's own tools create containers with.
+//!
+//! For rootless Podman, the API is served at `unix:///run/user/<uid>/podman/podman.sock`, which
+//! `moby_runtime.uri` must point at.
+
+use std::time::Duration;
+
+use failure::{Fail, ResultExt};
+use futures::prelude::*;
+use futures::{future, Stream};
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Request, Response, Uri};
+use log::{info, warn, Level};
+use serde_json::{self, json, Map, Value};
+use url::form_urlencoded;
+use url::Url;
+
+use docker::apis::{ApiError as DockerApiError, Error as DockerError};
+use docker::models::ContainerCreateBody;
+use edgelet_core::{
+    Authenticator, GetTrustBundle, LogOptions, MakeModuleRuntime, ModuleRuntime, ModuleSpec,
+    RuntimeOperation, UrlExt,
+};
+use edgelet_http::UrlConnector;
+use edgelet_utils::log_failure;
+use provisioning::ProvisioningResult;
+
+use crate::config::DockerConfig;
+use crate::error::{Error, ErrorKind, Result};
+use crate::module::{DockerModule, MODULE_TYPE as DOCKER_MODULE_TYPE};
+use crate::runtime::{Chunk, DockerModuleRuntime, Logs};
+use crate::settings::Settings;
+
+const LIBPOD_CREATE_PATH: &str = "/libpod/containers/create";
+
+/// The createOptions fields that are converted to the Podman container spec. Others are ignored
+/// with a warning.
+const CONVERTED_FIELDS: &[&str] = &[
+    "Cmd",
+    "Entrypoint",
+    "Env",
+    "ExposedPorts",
+    "Healthcheck",
+    "HostConfig",
+    "Hostname",
+    "Image",
+    "Labels",
+    "NetworkingConfig",
+    "OpenStdin",
+    "StopSignal",
+    "StopTimeout",
+    "Tty",
+    "User",
+    "Volumes",
+    "WorkingDir",
+];
+
+const CONVERTED_HOST_CONFIG_FIELDS: &[&str] = &[
+    "Binds",
+    "CapAdd",
+    "CapDrop",
+    "CpuPeriod",
+    "CpuQuota",
+    "CpuShares",
+    "CpusetCpus",
+    "Devices",
+    "Dns",
+    "DnsSearch",
+    "ExtraHosts",
+    "Init",
+    "IpcMode",
+    "LogConfig",
+    "Memory",
+    "MemoryReservation",
+    "MemorySwap",
+    "Mounts",
+    "NanoCpus",
+    "NetworkMode",
+    "PidMode",
+    "PidsLimit",
+    "PortBindings",
+    "Privileged",
+    "ReadonlyRootfs",
+    "RestartPolicy",
+    "ShmSize",
+    "Sysctls",
+];
+
+/// The CPU period that `NanoCpus` is converted to a quota of, which is also Docker's.
+const CPU_PERIOD: u64 = 100_000;
+
+#[derive(Clone)]
+pub struct PodmanModuleRuntime {
+    docker: DockerModuleRuntime,
+    client: Client<UrlConnector>,
+    scheme: String,
+    base_path: String,
+}
+
+impl PodmanModuleRuntime {
+    fn create_request(&self, name: &str, spec: &Value) -> Result<Request<Body>> {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("name", name)
+            .finish();
+        let uri: Uri = UrlConnector::build_hyper_uri(
+            &self.scheme,
+            &self.base_path,
+            &format!("{}?{}", LIBPOD_CREATE_PATH, query),
+        )
+        .context(ErrorKind::Docker)?;
+
+        let body = serde_json::to_vec(spec).context(ErrorKind::Docker)?;
+        let request = Request::post(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .context(ErrorKind::Docker)?;
+        Ok(request)
+    }
+}
+
+impl std::fmt::Debug for PodmanModuleRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PodmanModuleRuntime").finish()
+    }
+}
+
+/// Reads the response of `libpod`, whose errors have the same `message` as Docker's, so that
+/// they're reported like the errors of the other operations.
+fn check_response(
+    response: Response<Body>,
+    context: ErrorKind,
+) -> impl Future<Item = (), Error = Error> + Send {
+    let status = response.status();
+    response.into_body().concat2().then(move |body| match body {
+        Ok(_) if status.is_success() => Ok(()),
+        Ok(body) => Err(Error::from_docker_error(
+            DockerError::Api(DockerApiError {
+                code: status,
+                content: serde_json::from_slice(&body).ok(),
+            }),
+            context,
+        )),
+        Err(err) => Err(Error::from_docker_error(DockerError::Hyper(err), context)),
+    })
+}
+
+/// Converts the createOptions of module `name` to a Podman container spec.
+pub(crate) fn container_spec(name: &str, create_options: &ContainerCreateBody) -> Result<Value> {
+    let options = serde_json::to_value(create_options).context(ErrorKind::CloneCreateOptions)?;
+    let empty = Map::new();
+    let options = options.as_object().unwrap_or(&empty);
+    let host_config = options
+        .get("HostConfig")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    for field in options.keys() {
+        if !CONVERTED_FIELDS.contains(&field.as_str()) {
+            warn!(
+                "Ignoring createOptions field {} of module {}, which isn't supported with Podman",
+                field, name
+            );
+        }
+    }
+    for field in host_config.keys() {
+        if !CONVERTED_HOST_CONFIG_FIELDS.contains(&field.as_str()) {
+            warn!(
+                "Ignoring createOptions field HostConfig.{} of module {}, which isn't supported with Podman",
+                field, name
+            );
+        }
+    }
+
+    let mut spec = Map::new();
+    spec.insert("name".to_string(), name.into());
+
+    for (field, key) in &[
+        ("Image", "image"),
+        ("Hostname", "hostname"),
+        ("User", "user"),
+        ("WorkingDir", "work_dir"),
+        ("Cmd", "command"),
+        ("Entrypoint", "entrypoint"),
+        ("Labels", "labels"),
+        ("StopTimeout", "stop_timeout"),
+        ("Healthcheck", "healthconfig"),
+        ("Tty", "terminal"),
+        ("OpenStdin", "stdin"),
+    ] {
+        copy_field(options, field, &mut spec, key);
+    }
+    for (field, key) in &[
+        ("Privileged", "privileged"),
+        ("CapAdd", "cap_add"),
+        ("CapDrop", "cap_drop"),
+        ("ReadonlyRootfs", "read_only_filesystem"),
+        ("Init", "init"),
+        ("ExtraHosts", "hostadd"),
+        ("Dns", "dns_server"),
+        ("DnsSearch", "dns_search"),
+        ("Sysctls", "sysctl"),
+        ("ShmSize", "shm_size"),
+    ] {
+        copy_field(host_config, field, &mut spec, key);
+    }
+
+    if let Some(env) = options.get("Env").and_then(Value::as_array) {
+        // "KEY=value" entries become a map, and entries without a value are set to ""
+        let env: Map<String, Value> = env
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|entry| {
+                let mut parts = entry.splitn(2, '=');
+                let key = parts.next().unwrap_or_default().to_string();
+                (key, parts.next().unwrap_or_default().into())
+            })
+            .collect();
+        spec.insert("env".to_string(), env.into());
+    }
+
+    if let Some(signal) = options.get("StopSignal").and_then(Value::as_str) {
+        match signal_number(signal) {
+            Some(number) => {
+                spec.insert("stop_signal".to_string(), number.into());
+            }
+            None => warn!(
+                "Ignoring stop signal {} of module {}, which isn't supported with Podman",
+                signal, name
+            ),
+        }
+    }
+
+    if let Some(ports) = options.get("ExposedPorts").and_then(Value::as_object) {
+        let expose: Map<String, Value> = ports
+            .keys()
+            .filter_map(|port| parse_port(port))
+            .map(|(port, protocol)| (port.to_string(), protocol.into()))
+            .collect();
+        spec.insert("expose".to_string(), expose.into());
+    }
+
+    let (mounts, volumes) = mounts(options, host_config);
+    if !mounts.is_empty() {
+        spec.insert("mounts".to_string(), mounts.into());
+    }
+    if !volumes.is_empty() {
+        spec.insert("volumes".to_string(), volumes.into());
+    }
+
+    if let Some(bindings) = host_config.get("PortBindings").and_then(Value::as_object) {
+        spec.insert("portmappings".to_string(), port_mappings(bindings).into());
+    }
+
+    apply_namespaces(options, host_config, &mut spec);
+
+    let resource_limits = resource_limits(host_config);
+    if !resource_limits.is_empty() {
+        spec.insert("resource_limits".to_string(), resource_limits.into());
+    }
+
+    if let Some(devices) = host_config.get("Devices").and_then(Value::as_array) {
+        let devices: Vec<Value> = devices
+            .iter()
+            .filter_map(|device| {
+                let host = device["PathOnHost"].as_str()?;
+                let container = device["PathInContainer"].as_str().unwrap_or(host);
+                let permissions = device["CgroupPermissions"].as_str().unwrap_or("rwm");
+                Some(json!({ "path": format!("{}:{}:{}", host, container, permissions) }))
+            })
+            .collect();
+        spec.insert("devices".to_string(), devices.into());
+    }
+
+    if let Some(log_config) = host_config.get("LogConfig") {
+        let mut log_configuration = Map::new();
+        copy_field_value(log_config, "Type", &mut log_configuration, "driver");
+        copy_field_value(log_config, "Config", &mut log_configuration, "options");
+        spec.insert("log_configuration".to_string(), log_configuration.into());
+    }
+
+    if let Some(policy) = host_config.get("RestartPolicy") {
+        if let Some(policy_name) = policy["Name"].as_str().filter(|name| !name.is_empty()) {
+            spec.insert("restart_policy".to_string(), policy_name.into());
+            copy_field_value(policy, "MaximumRetryCount", &mut spec, "restart_tries");
+        }
+    }
+
+    Ok(spec.into())
+}
+
+fn copy_field(from: &Map<String, Value>, field: &str, to: &mut Map<String, Value>, key: &str) {
+    if let Some(value) = from.get(field).filter(|value| !value.is_null()) {
+        to.insert(key.to_string(), value.clone());
+    }
+}
+
+fn copy_field_value(from: &Value, field: &str, to: &mut Map<String, Value>, key: &str) {
+    if let Some(from) = from.as_object() {
+        copy_field(from, field, to, key);
+    }
+}
+
+/// The number of a signal given by name, such as `SIGTERM` or `TERM`, or by number. `libpod`
+/// only takes signal numbers.
+fn signal_number(signal: &str) -> Option<u32> {
+    if let Ok(number) = signal.parse() {
+        return Some(number);
+    }
+
+    let number = match signal.trim_start_matches("SIG") {
+        "HUP" => 1,
+        "INT" => 2,
+        "QUIT" => 3,
+        "KILL" => 9,
+        "USR1" => 10,
+        "USR2" => 12,
+        "TERM" => 15,
+        _ => return None,
+    };
+    Some(number)
+}
+
+/// Splits a port such as `80/tcp` into its number and protocol, which is TCP if it isn't given.
+fn parse_port(port: &str) -> Option<(u16, &str)> {
+    let mut parts = port.splitn(2, '/');
+    let number = parts.next()?.parse().ok()?;
+    Some((number, parts.next().unwrap_or("tcp")))
+}
+
+fn port_mappings(bindings: &Map<String, Value>) -> Vec<Value> {
+    let mut mappings = vec![];
+    for (port, host_bindings) in bindings {
+        let (container_port, protocol) = match parse_port(port) {
+            Some(port) => port,
+            None => continue,
+        };
+
+        for binding in host_bindings.as_array().into_iter().flatten() {
+            let mut mapping = Map::new();
+            mapping.insert("container_port".to_string(), container_port.into());
+            mapping.insert("protocol".to_string(), protocol.into());
+            if let Some(host_port) = binding["HostPort"]
+                .as_str()
+                .and_then(|port| port.parse::<u16>().ok())
+            {
+                mapping.insert("host_port".to_string(), host_port.into());
+            }
+            if let Some(host_ip) = binding["HostIp"].as_str().filter(|ip| !ip.is_empty()) {
+                mapping.insert("host_ip".to_string(), host_ip.into());
+            }
+            mappings.push(mapping.into());
+        }
+    }
+    mappings
+}
+
+/// The bind and tmpfs mounts, and the named and anonymous volumes, of the container. Podman
+/// takes named volumes separately from other mounts.
+fn mounts(
+    options: &Map<String, Value>,
+    host_config: &Map<String, Value>,
+) -> (Vec<Value>, Vec<Value>) {
+    let mut mounts = vec![];
+    let mut volumes = vec![];
+
+    let binds = host_config.get("Binds").and_then(Value::as_array);
+    for bind in binds.into_iter().flatten().filter_map(Value::as_str) {
+        let mut parts = bind.splitn(3, ':');
+        let (source, destination) = match (parts.next(), parts.next()) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => continue,
+        };
+        let bind_options: Vec<&str> = parts
+            .next()
+            .map_or_else(Vec::new, |bind_options| bind_options.split(',').collect());
+
+        if source.starts_with('/') {
+            mounts.push(json!({
+                "type": "bind",
+                "source": source,
+                "destination": destination,
+                "options": bind_options,
+            }));
+        } else {
+            volumes.push(json!({
+                "Name": source,
+                "Dest": destination,
+                "Options": bind_options,
+            }));
+        }
+    }
+
+    let host_mounts = host_config.get("Mounts").and_then(Value::as_array);
+    for mount in host_mounts.into_iter().flatten() {
+        let destination = match mount["Target"].as_str() {
+            Some(destination) => destination,
+            None => continue,
+        };
+        let mount_options: Vec<&str> = if mount["ReadOnly"].as_bool().unwrap_or(false) {
+            vec!["ro"]
+        } else {
+            vec![]
+        };
+
+        match mount["Type"].as_str() {
+            Some("bind") => mounts.push(json!({
+                "type": "bind",
+                "source": mount["Source"],
+                "destination": destination,
+                "options": mount_options,
+            })),
+            Some("volume") => volumes.push(json!({
+                "Name": mount["Source"].as_str().unwrap_or_default(),
+                "Dest": destination,
+                "Options": mount_options,
+            })),
+            Some("tmpfs") => {
+                let mut tmpfs_options: Vec<String> =
+                    mount_options.iter().map(ToString::to_string).collect();
+                if let Some(size) = mount["TmpfsOptions"]["SizeBytes"].as_i64() {
+                    tmpfs_options.push(format!("size={}", size));
+                }
+                if let Some(mode) = mount["TmpfsOptions"]["Mode"].as_i64() {
+                    tmpfs_options.push(format!("mode={:o}", mode));
+                }
+                mounts.push(json!({
+                    "type": "tmpfs",
+                    "source": "tmpfs",
+                    "destination": destination,
+                    "options": tmpfs_options,
+                }));
+            }
+            _ => (),
+        }
+    }
+
+    // Podman makes volumes without a name anonymous, as Docker does with `Volumes`
+    let anonymous_volumes = options.get("Volumes").and_then(Value::as_object);
+    for destination in anonymous_volumes.into_iter().flat_map(Map::keys) {
+        volumes.push(json!({ "Name": "", "Dest": destination, "Options": [] }));
+    }
+
+    (mounts, volumes)
+}
+
+/// Sets the network, PID and IPC namespaces of the container. Modules join a named network by
+/// default, as they do with Docker, and the network aliases of `NetworkingConfig` are kept.
+fn apply_namespaces(
+    options: &Map<String, Value>,
+    host_config: &Map<String, Value>,
+    spec: &mut Map<String, Value>,
+) {
+    let mut networks = Map::new();
+
+    match host_config.get("NetworkMode").and_then(Value::as_str) {
+        Some("host") => {
+            spec.insert("netns".to_string(), json!({ "nsmode": "host" }));
+        }
+        Some("none") => {
+            spec.insert("netns".to_string(), json!({ "nsmode": "none" }));
+        }
+        Some(mode) if mode.starts_with("container:") => {
+            spec.insert(
+                "netns".to_string(),
+                json!({ "nsmode": "container", "value": &mode["container:".len()..] }),
+            );
+        }
+        Some("bridge") | Some("default") | Some("") | None => (),
+        Some(network) => {
+            networks.insert(network.to_string(), json!({}));
+        }
+    }
+
+    let endpoints = options
+        .get("NetworkingConfig")
+        .and_then(|config| config["EndpointsConfig"].as_object());
+    for (network, endpoint) in endpoints.into_iter().flatten() {
+        let mut network_options = Map::new();
+        if let Some(aliases) = endpoint["Aliases"].as_array() {
+            network_options.insert("aliases".to_string(), aliases.clone().into());
+        }
+        networks.insert(network.to_string(), network_options.into());
+    }
+
+    if !networks.is_empty() && !spec.contains_key("netns") {
+        spec.insert("netns".to_string(), json!({ "nsmode": "bridge" }));
+        spec.insert("Networks".to_string(), networks.into());
+    }
+
+    for (field, key) in &[("PidMode", "pidns"), ("IpcMode", "ipcns")] {
+        if host_config.get(*field).and_then(Value::as_str) == Some("host") {
+            spec.insert((*key).to_string(), json!({ "nsmode": "host" }));
+        }
+    }
+}
+
+fn resource_limits(host_config: &Map<String, Value>) -> Map<String, Value> {
+    let mut memory = Map::new();
+    copy_field(host_config, "Memory", &mut memory, "limit");
+    copy_field(host_config, "MemorySwap", &mut memory, "swap");
+    copy_field(host_config, "MemoryReservation", &mut memory, "reservation");
+
+    let mut cpu = Map::new();
+    copy_field(host_config, "CpuShares", &mut cpu, "shares");
+    copy_field(host_config, "CpuQuota", &mut cpu, "quota");
+    copy_field(host_config, "CpuPeriod", &mut cpu, "period");
+    copy_field(host_config, "CpusetCpus", &mut cpu, "cpus");
+    if let Some(nano_cpus) = host_config.get("NanoCpus").and_then(Value::as_u64) {
+        cpu.insert(
+            "quota".to_string(),
+            (nano_cpus * CPU_PERIOD / 1_000_000_000).into(),
+        );
+        cpu.insert("period".to_string(), CPU_PERIOD.into());
+    }
+
+    let mut pids = Map::new();
+    copy_field(host_config, "PidsLimit", &mut pids, "limit");
+
+    let mut limits = Map::new();
+    for (key, limit) in vec![("memory", memory), ("cpu", cpu), ("pids", pids)] {
+        if !limit.is_empty() {
+            limits.insert(key.to_string(), limit.into());
+        }
+    }
+    limits
+}
+
+impl MakeModuleRuntime for PodmanModuleRuntime {
+    type Config = DockerConfig;
+    type Settings = Settings;
+    type ProvisioningResult = ProvisioningResult;
+    type ModuleRuntime = Self;
+    type Error = Error;
+    type Future = Box<dyn Future<Item = Self, Error = Self::Error> + Send>;
+
+    fn make_runtime(
+        settings: Settings,
+        provisioning_result: ProvisioningResult,
+        crypto: impl GetTrustBundle + Send + 'static,
+    ) -> Self::Future {
+        let libpod = libpod_client(settings.moby_runtime().uri());
+
+        Box::new(
+            DockerModuleRuntime::make_runtime(settings, provisioning_result, crypto).and_then(
+                move |docker| -> Result<PodmanModuleRuntime> {
+                    let (client, scheme, base_path) = libpod.map_err(|err| {
+                        log_failure(Level::Warn, &err);
+                        err
+                    })?;
+                    Ok(PodmanModuleRuntime {
+                        docker,
+                        client,
+                        scheme,
+                        base_path,
+                    })
+                },
+            ),
+        )
+    }
+}
+
+fn libpod_client(uri: &Url) -> Result<(Client<UrlConnector>, String, String)> {
+    let client =
+        Client::builder().build(UrlConnector::new(uri).context(ErrorKind::Initialization)?);
+    let base_path = uri
+        .to_base_path()
+        .context(ErrorKind::Initialization)?
+        .to_str()
+        .ok_or(ErrorKind::Initialization)?
+        .to_string();
+    Ok((client, uri.scheme().to_string(), base_path))
+}
+
+impl ModuleRuntime for PodmanModuleRuntime {
+    type Error = Error;
+    type Config = DockerConfig;
+    type Module = DockerModule<UrlConnector>;
+    type ModuleRegistry = DockerModuleRuntime;
+    type Chunk = Chunk;
+    type Logs = Logs;
+
+    type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type GetFuture = <DockerModuleRuntime as ModuleRuntime>::GetFuture;
+    type ListFuture = <DockerModuleRuntime as ModuleRuntime>::ListFuture;
+    type ListWithDetailsStream = <DockerModuleRuntime as ModuleRuntime>::ListWithDetailsStream;
+    type LogsFuture = <DockerModuleRuntime as ModuleRuntime>::LogsFuture;
+    type RemoveFuture = <DockerModuleRuntime as ModuleRuntime>::RemoveFuture;
+    type RestartFuture = <DockerModuleRuntime as ModuleRuntime>::RestartFuture;
+    type StartFuture = <DockerModuleRuntime as ModuleRuntime>::StartFuture;
+    type StopFuture = <DockerModuleRuntime as ModuleRuntime>::StopFuture;
+    type SystemInfoFuture = <DockerModuleRuntime as ModuleRuntime>::SystemInfoFuture;
+    type SystemResourcesFuture = <DockerModuleRuntime as ModuleRuntime>::SystemResourcesFuture;
+    type TopFuture = <DockerModuleRuntime as ModuleRuntime>::TopFuture;
+    type RemoveAllFuture = <DockerModuleRuntime as ModuleRuntime>::RemoveAllFuture;
+    type ImageSizeFuture = <DockerModuleRuntime as ModuleRuntime>::ImageSizeFuture;
+    type PruneImagesFuture = <DockerModuleRuntime as ModuleRuntime>::PruneImagesFuture;
+
+    fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
+        info!("Creating module {}...", module.name());
+
+        // we only want "docker" modules
+        if module.type_() != DOCKER_MODULE_TYPE {
+            return Box::new(future::err(Error::from(ErrorKind::InvalidModuleType(
+                module.type_().to_string(),
+            ))));
+        }
+
+        let verification = self.docker.verify_module_image(&module);
+        let request = self
+            .docker
+            .create_options(&module)
+            .and_then(|create_options| {
+                let spec = container_spec(module.name(), &create_options)?;
+                self.create_request(module.name(), &spec)
+            });
+
+        let client = self.client.clone();
+        let name = module.name().to_string();
+        let context = move || ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(name));
+        let result = request
+            .map_err({
+                let context = context.clone();
+                move |err| Error::from(err.context(context()))
+            })
+            .into_future()
+            .and_then(move |request| {
+                verification.and_then(move |()| {
+                    client
+                        .request(request)
+                        .then(move |response| match response {
+                            Ok(response) => future::Either::A(check_response(response, context())),
+                            Err(err) => future::Either::B(future::err(Error::from_docker_error(
+                                DockerError::Hyper(err),
+                                context(),
+                            ))),
+                        })
+                })
+            })
+            .then(move |result| match result {
+                Ok(()) => {
+                    info!("Successfully created module {}", module.name());
+                    Ok(())
+                }
+                Err(err) => {
+                    log_failure(Level::Warn, &err);
+                    Err(err)
+                }
+            });
+
+        Box::new(result)
+    }
+
+    fn get(&self, id: &str) -> Self::GetFuture {
+        self.docker.get(id)
+    }
+
+    fn start(&self, id: &str) -> Self::StartFuture {
+        self.docker.start(id)
+    }
+
+    fn stop(&self, id: &str, wait_before_kill: Option<Duration>) -> Self::StopFuture {
+        self.docker.stop(id, wait_before_kill)
+    }
+
+    fn restart(&self, id: &str) -> Self::RestartFuture {
+        self.docker.restart(id)
+    }
+
+    fn remove(&self, id: &str) -> Self::RemoveFuture {
+        ModuleRuntime::remove(&self.docker, id)
+    }
+
+    fn system_info(&self) -> Self::SystemInfoFuture {
+        self.docker.system_info()
+    }
+
+    fn system_resources(&self) -> Self::SystemResourcesFuture {
+        self.docker.system_resources()
+    }
+
+    fn top(&self, id: &str) -> Self::TopFuture {
+        self.docker.top(id)
+    }
+
+    fn list(&self) -> Self::ListFuture {
+        self.docker.list()
+    }
+
+    fn list_with_details(&self) -> Self::ListWithDetailsStream {
+        self.docker.list_with_details()
+    }
+
+    fn logs(&self, id: &str, options: &LogOptions) -> Self::LogsFuture {
+        self.docker.logs(id, options)
+    }
+
+    fn registry(&self) -> &Self::ModuleRegistry {
+        &self.docker
+    }
+
+    fn remove_all(&self) -> Self::RemoveAllFuture {
+        self.docker.remove_all()
+    }
+
+    fn image_size(&self, image: &str) -> Self::ImageSizeFuture {
+        self.docker.image_size(image)
+    }
+
+    fn prune_images(&self, keep: &[String]) -> Self::PruneImagesFuture {
+        self.docker.prune_images(keep)
+    }
+}
+
+impl Authenticator for PodmanModuleRuntime {
+    type Error = Error;
+    type Request = Request<Body>;
+    type AuthenticateFuture = <DockerModuleRuntime as Authenticator>::AuthenticateFuture;
+
+    fn authenticate(&self, req: &Self::Request) -> Self::AuthenticateFuture {
+        self.docker.authenticate(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(create_options: Value) -> Value {
+        let create_options: ContainerCreateBody = serde_json::from_value(create_options).unwrap();
+        container_spec("m1", &create_options).unwrap()
+    }
+
+    #[test]
+    fn container_spec_converts_process_fields() {
+        let spec = spec(json!({
+            "Image": "nginx:latest",
+            "Cmd": ["nginx", "-g", "daemon off;"],
+            "Env": ["k1=v1", "k2=a=b", "k3"],
+            "WorkingDir": "/app",
+            "StopSignal": "SIGQUIT",
+            "Labels": { "l1": "v1" },
+        }));
+
+        assert_eq!("m1", spec["name"]);
+        assert_eq!("nginx:latest", spec["image"]);
+        assert_eq!(json!(["nginx", "-g", "daemon off;"]), spec["command"]);
+        assert_eq!(json!({ "k1": "v1", "k2": "a=b", "k3": "" }), spec["env"]);
+        assert_eq!("/app", spec["work_dir"]);
+        assert_eq!(3, spec["stop_signal"]);
+        assert_eq!(json!({ "l1": "v1" }), spec["labels"]);
+    }
+
+    #[test]
+    fn container_spec_converts_host_config() {
+        let spec = spec(json!({
+            "HostConfig": {
+                "Binds": ["/etc/iotedge:/etc/iotedge:ro", "data:/data"],
+                "Mounts": [{ "Type": "tmpfs", "Target": "/tmp", "TmpfsOptions": { "SizeBytes": 1024 } }],
+                "PortBindings": { "443/tcp": [{ "HostPort": "8443" }] },
+                "NetworkMode": "azure-iot-edge",
+                "Memory": 1_048_576,
+                "NanoCpus": 500_000_000,
+                "Privileged": true,
+            },
+            "NetworkingConfig": {
+                "EndpointsConfig": { "azure-iot-edge": { "Aliases": ["edgeHub"] } },
+            },
+        }));
+
+        assert_eq!(
+            json!([
+                { "type": "bind", "source": "/etc/iotedge", "destination": "/etc/iotedge", "options": ["ro"] },
+                { "type": "tmpfs", "source": "tmpfs", "destination": "/tmp", "options": ["size=1024"] },
+            ]),
+            spec["mounts"]
+        );
+        assert_eq!(
+            json!([{ "Name": "data", "Dest": "/data", "Options": [] }]),
+            spec["volumes"]
+        );
+        assert_eq!(
+            json!([{ "container_port": 443, "protocol": "tcp", "host_port": 8443 }]),
+            spec["portmappings"]
+        );
+        assert_eq!(json!({ "nsmode": "bridge" }), spec["netns"]);
+        assert_eq!(
+            json!({ "azure-iot-edge": { "aliases": ["edgeHub"] } }),
+            spec["Networks"]
+        );
+        assert_eq!(
+            json!({
+                "memory": { "limit": 1_048_576 },
+                "cpu": { "quota": 50_000, "period": 100_000 },
+            }),
+            spec["resource_limits"]
+        );
+        assert_eq!(true, spec["privileged"]);
+    }
+
+    #[test]
+    fn container_spec_uses_host_network() {
+        let spec = spec(json!({ "HostConfig": { "NetworkMode": "host", "PidMode": "host" } }));
+
+        assert_eq!(json!({ "nsmode": "host" }), spec["netns"]);
+        assert_eq!(json!({ "nsmode": "host" }), spec["pidns"]);
+        assert_eq!(Value::Null, spec["Networks"]);
+    }
+}
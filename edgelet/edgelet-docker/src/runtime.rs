@@ -1,7 +1,11 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::io;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use base64;
@@ -9,21 +13,31 @@ use failure::{Fail, ResultExt};
 use futures::future::Either;
 use futures::prelude::*;
 use futures::{future, stream, Async, Stream};
-use hyper::{Body, Chunk as HyperChunk, Client, Request};
+use hyper::{Body, Chunk as HyperChunk, Client, Request, Uri};
 use lazy_static::lazy_static;
-use log::{debug, info, Level};
+use log::{debug, info, warn, Level};
 use serde_json;
+use tokio::io::{AsyncRead, AsyncWrite};
 use url::Url;
 
 use docker::apis::client::APIClient;
 use docker::apis::configuration::Configuration;
-use docker::models::{ContainerCreateBody, InlineResponse200, Ipam, NetworkConfig};
+use docker::models::{
+    ContainerCreateBody, ExecConfig, ExecStartConfig, HostConfig, ImageSummary, InlineResponse200,
+    Ipam, Mount, MountTmpfsOptions, NetworkConfig,
+};
 use edgelet_core::{
-    AuthId, Authenticator, GetTrustBundle, Ipam as CoreIpam, LogOptions, MakeModuleRuntime,
-    MobyNetwork, Module, ModuleId, ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec,
-    RegistryOperation, RuntimeOperation, SystemInfo as CoreSystemInfo, SystemResources, UrlExt,
+    is_host_path_allowed, AuthId, Authenticator, Chunked, GetTrustBundle, ImageSizeInfo,
+    Ipam as CoreIpam, LogChunk, LogDecode, LogOptions, MakeModuleRuntime, MobyNetwork, Module,
+    ModuleId, ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec, PruneReport,
+    RegistryOperation, ResourceLimits, RuntimeOperation, SignatureVerificationConfig,
+    SystemInfo as CoreSystemInfo, SystemResources, TopResult, UrlExt, VolumeMount, VolumeSource,
+    WorkloadCapability,
 };
-use edgelet_http::{Pid, UrlConnector};
+use edgelet_http::signature::parse_reference;
+use edgelet_http::{ImageSignatureVerifier, Pid, UrlConnector};
+#[cfg(not(windows))]
+use edgelet_utils::parse_mem_available_kb;
 use edgelet_utils::{ensure_not_empty_with_context, log_failure};
 use provisioning::ProvisioningResult;
 
@@ -39,6 +53,8 @@ use crate::settings::Settings;
 use edgelet_core::DiskInfo;
 #[cfg(not(windows))]
 use std::convert::TryInto;
+#[cfg(not(windows))]
+use std::fs;
 #[cfg(target_os = "linux")]
 use std::mem;
 #[cfg(not(windows))]
@@ -52,6 +68,8 @@ type Deserializer = &'static mut serde_json::Deserializer<serde_json::de::IoRead
 
 static LABEL_KEY: &str = "net.azure-devices.edge.owner";
 static LABEL_VALUE: &str = "Microsoft.Azure.Devices.Edge.Agent";
+static CAPABILITIES_LABEL_KEY: &str = "net.azure-devices.edge.capabilities";
+static RUNTIME_LOG_LEVEL_KEY: &str = "RuntimeLogLevel";
 
 lazy_static! {
     static ref LABELS: Vec<&'static str> = {
@@ -64,6 +82,7 @@ lazy_static! {
 #[derive(Clone)]
 pub struct DockerModuleRuntime {
     client: DockerClient<UrlConnector>,
+    allowed_host_paths: Option<Vec<PathBuf>>,
 }
 
 impl DockerModuleRuntime {
@@ -88,6 +107,219 @@ impl DockerModuleRuntime {
             .map(|(key, value)| format!("{}={}", key, value))
             .collect()
     }
+
+    /// The body of the request that creates the container of `module`: its createOptions with
+    /// its image, environment, labels, resource limits and volumes applied.
+    pub(crate) fn create_options(
+        &self,
+        module: &ModuleSpec<DockerConfig>,
+    ) -> Result<ContainerCreateBody> {
+        let create_options = module.config().clone_create_options()?;
+
+        // merge environment variables, passing the module's log level along with them
+        let mut env = module.env().clone();
+        if let Some(log_level) = module.log_level() {
+            env.insert(RUNTIME_LOG_LEVEL_KEY.to_string(), log_level.to_string());
+        }
+        let merged_env = DockerModuleRuntime::merge_env(create_options.env(), &env);
+
+        let mut labels = create_options
+            .labels()
+            .cloned()
+            .unwrap_or_else(HashMap::new);
+        labels.insert(LABEL_KEY.to_string(), LABEL_VALUE.to_string());
+        if let Some(capabilities) = module.capabilities() {
+            let capabilities = serde_json::to_string(capabilities).with_context(|_| {
+                ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
+                    module.name().to_string(),
+                ))
+            })?;
+            labels.insert(CAPABILITIES_LABEL_KEY.to_string(), capabilities);
+        }
+
+        debug!(
+            "Creating container {} with image {}",
+            module.name(),
+            module.config().image()
+        );
+
+        let mut create_options = create_options
+            .with_image(module.config().image().to_string())
+            .with_env(merged_env)
+            .with_labels(labels);
+
+        // resource limits on the spec take precedence over the ones in createOptions
+        if !module.resource_limits().is_empty() {
+            let host_config = apply_resource_limits(
+                create_options
+                    .host_config()
+                    .cloned()
+                    .unwrap_or_else(HostConfig::new),
+                module.resource_limits(),
+                module.name(),
+            )?;
+            create_options.set_host_config(host_config);
+        }
+
+        if !module.volumes().is_empty() {
+            let host_config = apply_volumes(
+                create_options
+                    .host_config()
+                    .cloned()
+                    .unwrap_or_else(HostConfig::new),
+                module.volumes(),
+                self.allowed_host_paths.as_ref().map(AsRef::as_ref),
+                module.name(),
+            )?;
+            create_options.set_host_config(host_config);
+        }
+
+        Ok(create_options)
+    }
+
+    /// Verifies the signature of the image of `module`, if its spec asks for that.
+    pub(crate) fn verify_module_image(
+        &self,
+        module: &ModuleSpec<DockerConfig>,
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        match module.image_signature_verification() {
+            Some(config) => {
+                let name = module.name().to_string();
+                Either::A(
+                    verify_image_signature(&self.client, module.config().image(), config).map_err(
+                        |err| {
+                            Error::from(err.context(ErrorKind::RuntimeOperation(
+                                RuntimeOperation::CreateModule(name),
+                            )))
+                        },
+                    ),
+                )
+            }
+            None => Either::B(future::ok(())),
+        }
+    }
+
+    /// Runs `cmd` inside the running module `id` and writes the process' stdout and stderr to
+    /// `stdout`. If `stdin` is given, it is streamed to the process until it reaches EOF.
+    ///
+    /// Resolves to the exit code of the process, if Docker reported one.
+    pub fn exec<R, W>(
+        &self,
+        id: &str,
+        cmd: &[&str],
+        stdin: Option<R>,
+        stdout: W,
+    ) -> impl Future<Item = Option<i32>, Error = Error> + Send
+    where
+        R: AsyncRead + Send + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        info!("Executing command in module {}...", id);
+        let id = id.to_string();
+
+        if let Err(err) = ensure_not_empty_with_context(&id, || {
+            ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(id.clone()))
+        }) {
+            return Either::A(future::err(Error::from(err)));
+        }
+
+        let client = self.client.clone();
+        let exec_config = ExecConfig::new()
+            .with_attach_stdin(stdin.is_some())
+            .with_attach_stdout(true)
+            .with_attach_stderr(true)
+            .with_tty(false)
+            .with_cmd(cmd.iter().map(ToString::to_string).collect());
+
+        let result = self
+            .client
+            .exec_api()
+            .container_exec(&id, exec_config)
+            .then({
+                let id = id.clone();
+                move |result| {
+                    result.map_err(|err| {
+                        Error::from_docker_error(
+                            err,
+                            ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(id)),
+                        )
+                    })
+                }
+            })
+            .and_then(move |exec| {
+                let exec_id = exec.id().to_string();
+
+                let output = match stdin {
+                    Some(stdin) => Either::A(
+                        client
+                            .exec_api()
+                            .exec_start_upgrade(&exec_id, ExecStartConfig::new())
+                            .map_err(Either::A)
+                            .and_then(move |upgraded| {
+                                let (reader, writer) = upgraded.split();
+                                let input = tokio::io::copy(stdin, writer)
+                                    .and_then(|(_, _, writer)| tokio::io::shutdown(writer));
+                                input
+                                    .join(write_exec_output(reader, stdout))
+                                    .map(|_| ())
+                                    .map_err(Either::B)
+                            }),
+                    ),
+                    None => Either::B(
+                        client
+                            .exec_api()
+                            .exec_start(&exec_id, ExecStartConfig::new())
+                            .map_err(Either::A)
+                            .and_then(move |body| {
+                                let body =
+                                    body.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+                                write_exec_output(Chunked::new(body), stdout).map_err(Either::B)
+                            }),
+                    ),
+                };
+
+                output
+                    .and_then(move |()| client.exec_api().exec_inspect(&exec_id).map_err(Either::A))
+                    .then(move |result| match result {
+                        Ok(inspect) => {
+                            info!("Successfully executed command in module {}", id);
+                            Ok(inspect.exit_code())
+                        }
+                        Err(err) => {
+                            let context =
+                                ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(id));
+                            let err = match err {
+                                Either::A(err) => Error::from_docker_error(err, context),
+                                Either::B(err) => Error::from(err.context(context)),
+                            };
+                            log_failure(Level::Warn, &err);
+                            Err(err)
+                        }
+                    })
+            });
+
+        Either::B(result)
+    }
+}
+
+fn write_exec_output<R, W>(output: R, writer: W) -> impl Future<Item = (), Error = io::Error>
+where
+    R: AsyncRead,
+    W: AsyncWrite,
+{
+    // Without a TTY, Docker multiplexes stdout and stderr using the same framing as the logs API.
+    LogDecode::new(output)
+        .fold(writer, |writer, chunk| {
+            let payload = match chunk {
+                LogChunk::Stdin(b)
+                | LogChunk::Stdout(b)
+                | LogChunk::Stderr(b)
+                | LogChunk::Unknown(b) => b,
+            };
+            tokio::io::write_all(writer, payload).map(|(writer, _)| writer)
+        })
+        .and_then(tokio::io::flush)
+        .map(|_| ())
 }
 
 impl std::fmt::Debug for DockerModuleRuntime {
@@ -179,6 +411,17 @@ impl ModuleRegistry for DockerModuleRuntime {
     }
 }
 
+// A label that can't be parsed grants no capabilities rather than all of them.
+fn parse_capabilities(capabilities: &str) -> Vec<WorkloadCapability> {
+    serde_json::from_str(capabilities).unwrap_or_else(|err| {
+        warn!(
+            "Could not parse module capabilities {:?}: {}",
+            capabilities, err
+        );
+        vec![]
+    })
+}
+
 fn parse_get_response<'de, D>(resp: &InlineResponse200) -> std::result::Result<String, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -205,6 +448,11 @@ impl MakeModuleRuntime for DockerModuleRuntime {
     ) -> Self::Future {
         info!("Initializing module runtime...");
 
+        let allowed_host_paths = settings
+            .moby_runtime()
+            .allowed_host_paths()
+            .map(ToOwned::to_owned);
+
         // Clippy incorrectly flags the use of `.map(..).unwrap_or_else(..)` code as being replaceable
         // with `.ok().map_or_else`. This is incorrect because `.ok()` will result in the error being dropped.
         // So we suppress this lint. There's an open issue for this on the Clippy repo:
@@ -249,7 +497,10 @@ impl MakeModuleRuntime for DockerModuleRuntime {
                     })
                     .map(|client| {
                         info!("Successfully initialized module runtime");
-                        DockerModuleRuntime { client }
+                        DockerModuleRuntime {
+                            client,
+                            allowed_host_paths,
+                        }
                     });
 
                 future::Either::A(fut)
@@ -263,6 +514,288 @@ impl MakeModuleRuntime for DockerModuleRuntime {
     }
 }
 
+fn apply_resource_limits(
+    host_config: HostConfig,
+    limits: &ResourceLimits,
+    module_name: &str,
+) -> Result<HostConfig> {
+    let context =
+        || ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(module_name.to_string()));
+
+    let mut host_config = host_config;
+    if let Some(cpu_shares) = limits.cpu_shares() {
+        host_config.set_cpu_shares(i32::try_from(cpu_shares).with_context(|_| context())?);
+    }
+    if let Some(memory_bytes) = limits.memory_bytes() {
+        host_config.set_memory(i64::try_from(memory_bytes).with_context(|_| context())?);
+    }
+    if let Some(memory_swap_bytes) = limits.memory_swap_bytes() {
+        host_config.set_memory_swap(memory_swap_bytes);
+    }
+    if let Some(pids_limit) = limits.pids_limit() {
+        host_config.set_pids_limit(pids_limit);
+    }
+
+    Ok(host_config)
+}
+
+/// Adds the module's volumes to its host config. Host paths become `Binds` so that, as with
+/// binds given in `createOptions`, Docker creates the host directory if it doesn't exist.
+fn apply_volumes(
+    host_config: HostConfig,
+    volumes: &[VolumeMount],
+    allowed_host_paths: Option<&[PathBuf]>,
+    module_name: &str,
+) -> Result<HostConfig> {
+    let context =
+        || ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(module_name.to_string()));
+
+    let mut binds = host_config.binds().map_or_else(Vec::new, ToOwned::to_owned);
+    let mut mounts = host_config
+        .mounts()
+        .map_or_else(Vec::new, ToOwned::to_owned);
+
+    // host paths can also be bound through createOptions, either as binds or as bind mounts
+    if let Some(allowed_host_paths) = allowed_host_paths {
+        let host_paths =
+            binds
+                .iter()
+                .filter_map(|bind| bind_host_path(bind))
+                .chain(mounts.iter().filter_map(|mount| match mount._type() {
+                    Some("bind") => Some(Path::new(mount.source().unwrap_or_default())),
+                    _ => None,
+                }));
+        for path in host_paths {
+            if !is_host_path_allowed(path, allowed_host_paths) {
+                return Err(Error::from(
+                    ErrorKind::HostPathNotAllowed(path.to_path_buf()).context(context()),
+                ));
+            }
+        }
+    }
+
+    for volume in volumes {
+        let mount = |type_: &str| {
+            Mount::new()
+                .with__type(type_.to_string())
+                .with_target(volume.target().to_string())
+                .with_read_only(volume.read_only())
+        };
+        match volume.source() {
+            VolumeSource::NamedVolume(name) => {
+                mounts.push(mount("volume").with_source(name.to_string()));
+            }
+            VolumeSource::HostPath(path) => {
+                if let Some(allowed_host_paths) = allowed_host_paths {
+                    if !volume.source().is_allowed(allowed_host_paths) {
+                        return Err(Error::from(
+                            ErrorKind::HostPathNotAllowed(path.clone()).context(context()),
+                        ));
+                    }
+                }
+                let mode = if volume.read_only() { "ro" } else { "rw" };
+                binds.push(format!("{}:{}:{}", path.display(), volume.target(), mode));
+            }
+            VolumeSource::Tmpfs { size_bytes } => {
+                let size_bytes = i64::try_from(*size_bytes).with_context(|_| context())?;
+                mounts.push(
+                    mount("tmpfs")
+                        .with_tmpfs_options(MountTmpfsOptions::new().with_size_bytes(size_bytes)),
+                );
+            }
+        }
+    }
+
+    let mut host_config = host_config;
+    if !binds.is_empty() {
+        host_config.set_binds(binds);
+    }
+    if !mounts.is_empty() {
+        host_config.set_mounts(mounts);
+    }
+    Ok(host_config)
+}
+
+/// The host path of a bind in the `source:target[:options]` form that `HostConfig.Binds` uses,
+/// or `None` if the source is the name of a volume.
+fn bind_host_path(bind: &str) -> Option<&Path> {
+    // the source of a Windows bind can start with a drive letter, like C:\data:C:\data
+    let skip =
+        if bind.len() > 2 && bind.as_bytes()[1] == b':' && bind.as_bytes()[0].is_ascii_alphabetic()
+        {
+            2
+        } else {
+            0
+        };
+    let source = match bind[skip..].find(':') {
+        Some(index) => &bind[..skip + index],
+        None => bind,
+    };
+    let source = Path::new(source);
+    if source.is_absolute() {
+        Some(source)
+    } else {
+        None
+    }
+}
+
+/// Verifies the signature of the digest that `image` was pulled as. The image must already have
+/// been pulled, since the digest is read from it.
+fn verify_image_signature(
+    client: &DockerClient<UrlConnector>,
+    image: &str,
+    config: &SignatureVerificationConfig,
+) -> impl Future<Item = (), Error = Error> + Send {
+    let image = image.to_string();
+    let config = config.clone();
+    let signature_error = {
+        let image = image.clone();
+        move |err: edgelet_http::Error| Error::from(err.context(ErrorKind::ImageSignature(image)))
+    };
+
+    let verifier = https_proxy_uri(&image).and_then(|proxy_uri| {
+        ImageSignatureVerifier::from_proxy(proxy_uri).map_err(signature_error.clone())
+    });
+    let verifier = match verifier {
+        Ok(verifier) => verifier,
+        Err(err) => return Either::B(future::err(err)),
+    };
+
+    let inspect_error = {
+        let image = image.clone();
+        move |err| Error::from_docker_error(err, ErrorKind::ImageSignature(image))
+    };
+    Either::A(
+        client
+            .image_api()
+            .image_inspect(&image)
+            .map_err(inspect_error)
+            .and_then(move |inspected| {
+                match image_digest(&image, inspected.repo_digests().unwrap_or_default()) {
+                    Some(digest) => Ok((image, digest)),
+                    None => Err(Error::from(ErrorKind::ImageSignature(image))),
+                }
+            })
+            .and_then(move |(image, digest)| {
+                debug!("Verifying the signature of image {} ({})", image, digest);
+                verifier
+                    .verify(&image, &digest, &config)
+                    .map_err(signature_error)
+            }),
+    )
+}
+
+/// The digest that `image` was pulled as. This is the digest in the reference itself if it has
+/// one, and otherwise the one recorded by the runtime for the image's repository.
+fn image_digest(image: &str, repo_digests: &[String]) -> Option<String> {
+    if let Some(i) = image.find('@') {
+        return Some(image[i + 1..].to_string());
+    }
+
+    // Docker records images from Docker Hub under their short names
+    let (repository, _) = parse_reference(image);
+    let repository = repository
+        .trim_start_matches("docker.io/library/")
+        .trim_start_matches("docker.io/");
+
+    repo_digests.iter().find_map(|repo_digest| {
+        let mut parts = repo_digest.splitn(2, '@');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(digest)) if name == repository => Some(digest.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Whether any of `keep` refers to `image`, by its ID, one of its tags or one of its digests.
+fn is_image_kept(image: &ImageSummary, keep: &[String]) -> bool {
+    keep.iter().any(|reference| {
+        if reference == image.id() {
+            return true;
+        }
+
+        // Docker records images from Docker Hub under their short names
+        let reference = reference
+            .trim_start_matches("docker.io/library/")
+            .trim_start_matches("docker.io/");
+        let (repository, tag) = parse_reference(reference);
+
+        let tagged = tag.map_or(false, |tag| {
+            let repo_tag = format!("{}:{}", repository, tag);
+            image.repo_tags().iter().any(|t| *t == repo_tag)
+        });
+        let digested = reference.find('@').map_or(false, |i| {
+            let repo_digest = format!("{}{}", repository, &reference[i..]);
+            image.repo_digests().iter().any(|d| *d == repo_digest)
+        });
+        tagged || digested
+    })
+}
+
+/// Removes `image` by each of its tags, and returns its ID and size once it is gone. Docker
+/// refuses to remove an image that a container uses, in which case the image is left in place and
+/// `None` is returned.
+fn remove_unused_image(
+    client: &DockerClient<UrlConnector>,
+    image: &ImageSummary,
+) -> impl Future<Item = Option<(String, u64)>, Error = Error> + Send {
+    let mut names: Vec<String> = image
+        .repo_tags()
+        .iter()
+        .filter(|tag| *tag != "<none>:<none>")
+        .cloned()
+        .collect();
+    if names.is_empty() {
+        names.push(image.id().to_string());
+    }
+
+    let client = client.clone();
+    let id = image.id().to_string();
+    let size = u64::try_from(*image.size()).unwrap_or_default();
+
+    stream::iter_ok(names)
+        .for_each(move |name| {
+            client
+                .image_api()
+                .image_delete(&name, false, false)
+                .map(|_| ())
+                .map_err(|err| {
+                    Error::from_docker_error(
+                        err,
+                        ErrorKind::RegistryOperation(RegistryOperation::RemoveImage(name)),
+                    )
+                })
+        })
+        .then(move |result| match result {
+            Ok(()) => {
+                debug!("Removed unused image {}", id);
+                Ok(Some((id, size)))
+            }
+            Err(err) => match Fail::find_root_cause(&err).downcast_ref::<ErrorKind>() {
+                Some(ErrorKind::Conflict) => {
+                    debug!("Not removing image {} since it is in use", id);
+                    Ok(None)
+                }
+                // removed by someone else in the meantime
+                Some(ErrorKind::NotFound(_)) => Ok(None),
+                _ => Err(err),
+            },
+        })
+}
+
+fn https_proxy_uri(image: &str) -> Result<Option<Uri>> {
+    let proxy_uri = env::var("HTTPS_PROXY")
+        .or_else(|_| env::var("https_proxy"))
+        .ok()
+        .map(|proxy_uri| {
+            proxy_uri
+                .parse::<Uri>()
+                .with_context(|_| ErrorKind::ImageSignature(image.to_string()))
+        })
+        .transpose()?;
+    Ok(proxy_uri)
+}
+
 fn get_ipv6_settings(network_configuration: &MobyNetwork) -> (bool, Option<Ipam>) {
     if let MobyNetwork::Network(network) = network_configuration {
         let ipv6 = network.ipv6().unwrap_or_default();
@@ -319,7 +852,10 @@ impl ModuleRuntime for DockerModuleRuntime {
     type SystemInfoFuture = Box<dyn Future<Item = CoreSystemInfo, Error = Self::Error> + Send>;
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
+    type TopFuture = Box<dyn Future<Item = TopResult, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ImageSizeFuture = Box<dyn Future<Item = ImageSizeInfo, Error = Self::Error> + Send>;
+    type PruneImagesFuture = Box<dyn Future<Item = PruneReport, Error = Self::Error> + Send>;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         info!("Creating module {}...", module.name());
@@ -331,49 +867,29 @@ impl ModuleRuntime for DockerModuleRuntime {
             ))));
         }
 
-        let result = module
-            .config()
-            .clone_create_options()
-            .and_then(|create_options| {
-                // merge environment variables
-                let merged_env = DockerModuleRuntime::merge_env(create_options.env(), module.env());
-
-                let mut labels = create_options
-                    .labels()
-                    .cloned()
-                    .unwrap_or_else(HashMap::new);
-                labels.insert(LABEL_KEY.to_string(), LABEL_VALUE.to_string());
-
-                debug!(
-                    "Creating container {} with image {}",
-                    module.name(),
-                    module.config().image()
-                );
-
-                let create_options = create_options
-                    .with_image(module.config().image().to_string())
-                    .with_env(merged_env)
-                    .with_labels(labels);
-
+        let verification = self.verify_module_image(&module);
+        let client = self.client.clone();
+        let result = self
+            .create_options(&module)
+            .into_future()
+            .and_then(move |create_options| {
                 // Here we don't add the container to the iot edge docker network as the edge-agent is expected to do that.
                 // It contains the logic to add a container to the iot edge network only if a network is not already specified.
-
-                Ok(self
-                    .client
-                    .container_api()
-                    .container_create(create_options, module.name())
-                    .then(|result| match result {
-                        Ok(_) => Ok(module),
-                        Err(err) => Err(Error::from_docker_error(
-                            err,
-                            ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
-                                module.name().to_string(),
+                verification.and_then(move |()| {
+                    client
+                        .container_api()
+                        .container_create(create_options, module.name())
+                        .then(|result| match result {
+                            Ok(_) => Ok(module),
+                            Err(err) => Err(Error::from_docker_error(
+                                err,
+                                ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
+                                    module.name().to_string(),
+                                )),
                             )),
-                        )),
-                    }))
+                        })
+                })
             })
-            .into_future()
-            .flatten()
             .then(|result| match result {
                 Ok(module) => {
                     info!("Successfully created module {}", module.name());
@@ -417,10 +933,17 @@ impl ModuleRuntime for DockerModuleRuntime {
                                         id.clone(),
                                     ))
                                 })?;
-                        let module =
-                            DockerModule::new(client_copy, name, config).with_context(|_| {
+                        let capabilities = container
+                            .config()
+                            .and_then(|container_config| container_config.labels())
+                            .and_then(|labels| labels.get(CAPABILITIES_LABEL_KEY))
+                            .map(String::as_str)
+                            .map(parse_capabilities);
+                        let module = DockerModule::new(client_copy, name, config)
+                            .with_context(|_| {
                                 ErrorKind::RuntimeOperation(RuntimeOperation::GetModule(id.clone()))
-                            })?;
+                            })?
+                            .with_capabilities(capabilities);
                         let state = runtime_state(container.id(), container.state());
                         Ok((module, state))
                     }
@@ -674,8 +1197,28 @@ impl ModuleRuntime for DockerModuleRuntime {
                 .find(|p| p.get_name() == "cpu")
                 .map_or_else(|| -1.0, |p| p.get_cpu_usage());
 
+            // The processor list includes an entry named "cpu" that aggregates the others
+            let cpu_cores = system_info
+                .get_processor_list()
+                .iter()
+                .filter(|p| p.get_name() != "cpu")
+                .count();
+
+            // sysinfo reports the usage as a fraction
+            let cpu_usage_percent = if used_cpu < 0.0 {
+                -1.0
+            } else {
+                f64::from(used_cpu) * 100.0
+            };
+
             let total_memory = system_info.get_total_memory() * 1000;
             let used_memory = system_info.get_used_memory() * 1000;
+            let memory_total_kb = system_info.get_total_memory();
+            // The free memory doesn't count the caches the kernel can reclaim
+            let memory_available_kb = fs::read_to_string("/proc/meminfo")
+                .ok()
+                .and_then(|meminfo| parse_mem_available_kb(&meminfo))
+                .unwrap_or_else(|| system_info.get_free_memory());
 
             let disks = system_info
                 .get_disks()
@@ -701,6 +1244,9 @@ impl ModuleRuntime for DockerModuleRuntime {
                     disks,
                     stats,
                 )
+                .with_cpu_cores(cpu_cores)
+                .with_cpu_usage_percent(cpu_usage_percent)
+                .with_memory_kb(memory_total_kb, memory_available_kb)
             });
 
             Box::new(result)
@@ -717,6 +1263,37 @@ impl ModuleRuntime for DockerModuleRuntime {
         }
     }
 
+    fn top(&self, id: &str) -> Self::TopFuture {
+        debug!("Listing processes for module {}...", id);
+        let id = id.to_string();
+
+        if let Err(err) = ensure_not_empty_with_context(&id, || {
+            ErrorKind::RuntimeOperation(RuntimeOperation::TopModule(id.clone()))
+        }) {
+            return Box::new(future::err(Error::from(err)));
+        }
+
+        Box::new(
+            self.client
+                .container_api()
+                .container_top(&id, "")
+                .then(|result| match result {
+                    Ok(resp) => Ok(TopResult::new(
+                        resp.titles().map_or_else(Vec::new, ToOwned::to_owned),
+                        resp.processes().map_or_else(Vec::new, ToOwned::to_owned),
+                    )),
+                    Err(err) => {
+                        let err = Error::from_docker_error(
+                            err,
+                            ErrorKind::RuntimeOperation(RuntimeOperation::TopModule(id)),
+                        );
+                        log_failure(Level::Warn, &err);
+                        Err(err)
+                    }
+                }),
+        )
+    }
+
     fn list(&self) -> Self::ListFuture {
         debug!("Listing modules...");
 
@@ -834,6 +1411,95 @@ impl ModuleRuntime for DockerModuleRuntime {
             future::join_all(n).map(|_| ())
         }))
     }
+
+    fn image_size(&self, image: &str) -> Self::ImageSizeFuture {
+        debug!("Getting the size of image {}...", image);
+
+        let image = image.to_string();
+        Box::new(
+            self.client
+                .image_api()
+                .image_inspect(&image)
+                .then(move |result| match result {
+                    Ok(inspected) => {
+                        let size = ImageSizeInfo::new(
+                            u64::try_from(*inspected.size()).unwrap_or_default(),
+                            u64::try_from(*inspected.virtual_size()).unwrap_or_default(),
+                            inspected.root_fs().layers().map_or(0, <[String]>::len),
+                        );
+                        debug!("Successfully got the size of image {}", image);
+                        Ok(size)
+                    }
+                    Err(err) => {
+                        let err = Error::from_docker_error(
+                            err,
+                            ErrorKind::RuntimeOperation(RuntimeOperation::GetImageSize(image)),
+                        );
+                        log_failure(Level::Warn, &err);
+                        Err(err)
+                    }
+                }),
+        )
+    }
+
+    fn prune_images(&self, keep: &[String]) -> Self::PruneImagesFuture {
+        info!("Pruning unused images...");
+
+        let keep = keep.to_vec();
+        let client = self.client.clone();
+
+        let result = self
+            .client
+            .image_api()
+            .image_list(false, "", false)
+            .map_err(|err| {
+                Error::from_docker_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages),
+                )
+            })
+            .and_then(move |images| {
+                let unused: Vec<ImageSummary> = images
+                    .into_iter()
+                    .filter(|image| !is_image_kept(image, &keep))
+                    .collect();
+                stream::iter_ok(unused)
+                    .and_then(move |image| remove_unused_image(&client, &image))
+                    .fold(
+                        (vec![], 0),
+                        |(mut images_deleted, space_reclaimed), removed| {
+                            let space_reclaimed = match removed {
+                                Some((id, size)) => {
+                                    images_deleted.push(id);
+                                    space_reclaimed + size
+                                }
+                                None => space_reclaimed,
+                            };
+                            Ok::<_, Error>((images_deleted, space_reclaimed))
+                        },
+                    )
+                    .map_err(|err| {
+                        Error::from(
+                            err.context(ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages)),
+                        )
+                    })
+            })
+            .then(|result| match result {
+                Ok((images_deleted, space_reclaimed)) => {
+                    info!(
+                        "Successfully pruned {} images, reclaiming {} bytes",
+                        images_deleted.len(),
+                        space_reclaimed
+                    );
+                    Ok(PruneReport::new(images_deleted, space_reclaimed))
+                }
+                Err(err) => {
+                    log_failure(Level::Warn, &err);
+                    Err(err)
+                }
+            });
+        Box::new(result)
+    }
 }
 
 impl Authenticator for DockerModuleRuntime {
@@ -1046,8 +1712,9 @@ mod tests {
     use serde_json::{self, json, Value as JsonValue};
 
     use edgelet_core::{
-        Certificates, Connect, Listen, ModuleRegistry, ModuleTop, Provisioning, RuntimeSettings,
-        WatchdogSettings,
+        Certificates, Connect, Listen, ManagementAuthSettings, MetricsSettings, ModuleRegistry,
+        ModuleTop, Pkcs11Settings, Provisioning, RateLimitSettings, RuntimeSettings,
+        TracingSettings, WatchdogSettings,
     };
     use edgelet_test_utils::crypto::TestHsm;
     use provisioning::ReprovisioningStatus;
@@ -1139,6 +1806,60 @@ mod tests {
             .any(|err| err.to_string().contains("Socket file could not be found")));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn bind_host_path_skips_named_volumes() {
+        assert_eq!(Some(Path::new("/etc")), bind_host_path("/etc:/etc:ro"));
+        assert_eq!(Some(Path::new("/data")), bind_host_path("/data"));
+        assert_eq!(None, bind_host_path("data:/data"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn bind_host_path_skips_named_volumes() {
+        assert_eq!(
+            Some(Path::new("C:\\data")),
+            bind_host_path("C:\\data:C:\\data")
+        );
+        assert_eq!(None, bind_host_path("data:C:\\data"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_volumes_checks_host_paths_of_create_options() {
+        let allowed = vec![PathBuf::from("/var/lib/modules")];
+        let apply = |host_config| apply_volumes(host_config, &[], Some(&allowed), "m1");
+        let host_path_not_allowed = |err: Error| match err.cause().and_then(Fail::downcast_ref) {
+            Some(ErrorKind::HostPathNotAllowed(path)) => path.clone(),
+            _ => panic!("Expected a HostPathNotAllowed error. Got {:?}", err),
+        };
+
+        let binds = |binds: &[&str]| {
+            HostConfig::new().with_binds(binds.iter().map(ToString::to_string).collect())
+        };
+        assert!(apply(binds(&["/var/lib/modules/m1:/data", "data:/data"])).is_ok());
+        assert_eq!(
+            PathBuf::from("/etc"),
+            host_path_not_allowed(apply(binds(&["/etc:/etc:ro"])).unwrap_err())
+        );
+
+        let mount = |type_: &str, source: &str| {
+            HostConfig::new().with_mounts(vec![Mount::new()
+                .with__type(type_.to_string())
+                .with_source(source.to_string())
+                .with_target("/data".to_string())])
+        };
+        assert!(apply(mount("bind", "/var/lib/modules/m1")).is_ok());
+        assert!(apply(mount("volume", "etc")).is_ok());
+        assert_eq!(
+            PathBuf::from("/etc"),
+            host_path_not_allowed(apply(mount("bind", "/etc")).unwrap_err())
+        );
+
+        // without allowed paths, any host path can be bound
+        assert!(apply_volumes(binds(&["/etc:/etc"]), &[], None, "m1").is_ok());
+    }
+
     #[test]
     fn merge_env_empty() {
         let cur_env = Some(&[][..]);
@@ -1179,6 +1900,71 @@ mod tests {
         assert_eq!(vec!["k1=v1", "k2=v2", "k3=v3"], merged_env);
     }
 
+    #[test]
+    fn image_digest_is_read_from_reference_or_repo_digests() {
+        let digest = "sha256:8f434346648f6b96df89dda901c5176b10a6d83961dd3c1ac88b59b2dc327aa4";
+        let repo_digests = vec![
+            format!("registry:5000/other@{}", "sha256:00"),
+            format!("registry:5000/module1@{}", digest),
+            format!("alpine@{}", digest),
+        ];
+
+        assert_eq!(
+            Some(digest.to_string()),
+            image_digest("registry:5000/module1:1.0", &repo_digests)
+        );
+        assert_eq!(
+            Some(digest.to_string()),
+            image_digest("docker.io/library/alpine", &repo_digests)
+        );
+        assert_eq!(
+            Some("sha256:11".to_string()),
+            image_digest("registry:5000/module1@sha256:11", &repo_digests)
+        );
+        assert_eq!(
+            None,
+            image_digest("registry:5000/module2:1.0", &repo_digests)
+        );
+    }
+
+    #[test]
+    fn image_is_kept_by_id_tag_or_digest() {
+        let image = ImageSummary::new(
+            "sha256:1234".to_string(),
+            String::new(),
+            vec![
+                "registry:5000/module1:1.0".to_string(),
+                "alpine:latest".to_string(),
+            ],
+            vec!["registry:5000/module1@sha256:5678".to_string()],
+            0,
+            1024,
+            -1,
+            1024,
+            HashMap::new(),
+            -1,
+        );
+
+        assert!(is_image_kept(&image, &["sha256:1234".to_string()]));
+        assert!(is_image_kept(
+            &image,
+            &["registry:5000/module1:1.0".to_string()]
+        ));
+        assert!(is_image_kept(
+            &image,
+            &["docker.io/library/alpine".to_string()]
+        ));
+        assert!(is_image_kept(
+            &image,
+            &["registry:5000/module1@sha256:5678".to_string()]
+        ));
+        assert!(!is_image_kept(
+            &image,
+            &["registry:5000/module1:2.0".to_string()]
+        ));
+        assert!(!is_image_kept(&image, &[]));
+    }
+
     #[test]
     fn list_with_details_filters_out_deleted_containers() {
         let runtime = prepare_module_runtime_with_known_modules();
@@ -1364,6 +2150,30 @@ mod tests {
         fn watchdog(&self) -> &WatchdogSettings {
             unimplemented!()
         }
+
+        fn rate_limits(&self) -> &RateLimitSettings {
+            unimplemented!()
+        }
+
+        fn management_auth(&self) -> &ManagementAuthSettings {
+            unimplemented!()
+        }
+
+        fn metrics(&self) -> &MetricsSettings {
+            unimplemented!()
+        }
+
+        fn tracing(&self) -> &TracingSettings {
+            unimplemented!()
+        }
+
+        fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+            unimplemented!()
+        }
+
+        fn twin_cache_dir(&self) -> Option<&Path> {
+            unimplemented!()
+        }
     }
 
     #[derive(Clone, Copy, Debug, PartialEq)]
@@ -1483,7 +2293,10 @@ mod tests {
         type SystemInfoFuture = FutureResult<CoreSystemInfo, Self::Error>;
         type SystemResourcesFuture =
             Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
+        type TopFuture = FutureResult<TopResult, Self::Error>;
         type RemoveAllFuture = FutureResult<(), Self::Error>;
+        type ImageSizeFuture = FutureResult<ImageSizeInfo, Self::Error>;
+        type PruneImagesFuture = FutureResult<PruneReport, Self::Error>;
 
         fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
             unimplemented!()
@@ -1517,6 +2330,10 @@ mod tests {
             unimplemented!()
         }
 
+        fn top(&self, _id: &str) -> Self::TopFuture {
+            unimplemented!()
+        }
+
         fn list(&self) -> Self::ListFuture {
             future::ok(self.modules.clone())
         }
@@ -1536,6 +2353,14 @@ mod tests {
         fn remove_all(&self) -> Self::RemoveAllFuture {
             unimplemented!()
         }
+
+        fn image_size(&self, _image: &str) -> Self::ImageSizeFuture {
+            unimplemented!()
+        }
+
+        fn prune_images(&self, _keep: &[String]) -> Self::PruneImagesFuture {
+            unimplemented!()
+        }
     }
 
     impl Authenticator for TestModuleList {
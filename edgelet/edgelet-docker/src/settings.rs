@@ -1,13 +1,14 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use config::{Config, Environment};
 use docker::models::{ContainerCreateBodyNetworkingConfig, EndpointSettings, HostConfig};
 use edgelet_core::{
-    Certificates, Connect, Listen, MobyNetwork, ModuleSpec, Provisioning, RuntimeSettings,
-    Settings as BaseSettings, UrlExt, WatchdogSettings,
+    Certificates, Connect, Listen, ManagementAuthSettings, MetricsSettings, MobyNetwork,
+    ModuleSpec, Pkcs11Settings, Provisioning, RateLimitSettings, RuntimeSettings,
+    Settings as BaseSettings, TracingSettings, UrlExt, WatchdogSettings,
 };
 use edgelet_utils::YamlFileSource;
 use failure::{Context, Fail, ResultExt};
@@ -33,6 +34,8 @@ pub struct MobyRuntime {
     #[serde(with = "url_serde")]
     uri: Url,
     network: MobyNetwork,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_host_paths: Option<Vec<PathBuf>>,
 }
 
 impl MobyRuntime {
@@ -43,6 +46,12 @@ impl MobyRuntime {
     pub fn network(&self) -> &MobyNetwork {
         &self.network
     }
+
+    /// The host directories that modules' `hostPath` volumes must be under. `None` allows any
+    /// host path.
+    pub fn allowed_host_paths(&self) -> Option<&[PathBuf]> {
+        self.allowed_host_paths.as_ref().map(AsRef::as_ref)
+    }
 }
 
 /// This struct is the same as the Settings type from the `edgelet_core` crate
@@ -114,6 +123,30 @@ impl RuntimeSettings for Settings {
     fn watchdog(&self) -> &WatchdogSettings {
         self.base.watchdog()
     }
+
+    fn rate_limits(&self) -> &RateLimitSettings {
+        self.base.rate_limits()
+    }
+
+    fn management_auth(&self) -> &ManagementAuthSettings {
+        self.base.management_auth()
+    }
+
+    fn metrics(&self) -> &MetricsSettings {
+        self.base.metrics()
+    }
+
+    fn tracing(&self) -> &TracingSettings {
+        self.base.tracing()
+    }
+
+    fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+        self.base.pkcs11()
+    }
+
+    fn twin_cache_dir(&self) -> Option<&Path> {
+        self.base.twin_cache_dir()
+    }
 }
 
 fn init_agent_spec(settings: &mut Settings) -> Result<(), LoadSettingsError> {
@@ -265,7 +298,8 @@ mod tests {
     use tempdir::TempDir;
 
     use edgelet_core::{
-        AttestationMethod, IpamConfig, ManualAuthMethod, ProvisioningType, DEFAULT_NETWORKID,
+        validate_config, AttestationMethod, IpamConfig, ManualAuthMethod, ProvisioningType,
+        DEFAULT_NETWORKID,
     };
 
     #[cfg(unix)]
@@ -281,6 +315,10 @@ mod tests {
     #[cfg(unix)]
     static GOOD_SETTINGS_DPS_SYM_KEY: &str = "test/linux/sample_settings.dps.sym.yaml";
     #[cfg(unix)]
+    static GOOD_SETTINGS_DPS_FIDO2: &str = "test/linux/sample_settings.dps.fido2.yaml";
+    #[cfg(unix)]
+    static GOOD_SETTINGS_PKCS11: &str = "test/linux/sample_settings.pkcs11.yaml";
+    #[cfg(unix)]
     static GOOD_SETTINGS_CASE_SENSITIVE: &str = "test/linux/case_sensitive.yaml";
     #[cfg(unix)]
     static GOOD_SETTINGS_DPS_TPM: &str = "test/linux/sample_settings.dps.tpm.yaml";
@@ -345,6 +383,10 @@ mod tests {
     #[cfg(windows)]
     static GOOD_SETTINGS_DPS_SYM_KEY: &str = "test/windows/sample_settings.dps.sym.yaml";
     #[cfg(windows)]
+    static GOOD_SETTINGS_DPS_FIDO2: &str = "test/windows/sample_settings.dps.fido2.yaml";
+    #[cfg(windows)]
+    static GOOD_SETTINGS_PKCS11: &str = "test/windows/sample_settings.pkcs11.yaml";
+    #[cfg(windows)]
     static GOOD_SETTINGS_CASE_SENSITIVE: &str = "test/windows/case_sensitive.yaml";
     #[cfg(windows)]
     static GOOD_SETTINGS_DPS_TPM: &str = "test/windows/sample_settings.dps.tpm.yaml";
@@ -416,12 +458,14 @@ mod tests {
         let moby1 = MobyRuntime {
             uri: Url::parse("http://test").unwrap(),
             network: MobyNetwork::Name("".to_string()),
+            allowed_host_paths: None,
         };
         assert_eq!(DEFAULT_NETWORKID, moby1.network().name());
 
         let moby2 = MobyRuntime {
             uri: Url::parse("http://test").unwrap(),
             network: MobyNetwork::Name("some-network".to_string()),
+            allowed_host_paths: None,
         };
         assert_eq!("some-network", moby2.network().name());
     }
@@ -572,6 +616,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn validate_config_accepts_good_settings() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_MANUAL_CS_AUTH)).unwrap();
+
+        assert_eq!(
+            Vec::<edgelet_core::ConfigError>::new(),
+            validate_config(&settings)
+        );
+    }
+
+    #[test]
+    fn validate_config_reports_empty_connection_string() {
+        let settings = Settings::new(Path::new(BAD_SETTINGS_MANUAL_CS3)).unwrap();
+
+        let errors = validate_config(&settings);
+
+        assert_eq!(1, errors.len());
+        assert_eq!("provisioning.device_connection_string", errors[0].setting());
+    }
+
     #[test]
     fn manual_authentication_bad_connection_string_fails() {
         let settings = Settings::new(Path::new(BAD_SETTINGS_MANUAL_CS_AUTH2));
@@ -762,6 +826,39 @@ mod tests {
             .expect("certificates not configured");
     }
 
+    #[test]
+    fn validate_config_reports_every_bad_certificate() {
+        let tmp_dir = TempDir::new("blah").unwrap();
+        let ca_cert_path = tmp_dir.path().join("device_ca_cert.pem");
+        let ca_key_path = tmp_dir.path().join("device_ca_pk.pem");
+        let trust_bundle_path = tmp_dir.path().join("trusted_ca_certs.pem");
+        let settings_path = tmp_dir.path().join("test_settings.yaml");
+        prepare_test_gateway_x509_certificate_settings_yaml(
+            &settings_path,
+            &ca_cert_path,
+            &ca_key_path,
+            &trust_bundle_path,
+            false,
+        );
+        std::fs::remove_file(&ca_key_path).unwrap();
+        let settings = Settings::new(&settings_path).unwrap();
+
+        let errors = validate_config(&settings);
+
+        let settings: Vec<_> = errors.iter().map(|err| err.setting()).collect();
+        assert_eq!(
+            vec![
+                "certificates.device_ca_cert",
+                "certificates.device_ca_pk",
+                "certificates.trusted_ca_certs",
+            ],
+            settings
+        );
+        assert!(errors[0].message().contains("PEM"));
+        assert!(errors[1].message().ends_with("does not exist"));
+        assert!(errors[2].message().contains("PEM"));
+    }
+
     #[test]
     fn manual_x509_authentication() {
         let tmp_dir = TempDir::new("blah").unwrap();
@@ -872,6 +969,45 @@ mod tests {
         };
     }
 
+    #[test]
+    fn dps_prov_fido2_get_settings() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_DPS_FIDO2)).unwrap();
+        match settings.provisioning().provisioning_type() {
+            ProvisioningType::Dps(ref dps) => match dps.attestation() {
+                AttestationMethod::Fido2(ref fido2) => {
+                    assert_eq!(fido2.registration_id(), "register me fool");
+                    assert_eq!(fido2.relying_party_id(), None);
+                    assert_eq!(fido2.pin(), Some("1234"));
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn pkcs11_get_settings() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_PKCS11)).unwrap();
+        let pkcs11 = settings.pkcs11().unwrap();
+        if cfg!(windows) {
+            assert_eq!(
+                pkcs11.lib_path(),
+                Path::new(r"C:\SoftHSM2\lib\softhsm2-x64.dll")
+            );
+        } else {
+            assert_eq!(
+                pkcs11.lib_path(),
+                Path::new("/usr/lib/softhsm/libsofthsm2.so")
+            );
+        }
+        assert_eq!(pkcs11.token_label(), "iotedge");
+        assert_eq!(pkcs11.pin(), "1234");
+        assert_eq!(pkcs11.key_label(), "iotedge-device");
+
+        let settings = Settings::new(Path::new(GOOD_SETTINGS)).unwrap();
+        assert!(settings.pkcs11().is_none());
+    }
+
     fn prepare_test_dps_x509_settings_yaml(
         settings_path: &Path,
         cert_path: &Path,
@@ -1080,6 +1216,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ct_log_url_is_none_by_default() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS)).unwrap();
+        assert!(settings.certificates().ct_log_url().is_none());
+    }
+
     #[test]
     fn networking_config_is_set() {
         let settings = Settings::new(Path::new(GOOD_SETTINGS)).unwrap();
@@ -5,6 +5,7 @@
 #![allow(clippy::too_many_lines)]
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
@@ -27,9 +28,10 @@ use docker::models::{
 
 use edgelet_core::{
     GetTrustBundle, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module,
-    ModuleRegistry, ModuleRuntime, ModuleSpec, RegistryOperation, RuntimeOperation,
+    ModuleRegistry, ModuleRuntime, ModuleSpec, RegistryOperation, ResourceLimits, RuntimeOperation,
+    VolumeMount, VolumeSource, WorkloadCapability,
 };
-use edgelet_docker::{DockerConfig, DockerModuleRuntime, Settings};
+use edgelet_docker::{DockerConfig, DockerModuleRuntime, PodmanModuleRuntime, Settings};
 use edgelet_docker::{Error, ErrorKind};
 use edgelet_test_utils::crypto::TestHsm;
 use edgelet_test_utils::web::{
@@ -712,6 +714,173 @@ fn image_remove_succeeds() {
     runtime.block_on(task).unwrap();
 }
 
+fn image_inspect_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::GET);
+    assert_eq!(req.uri().path(), &format!("/images/{}/json", IMAGE_NAME));
+
+    let response = json!({
+        "Id": "sha256:1234",
+        "Parent": "",
+        "Comment": "",
+        "Created": "2019-11-05T10:00:00Z",
+        "Container": "",
+        "DockerVersion": "18.09.7",
+        "Author": "",
+        "Architecture": "amd64",
+        "Os": "linux",
+        "Size": 52_428_800,
+        "VirtualSize": 131_072_000,
+        "GraphDriver": {
+            "Name": "overlay2"
+        },
+        "RootFS": {
+            "Type": "layers",
+            "Layers": [
+                "sha256:aaaa",
+                "sha256:bbbb"
+            ]
+        }
+    })
+    .to_string();
+    let response_len = response.len();
+
+    let mut response = Response::new(response.into());
+    response
+        .headers_mut()
+        .typed_insert(&ContentLength(response_len as u64));
+    response
+        .headers_mut()
+        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+    Box::new(future::ok(response))
+}
+
+#[test]
+fn image_size_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET format!("/images/{}/json", IMAGE_NAME) => image_inspect_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.image_size(IMAGE_NAME));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    let size = runtime.block_on(task).unwrap();
+
+    assert_eq!(52_428_800, size.compressed_bytes());
+    assert_eq!(131_072_000, size.uncompressed_bytes());
+    assert_eq!(2, size.layers());
+}
+
+fn image_list_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::GET);
+    assert_eq!(req.uri().path(), "/images/json");
+
+    let response = json!([
+        {
+            "Id": "sha256:1111",
+            "ParentId": "",
+            "RepoTags": [IMAGE_NAME],
+            "RepoDigests": [],
+            "Created": 0,
+            "Size": 1024,
+            "SharedSize": -1,
+            "VirtualSize": 1024,
+            "Labels": {},
+            "Containers": -1
+        },
+        {
+            "Id": "sha256:2222",
+            "ParentId": "",
+            "RepoTags": ["nginx:1.0"],
+            "RepoDigests": [],
+            "Created": 0,
+            "Size": 2048,
+            "SharedSize": -1,
+            "VirtualSize": 2048,
+            "Labels": {},
+            "Containers": -1
+        }
+    ])
+    .to_string();
+    let response_len = response.len();
+
+    let mut response = Response::new(response.into());
+    response
+        .headers_mut()
+        .typed_insert(&ContentLength(response_len as u64));
+    response
+        .headers_mut()
+        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+    Box::new(future::ok(response))
+}
+
+fn unused_image_remove_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::DELETE);
+    assert_eq!(req.uri().path(), "/images/nginx:1.0");
+
+    let response = serde_json::to_string(&vec![
+        ImageDeleteResponseItem::new().with_deleted("sha256:2222".to_string())
+    ])
+    .unwrap();
+    let response_len = response.len();
+
+    let mut response = Response::new(response.into());
+    response
+        .headers_mut()
+        .typed_insert(&ContentLength(response_len as u64));
+    response
+        .headers_mut()
+        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+    Box::new(future::ok(response))
+}
+
+#[test]
+fn prune_images_removes_images_not_kept() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET "/images/json" => image_list_handler,
+        DELETE "/images/nginx:1.0" => unused_image_remove_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.prune_images(&[IMAGE_NAME.to_string()]));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    let report = runtime.block_on(task).unwrap();
+
+    assert_eq!(&["sha256:2222".to_string()], report.images_deleted());
+    assert_eq!(2048, report.space_reclaimed());
+}
+
 fn container_create_handler(req: Request<Body>) -> ResponseFuture {
     assert_eq!(req.method(), &Method::POST);
     assert_eq!(req.uri().path(), "/containers/create");
@@ -870,19 +1039,54 @@ fn container_create_succeeds() {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn container_start_handler(req: Request<Body>) -> ResponseFuture {
+fn container_create_with_resource_limits_handler(req: Request<Body>) -> ResponseFuture {
     assert_eq!(req.method(), &Method::POST);
-    assert_eq!(req.uri().path(), "/containers/m1/start");
+    assert_eq!(req.uri().path(), "/containers/create");
 
-    Box::new(future::ok(Response::new(Body::empty())))
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: ContainerCreateBody =
+                    serde_json::from_slice(body.as_ref()).unwrap();
+
+                let host_config = create_options.host_config().unwrap();
+                assert_eq!(Some(512), host_config.cpu_shares());
+                assert_eq!(Some(268_435_456), host_config.memory());
+                assert_eq!(Some(-1), host_config.memory_swap());
+                assert_eq!(Some(100), host_config.pids_limit());
+
+                // settings from createOptions that are not resource limits are preserved
+                assert!(host_config.port_bindings().unwrap().contains_key("80/tcp"));
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
 }
 
 #[test]
-fn container_start_succeeds() {
+fn container_create_with_resource_limits_succeeds() {
     let dispatch_table = routes!(
         GET "/networks" => default_get_networks_handler(),
         POST "/networks/create" => default_create_network_handler(),
-        POST "/containers/m1/start" => container_start_handler,
+        POST "/containers/create" => container_create_with_resource_limits_handler,
     );
 
     let (server, port) = run_tcp_server(
@@ -898,27 +1102,90 @@ fn container_start_succeeds() {
     })));
 
     let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
-        .and_then(|runtime| runtime.start("m1"));
+        .and_then(|runtime| {
+            let mut port_bindings = HashMap::new();
+            port_bindings.insert(
+                "80/tcp".to_string(),
+                vec![HostConfigPortBindings::new().with_host_port("8080".to_string())],
+            );
+            let create_options = ContainerCreateBody::new().with_host_config(
+                HostConfig::new()
+                    .with_port_bindings(port_bindings)
+                    .with_memory(3_221_225_472),
+            );
+
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), create_options, None).unwrap(),
+                HashMap::new(),
+                ImagePullPolicy::default(),
+            )
+            .unwrap()
+            .with_resource_limits(
+                ResourceLimits::new()
+                    .with_cpu_shares(512)
+                    .with_memory_bytes(268_435_456)
+                    .with_memory_swap_bytes(-1)
+                    .with_pids_limit(100),
+            );
+
+            runtime.create(module_config)
+        });
 
     let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
     runtime.spawn(server);
     runtime.block_on(task).unwrap();
 }
 
-#[allow(clippy::needless_pass_by_value)]
-fn container_stop_handler(req: Request<Body>) -> ResponseFuture {
+fn container_create_with_healthcheck_handler(req: Request<Body>) -> ResponseFuture {
     assert_eq!(req.method(), &Method::POST);
-    assert_eq!(req.uri().path(), "/containers/m1/stop");
+    assert_eq!(req.uri().path(), "/containers/create");
 
-    Box::new(future::ok(Response::new(Body::empty())))
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: JsonValue = serde_json::from_slice(body.as_ref()).unwrap();
+
+                assert_eq!(
+                    json!({
+                        "Test": ["CMD", "curl", "-f", "http://localhost/"],
+                        "Interval": 30_000_000_000_i64,
+                        "Timeout": 5_000_000_000_i64,
+                        "Retries": 3,
+                    }),
+                    create_options["Healthcheck"]
+                );
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
 }
 
 #[test]
-fn container_stop_succeeds() {
+fn container_create_with_healthcheck_succeeds() {
     let dispatch_table = routes!(
         GET "/networks" => default_get_networks_handler(),
         POST "/networks/create" => default_create_network_handler(),
-        POST "/containers/m1/stop" => container_stop_handler,
+        POST "/containers/create" => container_create_with_healthcheck_handler,
     );
 
     let (server, port) = run_tcp_server(
@@ -934,7 +1201,36 @@ fn container_stop_succeeds() {
     })));
 
     let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
-        .and_then(|runtime| runtime.stop("m1", None));
+        .and_then(|runtime| {
+            let config: DockerConfig = serde_json::from_value(json!({
+                "image": "nginx:latest",
+                "createOptions": {
+                    "Healthcheck": {
+                        "Test": ["CMD", "curl", "-f", "http://localhost/"],
+                        "Interval": 30_000_000_000_i64,
+                        "Timeout": 5_000_000_000_i64,
+                        "Retries": 3,
+                    },
+                },
+            }))
+            .unwrap();
+
+            // Docker expresses health check durations in nanoseconds
+            let healthcheck = config.create_options().healthcheck().unwrap();
+            assert_eq!(Some(30_000_000_000), healthcheck.interval());
+            assert_eq!(Some(3), healthcheck.retries());
+
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                config,
+                HashMap::new(),
+                ImagePullPolicy::default(),
+            )
+            .unwrap();
+
+            runtime.create(module_config)
+        });
 
     let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
     runtime.spawn(server);
@@ -942,20 +1238,49 @@ fn container_stop_succeeds() {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn container_stop_with_timeout_handler(req: Request<Body>) -> ResponseFuture {
+fn container_create_with_log_level_handler(req: Request<Body>) -> ResponseFuture {
     assert_eq!(req.method(), &Method::POST);
-    assert_eq!(req.uri().path(), "/containers/m1/stop");
-    assert_eq!(req.uri().query().unwrap(), "t=600");
+    assert_eq!(req.uri().path(), "/containers/create");
 
-    Box::new(future::ok(Response::new(Body::empty())))
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: ContainerCreateBody =
+                    serde_json::from_slice(body.as_ref()).unwrap();
+
+                let env = create_options.env().unwrap();
+                assert!(env.contains(&"RuntimeLogLevel=debug".to_string()));
+                assert!(env.contains(&"k1=v1".to_string()));
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
 }
 
 #[test]
-fn container_stop_with_timeout_succeeds() {
+fn container_create_with_log_level_succeeds() {
     let dispatch_table = routes!(
         GET "/networks" => default_get_networks_handler(),
         POST "/networks/create" => default_create_network_handler(),
-        POST "/containers/m1/stop" => container_stop_with_timeout_handler,
+        POST "/containers/create" => container_create_with_log_level_handler,
     );
 
     let (server, port) = run_tcp_server(
@@ -971,11 +1296,740 @@ fn container_stop_with_timeout_succeeds() {
     })));
 
     let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
-        .and_then(|runtime| runtime.stop("m1", Some(Duration::from_secs(600))));
+        .and_then(|runtime| {
+            let mut env = HashMap::new();
+            env.insert("k1".to_string(), "v1".to_string());
 
-    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
-    runtime.spawn(server);
-    runtime.block_on(task).unwrap();
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), ContainerCreateBody::new(), None)
+                    .unwrap(),
+                env,
+                ImagePullPolicy::default(),
+            )
+            .unwrap()
+            .with_log_level(Some("debug".to_string()));
+
+            runtime.create(module_config)
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_create_with_capabilities_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/create");
+
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: ContainerCreateBody =
+                    serde_json::from_slice(body.as_ref()).unwrap();
+
+                let labels = create_options.labels().unwrap();
+                assert_eq!(
+                    Some(&r#"["sign","getCertificate"]"#.to_string()),
+                    labels.get("net.azure-devices.edge.capabilities")
+                );
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
+}
+
+#[test]
+fn container_create_with_capabilities_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/create" => container_create_with_capabilities_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| {
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), ContainerCreateBody::new(), None)
+                    .unwrap(),
+                HashMap::new(),
+                ImagePullPolicy::default(),
+            )
+            .unwrap()
+            .with_capabilities(Some(vec![
+                WorkloadCapability::Sign,
+                WorkloadCapability::GetCertificate,
+            ]));
+
+            runtime.create(module_config)
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_inspect_with_capabilities_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::GET);
+    assert_eq!(req.uri().path(), "/containers/m1/json");
+
+    let response = json!({
+        "Id": "12345",
+        "Name": "/m1",
+        "Config": {
+            "Labels": {
+                "net.azure-devices.edge.owner": "Microsoft.Azure.Devices.Edge.Agent",
+                "net.azure-devices.edge.capabilities": "[\"decrypt\"]"
+            }
+        },
+        "State": {
+            "Status": "running"
+        }
+    })
+    .to_string();
+    let response_len = response.len();
+
+    let mut response = Response::new(response.into());
+    response
+        .headers_mut()
+        .typed_insert(&ContentLength(response_len as u64));
+    response
+        .headers_mut()
+        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+    Box::new(future::ok(response))
+}
+
+#[test]
+fn container_get_reads_capabilities() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET "/containers/m1/json" => container_inspect_with_capabilities_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.get("m1"))
+        .map(|(module, _)| {
+            assert_eq!(
+                Some(&[WorkloadCapability::Decrypt][..]),
+                module.capabilities()
+            );
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_create_with_volumes_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/create");
+
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: ContainerCreateBody =
+                    serde_json::from_slice(body.as_ref()).unwrap();
+
+                let host_config = create_options.host_config().unwrap();
+                assert_eq!(
+                    Some(&["/var/lib/modules/m1:/cfg:ro".to_string()][..]),
+                    host_config.binds()
+                );
+
+                let mounts = host_config.mounts().unwrap();
+                assert_eq!(2, mounts.len());
+                assert_eq!(Some("volume"), mounts[0]._type());
+                assert_eq!(Some("data"), mounts[0].source());
+                assert_eq!(Some("/data"), mounts[0].target());
+                assert_eq!(Some(&false), mounts[0].read_only());
+                assert_eq!(Some("tmpfs"), mounts[1]._type());
+                assert_eq!(Some("/tmp"), mounts[1].target());
+                assert_eq!(Some(1024), mounts[1].tmpfs_options().unwrap().size_bytes());
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
+}
+
+fn module_with_volumes(host_path: &str) -> ModuleSpec<DockerConfig> {
+    ModuleSpec::new(
+        "m1".to_string(),
+        "docker".to_string(),
+        DockerConfig::new("nginx:latest".to_string(), ContainerCreateBody::new(), None).unwrap(),
+        HashMap::new(),
+        ImagePullPolicy::default(),
+    )
+    .unwrap()
+    .with_volumes(vec![
+        VolumeMount::new(
+            VolumeSource::NamedVolume("data".to_string()),
+            "/data".to_string(),
+        ),
+        VolumeMount::new(
+            VolumeSource::HostPath(PathBuf::from(host_path)),
+            "/cfg".to_string(),
+        )
+        .with_read_only(true),
+        VolumeMount::new(VolumeSource::Tmpfs { size_bytes: 1024 }, "/tmp".to_string()),
+    ])
+}
+
+#[test]
+fn container_create_with_volumes_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/create" => container_create_with_volumes_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port),
+            "allowed_host_paths": ["/var/lib/modules"]
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.create(module_with_volumes("/var/lib/modules/m1")));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[test]
+fn container_create_with_disallowed_host_path_fails() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port),
+            "allowed_host_paths": ["/var/lib/modules"]
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.create(module_with_volumes("/etc")));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    let err = runtime
+        .block_on(task)
+        .expect_err("Expected create to fail because of the host path");
+
+    match (err.kind(), err.cause().and_then(Fail::downcast_ref)) {
+        (
+            ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(name)),
+            Some(ErrorKind::HostPathNotAllowed(path)),
+        ) if name == "m1" => assert_eq!(&PathBuf::from("/etc"), path),
+        _ => panic!("Expected a HostPathNotAllowed error. Got {:?}", err),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn libpod_container_create_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/libpod/containers/create");
+    assert_eq!(req.uri().query(), Some("name=m1"));
+
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let spec: JsonValue = serde_json::from_slice(body.as_ref()).unwrap();
+
+                assert_eq!("m1", spec["name"]);
+                assert_eq!("nginx:latest", spec["image"]);
+                assert_eq!("v1", spec["env"]["k1"]);
+                assert_eq!("v4", spec["env"]["k4"]);
+                assert_eq!(
+                    "Microsoft.Azure.Devices.Edge.Agent",
+                    spec["labels"]["net.azure-devices.edge.owner"]
+                );
+                assert_eq!(
+                    json!([{ "container_port": 80, "protocol": "tcp", "host_port": 8080 }]),
+                    spec["portmappings"]
+                );
+                assert_eq!(
+                    3_221_225_472_i64,
+                    spec["resource_limits"]["memory"]["limit"]
+                );
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                *response.status_mut() = StatusCode::CREATED;
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
+}
+
+#[test]
+fn podman_container_create_uses_libpod() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/libpod/containers/create" => libpod_container_create_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = PodmanModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| {
+            let mut env = HashMap::new();
+            env.insert("k1".to_string(), "v1".to_string());
+
+            let mut port_bindings = HashMap::new();
+            port_bindings.insert(
+                "80/tcp".to_string(),
+                vec![HostConfigPortBindings::new().with_host_port("8080".to_string())],
+            );
+            let create_options = ContainerCreateBody::new()
+                .with_host_config(
+                    HostConfig::new()
+                        .with_port_bindings(port_bindings)
+                        .with_memory(3_221_225_472),
+                )
+                .with_env(vec!["k4=v4".to_string()]);
+
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), create_options, None).unwrap(),
+                env,
+                ImagePullPolicy::default(),
+            )
+            .unwrap();
+
+            runtime.create(module_config)
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[test]
+fn podman_container_create_reports_missing_image() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = PodmanModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| {
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), ContainerCreateBody::new(), None)
+                    .unwrap(),
+                HashMap::new(),
+                ImagePullPolicy::default(),
+            )
+            .unwrap();
+
+            runtime.create(module_config)
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    let err = runtime
+        .block_on(task)
+        .expect_err("Expected create to fail because libpod returned 404");
+
+    match err.kind() {
+        ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(name)) if name == "m1" => (),
+        _ => panic!("Expected a CreateModule error. Got {:?}", err),
+    }
+}
+
+fn container_start_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/m1/start");
+
+    Box::new(future::ok(Response::new(Body::empty())))
+}
+
+#[test]
+fn container_start_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/m1/start" => container_start_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.start("m1"));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_stop_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/m1/stop");
+
+    Box::new(future::ok(Response::new(Body::empty())))
+}
+
+#[test]
+fn container_stop_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/m1/stop" => container_stop_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.stop("m1", None));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_stop_with_timeout_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/m1/stop");
+    assert_eq!(req.uri().query().unwrap(), "t=600");
+
+    Box::new(future::ok(Response::new(Body::empty())))
+}
+
+#[test]
+fn container_stop_with_timeout_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/m1/stop" => container_stop_with_timeout_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.stop("m1", Some(Duration::from_secs(600))));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_restart_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/m1/restart");
+
+    Box::new(future::ok(Response::new(Body::empty())))
+}
+
+#[test]
+fn container_restart_succeeds() {
+    let restart_count_lock = Arc::new(RwLock::new(0));
+    let restart_count_lock_cloned = restart_count_lock.clone();
+
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/m1/restart" => move |req| {
+            *restart_count_lock.write().unwrap() += 1;
+            container_restart_handler(req)
+        },
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.restart("m1"));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+
+    assert_eq!(1, *restart_count_lock_cloned.read().unwrap());
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn container_exec_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/containers/m1/exec");
+
+    let response = req
+        .into_body()
+        .concat2()
+        .and_then(|body| {
+            let exec_config: JsonValue = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json!(["echo", "hello"]), exec_config["Cmd"]);
+            assert_eq!(json!(false), exec_config["AttachStdin"]);
+            assert_eq!(json!(true), exec_config["AttachStdout"]);
+
+            let response = json!({ "Id": "e1" }).to_string();
+            let response_len = response.len();
+            let mut response = Response::new(response.into());
+            response
+                .headers_mut()
+                .typed_insert(&ContentLength(response_len as u64));
+            response
+                .headers_mut()
+                .typed_insert(&ContentType(mime::APPLICATION_JSON));
+            Ok(response)
+        })
+        .map_err(|err| panic!(err));
+    Box::new(response)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn exec_start_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::POST);
+    assert_eq!(req.uri().path(), "/exec/e1/start");
+
+    // "hello\n" on stdout using Docker's multiplexed stream framing
+    let output = vec![
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, b'h', b'e', b'l', b'l', b'o', b'\n',
+    ];
+    Box::new(future::ok(Response::new(output.into())))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn exec_inspect_handler(req: Request<Body>) -> ResponseFuture {
+    assert_eq!(req.method(), &Method::GET);
+    assert_eq!(req.uri().path(), "/exec/e1/json");
+
+    let response = json!({ "ID": "e1", "Running": false, "ExitCode": 3 }).to_string();
+    let response_len = response.len();
+    let mut response = Response::new(response.into());
+    response
+        .headers_mut()
+        .typed_insert(&ContentLength(response_len as u64));
+    response
+        .headers_mut()
+        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+    Box::new(future::ok(response))
+}
+
+#[test]
+fn container_exec_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/m1/exec" => container_exec_handler,
+        POST "/exec/e1/start" => exec_start_handler,
+        GET "/exec/e1/json" => exec_inspect_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let output = Arc::new(RwLock::new(Vec::new()));
+    let output_copy = output.clone();
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| {
+            runtime.exec(
+                "m1",
+                &["echo", "hello"],
+                None::<&'static [u8]>,
+                SharedWriter(output_copy),
+            )
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    let exit_code = runtime.block_on(task).unwrap();
+
+    assert_eq!(Some(3), exit_code);
+    assert_eq!(b"hello\n", &output.read().unwrap()[..]);
+}
+
+struct SharedWriter(Arc<RwLock<Vec<u8>>>);
+
+impl std::io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl tokio::io::AsyncWrite for SharedWriter {
+    fn shutdown(&mut self) -> futures::Poll<(), std::io::Error> {
+        Ok(futures::Async::Ready(()))
+    }
 }
 
 #[allow(clippy::needless_pass_by_value)]
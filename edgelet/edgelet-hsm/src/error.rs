@@ -23,6 +23,10 @@ pub enum ErrorKind {
     EmptyStrings,
     #[fail(display = "Only Device keys are allowed to be activated")]
     NoModuleActivation,
+    #[fail(display = "PKCS#11 failure: {}", _0)]
+    Pkcs11(&'static str),
+    #[fail(display = "The key was not found on the PKCS#11 token")]
+    Pkcs11KeyNotFound,
 }
 
 impl Fail for Error {
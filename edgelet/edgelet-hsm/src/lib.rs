@@ -15,11 +15,15 @@ use std::sync::{Arc, Mutex};
 mod certificate_properties;
 mod crypto;
 mod error;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
 pub mod tpm;
 pub mod x509;
 
 pub use crypto::{Certificate, Crypto};
 pub use error::{Error, ErrorKind};
+#[cfg(feature = "pkcs11")]
+pub use pkcs11::{Pkcs11Config, Pkcs11Key, Pkcs11KeyStore, Pkcs11Library, Pkcs11Slot};
 pub use tpm::{TpmKey, TpmKeyStore};
 pub use x509::X509;
 
@@ -0,0 +1,452 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Private key operations with a PKCS#11 library, such as SoftHSM2 or a TPM2-based provider.
+//!
+//! Key pairs are generated on the token as sensitive, non-extractable objects, so the private
+//! key never leaves the token. Signing and decryption are performed by the token itself.
+
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::Arc;
+
+use ::pkcs11::types::{
+    CKA_CLASS, CKA_DECRYPT, CKA_ENCRYPT, CKA_EXTRACTABLE, CKA_LABEL, CKA_MODULUS, CKA_MODULUS_BITS,
+    CKA_PRIVATE, CKA_PUBLIC_EXPONENT, CKA_SENSITIVE, CKA_SIGN, CKA_TOKEN, CKA_VERIFY,
+    CKF_RW_SESSION, CKF_SERIAL_SESSION, CKM_RSA_PKCS, CKM_RSA_PKCS_KEY_PAIR_GEN,
+    CKM_SHA256_RSA_PKCS, CKO_PRIVATE_KEY, CKO_PUBLIC_KEY, CKU_SO, CKU_USER, CK_ATTRIBUTE,
+    CK_ATTRIBUTE_TYPE, CK_BBOOL, CK_FALSE, CK_MECHANISM, CK_MECHANISM_TYPE, CK_OBJECT_CLASS,
+    CK_OBJECT_HANDLE, CK_SESSION_HANDLE, CK_SLOT_ID, CK_TRUE, CK_ULONG, CK_VOID_PTR,
+};
+use ::pkcs11::Ctx;
+use failure::{Fail, ResultExt};
+
+use edgelet_core::crypto::{KeyIdentity, KeyStore as CoreKeyStore, Sign, SignatureAlgorithm};
+use edgelet_core::{Error as CoreError, ErrorKind as CoreErrorKind};
+
+pub use crate::error::{Error, ErrorKind};
+use crate::HsmLock;
+
+const RSA_MODULUS_BITS: CK_ULONG = 2048;
+const RSA_PUBLIC_EXPONENT: [u8; 3] = [0x01, 0x00, 0x01];
+
+/// Where to find the PKCS#11 library and the token that holds the keys.
+#[derive(Clone, Debug)]
+pub struct Pkcs11Config {
+    lib_path: PathBuf,
+    token_label: String,
+    pin: String,
+}
+
+impl Pkcs11Config {
+    pub fn new(lib_path: PathBuf, token_label: String, pin: String) -> Self {
+        Pkcs11Config {
+            lib_path,
+            token_label,
+            pin,
+        }
+    }
+
+    pub fn lib_path(&self) -> &Path {
+        &self.lib_path
+    }
+
+    pub fn token_label(&self) -> &str {
+        &self.token_label
+    }
+}
+
+/// A slot of the PKCS#11 library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pkcs11Slot {
+    id: CK_SLOT_ID,
+    /// `None` if the slot has no initialized token.
+    token_label: Option<String>,
+}
+
+impl Pkcs11Slot {
+    pub fn id(&self) -> CK_SLOT_ID {
+        self.id
+    }
+
+    pub fn token_label(&self) -> Option<&str> {
+        self.token_label.as_ref().map(AsRef::as_ref)
+    }
+}
+
+/// A loaded and initialized PKCS#11 library.
+///
+/// PKCS#11 libraries can only be initialized once per process, so the library should be loaded
+/// once and shared by everything that uses it.
+#[derive(Clone)]
+pub struct Pkcs11Library {
+    ctx: Arc<Ctx>,
+    hsm_lock: Arc<HsmLock>,
+}
+
+// pkcs11::Ctx is a table of function pointers into the library, which are safe to call from
+// any thread. Calls are serialized by Pkcs11Library::hsm_lock regardless, since not every
+// library supports concurrent use of one session.
+//
+// For the same reason, Pkcs11Library also needs an explicit Send impl
+// since Arc<T>: Send requires T: Send + Sync.
+unsafe impl Send for Pkcs11Library {}
+unsafe impl Sync for Pkcs11Library {}
+
+impl Pkcs11Library {
+    pub fn load(lib_path: &Path, hsm_lock: Arc<HsmLock>) -> Result<Self, Error> {
+        let ctx = Ctx::new_and_initialize(lib_path)
+            .context(ErrorKind::Pkcs11("could not load the PKCS#11 library"))?;
+        Ok(Pkcs11Library {
+            ctx: Arc::new(ctx),
+            hsm_lock,
+        })
+    }
+
+    /// Lists all the slots of the library, including those without an initialized token.
+    pub fn slots(&self) -> Result<Vec<Pkcs11Slot>, Error> {
+        let _hsm_lock = self.hsm_lock.0.lock().expect("Acquiring HSM lock failed");
+
+        let slots = self
+            .ctx
+            .get_slot_list(false)
+            .context(ErrorKind::Pkcs11("could not list slots"))?;
+
+        slots
+            .into_iter()
+            .map(|id| {
+                let token_label = match self.ctx.get_token_info(id) {
+                    Ok(info) => Some(String::from_utf8_lossy(&info.label).trim_end().to_string()),
+                    Err(_) => None,
+                };
+                Ok(Pkcs11Slot { id, token_label })
+            })
+            .collect()
+    }
+
+    /// Initializes the token in `slot` with `token_label`, and sets the user PIN that
+    /// `Pkcs11KeyStore::open` logs in with.
+    pub fn init_token(
+        &self,
+        slot: CK_SLOT_ID,
+        token_label: &str,
+        so_pin: &str,
+        pin: &str,
+    ) -> Result<(), Error> {
+        let _hsm_lock = self.hsm_lock.0.lock().expect("Acquiring HSM lock failed");
+
+        self.ctx
+            .init_token(slot, Some(so_pin), token_label)
+            .context(ErrorKind::Pkcs11("could not initialize the token"))?;
+
+        let session = self
+            .ctx
+            .open_session(slot, CKF_SERIAL_SESSION | CKF_RW_SESSION, None, None)
+            .context(ErrorKind::Pkcs11("could not open a session"))?;
+        let result = self
+            .ctx
+            .login(session, CKU_SO, Some(so_pin))
+            .and_then(|_| self.ctx.init_pin(session, Some(pin)))
+            .context(ErrorKind::Pkcs11("could not set the user PIN"));
+        let _ = self.ctx.close_session(session);
+        result?;
+
+        Ok(())
+    }
+}
+
+/// A session with a token, logged in as the user.
+struct Session {
+    library: Pkcs11Library,
+    handle: CK_SESSION_HANDLE,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _hsm_lock = self
+            .library
+            .hsm_lock
+            .0
+            .lock()
+            .expect("Acquiring HSM lock failed");
+        let _ = self.library.ctx.logout(self.handle);
+        let _ = self.library.ctx.close_session(self.handle);
+    }
+}
+
+/// The key pairs on a PKCS#11 token, identified by their labels.
+#[derive(Clone)]
+pub struct Pkcs11KeyStore {
+    session: Arc<Session>,
+}
+
+impl Pkcs11KeyStore {
+    /// Logs in to the token with the label and PIN from `config`.
+    pub fn open(library: &Pkcs11Library, config: &Pkcs11Config) -> Result<Self, Error> {
+        let slot = library
+            .slots()?
+            .into_iter()
+            .find(|slot| slot.token_label() == Some(config.token_label()))
+            .ok_or_else(|| ErrorKind::Pkcs11("no token has the configured label"))?;
+
+        let _hsm_lock = library
+            .hsm_lock
+            .0
+            .lock()
+            .expect("Acquiring HSM lock failed");
+        let handle = library
+            .ctx
+            .open_session(slot.id(), CKF_SERIAL_SESSION | CKF_RW_SESSION, None, None)
+            .context(ErrorKind::Pkcs11("could not open a session"))?;
+        if let Err(err) = library.ctx.login(handle, CKU_USER, Some(&config.pin)) {
+            let _ = library.ctx.close_session(handle);
+            return Err(Error::from(
+                err.context(ErrorKind::Pkcs11("could not log in to the token")),
+            ));
+        }
+
+        Ok(Pkcs11KeyStore {
+            session: Arc::new(Session {
+                library: library.clone(),
+                handle,
+            }),
+        })
+    }
+
+    /// Generates an RSA key pair on the token. The private key is sensitive and can't be
+    /// extracted from the token.
+    pub fn generate_key_pair(&self, label: &str) -> Result<Pkcs11Key, Error> {
+        let _hsm_lock = self.lock();
+
+        let mechanism = mechanism(CKM_RSA_PKCS_KEY_PAIR_GEN);
+        let public_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_LABEL).with_bytes(label.as_bytes()),
+            CK_ATTRIBUTE::new(CKA_VERIFY).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_ENCRYPT).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_MODULUS_BITS).with_ck_ulong(&RSA_MODULUS_BITS),
+            CK_ATTRIBUTE::new(CKA_PUBLIC_EXPONENT).with_bytes(&RSA_PUBLIC_EXPONENT),
+        ];
+        let private_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_LABEL).with_bytes(label.as_bytes()),
+            CK_ATTRIBUTE::new(CKA_PRIVATE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_SENSITIVE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_EXTRACTABLE).with_bool(&CK_FALSE),
+            CK_ATTRIBUTE::new(CKA_SIGN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_DECRYPT).with_bool(&CK_TRUE),
+        ];
+
+        let (public_key, private_key) = self
+            .session
+            .library
+            .ctx
+            .generate_key_pair(
+                self.session.handle,
+                &mechanism,
+                &public_template,
+                &private_template,
+            )
+            .context(ErrorKind::Pkcs11("could not generate the key pair"))?;
+
+        Ok(Pkcs11Key {
+            store: self.clone(),
+            public_key,
+            private_key,
+        })
+    }
+
+    /// Gets the key pair with `label`.
+    pub fn get(&self, label: &str) -> Result<Pkcs11Key, Error> {
+        let _hsm_lock = self.lock();
+
+        let public_key = self.find_object(CKO_PUBLIC_KEY, label)?;
+        let private_key = self.find_object(CKO_PRIVATE_KEY, label)?;
+        Ok(Pkcs11Key {
+            store: self.clone(),
+            public_key,
+            private_key,
+        })
+    }
+
+    fn find_object(&self, class: CK_OBJECT_CLASS, label: &str) -> Result<CK_OBJECT_HANDLE, Error> {
+        let ctx = &self.session.library.ctx;
+        let template = vec![
+            CK_ATTRIBUTE::new(CKA_CLASS).with_ck_ulong(&class),
+            CK_ATTRIBUTE::new(CKA_LABEL).with_bytes(label.as_bytes()),
+        ];
+
+        ctx.find_objects_init(self.session.handle, &template)
+            .context(ErrorKind::Pkcs11("could not search for the key"))?;
+        let objects = ctx.find_objects(self.session.handle, 1);
+        let _ = ctx.find_objects_final(self.session.handle);
+
+        objects
+            .context(ErrorKind::Pkcs11("could not search for the key"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::from(ErrorKind::Pkcs11KeyNotFound))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.session
+            .library
+            .hsm_lock
+            .0
+            .lock()
+            .expect("Acquiring HSM lock failed")
+    }
+}
+
+/// An RSA key pair on a PKCS#11 token.
+#[derive(Clone)]
+pub struct Pkcs11Key {
+    store: Pkcs11KeyStore,
+    public_key: CK_OBJECT_HANDLE,
+    private_key: CK_OBJECT_HANDLE,
+}
+
+impl Pkcs11Key {
+    /// Signs the SHA-256 digest of `data` with RSASSA-PKCS1-v1_5.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let _hsm_lock = self.store.lock();
+        let session = &self.store.session;
+
+        session
+            .library
+            .ctx
+            .sign_init(
+                session.handle,
+                &mechanism(CKM_SHA256_RSA_PKCS),
+                self.private_key,
+            )
+            .and_then(|_| session.library.ctx.sign(session.handle, data))
+            .context(ErrorKind::Pkcs11("could not sign"))
+            .map_err(Error::from)
+    }
+
+    /// Decrypts `ciphertext` that was encrypted with the public key with RSAES-PKCS1-v1_5.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let _hsm_lock = self.store.lock();
+        let session = &self.store.session;
+
+        session
+            .library
+            .ctx
+            .decrypt_init(session.handle, &mechanism(CKM_RSA_PKCS), self.private_key)
+            .and_then(|_| session.library.ctx.decrypt(session.handle, ciphertext))
+            .context(ErrorKind::Pkcs11("could not decrypt"))
+            .map_err(Error::from)
+    }
+
+    /// Returns the modulus and the public exponent of the public key, as big-endian bytes.
+    pub fn public_key(&self) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let _hsm_lock = self.store.lock();
+        let session = &self.store.session;
+
+        // The first call only returns the lengths of the values
+        let mut lengths = vec![
+            CK_ATTRIBUTE::new(CKA_MODULUS),
+            CK_ATTRIBUTE::new(CKA_PUBLIC_EXPONENT),
+        ];
+        session
+            .library
+            .ctx
+            .get_attribute_value(session.handle, self.public_key, &mut lengths)
+            .context(ErrorKind::Pkcs11("could not read the public key"))?;
+
+        #[allow(clippy::cast_possible_truncation)]
+        let mut modulus = vec![0_u8; lengths[0].ulValueLen as usize];
+        #[allow(clippy::cast_possible_truncation)]
+        let mut exponent = vec![0_u8; lengths[1].ulValueLen as usize];
+        let mut values = vec![
+            output_attribute(CKA_MODULUS, &mut modulus),
+            output_attribute(CKA_PUBLIC_EXPONENT, &mut exponent),
+        ];
+        session
+            .library
+            .ctx
+            .get_attribute_value(session.handle, self.public_key, &mut values)
+            .context(ErrorKind::Pkcs11("could not read the public key"))?;
+
+        Ok((modulus, exponent))
+    }
+
+    /// Whether the private key can be read from the token. Keys generated by
+    /// `Pkcs11KeyStore::generate_key_pair` never can.
+    pub fn is_extractable(&self) -> Result<bool, Error> {
+        let _hsm_lock = self.store.lock();
+        let session = &self.store.session;
+
+        let mut extractable: CK_BBOOL = CK_FALSE;
+        let mut values = vec![output_attribute(
+            CKA_EXTRACTABLE,
+            std::slice::from_mut(&mut extractable),
+        )];
+        session
+            .library
+            .ctx
+            .get_attribute_value(session.handle, self.private_key, &mut values)
+            .context(ErrorKind::Pkcs11("could not read the key attributes"))?;
+
+        Ok(extractable == CK_TRUE)
+    }
+}
+
+impl CoreKeyStore for Pkcs11KeyStore {
+    type Key = Pkcs11Key;
+
+    /// Gets the key pair of the device, labelled `key_name`, or of a module, labelled with the
+    /// module name followed by `key_name`.
+    fn get(&self, identity: &KeyIdentity, key_name: &str) -> Result<Self::Key, CoreError> {
+        let label = match *identity {
+            KeyIdentity::Device => key_name.to_string(),
+            KeyIdentity::Module(ref m) => format!("{}{}", m, key_name),
+        };
+        if key_name.is_empty() {
+            Err(ErrorKind::EmptyStrings)
+                .map_err(|err| Error::from(err.context(ErrorKind::Hsm)))
+                .map_err(|err| CoreError::from(err.context(CoreErrorKind::KeyStore)))?;
+        }
+
+        Pkcs11KeyStore::get(self, &label).map_err(|err| {
+            let kind = match err.kind() {
+                ErrorKind::Pkcs11KeyNotFound => CoreErrorKind::KeyStoreItemNotFound,
+                _ => CoreErrorKind::KeyStore,
+            };
+            CoreError::from(err.context(kind))
+        })
+    }
+}
+
+impl Sign for Pkcs11Key {
+    type Signature = Vec<u8>;
+
+    /// Sign data with this key. Like `TpmKey`, the signature is made with the algorithm of the
+    /// key on the token, RSASSA-PKCS1-v1_5 with SHA-256, whatever `signature_algorithm` is.
+    fn sign(
+        &self,
+        _signature_algorithm: SignatureAlgorithm,
+        data: &[u8],
+    ) -> Result<Self::Signature, CoreError> {
+        Pkcs11Key::sign(self, data)
+            .map_err(|err| CoreError::from(err.context(CoreErrorKind::KeyStore)))
+    }
+}
+
+/// An attribute for `get_attribute_value` to write the value into `buf`.
+#[allow(clippy::cast_possible_truncation)]
+fn output_attribute(attr_type: CK_ATTRIBUTE_TYPE, buf: &mut [u8]) -> CK_ATTRIBUTE {
+    CK_ATTRIBUTE {
+        attrType: attr_type,
+        pValue: buf.as_mut_ptr() as CK_VOID_PTR,
+        ulValueLen: buf.len() as CK_ULONG,
+    }
+}
+
+fn mechanism(mechanism: CK_MECHANISM_TYPE) -> CK_MECHANISM {
+    CK_MECHANISM {
+        mechanism,
+        pParameter: ptr::null_mut(),
+        ulParameterLen: 0,
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Runs against SoftHSM2. The path of the library can be set with `SOFTHSM2_LIB`.
+
+#![cfg(feature = "pkcs11")]
+#![deny(unused_extern_crates, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(clippy::must_use_candidate)]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::Verifier;
+use tempfile::TempDir;
+
+use edgelet_hsm::{ErrorKind, HsmLock, Pkcs11Config, Pkcs11KeyStore, Pkcs11Library};
+
+const DEFAULT_SOFTHSM2_LIB: &str = "/usr/lib/softhsm/libsofthsm2.so";
+const TOKEN_LABEL: &str = "iotedge-test";
+const SO_PIN: &str = "12345678";
+const PIN: &str = "1234";
+
+// PKCS#11 libraries can only be initialized once per process, so everything is tested in one
+// test.
+//
+// This tests the following:
+//  1) A new token can be initialized in a free slot, and is then listed with its label.
+//  2) A key pair generated on the token can be found again by its label, and its private key
+//     can't be extracted.
+//  3) Signatures made by the token verify with the public key.
+//  4) Data encrypted with the public key is decrypted by the token.
+#[test]
+fn pkcs11_softhsm_key_operations() {
+    // arrange
+    let token_dir = TempDir::new().unwrap();
+    let conf = token_dir.path().join("softhsm2.conf");
+    fs::write(
+        &conf,
+        format!(
+            "directories.tokendir = {}\nobjectstore.backend = file\n",
+            token_dir.path().display()
+        ),
+    )
+    .unwrap();
+    env::set_var("SOFTHSM2_CONF", &conf);
+
+    let lib_path = env::var_os("SOFTHSM2_LIB")
+        .map_or_else(|| PathBuf::from(DEFAULT_SOFTHSM2_LIB), PathBuf::from);
+    let library = Pkcs11Library::load(&lib_path, HsmLock::new()).unwrap();
+
+    let free_slot = library
+        .slots()
+        .unwrap()
+        .into_iter()
+        .find(|slot| slot.token_label().map_or(true, str::is_empty))
+        .unwrap();
+    library
+        .init_token(free_slot.id(), TOKEN_LABEL, SO_PIN, PIN)
+        .unwrap();
+    assert!(library
+        .slots()
+        .unwrap()
+        .iter()
+        .any(|slot| slot.token_label() == Some(TOKEN_LABEL)));
+
+    let config = Pkcs11Config::new(lib_path, TOKEN_LABEL.to_string(), PIN.to_string());
+    let key_store = Pkcs11KeyStore::open(&library, &config).unwrap();
+
+    // act
+    key_store.generate_key_pair("device-id").unwrap();
+    let key = key_store.get("device-id").unwrap();
+
+    let data = b"I am the very model of a modern major general";
+    let signature = key.sign(data).unwrap();
+
+    let (modulus, exponent) = key.public_key().unwrap();
+    let public_key = Rsa::from_public_components(
+        BigNum::from_slice(&modulus).unwrap(),
+        BigNum::from_slice(&exponent).unwrap(),
+    )
+    .unwrap();
+
+    let secret = b"the plaintext";
+    let mut ciphertext = vec![0; public_key.size() as usize];
+    let len = public_key
+        .public_encrypt(secret, &mut ciphertext, Padding::PKCS1)
+        .unwrap();
+    ciphertext.truncate(len);
+    let plaintext = key.decrypt(&ciphertext).unwrap();
+
+    // assert
+    assert!(!key.is_extractable().unwrap());
+
+    let public_key = PKey::from_rsa(public_key).unwrap();
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key).unwrap();
+    verifier.update(data).unwrap();
+    assert!(verifier.verify(&signature).unwrap());
+
+    assert_eq!(&secret[..], &plaintext[..]);
+
+    let err = key_store.get("no-such-key").err().unwrap();
+    assert_eq!(&ErrorKind::Pkcs11KeyNotFound, err.kind());
+}
@@ -1,5 +1,6 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::convert::TryInto;
 use std::fmt;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -13,6 +14,7 @@ use hyper::{Body, Chunk as HyperChunk, Client};
 use management::apis::client::APIClient;
 use management::apis::configuration::Configuration;
 use management::models::{Config, ModuleDetails as HttpModuleDetails};
+use serde::{Serialize, Serializer};
 use serde_json;
 use url::Url;
 
@@ -66,6 +68,16 @@ impl Clone for ModuleClient {
 #[derive(Clone, Debug)]
 pub struct ModuleDetails(HttpModuleDetails, ModuleConfig);
 
+/// Serializes as returned by the management API's `GET /modules`.
+impl Serialize for ModuleDetails {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ModuleConfig(String, Config);
 
@@ -166,7 +178,10 @@ impl ModuleRuntime for ModuleClient {
     type SystemInfoFuture = Box<dyn Future<Item = CoreSystemInfo, Error = Self::Error> + Send>;
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
+    type TopFuture = Box<dyn Future<Item = TopResult, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ImageSizeFuture = Box<dyn Future<Item = ImageSizeInfo, Error = Self::Error> + Send>;
+    type PruneImagesFuture = Box<dyn Future<Item = PruneReport, Error = Self::Error> + Send>;
 
     fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         unimplemented!()
@@ -257,6 +272,10 @@ impl ModuleRuntime for ModuleClient {
         unimplemented!()
     }
 
+    fn top(&self, _id: &str) -> Self::TopFuture {
+        unimplemented!()
+    }
+
     fn list(&self) -> Self::ListFuture {
         let modules = self
             .client
@@ -345,6 +364,49 @@ impl ModuleRuntime for ModuleClient {
             future::join_all(n).map(|_| ())
         }))
     }
+
+    fn image_size(&self, image: &str) -> Self::ImageSizeFuture {
+        let image = image.to_string();
+
+        let size = self
+            .client
+            .image_api()
+            .get_image_size(&API_VERSION.to_string(), &image)
+            .map(|size| {
+                ImageSizeInfo::new(
+                    size.compressed_bytes().try_into().unwrap_or_default(),
+                    size.uncompressed_bytes().try_into().unwrap_or_default(),
+                    size.layers().try_into().unwrap_or_default(),
+                )
+            })
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::GetImageSize(image)),
+                )
+            });
+        Box::new(size)
+    }
+
+    fn prune_images(&self, keep: &[String]) -> Self::PruneImagesFuture {
+        let report = self
+            .client
+            .image_api()
+            .prune_images(&API_VERSION.to_string(), keep.to_vec())
+            .map(|result| {
+                PruneReport::new(
+                    result.images_deleted().to_vec(),
+                    result.space_reclaimed().try_into().unwrap_or_default(),
+                )
+            })
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages),
+                )
+            });
+        Box::new(report)
+    }
 }
 
 pub struct Logs(String, Body);
@@ -9,10 +9,10 @@ use failure::{Backtrace, Context, Fail};
 use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use hyper::{Body, Response, StatusCode};
 use log::error;
-use serde_json;
+use serde_json::{self, json};
 
+use edgelet_http::ApiError;
 use management::apis::Error as MgmtError;
-use management::models::ErrorResponse;
 
 use crate::IntoResponse;
 
@@ -27,6 +27,12 @@ pub enum ErrorKind {
     #[fail(display = "Client error")]
     Client(MgmtError<serde_json::Value>),
 
+    #[fail(display = "Could not delete modules")]
+    DeleteModules,
+
+    #[fail(display = "Could not get config of module {:?}", _0)]
+    GetModuleConfig(String),
+
     #[fail(display = "{}", _0)]
     IdentityOperation(IdentityOperation),
 
@@ -36,6 +42,12 @@ pub enum ErrorKind {
     #[fail(display = "Invalid API version {:?}", _0)]
     InvalidApiVersion(String),
 
+    #[fail(display = "Invalid image signature verification source {:?}", _0)]
+    InvalidImageSignatureVerification(String),
+
+    #[fail(display = "Invalid volume {:?}", _0)]
+    InvalidVolume(String),
+
     #[fail(display = "A request to Azure IoT Hub failed")]
     IotHub,
 
@@ -45,15 +57,39 @@ pub enum ErrorKind {
     #[fail(display = "The request parameter `{}` is malformed", _0)]
     MalformedRequestParameter(&'static str),
 
+    #[fail(display = "Could not collect metrics: {}", _0)]
+    Metrics(String),
+
+    #[fail(display = "Metrics may not be read from this address")]
+    MetricsForbidden,
+
+    #[fail(display = "Metrics are not enabled")]
+    MetricsNotEnabled,
+
     #[fail(display = "The request is missing required parameter `{}`", _0)]
     MissingRequiredParameter(&'static str),
 
+    #[fail(display = "Could not stream module events")]
+    ModuleEvents,
+
+    #[fail(display = "Could not get history of module {:?}", _0)]
+    ModuleHistory(String),
+
     #[fail(display = "{}", _0)]
     ModuleOperation(ModuleOperation),
 
+    #[fail(display = "No spec found for module {:?}", _0)]
+    ModuleSpecNotFound(String),
+
+    #[fail(display = "No twin found for module {:?}", _0)]
+    ModuleTwinNotFound(String),
+
     #[fail(display = "State not modified")]
     NotModified,
 
+    #[fail(display = "Could not patch module {:?}", _0)]
+    PatchModule(String),
+
     #[fail(display = "Could not prepare update for module {:?}", _0)]
     PrepareUpdateModule(String),
 
@@ -66,6 +102,9 @@ pub enum ErrorKind {
     #[fail(display = "Could not start management service")]
     StartService,
 
+    #[fail(display = "Could not set up tracing: {}", _0)]
+    Tracing(String),
+
     #[fail(display = "Could not update module {:?}", _0)]
     UpdateModule(String),
 }
@@ -125,33 +164,70 @@ impl IntoResponse for Error {
         }
 
         // Specialize status code based on the underlying docker runtime error, if any
-        let status_code =
-            if let Some(cause) = Fail::find_root_cause(&self).downcast_ref::<DockerErrorKind>() {
-                match cause {
-                    DockerErrorKind::NotFound(_) => StatusCode::NOT_FOUND,
-                    DockerErrorKind::Conflict => StatusCode::CONFLICT,
-                    DockerErrorKind::NotModified => StatusCode::NOT_MODIFIED,
-                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+        let (status_code, code) = if let Some(cause) =
+            Fail::find_root_cause(&self).downcast_ref::<DockerErrorKind>()
+        {
+            match cause {
+                DockerErrorKind::NotFound(_) => (StatusCode::NOT_FOUND, ApiError::MODULE_NOT_FOUND),
+                DockerErrorKind::Conflict => (StatusCode::CONFLICT, ApiError::CONFLICT),
+                DockerErrorKind::NotModified => (StatusCode::NOT_MODIFIED, ApiError::RUNTIME_ERROR),
+                DockerErrorKind::HostPathNotAllowed(_) => {
+                    (StatusCode::FORBIDDEN, ApiError::FORBIDDEN)
                 }
-            } else {
-                match self.kind() {
-                    ErrorKind::InvalidApiVersion(_)
-                    | ErrorKind::MalformedRequestBody
-                    | ErrorKind::MalformedRequestParameter(_)
-                    | ErrorKind::MissingRequiredParameter(_) => StatusCode::BAD_REQUEST,
-                    _ => {
-                        error!("Internal server error: {}", message);
-                        StatusCode::INTERNAL_SERVER_ERROR
-                    }
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, ApiError::RUNTIME_ERROR),
+            }
+        } else {
+            match self.kind() {
+                ErrorKind::InvalidApiVersion(_) => {
+                    (StatusCode::BAD_REQUEST, ApiError::INVALID_API_VERSION)
                 }
-            };
+                ErrorKind::MalformedRequestBody => {
+                    (StatusCode::BAD_REQUEST, ApiError::INVALID_SPEC)
+                }
+                ErrorKind::MalformedRequestParameter(_)
+                | ErrorKind::MissingRequiredParameter(_) => {
+                    (StatusCode::BAD_REQUEST, ApiError::INVALID_PARAMETER)
+                }
+                ErrorKind::MetricsForbidden => (StatusCode::FORBIDDEN, ApiError::FORBIDDEN),
+                ErrorKind::MetricsNotEnabled => (StatusCode::NOT_FOUND, ApiError::NOT_FOUND),
+                ErrorKind::ModuleSpecNotFound(_) | ErrorKind::ModuleTwinNotFound(_) => {
+                    (StatusCode::NOT_FOUND, ApiError::MODULE_NOT_FOUND)
+                }
+                kind => {
+                    error!("Internal server error: {}", message);
+                    let code = match kind {
+                        ErrorKind::IotHub => ApiError::IOTHUB_ERROR,
+                        ErrorKind::ModuleOperation(_) | ErrorKind::RuntimeOperation(_) => {
+                            ApiError::RUNTIME_ERROR
+                        }
+                        _ => ApiError::INTERNAL_ERROR,
+                    };
+                    (StatusCode::INTERNAL_SERVER_ERROR, code)
+                }
+            }
+        };
+
+        let details = match self.kind() {
+            ErrorKind::MalformedRequestParameter(parameter)
+            | ErrorKind::MissingRequiredParameter(parameter) => {
+                Some(json!({ "parameter": parameter }))
+            }
+            ErrorKind::ModuleSpecNotFound(name) | ErrorKind::ModuleTwinNotFound(name) => {
+                Some(json!({ "module": name }))
+            }
+            _ => None,
+        };
 
         // Per the RFC, status code NotModified should not have a body
         let body = if status_code == StatusCode::NOT_MODIFIED {
             String::new()
         } else {
-            serde_json::to_string(&ErrorResponse::new(message))
-                .expect("serialization of ErrorResponse failed.")
+            let error = ApiError::new(code, message);
+            let error = match details {
+                Some(details) => error.with_details(details),
+                None => error,
+            };
+            serde_json::to_string(&error).expect("serialization of ApiError failed.")
         };
 
         let mut response = Response::builder();
@@ -19,7 +19,7 @@ mod server;
 pub use client::ModuleClient;
 pub use error::{Error, ErrorKind};
 pub use server::ListModules;
-pub use server::ManagementService;
+pub use server::{ManagementService, ModuleSpecs};
 
 pub trait IntoResponse {
     fn into_response(self) -> Response<Body>;
@@ -3,11 +3,13 @@
 mod create;
 mod delete;
 mod list;
+mod twin;
 mod update;
 
 pub use self::create::CreateIdentity;
 pub use self::delete::DeleteIdentity;
 pub use self::list::ListIdentities;
+pub use self::twin::GetTwin;
 pub use self::update::UpdateIdentity;
 
 #[cfg(test)]
@@ -0,0 +1,264 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::warn;
+use serde_json::{self, Value};
+
+use edgelet_core::{CachedTwin, GetModuleTwin, IdentityOperation, TwinCache};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+const X_CACHE: &str = "X-Cache";
+
+/// Responds with the module's twin exactly as IoT Hub returned it.
+///
+/// With a cache, each twin fetched from IoT Hub is also cached, and the cached twin is served
+/// when IoT Hub can't be reached. The response then has an `X-Cache` header that says whether
+/// it came from the cache, and the twin has a `lastUpdated` property with the time it was
+/// fetched.
+pub struct GetTwin<I> {
+    id_manager: I,
+    cache: Option<TwinCache>,
+}
+
+impl<I> GetTwin<I> {
+    pub fn new(id_manager: I) -> Self {
+        GetTwin {
+            id_manager,
+            cache: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: TwinCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+impl<I> Handler<Parameters> for GetTwin<I>
+where
+    I: 'static + GetModuleTwin + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let cache = self.cache.clone();
+
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .map(|name| {
+                let name = name.to_string();
+
+                self.id_manager
+                    .get_twin(&name)
+                    .then(move |result| match result {
+                        Ok(Some(twin)) => fetched_response(&name, twin, cache.as_ref()),
+                        Ok(None) => Err(Error::from(ErrorKind::ModuleTwinNotFound(name))),
+                        Err(err) => cached_response(name, err, cache.as_ref()),
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn fetched_response(
+    name: &str,
+    twin: Value,
+    cache: Option<&TwinCache>,
+) -> Result<Response<Body>, Error> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return twin_response(name, &twin, None),
+    };
+
+    match cache.put(name, twin.clone()) {
+        Ok(cached) => twin_response(name, &with_last_updated(cached), Some("MISS")),
+        Err(err) => {
+            warn!("Could not cache twin of module {}: {}", name, err);
+            twin_response(name, &twin, Some("MISS"))
+        }
+    }
+}
+
+fn cached_response<E: Fail>(
+    name: String,
+    err: E,
+    cache: Option<&TwinCache>,
+) -> Result<Response<Body>, Error> {
+    let cached = cache.and_then(|cache| {
+        cache.get(&name).unwrap_or_else(|err| {
+            warn!("Could not read cached twin of module {}: {}", name, err);
+            None
+        })
+    });
+
+    if let Some(cached) = cached {
+        warn!(
+            "Could not get twin of module {} from IoT Hub, serving the cached twin: {}",
+            name, err
+        );
+        twin_response(&name, &with_last_updated(cached), Some("HIT"))
+    } else {
+        Err(Error::from(err.context(ErrorKind::IdentityOperation(
+            IdentityOperation::GetTwin(name),
+        ))))
+    }
+}
+
+fn with_last_updated(cached: CachedTwin) -> Value {
+    let last_updated = cached.last_updated();
+    let mut twin = cached.into_twin();
+    if let Value::Object(properties) = &mut twin {
+        properties.insert(
+            "lastUpdated".to_string(),
+            Value::String(last_updated.to_rfc3339()),
+        );
+    }
+    twin
+}
+
+fn twin_response(
+    name: &str,
+    twin: &Value,
+    cache_status: Option<&'static str>,
+) -> Result<Response<Body>, Error> {
+    let context = || ErrorKind::IdentityOperation(IdentityOperation::GetTwin(name.to_string()));
+
+    let b = serde_json::to_string(twin).with_context(|_| context())?;
+    let mut response = Response::builder();
+    response
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_LENGTH, b.len().to_string().as_str());
+    if let Some(cache_status) = cache_status {
+        response.header(X_CACHE, cache_status);
+    }
+    let response = response.body(b.into()).with_context(|_| context())?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use edgelet_test_utils::identity::TestIdentityManager;
+    use futures::Stream;
+    use management::models::ErrorResponse;
+    use serde_json::json;
+
+    use super::*;
+
+    fn params() -> Parameters {
+        Parameters::with_captures(vec![(Some("name".to_string()), "m1".to_string())])
+    }
+
+    fn twin() -> Value {
+        json!({
+            "moduleId": "m1",
+            "properties": {
+                "desired": { "$version": 2, "interval": 10 },
+                "reported": { "$version": 1 }
+            }
+        })
+    }
+
+    fn cache(test_name: &str) -> (TwinCache, PathBuf) {
+        let dir = env::temp_dir().join(format!("edgelet-http-mgmt-{}-twins", test_name));
+        let _ = fs::remove_dir_all(&dir);
+        (TwinCache::new(dir.clone()), dir)
+    }
+
+    fn get<I: 'static + GetModuleTwin + Send>(handler: &GetTwin<I>) -> Response<Body> {
+        let request = Request::get("http://localhost/modules/m1/twin")
+            .body(Body::default())
+            .unwrap();
+        handler.handle(request, params()).wait().unwrap()
+    }
+
+    fn body_json(response: Response<Body>) -> Value {
+        let body = response.into_body().concat2().wait().unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn get_returns_twin_verbatim() {
+        let manager = TestIdentityManager::new(vec![])
+            .with_fail_get(false)
+            .with_twin("m1", twin());
+        let response = get(&GetTwin::new(manager));
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response.headers().get(X_CACHE).is_none());
+        assert_eq!(twin(), body_json(response));
+    }
+
+    #[test]
+    fn get_unknown_module_responds_with_not_found() {
+        let manager = TestIdentityManager::new(vec![]).with_fail_get(false);
+        let response = get(&GetTwin::new(manager));
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[test]
+    fn get_fails() {
+        let manager = TestIdentityManager::new(vec![]);
+        let response = get(&GetTwin::new(manager));
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            "Could not get twin of module m1\n\tcaused by: General error",
+            error.message()
+        );
+    }
+
+    #[test]
+    fn get_with_cache_serves_cached_twin_when_iothub_fails() {
+        let (cache, dir) = cache("twin-cache-hit");
+
+        let manager = TestIdentityManager::new(vec![])
+            .with_fail_get(false)
+            .with_twin("m1", twin());
+        let response = get(&GetTwin::new(manager).with_cache(cache.clone()));
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("MISS", response.headers()[X_CACHE]);
+        let fetched = body_json(response);
+        assert_eq!(twin()["properties"], fetched["properties"]);
+        assert!(fetched["lastUpdated"].is_string());
+
+        let manager = TestIdentityManager::new(vec![]);
+        let response = get(&GetTwin::new(manager).with_cache(cache));
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("HIT", response.headers()[X_CACHE]);
+        assert_eq!(fetched, body_json(response));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn get_with_empty_cache_fails_when_iothub_fails() {
+        let (cache, _) = cache("twin-cache-empty");
+
+        let manager = TestIdentityManager::new(vec![]);
+        let response = get(&GetTwin::new(manager).with_cache(cache));
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+}
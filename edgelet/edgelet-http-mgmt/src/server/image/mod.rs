@@ -0,0 +1,7 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+mod prune;
+mod size;
+
+pub use self::prune::PruneImages;
+pub use self::size::GetImageSize;
@@ -0,0 +1,125 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, Stream};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+
+use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct PruneImages<M> {
+    runtime: M,
+}
+
+impl<M> PruneImages<M> {
+    pub fn new(runtime: M) -> Self {
+        PruneImages { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for PruneImages<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let runtime = self.runtime.clone();
+
+        let response = req
+            .into_body()
+            .concat2()
+            .then(|b| -> Result<Vec<String>, Error> {
+                let b = b.context(ErrorKind::MalformedRequestBody)?;
+                let keep = serde_json::from_slice(&b).context(ErrorKind::MalformedRequestBody)?;
+                Ok(keep)
+            })
+            .and_then(move |keep| {
+                runtime.prune_images(&keep).map_err(|err| {
+                    Error::from(
+                        err.context(ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages)),
+                    )
+                })
+            })
+            .and_then(|report| -> Result<_, Error> {
+                let body = serde_json::to_string(&report)
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages))?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_http::route::Parameters;
+    use management::models::PruneImagesResult;
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, running_module, Error};
+
+    #[test]
+    fn success() {
+        // arrange
+        let handler = PruneImages::new(make_runtime(Ok(running_module())));
+        let request = Request::post("http://localhost/images/prune")
+            .body(r#"["microsoft/test-image"]"#.into())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let result: PruneImagesResult = serde_json::from_slice(&body).unwrap();
+        assert!(result.images_deleted().is_empty());
+        assert_eq!(0, result.space_reclaimed());
+    }
+
+    #[test]
+    fn malformed_body() {
+        // arrange
+        let handler = PruneImages::new(make_runtime(Ok(running_module())));
+        let request = Request::post("http://localhost/images/prune")
+            .body(r#"{"keep": "microsoft/test-image"}"#.into())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[test]
+    fn runtime_error() {
+        // arrange
+        let handler = PruneImages::new(make_runtime(Err(Error::General)));
+        let request = Request::post("http://localhost/images/prune")
+            .body("[]".into())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+}
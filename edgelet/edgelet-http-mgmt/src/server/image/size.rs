@@ -0,0 +1,135 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+
+use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct GetImageSize<M> {
+    runtime: M,
+}
+
+impl<M> GetImageSize<M> {
+    pub fn new(runtime: M) -> Self {
+        GetImageSize { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for GetImageSize<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .map(|name| {
+                let name = name.to_string();
+
+                self.runtime.image_size(&name).then(|result| match result {
+                    Ok(size) => Ok((name, size)),
+                    Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
+                        RuntimeOperation::GetImageSize(name),
+                    )))),
+                })
+            })
+            .into_future()
+            .flatten()
+            .and_then(|(name, size)| {
+                let body = serde_json::to_string(&size).with_context(|_| {
+                    ErrorKind::RuntimeOperation(RuntimeOperation::GetImageSize(name.clone()))
+                })?;
+
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::GetImageSize(
+                        name,
+                    )))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_http::route::Parameters;
+    use futures::Stream;
+    use management::models::ImageSize;
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, running_module, Error};
+
+    #[test]
+    fn success() {
+        // arrange
+        let handler = GetImageSize::new(make_runtime(Ok(running_module())));
+        let parameters = Parameters::with_captures(vec![(
+            Some("name".to_string()),
+            "microsoft/test-image".to_string(),
+        )]);
+        let request = Request::get("http://localhost/images/microsoft%2Ftest-image/size")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let size: ImageSize = serde_json::from_slice(&body).unwrap();
+        assert_eq!(1024, size.compressed_bytes());
+        assert_eq!(4096, size.uncompressed_bytes());
+        assert_eq!(2, size.layers());
+    }
+
+    #[test]
+    fn bad_params() {
+        // arrange
+        let handler = GetImageSize::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/images/test/size")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[test]
+    fn runtime_error() {
+        // arrange
+        let handler = GetImageSize::new(make_runtime(Err(Error::General)));
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
+        let request = Request::get("http://localhost/images/test/size")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::Arc;
+#[cfg(feature = "prometheus")]
+use std::time::Instant;
+
+#[cfg(feature = "prometheus")]
+use futures::Stream;
+use futures::{future, Future};
+#[cfg(feature = "prometheus")]
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+#[cfg(feature = "prometheus")]
+use hyper::StatusCode;
+use hyper::{Body, Request, Response};
+#[cfg(feature = "prometheus")]
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+#[cfg(feature = "prometheus")]
+use edgelet_core::ModuleEvent;
+use edgelet_core::{EventLog, MetricsSettings};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::{Error as HttpError, PeerAddr};
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// Collects the metrics served on `GET /metrics`. Unless the crate is built with the
+/// `prometheus` feature and metrics are enabled in the settings, nothing is collected.
+#[derive(Clone)]
+pub struct Metrics {
+    settings: Arc<MetricsSettings>,
+    #[cfg(feature = "prometheus")]
+    collectors: Option<Arc<Collectors>>,
+}
+
+#[cfg(feature = "prometheus")]
+struct Collectors {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    connections: IntGauge,
+    module_restarts: IntCounterVec,
+    image_updates: IntCounterVec,
+}
+
+#[cfg(feature = "prometheus")]
+impl Collectors {
+    fn new() -> Result<Self, prometheus::Error> {
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "edgelet_mgmt_requests_total",
+                "Number of management API requests",
+            ),
+            &["endpoint", "status"],
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "edgelet_mgmt_request_duration_seconds",
+                "Time taken to handle management API requests",
+            ),
+            &["endpoint"],
+        )?;
+        let connections = IntGauge::new(
+            "edgelet_mgmt_active_connections",
+            "Number of open connections to the management API",
+        )?;
+        let module_restarts = IntCounterVec::new(
+            Opts::new(
+                "edgelet_module_restarts_total",
+                "Number of times the watchdog restarted a module",
+            ),
+            &["module"],
+        )?;
+        let image_updates = IntCounterVec::new(
+            Opts::new(
+                "edgelet_image_updates_available_total",
+                "Number of times a newer image was found in the registry for a module",
+            ),
+            &["module", "image"],
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(connections.clone()))?;
+        registry.register(Box::new(module_restarts.clone()))?;
+        registry.register(Box::new(image_updates.clone()))?;
+
+        Ok(Collectors {
+            registry,
+            requests,
+            request_duration,
+            connections,
+            module_restarts,
+            image_updates,
+        })
+    }
+}
+
+impl Metrics {
+    pub fn new(settings: &MetricsSettings) -> Result<Self, Error> {
+        #[cfg(feature = "prometheus")]
+        let collectors = if settings.enabled() {
+            let collectors = Collectors::new()
+                .map_err(|err| Error::from(ErrorKind::Metrics(err.to_string())))?;
+            Some(Arc::new(collectors))
+        } else {
+            None
+        };
+
+        Ok(Metrics {
+            settings: Arc::new(settings.clone()),
+            #[cfg(feature = "prometheus")]
+            collectors,
+        })
+    }
+
+    /// Wraps `handler` so that the number and duration of the calls to it are recorded.
+    pub fn instrument<H>(&self, endpoint: &'static str, handler: H) -> Instrumented<H> {
+        Instrumented {
+            endpoint,
+            metrics: self.clone(),
+            inner: handler,
+        }
+    }
+
+    /// Records a new connection. The connection counts as active until the returned value is
+    /// dropped.
+    pub fn connection(&self) -> Connection {
+        Connection::new(self)
+    }
+
+    /// Counts module restarts and image updates recorded in the event log for as long as the
+    /// returned future runs.
+    pub fn count_events(
+        &self,
+        event_log: &EventLog,
+    ) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+        #[cfg(feature = "prometheus")]
+        {
+            if let Some(collectors) = self.collectors.clone() {
+                let events = event_log.subscribe().for_each(move |entry| {
+                    match entry.event() {
+                        ModuleEvent::Restarted { .. } => collectors
+                            .module_restarts
+                            .with_label_values(&[entry.module_id()])
+                            .inc(),
+                        ModuleEvent::ImageUpdateAvailable { image, .. } => collectors
+                            .image_updates
+                            .with_label_values(&[entry.module_id(), image.as_str()])
+                            .inc(),
+                        _ => (),
+                    }
+                    Ok(())
+                });
+                return Box::new(events);
+            }
+        }
+
+        #[cfg(not(feature = "prometheus"))]
+        let _ = event_log;
+
+        Box::new(future::ok(()))
+    }
+}
+
+/// Keeps a connection counted as active. See `Metrics::connection`.
+pub struct Connection {
+    #[cfg(feature = "prometheus")]
+    gauge: Option<IntGauge>,
+}
+
+impl Connection {
+    #[cfg(feature = "prometheus")]
+    fn new(metrics: &Metrics) -> Self {
+        let gauge = metrics.collectors.as_ref().map(|c| c.connections.clone());
+        if let Some(ref gauge) = gauge {
+            gauge.inc();
+        }
+        Connection { gauge }
+    }
+
+    #[cfg(not(feature = "prometheus"))]
+    fn new(_metrics: &Metrics) -> Self {
+        Connection {}
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(ref gauge) = self.gauge {
+            gauge.dec();
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "prometheus"), allow(dead_code))]
+pub struct Instrumented<H> {
+    endpoint: &'static str,
+    metrics: Metrics,
+    inner: H,
+}
+
+impl<H> Handler<Parameters> for Instrumented<H>
+where
+    H: Handler<Parameters>,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        #[cfg(feature = "prometheus")]
+        {
+            if let Some(collectors) = self.metrics.collectors.clone() {
+                let endpoint = self.endpoint;
+                let start = Instant::now();
+                let response = self.inner.handle(req, params).map(move |response| {
+                    collectors
+                        .request_duration
+                        .with_label_values(&[endpoint])
+                        .observe(start.elapsed().as_secs_f64());
+                    collectors
+                        .requests
+                        .with_label_values(&[endpoint, response.status().as_str()])
+                        .inc();
+                    response
+                });
+                return Box::new(response);
+            }
+        }
+
+        self.inner.handle(req, params)
+    }
+}
+
+/// Serves the collected metrics in the Prometheus text format to callers whose address is
+/// allowed by the settings.
+pub struct GetMetrics {
+    metrics: Metrics,
+}
+
+impl GetMetrics {
+    pub fn new(metrics: Metrics) -> Self {
+        GetMetrics { metrics }
+    }
+}
+
+impl Handler<Parameters> for GetMetrics {
+    fn handle(
+        &self,
+        req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let allowed = match req.extensions().get::<PeerAddr>() {
+            Some(PeerAddr::Ip(ip)) => self.metrics.settings.allowed_ips().contains(ip),
+            Some(PeerAddr::Unix) => true,
+            None => false,
+        };
+
+        let response = if allowed {
+            render(&self.metrics)
+        } else {
+            Err(Error::from(ErrorKind::MetricsForbidden))
+        }
+        .unwrap_or_else(|e| e.into_response());
+
+        Box::new(future::ok(response))
+    }
+}
+
+#[cfg(feature = "prometheus")]
+fn render(metrics: &Metrics) -> Result<Response<Body>, Error> {
+    let collectors = metrics
+        .collectors
+        .as_ref()
+        .ok_or(ErrorKind::MetricsNotEnabled)?;
+
+    let encoder = TextEncoder::new();
+    let mut body = vec![];
+    encoder
+        .encode(&collectors.registry.gather(), &mut body)
+        .map_err(|err| Error::from(ErrorKind::Metrics(err.to_string())))?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, encoder.format_type())
+        .header(CONTENT_LENGTH, body.len().to_string().as_str())
+        .body(body.into())
+        .map_err(|err| Error::from(ErrorKind::Metrics(err.to_string())))?;
+    Ok(response)
+}
+
+#[cfg(not(feature = "prometheus"))]
+fn render(_metrics: &Metrics) -> Result<Response<Body>, Error> {
+    Err(Error::from(ErrorKind::MetricsNotEnabled))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use hyper::StatusCode;
+
+    use super::*;
+
+    fn get(metrics: &Metrics, peer_addr: PeerAddr) -> Response<Body> {
+        let mut request = Request::get("http://localhost/metrics")
+            .body(Body::default())
+            .unwrap();
+        request.extensions_mut().insert(peer_addr);
+
+        GetMetrics::new(metrics.clone())
+            .handle(request, Parameters::new())
+            .wait()
+            .unwrap()
+    }
+
+    fn enabled() -> Metrics {
+        Metrics::new(&MetricsSettings::new(
+            true,
+            vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn disallowed_address_is_forbidden() {
+        let metrics = enabled();
+
+        let response = get(
+            &metrics,
+            PeerAddr::Ip(IpAddr::V4(Ipv4Addr::new(172, 17, 0, 2))),
+        );
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+    }
+
+    #[test]
+    fn unix_socket_caller_is_allowed() {
+        let metrics = Metrics::new(&MetricsSettings::new(false, vec![])).unwrap();
+
+        let response = get(&metrics, PeerAddr::Unix);
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[test]
+    fn disabled_metrics_are_not_found() {
+        let metrics = Metrics::new(&MetricsSettings::new(
+            false,
+            vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+        ))
+        .unwrap();
+
+        let response = get(&metrics, PeerAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn requests_connections_and_module_events_are_reported() {
+        // arrange
+        let metrics = enabled();
+        let handler = metrics.instrument("Test", |_req, _params| {
+            Box::new(future::ok(Response::new(Body::default())))
+                as Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send>
+        });
+        let _connection = metrics.connection();
+        let event_log = EventLog::default();
+        let events = metrics.count_events(&event_log);
+
+        // act
+        handler
+            .handle(Request::default(), Parameters::new())
+            .wait()
+            .unwrap();
+        event_log.record("m1", ModuleEvent::Restarted { attempt: 1 });
+        event_log.record(
+            "m1",
+            ModuleEvent::ImageUpdateAvailable {
+                image: "module1:1.0".to_string(),
+                digest: "sha256:1234".to_string(),
+            },
+        );
+        drop(event_log);
+        events.wait().unwrap();
+
+        // assert
+        let response = get(&metrics, PeerAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#"edgelet_mgmt_requests_total{endpoint="Test",status="200"} 1"#));
+        assert!(body.contains(r#"edgelet_mgmt_request_duration_seconds_count{endpoint="Test"} 1"#));
+        assert!(body.contains("edgelet_mgmt_active_connections 1"));
+        assert!(body.contains(r#"edgelet_module_restarts_total{module="m1"} 1"#));
+        assert!(body.contains(
+            r#"edgelet_image_updates_available_total{image="module1:1.0",module="m1"} 1"#
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::sync::Arc;
+
 use failure::{Compat, Fail, ResultExt};
 use futures::sync::mpsc::UnboundedSender;
 use futures::{future, Future};
@@ -10,24 +12,33 @@ use lazy_static::lazy_static;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use edgelet_core::crypto::Sign;
 use edgelet_core::{
-    Authenticator, IdentityManager, Module, ModuleRuntime, ModuleRuntimeErrorReason, Policy,
+    Authenticator, EventLog, GetModuleTwin, IdentityManager, MetricsSettings, Module,
+    ModuleRuntime, ModuleRuntimeErrorReason, Policy, RateLimitSettings, TracingSettings, TwinCache,
 };
 use edgelet_http::authentication::Authentication;
 use edgelet_http::authorization::Authorization;
 use edgelet_http::route::*;
 use edgelet_http::router;
-use edgelet_http::Version;
+use edgelet_http::token_auth::{TokenAuthorizer, TokenPolicy};
+use edgelet_http::{RateLimiter, Version};
 
 mod device_actions;
 mod identity;
+mod image;
+mod metrics;
 mod module;
 mod system_info;
+mod tracing;
 
 use self::device_actions::*;
 use self::identity::*;
+use self::image::*;
+use self::metrics::{Connection, GetMetrics, Metrics};
 pub use self::module::*;
 use self::system_info::*;
+use self::tracing::Tracer;
 use crate::error::{Error, ErrorKind};
 
 lazy_static! {
@@ -37,12 +48,23 @@ lazy_static! {
 #[derive(Clone)]
 pub struct ManagementService {
     inner: RouterService<RegexRecognizer>,
+    metrics: Metrics,
+    // Counts the connection served by this instance as active until the instance is dropped
+    _connection: Option<Arc<Connection>>,
 }
 
 impl ManagementService {
-    pub fn new<M, I>(
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<M, I, K>(
         runtime: &M,
         identity: &I,
+        event_log: &EventLog,
+        token_key: Option<K>,
+        rate_limits: &RateLimitSettings,
+        metrics: &MetricsSettings,
+        tracing: &TracingSettings,
+        twin_cache: &TwinCache,
+        specs: &ModuleSpecs,
         initiate_shutdown_and_reprovision: UnboundedSender<()>,
     ) -> impl Future<Item = Self, Error = Error>
     where
@@ -50,37 +72,73 @@ impl ManagementService {
         for<'r> &'r <M as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
         <M::Module as Module>::Config: DeserializeOwned + Serialize,
         M::Logs: Into<Body>,
-        I: IdentityManager + Clone + Send + Sync + 'static,
+        I: IdentityManager + GetModuleTwin + Clone + Send + Sync + 'static,
         I::Identity: Serialize,
+        K: Sign + Send + Sync + 'static,
         <M::AuthenticateFuture as Future>::Error: Fail,
     {
+        let metrics = match Metrics::new(metrics) {
+            Ok(metrics) => metrics,
+            Err(err) => return future::Either::A(future::err(err)),
+        };
+        let (tracer, export_spans) = match Tracer::new(tracing) {
+            Ok(tracer) => tracer,
+            Err(err) => return future::Either::A(future::err(err)),
+        };
+        let middleware = Middleware {
+            tokens: TokenAuthorizer::new(token_key)
+                .with_exempt_module(&*AGENT_NAME, runtime.clone()),
+            limiter: RateLimiter::new(rate_limits.clone(), runtime.clone()),
+            metrics: metrics.clone(),
+            tracer,
+        };
+
         let router = router!(
-            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules"                           => ListModules::new(runtime.clone()),
-            post    Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules"                           => CreateModule::new(runtime.clone()),
-            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)"           => GetModule,
-            put     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)"           => UpdateModule::new(runtime.clone()),
-            post    Version2019_01_30 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)/prepareupdate"   => PrepareUpdateModule::new(runtime.clone()),
-            delete  Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)"           => DeleteModule::new(runtime.clone()),
-            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/start"     => StartModule::new(runtime.clone()),
-            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/stop"      => StopModule::new(runtime.clone()),
-            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/restart"   => RestartModule::new(runtime.clone()),
-            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/logs"      => ModuleLogs::new(runtime.clone()),
-
-            get     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => ListIdentities::new(identity.clone()),
-            post    Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => CreateIdentity::new(identity.clone()),
-            put     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities/(?P<name>[^/]+)"        => UpdateIdentity::new(identity.clone()),
-            delete  Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities/(?P<name>[^/]+)"        => DeleteIdentity::new(identity.clone()),
-
-            get     Version2018_06_28 runtime Policy::Anonymous             => "/systeminfo"                        => GetSystemInfo::new(runtime.clone()),
-            get     Version2019_11_05 runtime Policy::Anonymous             => "/systeminfo/resources"              => GetSystemResources::new(runtime.clone()),
-
-            post    Version2019_10_22 runtime Policy::Module(&*AGENT_NAME)  => "/device/reprovision"                => ReprovisionDevice::new(initiate_shutdown_and_reprovision),
+            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules"                           => middleware.wrap("ListModules", TokenPolicy::AnyModule, ListModules::new(runtime.clone())),
+            post    Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules"                           => middleware.wrap("CreateModule", TokenPolicy::Module(&*AGENT_NAME), CreateModule::new(runtime.clone()).with_specs(specs.clone()).with_event_log(event_log.clone())),
+            delete  Version2019_11_05 runtime Policy::Module(&*AGENT_NAME)  => "/modules"                           => middleware.wrap("DeleteModules", TokenPolicy::Module(&*AGENT_NAME), DeleteModules::new(runtime.clone()).with_specs(specs.clone()).with_event_log(event_log.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/modules/events"                    => middleware.wrap("ModuleEvents", TokenPolicy::AnyModule, ModuleEvents::new(event_log.clone())),
+            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)"           => middleware.wrap("GetModule", TokenPolicy::AnyModule, GetModule),
+            put     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)"           => middleware.wrap("UpdateModule", TokenPolicy::Module(&*AGENT_NAME), UpdateModule::new(runtime.clone()).with_specs(specs.clone()).with_event_log(event_log.clone())),
+            patch   Version2019_11_05 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)"           => middleware.wrap("PatchModule", TokenPolicy::Module(&*AGENT_NAME), PatchModule::new(runtime.clone()).with_specs(specs.clone()).with_event_log(event_log.clone())),
+            post    Version2019_01_30 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)/prepareupdate"   => middleware.wrap("PrepareUpdateModule", TokenPolicy::Module(&*AGENT_NAME), PrepareUpdateModule::new(runtime.clone())),
+            delete  Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/modules/(?P<name>[^/]+)"           => middleware.wrap("DeleteModule", TokenPolicy::Module(&*AGENT_NAME), DeleteModule::new(runtime.clone()).with_specs(specs.clone()).with_event_log(event_log.clone())),
+            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/start"     => middleware.wrap("StartModule", TokenPolicy::CallerOrModule(&*AGENT_NAME), StartModule::new(runtime.clone()).with_event_log(event_log.clone())),
+            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/stop"      => middleware.wrap("StopModule", TokenPolicy::CallerOrModule(&*AGENT_NAME), StopModule::new(runtime.clone()).with_event_log(event_log.clone())),
+            post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/restart"   => middleware.wrap("RestartModule", TokenPolicy::CallerOrModule(&*AGENT_NAME), RestartModule::new(runtime.clone()).with_event_log(event_log.clone())),
+            get     Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/logs"      => middleware.wrap("ModuleLogs", TokenPolicy::AnyModule, ModuleLogs::new(runtime.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/top"       => middleware.wrap("TopModule", TokenPolicy::AnyModule, TopModule::new(runtime.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/history"   => middleware.wrap("ModuleHistory", TokenPolicy::AnyModule, ModuleHistory::new(event_log.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/config"    => middleware.wrap("GetModuleConfig", TokenPolicy::AnyModule, GetModuleConfig::<M>::new(specs.clone()).with_twin_cache(twin_cache.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/twin"      => middleware.wrap("GetModuleTwin", TokenPolicy::AnyModule, GetTwin::new(identity.clone()).with_cache(twin_cache.clone())),
+
+            get     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => middleware.wrap("ListIdentities", TokenPolicy::Module(&*AGENT_NAME), ListIdentities::new(identity.clone())),
+            post    Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => middleware.wrap("CreateIdentity", TokenPolicy::Module(&*AGENT_NAME), CreateIdentity::new(identity.clone())),
+            put     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities/(?P<name>[^/]+)"        => middleware.wrap("UpdateIdentity", TokenPolicy::Module(&*AGENT_NAME), UpdateIdentity::new(identity.clone())),
+            delete  Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities/(?P<name>[^/]+)"        => middleware.wrap("DeleteIdentity", TokenPolicy::Module(&*AGENT_NAME), DeleteIdentity::new(identity.clone())),
+
+            get     Version2018_06_28 runtime Policy::Anonymous             => "/systeminfo"                        => middleware.wrap("GetSystemInfo", TokenPolicy::AnyModule, GetSystemInfo::new(runtime.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/systeminfo/resources"              => middleware.wrap("GetSystemResources", TokenPolicy::AnyModule, GetSystemResources::new(runtime.clone())),
+
+            post    Version2019_11_05 runtime Policy::Module(&*AGENT_NAME)  => "/images/prune"                      => middleware.wrap("PruneImages", TokenPolicy::Module(&*AGENT_NAME), PruneImages::new(runtime.clone())),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/images/(?P<name>[^/]+)/size"       => middleware.wrap("GetImageSize", TokenPolicy::AnyModule, GetImageSize::new(runtime.clone())),
+
+            get     Unversioned       runtime Policy::Anonymous             => "/metrics"                           => middleware.wrap("GetMetrics", TokenPolicy::AnyModule, GetMetrics::new(metrics.clone())),
+
+            post    Version2019_10_22 runtime Policy::Module(&*AGENT_NAME)  => "/device/reprovision"                => middleware.wrap("ReprovisionDevice", TokenPolicy::Module(&*AGENT_NAME), ReprovisionDevice::new(initiate_shutdown_and_reprovision)),
         );
 
-        router.new_service().then(|inner| {
+        let event_log = event_log.clone();
+        future::Either::B(router.new_service().then(move |inner| {
             let inner = inner.context(ErrorKind::StartService)?;
-            Ok(ManagementService { inner })
-        })
+            hyper::rt::spawn(metrics.count_events(&event_log));
+            hyper::rt::spawn(export_spans);
+            Ok(ManagementService {
+                inner,
+                metrics,
+                _connection: None,
+            })
+        }))
     }
 }
 
@@ -104,6 +162,40 @@ impl NewService for ManagementService {
     type InitError = Compat<Error>;
 
     fn new_service(&self) -> Self::Future {
-        future::ok(self.clone())
+        future::ok(ManagementService {
+            inner: self.inner.clone(),
+            metrics: self.metrics.clone(),
+            _connection: Some(Arc::new(self.metrics.connection())),
+        })
+    }
+}
+
+/// Applies the checks and instrumentation that every management API endpoint gets to the
+/// endpoint's handler.
+struct Middleware<K, M> {
+    tokens: TokenAuthorizer<K, M>,
+    limiter: RateLimiter<M>,
+    metrics: Metrics,
+    tracer: Tracer,
+}
+
+impl<K, M> Middleware<K, M>
+where
+    K: Sign + Send + Sync + 'static,
+    M: ModuleRuntime + Authenticator<Request = Request<Body>> + Clone + Send + Sync + 'static,
+{
+    fn wrap<H>(
+        &self,
+        endpoint: &'static str,
+        policy: TokenPolicy,
+        handler: H,
+    ) -> impl Handler<Parameters> + Sync
+    where
+        H: Handler<Parameters> + Sync,
+    {
+        let handler = self.metrics.instrument(endpoint, handler);
+        let handler = self.limiter.limit(endpoint, handler);
+        let handler = self.tokens.require(policy, handler);
+        self.tracer.trace(endpoint, handler)
     }
 }
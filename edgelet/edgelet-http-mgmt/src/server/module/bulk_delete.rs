@@ -0,0 +1,201 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future, Stream};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::{debug, info};
+use serde_json;
+
+use edgelet_core::{EventLog, ModuleEvent, ModuleRuntime};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::{DeleteModulesResult, ModuleFailure};
+
+use super::ModuleSpecs;
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct DeleteModules<M> {
+    runtime: M,
+    specs: ModuleSpecs,
+    event_log: EventLog,
+}
+
+impl<M> DeleteModules<M> {
+    pub fn new(runtime: M) -> Self {
+        DeleteModules {
+            runtime,
+            specs: ModuleSpecs::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_specs(mut self, specs: ModuleSpecs) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
+    }
+}
+
+impl<M> Handler<Parameters> for DeleteModules<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let runtime = self.runtime.clone();
+        let specs = self.specs.clone();
+        let event_log = self.event_log.clone();
+
+        let response = req
+            .into_body()
+            .concat2()
+            .then(|b| -> Result<Vec<String>, Error> {
+                let b = b.context(ErrorKind::MalformedRequestBody)?;
+                let names = serde_json::from_slice(&b).context(ErrorKind::MalformedRequestBody)?;
+                Ok(names)
+            })
+            .and_then(move |names| {
+                info!("Deleting modules {:?}", names);
+
+                let deletions = names.into_iter().map(move |name| {
+                    let runtime = runtime.clone();
+
+                    // Stopping fails for modules that aren't running, and removing the module
+                    // kills it anyway, so only the result of the removal matters.
+                    runtime.stop(&name, None).then(move |result| {
+                        if result.is_err() {
+                            debug!("Could not stop module {} before removing it", name);
+                        }
+                        runtime
+                            .remove(&name)
+                            .then(|result| -> Result<_, Error> { Ok((name, result)) })
+                    })
+                });
+
+                future::join_all(deletions)
+            })
+            .and_then(move |results| -> Result<_, Error> {
+                let mut succeeded = vec![];
+                let mut failed = vec![];
+                for (name, result) in results {
+                    match result {
+                        Ok(()) => {
+                            specs.remove(&name);
+                            event_log.record(&name, ModuleEvent::Removed);
+                            succeeded.push(name);
+                        }
+                        Err(err) => {
+                            let error = error_message(&err);
+                            failed.push(ModuleFailure::new(name, error));
+                        }
+                    }
+                }
+
+                let body = serde_json::to_string(&DeleteModulesResult::new(succeeded, failed))
+                    .context(ErrorKind::DeleteModules)?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::DeleteModules)?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn error_message(err: &dyn Fail) -> String {
+    let mut message = err.to_string();
+    for cause in err.iter_causes() {
+        message.push_str(&format!("\n\tcaused by: {}", cause));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::ModuleRuntimeState;
+    use edgelet_test_utils::module::*;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, Error};
+
+    fn delete(runtime: TestRuntime<Error, TestSettings>, body: &str) -> Response<Body> {
+        let handler = DeleteModules::new(runtime);
+        let request = Request::delete("http://localhost/modules")
+            .body(body.to_string().into())
+            .unwrap();
+
+        handler.handle(request, Parameters::new()).wait().unwrap()
+    }
+
+    #[test]
+    fn success() {
+        // arrange
+        let config = TestConfig::new("microsoft/test-image".to_string());
+        let module = TestModule::new(
+            "test-module".to_string(),
+            config,
+            Ok(ModuleRuntimeState::default()),
+        );
+        let runtime = make_runtime(Ok(module));
+
+        // act
+        let response = delete(runtime, r#"["m1", "m2"]"#);
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let result: Value = serde_json::from_slice(&b).unwrap();
+        assert_eq!(json!({ "succeeded": ["m1", "m2"], "failed": [] }), result);
+    }
+
+    #[test]
+    fn failures_are_reported_per_module() {
+        // arrange
+        let runtime = make_runtime(Err(Error::General));
+
+        // act
+        let response = delete(runtime, r#"["m1", "m2"]"#);
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let result: DeleteModulesResult = serde_json::from_slice(&b).unwrap();
+        assert!(result.succeeded().is_empty());
+        let failed: Vec<_> = result
+            .failed()
+            .iter()
+            .map(|failure| (failure.id().as_str(), failure.error().as_str()))
+            .collect();
+        assert_eq!(
+            vec![("m1", "General error"), ("m2", "General error")],
+            failed
+        );
+    }
+
+    #[test]
+    fn bad_body() {
+        // arrange
+        let runtime = make_runtime(Err(Error::General));
+
+        // act
+        let response = delete(runtime, r#"{"name": "m1"}"#);
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
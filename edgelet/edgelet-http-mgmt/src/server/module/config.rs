@@ -0,0 +1,248 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::marker::PhantomData;
+
+use failure::ResultExt;
+use futures::{future, Future};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use edgelet_core::{Module, ModuleRuntime, TwinCache};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::ModuleSpec;
+
+use super::{spec_to_core, ModuleSpecs};
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// Returns the spec of a module the way the runtime receives it, i.e. with defaults applied and
+/// settings such as resource limits parsed out of `createOptions`.
+///
+/// With a twin cache, the response also has the desired properties of the module's last known
+/// twin under `desiredProperties`, since that's where modules read the rest of their
+/// configuration from. For the Edge Hub that includes the routes it was given, which are also
+/// put under `routes`.
+pub struct GetModuleConfig<M> {
+    specs: ModuleSpecs,
+    twin_cache: Option<TwinCache>,
+    phantom: PhantomData<M>,
+}
+
+impl<M> GetModuleConfig<M> {
+    pub fn new(specs: ModuleSpecs) -> Self {
+        GetModuleConfig {
+            specs,
+            twin_cache: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_twin_cache(mut self, twin_cache: TwinCache) -> Self {
+        self.twin_cache = Some(twin_cache);
+        self
+    }
+}
+
+impl<M> Handler<Parameters> for GetModuleConfig<M>
+where
+    M: 'static + ModuleRuntime + Send + Sync,
+    <M::Module as Module>::Config: DeserializeOwned + Serialize,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .and_then(|name| {
+                let spec = self
+                    .specs
+                    .get(name)
+                    .ok_or_else(|| ErrorKind::ModuleSpecNotFound(name.to_string()))?;
+                let spec: ModuleSpec = serde_json::from_value(spec)
+                    .context(ErrorKind::GetModuleConfig(name.to_string()))?;
+                let core_spec =
+                    spec_to_core::<M>(&spec, ErrorKind::GetModuleConfig(name.to_string()))?;
+
+                let mut config = serde_json::to_value(&core_spec)
+                    .context(ErrorKind::GetModuleConfig(name.to_string()))?;
+                if let (Some(desired), Value::Object(config)) =
+                    (self.desired_properties(name), &mut config)
+                {
+                    if let Some(routes) = desired.get("routes") {
+                        config.insert("routes".to_string(), routes.clone());
+                    }
+                    config.insert("desiredProperties".to_string(), desired);
+                }
+
+                let body = serde_json::to_string(&config)
+                    .context(ErrorKind::GetModuleConfig(name.to_string()))?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::GetModuleConfig(name.to_string()))?;
+                Ok(response)
+            })
+            .unwrap_or_else(|e| e.into_response());
+
+        Box::new(future::ok(response))
+    }
+}
+
+impl<M> GetModuleConfig<M> {
+    fn desired_properties(&self, name: &str) -> Option<Value> {
+        let cached = self.twin_cache.as_ref()?.get(name).unwrap_or_else(|err| {
+            warn!("Could not read cached twin of module {}: {}", name, err);
+            None
+        })?;
+        cached
+            .twin()
+            .get("properties")
+            .and_then(|properties| properties.get("desired"))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+
+    use edgelet_test_utils::module::*;
+    use futures::Stream;
+    use management::models::{Config, EnvVar};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    type Runtime = TestRuntime<Error, TestSettings>;
+
+    #[test]
+    fn success() {
+        // arrange
+        let config = Config::new(json!({
+            "image": "microsoft/test-image",
+            "createOptions": { "HostConfig": { "Memory": 1024 } },
+        }))
+        .with_env(vec![EnvVar::new("A".to_string(), "1".to_string())]);
+        let specs = ModuleSpecs::new();
+        specs.insert(&ModuleSpec::new(
+            "test-module".to_string(),
+            "docker".to_string(),
+            config,
+        ));
+        let handler = GetModuleConfig::<Runtime>::new(specs);
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test-module".to_string())]);
+        let request = Request::get("http://localhost/modules/test-module/config")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let spec: Value = serde_json::from_slice(&b).unwrap();
+        assert_eq!("test-module", spec["name"]);
+        assert_eq!("microsoft/test-image", spec["config"]["image"]);
+        let env: HashMap<String, String> = serde_json::from_value(spec["env"].clone()).unwrap();
+        assert_eq!(Some("1"), env.get("A").map(String::as_str));
+        assert_eq!(1024, spec["resourceLimits"]["Memory"]);
+        assert!(spec["imagePullPolicy"].is_string());
+    }
+
+    #[test]
+    fn success_with_cached_twin() {
+        // arrange
+        let dir = env::temp_dir().join("edgelet-http-mgmt-config-twins");
+        let _ = fs::remove_dir_all(&dir);
+        let twin_cache = TwinCache::new(dir.clone());
+        twin_cache
+            .put(
+                "edgeHub",
+                json!({
+                    "moduleId": "edgeHub",
+                    "properties": {
+                        "desired": {
+                            "$version": 2,
+                            "routes": { "route1": "FROM /messages/* INTO $upstream" },
+                        },
+                    },
+                }),
+            )
+            .unwrap();
+        let specs = ModuleSpecs::new();
+        specs.insert(&ModuleSpec::new(
+            "edgeHub".to_string(),
+            "docker".to_string(),
+            Config::new(json!({ "image": "microsoft/test-image" })),
+        ));
+        let handler = GetModuleConfig::<Runtime>::new(specs).with_twin_cache(twin_cache);
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "edgeHub".to_string())]);
+        let request = Request::get("http://localhost/modules/edgeHub/config")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let spec: Value = serde_json::from_slice(&b).unwrap();
+        assert_eq!("edgeHub", spec["name"]);
+        assert_eq!(2, spec["desiredProperties"]["$version"]);
+        assert_eq!(
+            json!({ "route1": "FROM /messages/* INTO $upstream" }),
+            spec["routes"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_module() {
+        // arrange
+        let handler = GetModuleConfig::<Runtime>::new(ModuleSpecs::new());
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test-module".to_string())]);
+        let request = Request::get("http://localhost/modules/test-module/config")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[test]
+    fn config_bad_params() {
+        // arrange
+        let handler = GetModuleConfig::<Runtime>::new(ModuleSpecs::new());
+        let request = Request::get("http://localhost/modules/test-module/config")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
@@ -11,23 +11,40 @@ use serde::Serialize;
 use serde_json;
 
 use edgelet_core::{
-    ImagePullPolicy, Module, ModuleRegistry, ModuleRuntime, ModuleStatus, RuntimeOperation,
+    EventLog, ImagePullPolicy, Module, ModuleEvent, ModuleRegistry, ModuleRuntime, ModuleStatus,
+    RuntimeOperation,
 };
 use edgelet_http::route::{Handler, Parameters};
 use edgelet_http::Error as HttpError;
 use management::models::*;
 
-use super::{spec_to_core, spec_to_details};
+use super::{spec_to_core, spec_to_details, ModuleSpecs};
 use crate::error::{Error, ErrorKind};
 use crate::IntoResponse;
 
 pub struct CreateModule<M> {
     runtime: M,
+    specs: ModuleSpecs,
+    event_log: EventLog,
 }
 
 impl<M> CreateModule<M> {
     pub fn new(runtime: M) -> Self {
-        CreateModule { runtime }
+        CreateModule {
+            runtime,
+            specs: ModuleSpecs::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_specs(mut self, specs: ModuleSpecs) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
     }
 }
 
@@ -42,6 +59,8 @@ where
         _params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         let runtime = self.runtime.clone();
+        let specs = self.specs.clone();
+        let event_log = self.event_log.clone();
         let response = req
             .into_body()
             .concat2()
@@ -91,6 +110,8 @@ where
                                     name.clone(),
                                 ))
                             })?;
+                            specs.insert(&spec);
+                            event_log.record(&name, ModuleEvent::Created);
                             let details = spec_to_details(&spec, ModuleStatus::Stopped);
                             let b = serde_json::to_string(&details).with_context(|_| {
                                 ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
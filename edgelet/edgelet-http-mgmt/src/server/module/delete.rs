@@ -4,20 +4,37 @@ use failure::{Fail, ResultExt};
 use futures::{Future, IntoFuture};
 use hyper::{Body, Request, Response, StatusCode};
 
-use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_core::{EventLog, ModuleEvent, ModuleRuntime, RuntimeOperation};
 use edgelet_http::route::{Handler, Parameters};
 use edgelet_http::Error as HttpError;
 
+use super::ModuleSpecs;
 use crate::error::{Error, ErrorKind};
 use crate::IntoResponse;
 
 pub struct DeleteModule<M> {
     runtime: M,
+    specs: ModuleSpecs,
+    event_log: EventLog,
 }
 
 impl<M> DeleteModule<M> {
     pub fn new(runtime: M) -> Self {
-        DeleteModule { runtime }
+        DeleteModule {
+            runtime,
+            specs: ModuleSpecs::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_specs(mut self, specs: ModuleSpecs) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
     }
 }
 
@@ -30,14 +47,20 @@ where
         _req: Request<Body>,
         params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let specs = self.specs.clone();
+        let event_log = self.event_log.clone();
         let response = params
             .name("name")
             .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
             .map(|name| {
                 let name = name.to_string();
 
-                self.runtime.remove(&name).then(|result| match result {
-                    Ok(_) => Ok(name),
+                self.runtime.remove(&name).then(move |result| match result {
+                    Ok(_) => {
+                        specs.remove(&name);
+                        event_log.record(&name, ModuleEvent::Removed);
+                        Ok(name)
+                    }
                     Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
                         RuntimeOperation::RemoveModule(name),
                     )))),
@@ -92,7 +115,8 @@ mod tests {
         .wait()
         .unwrap()
         .with_module(Ok(module));
-        let handler = DeleteModule::new(runtime);
+        let event_log = EventLog::default();
+        let handler = DeleteModule::new(runtime).with_event_log(event_log.clone());
         let parameters =
             Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
         let request = Request::delete("http://localhost/modules/test")
@@ -104,6 +128,14 @@ mod tests {
 
         // assert
         assert_eq!(StatusCode::NO_CONTENT, response.status());
+        assert_eq!(
+            vec![ModuleEvent::Removed],
+            event_log
+                .history("test", 100)
+                .iter()
+                .map(|entry| entry.event().clone())
+                .collect::<Vec<_>>()
+        );
     }
 
     #[test]
@@ -0,0 +1,96 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io;
+
+use failure::ResultExt;
+use futures::{future, Future, Stream};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+
+use edgelet_core::EventLog;
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// Streams module lifecycle events as server-sent events, one `data` line of JSON per event.
+pub struct ModuleEvents {
+    event_log: EventLog,
+}
+
+impl ModuleEvents {
+    pub fn new(event_log: EventLog) -> Self {
+        ModuleEvents { event_log }
+    }
+}
+
+impl Handler<Parameters> for ModuleEvents {
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let events = self
+            .event_log
+            .subscribe()
+            .filter_map(|entry| serde_json::to_string(&entry).ok())
+            .map(|entry| format!("data: {}\n\n", entry))
+            .map_err(|()| io::Error::new(io::ErrorKind::Other, "event stream failed"));
+
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(Body::wrap_stream(events))
+            .context(ErrorKind::ModuleEvents)
+            .map_err(Error::from)
+            .unwrap_or_else(|e| e.into_response());
+
+        Box::new(future::ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::ModuleEvent;
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn success() {
+        // arrange
+        let event_log = EventLog::default();
+        event_log.record("test", ModuleEvent::Created);
+        let handler = ModuleEvents::new(event_log.clone());
+        let request = Request::get("http://localhost/modules/events")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+        event_log.record("test", ModuleEvent::Restarted { attempt: 2 });
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "text/event-stream",
+            response.headers().get(CONTENT_TYPE).unwrap()
+        );
+        let (chunk, _) = response
+            .into_body()
+            .into_future()
+            .wait()
+            .map_err(|_| ())
+            .unwrap();
+        let chunk = String::from_utf8(chunk.unwrap().to_vec()).unwrap();
+        assert!(chunk.starts_with("data: "));
+        assert!(chunk.ends_with("\n\n"));
+        let entry: Value = serde_json::from_str(&chunk["data: ".len()..]).unwrap();
+        assert_eq!("test", entry["moduleId"]);
+        assert_eq!("restarted", entry["event"]["type"]);
+        assert_eq!(2, entry["event"]["attempt"]);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::ResultExt;
+use futures::{future, Future};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+
+use edgelet_core::EventLog;
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// This is the maximum number of events returned for a module.
+const HISTORY_LIMIT: usize = 100;
+
+pub struct ModuleHistory {
+    event_log: EventLog,
+}
+
+impl ModuleHistory {
+    pub fn new(event_log: EventLog) -> Self {
+        ModuleHistory { event_log }
+    }
+}
+
+impl Handler<Parameters> for ModuleHistory {
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .and_then(|name| {
+                let history = self.event_log.history(name, HISTORY_LIMIT);
+                let body = serde_json::to_string(&history)
+                    .context(ErrorKind::ModuleHistory(name.to_string()))?;
+
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::ModuleHistory(name.to_string()))?;
+                Ok(response)
+            })
+            .unwrap_or_else(|e| e.into_response());
+
+        Box::new(future::ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::ModuleEvent;
+    use futures::Stream;
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn success() {
+        // arrange
+        let event_log = EventLog::default();
+        event_log.record("test", ModuleEvent::Started);
+        event_log.record("other", ModuleEvent::Started);
+        event_log.record(
+            "test",
+            ModuleEvent::Stopped {
+                exit_code: Some(137),
+                status_description: Some("OOMKilled".to_string()),
+            },
+        );
+        let handler = ModuleHistory::new(event_log);
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
+        let request = Request::get("http://localhost/modules/test/history")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let history: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(2, history.len());
+        assert_eq!("test", history[0]["moduleId"]);
+        assert_eq!("started", history[0]["event"]["type"]);
+        assert_eq!("stopped", history[1]["event"]["type"]);
+        assert_eq!(137, history[1]["event"]["exitCode"]);
+    }
+
+    #[test]
+    fn history_is_limited() {
+        // arrange
+        let event_log = EventLog::default();
+        for attempt in 0..110 {
+            event_log.record("test", ModuleEvent::Restarted { attempt });
+        }
+        let handler = ModuleHistory::new(event_log);
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
+        let request = Request::get("http://localhost/modules/test/history")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        let body = response.into_body().concat2().wait().unwrap();
+        let history: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(HISTORY_LIMIT, history.len());
+        assert_eq!(10, history[0]["event"]["attempt"]);
+    }
+
+    #[test]
+    fn history_bad_params() {
+        // arrange
+        let handler = ModuleHistory::new(EventLog::default());
+        let request = Request::get("http://localhost/modules/test/history")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
@@ -1,12 +1,13 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use failure::ResultExt;
-use futures::{Future, Stream};
-use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use futures::{future, Future, IntoFuture, Stream};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE, LINK};
 use hyper::{Body, Request, Response, StatusCode};
 use log::debug;
 use serde::Serialize;
 use serde_json;
+use url::form_urlencoded;
 
 use edgelet_core::{Module, ModuleRuntime, ModuleRuntimeState, RuntimeOperation};
 use edgelet_http::route::{Handler, Parameters};
@@ -33,38 +34,119 @@ where
 {
     fn handle(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
         _params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         debug!("List modules");
 
-        let response = self
-            .runtime
-            .list_with_details()
-            .collect()
-            .then(|result| -> Result<_, Error> {
-                let details: Result<_, Error> = result
-                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?
-                    .into_iter()
-                    .map(|(module, state)| core_to_details(&module, &state))
-                    .collect();
-                let body = ModuleList::new(details?);
-                let b = serde_json::to_string(&body)
-                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?;
-                let response = Response::builder()
-                    .status(StatusCode::OK)
-                    .header(CONTENT_TYPE, "application/json")
-                    .header(CONTENT_LENGTH, b.len().to_string().as_str())
-                    .body(b.into())
-                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?;
-                Ok(response)
+        let runtime = &self.runtime;
+        let response = req
+            .uri()
+            .query()
+            .map_or_else(|| Ok(Paging::default()), parse_paging)
+            .map(|paging| {
+                let next_link = paging.next_link(req.uri().path(), req.uri().query());
+
+                // fetch one extra module to find out whether there is another page
+                let modules = runtime.list_with_details().skip(paging.skip);
+                let modules = match paging.limit {
+                    Some(limit) => {
+                        future::Either::A(modules.take(limit.saturating_add(1)).collect())
+                    }
+                    None => future::Either::B(modules.collect()),
+                };
+
+                modules.then(move |result| -> Result<_, Error> {
+                    let mut modules = result
+                        .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?;
+                    let has_more = match paging.limit {
+                        Some(limit) if modules.len() as u64 > limit => {
+                            modules.pop();
+                            true
+                        }
+                        _ => false,
+                    };
+
+                    let details: Result<_, Error> = modules
+                        .into_iter()
+                        .map(|(module, state)| core_to_details(&module, &state))
+                        .collect();
+                    let body = ModuleList::new(details?);
+                    let b = serde_json::to_string(&body)
+                        .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?;
+                    let mut response = Response::builder();
+                    response
+                        .status(StatusCode::OK)
+                        .header(CONTENT_TYPE, "application/json")
+                        .header(CONTENT_LENGTH, b.len().to_string().as_str());
+                    if has_more {
+                        if let Some(next_link) = next_link {
+                            response
+                                .header(LINK, format!("<{}>; rel=\"next\"", next_link).as_str());
+                        }
+                    }
+                    let response = response
+                        .body(b.into())
+                        .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules))?;
+                    Ok(response)
+                })
             })
+            .into_future()
+            .flatten()
             .or_else(|e| Ok(e.into_response()));
 
         Box::new(response)
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Paging {
+    skip: u64,
+    limit: Option<u64>,
+}
+
+impl Paging {
+    // Builds the URI of the page after this one, keeping any other query parameters.
+    fn next_link(self, path: &str, query: Option<&str>) -> Option<String> {
+        let limit = self.limit?;
+
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        for (key, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            if key != "skip" && key != "limit" {
+                serializer.append_pair(&key, &value);
+            }
+        }
+        serializer
+            .append_pair("skip", &self.skip.saturating_add(limit).to_string())
+            .append_pair("limit", &limit.to_string());
+
+        Some(format!("{}?{}", path, serializer.finish()))
+    }
+}
+
+fn parse_paging(query: &str) -> Result<Paging, Error> {
+    let parse: Vec<_> = form_urlencoded::parse(query.as_bytes()).collect();
+
+    let skip = parse
+        .iter()
+        .find(|&(ref key, _)| key == "skip")
+        .map_or_else(|| Ok(0), |(_, val)| val.parse::<u64>())
+        .context(ErrorKind::MalformedRequestParameter("skip"))?;
+
+    let limit = parse
+        .iter()
+        .find(|&(ref key, _)| key == "limit")
+        .map(|(_, val)| val.parse::<u64>())
+        .transpose()
+        .context(ErrorKind::MalformedRequestParameter("limit"))?;
+    // an empty page would link to itself as the next page
+    if limit == Some(0) {
+        return Err(Error::from(ErrorKind::MalformedRequestParameter("limit")));
+    }
+
+    Ok(Paging { skip, limit })
+}
+
 fn core_to_details<M>(module: &M, state: &ModuleRuntimeState) -> Result<ModuleDetails, Error>
 where
     M: 'static + Module + Send,
@@ -107,7 +189,7 @@ mod tests {
     use management::models::ModuleList;
 
     use super::*;
-    use crate::server::module::tests::Error;
+    use crate::server::module::tests::{make_runtime, running_module, Error};
 
     #[test]
     fn success() {
@@ -246,4 +328,109 @@ mod tests {
             .wait()
             .unwrap();
     }
+
+    #[test]
+    fn zero_limit_is_rejected() {
+        // arrange
+        let handler = ListModules::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/modules?api-version=2019-11-05&limit=0")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        assert!(response.headers().get(LINK).is_none());
+    }
+
+    #[test]
+    fn next_link_advances_skip_by_limit() {
+        let paging = Paging {
+            skip: 2,
+            limit: Some(3),
+        };
+
+        assert_eq!(
+            Some("/modules?api-version=2019-11-05&skip=5&limit=3".to_string()),
+            paging.next_link("/modules", Some("api-version=2019-11-05&skip=2&limit=3"))
+        );
+        assert_eq!(None, Paging::default().next_link("/modules", None));
+    }
+
+    #[test]
+    fn last_page_has_no_next_link() {
+        // arrange
+        let handler = ListModules::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/modules?limit=1")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response.headers().get(LINK).is_none());
+        let b = response.into_body().concat2().wait().unwrap();
+        let list: ModuleList = serde_json::from_slice(&b).unwrap();
+        assert_eq!(1, list.modules().len());
+    }
+
+    #[test]
+    fn skip_past_end_returns_empty_list() {
+        // arrange
+        let handler = ListModules::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/modules?skip=1")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response.headers().get(LINK).is_none());
+        let b = response.into_body().concat2().wait().unwrap();
+        let list: ModuleList = serde_json::from_slice(&b).unwrap();
+        assert!(list.modules().is_empty());
+    }
+
+    #[test]
+    fn malformed_limit_fails() {
+        // arrange
+        let handler = ListModules::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/modules?limit=-1")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[test]
+    fn paging_defaults() {
+        assert_eq!(
+            Paging::default(),
+            parse_paging("api-version=2019-11-05").unwrap()
+        );
+        assert_eq!(
+            None,
+            Paging::default().next_link("/modules", Some("api-version=2019-11-05"))
+        );
+    }
+
+    #[test]
+    fn next_link_replaces_paging_parameters() {
+        let paging = parse_paging("skip=2&limit=3&api-version=2019-11-05").unwrap();
+
+        assert_eq!(
+            Some("/modules?api-version=2019-11-05&skip=5&limit=3".to_string()),
+            paging.next_link("/modules", Some("skip=2&limit=3&api-version=2019-11-05"))
+        );
+    }
 }
@@ -1,41 +1,235 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use failure::Fail;
+use log::warn;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json;
+use serde_json::{self, json, Value};
+use url::Url;
 
 use edgelet_core::{
     ImagePullPolicy, Module, ModuleRuntime, ModuleSpec as CoreModuleSpec, ModuleStatus,
+    ResourceLimits, SignatureSource, SignatureVerificationConfig, VolumeMount, VolumeSource,
+    WorkloadCapability,
 };
 use management::models::*;
 
 use crate::error::{Error, ErrorKind};
 
+mod bulk_delete;
+mod config;
 mod create;
 mod delete;
+mod events;
 mod get;
+mod history;
 mod list;
 mod logs;
+mod patch;
 mod prepare_update;
 mod restart;
 mod start;
 mod stop;
+mod top;
 mod update;
 
+pub use self::bulk_delete::DeleteModules;
+pub use self::config::GetModuleConfig;
 pub use self::create::CreateModule;
 pub use self::delete::DeleteModule;
+pub use self::events::ModuleEvents;
 pub use self::get::GetModule;
+pub use self::history::ModuleHistory;
 pub use self::list::ListModules;
 pub use self::logs::ModuleLogs;
+pub use self::patch::PatchModule;
 pub use self::prepare_update::PrepareUpdateModule;
 pub use self::restart::RestartModule;
 pub use self::start::StartModule;
 pub use self::stop::StopModule;
+pub use self::top::TopModule;
 pub use self::update::UpdateModule;
 
+/// The last spec applied to each module, either through the management API or, for the Edge
+/// Agent, by the daemon itself.
+///
+/// The runtime can't reconstruct the full spec of an existing module, so this is what partial
+/// updates are applied on top of. Clones share the same underlying store.
+///
+/// With a directory, each spec is also written to a JSON file in it, so that the specs of
+/// modules created before the daemon restarted are still known. Module specs can contain
+/// registry credentials, so the directory should only be readable by the daemon.
+#[derive(Clone, Debug, Default)]
+pub struct ModuleSpecs {
+    specs: Arc<Mutex<HashMap<String, Value>>>,
+    dir: Option<PathBuf>,
+}
+
+impl ModuleSpecs {
+    pub fn new() -> Self {
+        ModuleSpecs::default()
+    }
+
+    /// Keeps the specs in `dir`. The directory is created by the first write.
+    pub fn with_dir(mut self, dir: PathBuf) -> Self {
+        self.dir = Some(dir);
+        self
+    }
+
+    /// Records the spec a module was handed to the runtime with when it wasn't created through
+    /// the management API, i.e. the Edge Agent's. Only the parts of the spec that the management
+    /// API can express are kept: the settings, resource limits, environment variables, image pull
+    /// policy, log level and capabilities.
+    pub fn insert_runtime_spec<C>(&self, spec: &CoreModuleSpec<C>)
+    where
+        C: Serialize,
+    {
+        match core_to_spec(spec) {
+            Ok(spec) => self.insert(&spec),
+            Err(err) => warn!(
+                "Could not record the spec of module {}: {}",
+                spec.name(),
+                err
+            ),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        let mut specs = self
+            .specs
+            .lock()
+            .expect("Unable to lock the module specs mutex");
+        if let Some(spec) = specs.get(name) {
+            return Some(spec.clone());
+        }
+
+        let path = self.path(name)?;
+        let spec: Value = match fs::read(&path) {
+            Ok(contents) => match serde_json::from_slice(&contents) {
+                Ok(spec) => spec,
+                Err(err) => {
+                    warn!("Could not read the spec of module {}: {}", name, err);
+                    return None;
+                }
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Could not read the spec of module {}: {}", name, err);
+                return None;
+            }
+        };
+        specs.insert(name.to_string(), spec.clone());
+        Some(spec)
+    }
+
+    fn insert(&self, spec: &ModuleSpec) {
+        // ModuleSpec is always representable as JSON
+        if let Ok(value) = serde_json::to_value(spec) {
+            if let Some(path) = self.path(spec.name()) {
+                if let Err(err) = write_spec(&path, &value) {
+                    warn!("Could not save the spec of module {}: {}", spec.name(), err);
+                }
+            }
+
+            self.specs
+                .lock()
+                .expect("Unable to lock the module specs mutex")
+                .insert(spec.name().to_string(), value);
+        }
+    }
+
+    fn remove(&self, name: &str) {
+        if let Some(path) = self.path(name) {
+            match fs::remove_file(path) {
+                Ok(()) => (),
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => warn!("Could not delete the spec of module {}: {}", name, err),
+            }
+        }
+
+        self.specs
+            .lock()
+            .expect("Unable to lock the module specs mutex")
+            .remove(name);
+    }
+
+    /// Module names may contain characters that aren't valid in file names, so they're encoded.
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| {
+            dir.join(format!(
+                "{}.json",
+                base64::encode_config(name, base64::URL_SAFE_NO_PAD)
+            ))
+        })
+    }
+}
+
+fn write_spec(path: &Path, spec: &Value) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, serde_json::to_vec(spec)?)?;
+    fs::rename(&temp_path, path)
+}
+
+fn core_to_spec<C>(spec: &CoreModuleSpec<C>) -> Result<ModuleSpec, serde_json::Error>
+where
+    C: Serialize,
+{
+    let mut settings = serde_json::to_value(spec.config())?;
+
+    // resource limits use Docker's names and live in the HostConfig section of createOptions
+    if let (Value::Object(limits), Value::Object(settings)) =
+        (serde_json::to_value(spec.resource_limits())?, &mut settings)
+    {
+        if !limits.is_empty() {
+            let host_config = settings
+                .entry("createOptions")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+                .map(|create_options| {
+                    create_options
+                        .entry("HostConfig")
+                        .or_insert_with(|| json!({}))
+                });
+            if let Some(Value::Object(host_config)) = host_config {
+                host_config.extend(limits);
+            }
+        }
+    }
+
+    let mut env: Vec<_> = spec
+        .env()
+        .iter()
+        .map(|(key, value)| EnvVar::new(key.clone(), value.clone()))
+        .collect();
+    env.sort_by(|a, b| a.key().cmp(b.key()));
+
+    let mut module_spec = ModuleSpec::new(
+        spec.name().to_string(),
+        spec.type_().to_string(),
+        Config::new(settings).with_env(env),
+    );
+    if let Value::String(image_pull_policy) = serde_json::to_value(spec.image_pull_policy())? {
+        module_spec.set_image_pull_policy(image_pull_policy);
+    }
+    if let Some(log_level) = spec.log_level() {
+        module_spec.set_log_level(log_level.to_string());
+    }
+    if let Some(capabilities) = spec.capabilities() {
+        module_spec.set_capabilities(capabilities.iter().map(ToString::to_string).collect());
+    }
+
+    Ok(module_spec)
+}
+
 fn spec_to_core<M>(
     spec: &ModuleSpec,
     context: ErrorKind,
@@ -52,7 +246,20 @@ where
             .collect()
     });
 
-    let config = match serde_json::from_value(spec.config().settings().clone()) {
+    // registry credentials use Docker's names and live in the auth section of the settings
+    let mut settings = spec.config().settings().clone();
+    if let (Some(registry_auth), Value::Object(settings)) = (spec.registry_auth(), &mut settings) {
+        settings.insert(
+            "auth".to_string(),
+            json!({
+                "serveraddress": registry_auth.server(),
+                "username": registry_auth.username(),
+                "password": registry_auth.password(),
+            }),
+        );
+    }
+
+    let config = match serde_json::from_value(settings) {
         Ok(config) => config,
         Err(err) => return Err(Error::from(err.context(context))),
     };
@@ -65,14 +272,101 @@ where
         Err(err) => return Err(Error::from(err.context(context))),
     };
 
+    // resource limits use Docker's names and live in the HostConfig section of createOptions
+    let resource_limits = match spec
+        .config()
+        .settings()
+        .get("createOptions")
+        .and_then(|create_options| create_options.get("HostConfig"))
+    {
+        Some(host_config) => match serde_json::from_value(host_config.clone()) {
+            Ok(resource_limits) => resource_limits,
+            Err(err) => return Err(Error::from(err.context(context))),
+        },
+        None => ResourceLimits::default(),
+    };
+
+    let capabilities = match spec
+        .capabilities()
+        .map(|capabilities| {
+            capabilities
+                .iter()
+                .map(String::as_str)
+                .map(str::parse)
+                .collect::<Result<Vec<WorkloadCapability>, _>>()
+        })
+        .transpose()
+    {
+        Ok(capabilities) => capabilities,
+        Err(err) => return Err(Error::from(err.context(context))),
+    };
+
+    let volumes = match spec
+        .volumes()
+        .unwrap_or_default()
+        .iter()
+        .map(volume_to_core)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(volumes) => volumes,
+        Err(err) => return Err(Error::from(err.context(context))),
+    };
+
+    let image_signature_verification = match spec
+        .image_signature_verification()
+        .map(signature_verification_to_core)
+        .transpose()
+    {
+        Ok(image_signature_verification) => image_signature_verification,
+        Err(err) => return Err(Error::from(err.context(context))),
+    };
+
     let module_spec = match CoreModuleSpec::new(name, type_, config, env, image_pull_policy) {
-        Ok(module_spec) => module_spec,
+        Ok(module_spec) => module_spec
+            .with_resource_limits(resource_limits)
+            .with_log_level(spec.log_level().map(ToOwned::to_owned))
+            .with_capabilities(capabilities)
+            .with_volumes(volumes)
+            .with_image_signature_verification(image_signature_verification),
         Err(err) => return Err(Error::from(err.context(context))),
     };
 
     Ok(module_spec)
 }
 
+fn volume_to_core(volume: &Volume) -> Result<VolumeMount, ErrorKind> {
+    let source = match (
+        volume._type().as_str(),
+        volume.source(),
+        volume.size_bytes(),
+    ) {
+        ("namedVolume", Some(name), None) => VolumeSource::NamedVolume(name.to_string()),
+        ("hostPath", Some(path), None) => VolumeSource::HostPath(PathBuf::from(path)),
+        ("tmpfs", None, Some(size_bytes)) => VolumeSource::Tmpfs { size_bytes },
+        _ => return Err(ErrorKind::InvalidVolume(volume.target().clone())),
+    };
+
+    Ok(VolumeMount::new(source, volume.target().clone())
+        .with_read_only(volume.read_only().unwrap_or_default()))
+}
+
+fn signature_verification_to_core(
+    verification: &ImageSignatureVerification,
+) -> Result<SignatureVerificationConfig, ErrorKind> {
+    let invalid = || ErrorKind::InvalidImageSignatureVerification(verification.url().clone());
+    let url = Url::parse(verification.url()).map_err(|_| invalid())?;
+    let source = match verification._type().as_str() {
+        "tuf" => SignatureSource::Tuf(url),
+        "rekor" => SignatureSource::Rekor(url),
+        _ => return Err(invalid()),
+    };
+
+    Ok(SignatureVerificationConfig::new(
+        source,
+        verification.public_key().clone(),
+    ))
+}
+
 fn spec_to_details(spec: &ModuleSpec, module_status: ModuleStatus) -> ModuleDetails {
     let id = spec.name().clone();
     let name = spec.name().clone();
@@ -95,15 +389,32 @@ fn spec_to_details(spec: &ModuleSpec, module_status: ModuleStatus) -> ModuleDeta
 
 #[cfg(test)]
 pub mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
     use failure::Fail;
     use futures::{Future, Stream};
     use hyper::{Body, Response, StatusCode};
-    use serde_json;
+    use serde_json::{self, json, Value};
+    use url::Url;
 
-    use edgelet_core::RuntimeOperation;
-    use edgelet_docker::{Error as DockerError, ErrorKind as DockerErrorKind};
-    use management::models::ErrorResponse;
+    use edgelet_core::{
+        ImagePullPolicy, MakeModuleRuntime, ModuleRuntimeState, ModuleSpec as CoreModuleSpec,
+        ModuleStatus, ResourceLimits, RuntimeOperation, SignatureSource,
+        SignatureVerificationConfig, VolumeMount, VolumeSource, WorkloadCapability,
+    };
+    use edgelet_docker::{DockerModuleRuntime, Error as DockerError, ErrorKind as DockerErrorKind};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::{
+        TestConfig, TestModule, TestProvisioningResult, TestRuntime, TestSettings,
+    };
+    use management::models::{
+        Config, ErrorResponse, ImageSignatureVerification, ModuleSpec, RegistryAuth, Volume,
+    };
 
+    use super::{spec_to_core, ModuleSpecs};
     use crate::error::{Error as MgmtError, ErrorKind};
     use crate::IntoResponse;
 
@@ -124,6 +435,27 @@ pub mod tests {
         }
     }
 
+    /// A running module named `test-module`.
+    pub fn running_module() -> TestModule<Error, TestConfig> {
+        let state = ModuleRuntimeState::default().with_status(ModuleStatus::Running);
+        let config = TestConfig::new("microsoft/test-image".to_string());
+        TestModule::new("test-module".to_string(), config, Ok(state))
+    }
+
+    /// A runtime whose only module is `module`, or that fails every operation with the error.
+    pub fn make_runtime(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> TestRuntime<Error, TestSettings> {
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module)
+    }
+
     #[test]
     fn not_found() {
         // arrange
@@ -245,6 +577,62 @@ pub mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn error_codes() {
+        let code = |error: MgmtError| {
+            let b = error.into_response().into_body().concat2().wait().unwrap();
+            let error: Value = serde_json::from_slice(&b).unwrap();
+            error["code"].as_str().unwrap().to_string()
+        };
+
+        assert_eq!(
+            "MODULE_NOT_FOUND",
+            code(MgmtError::from(
+                DockerErrorKind::NotFound("m1".to_string()).context(ErrorKind::RuntimeOperation(
+                    RuntimeOperation::StartModule("m1".to_string())
+                )),
+            ))
+        );
+        assert_eq!(
+            "RUNTIME_ERROR",
+            code(MgmtError::from(
+                DockerError::from(DockerErrorKind::Docker).context(ErrorKind::RuntimeOperation(
+                    RuntimeOperation::StartModule("m1".to_string())
+                ),)
+            ))
+        );
+        assert_eq!(
+            "INVALID_SPEC",
+            code(MgmtError::from(ErrorKind::MalformedRequestBody))
+        );
+        assert_eq!(
+            "INTERNAL_ERROR",
+            code(MgmtError::from(ErrorKind::StartService))
+        );
+    }
+
+    #[test]
+    fn missing_parameter_details() {
+        // arrange
+        let error = MgmtError::from(ErrorKind::MissingRequiredParameter("name"));
+
+        // act
+        let response = error.into_response();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let error: Value = serde_json::from_slice(&b).unwrap();
+        assert_eq!(
+            json!({
+                "code": "INVALID_PARAMETER",
+                "message": "The request is missing required parameter `name`",
+                "details": { "parameter": "name" },
+            }),
+            error
+        );
+    }
+
     #[test]
     fn formatted_docker_runtime() {
         // arrange
@@ -276,4 +664,267 @@ pub mod tests {
             .wait()
             .unwrap();
     }
+
+    #[test]
+    fn spec_to_core_reads_resource_limits() {
+        let config = Config::new(json!({
+            "image": "microsoft/test-image",
+            "createOptions": {
+                "HostConfig": {
+                    "CpuShares": 512,
+                    "Memory": 268_435_456,
+                    "MemorySwap": -1,
+                    "PidsLimit": 100,
+                    "Privileged": true,
+                },
+            },
+        }));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config);
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &ResourceLimits::new()
+                .with_cpu_shares(512)
+                .with_memory_bytes(268_435_456)
+                .with_memory_swap_bytes(-1)
+                .with_pids_limit(100),
+            core_spec.resource_limits()
+        );
+    }
+
+    #[test]
+    fn spec_to_core_defaults_resource_limits() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config);
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert!(core_spec.resource_limits().is_empty());
+    }
+
+    #[test]
+    fn spec_to_core_reads_log_level() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_log_level("debug".to_string());
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert_eq!(Some("debug"), core_spec.log_level());
+    }
+
+    #[test]
+    fn spec_to_core_reads_capabilities() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_capabilities(vec!["sign".to_string(), "getCertificate".to_string()]);
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(&[WorkloadCapability::Sign, WorkloadCapability::GetCertificate][..]),
+            core_spec.capabilities()
+        );
+    }
+
+    #[test]
+    fn spec_to_core_rejects_unknown_capability() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_capabilities(vec!["launchMissiles".to_string()]);
+
+        let err = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap_err();
+
+        assert_eq!(StatusCode::BAD_REQUEST, err.into_response().status());
+    }
+
+    #[test]
+    fn spec_to_core_reads_volumes() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_volumes(vec![
+                Volume::new("hostPath".to_string(), "/cfg".to_string())
+                    .with_source("/var/lib/test-module".to_string())
+                    .with_read_only(true),
+                Volume::new("tmpfs".to_string(), "/tmp".to_string()).with_size_bytes(1024),
+            ]);
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &[
+                VolumeMount::new(
+                    VolumeSource::HostPath(PathBuf::from("/var/lib/test-module")),
+                    "/cfg".to_string()
+                )
+                .with_read_only(true),
+                VolumeMount::new(VolumeSource::Tmpfs { size_bytes: 1024 }, "/tmp".to_string()),
+            ][..],
+            core_spec.volumes()
+        );
+    }
+
+    #[test]
+    fn spec_to_core_rejects_volume_without_source() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec =
+            ModuleSpec::new("test-module".to_string(), "docker".to_string(), config).with_volumes(
+                vec![Volume::new("namedVolume".to_string(), "/data".to_string())],
+            );
+
+        let err = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap_err();
+
+        assert_eq!(StatusCode::BAD_REQUEST, err.into_response().status());
+    }
+
+    #[test]
+    fn spec_to_core_reads_image_signature_verification() {
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_image_signature_verification(ImageSignatureVerification::new(
+                "rekor".to_string(),
+                "https://rekor.example.com".to_string(),
+                "key".to_string(),
+            ));
+
+        let core_spec = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(&SignatureVerificationConfig::new(
+                SignatureSource::Rekor(Url::parse("https://rekor.example.com").unwrap()),
+                "key".to_string()
+            )),
+            core_spec.image_signature_verification()
+        );
+    }
+
+    #[test]
+    fn spec_to_core_reads_registry_auth() {
+        let config = Config::new(json!({
+            "image": "repo.azurecr.io/test-image",
+            "auth": {
+                "serveraddress": "other.azurecr.io",
+            },
+        }));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config)
+            .with_registry_auth(RegistryAuth::new(
+                "repo.azurecr.io".to_string(),
+                "username".to_string(),
+                "password".to_string(),
+            ));
+
+        let core_spec =
+            spec_to_core::<DockerModuleRuntime>(&spec, ErrorKind::MalformedRequestBody).unwrap();
+
+        let auth = core_spec.config().auth().unwrap();
+        assert_eq!(Some("repo.azurecr.io"), auth.serveraddress());
+        assert_eq!(Some("username"), auth.username());
+        assert_eq!(Some("password"), auth.password());
+        assert!(!format!("{:?}", spec).contains("\"password\""));
+        assert!(!format!("{:?}", core_spec).contains("\"password\""));
+    }
+
+    #[test]
+    fn specs_are_kept_in_dir() {
+        let dir = env::temp_dir().join("edgelet-http-mgmt-module-specs");
+        let _ = fs::remove_dir_all(&dir);
+        let config = Config::new(json!({"image": "microsoft/test-image"}));
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config);
+
+        ModuleSpecs::new().with_dir(dir.clone()).insert(&spec);
+
+        // a new store, as after a restart, reads the spec back
+        let specs = ModuleSpecs::new().with_dir(dir.clone());
+        assert_eq!(
+            "microsoft/test-image",
+            specs.get("test-module").unwrap()["config"]["settings"]["image"]
+        );
+        assert_eq!(None, specs.get("other-module"));
+
+        specs.remove("test-module");
+        assert_eq!(
+            None,
+            ModuleSpecs::new().with_dir(dir.clone()).get("test-module")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn runtime_spec_round_trips_through_spec_to_core() {
+        let mut env = HashMap::new();
+        env.insert("B".to_string(), "2".to_string());
+        env.insert("A".to_string(), "1".to_string());
+        let core_spec = CoreModuleSpec::new(
+            "edgeAgent".to_string(),
+            "docker".to_string(),
+            TestConfig::new("microsoft/test-image".to_string()),
+            env.clone(),
+            ImagePullPolicy::Never,
+        )
+        .unwrap()
+        .with_resource_limits(ResourceLimits::new().with_memory_bytes(1024))
+        .with_log_level(Some("debug".to_string()))
+        .with_capabilities(Some(vec![WorkloadCapability::Sign]));
+        let specs = ModuleSpecs::new();
+
+        specs.insert_runtime_spec(&core_spec);
+
+        let stored = specs.get("edgeAgent").unwrap();
+        assert_eq!(
+            json!([{"key": "A", "value": "1"}, {"key": "B", "value": "2"}]),
+            stored["config"]["env"]
+        );
+        let spec: ModuleSpec = serde_json::from_value(stored).unwrap();
+        let round_tripped = spec_to_core::<TestRuntime<Error, TestSettings>>(
+            &spec,
+            ErrorKind::MalformedRequestBody,
+        )
+        .unwrap();
+        assert_eq!("microsoft/test-image", round_tripped.config().image());
+        assert_eq!(&env, round_tripped.env());
+        assert_eq!(ImagePullPolicy::Never, round_tripped.image_pull_policy());
+        assert_eq!(
+            &ResourceLimits::new().with_memory_bytes(1024),
+            round_tripped.resource_limits()
+        );
+        assert_eq!(Some("debug"), round_tripped.log_level());
+        assert_eq!(
+            Some(&[WorkloadCapability::Sign][..]),
+            round_tripped.capabilities()
+        );
+    }
 }
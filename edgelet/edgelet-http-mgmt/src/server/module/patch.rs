@@ -0,0 +1,380 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::ResultExt;
+use futures::future::Either;
+use futures::{future, Future, IntoFuture, Stream};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use edgelet_core::{EventLog, Module, ModuleRuntime, ModuleStatus};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::ModuleSpec;
+
+use super::update::recreate_module;
+use super::{spec_to_core, spec_to_details, ModuleSpecs};
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct PatchModule<M> {
+    runtime: M,
+    specs: ModuleSpecs,
+    event_log: EventLog,
+}
+
+impl<M> PatchModule<M> {
+    pub fn new(runtime: M) -> Self {
+        PatchModule {
+            runtime,
+            specs: ModuleSpecs::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_specs(mut self, specs: ModuleSpecs) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
+    }
+}
+
+impl<M> Handler<Parameters> for PatchModule<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+    <M::Module as Module>::Config: DeserializeOwned + Serialize,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let runtime = self.runtime.clone();
+        let specs = self.specs.clone();
+        let stored_specs = self.specs.clone();
+        let event_log = self.event_log.clone();
+
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .map(ToString::to_string)
+            .into_future()
+            .and_then(move |name| {
+                req.into_body()
+                    .concat2()
+                    .then(move |b| -> Result<_, Error> {
+                        let b = b.context(ErrorKind::MalformedRequestBody)?;
+                        let patch: Value =
+                            serde_json::from_slice(&b).context(ErrorKind::MalformedRequestBody)?;
+                        if !patch.is_object() {
+                            return Err(Error::from(ErrorKind::MalformedRequestBody));
+                        }
+
+                        let base = specs
+                            .get(&name)
+                            .ok_or_else(|| ErrorKind::ModuleSpecNotFound(name.clone()))?;
+                        let mut merged = patch_spec(base.clone(), patch);
+                        merged["name"] = Value::String(name);
+
+                        let spec: ModuleSpec = serde_json::from_value(merged.clone())
+                            .context(ErrorKind::MalformedRequestBody)?;
+                        let core_spec = spec_to_core::<M>(&spec, ErrorKind::MalformedRequestBody)?;
+
+                        // Only the type and config end up in the container, so changes to
+                        // anything else can be applied without recreating the module.
+                        let recreate = base.get("type") != merged.get("type")
+                            || base.get("config") != merged.get("config");
+
+                        Ok((core_spec, spec, recreate))
+                    })
+            })
+            .and_then(move |(core_spec, spec, recreate)| {
+                let name = spec.name().to_string();
+
+                runtime.get(&name).then(move |result| {
+                    let (_, state) =
+                        result.with_context(|_| ErrorKind::PatchModule(name.clone()))?;
+                    Ok((runtime, core_spec, spec, recreate, *state.status()))
+                })
+            })
+            .and_then(move |(runtime, core_spec, spec, recreate, status)| {
+                if recreate {
+                    info!("Patching module {}", spec.name());
+                    Either::A(recreate_module(
+                        runtime,
+                        core_spec,
+                        spec,
+                        status == ModuleStatus::Running,
+                        event_log,
+                    ))
+                } else {
+                    info!("Patching module {} without recreating it", spec.name());
+                    Either::B(future::ok((status, spec)))
+                }
+            })
+            .and_then(move |(status, spec)| -> Result<_, Error> {
+                stored_specs.insert(&spec);
+
+                let name = spec.name().to_string();
+                let details = spec_to_details(&spec, status);
+                let b = serde_json::to_string(&details)
+                    .with_context(|_| ErrorKind::PatchModule(name.clone()))?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, b.len().to_string().as_str())
+                    .body(b.into())
+                    .context(ErrorKind::PatchModule(name))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+/// Applies `patch` to `spec` with JSON merge patch (RFC 7396) semantics, except that
+/// environment variables are merged by key instead of the whole list being replaced.
+/// A variable with a `null` value is removed.
+fn patch_spec(mut spec: Value, mut patch: Value) -> Value {
+    let env = patch
+        .get_mut("config")
+        .and_then(Value::as_object_mut)
+        .and_then(|config| config.remove("env"));
+
+    merge(&mut spec, patch);
+
+    match env {
+        Some(Value::Array(vars)) => merge_env(&mut spec["config"]["env"], vars),
+        Some(env) => {
+            let mut config = Map::new();
+            config.insert("env".to_string(), env);
+            let mut patch = Map::new();
+            patch.insert("config".to_string(), Value::Object(config));
+            merge(&mut spec, Value::Object(patch));
+        }
+        None => (),
+    }
+
+    spec
+}
+
+fn merge(target: &mut Value, patch: Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+
+            if let Value::Object(target) = target {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        target.remove(&key);
+                    } else {
+                        merge(target.entry(key).or_insert(Value::Null), value);
+                    }
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
+fn merge_env(target: &mut Value, vars: Vec<Value>) {
+    if !target.is_array() {
+        *target = Value::Array(vec![]);
+    }
+
+    if let Value::Array(env) = target {
+        for var in vars {
+            let position = env
+                .iter()
+                .position(|existing| existing.get("key") == var.get("key"));
+            let remove = var.get("value").map_or(true, Value::is_null);
+
+            match (position, remove) {
+                (Some(i), true) => {
+                    env.remove(i);
+                }
+                (Some(i), false) => env[i] = var,
+                (None, true) => (),
+                (None, false) => env.push(var),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_http::route::Parameters;
+    use management::models::{Config, EnvVar, ErrorResponse, ModuleDetails};
+    use serde_json::json;
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, running_module};
+
+    fn make_specs() -> ModuleSpecs {
+        let config = Config::new(json!({"image":"microsoft/test-image"})).with_env(vec![
+            EnvVar::new("A".to_string(), "1".to_string()),
+            EnvVar::new("B".to_string(), "2".to_string()),
+        ]);
+        let spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config);
+
+        let specs = ModuleSpecs::new();
+        specs.insert(&spec);
+        specs
+    }
+
+    fn patch(specs: &ModuleSpecs, body: &Value) -> Response<Body> {
+        let handler =
+            PatchModule::new(make_runtime(Ok(running_module()))).with_specs(specs.clone());
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test-module".to_string())]);
+        let request = Request::patch("http://localhost/modules/test-module")
+            .body(serde_json::to_string(body).unwrap().into())
+            .unwrap();
+
+        handler.handle(request, parameters).wait().unwrap()
+    }
+
+    #[test]
+    fn env_is_merged() {
+        // arrange
+        let specs = make_specs();
+
+        // act
+        let response = patch(
+            &specs,
+            &json!({
+                "config": {
+                    "env": [
+                        { "key": "B", "value": null },
+                        { "key": "C", "value": "3" },
+                    ],
+                },
+            }),
+        );
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let details: ModuleDetails = serde_json::from_slice(&b).unwrap();
+        let env: Vec<_> = details
+            .config()
+            .env()
+            .unwrap()
+            .iter()
+            .map(|var| (var.key().as_str(), var.value().as_str()))
+            .collect();
+        assert_eq!(vec![("A", "1"), ("C", "3")], env);
+        assert_eq!("microsoft/test-image", details.config().settings()["image"]);
+    }
+
+    #[test]
+    fn settings_are_merged() {
+        // arrange
+        let specs = make_specs();
+
+        // act
+        let response = patch(
+            &specs,
+            &json!({
+                "config": {
+                    "settings": {
+                        "createOptions": { "Labels": { "version": "2" } },
+                    },
+                },
+            }),
+        );
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let stored = specs.get("test-module").unwrap();
+        assert_eq!(
+            "microsoft/test-image",
+            stored["config"]["settings"]["image"]
+        );
+        assert_eq!(
+            "2",
+            stored["config"]["settings"]["createOptions"]["Labels"]["version"]
+        );
+        assert_eq!(2, stored["config"]["env"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn metadata_change_does_not_recreate() {
+        // arrange
+        let specs = make_specs();
+
+        // act
+        let response = patch(&specs, &json!({ "imagePullPolicy": "never" }));
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let details: ModuleDetails = serde_json::from_slice(&b).unwrap();
+        // the status comes from the runtime rather than from a recreated module
+        assert_eq!("unknown", details.status().runtime_status().status());
+        assert_eq!(
+            "never",
+            specs.get("test-module").unwrap()["imagePullPolicy"]
+        );
+    }
+
+    #[test]
+    fn unknown_module() {
+        // arrange
+        let specs = ModuleSpecs::new();
+
+        // act
+        let response = patch(&specs, &json!({ "imagePullPolicy": "never" }));
+
+        // assert
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&b).unwrap();
+        assert_eq!(Some("MODULE_NOT_FOUND"), error.code());
+        assert_eq!("No spec found for module \"test-module\"", error.message());
+    }
+
+    #[test]
+    fn invalid_patch() {
+        // arrange
+        let specs = make_specs();
+
+        // act
+        let response = patch(&specs, &json!({ "config": { "settings": null } }));
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        assert_eq!(
+            "microsoft/test-image",
+            specs.get("test-module").unwrap()["config"]["settings"]["image"]
+        );
+    }
+
+    #[test]
+    fn patch_must_be_object() {
+        // arrange
+        let specs = make_specs();
+
+        for body in &[json!([1]), json!("x"), json!(5), json!(null)] {
+            // act
+            let response = patch(&specs, body);
+
+            // assert
+            assert_eq!(StatusCode::BAD_REQUEST, response.status());
+            assert_eq!(
+                "microsoft/test-image",
+                specs.get("test-module").unwrap()["config"]["settings"]["image"]
+            );
+        }
+    }
+}
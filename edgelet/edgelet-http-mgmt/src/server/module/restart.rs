@@ -4,7 +4,7 @@ use failure::{Fail, ResultExt};
 use futures::{Future, IntoFuture};
 use hyper::{Body, Request, Response, StatusCode};
 
-use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_core::{EventLog, ModuleEvent, ModuleRuntime, RuntimeOperation};
 use edgelet_http::route::{Handler, Parameters};
 use edgelet_http::Error as HttpError;
 
@@ -13,11 +13,20 @@ use crate::IntoResponse;
 
 pub struct RestartModule<M> {
     runtime: M,
+    event_log: EventLog,
 }
 
 impl<M> RestartModule<M> {
     pub fn new(runtime: M) -> Self {
-        RestartModule { runtime }
+        RestartModule {
+            runtime,
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
     }
 }
 
@@ -30,18 +39,24 @@ where
         _req: Request<Body>,
         params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let event_log = self.event_log.clone();
         let response = params
             .name("name")
             .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
-            .map(|name| {
+            .map(move |name| {
                 let name = name.to_string();
 
-                self.runtime.restart(&name).then(|result| match result {
-                    Ok(_) => Ok(name),
-                    Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
-                        RuntimeOperation::RestartModule(name),
-                    )))),
-                })
+                self.runtime
+                    .restart(&name)
+                    .then(move |result| match result {
+                        Ok(_) => {
+                            event_log.record(&name, ModuleEvent::Restarted { attempt: 0 });
+                            Ok(name)
+                        }
+                        Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
+                            RuntimeOperation::RestartModule(name),
+                        )))),
+                    })
             })
             .into_future()
             .flatten()
@@ -91,7 +106,8 @@ mod tests {
         .wait()
         .unwrap()
         .with_module(Ok(module));
-        let handler = RestartModule::new(runtime);
+        let event_log = EventLog::default();
+        let handler = RestartModule::new(runtime).with_event_log(event_log.clone());
         let parameters =
             Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
         let request = Request::post("http://localhost/modules/test/restart")
@@ -103,6 +119,14 @@ mod tests {
 
         // assert
         assert_eq!(StatusCode::NO_CONTENT, response.status());
+        assert_eq!(
+            vec![ModuleEvent::Restarted { attempt: 0 }],
+            event_log
+                .history("test", 100)
+                .iter()
+                .map(|entry| entry.event().clone())
+                .collect::<Vec<_>>()
+        );
     }
 
     #[test]
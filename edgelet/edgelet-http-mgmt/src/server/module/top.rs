@@ -0,0 +1,120 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+
+use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct TopModule<M> {
+    runtime: M,
+}
+
+impl<M> TopModule<M> {
+    pub fn new(runtime: M) -> Self {
+        TopModule { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for TopModule<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .map(|name| {
+                let name = name.to_string();
+
+                self.runtime.top(&name).then(|result| match result {
+                    Ok(top) => Ok((name, top)),
+                    Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
+                        RuntimeOperation::TopModule(name),
+                    )))),
+                })
+            })
+            .into_future()
+            .flatten()
+            .and_then(|(name, top)| {
+                let body = serde_json::to_string(&top).with_context(|_| {
+                    ErrorKind::RuntimeOperation(RuntimeOperation::TopModule(name.clone()))
+                })?;
+
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::TopModule(
+                        name,
+                    )))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_http::route::Parameters;
+    use futures::Stream;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, running_module};
+
+    #[test]
+    fn success() {
+        // arrange
+        let handler = TopModule::new(make_runtime(Ok(running_module())));
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "test".to_string())]);
+        let request = Request::get("http://localhost/modules/test/top")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let top: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json!({
+                "titles": ["PID", "CMD"],
+                "processes": [["1", "/bin/sh"]],
+            }),
+            top
+        );
+    }
+
+    #[test]
+    fn top_bad_params() {
+        // arrange
+        let handler = TopModule::new(make_runtime(Ok(running_module())));
+        let request = Request::get("http://localhost/modules/test/top")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
@@ -12,21 +12,41 @@ use serde::Serialize;
 use serde_json;
 use url::form_urlencoded::parse as parse_query;
 
-use edgelet_core::{ImagePullPolicy, Module, ModuleRegistry, ModuleRuntime, ModuleStatus};
+use edgelet_core::{
+    EventLog, ImagePullPolicy, Module, ModuleEvent, ModuleRegistry, ModuleRuntime,
+    ModuleSpec as CoreModuleSpec, ModuleStatus,
+};
 use edgelet_http::route::{Handler, Parameters};
 use edgelet_http::Error as HttpError;
+use management::models::ModuleSpec;
 
-use super::{spec_to_core, spec_to_details};
+use super::{spec_to_core, spec_to_details, ModuleSpecs};
 use crate::error::{Error, ErrorKind};
 use crate::IntoResponse;
 
 pub struct UpdateModule<M> {
     runtime: M,
+    specs: ModuleSpecs,
+    event_log: EventLog,
 }
 
 impl<M> UpdateModule<M> {
     pub fn new(runtime: M) -> Self {
-        UpdateModule { runtime }
+        UpdateModule {
+            runtime,
+            specs: ModuleSpecs::default(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    pub fn with_specs(mut self, specs: ModuleSpecs) -> Self {
+        self.specs = specs;
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = event_log;
+        self
     }
 }
 
@@ -41,6 +61,8 @@ where
         _params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         let runtime = self.runtime.clone();
+        let specs = self.specs.clone();
+        let event_log = self.event_log.clone();
 
         let start: bool = req
             .uri()
@@ -71,54 +93,12 @@ where
                     info!("Updating module {}", name);
                 }
 
-                runtime.remove(&name).then(|result| {
-                    result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
-                    Ok((core_spec, spec, name, runtime))
-                })
-            })
-            .and_then(|(core_spec, spec, name, runtime)| {
-                debug!("Removed existing module {}", name);
-
-                match core_spec.image_pull_policy() {
-                    ImagePullPolicy::OnCreate => {
-                        Either::A(runtime.registry().pull(core_spec.config()).then(|result| {
-                            result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
-                            Ok((core_spec, spec, name, runtime, true))
-                        }))
-                    }
-                    ImagePullPolicy::Never => {
-                        Either::B(futures::future::ok((core_spec, spec, name, runtime, false)))
-                    }
-                }
+                recreate_module(runtime, core_spec, spec, start, event_log)
             })
-            .and_then(|(core_spec, spec, name, runtime, image_pulled)| {
-                if image_pulled {
-                    debug!("Successfully pulled new image for module {}", name)
-                } else {
-                    debug!(
-                        "Skipped pulling image for module {} as per pull policy",
-                        name
-                    )
-                }
+            .and_then(move |(status, spec)| -> Result<_, Error> {
+                specs.insert(&spec);
 
-                runtime.create(core_spec).then(|result| {
-                    result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
-                    Ok((name, spec, runtime))
-                })
-            })
-            .and_then(move |(name, spec, runtime)| {
-                debug!("Created module {}", name);
-                if start {
-                    info!("Starting module {}", name);
-                    future::Either::A(runtime.start(&name).then(|result| {
-                        result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
-                        Ok((ModuleStatus::Running, spec, name))
-                    }))
-                } else {
-                    future::Either::B(future::ok((ModuleStatus::Stopped, spec, name)))
-                }
-            })
-            .and_then(|(status, spec, name)| -> Result<_, Error> {
+                let name = spec.name().to_string();
                 let details = spec_to_details(&spec, status);
                 let b = serde_json::to_string(&details)
                     .with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
@@ -136,6 +116,76 @@ where
     }
 }
 
+// Replaces a module by removing it and creating it again from the new spec, pulling the image
+// first if the pull policy requires it. Each step is recorded in the event log.
+pub(super) fn recreate_module<M>(
+    runtime: M,
+    core_spec: CoreModuleSpec<<M::Module as Module>::Config>,
+    spec: ModuleSpec,
+    start: bool,
+    event_log: EventLog,
+) -> impl Future<Item = (ModuleStatus, ModuleSpec), Error = Error>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    let name = core_spec.name().to_string();
+
+    let removed_log = event_log.clone();
+    let created_log = event_log.clone();
+
+    runtime
+        .remove(&name)
+        .then(|result| {
+            result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
+            Ok((core_spec, spec, name, runtime))
+        })
+        .and_then(move |(core_spec, spec, name, runtime)| {
+            debug!("Removed existing module {}", name);
+            removed_log.record(&name, ModuleEvent::Removed);
+
+            match core_spec.image_pull_policy() {
+                ImagePullPolicy::OnCreate => {
+                    Either::A(runtime.registry().pull(core_spec.config()).then(|result| {
+                        result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
+                        Ok((core_spec, spec, name, runtime, true))
+                    }))
+                }
+                ImagePullPolicy::Never => {
+                    Either::B(futures::future::ok((core_spec, spec, name, runtime, false)))
+                }
+            }
+        })
+        .and_then(|(core_spec, spec, name, runtime, image_pulled)| {
+            if image_pulled {
+                debug!("Successfully pulled new image for module {}", name)
+            } else {
+                debug!(
+                    "Skipped pulling image for module {} as per pull policy",
+                    name
+                )
+            }
+
+            runtime.create(core_spec).then(move |result| {
+                result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
+                created_log.record(&name, ModuleEvent::Created);
+                Ok((name, spec, runtime))
+            })
+        })
+        .and_then(move |(name, spec, runtime)| {
+            debug!("Created module {}", name);
+            if start {
+                info!("Starting module {}", name);
+                future::Either::A(runtime.start(&name).then(move |result| {
+                    result.with_context(|_| ErrorKind::UpdateModule(name.clone()))?;
+                    event_log.record(&name, ModuleEvent::Started);
+                    Ok((ModuleStatus::Running, spec))
+                }))
+            } else {
+                future::Either::B(future::ok((ModuleStatus::Stopped, spec)))
+            }
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::prelude::*;
@@ -213,7 +263,8 @@ mod tests {
 
     #[test]
     fn success_start() {
-        let handler = UpdateModule::new(RUNTIME.clone());
+        let event_log = EventLog::default();
+        let handler = UpdateModule::new(RUNTIME.clone()).with_event_log(event_log.clone());
         let config = Config::new(json!({"image":"microsoft/test-image"}));
         let mut spec = ModuleSpec::new("test-module".to_string(), "docker".to_string(), config);
         spec.set_image_pull_policy("on-create".to_string());
@@ -226,6 +277,18 @@ mod tests {
 
         // assert
         assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            vec![
+                ModuleEvent::Removed,
+                ModuleEvent::Created,
+                ModuleEvent::Started
+            ],
+            event_log
+                .history("test-module", 100)
+                .iter()
+                .map(|entry| entry.event().clone())
+                .collect::<Vec<_>>()
+        );
         assert_eq!("160", *response.headers().get(CONTENT_LENGTH).unwrap());
         assert_eq!(
             "application/json",
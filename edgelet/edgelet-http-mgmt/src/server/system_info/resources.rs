@@ -64,3 +64,51 @@ where
         Box::new(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use edgelet_http::route::Parameters;
+    use futures::Stream;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::server::module::tests::{make_runtime, running_module, Error};
+
+    #[test]
+    fn system_resources_success() {
+        // arrange
+        let runtime = make_runtime(Ok(running_module()));
+        let handler = GetSystemResources::new(runtime);
+        let request = Request::get("http://localhost/systeminfo/resources")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let resources: Value = serde_json::from_slice(&b).unwrap();
+        assert_eq!(4, resources["cpu_cores"]);
+        assert_eq!(25.0, resources["cpu_usage_percent"]);
+        assert_eq!(8, resources["memory_total_kb"]);
+        assert_eq!(3, resources["memory_available_kb"]);
+    }
+
+    #[test]
+    fn system_resources_failed() {
+        // arrange
+        let runtime = make_runtime(Err(Error::General));
+        let handler = GetSystemResources::new(runtime);
+        let request = Request::get("http://localhost/systeminfo/resources")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    }
+}
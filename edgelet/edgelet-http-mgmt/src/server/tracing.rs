@@ -0,0 +1,441 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+#[cfg(feature = "opentelemetry")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "opentelemetry")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "opentelemetry")]
+use futures::sync::mpsc::{self, Sender};
+use futures::{future, Future};
+#[cfg(feature = "opentelemetry")]
+use futures::{Async, Poll, Stream};
+#[cfg(feature = "opentelemetry")]
+use hyper::client::HttpConnector;
+#[cfg(feature = "opentelemetry")]
+use hyper::header::CONTENT_TYPE;
+#[cfg(feature = "opentelemetry")]
+use hyper::Client;
+use hyper::{Body, Request, Response};
+#[cfg(feature = "opentelemetry")]
+use hyper_tls::HttpsConnector;
+#[cfg(feature = "opentelemetry")]
+use log::{debug, warn};
+#[cfg(feature = "opentelemetry")]
+use serde_json::{json, Value};
+#[cfg(feature = "opentelemetry")]
+use url::Url;
+
+use edgelet_core::TracingSettings;
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+#[cfg(feature = "opentelemetry")]
+use edgelet_http::{trace_context::hex, TraceContext};
+
+use crate::error::Error;
+#[cfg(feature = "opentelemetry")]
+use crate::error::ErrorKind;
+
+// https://github.com/open-telemetry/opentelemetry-proto/blob/main/opentelemetry/proto/trace/v1/trace.proto
+#[cfg(feature = "opentelemetry")]
+const SPAN_KIND_SERVER: u8 = 2;
+#[cfg(feature = "opentelemetry")]
+const STATUS_CODE_UNSET: u8 = 0;
+#[cfg(feature = "opentelemetry")]
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// How many spans may wait for the exporter before new spans are dropped.
+#[cfg(feature = "opentelemetry")]
+const MAX_QUEUED_SPANS: usize = 1024;
+/// How many spans are sent to the OTLP endpoint in one request.
+#[cfg(feature = "opentelemetry")]
+const MAX_SPANS_PER_EXPORT: usize = 128;
+
+/// Posts the recorded spans to the OTLP endpoint.
+pub type ExportSpans = Box<dyn Future<Item = (), Error = ()> + Send>;
+
+/// Records a span for every management API request and exports the spans to the configured
+/// OTLP endpoint in batches. Spans are dropped rather than queued without bound if the endpoint
+/// can't keep up. Unless the crate is built with the `opentelemetry` feature and an endpoint is
+/// configured, nothing is recorded.
+#[derive(Clone)]
+pub struct Tracer {
+    // The sender is shared so that the queue is bounded by its capacity; every clone of a
+    // sender would get a slot of its own.
+    #[cfg(feature = "opentelemetry")]
+    spans: Option<Arc<Mutex<Sender<Span>>>>,
+}
+
+#[cfg(feature = "opentelemetry")]
+#[derive(Debug)]
+struct Span {
+    context: TraceContext,
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    status: Option<u16>,
+}
+
+impl Tracer {
+    /// Returns the tracer along with the future that exports the recorded spans. The future
+    /// completes once the tracer and all its clones are dropped.
+    #[cfg(feature = "opentelemetry")]
+    pub fn new(settings: &TracingSettings) -> Result<(Self, ExportSpans), Error> {
+        let endpoint = match settings.otlp_endpoint() {
+            Some(endpoint) => endpoint.clone(),
+            None => return Ok((Tracer { spans: None }, Box::new(future::ok(())))),
+        };
+
+        let connector = HttpsConnector::new(1)
+            .map_err(|err| Error::from(ErrorKind::Tracing(err.to_string())))?;
+        let client = Client::builder().build::<_, Body>(connector);
+        let service_name = Arc::new(settings.service_name().to_string());
+
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+        let exporter = Batches::new(receiver, MAX_SPANS_PER_EXPORT)
+            .for_each(move |spans| export(&client, &endpoint, &service_name, &spans));
+
+        Ok((
+            Tracer {
+                spans: Some(Arc::new(Mutex::new(sender))),
+            },
+            Box::new(exporter),
+        ))
+    }
+
+    #[cfg(not(feature = "opentelemetry"))]
+    pub fn new(_settings: &TracingSettings) -> Result<(Self, ExportSpans), Error> {
+        Ok((Tracer {}, Box::new(future::ok(()))))
+    }
+
+    /// Wraps `handler` so that a span named `name` is recorded for every call to it.
+    pub fn trace<H>(&self, name: &'static str, handler: H) -> Traced<H> {
+        Traced {
+            name,
+            tracer: self.clone(),
+            inner: handler,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "opentelemetry"), allow(dead_code))]
+pub struct Traced<H> {
+    name: &'static str,
+    tracer: Tracer,
+    inner: H,
+}
+
+impl<H> Handler<Parameters> for Traced<H>
+where
+    H: Handler<Parameters>,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        #[cfg(feature = "opentelemetry")]
+        {
+            if let Some(spans) = self.tracer.spans.clone() {
+                let parent = TraceContext::from_headers(req.headers());
+                let context = parent.map_or_else(TraceContext::new_root, |parent| parent.child());
+
+                let name = self.name;
+                let start = SystemTime::now();
+                let response = self.inner.handle(req, params).then(move |response| {
+                    if context.sampled() {
+                        let span = Span {
+                            context,
+                            parent_span_id: parent.map(|parent| *parent.span_id()),
+                            name,
+                            start,
+                            end: SystemTime::now(),
+                            status: response.as_ref().ok().map(|r| r.status().as_u16()),
+                        };
+                        // The span is dropped if the queue is full, or if the exporter went away
+                        // because the runtime is shutting down
+                        let mut spans = spans.lock().expect("span sender lock poisoned");
+                        if spans.try_send(span).is_err() {
+                            debug!(
+                                "Dropped span of {} request, the OTLP export queue is full",
+                                name
+                            );
+                        }
+                    }
+                    response
+                });
+                return Box::new(response);
+            }
+        }
+
+        self.inner.handle(req, params)
+    }
+}
+
+/// Groups the queued spans into batches of at most `max` spans. A batch is yielded as soon as no
+/// more spans are queued, so spans aren't held back waiting for a full batch.
+#[cfg(feature = "opentelemetry")]
+struct Batches<S> {
+    spans: S,
+    max: usize,
+}
+
+#[cfg(feature = "opentelemetry")]
+impl<S> Batches<S> {
+    fn new(spans: S, max: usize) -> Self {
+        Batches { spans, max }
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+impl<S> Stream for Batches<S>
+where
+    S: Stream,
+{
+    type Item = Vec<S::Item>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut batch = vec![];
+        loop {
+            match self.spans.poll()? {
+                Async::Ready(Some(span)) => {
+                    batch.push(span);
+                    if batch.len() >= self.max {
+                        return Ok(Async::Ready(Some(batch)));
+                    }
+                }
+                Async::Ready(None) if batch.is_empty() => return Ok(Async::Ready(None)),
+                Async::NotReady if batch.is_empty() => return Ok(Async::NotReady),
+                Async::Ready(None) | Async::NotReady => return Ok(Async::Ready(Some(batch))),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+fn export(
+    client: &Client<HttpsConnector<HttpConnector>>,
+    endpoint: &Url,
+    service_name: &str,
+    spans: &[Span],
+) -> impl Future<Item = (), Error = ()> {
+    let body = export_request(service_name, spans).to_string();
+    let request = Request::post(endpoint.as_str())
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("OTLP export request is valid");
+
+    client.request(request).then(|response| {
+        match response {
+            Ok(ref response) if response.status().is_success() => (),
+            Ok(response) => warn!(
+                "Could not export spans: OTLP endpoint responded with {}",
+                response.status()
+            ),
+            Err(err) => warn!("Could not export spans: {}", err),
+        }
+        Ok(())
+    })
+}
+
+/// Builds an OTLP/HTTP `ExportTraceServiceRequest` in the protobuf JSON encoding.
+#[cfg(feature = "opentelemetry")]
+fn export_request(service_name: &str, spans: &[Span]) -> Value {
+    let nanos = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let otlp_span = |span: &Span| {
+        let mut attributes = vec![];
+        let status_code = match span.status {
+            Some(status) => {
+                attributes.push(json!({
+                    "key": "http.status_code",
+                    "value": { "intValue": status.to_string() },
+                }));
+                if status >= 500 {
+                    STATUS_CODE_ERROR
+                } else {
+                    STATUS_CODE_UNSET
+                }
+            }
+            None => STATUS_CODE_ERROR,
+        };
+
+        let mut otlp_span = json!({
+            "traceId": hex(span.context.trace_id()),
+            "spanId": hex(span.context.span_id()),
+            "name": span.name,
+            "kind": SPAN_KIND_SERVER,
+            "startTimeUnixNano": nanos(span.start),
+            "endTimeUnixNano": nanos(span.end),
+            "attributes": attributes,
+            "status": { "code": status_code },
+        });
+        if let Some(ref parent_span_id) = span.parent_span_id {
+            otlp_span["parentSpanId"] = json!(hex(parent_span_id));
+        }
+        otlp_span
+    };
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": { "stringValue": service_name },
+                }],
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME") },
+                "spans": spans.iter().map(otlp_span).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+#[cfg(all(test, feature = "opentelemetry"))]
+mod tests {
+    use futures::stream;
+
+    use edgelet_http::trace_context::TRACEPARENT;
+
+    use super::*;
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn tracer(capacity: usize) -> (Tracer, mpsc::Receiver<Span>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (
+            Tracer {
+                spans: Some(Arc::new(Mutex::new(sender))),
+            },
+            receiver,
+        )
+    }
+
+    fn handler(
+        _req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        Box::new(future::ok(Response::new(Body::default())))
+    }
+
+    fn span(name: &'static str, status: Option<u16>) -> Span {
+        Span {
+            context: TraceContext::parse(PARENT).unwrap(),
+            parent_span_id: None,
+            name,
+            start: UNIX_EPOCH,
+            end: UNIX_EPOCH + std::time::Duration::from_millis(1),
+            status,
+        }
+    }
+
+    #[test]
+    fn span_continues_trace_from_traceparent() {
+        // arrange
+        let (tracer, spans) = tracer(MAX_QUEUED_SPANS);
+        let mut request = Request::default();
+        request
+            .headers_mut()
+            .insert(TRACEPARENT, PARENT.parse().unwrap());
+
+        // act
+        tracer
+            .trace("Test", handler)
+            .handle(request, Parameters::new())
+            .wait()
+            .unwrap();
+        drop(tracer);
+
+        // assert
+        let spans = spans.collect().wait().unwrap();
+        assert_eq!(1, spans.len());
+
+        let span = &spans[0];
+        assert_eq!(
+            "4bf92f3577b34da6a3ce929d0e0e4736",
+            hex(span.context.trace_id())
+        );
+        assert_ne!("00f067aa0ba902b7", hex(span.context.span_id()));
+        assert_eq!(
+            "00f067aa0ba902b7",
+            hex(span.parent_span_id.as_ref().unwrap())
+        );
+        assert_eq!("Test", span.name);
+        assert_eq!(Some(200), span.status);
+    }
+
+    #[test]
+    fn span_starts_new_trace_without_traceparent() {
+        let (tracer, spans) = tracer(MAX_QUEUED_SPANS);
+
+        tracer
+            .trace("Test", handler)
+            .handle(Request::default(), Parameters::new())
+            .wait()
+            .unwrap();
+        drop(tracer);
+
+        let spans = spans.collect().wait().unwrap();
+        assert_eq!(1, spans.len());
+        assert_eq!(None, spans[0].parent_span_id);
+    }
+
+    #[test]
+    fn spans_are_dropped_when_queue_is_full() {
+        // A channel with no buffer holds one message for its only sender
+        let (tracer, spans) = tracer(0);
+        let traced_handler = tracer.trace("Test", handler);
+
+        for _ in 0..3 {
+            traced_handler
+                .handle(Request::default(), Parameters::new())
+                .wait()
+                .unwrap();
+        }
+        drop(traced_handler);
+        drop(tracer);
+
+        let spans = spans.collect().wait().unwrap();
+        assert_eq!(1, spans.len());
+    }
+
+    #[test]
+    fn batches_hold_at_most_max_spans() {
+        let spans = stream::iter_ok::<_, ()>(vec![1, 2, 3, 4, 5]);
+
+        let batches = Batches::new(spans, 2).collect().wait().unwrap();
+
+        assert_eq!(vec![vec![1, 2], vec![3, 4], vec![5]], batches);
+    }
+
+    #[test]
+    fn export_request_is_otlp_json() {
+        let spans = vec![span("ListModules", Some(500)), span("StartModule", None)];
+
+        let request = export_request("iotedged", &spans);
+
+        let otlp_spans = &request["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(
+            "iotedged",
+            request["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"]
+        );
+        assert_eq!(2, otlp_spans.as_array().unwrap().len());
+
+        let otlp_span = &otlp_spans[0];
+        assert_eq!("4bf92f3577b34da6a3ce929d0e0e4736", otlp_span["traceId"]);
+        assert_eq!("00f067aa0ba902b7", otlp_span["spanId"]);
+        assert_eq!(Value::Null, otlp_span["parentSpanId"]);
+        assert_eq!("ListModules", otlp_span["name"]);
+        assert_eq!("1000000", otlp_span["endTimeUnixNano"]);
+        assert_eq!(2, otlp_span["status"]["code"]);
+        assert_eq!("StartModule", otlp_spans[1]["name"]);
+    }
+}
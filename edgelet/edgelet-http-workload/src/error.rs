@@ -9,6 +9,8 @@ use log::error;
 use serde_json;
 use workload::models::ErrorResponse;
 
+use edgelet_core::WorkloadCapability;
+
 use crate::IntoResponse;
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -23,6 +25,12 @@ pub enum ErrorKind {
     #[fail(display = "Certificate has an invalid private key")]
     BadPrivateKey,
 
+    #[fail(display = "Could not check the capabilities of module {:?}", _0)]
+    CapabilityCheck(String),
+
+    #[fail(display = "Module {:?} does not have the {} capability", _0, _1)]
+    CapabilityNotGranted(String, WorkloadCapability),
+
     #[fail(display = "{}", _0)]
     CertOperation(CertOperation),
 
@@ -41,6 +49,15 @@ pub enum ErrorKind {
     #[fail(display = "Module not found")]
     ModuleNotFound(String),
 
+    #[fail(display = "Could not create module token")]
+    ModuleToken,
+
+    #[fail(display = "Secret {:?} not found", _0)]
+    SecretNotFound(String),
+
+    #[fail(display = "{}", _0)]
+    SecretOperation(SecretOperation),
+
     #[fail(display = "Could not start workload service")]
     StartService,
 }
@@ -91,7 +108,8 @@ impl IntoResponse for Error {
         }
 
         let status_code = match *self.kind() {
-            ErrorKind::ModuleNotFound(_) => StatusCode::NOT_FOUND,
+            ErrorKind::CapabilityNotGranted(_, _) => StatusCode::FORBIDDEN,
+            ErrorKind::ModuleNotFound(_) | ErrorKind::SecretNotFound(_) => StatusCode::NOT_FOUND,
             ErrorKind::MalformedRequestBody
             | ErrorKind::MalformedRequestParameter(_)
             | ErrorKind::MissingRequiredParameter(_) => StatusCode::BAD_REQUEST,
@@ -157,3 +175,20 @@ impl fmt::Display for EncryptionOperation {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug)]
+pub enum SecretOperation {
+    Get,
+    Rotate,
+    Set,
+}
+
+impl fmt::Display for SecretOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretOperation::Get => write!(f, "Could not get secret"),
+            SecretOperation::Rotate => write!(f, "Could not rotate secret"),
+            SecretOperation::Set => write!(f, "Could not set secret"),
+        }
+    }
+}
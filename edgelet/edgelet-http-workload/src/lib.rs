@@ -15,7 +15,8 @@ use hyper::{Body, Response};
 mod error;
 mod server;
 
-pub use crate::server::WorkloadService;
+pub use crate::error::{Error, ErrorKind};
+pub use crate::server::{CertificateEnrollment, WorkloadService};
 
 pub trait IntoResponse {
     fn into_response(self) -> Response<Body>;
@@ -0,0 +1,217 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::Arc;
+
+use failure::Fail;
+use futures::future::{self, Either};
+use futures::Future;
+use hyper::{Body, Request, Response};
+
+use edgelet_core::{Module, ModuleRuntime, ModuleRuntimeErrorReason, WorkloadCapability};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// Only calls the inner handler if the module named in the request may use `capability`.
+///
+/// This is meant to wrap handlers of routes with `Policy::Caller`, so that the module has
+/// already been authenticated as the caller.
+pub struct RequireCapability<H, M> {
+    inner: Arc<H>,
+    runtime: M,
+    capability: WorkloadCapability,
+}
+
+impl<H, M> RequireCapability<H, M> {
+    pub fn new(inner: H, runtime: M, capability: WorkloadCapability) -> Self {
+        RequireCapability {
+            inner: Arc::new(inner),
+            runtime,
+            capability,
+        }
+    }
+}
+
+impl<H, M> Handler<Parameters> for RequireCapability<H, M>
+where
+    H: Handler<Parameters> + Send + Sync + 'static,
+    M: ModuleRuntime + Send + Sync + 'static,
+    for<'r> &'r <M as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let name = match params.name("name") {
+            Some(name) => name.trim_start_matches('$').to_string(),
+            None => {
+                return Box::new(future::ok(
+                    Error::from(ErrorKind::MissingRequiredParameter("name")).into_response(),
+                ))
+            }
+        };
+
+        let inner = self.inner.clone();
+        let capability = self.capability;
+
+        let response = self.runtime.get(&name).then(move |result| match result {
+            Ok((module, _)) => {
+                let allowed = module
+                    .capabilities()
+                    .map_or(true, |capabilities| capabilities.contains(&capability));
+                if allowed {
+                    Either::A(inner.handle(req, params))
+                } else {
+                    Either::B(future::ok(
+                        Error::from(ErrorKind::CapabilityNotGranted(name, capability))
+                            .into_response(),
+                    ))
+                }
+            }
+            Err(err) => {
+                let err = match (&err).into() {
+                    ModuleRuntimeErrorReason::NotFound => {
+                        Error::from(err.context(ErrorKind::ModuleNotFound(name)))
+                    }
+                    ModuleRuntimeErrorReason::Other => {
+                        Error::from(err.context(ErrorKind::CapabilityCheck(name)))
+                    }
+                };
+                Either::B(future::ok(err.into_response()))
+            }
+        });
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::{
+        TestConfig, TestModule, TestProvisioningResult, TestRuntime, TestSettings,
+    };
+    use futures::Stream;
+    use hyper::StatusCode;
+
+    #[derive(Clone, Copy, Debug, Fail)]
+    pub enum Error {
+        #[fail(display = "General error")]
+        General,
+        #[fail(display = "Not found error")]
+        NotFound,
+    }
+
+    impl<'a> From<&'a Error> for ModuleRuntimeErrorReason {
+        fn from(err: &'a Error) -> Self {
+            match err {
+                Error::General => ModuleRuntimeErrorReason::Other,
+                Error::NotFound => ModuleRuntimeErrorReason::NotFound,
+            }
+        }
+    }
+
+    struct TestHandler;
+
+    impl Handler<Parameters> for TestHandler {
+        fn handle(
+            &self,
+            _req: Request<Body>,
+            _params: Parameters,
+        ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+            Box::new(future::ok(Response::new("from TestHandler".into())))
+        }
+    }
+
+    fn runtime(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> TestRuntime<Error, TestSettings> {
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module)
+    }
+
+    fn module(capabilities: Option<Vec<WorkloadCapability>>) -> TestModule<Error, TestConfig> {
+        TestModule::new(
+            "m1".to_string(),
+            TestConfig::new("microsoft/test-image".to_string()),
+            Ok(ModuleRuntimeState::default()),
+        )
+        .with_capabilities(capabilities)
+    }
+
+    fn handle<H: Handler<Parameters>>(handler: &H) -> Response<Body> {
+        let params = Parameters::with_captures(vec![(Some("name".to_string()), "m1".to_string())]);
+        handler.handle(Request::default(), params).wait().unwrap()
+    }
+
+    #[test]
+    fn calls_inner_handler_when_capability_is_granted() {
+        let handler = RequireCapability::new(
+            TestHandler,
+            runtime(Ok(module(Some(vec![WorkloadCapability::Sign])))),
+            WorkloadCapability::Sign,
+        );
+
+        let response = handle(&handler);
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        assert_eq!(b"from TestHandler", &body[..]);
+    }
+
+    #[test]
+    fn calls_inner_handler_when_module_has_no_capabilities() {
+        let handler = RequireCapability::new(
+            TestHandler,
+            runtime(Ok(module(None))),
+            WorkloadCapability::Decrypt,
+        );
+
+        assert_eq!(StatusCode::OK, handle(&handler).status());
+    }
+
+    #[test]
+    fn responds_with_forbidden_when_capability_is_not_granted() {
+        let handler = RequireCapability::new(
+            TestHandler,
+            runtime(Ok(module(Some(vec![WorkloadCapability::Sign])))),
+            WorkloadCapability::Decrypt,
+        );
+
+        assert_eq!(StatusCode::FORBIDDEN, handle(&handler).status());
+    }
+
+    #[test]
+    fn responds_with_not_found_when_module_does_not_exist() {
+        let handler = RequireCapability::new(
+            TestHandler,
+            runtime(Err(Error::NotFound)),
+            WorkloadCapability::Sign,
+        );
+
+        assert_eq!(StatusCode::NOT_FOUND, handle(&handler).status());
+    }
+
+    #[test]
+    fn responds_with_internal_error_when_runtime_fails() {
+        let handler = RequireCapability::new(
+            TestHandler,
+            runtime(Err(Error::General)),
+            WorkloadCapability::Sign,
+        );
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, handle(&handler).status());
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use failure::ResultExt;
+use futures::{future, Future};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::stack::Stack;
+use openssl::x509::extension::{ExtendedKeyUsage, SubjectAlternativeName};
+use openssl::x509::{X509NameBuilder, X509Req, X509};
+
+use edgelet_core::{
+    parse_openssl_time, Certificate, CertificateProperties, CertificateType, Error as CoreError,
+    ErrorKind as CoreErrorKind, KeyBytes, PrivateKey,
+};
+use edgelet_http::client::ClientImpl;
+use edgelet_http::{Error as HttpError, EstClient, PemCertificate, ScepClient};
+
+use crate::error::{Error, ErrorKind, Result};
+
+type EnrollFn = dyn Fn(&[u8], Vec<u8>) -> Box<dyn Future<Item = PemCertificate, Error = HttpError> + Send>
+    + Send
+    + Sync;
+
+/// Issues the certificates requested through the workload API from an external certificate
+/// authority instead of the HSM.
+///
+/// A private key is generated for every certificate and sent back to the module along with it,
+/// since the certificate authority only ever sees the certificate request.
+#[derive(Clone)]
+pub struct CertificateEnrollment {
+    enroll: Arc<EnrollFn>,
+}
+
+impl CertificateEnrollment {
+    /// Enrolls certificates with the `/simpleenroll` operation of an EST server.
+    pub fn est<C>(client: EstClient<C>) -> Self
+    where
+        C: ClientImpl + 'static,
+    {
+        CertificateEnrollment {
+            enroll: Arc::new(move |csr, private_key| {
+                Box::new(client.simple_enroll(csr, Some(private_key)))
+            }),
+        }
+    }
+
+    /// Enrolls certificates with the `PKIOperation` transaction of a SCEP server.
+    pub fn scep<C>(client: ScepClient<C>) -> Self
+    where
+        C: ClientImpl + 'static,
+    {
+        CertificateEnrollment {
+            enroll: Arc::new(move |csr, private_key| Box::new(client.enroll(csr, private_key))),
+        }
+    }
+
+    pub(crate) fn enroll(
+        &self,
+        props: &CertificateProperties,
+        context: ErrorKind,
+    ) -> impl Future<Item = EnrolledCertificate, Error = Error> + Send {
+        match certificate_request(props, context.clone()) {
+            Ok((csr, private_key)) => {
+                let common_name = props.common_name().to_string();
+                future::Either::A((self.enroll)(&csr, private_key.clone()).then(move |cert| {
+                    let cert = cert.context(context)?;
+                    Ok(EnrolledCertificate {
+                        chain: cert.get_certificate().to_vec(),
+                        private_key,
+                        common_name,
+                    })
+                }))
+            }
+            Err(err) => future::Either::B(future::err(err)),
+        }
+    }
+}
+
+/// A certificate issued through a `CertificateEnrollment`, followed by the rest of the chain that
+/// the certificate authority returned.
+pub(crate) struct EnrolledCertificate {
+    chain: Vec<u8>,
+    private_key: Vec<u8>,
+    common_name: String,
+}
+
+impl Certificate for EnrolledCertificate {
+    type Buffer = Vec<u8>;
+    type KeyBuffer = Vec<u8>;
+
+    fn pem(&self) -> std::result::Result<Vec<u8>, CoreError> {
+        Ok(self.chain.clone())
+    }
+
+    fn get_private_key(&self) -> std::result::Result<Option<PrivateKey<Vec<u8>>>, CoreError> {
+        Ok(Some(PrivateKey::Key(KeyBytes::Pem(
+            self.private_key.clone(),
+        ))))
+    }
+
+    fn get_valid_to(&self) -> std::result::Result<DateTime<Utc>, CoreError> {
+        let cert = X509::from_pem(&self.chain).context(CoreErrorKind::CertificateContent)?;
+        let valid_to =
+            parse_openssl_time(cert.not_after()).context(CoreErrorKind::CertificateContent)?;
+        Ok(valid_to)
+    }
+
+    fn get_common_name(&self) -> std::result::Result<String, CoreError> {
+        Ok(self.common_name.clone())
+    }
+}
+
+/// Generates a new RSA key and returns a DER-encoded PKCS#10 request signed with it, along with
+/// the PEM of the key. RSA is used because SCEP servers can only encrypt their response to an
+/// RSA key.
+fn certificate_request(
+    props: &CertificateProperties,
+    context: ErrorKind,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let csr = || -> std::result::Result<_, openssl::error::ErrorStack> {
+        let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, props.common_name())?;
+        let name = name.build();
+
+        let mut req = X509Req::builder()?;
+        req.set_subject_name(&name)?;
+        req.set_pubkey(&key)?;
+
+        let mut extensions = Stack::new()?;
+        extensions.push(match props.certificate_type() {
+            CertificateType::Server => ExtendedKeyUsage::new().server_auth().build()?,
+            _ => ExtendedKeyUsage::new().client_auth().build()?,
+        })?;
+        if let Some(san) = subject_alt_name(props) {
+            extensions.push(san.build(&req.x509v3_context(None))?)?;
+        }
+        req.add_extensions(&extensions)?;
+
+        req.sign(&key, MessageDigest::sha256())?;
+        Ok((req.build().to_der()?, key.private_key_to_pem_pkcs8()?))
+    };
+
+    let (csr, private_key) = csr().context(context)?;
+    Ok((csr, private_key))
+}
+
+/// Converts the SAN entries of `props`, which are comma-separated lists of entries like
+/// `DNS:name` or `URI: azureiot://...`, into the SAN extension of the certificate request.
+fn subject_alt_name(props: &CertificateProperties) -> Option<SubjectAlternativeName> {
+    let entries: Vec<(String, String)> = props
+        .san_entries()
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|entries| entries.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(2, ':');
+            let kind = parts.next()?.trim().to_uppercase();
+            let value = parts.next()?.trim().to_string();
+            Some((kind, value))
+        })
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+
+    let mut san = SubjectAlternativeName::new();
+    for (kind, value) in &entries {
+        match kind.as_str() {
+            "DNS" => san.dns(value),
+            "URI" => san.uri(value),
+            "IP" => san.ip(value),
+            "EMAIL" => san.email(value),
+            _ => &mut san,
+        };
+    }
+    Some(san)
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_test_utils::cert::{TestCa, TestCertUsage};
+
+    use super::*;
+    use crate::error::CertOperation;
+
+    fn common_name(req: &X509Req) -> String {
+        req.subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap()
+            .data()
+            .as_utf8()
+            .unwrap()
+            .to_string()
+    }
+
+    fn server_props() -> CertificateProperties {
+        CertificateProperties::new(
+            3600,
+            "beeblebrox".to_string(),
+            CertificateType::Server,
+            "beeblebroxIserver".to_string(),
+        )
+        .with_san_entries(vec!["DNS:beeblebrox, DNS:localhost".to_string()])
+    }
+
+    #[test]
+    fn enroll_sends_request_for_properties() {
+        let ca = TestCa::new("est-ca");
+        let issued = ca.issue("beeblebrox", TestCertUsage::Server);
+        let chain = issued.get_full_certificate().to_vec();
+
+        let enrollment = CertificateEnrollment {
+            enroll: Arc::new(move |csr, private_key| {
+                let csr = X509Req::from_der(csr).unwrap();
+                assert_eq!("beeblebrox", common_name(&csr));
+                assert!(csr.verify(&csr.public_key().unwrap()).unwrap());
+                assert!(PKey::private_key_from_pem(&private_key).is_ok());
+
+                Box::new(future::ok(PemCertificate::new(
+                    chain.clone(),
+                    Some(private_key),
+                    None,
+                    None,
+                )))
+            }),
+        };
+        let cert = enrollment
+            .enroll(&server_props(), ErrorKind::MalformedRequestBody)
+            .wait()
+            .unwrap();
+
+        assert_eq!(issued.get_full_certificate(), &cert.pem().unwrap()[..]);
+        assert_eq!("beeblebrox", cert.get_common_name().unwrap());
+        assert!(cert.get_valid_to().unwrap() > Utc::now());
+        match cert.get_private_key().unwrap() {
+            Some(PrivateKey::Key(KeyBytes::Pem(key))) => {
+                assert!(PKey::private_key_from_pem(&key).is_ok());
+            }
+            _ => panic!("expected a PEM private key"),
+        }
+    }
+
+    #[test]
+    fn enroll_fails_if_certificate_authority_fails() {
+        let enrollment = CertificateEnrollment {
+            enroll: Arc::new(|_, _| {
+                Box::new(future::err(HttpError::from(
+                    edgelet_http::ErrorKind::EstResponse,
+                )))
+            }),
+        };
+        let err = enrollment
+            .enroll(
+                &server_props(),
+                ErrorKind::CertOperation(CertOperation::GetServerCert),
+            )
+            .wait()
+            .err()
+            .unwrap();
+
+        match err.kind() {
+            ErrorKind::CertOperation(CertOperation::GetServerCert) => (),
+            kind => panic!("unexpected error kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn request_has_eku_and_san_extensions() {
+        let props = CertificateProperties::new(
+            3600,
+            "beeblebrox".to_string(),
+            CertificateType::Client,
+            "beeblebroxidentity".to_string(),
+        )
+        .with_san_entries(vec![
+            "URI: azureiot://zaphods_hub/devices/marvins_device/modules/beeblebrox".to_string(),
+        ]);
+        let (csr, _) = certificate_request(&props, ErrorKind::MalformedRequestBody).unwrap();
+        let csr = X509Req::from_der(&csr).unwrap();
+
+        assert_eq!(2, csr.extensions().unwrap().len());
+    }
+}
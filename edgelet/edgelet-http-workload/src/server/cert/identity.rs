@@ -1,8 +1,8 @@
 // Copyright (c) Microsoft. All rights reserved.
 
-use super::{compute_validity, refresh_cert};
+use super::{cert_response, compute_validity, refresh_cert, CertificateEnrollment};
 use failure::ResultExt;
-use futures::{Future, IntoFuture, Stream};
+use futures::{future, Future, IntoFuture, Stream};
 use hyper::{Body, Request, Response};
 use serde_json;
 
@@ -20,11 +20,22 @@ use crate::IntoResponse;
 pub struct IdentityCertHandler<T: CreateCertificate, W: WorkloadConfig> {
     hsm: T,
     config: W,
+    enrollment: Option<CertificateEnrollment>,
 }
 
 impl<T: CreateCertificate, W: WorkloadConfig> IdentityCertHandler<T, W> {
     pub fn new(hsm: T, config: W) -> Self {
-        IdentityCertHandler { hsm, config }
+        IdentityCertHandler {
+            hsm,
+            config,
+            enrollment: None,
+        }
+    }
+
+    /// Issues the certificates through `enrollment` instead of the HSM.
+    pub fn with_enrollment(mut self, enrollment: Option<CertificateEnrollment>) -> Self {
+        self.enrollment = enrollment;
+        self
     }
 }
 
@@ -40,6 +51,7 @@ where
         params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         let hsm = self.hsm.clone();
+        let enrollment = self.enrollment.clone();
         let cfg = self.config.clone();
         let max_duration = cfg.get_cert_max_duration(CertificateType::Client);
 
@@ -88,12 +100,20 @@ where
                     alias.clone(),
                 )
                 .with_san_entries(sans);
-                refresh_cert(
-                    &hsm,
-                    alias,
-                    &props,
-                    ErrorKind::CertOperation(CertOperation::CreateIdentityCert),
-                )
+                Ok((alias, props))
+            })
+            .and_then(move |(alias, props)| {
+                let context = ErrorKind::CertOperation(CertOperation::CreateIdentityCert);
+                match enrollment {
+                    Some(enrollment) => future::Either::A(
+                        enrollment
+                            .enroll(&props, context.clone())
+                            .and_then(|cert| cert_response(&cert, context)),
+                    ),
+                    None => {
+                        future::Either::B(refresh_cert(&hsm, alias, &props, context).into_future())
+                    }
+                }
             })
             .or_else(|e| Ok(e.into_response()));
 
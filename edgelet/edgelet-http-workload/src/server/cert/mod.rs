@@ -14,9 +14,11 @@ use workload::models::{CertificateResponse, PrivateKey as PrivateKeyResponse};
 
 use crate::error::{Error, ErrorKind, Result};
 
+mod enrollment;
 mod identity;
 mod server;
 
+pub use self::enrollment::CertificateEnrollment;
 pub use self::identity::IdentityCertHandler;
 pub use self::server::ServerCertHandler;
 
@@ -69,16 +71,28 @@ fn refresh_cert<T: CreateCertificate>(
     props: &CertificateProperties,
     context: ErrorKind,
 ) -> Result<Response<Body>> {
+    let cert = recreate_cert(hsm, alias, props, context.clone())?;
+    cert_response(&cert, context)
+}
+
+fn recreate_cert<T: CreateCertificate>(
+    hsm: &T,
+    alias: String,
+    props: &CertificateProperties,
+    context: ErrorKind,
+) -> Result<T::Certificate> {
     if let Err(err) = hsm.destroy_certificate(alias) {
         return Err(Error::from(err.context(context)));
     };
 
-    let cert = match hsm.create_certificate(props) {
-        Ok(cert) => cert,
-        Err(err) => return Err(Error::from(err.context(context))),
-    };
+    match hsm.create_certificate(props) {
+        Ok(cert) => Ok(cert),
+        Err(err) => Err(Error::from(err.context(context))),
+    }
+}
 
-    let cert = cert_to_response(&cert, context.clone())?;
+fn cert_response<T: Certificate>(cert: &T, context: ErrorKind) -> Result<Response<Body>> {
+    let cert = cert_to_response(cert, context.clone())?;
 
     let body = match serde_json::to_string(&cert) {
         Ok(body) => body,
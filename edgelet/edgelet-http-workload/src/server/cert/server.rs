@@ -1,16 +1,18 @@
 // Copyright (c) Microsoft. All rights reserved.
 
-use super::{compute_validity, refresh_cert};
+use super::{cert_response, compute_validity, recreate_cert, CertificateEnrollment};
 use failure::ResultExt;
 use futures::{future, Future, IntoFuture, Stream};
 use hyper::{Body, Request, Response};
+use log::{info, warn};
 use serde_json;
+use url::Url;
 
 use edgelet_core::{
     Certificate, CertificateProperties, CertificateType, CreateCertificate, WorkloadConfig,
 };
 use edgelet_http::route::{Handler, Parameters};
-use edgelet_http::Error as HttpError;
+use edgelet_http::{CtLogClient, Error as HttpError, MaybeProxyClient};
 use edgelet_utils::{
     append_dns_san_entries, ensure_not_empty_with_context, prepare_dns_san_entries,
 };
@@ -22,11 +24,22 @@ use crate::IntoResponse;
 pub struct ServerCertHandler<T: CreateCertificate, W: WorkloadConfig> {
     hsm: T,
     config: W,
+    enrollment: Option<CertificateEnrollment>,
 }
 
 impl<T: CreateCertificate, W: WorkloadConfig> ServerCertHandler<T, W> {
     pub fn new(hsm: T, config: W) -> Self {
-        ServerCertHandler { hsm, config }
+        ServerCertHandler {
+            hsm,
+            config,
+            enrollment: None,
+        }
+    }
+
+    /// Issues the certificates through `enrollment` instead of the HSM.
+    pub fn with_enrollment(mut self, enrollment: Option<CertificateEnrollment>) -> Self {
+        self.enrollment = enrollment;
+        self
     }
 }
 impl<T, W> Handler<Parameters> for ServerCertHandler<T, W>
@@ -41,8 +54,10 @@ where
         params: Parameters,
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         let hsm = self.hsm.clone();
+        let enrollment = self.enrollment.clone();
         let cfg = self.config.clone();
         let max_duration = cfg.get_cert_max_duration(CertificateType::Server);
+        let ct_log_url = cfg.ct_log_url().cloned();
 
         let response = params
             .name("name")
@@ -102,13 +117,26 @@ where
                     alias.clone(),
                 )
                 .with_san_entries(sans);
-                let body = refresh_cert(
-                    &hsm,
-                    alias,
-                    &props,
-                    ErrorKind::CertOperation(CertOperation::GetServerCert),
-                )?;
-                Ok(body)
+                Ok((alias, module_id, props))
+            })
+            .and_then(move |(alias, module_id, props)| {
+                let context = ErrorKind::CertOperation(CertOperation::GetServerCert);
+                match enrollment {
+                    Some(enrollment) => {
+                        future::Either::A(enrollment.enroll(&props, context.clone()).and_then(
+                            move |cert| {
+                                issued_cert_response(&cert, ct_log_url, &module_id, context)
+                            },
+                        ))
+                    }
+                    None => future::Either::B(
+                        recreate_cert(&hsm, alias, &props, context.clone())
+                            .and_then(|cert| {
+                                issued_cert_response(&cert, ct_log_url, &module_id, context)
+                            })
+                            .into_future(),
+                    ),
+                }
             })
             .or_else(|e| future::ok(e.into_response()));
 
@@ -116,6 +144,65 @@ where
     }
 }
 
+fn issued_cert_response<C: Certificate>(
+    cert: &C,
+    ct_log_url: Option<Url>,
+    module_id: &str,
+    context: ErrorKind,
+) -> Result<Response<Body>, Error> {
+    if let Some(ct_log_url) = ct_log_url {
+        submit_to_ct_log(ct_log_url, module_id, cert);
+    }
+
+    cert_response(cert, context)
+}
+
+/// Submits a newly issued server certificate to the certificate transparency log in the
+/// background. The certificate has already been signed, so the returned SCT can't be embedded in
+/// it and is only logged, for the audit trail.
+fn submit_to_ct_log<C: Certificate>(log_url: Url, module_id: &str, cert: &C) {
+    let chain = match cert.pem() {
+        Ok(chain) => chain.as_ref().to_vec(),
+        Err(err) => {
+            warn!(
+                "Could not submit server certificate of module {} to certificate transparency log: {}",
+                module_id, err
+            );
+            return;
+        }
+    };
+    let client = match MaybeProxyClient::new(None, None, None) {
+        Ok(client) => CtLogClient::new(client, log_url),
+        Err(err) => {
+            warn!(
+                "Could not submit server certificate of module {} to certificate transparency log: {}",
+                module_id, err
+            );
+            return;
+        }
+    };
+
+    let module_id = module_id.to_string();
+    tokio::spawn(client.add_chain(&chain).then(move |sct| {
+        match sct {
+            Ok(sct) => info!(
+                "Submitted server certificate of module {} to certificate transparency log {} (log ID {}, timestamp {})",
+                module_id,
+                client.log_url(),
+                sct.log_id(),
+                sct.timestamp(),
+            ),
+            Err(err) => warn!(
+                "Could not submit server certificate of module {} to certificate transparency log {}: {}",
+                module_id,
+                client.log_url(),
+                err
+            ),
+        }
+        Ok(())
+    }));
+}
+
 #[cfg(test)]
 mod tests {
     use std::result::Result as StdResult;
@@ -1,14 +1,18 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+mod capability;
 mod cert;
 mod decrypt;
 mod encrypt;
+mod module_token;
+mod secret;
 mod sign;
 mod trust_bundle;
 
 use edgelet_core::{
-    Authenticator, CreateCertificate, Decrypt, Encrypt, GetTrustBundle, KeyStore, Module,
-    ModuleRuntime, ModuleRuntimeErrorReason, Policy, WorkloadConfig,
+    Authenticator, CreateCertificate, Decrypt, Encrypt, GetTrustBundle, KeyStore,
+    ManagementAuthSettings, Module, ModuleRuntime, ModuleRuntimeErrorReason, Policy, SecretStore,
+    WorkloadCapability, WorkloadConfig,
 };
 use edgelet_http::authentication::Authentication;
 use edgelet_http::authorization::Authorization;
@@ -21,9 +25,14 @@ use hyper::service::{NewService, Service};
 use hyper::{Body, Request};
 use serde::Serialize;
 
+pub use self::cert::CertificateEnrollment;
+
+use self::capability::RequireCapability;
 use self::cert::{IdentityCertHandler, ServerCertHandler};
 use self::decrypt::DecryptHandler;
 use self::encrypt::EncryptHandler;
+use self::module_token::ModuleTokenHandler;
+use self::secret::{GetSecretHandler, RotateSecretHandler, SetSecretHandler};
 use self::sign::SignHandler;
 use self::trust_bundle::TrustBundleHandler;
 use crate::error::{Error, ErrorKind};
@@ -39,6 +48,9 @@ impl WorkloadService {
         hsm: H,
         runtime: &M,
         config: W,
+        management_auth: &ManagementAuthSettings,
+        secret_store: &SecretStore<H>,
+        enrollment: Option<CertificateEnrollment>,
     ) -> impl Future<Item = Self, Error = Error>
     where
         K: KeyStore + Clone + Send + Sync + 'static,
@@ -52,11 +64,15 @@ impl WorkloadService {
     {
         let router = router!(
             get   Version2018_06_28 runtime Policy::Anonymous => "/modules" => ListModules::new(runtime.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/sign"     => SignHandler::new(key_store.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/decrypt"  => DecryptHandler::new(hsm.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/encrypt"  => EncryptHandler::new(hsm.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/certificate/identity"            => IdentityCertHandler::new(hsm.clone(), config.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/certificate/server" => ServerCertHandler::new(hsm.clone(), config),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/sign"     => RequireCapability::new(SignHandler::new(key_store.clone()), runtime.clone(), WorkloadCapability::Sign),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/decrypt"  => RequireCapability::new(DecryptHandler::new(hsm.clone()), runtime.clone(), WorkloadCapability::Decrypt),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/encrypt"  => RequireCapability::new(EncryptHandler::new(hsm.clone()), runtime.clone(), WorkloadCapability::Encrypt),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/certificate/identity"            => RequireCapability::new(IdentityCertHandler::new(hsm.clone(), config.clone()).with_enrollment(enrollment.clone()), runtime.clone(), WorkloadCapability::GetCertificate),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/certificate/server" => RequireCapability::new(ServerCertHandler::new(hsm.clone(), config).with_enrollment(enrollment), runtime.clone(), WorkloadCapability::GetCertificate),
+            post  Version2019_11_05 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/token"                           => ModuleTokenHandler::new(key_store.clone(), management_auth.token_lifetime_secs()),
+            put   Version2019_11_05 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/secrets/(?P<secret>[^/]+)"        => RequireCapability::new(SetSecretHandler::new(secret_store.clone()), runtime.clone(), WorkloadCapability::Encrypt),
+            get   Version2019_11_05 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/secrets/(?P<secret>[^/]+)"        => RequireCapability::new(GetSecretHandler::new(secret_store.clone()), runtime.clone(), WorkloadCapability::Decrypt),
+            post  Version2019_11_05 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/secrets/(?P<secret>[^/]+)/rotate" => RequireCapability::new(RotateSecretHandler::new(secret_store.clone()), runtime.clone(), WorkloadCapability::Encrypt),
 
             get   Version2018_06_28 runtime Policy::Anonymous => "/trust-bundle" => TrustBundleHandler::new(hsm),
         );
@@ -0,0 +1,141 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use failure::ResultExt;
+use futures::{future, Future};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json;
+use workload::models::ModuleTokenResponse;
+
+use edgelet_core::crypto::{KeyIdentity, KeyStore};
+use edgelet_core::module_token::{create_module_token, MODULE_TOKEN_KEY_NAME};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+/// Issues the token that a module presents to the management API when it is configured to
+/// authorize calls with module tokens.
+pub struct ModuleTokenHandler<K>
+where
+    K: 'static + KeyStore + Clone,
+{
+    key_store: K,
+    lifetime: Duration,
+}
+
+impl<K> ModuleTokenHandler<K>
+where
+    K: 'static + KeyStore + Clone,
+{
+    pub fn new(key_store: K, lifetime_secs: u64) -> Self {
+        let lifetime = Duration::from_std(StdDuration::from_secs(lifetime_secs))
+            .unwrap_or_else(|_| Duration::max_value());
+        ModuleTokenHandler {
+            key_store,
+            lifetime,
+        }
+    }
+}
+
+impl<K> Handler<Parameters> for ModuleTokenHandler<K>
+where
+    K: 'static + KeyStore + Clone + Send + Sync,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .and_then(|name| {
+                let key = self
+                    .key_store
+                    .get(&KeyIdentity::Device, MODULE_TOKEN_KEY_NAME)
+                    .context(ErrorKind::ModuleToken)?;
+                let expiration = Utc::now()
+                    .checked_add_signed(self.lifetime)
+                    .ok_or(ErrorKind::ModuleToken)?;
+                let token =
+                    create_module_token(&key, name, expiration).context(ErrorKind::ModuleToken)?;
+
+                let body = serde_json::to_string(&ModuleTokenResponse::new(
+                    token,
+                    expiration.to_rfc3339(),
+                ))
+                .context(ErrorKind::ModuleToken)?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, body.len().to_string().as_str())
+                    .body(body.into())
+                    .context(ErrorKind::ModuleToken)?;
+                Ok(response)
+            })
+            .unwrap_or_else(|e| e.into_response());
+
+        Box::new(future::ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use futures::Stream;
+
+    use edgelet_core::crypto::{DerivedKeyStore, MemoryKey};
+    use edgelet_core::module_token::validate_module_token;
+
+    use super::*;
+
+    #[test]
+    fn success() {
+        // arrange
+        let key_store = DerivedKeyStore::new(MemoryKey::new("key"));
+        let handler = ModuleTokenHandler::new(key_store.clone(), 60);
+        let parameters =
+            Parameters::with_captures(vec![(Some("name".to_string()), "m1".to_string())]);
+        let request = Request::post("http://localhost/modules/m1/token")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, parameters).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let b = response.into_body().concat2().wait().unwrap();
+        let response: ModuleTokenResponse = serde_json::from_slice(&b).unwrap();
+
+        let expiration = DateTime::parse_from_rfc3339(response.expiration()).unwrap();
+        let lifetime = expiration.signed_duration_since(Utc::now());
+        assert!(lifetime > Duration::seconds(50) && lifetime <= Duration::seconds(60));
+
+        let key = key_store
+            .get(&KeyIdentity::Device, MODULE_TOKEN_KEY_NAME)
+            .unwrap();
+        let module_id = validate_module_token(&key, response.token(), Utc::now()).unwrap();
+        assert_eq!(module_id, "m1");
+    }
+
+    #[test]
+    fn missing_name() {
+        // arrange
+        let handler = ModuleTokenHandler::new(DerivedKeyStore::new(MemoryKey::new("key")), 60);
+        let request = Request::post("http://localhost/modules//token")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
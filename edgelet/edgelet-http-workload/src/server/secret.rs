@@ -0,0 +1,293 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::convert::TryFrom;
+
+use base64;
+use failure::{Fail, ResultExt};
+use futures::{future, Future, IntoFuture, Stream};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Serialize;
+use serde_json;
+
+use edgelet_core::{Decrypt, Encrypt, Error as CoreError, ErrorKind as CoreErrorKind, SecretStore};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use workload::models::{RotateSecretResponse, Secret};
+
+use crate::error::{Error, ErrorKind, SecretOperation};
+use crate::IntoResponse;
+
+pub struct SetSecretHandler<H> {
+    store: SecretStore<H>,
+}
+
+impl<H> SetSecretHandler<H> {
+    pub fn new(store: SecretStore<H>) -> Self {
+        SetSecretHandler { store }
+    }
+}
+
+impl<H> Handler<Parameters> for SetSecretHandler<H>
+where
+    H: Decrypt + Encrypt + Clone + Send + Sync + 'static,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let store = self.store.clone();
+
+        let response = secret_params(&params)
+            .map(|(module_id, name)| {
+                req.into_body().concat2().then(|body| {
+                    let body = body.context(ErrorKind::SecretOperation(SecretOperation::Set))?;
+                    Ok((module_id, name, body))
+                })
+            })
+            .into_future()
+            .flatten()
+            .and_then(move |(module_id, name, body)| -> Result<_, Error> {
+                let request: Secret =
+                    serde_json::from_slice(&body).context(ErrorKind::MalformedRequestBody)?;
+                let value =
+                    base64::decode(request.value()).context(ErrorKind::MalformedRequestBody)?;
+                store
+                    .set(&module_id, &name, &value)
+                    .map_err(|err| store_error(err, SecretOperation::Set))?;
+
+                let response = Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::empty())
+                    .context(ErrorKind::SecretOperation(SecretOperation::Set))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+pub struct GetSecretHandler<H> {
+    store: SecretStore<H>,
+}
+
+impl<H> GetSecretHandler<H> {
+    pub fn new(store: SecretStore<H>) -> Self {
+        GetSecretHandler { store }
+    }
+}
+
+impl<H> Handler<Parameters> for GetSecretHandler<H>
+where
+    H: Decrypt + Encrypt + Clone + Send + Sync + 'static,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = secret_params(&params)
+            .and_then(|(module_id, name)| {
+                let value = self
+                    .store
+                    .get(&module_id, &name)
+                    .map_err(|err| store_error(err, SecretOperation::Get))?;
+                json_response(&Secret::new(base64::encode(&value)), SecretOperation::Get)
+            })
+            .unwrap_or_else(IntoResponse::into_response);
+
+        Box::new(future::ok(response))
+    }
+}
+
+pub struct RotateSecretHandler<H> {
+    store: SecretStore<H>,
+}
+
+impl<H> RotateSecretHandler<H> {
+    pub fn new(store: SecretStore<H>) -> Self {
+        RotateSecretHandler { store }
+    }
+}
+
+impl<H> Handler<Parameters> for RotateSecretHandler<H>
+where
+    H: Decrypt + Encrypt + Clone + Send + Sync + 'static,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = secret_params(&params)
+            .and_then(|(module_id, name)| {
+                let key_version = self
+                    .store
+                    .rotate(&module_id, &name)
+                    .map_err(|err| store_error(err, SecretOperation::Rotate))?;
+                let key_version = i32::try_from(key_version)
+                    .context(ErrorKind::SecretOperation(SecretOperation::Rotate))?;
+                json_response(
+                    &RotateSecretResponse::new(key_version),
+                    SecretOperation::Rotate,
+                )
+            })
+            .unwrap_or_else(IntoResponse::into_response);
+
+        Box::new(future::ok(response))
+    }
+}
+
+fn secret_params(params: &Parameters) -> Result<(String, String), Error> {
+    let module_id = params
+        .name("name")
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))?;
+    let name = params
+        .name("secret")
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("secret")))?;
+    Ok((module_id.to_string(), name.to_string()))
+}
+
+fn store_error(err: CoreError, operation: SecretOperation) -> Error {
+    let kind = match err.kind() {
+        CoreErrorKind::SecretNotFound(name) => ErrorKind::SecretNotFound(name.clone()),
+        _ => ErrorKind::SecretOperation(operation),
+    };
+    Error::from(err.context(kind))
+}
+
+fn json_response<T: Serialize>(
+    body: &T,
+    operation: SecretOperation,
+) -> Result<Response<Body>, Error> {
+    let body = serde_json::to_string(body).context(ErrorKind::SecretOperation(operation))?;
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_LENGTH, body.len().to_string().as_str())
+        .body(body.into())
+        .context(ErrorKind::SecretOperation(operation))?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// This "encrypts" and "decrypts" by reversing the buffer's contents.
+    #[derive(Clone, Debug, Default)]
+    struct TestHsm;
+
+    impl Encrypt for TestHsm {
+        type Buffer = Vec<u8>;
+
+        fn encrypt(
+            &self,
+            _client_id: &[u8],
+            plaintext: &[u8],
+            _initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, CoreError> {
+            Ok(plaintext.iter().rev().cloned().collect())
+        }
+    }
+
+    impl Decrypt for TestHsm {
+        type Buffer = Vec<u8>;
+
+        fn decrypt(
+            &self,
+            _client_id: &[u8],
+            ciphertext: &[u8],
+            _initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, CoreError> {
+            Ok(ciphertext.iter().rev().cloned().collect())
+        }
+    }
+
+    fn store(test_name: &str) -> (SecretStore<TestHsm>, PathBuf) {
+        let path =
+            env::temp_dir().join(format!("edgelet-http-workload-{}-secrets.json", test_name));
+        let _ = fs::remove_file(&path);
+        (SecretStore::new(TestHsm, path.clone()).unwrap(), path)
+    }
+
+    fn params() -> Parameters {
+        Parameters::with_captures(vec![
+            (Some("name".to_string()), "m1".to_string()),
+            (Some("secret".to_string()), "api-key".to_string()),
+        ])
+    }
+
+    #[test]
+    fn set_then_get_returns_value() {
+        let (store, path) = store("set-get");
+
+        let body = serde_json::to_string(&Secret::new(base64::encode("hunter2"))).unwrap();
+        let response = SetSecretHandler::new(store.clone())
+            .handle(Request::new(body.into()), params())
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+
+        let response = GetSecretHandler::new(store)
+            .handle(Request::default(), params())
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let secret: Secret = serde_json::from_slice(&body).unwrap();
+        assert_eq!(&base64::encode("hunter2"), secret.value());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn set_with_unencoded_value_fails() {
+        let (store, _) = store("set-unencoded");
+
+        let body = serde_json::to_string(&Secret::new("!@#$%".to_string())).unwrap();
+        let response = SetSecretHandler::new(store)
+            .handle(Request::new(body.into()), params())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[test]
+    fn get_unknown_secret_responds_with_not_found() {
+        let (store, _) = store("get-unknown");
+
+        let response = GetSecretHandler::new(store)
+            .handle(Request::default(), params())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[test]
+    fn rotate_responds_with_new_key_version() {
+        let (store, path) = store("rotate");
+        store.set("m1", "api-key", b"hunter2").unwrap();
+
+        let response = RotateSecretHandler::new(store.clone())
+            .handle(Request::default(), params())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let response: RotateSecretResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(2, response.key_version());
+        assert_eq!(b"hunter2".to_vec(), store.get("m1", "api-key").unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -8,6 +8,7 @@
 #![allow(clippy::must_use_candidate)]
 
 use std::env;
+use std::path::Path;
 use std::str;
 
 use chrono::{Duration, Utc};
@@ -29,8 +30,8 @@ use tokio::prelude::*;
 use edgelet_core::crypto::MemoryKeyStore;
 use edgelet_core::{
     AuthId, Certificate, CertificateIssuer, CertificateProperties, CertificateType,
-    CreateCertificate, MakeModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState,
-    ModuleStatus, WorkloadConfig, IOTEDGED_CA_ALIAS,
+    CreateCertificate, MakeModuleRuntime, ManagementAuthSettings, ModuleRuntimeErrorReason,
+    ModuleRuntimeState, ModuleStatus, SecretStore, WorkloadConfig, IOTEDGED_CA_ALIAS,
 };
 use edgelet_hsm::{Crypto, HsmLock};
 use edgelet_http_workload::WorkloadService;
@@ -179,11 +180,24 @@ fn create_workload_service(module_id: &str) -> (WorkloadService, Crypto) {
         device_id: "d1".to_string(),
         cert_max_duration: 10_000_000,
     };
+    let secret_store = SecretStore::new(
+        crypto.clone(),
+        Path::new(&env::var(HOMEDIR_KEY).unwrap()).join("secrets.json"),
+    )
+    .unwrap();
 
     (
-        WorkloadService::new(&key_store, crypto.clone(), &runtime, config)
-            .wait()
-            .unwrap(),
+        WorkloadService::new(
+            &key_store,
+            crypto.clone(),
+            &runtime,
+            config,
+            &ManagementAuthSettings::default(),
+            &secret_store,
+            None,
+        )
+        .wait()
+        .unwrap(),
         crypto,
     )
 }
@@ -1,9 +1,14 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+#[cfg(unix)]
+use std::sync::Mutex;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
-use futures::future::Either;
+use futures::future::{Either, Loop};
+use log::{info, warn};
+#[cfg(unix)]
+use native_tls::Identity;
 #[cfg(unix)]
 use openssl::pkcs12::Pkcs12;
 #[cfg(unix)]
@@ -12,8 +17,12 @@ use openssl::pkey::PKey;
 use openssl::stack::Stack;
 #[cfg(unix)]
 use openssl::x509::X509;
+#[cfg(unix)]
+use tokio::net::TcpStream;
 use tokio::prelude::*;
 use tokio::timer::Delay;
+#[cfg(unix)]
+use tokio_tls::{Accept, TlsAcceptor};
 
 use edgelet_core::crypto::{
     Certificate as CryptoCertificate, CreateCertificate, KeyBytes, PrivateKey, Signature,
@@ -23,17 +32,25 @@ use failure::ResultExt;
 
 pub use crate::error::{Error, ErrorKind};
 
+/// How long `schedule_renewal` waits before it retries a failed renewal.
+const RENEWAL_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The percentage of the certificate's validity that may remain before it is renewed, unless
+/// set with `with_renewal_threshold_percent`.
+pub const DEFAULT_RENEWAL_THRESHOLD_PERCENT: u8 = 20;
+
 pub struct CertificateManager<C: CreateCertificate + Clone> {
     certificate: Arc<RwLock<Option<Certificate>>>,
     crypto: C,
     props: CertificateProperties,
-    creation_time: Instant,
+    renewal_threshold_percent: u8,
 }
 
 #[derive(Clone)]
 struct Certificate {
     cert: String,
     private_key: String,
+    creation_time: Instant,
 }
 
 impl<C: CreateCertificate + Clone> CertificateManager<C> {
@@ -42,7 +59,7 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
             certificate: Arc::new(RwLock::new(None)),
             crypto,
             props,
-            creation_time: Instant::now(),
+            renewal_threshold_percent: DEFAULT_RENEWAL_THRESHOLD_PERCENT,
         };
 
         {
@@ -51,7 +68,8 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
                 .write()
                 .expect("Locking the certificate for write failed.");
 
-            let created_certificate = cert_manager.create_cert()?;
+            let created_certificate =
+                create_cert(&cert_manager.crypto, &cert_manager.props, Instant::now())?;
 
             *cert = Some(created_certificate);
         }
@@ -59,38 +77,34 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
         Ok(cert_manager)
     }
 
+    pub fn with_renewal_threshold_percent(mut self, renewal_threshold_percent: u8) -> Self {
+        self.renewal_threshold_percent = renewal_threshold_percent;
+        self
+    }
+
     // Convenience function since native-tls does not yet support PEM
     // and since everything else uses PEM certificates, we want to keep
     // the actual storage of the certificate in the PEM format.
     #[cfg(unix)]
     pub fn get_pkcs12_certificate(&self) -> Result<Vec<u8>, Error> {
-        let stored_cert_bundle = self.get_certificate()?;
-
-        let cert = stored_cert_bundle.cert.as_bytes();
-
-        let mut certs =
-            X509::stack_from_pem(cert).with_context(|_| ErrorKind::CertificateConversionError)?;
-
-        let mut ca_certs = Stack::new().with_context(|_| ErrorKind::CertificateConversionError)?;
-        for cert in certs.split_off(1) {
-            ca_certs
-                .push(cert)
-                .with_context(|_| ErrorKind::CertificateConversionError)?;
-        }
-
-        let key = PKey::private_key_from_pem(stored_cert_bundle.private_key.as_bytes())
-            .expect("Error processing private key from pem");
-
-        let server_cert = &certs[0];
-        let mut builder = Pkcs12::builder();
-        builder.ca(ca_certs);
-        let pkcs_certs = builder
-            .build("", "", &key, &server_cert)
-            .with_context(|_| ErrorKind::CertificateConversionError)?;
+        pkcs12_certificate(&self.get_certificate()?)
+    }
 
-        Ok(pkcs_certs
-            .to_der()
-            .with_context(|_| ErrorKind::CertificateConversionError)?)
+    /// A TLS acceptor for the manager's certificate. The acceptor keeps up with renewals: once
+    /// the certificate is renewed, connections are accepted with the renewed one, also by
+    /// listeners that were bound before.
+    #[cfg(unix)]
+    pub fn tls_acceptor(
+        &self,
+        min_protocol_version: Option<native_tls::Protocol>,
+    ) -> Result<RenewingTlsAcceptor, Error> {
+        let certificate = self.get_certificate()?;
+        let acceptor = build_tls_acceptor(&certificate, min_protocol_version)?;
+        Ok(RenewingTlsAcceptor {
+            certificate: self.certificate.clone(),
+            min_protocol_version,
+            current: Arc::new(Mutex::new((certificate.cert, Arc::new(acceptor)))),
+        })
     }
 
     pub fn get_stored_cert_bytes(&self) -> Result<String, Error> {
@@ -99,6 +113,8 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
         Ok(stored_cert.cert)
     }
 
+    /// Calls `expiration_callback` shortly before the certificate expires. If the certificate is
+    /// renewed in the meantime, the timer waits for the renewed certificate to expire instead.
     pub fn schedule_expiration_timer<F>(
         &self,
         expiration_callback: F,
@@ -108,7 +124,9 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
     {
         // Now, let's set a timer to expire this certificate
         // expire the certificate with 2 minutes remaining in it's lifetime
-        let when = self.compute_certificate_alarm_time();
+        let certificate = self.certificate.clone();
+        let validity = Duration::from_secs(*self.props.validity_in_secs());
+        let when = alarm_time(&certificate, validity);
 
         // Fail if the cert has already been expired when the call to create
         // a timer happens.
@@ -118,16 +136,75 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
             )))
         } else {
             Either::B(
-                Delay::new(when)
-                    .map_err(|_| Error::from(ErrorKind::CertificateTimerCreationError))
-                    .and_then(move |_| match expiration_callback() {
-                        Ok(_) => Ok(()),
-                        Err(_) => Err(Error::from(ErrorKind::CertificateTimerRuntimeError)),
-                    }),
+                future::loop_fn(when, move |when| {
+                    let certificate = certificate.clone();
+                    Delay::new(when)
+                        .map_err(|_| Error::from(ErrorKind::CertificateTimerCreationError))
+                        .map(move |_| {
+                            let renewed_when = alarm_time(&certificate, validity);
+                            if renewed_when > when {
+                                Loop::Continue(renewed_when)
+                            } else {
+                                Loop::Break(())
+                            }
+                        })
+                })
+                .and_then(move |_| match expiration_callback() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(Error::from(ErrorKind::CertificateTimerRuntimeError)),
+                }),
             )
         }
     }
 
+    /// Renews the certificate once less than `renewal_threshold_percent` of its validity
+    /// remains, and then waits for the renewed certificate to reach the threshold. Everything
+    /// that reads the certificate from this manager afterwards, including the acceptors from
+    /// `tls_acceptor`, gets the renewed one.
+    ///
+    /// The returned future only completes if the timer fails. A failed renewal is retried an hour
+    /// later.
+    pub fn schedule_renewal(&self) -> impl Future<Item = (), Error = Error> {
+        let certificate = self.certificate.clone();
+        let crypto = self.crypto.clone();
+        let props = self.props.clone();
+        let validity = Duration::from_secs(*props.validity_in_secs());
+        let renewal_threshold_percent = self.renewal_threshold_percent;
+
+        future::loop_fn(None, move |retry_at| {
+            let when = retry_at.unwrap_or_else(|| {
+                let creation_time = certificate
+                    .read()
+                    .expect("Locking the certificate for read failed.")
+                    .as_ref()
+                    .map_or_else(Instant::now, |cert| cert.creation_time);
+                renewal_time(creation_time, validity, renewal_threshold_percent)
+            });
+
+            let certificate = certificate.clone();
+            let crypto = crypto.clone();
+            let props = props.clone();
+            Delay::new(when)
+                .map_err(|_| Error::from(ErrorKind::CertificateTimerRuntimeError))
+                .map(move |_| {
+                    let now = Instant::now();
+                    match renew_if_needed(
+                        &certificate,
+                        &crypto,
+                        &props,
+                        renewal_threshold_percent,
+                        now,
+                    ) {
+                        Ok(()) => Loop::Continue(None),
+                        Err(err) => {
+                            warn!("Could not renew the {} certificate: {}", props.alias(), err);
+                            Loop::Continue(Some(now + RENEWAL_RETRY_INTERVAL))
+                        }
+                    }
+                })
+        })
+    }
+
     fn get_certificate(&self) -> Result<Certificate, Error> {
         // Try to directly read
         let stored_cert = self
@@ -141,71 +218,230 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
         }
     }
 
-    fn create_cert(&self) -> Result<Certificate, Error> {
-        // In some use cases, the CA cert might change - to protect against that,
-        // we will retry once (after attempting to delete) if the cert creation fails.
-        let cert = if let Ok(val) = self.crypto.create_certificate(&self.props) {
-            val
-        } else {
-            self.crypto
-                .destroy_certificate(self.props.alias().to_string())
-                .with_context(|_| ErrorKind::CertificateDeletionError)?;
-            self.crypto
-                .create_certificate(&self.props)
-                .with_context(|_| ErrorKind::CertificateCreationError)?
-        };
+    #[cfg(test)]
+    fn has_certificate(&self) -> bool {
+        !self
+            .certificate
+            .read()
+            .expect("Locking the certificate for read failed.")
+            .is_none()
+    }
+}
 
-        let cert_pem = cert
-            .pem()
-            .with_context(|_| ErrorKind::CertificateCreationError)?;
+/// Accepts TLS connections with the current certificate of the `CertificateManager` it was
+/// created by. Clones share the acceptor.
+#[cfg(unix)]
+#[derive(Clone)]
+pub struct RenewingTlsAcceptor {
+    certificate: Arc<RwLock<Option<Certificate>>>,
+    min_protocol_version: Option<native_tls::Protocol>,
+    // The certificate PEM that the acceptor was built with
+    current: Arc<Mutex<(String, Arc<TlsAcceptor>)>>,
+}
 
-        let cert_private_key = cert
-            .get_private_key()
-            .with_context(|_| ErrorKind::CertificateCreationError)?;
+#[cfg(unix)]
+impl RenewingTlsAcceptor {
+    pub fn accept(&self, stream: TcpStream) -> Accept<TcpStream> {
+        self.acceptor().accept(stream)
+    }
 
-        let pk = match cert_private_key {
-            Some(pk) => pk,
-            None => panic!("Unable to acquire a private key."),
-        };
+    /// The acceptor for the current certificate, which is rebuilt if the certificate was renewed
+    /// since the last connection. If the renewed certificate can't be used, connections keep
+    /// being accepted with the previous one.
+    fn acceptor(&self) -> Arc<TlsAcceptor> {
+        let certificate = self
+            .certificate
+            .read()
+            .expect("Locking the certificate for read failed.");
+        let mut current = self
+            .current
+            .lock()
+            .expect("Locking the TLS acceptor failed.");
+
+        if let Some(certificate) = certificate.as_ref() {
+            if current.0 != certificate.cert {
+                match build_tls_acceptor(certificate, self.min_protocol_version) {
+                    Ok(acceptor) => current.1 = Arc::new(acceptor),
+                    Err(err) => warn!("Could not use the renewed TLS certificate: {}", err),
+                }
+                current.0 = certificate.cert.clone();
+            }
+        }
 
-        // Our implementations do not return a ref, and if they did, it would be unusable by Tokio
-        // a ref simply is a label/alias to a private key, not the actual bits.
-        let pk_bytes = match pk {
-            PrivateKey::Ref(_) => panic!(
-                "A reference private key does not contain the bits needed for the TLS certificate."
-            ),
-            PrivateKey::Key(KeyBytes::Pem(k)) => k,
-        };
+        current.1.clone()
+    }
+}
 
-        let cert_str = String::from_utf8(cert_pem.as_ref().to_vec())
-            .with_context(|_| ErrorKind::CertificateCreationError)?;
+#[cfg(unix)]
+fn pkcs12_certificate(certificate: &Certificate) -> Result<Vec<u8>, Error> {
+    let cert = certificate.cert.as_bytes();
 
-        let key_str = String::from_utf8(pk_bytes.as_bytes().to_vec())
-            .with_context(|_| ErrorKind::CertificateCreationError)?;
+    let mut certs =
+        X509::stack_from_pem(cert).with_context(|_| ErrorKind::CertificateConversionError)?;
 
-        Ok(Certificate {
-            cert: cert_str,
-            private_key: key_str,
-        })
+    let mut ca_certs = Stack::new().with_context(|_| ErrorKind::CertificateConversionError)?;
+    for cert in certs.split_off(1) {
+        ca_certs
+            .push(cert)
+            .with_context(|_| ErrorKind::CertificateConversionError)?;
     }
 
-    // Determine when to sound the alarm and renew the certificate.
-    #[allow(clippy::cast_possible_truncation)]
-    #[allow(clippy::cast_sign_loss)]
-    #[allow(clippy::cast_precision_loss)]
-    fn compute_certificate_alarm_time(&self) -> Instant {
-        self.creation_time
-            + Duration::from_secs((*self.props.validity_in_secs() as f64 * 0.95) as u64)
-    }
+    let key = PKey::private_key_from_pem(certificate.private_key.as_bytes())
+        .expect("Error processing private key from pem");
 
-    #[cfg(test)]
-    fn has_certificate(&self) -> bool {
-        !self
-            .certificate
-            .read()
-            .expect("Locking the certificate for read failed.")
-            .is_none()
+    let server_cert = &certs[0];
+    let mut builder = Pkcs12::builder();
+    builder.ca(ca_certs);
+    let pkcs_certs = builder
+        .build("", "", &key, &server_cert)
+        .with_context(|_| ErrorKind::CertificateConversionError)?;
+
+    Ok(pkcs_certs
+        .to_der()
+        .with_context(|_| ErrorKind::CertificateConversionError)?)
+}
+
+#[cfg(unix)]
+fn build_tls_acceptor(
+    certificate: &Certificate,
+    min_protocol_version: Option<native_tls::Protocol>,
+) -> Result<TlsAcceptor, Error> {
+    let cert = pkcs12_certificate(certificate).context(ErrorKind::TlsBootstrapError)?;
+
+    let cert_identity =
+        Identity::from_pkcs12(&cert, "").context(ErrorKind::TlsIdentityCreationError)?;
+
+    let tls_acceptor = native_tls::TlsAcceptor::builder(cert_identity)
+        .min_protocol_version(min_protocol_version)
+        .build()
+        .context(ErrorKind::TlsBootstrapError)?;
+    Ok(TlsAcceptor::from(tls_acceptor))
+}
+
+fn create_cert<C>(
+    crypto: &C,
+    props: &CertificateProperties,
+    creation_time: Instant,
+) -> Result<Certificate, Error>
+where
+    C: CreateCertificate,
+{
+    // In some use cases, the CA cert might change - to protect against that,
+    // we will retry once (after attempting to delete) if the cert creation fails.
+    let cert = if let Ok(val) = crypto.create_certificate(props) {
+        val
+    } else {
+        crypto
+            .destroy_certificate(props.alias().to_string())
+            .with_context(|_| ErrorKind::CertificateDeletionError)?;
+        crypto
+            .create_certificate(props)
+            .with_context(|_| ErrorKind::CertificateCreationError)?
+    };
+
+    let cert_pem = cert
+        .pem()
+        .with_context(|_| ErrorKind::CertificateCreationError)?;
+
+    let cert_private_key = cert
+        .get_private_key()
+        .with_context(|_| ErrorKind::CertificateCreationError)?;
+
+    let pk = match cert_private_key {
+        Some(pk) => pk,
+        None => panic!("Unable to acquire a private key."),
+    };
+
+    // Our implementations do not return a ref, and if they did, it would be unusable by Tokio
+    // a ref simply is a label/alias to a private key, not the actual bits.
+    let pk_bytes = match pk {
+        PrivateKey::Ref(_) => panic!(
+            "A reference private key does not contain the bits needed for the TLS certificate."
+        ),
+        PrivateKey::Key(KeyBytes::Pem(k)) => k,
+    };
+
+    let cert_str = String::from_utf8(cert_pem.as_ref().to_vec())
+        .with_context(|_| ErrorKind::CertificateCreationError)?;
+
+    let key_str = String::from_utf8(pk_bytes.as_bytes().to_vec())
+        .with_context(|_| ErrorKind::CertificateCreationError)?;
+
+    Ok(Certificate {
+        cert: cert_str,
+        private_key: key_str,
+        creation_time,
+    })
+}
+
+fn renew_if_needed<C>(
+    certificate: &RwLock<Option<Certificate>>,
+    crypto: &C,
+    props: &CertificateProperties,
+    renewal_threshold_percent: u8,
+    now: Instant,
+) -> Result<(), Error>
+where
+    C: CreateCertificate,
+{
+    let validity = Duration::from_secs(*props.validity_in_secs());
+    let renew = certificate
+        .read()
+        .expect("Locking the certificate for read failed.")
+        .as_ref()
+        .map_or(true, |cert| {
+            needs_renewal(cert.creation_time, validity, renewal_threshold_percent, now)
+        });
+
+    if renew {
+        // The certificate is destroyed first, since creating a certificate with the alias of an
+        // existing one may return the existing certificate.
+        crypto
+            .destroy_certificate(props.alias().to_string())
+            .with_context(|_| ErrorKind::CertificateDeletionError)?;
+        let renewed = create_cert(crypto, props, now)?;
+
+        *certificate
+            .write()
+            .expect("Locking the certificate for write failed.") = Some(renewed);
+        info!("Renewed the {} certificate", props.alias());
     }
+
+    Ok(())
+}
+
+fn needs_renewal(
+    creation_time: Instant,
+    validity: Duration,
+    renewal_threshold_percent: u8,
+    now: Instant,
+) -> bool {
+    now >= renewal_time(creation_time, validity, renewal_threshold_percent)
+}
+
+/// When a certificate created at `creation_time` has `renewal_threshold_percent` of its validity
+/// left.
+fn renewal_time(
+    creation_time: Instant,
+    validity: Duration,
+    renewal_threshold_percent: u8,
+) -> Instant {
+    let renewal_threshold_percent = u32::from(renewal_threshold_percent.min(100));
+    creation_time + validity * (100 - renewal_threshold_percent) / 100
+}
+
+// Determine when to sound the alarm and restart the listeners.
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+#[allow(clippy::cast_precision_loss)]
+fn alarm_time(certificate: &RwLock<Option<Certificate>>, validity: Duration) -> Instant {
+    let creation_time = certificate
+        .read()
+        .expect("Locking the certificate for read failed.")
+        .as_ref()
+        .map_or_else(Instant::now, |cert| cert.creation_time);
+
+    creation_time + Duration::from_secs((validity.as_secs() as f64 * 0.95) as u64)
 }
 
 #[cfg(test)]
@@ -214,6 +450,8 @@ mod tests {
     use edgelet_core::crypto::{KeyBytes, PrivateKey};
     use edgelet_core::{CertificateProperties, CertificateType};
 
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use chrono::{DateTime, Utc};
 
     use edgelet_core::{
@@ -221,6 +459,8 @@ mod tests {
         CreateCertificate as CoreCreateCertificate, Error as CoreError,
         PrivateKey as CorePrivateKey,
     };
+    #[cfg(unix)]
+    use edgelet_test_utils::cert::{PemCertificate, TestCa, TestCertUsage};
 
     #[test]
     pub fn test_cert_manager_pem_has_cert() {
@@ -237,7 +477,7 @@ mod tests {
 
         let cert = manager.get_certificate().unwrap();
 
-        assert_eq!(cert.cert, "test".to_string());
+        assert_eq!(cert.cert, "test-1".to_string());
 
         assert_eq!(manager.has_certificate(), true);
     }
@@ -286,14 +526,111 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_needs_renewal_below_threshold() {
+        let creation_time = Instant::now();
+        let validity = Duration::from_secs(100);
+
+        let at = |secs| creation_time + Duration::from_secs(secs);
+        assert!(!needs_renewal(creation_time, validity, 20, at(0)));
+        assert!(!needs_renewal(creation_time, validity, 20, at(79)));
+        assert!(needs_renewal(creation_time, validity, 20, at(80)));
+        assert!(needs_renewal(creation_time, validity, 20, at(200)));
+        assert_eq!(at(80), renewal_time(creation_time, validity, 20));
+    }
+
+    #[test]
+    pub fn test_cert_manager_renews_certificate() {
+        let crypto = TestCrypto::new().unwrap();
+
+        let edgelet_cert_props = CertificateProperties::new(
+            100,
+            "IOTEDGED_TLS_COMMONNAME".to_string(),
+            CertificateType::Server,
+            "iotedge-tls".to_string(),
+        );
+
+        let manager = CertificateManager::new(crypto, edgelet_cert_props).unwrap();
+        let creation_time = manager.get_certificate().unwrap().creation_time;
+        let renew_at = |secs| {
+            renew_if_needed(
+                &manager.certificate,
+                &manager.crypto,
+                &manager.props,
+                20,
+                creation_time + Duration::from_secs(secs),
+            )
+            .unwrap()
+        };
+
+        // Not yet within the threshold
+        renew_at(50);
+        assert_eq!("test-1", manager.get_stored_cert_bytes().unwrap());
+        assert_eq!(
+            creation_time + Duration::from_secs(95),
+            alarm_time(&manager.certificate, Duration::from_secs(100))
+        );
+
+        renew_at(90);
+        assert_eq!("test-2", manager.get_stored_cert_bytes().unwrap());
+        assert_eq!(
+            creation_time + Duration::from_secs(90),
+            manager.get_certificate().unwrap().creation_time
+        );
+
+        // The expiration timer follows the renewed certificate
+        assert_eq!(
+            creation_time + Duration::from_secs(90 + 95),
+            alarm_time(&manager.certificate, Duration::from_secs(100))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_tls_acceptor_uses_renewed_certificate() {
+        let crypto = TestCaCrypto(Arc::new(TestCa::new("test-ca")));
+
+        let edgelet_cert_props = CertificateProperties::new(
+            100,
+            "localhost".to_string(),
+            CertificateType::Server,
+            "iotedge-tls".to_string(),
+        );
+
+        let manager = CertificateManager::new(crypto, edgelet_cert_props).unwrap();
+        let acceptor = manager.tls_acceptor(None).unwrap();
+        let first = acceptor.acceptor();
+        assert!(Arc::ptr_eq(&first, &acceptor.acceptor()));
+
+        let creation_time = manager.get_certificate().unwrap().creation_time;
+        renew_if_needed(
+            &manager.certificate,
+            &manager.crypto,
+            &manager.props,
+            20,
+            creation_time + Duration::from_secs(90),
+        )
+        .unwrap();
+
+        let renewed = acceptor.clone().acceptor();
+        assert!(!Arc::ptr_eq(&first, &renewed));
+        assert_eq!(
+            manager.get_stored_cert_bytes().unwrap(),
+            acceptor.current.lock().unwrap().0
+        );
+        assert!(Arc::ptr_eq(&renewed, &acceptor.acceptor()));
+    }
+
     #[derive(Clone)]
     struct TestCrypto {
-        created: bool,
+        created: Arc<AtomicUsize>,
     }
 
     impl TestCrypto {
         pub fn new() -> Result<Self, CoreError> {
-            Ok(Self { created: true })
+            Ok(Self {
+                created: Arc::new(AtomicUsize::new(0)),
+            })
         }
     }
 
@@ -304,7 +641,10 @@ mod tests {
             &self,
             _properties: &CoreCertificateProperties,
         ) -> Result<Self::Certificate, CoreError> {
-            Ok(TestCertificate {})
+            let created = self.created.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(TestCertificate {
+                pem: format!("test-{}", created),
+            })
         }
 
         fn destroy_certificate(&self, _alias: String) -> Result<(), CoreError> {
@@ -312,18 +652,22 @@ mod tests {
         }
 
         fn get_certificate(&self, _alias: String) -> Result<Self::Certificate, CoreError> {
-            Ok(TestCertificate {})
+            Ok(TestCertificate {
+                pem: "test".to_string(),
+            })
         }
     }
 
-    struct TestCertificate {}
+    struct TestCertificate {
+        pem: String,
+    }
 
     impl CoreCertificate for TestCertificate {
         type Buffer = String;
         type KeyBuffer = Vec<u8>;
 
         fn pem(&self) -> Result<Self::Buffer, CoreError> {
-            Ok("test".to_string())
+            Ok(self.pem.clone())
         }
 
         fn get_private_key(&self) -> Result<Option<CorePrivateKey<Self::KeyBuffer>>, CoreError> {
@@ -342,4 +686,59 @@ mod tests {
             Ok("IOTEDGED_TLS_COMMONNAME".to_string())
         }
     }
+
+    /// Issues a new server certificate from the test CA for every certificate that is created.
+    #[cfg(unix)]
+    #[derive(Clone)]
+    struct TestCaCrypto(Arc<TestCa>);
+
+    #[cfg(unix)]
+    impl CoreCreateCertificate for TestCaCrypto {
+        type Certificate = TestCaCertificate;
+
+        fn create_certificate(
+            &self,
+            properties: &CoreCertificateProperties,
+        ) -> Result<Self::Certificate, CoreError> {
+            Ok(TestCaCertificate(
+                self.0
+                    .issue(properties.common_name(), TestCertUsage::Server),
+            ))
+        }
+
+        fn destroy_certificate(&self, _alias: String) -> Result<(), CoreError> {
+            Ok(())
+        }
+
+        fn get_certificate(&self, _alias: String) -> Result<Self::Certificate, CoreError> {
+            unimplemented!()
+        }
+    }
+
+    #[cfg(unix)]
+    struct TestCaCertificate(PemCertificate);
+
+    #[cfg(unix)]
+    impl CoreCertificate for TestCaCertificate {
+        type Buffer = Vec<u8>;
+        type KeyBuffer = Vec<u8>;
+
+        fn pem(&self) -> Result<Self::Buffer, CoreError> {
+            Ok(self.0.get_full_certificate().to_vec())
+        }
+
+        fn get_private_key(&self) -> Result<Option<CorePrivateKey<Self::KeyBuffer>>, CoreError> {
+            Ok(Some(PrivateKey::Key(KeyBytes::Pem(
+                self.0.get_private_key().to_vec(),
+            ))))
+        }
+
+        fn get_valid_to(&self) -> Result<DateTime<Utc>, CoreError> {
+            unimplemented!()
+        }
+
+        fn get_common_name(&self) -> Result<String, CoreError> {
+            unimplemented!()
+        }
+    }
 }
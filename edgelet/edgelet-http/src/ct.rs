@@ -0,0 +1,208 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A client for the `add-chain` operation of a certificate transparency log (RFC 6962).
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future, Stream};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Method, Request, StatusCode, Uri};
+use openssl::x509::X509;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use crate::client::ClientImpl;
+use crate::error::{Error, ErrorKind};
+
+const ADD_CHAIN_PATH: &str = "ct/v1/add-chain";
+
+#[derive(Debug, Serialize)]
+struct AddChainRequest {
+    /// The base64-encoded DER of the leaf certificate, followed by the rest of its chain
+    chain: Vec<String>,
+}
+
+/// The signed certificate timestamp returned by the log for a submitted certificate.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SignedCertificateTimestamp {
+    sct_version: u8,
+    id: String,
+    timestamp: u64,
+    #[serde(default)]
+    extensions: String,
+    signature: String,
+}
+
+impl SignedCertificateTimestamp {
+    pub fn sct_version(&self) -> u8 {
+        self.sct_version
+    }
+
+    /// The base64-encoded SHA-256 hash of the log's public key.
+    pub fn log_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Milliseconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn extensions(&self) -> &str {
+        &self.extensions
+    }
+
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+}
+
+#[derive(Clone)]
+pub struct CtLogClient<C> {
+    client: C,
+    log_url: Url,
+}
+
+impl<C> CtLogClient<C>
+where
+    C: ClientImpl,
+{
+    pub fn new(client: C, mut log_url: Url) -> Self {
+        if !log_url.path().ends_with('/') {
+            let path = format!("{}/", log_url.path());
+            log_url.set_path(&path);
+        }
+
+        CtLogClient { client, log_url }
+    }
+
+    pub fn log_url(&self) -> &Url {
+        &self.log_url
+    }
+
+    /// Submits `chain`, a PEM certificate followed by its issuers, to the log.
+    pub fn add_chain(
+        &self,
+        chain: &[u8],
+    ) -> impl Future<Item = SignedCertificateTimestamp, Error = Error> + Send {
+        let request = self
+            .log_url
+            .join(ADD_CHAIN_PATH)
+            .with_context(|_| ErrorKind::UrlJoin(self.log_url.clone(), ADD_CHAIN_PATH.to_string()))
+            .map_err(Error::from)
+            .and_then(|url| -> Result<_, Error> {
+                let uri = url
+                    .as_str()
+                    .parse::<Uri>()
+                    .with_context(|_| ErrorKind::InvalidUrl(url.to_string()))?;
+
+                let certs = X509::stack_from_pem(chain).context(ErrorKind::CtLogSubmission)?;
+                let chain = certs
+                    .iter()
+                    .map(|cert| cert.to_der().map(|der| base64::encode(&der)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .context(ErrorKind::CtLogSubmission)?;
+                let body = serde_json::to_string(&AddChainRequest { chain })
+                    .context(ErrorKind::CtLogSubmission)?;
+
+                let mut request = Request::builder();
+                request
+                    .method(Method::POST)
+                    .uri(uri)
+                    .header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                let request = request
+                    .body(Body::from(body))
+                    .context(ErrorKind::CtLogSubmission)?;
+                Ok(request)
+            });
+
+        match request {
+            Ok(request) => future::Either::A(
+                self.client
+                    .call(request)
+                    .and_then(|response| {
+                        let (parts, body) = response.into_parts();
+                        body.concat2().map(move |body| (parts.status, body))
+                    })
+                    .then(|response| -> Result<_, Error> {
+                        let (status, body) = response
+                            .context(ErrorKind::Http)
+                            .context(ErrorKind::CtLogSubmission)?;
+                        if status != StatusCode::OK {
+                            return Err(Error::from(
+                                Error::http_with_error_response(status, &*body)
+                                    .context(ErrorKind::CtLogSubmission),
+                            ));
+                        }
+
+                        let sct =
+                            serde_json::from_slice(&body).context(ErrorKind::CtLogSubmission)?;
+                        Ok(sct)
+                    }),
+            ),
+            Err(err) => future::Either::B(future::err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Response;
+    use serde_json::{json, Value};
+
+    use edgelet_test_utils::cert::{TestCa, TestCertUsage};
+
+    #[test]
+    fn add_chain_returns_sct() {
+        let ca = TestCa::new("ct-test-ca");
+        let cert = ca.issue("module1", TestCertUsage::Server);
+
+        let handler = |req: Request<Body>| {
+            assert_eq!(Method::POST, *req.method());
+            assert_eq!("/logs/test/ct/v1/add-chain", req.uri().path());
+
+            let body = req.into_body().concat2().wait().unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(2, body["chain"].as_array().unwrap().len());
+
+            let sct = json!({
+                "sct_version": 0,
+                "id": "aPaY+B9kgr46jO65KB1M/HFRXWeT1ETRCmesu09P+8Q=",
+                "timestamp": 1_573_682_210_848_u64,
+                "extensions": "",
+                "signature": "BAMARzBFAiEA",
+            });
+            Ok::<_, hyper::Error>(Response::new(Body::from(sct.to_string())))
+        };
+
+        let client = CtLogClient::new(
+            handler,
+            Url::parse("https://ct.example.com/logs/test").unwrap(),
+        );
+        let sct = client
+            .add_chain(cert.get_full_certificate())
+            .wait()
+            .unwrap();
+
+        assert_eq!(0, sct.sct_version());
+        assert_eq!("aPaY+B9kgr46jO65KB1M/HFRXWeT1ETRCmesu09P+8Q=", sct.log_id());
+        assert_eq!(1_573_682_210_848, sct.timestamp());
+    }
+
+    #[test]
+    fn add_chain_fails_on_error_response() {
+        let ca = TestCa::new("ct-test-ca");
+
+        let handler = |_req: Request<Body>| {
+            let mut response = Response::new(Body::from("unknown anchor"));
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+            Ok::<_, hyper::Error>(response)
+        };
+
+        let client = CtLogClient::new(handler, Url::parse("https://ct.example.com/").unwrap());
+        let err = client.add_chain(&ca.cert_pem()).wait().unwrap_err();
+
+        assert_eq!(&ErrorKind::CtLogSubmission, err.kind());
+    }
+}
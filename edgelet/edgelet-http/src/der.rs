@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! The PKCS#7 structures used by the EST and SCEP clients, on top of the DER reader and writer
+//! of edgelet-core.
+
+pub(crate) use edgelet_core::der::{
+    read, read_optional, write, write_all, TAG_BIT_STRING, TAG_CONTEXT_0, TAG_CONTEXT_0_PRIMITIVE,
+    TAG_CONTEXT_1, TAG_INTEGER, TAG_NULL, TAG_OCTET_STRING, TAG_OID, TAG_PRINTABLE_STRING,
+    TAG_SEQUENCE, TAG_SET, TAG_UTF8_STRING,
+};
+
+/// 1.2.840.113549.1.7.1
+pub(crate) const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+/// 1.2.840.113549.1.7.2
+pub(crate) const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// 1.2.840.113549.1.7.3
+pub(crate) const OID_ENVELOPED_DATA: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x03];
+
+/// Encodes an `AlgorithmIdentifier` with NULL parameters.
+pub(crate) fn algorithm(oid: &[u8]) -> Vec<u8> {
+    write_all(TAG_SEQUENCE, &[&write(TAG_OID, oid), &write(TAG_NULL, &[])])
+}
+
+/// Returns the DER encoding of every certificate in the `certificates` field of a PKCS#7
+/// signedData structure, in the order they appear.
+pub(crate) fn pkcs7_certificates(der: &[u8]) -> Option<Vec<&[u8]>> {
+    // ContentInfo ::= SEQUENCE { contentType OID, content [0] EXPLICIT ANY }
+    let (_, content_info, _) = read(der, TAG_SEQUENCE)?;
+    let (_, content_type, content_info) = read(content_info, TAG_OID)?;
+    if content_type != OID_SIGNED_DATA {
+        return None;
+    }
+    let (_, content, _) = read(content_info, TAG_CONTEXT_0)?;
+
+    // SignedData ::= SEQUENCE { version INTEGER, digestAlgorithms SET, contentInfo SEQUENCE,
+    //                           certificates [0] IMPLICIT SET OF Certificate OPTIONAL, ... }
+    let (_, signed_data, _) = read(content, TAG_SEQUENCE)?;
+    let (_, _, signed_data) = read(signed_data, TAG_INTEGER)?;
+    let (_, _, signed_data) = read(signed_data, TAG_SET)?;
+    let (_, _, signed_data) = read(signed_data, TAG_SEQUENCE)?;
+    let (_, certificates, _) = read(signed_data, TAG_CONTEXT_0)?;
+
+    split_certificates(certificates)
+}
+
+/// Encodes a certs-only PKCS#7 signedData structure with the given DER-encoded certificates, like
+/// the ones EST and SCEP servers send.
+#[cfg(test)]
+pub(crate) fn certs_only(certificates: &[&[u8]]) -> Vec<u8> {
+    let signed_data = write_all(
+        TAG_SEQUENCE,
+        &[
+            &write(TAG_INTEGER, &[1]),
+            &write(TAG_SET, &[]),
+            &write_all(TAG_SEQUENCE, &[&write(TAG_OID, OID_DATA)]),
+            &write(TAG_CONTEXT_0, &certificates.concat()),
+            &write(TAG_SET, &[]),
+        ],
+    );
+    write_all(
+        TAG_SEQUENCE,
+        &[
+            &write(TAG_OID, OID_SIGNED_DATA),
+            &write(TAG_CONTEXT_0, &signed_data),
+        ],
+    )
+}
+
+/// Splits a concatenation of DER-encoded certificates.
+pub(crate) fn split_certificates(mut certificates: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut result = vec![];
+    while !certificates.is_empty() {
+        let (cert, _, rest) = read(certificates, TAG_SEQUENCE)?;
+        result.push(cert);
+        certificates = rest;
+    }
+    Some(result)
+}
+
+/// The issuer, serial number and subject of a DER-encoded certificate, each as a whole element.
+pub(crate) struct CertificateNames<'a> {
+    pub(crate) issuer: &'a [u8],
+    pub(crate) serial_number: &'a [u8],
+    pub(crate) subject: &'a [u8],
+}
+
+impl<'a> CertificateNames<'a> {
+    pub(crate) fn parse(cert: &'a [u8]) -> Option<Self> {
+        // Certificate ::= SEQUENCE { tbsCertificate SEQUENCE { version [0] EXPLICIT OPTIONAL,
+        //                            serialNumber INTEGER, signature SEQUENCE, issuer Name,
+        //                            validity SEQUENCE, subject Name, ... }, ... }
+        let (_, cert, _) = read(cert, TAG_SEQUENCE)?;
+        let (_, tbs, _) = read(cert, TAG_SEQUENCE)?;
+        let (_, tbs) = read_optional(tbs, TAG_CONTEXT_0)?;
+        let (serial_number, _, tbs) = read(tbs, TAG_INTEGER)?;
+        let (_, _, tbs) = read(tbs, TAG_SEQUENCE)?;
+        let (issuer, _, tbs) = read(tbs, TAG_SEQUENCE)?;
+        let (_, _, tbs) = read(tbs, TAG_SEQUENCE)?;
+        let (subject, _, _) = read(tbs, TAG_SEQUENCE)?;
+
+        Some(CertificateNames {
+            issuer,
+            serial_number,
+            subject,
+        })
+    }
+
+    /// Encodes the `IssuerAndSerialNumber` that identifies the certificate in PKCS#7 structures.
+    pub(crate) fn issuer_and_serial_number(&self) -> Vec<u8> {
+        write_all(TAG_SEQUENCE, &[self.issuer, self.serial_number])
+    }
+
+    pub(crate) fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+}
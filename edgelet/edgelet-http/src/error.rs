@@ -7,10 +7,11 @@ use std::str;
 use failure::{Backtrace, Compat, Context, Fail};
 use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use hyper::{Body, Response, StatusCode, Uri};
-use serde_json::json;
+use serde_json::Value;
 use systemd::Fd;
 use url::Url;
 
+use crate::est::EstOperation;
 use crate::IntoResponse;
 
 #[derive(Debug)]
@@ -44,6 +45,15 @@ pub enum ErrorKind {
     #[fail(display = "A valid certificate was not found")]
     CertificateNotFound,
 
+    #[fail(display = "Could not submit a certificate to the certificate transparency log")]
+    CtLogSubmission,
+
+    #[fail(display = "Could not {} with the EST server", _0)]
+    EstEnrollment(EstOperation),
+
+    #[fail(display = "Could not parse the certificates returned by the EST server")]
+    EstResponse,
+
     #[fail(display = "Could not perform HTTP request")]
     Http,
 
@@ -59,6 +69,18 @@ pub enum ErrorKind {
     #[fail(display = "Reading identity private key from PEM bytes failed {}", _0)]
     IdentityPrivateKeyRead(String),
 
+    #[fail(
+        display = "Image {} has no valid signature made with the configured key",
+        _0
+    )]
+    ImageSignatureInvalid(String),
+
+    #[fail(display = "Could not parse the public key for image signature verification")]
+    ImageSignatureKey,
+
+    #[fail(display = "Could not look up the signature of image {}", _0)]
+    ImageSignatureLookup(String),
+
     #[fail(display = "Could not initialize")]
     Initialization,
 
@@ -81,9 +103,45 @@ pub enum ErrorKind {
         path: String,
     },
 
+    #[fail(display = "Module {} is not authorized to call this endpoint", _0)]
+    ModuleNotAuthorized(String),
+
     #[fail(display = "Module not found")]
     ModuleNotFound(String),
 
+    #[fail(display = "The module token is missing or invalid")]
+    ModuleToken,
+
+    #[fail(display = "Could not write to the image cache at {}", _0)]
+    OciCache(String),
+
+    #[fail(display = "Blob {} doesn't match its digest", _0)]
+    OciDigestMismatch(String),
+
+    #[fail(display = "Could not get the latest digest of image {}", _0)]
+    OciLatestDigest(String),
+
+    #[fail(display = "Could not read the size of layer {}", _0)]
+    OciLayerSize(String),
+
+    #[fail(display = "Could not read manifest {}", _0)]
+    OciManifest(String),
+
+    #[fail(display = "The image has no manifest for linux/{}", _0)]
+    OciPlatform(String),
+
+    #[fail(display = "Could not pull image {}", _0)]
+    OciPull(String),
+
+    #[fail(display = "Layer entry {} is inside a symlinked directory", _0)]
+    OciSymlinkedPath(String),
+
+    #[fail(display = "Could not unpack layer {}", _0)]
+    OciUnpack(String),
+
+    #[fail(display = "Layers of media type {} are not supported", _0)]
+    OciUnsupportedMediaType(String),
+
     #[fail(display = "An error occurred for path {}", _0)]
     Path(String),
 
@@ -96,6 +154,38 @@ pub enum ErrorKind {
     )]
     PKCS12Identity(String),
 
+    #[fail(display = "Could not get the CA certificates of the SCEP server")]
+    ScepCaCertificates,
+
+    #[fail(
+        display = "None of the CA certificates of the SCEP server match the configured fingerprint"
+    )]
+    ScepCaFingerprint,
+
+    #[fail(display = "Could not enroll with the SCEP server")]
+    ScepEnrollment,
+
+    #[fail(display = "The SCEP server rejected the certificate request: {}", _0)]
+    ScepFailure(String),
+
+    #[fail(
+        display = "The SCEP server at {} is reached over plain http, so its CA fingerprint must be configured",
+        _0
+    )]
+    ScepInsecureUrl(String),
+
+    #[fail(
+        display = "The SCEP server has not issued the certificate yet (transaction {})",
+        _0
+    )]
+    ScepPending(String),
+
+    #[fail(display = "Could not parse the response of the SCEP server")]
+    ScepResponse,
+
+    #[fail(display = "SCEP enrollment requires RSA keys")]
+    ScepUnsupportedKey,
+
     #[fail(display = "An error occurred in the service")]
     ServiceError,
 
@@ -108,6 +198,9 @@ pub enum ErrorKind {
     #[fail(display = "Token source error")]
     TokenSource,
 
+    #[fail(display = "Too many requests to {}", _0)]
+    TooManyRequests(String),
+
     #[fail(display = "Could not parse trust bundle")]
     TrustBundle,
 
@@ -175,16 +268,22 @@ impl IntoResponse for Error {
             fail = cause;
         }
 
-        let status_code = match *self.kind() {
-            ErrorKind::Authorization | ErrorKind::ModuleNotFound(_) => StatusCode::NOT_FOUND,
-            ErrorKind::InvalidApiVersion(_) => StatusCode::BAD_REQUEST,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        let (status_code, code) = match *self.kind() {
+            ErrorKind::Authorization => (StatusCode::NOT_FOUND, ApiError::NOT_FOUND),
+            ErrorKind::ModuleNotAuthorized(_) => (StatusCode::FORBIDDEN, ApiError::FORBIDDEN),
+            ErrorKind::ModuleNotFound(_) => (StatusCode::NOT_FOUND, ApiError::MODULE_NOT_FOUND),
+            ErrorKind::ModuleToken => (StatusCode::UNAUTHORIZED, ApiError::UNAUTHORIZED),
+            ErrorKind::InvalidApiVersion(_) => {
+                (StatusCode::BAD_REQUEST, ApiError::INVALID_API_VERSION)
+            }
+            ErrorKind::TooManyRequests(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, ApiError::TOO_MANY_REQUESTS)
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, ApiError::INTERNAL_ERROR),
         };
 
-        let body = json!({
-            "message": message,
-        })
-        .to_string();
+        let body = serde_json::to_string(&ApiError::new(code, message))
+            .expect("serialization of ApiError failed.");
 
         Response::builder()
             .status(status_code)
@@ -201,6 +300,71 @@ impl IntoResponse for Compat<Error> {
     }
 }
 
+/// The body of an error response.
+///
+/// `code` is one of the associated constants below and is stable across releases, so clients
+/// can match on it instead of parsing `message`.
+#[derive(Clone, Debug, PartialEq, serde_derive::Serialize)]
+pub struct ApiError {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl ApiError {
+    /// The request conflicts with the current state of the module.
+    pub const CONFLICT: &'static str = "CONFLICT";
+    /// The module token is valid, but the module isn't allowed to call the endpoint.
+    pub const FORBIDDEN: &'static str = "FORBIDDEN";
+    /// Any error that doesn't have a more specific code.
+    pub const INTERNAL_ERROR: &'static str = "INTERNAL_ERROR";
+    /// The `api-version` query parameter is missing or not supported.
+    pub const INVALID_API_VERSION: &'static str = "INVALID_API_VERSION";
+    /// A path or query parameter is missing or malformed.
+    pub const INVALID_PARAMETER: &'static str = "INVALID_PARAMETER";
+    /// The request body is malformed or describes an invalid spec.
+    pub const INVALID_SPEC: &'static str = "INVALID_SPEC";
+    /// A request to IoT Hub failed.
+    pub const IOTHUB_ERROR: &'static str = "IOTHUB_ERROR";
+    /// The module doesn't exist.
+    pub const MODULE_NOT_FOUND: &'static str = "MODULE_NOT_FOUND";
+    /// The resource doesn't exist, or the caller isn't allowed to access it.
+    pub const NOT_FOUND: &'static str = "NOT_FOUND";
+    /// The module runtime failed to carry out the operation.
+    pub const RUNTIME_ERROR: &'static str = "RUNTIME_ERROR";
+    /// The caller exceeded the rate limit of the endpoint and should retry after the number of
+    /// seconds in the `Retry-After` header.
+    pub const TOO_MANY_REQUESTS: &'static str = "TOO_MANY_REQUESTS";
+    /// The module token is missing from the `Authorization` header, malformed or expired.
+    pub const UNAUTHORIZED: &'static str = "UNAUTHORIZED";
+
+    pub fn new(code: &'static str, message: String) -> Self {
+        ApiError {
+            code,
+            message,
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        self.details.as_ref()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BindListenerType {
     Address(SocketAddr),
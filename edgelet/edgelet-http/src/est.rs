@@ -0,0 +1,403 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A client for the `/simpleenroll` and `/simplereenroll` operations of Enrollment over Secure
+//! Transport (EST, RFC 7030).
+//!
+//! The client authenticates with its identity certificate over mutual TLS, sends a base64-encoded
+//! PKCS#10 certificate request and parses the certs-only PKCS#7 structure that the server returns.
+
+use std::fmt;
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future, Stream};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Method, Request, StatusCode, Uri};
+use openssl::x509::{X509Req, X509};
+use url::Url;
+
+use crate::client::ClientImpl;
+use crate::der;
+use crate::error::{Error, ErrorKind};
+use crate::{MaybeProxyClient, PemCertificate};
+
+const CONTENT_TRANSFER_ENCODING: &str = "content-transfer-encoding";
+const PKCS10_CONTENT_TYPE: &str = "application/pkcs10";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EstOperation {
+    SimpleEnroll,
+    SimpleReenroll,
+}
+
+impl EstOperation {
+    fn path(self) -> &'static str {
+        match self {
+            EstOperation::SimpleEnroll => "simpleenroll",
+            EstOperation::SimpleReenroll => "simplereenroll",
+        }
+    }
+}
+
+impl fmt::Display for EstOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path())
+    }
+}
+
+#[derive(Clone)]
+pub struct EstClient<C> {
+    client: C,
+    base_url: Url,
+}
+
+impl EstClient<MaybeProxyClient> {
+    /// Creates a client for the EST server at `base_url` (usually ending in `/.well-known/est`)
+    /// that authenticates with `identity_certificate`.
+    pub fn from_identity(
+        base_url: Url,
+        identity_certificate: PemCertificate,
+        trust_bundle: Option<PemCertificate>,
+        proxy_uri: Option<Uri>,
+    ) -> Result<Self, Error> {
+        let client = MaybeProxyClient::new(proxy_uri, Some(identity_certificate), trust_bundle)?;
+        Ok(EstClient::new(client, base_url))
+    }
+}
+
+impl<C> EstClient<C>
+where
+    C: ClientImpl,
+{
+    pub fn new(client: C, mut base_url: Url) -> Self {
+        // make sure the operation is joined onto the base path instead of replacing its last
+        // segment
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+
+        EstClient { client, base_url }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Requests a new certificate for the DER-encoded PKCS#10 request `csr`.
+    ///
+    /// The returned certificate contains the leaf certificate followed by the rest of the chain
+    /// returned by the server. `private_key` is the PEM of the key the request was signed with,
+    /// and is attached to the certificate so that it can be used as a TLS identity.
+    pub fn simple_enroll(
+        &self,
+        csr: &[u8],
+        private_key: Option<Vec<u8>>,
+    ) -> impl Future<Item = PemCertificate, Error = Error> + Send {
+        self.enroll(EstOperation::SimpleEnroll, csr, private_key)
+    }
+
+    /// Renews the identity certificate of this client. The request in `csr` must have the same
+    /// subject as the certificate being renewed.
+    pub fn simple_reenroll(
+        &self,
+        csr: &[u8],
+        private_key: Option<Vec<u8>>,
+    ) -> impl Future<Item = PemCertificate, Error = Error> + Send {
+        self.enroll(EstOperation::SimpleReenroll, csr, private_key)
+    }
+
+    fn enroll(
+        &self,
+        operation: EstOperation,
+        csr: &[u8],
+        private_key: Option<Vec<u8>>,
+    ) -> impl Future<Item = PemCertificate, Error = Error> + Send {
+        // the issued certificate is the one with the public key of the request
+        let public_key = X509Req::from_der(csr)
+            .and_then(|csr| csr.public_key())
+            .and_then(|key| key.public_key_to_der())
+            .context(ErrorKind::EstEnrollment(operation))
+            .map_err(Error::from);
+
+        let request = public_key.and_then(|public_key| -> Result<_, Error> {
+            let url = self.base_url.join(operation.path()).with_context(|_| {
+                ErrorKind::UrlJoin(self.base_url.clone(), operation.to_string())
+            })?;
+            let uri = url
+                .as_str()
+                .parse::<Uri>()
+                .with_context(|_| ErrorKind::InvalidUrl(url.to_string()))?;
+
+            let mut request = Request::builder();
+            request
+                .method(Method::POST)
+                .uri(uri)
+                .header(CONTENT_TYPE, HeaderValue::from_static(PKCS10_CONTENT_TYPE))
+                .header(
+                    CONTENT_TRANSFER_ENCODING,
+                    HeaderValue::from_static("base64"),
+                );
+            let request = request
+                .body(Body::from(base64::encode(csr)))
+                .context(ErrorKind::EstEnrollment(operation))?;
+            Ok((request, public_key))
+        });
+
+        match request {
+            Ok((request, public_key)) => future::Either::A(
+                self.client
+                    .call(request)
+                    .then(move |response| -> Result<_, Error> {
+                        let response = response
+                            .context(ErrorKind::Http)
+                            .context(ErrorKind::EstEnrollment(operation))?;
+                        Ok(response)
+                    })
+                    .and_then(move |response| {
+                        let (parts, body) = response.into_parts();
+                        body.concat2().then(move |body| {
+                            let body = body
+                                .context(ErrorKind::Http)
+                                .context(ErrorKind::EstEnrollment(operation))?;
+                            Ok((parts.status, body))
+                        })
+                    })
+                    .and_then(move |(status, body)| {
+                        if status != StatusCode::OK {
+                            return Err(Error::from(
+                                Error::http_with_error_response(status, &*body)
+                                    .context(ErrorKind::EstEnrollment(operation)),
+                            ));
+                        }
+
+                        let certs = parse_certs_only_response(&body, &public_key)
+                            .context(ErrorKind::EstEnrollment(operation))?;
+                        Ok(PemCertificate::new(certs, private_key, None, None))
+                    }),
+            ),
+            Err(err) => future::Either::B(future::err(err)),
+        }
+    }
+}
+
+/// Parses the base64-encoded, DER-encoded PKCS#7 response of the server and returns the
+/// certificates in it as a PEM chain, starting with the certificate issued for `public_key`.
+fn parse_certs_only_response(body: &[u8], public_key: &[u8]) -> Result<Vec<u8>, Error> {
+    // the body may be wrapped at any width
+    let body: Vec<u8> = body
+        .iter()
+        .cloned()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    let der = base64::decode(&body).context(ErrorKind::EstResponse)?;
+
+    // the issued certificate goes first, whatever order the server sent the chain in
+    let mut chain = vec![];
+    let mut issued = false;
+    for cert in pkcs7_certificates(&der)? {
+        let cert = X509::from_der(cert).context(ErrorKind::EstResponse)?;
+        let cert_public_key = cert
+            .public_key()
+            .and_then(|key| key.public_key_to_der())
+            .context(ErrorKind::EstResponse)?;
+        if !issued && cert_public_key == public_key {
+            issued = true;
+            chain.insert(0, cert);
+        } else {
+            chain.push(cert);
+        }
+    }
+
+    if !issued {
+        return Err(Error::from(ErrorKind::EstResponse));
+    }
+
+    let mut pem = vec![];
+    for cert in chain {
+        pem.extend(cert.to_pem().context(ErrorKind::EstResponse)?);
+    }
+
+    Ok(pem)
+}
+
+/// Returns the DER encoding of every certificate in the certs-only PKCS#7 structure, in the order
+/// the server sent them.
+fn pkcs7_certificates(der: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    der::pkcs7_certificates(der).ok_or_else(|| Error::from(ErrorKind::EstResponse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Response;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, PKeyRef, Private};
+    use openssl::x509::{X509NameBuilder, X509Req};
+
+    use edgelet_test_utils::cert::{TestCa, TestCertUsage};
+
+    // a certs-only PKCS#7 with the certificate "device", issued by "est-ca", followed by "est-ca",
+    // as an EST server wraps it
+    const CERTS_ONLY_RESPONSE: &str = "\
+MIICxAYJKoZIhvcNAQcCoIICtTCCArECAQExADALBgkqhkiG9w0BBwGgggKZMIIB
+GzCBwwIUZ4qtqtW8x9Fx9NESr8C4vIFFmGIwCgYIKoZIzj0EAwIwETEPMA0GA1UE
+AwwGZXN0LWNhMB4XDTI2MTAxNDA1MTAwN1oXDTM2MTAxMTA1MTAwN1owETEPMA0G
+A1UEAwwGZGV2aWNlMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAECUZ5JkyOA1Br
+lfp0R3H0+4pbTtPHTM58NnNTBmw+7zA8bD7n4+KumbHjfRksUlbs248orjqr9EwN
+LCQeg6z+lDAKBggqhkjOPQQDAgNHADBEAiB0bAZif7jiFdgm9/uHfaRLraXyyXyR
+bI0x8lTXfQCF4QIgH1UJiE90sn8pCXSKL0huSFRism3A7Jh14Lt1YS3e3UowggF2
+MIIBHaADAgECAhRjVsRsdce33fWoNBe2BBbsoLIkcjAKBggqhkjOPQQDAjARMQ8w
+DQYDVQQDDAZlc3QtY2EwHhcNMjYxMDE0MDUxMDA3WhcNMzYxMDExMDUxMDA3WjAR
+MQ8wDQYDVQQDDAZlc3QtY2EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAROSLen
+tXq0X6oLMIqYpuhKwfspdQE1AgHitgImjxbAFJX+gq5/J7/L6M0Mg35PoI1RpL9X
+3qTC0IH7tYWudeeYo1MwUTAdBgNVHQ4EFgQURVtCyAhlhGt2GujB/t8sfnjRvGIw
+HwYDVR0jBBgwFoAURVtCyAhlhGt2GujB/t8sfnjRvGIwDwYDVR0TAQH/BAUwAwEB
+/zAKBggqhkjOPQQDAgNHADBEAiBlYyzMkro2ADdfnhiniH4NR2IIYzAxamJJrpyG
+2lSdwAIgMYxXY/yK8kZbDZx11cLFEctxpUQg9dPUpNRDS7WxDhMxAA==
+";
+
+    fn csr() -> (Vec<u8>, Vec<u8>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        csr_with_key(&key)
+    }
+
+    fn csr_with_key(key: &PKeyRef<Private>) -> (Vec<u8>, Vec<u8>) {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "device").unwrap();
+        let name = name.build();
+
+        let mut req = X509Req::builder().unwrap();
+        req.set_subject_name(&name).unwrap();
+        req.set_pubkey(key).unwrap();
+        req.sign(key, MessageDigest::sha256()).unwrap();
+
+        (
+            req.build().to_der().unwrap(),
+            key.private_key_to_pem_pkcs8().unwrap(),
+        )
+    }
+
+    fn common_name(cert: &X509) -> String {
+        cert.subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap()
+            .data()
+            .as_utf8()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn simple_enroll_returns_chain() {
+        let ca = TestCa::new("est-ca");
+        let device = ca.issue("device", TestCertUsage::Client);
+        let (csr, key) =
+            csr_with_key(&PKey::private_key_from_pem(device.get_private_key()).unwrap());
+        let expected_csr = csr.clone();
+
+        let chain: Vec<Vec<u8>> = X509::stack_from_pem(device.get_full_certificate())
+            .unwrap()
+            .iter()
+            .map(|cert| cert.to_der().unwrap())
+            .collect();
+        let chain: Vec<&[u8]> = chain.iter().map(AsRef::as_ref).collect();
+        let response = base64::encode(&der::certs_only(&chain));
+
+        let handler = move |req: Request<Body>| {
+            assert_eq!(Method::POST, *req.method());
+            assert_eq!("/.well-known/est/simpleenroll", req.uri().path());
+            assert_eq!(PKCS10_CONTENT_TYPE, req.headers()[CONTENT_TYPE]);
+
+            let body = req.into_body().concat2().wait().unwrap();
+            assert_eq!(expected_csr, base64::decode(&body).unwrap());
+
+            Ok::<_, hyper::Error>(Response::new(Body::from(response.clone())))
+        };
+
+        let client = EstClient::new(
+            handler,
+            Url::parse("https://est.example.com/.well-known/est").unwrap(),
+        );
+        let cert = client.simple_enroll(&csr, Some(key)).wait().unwrap();
+
+        let chain = X509::stack_from_pem(cert.get_certificate()).unwrap();
+        assert_eq!(2, chain.len());
+        assert_eq!("device", common_name(&chain[0]));
+        assert_eq!("est-ca", common_name(&chain[1]));
+        assert!(cert.get_identity().is_ok());
+    }
+
+    #[test]
+    fn issued_certificate_is_moved_to_the_front() {
+        let ca = TestCa::new("est-ca");
+        let device = ca.issue("device", TestCertUsage::Client);
+        let public_key = PKey::private_key_from_pem(device.get_private_key())
+            .unwrap()
+            .public_key_to_der()
+            .unwrap();
+
+        let chain: Vec<Vec<u8>> = X509::stack_from_pem(device.get_full_certificate())
+            .unwrap()
+            .iter()
+            .rev()
+            .map(|cert| cert.to_der().unwrap())
+            .collect();
+        let chain: Vec<&[u8]> = chain.iter().map(AsRef::as_ref).collect();
+        let response = base64::encode(&der::certs_only(&chain));
+
+        let pem = parse_certs_only_response(response.as_bytes(), &public_key).unwrap();
+        let chain = X509::stack_from_pem(&pem).unwrap();
+        assert_eq!(2, chain.len());
+        assert_eq!("device", common_name(&chain[0]));
+        assert_eq!("est-ca", common_name(&chain[1]));
+    }
+
+    #[test]
+    fn response_without_issued_certificate_is_rejected() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let public_key = key.public_key_to_der().unwrap();
+
+        let err =
+            parse_certs_only_response(CERTS_ONLY_RESPONSE.as_bytes(), &public_key).unwrap_err();
+        assert_eq!(&ErrorKind::EstResponse, err.kind());
+    }
+
+    #[test]
+    fn simple_reenroll_fails_on_error_response() {
+        let (csr, key) = csr();
+
+        let handler = |req: Request<Body>| {
+            assert_eq!("/.well-known/est/simplereenroll", req.uri().path());
+
+            let mut response = Response::new(Body::from("certificate is revoked"));
+            *response.status_mut() = StatusCode::FORBIDDEN;
+            Ok::<_, hyper::Error>(response)
+        };
+
+        let client = EstClient::new(
+            handler,
+            Url::parse("https://est.example.com/.well-known/est/").unwrap(),
+        );
+        let err = client.simple_reenroll(&csr, Some(key)).wait().unwrap_err();
+
+        assert_eq!(
+            &ErrorKind::EstEnrollment(EstOperation::SimpleReenroll),
+            err.kind()
+        );
+    }
+
+    #[test]
+    fn malformed_response_is_rejected() {
+        let der = base64::decode(&CERTS_ONLY_RESPONSE.replace('\n', "")).unwrap();
+
+        assert!(parse_certs_only_response(b"not base64!", &[]).is_err());
+        assert!(pkcs7_certificates(&der[..der.len() - 10]).is_err());
+        assert_eq!(2, pkcs7_certificates(&der).unwrap().len());
+    }
+}
@@ -32,8 +32,6 @@ use hyper::service::{NewService, Service};
 use hyper::{Body, Response};
 use log::{debug, error, Level};
 use native_tls::Identity;
-#[cfg(unix)]
-use native_tls::TlsAcceptor;
 use openssl::pkcs12::Pkcs12;
 use openssl::pkey::PKey;
 use openssl::stack::Stack;
@@ -53,19 +51,35 @@ pub mod authentication;
 pub mod authorization;
 pub mod certificate_manager;
 pub mod client;
+pub mod ct;
+mod der;
 pub mod error;
+pub mod est;
 pub mod logging;
+pub mod oci;
 mod pid;
+pub mod rate_limit;
 pub mod route;
+pub mod scep;
+pub mod signature;
+pub mod token_auth;
+pub mod trace_context;
 mod unix;
 mod util;
 mod version;
 
 pub use certificate_manager::CertificateManager;
-pub use error::{BindListenerType, Error, ErrorKind, InvalidUrlReason};
+pub use ct::{CtLogClient, SignedCertificateTimestamp};
+pub use error::{ApiError, BindListenerType, Error, ErrorKind, InvalidUrlReason};
+pub use est::{EstClient, EstOperation};
+pub use oci::{OciImage, OciImagePuller, OciLayer};
 pub use pid::Pid;
+pub use rate_limit::RateLimiter;
+pub use scep::ScepClient;
+pub use signature::ImageSignatureVerifier;
+pub use trace_context::TraceContext;
 pub use util::proxy::MaybeProxyClient;
-pub use util::UrlConnector;
+pub use util::{PeerAddr, UrlConnector};
 pub use version::{Version, API_VERSION};
 
 use crate::pid::PidService;
@@ -232,6 +246,7 @@ where
 
             debug!("accepted new connection ({})", addr);
             let pid = socket.pid()?;
+            let peer_addr = PeerAddr::from(&addr);
             let fut = new_service
                 .new_service()
                 .then(move |srv| match srv {
@@ -243,7 +258,7 @@ where
                     }
                 })
                 .and_then(move |(srv, addr)| {
-                    let service = PidService::new(pid, srv);
+                    let service = PidService::new(pid, peer_addr, srv);
                     protocol
                         .serve_connection(socket, service)
                         .then(move |result| match result {
@@ -340,16 +355,6 @@ impl HyperExt for Http {
                         )
                     })?;
 
-                let cert = tls_params
-                    .as_ref()
-                    .map(|params| params.cert_manager.get_pkcs12_certificate())
-                    .ok_or(ErrorKind::CertificateCreationError)?;
-
-                let cert = cert.context(ErrorKind::TlsBootstrapError)?;
-
-                let cert_identity = Identity::from_pkcs12(&cert, "")
-                    .context(ErrorKind::TlsIdentityCreationError)?;
-
                 let min_protocol_version =
                     tls_params
                         .as_ref()
@@ -359,11 +364,11 @@ impl HyperExt for Http {
                             Protocol::Tls12 => native_tls::Protocol::Tlsv12,
                         });
 
-                let tls_acceptor = TlsAcceptor::builder(cert_identity)
-                    .min_protocol_version(min_protocol_version)
-                    .build()
+                let tls_acceptor = tls_params
+                    .as_ref()
+                    .map(|params| params.cert_manager.tls_acceptor(min_protocol_version))
+                    .ok_or(ErrorKind::CertificateCreationError)?
                     .context(ErrorKind::TlsBootstrapError)?;
-                let tls_acceptor = tokio_tls::TlsAcceptor::from(tls_acceptor);
 
                 let listener = TcpListener::bind(&addr)
                     .with_context(|_| ErrorKind::BindListener(BindListenerType::Address(addr)))?;
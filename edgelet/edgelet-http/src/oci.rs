@@ -0,0 +1,1187 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Pulling of OCI images straight from a registry, for module runtimes without a daemon that
+//! pulls images for them, such as containerd.
+//!
+//! Every blob is written to the cache at `{cache_dir}/sha256/{hex}` once its SHA-256 digest has
+//! been verified, and is never downloaded again, so layers shared between images are only
+//! downloaded once. Layers are only decompressed from the cache.
+//!
+//! Only anonymous pulls are supported. Registries that require a bearer token get one from their
+//! token service without credentials.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use failure::{Fail, ResultExt};
+use flate2::read::GzDecoder;
+use futures::{future, stream, Future, IntoFuture, Stream};
+use hyper::header::{ACCEPT, AUTHORIZATION, LOCATION, WWW_AUTHENTICATE};
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use log::{debug, info};
+use openssl::sha::Sha256;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use tar::Archive;
+use url::Url;
+
+use crate::client::ClientImpl;
+use crate::error::{Error, ErrorKind};
+use crate::signature::parse_reference;
+use crate::MaybeProxyClient;
+
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+                                    application/vnd.oci.image.manifest.v1+json, \
+                                    application/vnd.docker.distribution.manifest.list.v2+json, \
+                                    application/vnd.docker.distribution.manifest.v2+json";
+const SHA256_PREFIX: &str = "sha256:";
+const DOCKER_CONTENT_DIGEST: &str = "Docker-Content-Digest";
+const DEFAULT_REGISTRY: &str = "registry-1.docker.io";
+const MAX_REDIRECTS: usize = 5;
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// Makes the names of partially downloaded blobs unique, so concurrent pulls of the same blob
+/// don't write to the same file.
+static PARTIAL_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Deserialize)]
+struct Descriptor {
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    platform: Option<Platform>,
+}
+
+#[derive(Clone, Deserialize)]
+struct Platform {
+    architecture: String,
+    os: String,
+}
+
+/// Either an image manifest or, if `manifests` isn't empty, an image index.
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+/// A pulled image, whose blobs are all in the cache.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OciImage {
+    digest: String,
+    config: Value,
+    layers: Vec<OciLayer>,
+}
+
+impl OciImage {
+    /// The `sha256:` digest of the image manifest.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// The image configuration, with the entrypoint, environment and so on of the image.
+    pub fn config(&self) -> &Value {
+        &self.config
+    }
+
+    /// The layers of the image, from the bottom one up.
+    pub fn layers(&self) -> &[OciLayer] {
+        &self.layers
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OciLayer {
+    digest: String,
+    media_type: String,
+}
+
+impl OciLayer {
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+}
+
+#[derive(Clone)]
+pub struct OciImagePuller<C> {
+    client: Arc<C>,
+    cache_dir: PathBuf,
+}
+
+impl OciImagePuller<MaybeProxyClient> {
+    pub fn from_proxy(proxy_uri: Option<Uri>, cache_dir: PathBuf) -> Result<Self, Error> {
+        let client = MaybeProxyClient::new(proxy_uri, None, None)?;
+        Ok(OciImagePuller::new(client, cache_dir))
+    }
+}
+
+impl<C> OciImagePuller<C>
+where
+    C: 'static + ClientImpl,
+{
+    pub fn new(client: C, cache_dir: PathBuf) -> Self {
+        OciImagePuller {
+            client: Arc::new(client),
+            cache_dir,
+        }
+    }
+
+    /// Pulls `image`, downloading the blobs that aren't cached yet, and unpacks its layers into
+    /// `rootfs`, which must be empty or not exist.
+    ///
+    /// If the image has an index, the manifest for Linux on the architecture of the daemon is
+    /// pulled.
+    pub fn pull(
+        &self,
+        image: &str,
+        rootfs: &Path,
+    ) -> impl Future<Item = OciImage, Error = Error> + Send {
+        let puller = OciImagePuller {
+            client: self.client.clone(),
+            cache_dir: self.cache_dir.clone(),
+        };
+        let name = image.to_string();
+        let rootfs = rootfs.to_path_buf();
+
+        self.fetch(image).and_then(move |image| {
+            puller
+                .unpack(&image, &rootfs)
+                .with_context(|_| ErrorKind::OciPull(name))?;
+            Ok(image)
+        })
+    }
+
+    /// Downloads the blobs of `image` that aren't cached yet, without unpacking them, so that
+    /// the image can be unpacked later without a connection to the registry.
+    pub fn fetch(&self, image: &str) -> impl Future<Item = OciImage, Error = Error> + Send {
+        info!("Pulling image {}...", image);
+
+        let (registry, repository, reference) = split_image(image);
+        let url = format!("https://{}/v2/{}/", registry, repository);
+        let base_url = match Url::parse(&url).context(ErrorKind::InvalidUrl(url)) {
+            Ok(base_url) => base_url,
+            Err(err) => return future::Either::B(future::err(Error::from(err))),
+        };
+
+        let image = image.to_string();
+        let client = self.client.clone();
+        let cache_dir = self.cache_dir.join("sha256");
+        let pull_error = {
+            let image = image.clone();
+            move |err: Error| Error::from(err.context(ErrorKind::OciPull(image)))
+        };
+
+        let pulled = fs::create_dir_all(&cache_dir)
+            .with_context(|_| ErrorKind::OciCache(cache_dir.display().to_string()))
+            .map_err(Error::from)
+            .into_future()
+            .and_then({
+                let client = client.clone();
+                let base_url = base_url.clone();
+                move |_| manifest(client, base_url, reference, None)
+            })
+            .and_then(move |(digest, manifest, token)| {
+                let config = match manifest.config {
+                    Some(config) => config,
+                    None => {
+                        return future::Either::B(future::err(Error::from(ErrorKind::OciManifest(
+                            digest,
+                        ))))
+                    }
+                };
+                let layers = manifest.layers;
+
+                // blobs are fetched one at a time, so a layer that appears twice is only
+                // downloaded once
+                let blobs: Vec<String> = std::iter::once(config.digest.clone())
+                    .chain(layers.iter().map(|layer| layer.digest.clone()))
+                    .collect();
+                let fetched = stream::iter_ok(blobs).for_each({
+                    let cache_dir = cache_dir.clone();
+                    move |digest| {
+                        fetch_blob(client.clone(), &base_url, digest, token.clone(), &cache_dir)
+                    }
+                });
+
+                future::Either::A(fetched.and_then(move |_| {
+                    let config_path = blob_path(&cache_dir, &config.digest)?;
+                    let config = File::open(&config_path)
+                        .map_err(failure::Error::from)
+                        .and_then(|file| -> Result<Value, failure::Error> {
+                            Ok(serde_json::from_reader(file)?)
+                        })
+                        .with_context(|_| ErrorKind::OciCache(config_path.display().to_string()))?;
+
+                    Ok(OciImage {
+                        digest,
+                        config,
+                        layers: layers
+                            .into_iter()
+                            .map(|layer| OciLayer {
+                                digest: layer.digest,
+                                media_type: layer.media_type,
+                            })
+                            .collect(),
+                    })
+                }))
+            })
+            .then(move |result| {
+                if result.is_ok() {
+                    info!("Successfully pulled image {}", image);
+                }
+                result
+            })
+            .map_err(pull_error);
+
+        future::Either::A(pulled)
+    }
+
+    /// Unpacks the layers of an image that was fetched into `rootfs`, which must be empty or
+    /// not exist.
+    pub fn unpack(&self, image: &OciImage, rootfs: &Path) -> Result<(), Error> {
+        let cache_dir = self.cache_dir.join("sha256");
+        for layer in &image.layers {
+            debug!("Unpacking layer {}", layer.digest);
+            let blob = blob_path(&cache_dir, &layer.digest)?;
+            unpack_layer(&blob, &layer.media_type, rootfs)
+                .with_context(|_| ErrorKind::OciUnpack(layer.digest.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// The size of the layer blobs of an image that was fetched, and the size of its layers once
+    /// they're decompressed, which is read by decompressing them.
+    pub fn image_size(&self, image: &OciImage) -> Result<(u64, u64), Error> {
+        let cache_dir = self.cache_dir.join("sha256");
+        let mut compressed = 0;
+        let mut uncompressed = 0;
+        for layer in &image.layers {
+            let blob = blob_path(&cache_dir, &layer.digest)?;
+            let size = || -> Result<(u64, u64), failure::Error> {
+                let compressed = fs::metadata(&blob)?.len();
+                let uncompressed = io::copy(
+                    &mut layer_reader(&blob, &layer.media_type)?,
+                    &mut io::sink(),
+                )?;
+                Ok((compressed, uncompressed))
+            };
+            let (layer_compressed, layer_uncompressed) =
+                size().with_context(|_| ErrorKind::OciLayerSize(layer.digest.clone()))?;
+            compressed += layer_compressed;
+            uncompressed += layer_uncompressed;
+        }
+        Ok((compressed, uncompressed))
+    }
+
+    /// Removes the blobs in the cache that aren't layers of any of `images`, and returns the
+    /// number of bytes they took up. Image configurations are only read while an image is
+    /// fetched, so their blobs are removed too. Downloads in progress are left alone.
+    pub fn remove_unused_blobs(&self, images: &[OciImage]) -> Result<u64, Error> {
+        let cache_dir = self.cache_dir.join("sha256");
+        let cache_error = |path: &Path| ErrorKind::OciCache(path.display().to_string());
+
+        let used = images
+            .iter()
+            .flat_map(|image| &image.layers)
+            .map(|layer| blob_path(&cache_dir, &layer.digest))
+            .collect::<Result<HashSet<PathBuf>, Error>>()?;
+
+        let entries = match fs::read_dir(&cache_dir) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(Error::from(err.context(cache_error(&cache_dir)))),
+        };
+
+        let mut reclaimed = 0;
+        for entry in entries {
+            let path = entry.with_context(|_| cache_error(&cache_dir))?.path();
+            let partial = path.extension() == Some(OsStr::new("partial"));
+            if partial || used.contains(&path) {
+                continue;
+            }
+
+            let size = fs::metadata(&path)
+                .with_context(|_| cache_error(&path))?
+                .len();
+            debug!("Removing unused blob {}", path.display());
+            fs::remove_file(&path).with_context(|_| cache_error(&path))?;
+            reclaimed += size;
+        }
+        Ok(reclaimed)
+    }
+}
+
+/// The digest of the manifest that `image` refers to in its registry right now, which changes
+/// when its tag is pushed again. Only the headers of the manifest are requested, and the digest
+/// is read from `Docker-Content-Digest`. For images with manifests for several platforms, this is
+/// the digest of the image index.
+pub fn latest_digest<C>(
+    client: Arc<C>,
+    image: &str,
+) -> impl Future<Item = String, Error = Error> + Send
+where
+    C: 'static + ClientImpl,
+{
+    let (registry, repository, reference) = split_image(image);
+    let url = format!(
+        "https://{}/v2/{}/manifests/{}",
+        registry, repository, reference
+    );
+
+    let image = image.to_string();
+    Url::parse(&url)
+        .with_context(|_| ErrorKind::InvalidUrl(url.clone()))
+        .map_err(Error::from)
+        .into_future()
+        .and_then(move |url| {
+            let accept = Some(MANIFEST_MEDIA_TYPES);
+            send_authorized(client, Method::HEAD, url, accept, None)
+        })
+        .and_then(move |(response, _)| {
+            response
+                .headers()
+                .get(DOCKER_CONTENT_DIGEST)
+                .and_then(|digest| digest.to_str().ok())
+                .filter(|digest| digest.starts_with(SHA256_PREFIX))
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| Error::from(ErrorKind::OciManifest(reference)))
+        })
+        .map_err(move |err| Error::from(err.context(ErrorKind::OciLatestDigest(image))))
+}
+
+/// Splits an image reference into the registry to pull it from, its repository and the tag or
+/// digest to pull. Like Docker, images without a registry are pulled from Docker Hub.
+fn split_image(image: &str) -> (String, String, String) {
+    let (name, tag) = parse_reference(image);
+    let reference = match image.find('@') {
+        Some(i) => image[i + 1..].to_string(),
+        None => tag.unwrap_or("latest").to_string(),
+    };
+
+    let (registry, repository) = match name.find('/') {
+        Some(i)
+            if name[..i].contains('.') || name[..i].contains(':') || &name[..i] == "localhost" =>
+        {
+            (&name[..i], &name[i + 1..])
+        }
+        _ => ("docker.io", name),
+    };
+
+    if registry == "docker.io" {
+        let repository = if repository.contains('/') {
+            repository.to_string()
+        } else {
+            format!("library/{}", repository)
+        };
+        (DEFAULT_REGISTRY.to_string(), repository, reference)
+    } else {
+        (registry.to_string(), repository.to_string(), reference)
+    }
+}
+
+/// Gets the manifest `reference` of the repository at `base_url`, and returns its digest, the
+/// manifest and the bearer token the registry asked for, if any.
+///
+/// An image index is resolved to the manifest for the platform of the daemon.
+fn manifest<C>(
+    client: Arc<C>,
+    base_url: Url,
+    reference: String,
+    token: Option<String>,
+) -> Box<dyn Future<Item = (String, Manifest, Option<String>), Error = Error> + Send>
+where
+    C: 'static + ClientImpl,
+{
+    let path = format!("manifests/{}", reference);
+    let url = match base_url
+        .join(&path)
+        .with_context(|_| ErrorKind::UrlJoin(base_url.clone(), path))
+    {
+        Ok(url) => url,
+        Err(err) => return Box::new(future::err(Error::from(err))),
+    };
+
+    let accept = Some(MANIFEST_MEDIA_TYPES);
+    let manifest = send_authorized(client.clone(), Method::GET, url, accept, token)
+        .and_then(|(response, token)| {
+            response
+                .into_body()
+                .concat2()
+                .then(|body| -> Result<_, Error> {
+                    let body = body.context(ErrorKind::Http)?;
+                    Ok((body, token))
+                })
+        })
+        .and_then(move |(body, token)| {
+            let digest = sha256_digest(&body);
+            if reference.starts_with(SHA256_PREFIX) && reference != digest {
+                return future::Either::B(future::err(Error::from(ErrorKind::OciDigestMismatch(
+                    reference,
+                ))));
+            }
+
+            let parsed: Manifest = match serde_json::from_slice(&body)
+                .with_context(|_| ErrorKind::OciManifest(reference))
+            {
+                Ok(parsed) => parsed,
+                Err(err) => return future::Either::B(future::err(Error::from(err))),
+            };
+            if parsed.manifests.is_empty() {
+                return future::Either::B(future::ok((digest, parsed, token)));
+            }
+
+            let platform_manifest = parsed
+                .manifests
+                .iter()
+                .find(|descriptor| {
+                    descriptor.platform.as_ref().map_or(false, |platform| {
+                        platform.os == "linux" && platform.architecture == architecture()
+                    })
+                })
+                .map(|descriptor| descriptor.digest.clone());
+            match platform_manifest {
+                Some(digest) => future::Either::A(manifest(client, base_url, digest, token)),
+                None => future::Either::B(future::err(Error::from(ErrorKind::OciPlatform(
+                    architecture().to_string(),
+                )))),
+            }
+        });
+
+    Box::new(manifest)
+}
+
+/// The architecture of the daemon, as OCI platforms name it.
+fn architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        arch => arch,
+    }
+}
+
+/// Downloads the blob `digest` into the cache, unless it's already there.
+fn fetch_blob<C>(
+    client: Arc<C>,
+    base_url: &Url,
+    digest: String,
+    token: Option<String>,
+    cache_dir: &Path,
+) -> Box<dyn Future<Item = (), Error = Error> + Send>
+where
+    C: 'static + ClientImpl,
+{
+    let path = match blob_path(cache_dir, &digest) {
+        Ok(path) => path,
+        Err(err) => return Box::new(future::err(err)),
+    };
+    if path.exists() {
+        debug!("Using cached blob {}", digest);
+        return Box::new(future::ok(()));
+    }
+
+    let blob_url = format!("blobs/{}", digest);
+    let url = match base_url
+        .join(&blob_url)
+        .with_context(|_| ErrorKind::UrlJoin(base_url.clone(), blob_url))
+    {
+        Ok(url) => url,
+        Err(err) => return Box::new(future::err(Error::from(err))),
+    };
+
+    let partial_path = path.with_extension(format!(
+        "{}.{}.partial",
+        std::process::id(),
+        PARTIAL_DOWNLOADS.fetch_add(1, Ordering::SeqCst)
+    ));
+    let cache_error = {
+        let partial_path = partial_path.clone();
+        move |err: io::Error| {
+            Error::from(err.context(ErrorKind::OciCache(partial_path.display().to_string())))
+        }
+    };
+
+    debug!("Downloading blob {}", digest);
+    let fetched = send_authorized(client, Method::GET, url, None, token)
+        .and_then({
+            let cache_error = cache_error.clone();
+            let partial_path = partial_path.clone();
+            move |(response, _)| {
+                File::create(&partial_path)
+                    .map_err(cache_error.clone())
+                    .into_future()
+                    .and_then(move |file| {
+                        response
+                            .into_body()
+                            .map_err(|err| Error::from(err.context(ErrorKind::Http)))
+                            .fold(
+                                (file, Sha256::new()),
+                                move |(mut file, mut hasher), chunk| {
+                                    hasher.update(&chunk);
+                                    file.write_all(&chunk).map_err(cache_error.clone())?;
+                                    Ok::<_, Error>((file, hasher))
+                                },
+                            )
+                    })
+            }
+        })
+        .then(move |result| -> Result<_, Error> {
+            let verified = result.and_then(|(_, hasher)| {
+                if format!("{}{}", SHA256_PREFIX, hex(&hasher.finish())) == digest {
+                    fs::rename(&partial_path, &path).map_err(cache_error)
+                } else {
+                    Err(Error::from(ErrorKind::OciDigestMismatch(digest)))
+                }
+            });
+            if verified.is_err() {
+                let _ = fs::remove_file(&partial_path);
+            }
+            verified
+        });
+
+    Box::new(fetched)
+}
+
+/// The path of the blob `digest` in the cache. The digest is checked first, since it comes from
+/// the registry and becomes part of the path.
+fn blob_path(cache_dir: &Path, digest: &str) -> Result<PathBuf, Error> {
+    let invalid = || Error::from(ErrorKind::OciDigestMismatch(digest.to_string()));
+
+    if !digest.starts_with(SHA256_PREFIX) {
+        return Err(invalid());
+    }
+    let hex = &digest[SHA256_PREFIX.len()..];
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    Ok(cache_dir.join(hex.to_ascii_lowercase()))
+}
+
+fn sha256_digest(data: &[u8]) -> String {
+    format!("{}{}", SHA256_PREFIX, hex(&openssl::sha::sha256(data)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Sends a `method` request for `url`. If the registry asks for a bearer token, one is requested
+/// from its token service and the request is sent again with it. Fails unless the final response
+/// is `200 OK`, and returns it with the token, which the registry accepts for later requests.
+fn send_authorized<C>(
+    client: Arc<C>,
+    method: Method,
+    url: Url,
+    accept: Option<&'static str>,
+    token: Option<String>,
+) -> impl Future<Item = (Response<Body>, Option<String>), Error = Error> + Send
+where
+    C: 'static + ClientImpl,
+{
+    let response = send(
+        client.clone(),
+        method.clone(),
+        url.clone(),
+        accept,
+        token.clone(),
+        0,
+    );
+    response
+        .and_then(move |response| {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|challenge| challenge.to_str().ok())
+                .and_then(parse_bearer_challenge);
+            match (response.status(), challenge) {
+                (StatusCode::UNAUTHORIZED, Some(challenge)) if token.is_none() => {
+                    future::Either::A(bearer_token(client.clone(), &challenge).and_then(
+                        move |token| {
+                            send(client, method, url, accept, Some(token.clone()), 0)
+                                .map(|response| (response, Some(token)))
+                        },
+                    ))
+                }
+                _ => future::Either::B(future::ok((response, token))),
+            }
+        })
+        .and_then(|(response, token)| {
+            let (parts, body) = response.into_parts();
+            if parts.status == StatusCode::OK {
+                future::Either::A(future::ok((Response::from_parts(parts, body), token)))
+            } else {
+                future::Either::B(body.concat2().then(
+                    move |body| -> Result<(Response<Body>, Option<String>), Error> {
+                        let body = body.context(ErrorKind::Http)?;
+                        Err(Error::http_with_error_response(parts.status, &*body))
+                    },
+                ))
+            }
+        })
+}
+
+/// Gets an anonymous token from the token service described by a `Bearer` challenge.
+fn bearer_token<C>(
+    client: Arc<C>,
+    challenge: &HashMap<String, String>,
+) -> impl Future<Item = String, Error = Error> + Send
+where
+    C: 'static + ClientImpl,
+{
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        token: Option<String>,
+        access_token: Option<String>,
+    }
+
+    let realm = challenge.get("realm").map_or("", String::as_str);
+    let url = Url::parse(realm).with_context(|_| ErrorKind::InvalidUrl(realm.to_string()));
+    let mut url = match url {
+        Ok(url) => url,
+        Err(err) => return future::Either::B(future::err(Error::from(err))),
+    };
+    for param in &["service", "scope"] {
+        if let Some(value) = challenge.get(*param) {
+            url.query_pairs_mut().append_pair(param, value);
+        }
+    }
+
+    future::Either::A(
+        send(client, Method::GET, url, None, None, 0)
+            .and_then(|response| {
+                let (parts, body) = response.into_parts();
+                body.concat2().then(move |body| -> Result<_, Error> {
+                    let body = body.context(ErrorKind::Http)?;
+                    if parts.status != StatusCode::OK {
+                        return Err(Error::http_with_error_response(parts.status, &*body));
+                    }
+                    Ok(body)
+                })
+            })
+            .and_then(|body| -> Result<_, Error> {
+                let response: TokenResponse =
+                    serde_json::from_slice(&body).context(ErrorKind::TokenSource)?;
+                let token = response
+                    .token
+                    .or(response.access_token)
+                    .ok_or(ErrorKind::TokenSource)?;
+                Ok(token)
+            }),
+    )
+}
+
+/// Parses the parameters of a `WWW-Authenticate: Bearer realm="...",service="...",scope="..."`
+/// challenge. Values may be quoted, and quoted values may contain commas.
+fn parse_bearer_challenge(challenge: &str) -> Option<HashMap<String, String>> {
+    let challenge = challenge.trim();
+    if challenge.len() < 7 || !challenge[..7].eq_ignore_ascii_case("bearer ") {
+        return None;
+    }
+
+    let mut params = HashMap::new();
+    let mut rest = challenge[7..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim().to_string();
+        rest = &rest[eq + 1..];
+
+        let value = if rest.starts_with('"') {
+            let end = rest[1..].find('"')? + 1;
+            let value = &rest[1..end];
+            rest = &rest[end + 1..];
+            value
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let value = rest[..end].trim();
+            rest = &rest[end..];
+            value
+        };
+        params.insert(key, value.to_string());
+
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+    }
+    Some(params)
+}
+
+/// Sends a `method` request for `url`, following redirects, since registries usually redirect
+/// blob downloads to a storage service. The token is only sent to the registry itself.
+fn send<C>(
+    client: Arc<C>,
+    method: Method,
+    url: Url,
+    accept: Option<&'static str>,
+    token: Option<String>,
+    redirects: usize,
+) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send>
+where
+    C: 'static + ClientImpl,
+{
+    let request = url
+        .as_str()
+        .parse::<Uri>()
+        .with_context(|_| ErrorKind::InvalidUrl(url.to_string()))
+        .map_err(Error::from)
+        .and_then(|uri| -> Result<_, Error> {
+            let mut request = Request::builder();
+            request.method(method.clone()).uri(uri);
+            if let Some(accept) = accept {
+                request.header(ACCEPT, accept);
+            }
+            if let Some(token) = &token {
+                request.header(AUTHORIZATION, format!("Bearer {}", token).as_str());
+            }
+            let request = request.body(Body::empty()).context(ErrorKind::Http)?;
+            Ok(request)
+        });
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => return Box::new(future::err(err)),
+    };
+
+    let response = client
+        .call(request)
+        .then(|response| -> Result<_, Error> {
+            let response = response.context(ErrorKind::Http)?;
+            Ok(response)
+        })
+        .and_then(move |response| {
+            let location = if response.status().is_redirection() {
+                response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .and_then(|location| url.join(location).ok())
+            } else {
+                None
+            };
+
+            match location {
+                Some(location) if redirects < MAX_REDIRECTS => {
+                    let token = token.filter(|_| location.host_str() == url.host_str());
+                    future::Either::A(send(client, method, location, accept, token, redirects + 1))
+                }
+                _ => future::Either::B(future::ok(response)),
+            }
+        });
+
+    Box::new(response)
+}
+
+/// Opens the blob of a layer as the tar archive it decompresses to.
+fn layer_reader(blob: &Path, media_type: &str) -> Result<Box<dyn Read>, failure::Error> {
+    let file = File::open(blob)?;
+    if media_type.ends_with("tar+gzip") || media_type.ends_with("tar.gzip") {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else if media_type.ends_with(".tar") {
+        Ok(Box::new(file))
+    } else {
+        Err(ErrorKind::OciUnsupportedMediaType(media_type.to_string()).into())
+    }
+}
+
+/// Unpacks a layer into `rootfs`, on top of the layers below it.
+///
+/// Whiteout files remove files of the lower layers: `.wh.{name}` removes `name`, and
+/// `.wh..wh..opq` removes the contents of its directory. They are applied before the rest of the
+/// layer is unpacked, so that they don't remove files of the layer itself.
+fn unpack_layer(blob: &Path, media_type: &str, rootfs: &Path) -> Result<(), failure::Error> {
+    fs::create_dir_all(rootfs)?;
+
+    let mut archive = Archive::new(layer_reader(blob, media_type)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = match relative_path(&entry.path()?) {
+            Some(path) => path,
+            None => continue,
+        };
+        let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+        if !file_name.starts_with(WHITEOUT_PREFIX) {
+            continue;
+        }
+        let whiteout = match join_in_rootfs(rootfs, &path)? {
+            Some(whiteout) => whiteout,
+            None => continue,
+        };
+
+        if file_name == OPAQUE_WHITEOUT {
+            let dir = whiteout.parent().unwrap_or(rootfs);
+            if let Ok(children) = fs::read_dir(dir) {
+                for child in children {
+                    remove(&child?.path())?;
+                }
+            }
+        } else {
+            remove(&whiteout.with_file_name(&file_name[WHITEOUT_PREFIX.len()..]))?;
+        }
+    }
+
+    let mut archive = Archive::new(layer_reader(blob, media_type)?);
+    archive.set_preserve_permissions(true);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = match relative_path(&entry.path()?) {
+            Some(path) => path,
+            None => continue,
+        };
+        if path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .map_or(false, |name| name.starts_with(WHITEOUT_PREFIX))
+        {
+            continue;
+        }
+
+        // a file of a lower layer is replaced, unless both are directories
+        let target = match join_in_rootfs(rootfs, &path)? {
+            Some(target) => target,
+            None => rootfs.join(&path),
+        };
+        if let Ok(metadata) = fs::symlink_metadata(&target) {
+            if !(metadata.is_dir() && entry.header().entry_type().is_dir()) {
+                remove(&target)?;
+            }
+        }
+        entry.unpack_in(rootfs)?;
+    }
+
+    Ok(())
+}
+
+/// The path of a layer entry relative to the rootfs, or `None` if it would be outside of it.
+fn relative_path(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(component) => relative.push(component),
+            Component::CurDir | Component::RootDir => (),
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(relative).filter(|relative| relative.components().next().is_some())
+}
+
+/// Joins `path` onto `rootfs`, or returns `None` if one of its parent directories doesn't exist.
+///
+/// Fails if one of the parents is a symlink, since an earlier entry could have pointed it outside
+/// of the rootfs, and the files of the host would be removed or overwritten through it.
+fn join_in_rootfs(rootfs: &Path, path: &Path) -> Result<Option<PathBuf>, failure::Error> {
+    let mut joined = rootfs.to_path_buf();
+    for component in path.parent().into_iter().flat_map(Path::components) {
+        joined.push(component);
+        match fs::symlink_metadata(&joined) {
+            Ok(ref metadata) if metadata.file_type().is_symlink() => {
+                return Err(ErrorKind::OciSymlinkedPath(path.display().to_string()).into());
+            }
+            Ok(_) => (),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(Some(rootfs.join(path)))
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(ref metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(err) => Err(err),
+    };
+    match result {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+    use tar::{Builder, EntryType, Header};
+    use tempfile::tempdir;
+    use tokio::runtime::current_thread::Runtime;
+
+    const GZIP_LAYER: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+    fn layer(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (path, contents) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn symlink_layer(path: &str, target: &Path) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(vec![], Compression::default()));
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        header.set_link_name(target).unwrap();
+        builder.append_data(&mut header, path, &[][..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// A registry that serves `blobs` and a manifest for the `1.0` tag of `module1` with them,
+    /// the first blob being the image config, and counts the blobs it serves.
+    fn registry(
+        blobs: Vec<Vec<u8>>,
+        downloads: Arc<AtomicUsize>,
+    ) -> impl Fn(Request<Body>) -> Result<Response<Body>, hyper::Error> + Send + Sync {
+        let digests: Vec<String> = blobs.iter().map(|blob| sha256_digest(blob)).collect();
+        let manifest = json!({
+            "schemaVersion": 2,
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": digests[0],
+            },
+            "layers": digests[1..].iter().map(|digest| json!({
+                "mediaType": GZIP_LAYER,
+                "digest": digest,
+            })).collect::<Vec<_>>(),
+        })
+        .to_string();
+        let blobs: HashMap<String, Vec<u8>> = digests.into_iter().zip(blobs).collect();
+
+        move |req| {
+            let path = req.uri().path();
+            let body = if path == "/v2/module1/manifests/1.0" {
+                manifest.clone().into_bytes()
+            } else if path.starts_with("/v2/module1/blobs/") {
+                downloads.fetch_add(1, Ordering::SeqCst);
+                blobs[&path["/v2/module1/blobs/".len()..]].clone()
+            } else {
+                panic!("unexpected request to {}", path);
+            };
+            Ok(Response::new(Body::from(body)))
+        }
+    }
+
+    #[test]
+    fn latest_digest_reads_manifest_headers() {
+        let digest = sha256_digest(b"manifest");
+        let client = {
+            let digest = digest.clone();
+            move |req: Request<Body>| -> Result<Response<Body>, hyper::Error> {
+                assert_eq!(&Method::HEAD, req.method());
+                assert_eq!("/v2/module1/manifests/1.0", req.uri().path());
+                Ok(Response::builder()
+                    .header(DOCKER_CONTENT_DIGEST, digest.as_str())
+                    .body(Body::empty())
+                    .unwrap())
+            }
+        };
+
+        let latest = Runtime::new()
+            .unwrap()
+            .block_on(latest_digest(
+                Arc::new(client),
+                "registry.example.com/module1:1.0",
+            ))
+            .unwrap();
+
+        assert_eq!(digest, latest);
+    }
+
+    #[test]
+    fn split_image_defaults_to_docker_hub() {
+        assert_eq!(
+            (
+                DEFAULT_REGISTRY.to_string(),
+                "library/ubuntu".to_string(),
+                "latest".to_string()
+            ),
+            split_image("ubuntu")
+        );
+        assert_eq!(
+            (
+                "registry.example.com:5000".to_string(),
+                "repo/module1".to_string(),
+                "1.0".to_string()
+            ),
+            split_image("registry.example.com:5000/repo/module1:1.0")
+        );
+        assert_eq!(
+            (
+                DEFAULT_REGISTRY.to_string(),
+                "microsoft/module1".to_string(),
+                "sha256:abc".to_string()
+            ),
+            split_image("microsoft/module1:1.0@sha256:abc")
+        );
+    }
+
+    #[test]
+    fn parse_bearer_challenge_reads_quoted_params() {
+        let challenge = parse_bearer_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/ubuntu:pull,push""#,
+        )
+        .unwrap();
+
+        assert_eq!("https://auth.docker.io/token", challenge["realm"]);
+        assert_eq!("registry.docker.io", challenge["service"]);
+        assert_eq!("repository:library/ubuntu:pull,push", challenge["scope"]);
+        assert!(parse_bearer_challenge(r#"Basic realm="registry""#).is_none());
+    }
+
+    #[test]
+    fn pull_unpacks_layers_and_caches_blobs() {
+        let cache_dir = tempdir().unwrap();
+        let rootfs = tempdir().unwrap();
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let blobs = vec![
+            json!({ "config": { "Cmd": ["/app"] } })
+                .to_string()
+                .into_bytes(),
+            layer(&[("etc/removed", "1"), ("etc/kept", "1"), ("app", "1")]),
+            layer(&[("etc/.wh.removed", ""), ("app", "2")]),
+        ];
+        let puller = OciImagePuller::new(
+            registry(blobs, downloads.clone()),
+            cache_dir.path().to_path_buf(),
+        );
+
+        let mut runtime = Runtime::new().unwrap();
+        let image = runtime
+            .block_on(puller.pull("registry.example.com/module1:1.0", rootfs.path()))
+            .unwrap();
+
+        assert_eq!(json!(["/app"]), image.config()["config"]["Cmd"]);
+        assert_eq!(2, image.layers().len());
+        assert_eq!("2", fs::read_to_string(rootfs.path().join("app")).unwrap());
+        assert!(rootfs.path().join("etc/kept").exists());
+        assert!(!rootfs.path().join("etc/removed").exists());
+        assert_eq!(3, downloads.load(Ordering::SeqCst));
+
+        let rootfs = tempdir().unwrap();
+        runtime
+            .block_on(puller.pull("registry.example.com/module1:1.0", rootfs.path()))
+            .unwrap();
+        assert_eq!(3, downloads.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unpack_layer_does_not_follow_symlinks_out_of_rootfs() {
+        let host = tempdir().unwrap();
+        fs::write(host.path().join("passwd"), "root").unwrap();
+        fs::write(host.path().join("shadow"), "root").unwrap();
+        let blobs = tempdir().unwrap();
+        let symlink = blobs.path().join("symlink");
+        fs::write(&symlink, symlink_layer("etc", host.path())).unwrap();
+
+        for files in &[
+            &[("etc/.wh..wh..opq", "")][..],
+            &[("etc/.wh.passwd", "")][..],
+            &[("etc/passwd", "evil")][..],
+        ] {
+            let rootfs = tempdir().unwrap();
+            unpack_layer(&symlink, GZIP_LAYER, rootfs.path()).unwrap();
+            let malicious = blobs.path().join("malicious");
+            fs::write(&malicious, layer(files)).unwrap();
+
+            let err = unpack_layer(&malicious, GZIP_LAYER, rootfs.path()).unwrap_err();
+
+            assert_eq!(
+                Some(&ErrorKind::OciSymlinkedPath(files[0].0.to_string())),
+                err.downcast_ref::<ErrorKind>()
+            );
+            assert_eq!(
+                "root",
+                fs::read_to_string(host.path().join("passwd")).unwrap()
+            );
+            assert!(host.path().join("shadow").exists());
+        }
+    }
+
+    #[test]
+    fn remove_unused_blobs_keeps_layers_of_images() {
+        let cache_dir = tempdir().unwrap();
+        let rootfs = tempdir().unwrap();
+        let config = json!({ "config": {} }).to_string().into_bytes();
+        let kept = layer(&[("app", "1")]);
+        let puller = OciImagePuller::new(
+            registry(vec![config.clone(), kept.clone()], Arc::default()),
+            cache_dir.path().to_path_buf(),
+        );
+
+        let mut runtime = Runtime::new().unwrap();
+        let image = runtime
+            .block_on(puller.pull("registry.example.com/module1:1.0", rootfs.path()))
+            .unwrap();
+        let blobs = cache_dir.path().join("sha256");
+        fs::write(blobs.join("abc.1.0.partial"), "partial").unwrap();
+
+        let reclaimed = puller.remove_unused_blobs(&[image]).unwrap();
+        assert_eq!(config.len() as u64, reclaimed);
+        assert_eq!(2, fs::read_dir(&blobs).unwrap().count());
+
+        let reclaimed = puller.remove_unused_blobs(&[]).unwrap();
+        assert_eq!(kept.len() as u64, reclaimed);
+        assert_eq!(1, fs::read_dir(&blobs).unwrap().count());
+    }
+
+    #[test]
+    fn pull_rejects_blob_not_matching_digest() {
+        let cache_dir = tempdir().unwrap();
+        let rootfs = tempdir().unwrap();
+        let config = b"{}".to_vec();
+        let layer = layer(&[("app", "1")]);
+        let manifest = json!({
+            "schemaVersion": 2,
+            "config": { "digest": sha256_digest(&config) },
+            "layers": [{ "mediaType": GZIP_LAYER, "digest": sha256_digest(b"other") }],
+        })
+        .to_string();
+        let handler = move |req: Request<Body>| {
+            let body = match req.uri().path() {
+                "/v2/module1/manifests/1.0" => manifest.clone().into_bytes(),
+                path if path.ends_with(&sha256_digest(&config)) => config.clone(),
+                _ => layer.clone(),
+            };
+            Ok::<_, hyper::Error>(Response::new(Body::from(body)))
+        };
+        let puller = OciImagePuller::new(handler, cache_dir.path().to_path_buf());
+
+        let err = Runtime::new()
+            .unwrap()
+            .block_on(puller.pull("registry.example.com/module1:1.0", rootfs.path()))
+            .unwrap_err();
+
+        assert_eq!(
+            &ErrorKind::OciDigestMismatch(sha256_digest(b"other")),
+            err.cause()
+                .and_then(|cause| cause.downcast_ref::<Error>())
+                .unwrap()
+                .kind()
+        );
+        assert_eq!(
+            1,
+            fs::read_dir(cache_dir.path().join("sha256"))
+                .unwrap()
+                .count()
+        );
+        assert!(!rootfs.path().join("app").exists());
+    }
+}
@@ -10,6 +10,8 @@ use tokio_uds::UnixStream;
 #[cfg(windows)]
 use tokio_uds_windows::UnixStream;
 
+use crate::util::PeerAddr;
+
 #[derive(Clone, Copy, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 pub enum Pid {
     None,
@@ -69,12 +71,17 @@ mod tests {
 #[derive(Clone)]
 pub struct PidService<T> {
     pid: Pid,
+    peer_addr: PeerAddr,
     inner: T,
 }
 
 impl<T> PidService<T> {
-    pub fn new(pid: Pid, inner: T) -> Self {
-        PidService { pid, inner }
+    pub fn new(pid: Pid, peer_addr: PeerAddr, inner: T) -> Self {
+        PidService {
+            pid,
+            peer_addr,
+            inner,
+        }
     }
 }
 
@@ -92,6 +99,7 @@ where
     fn call(&mut self, req: Request<Self::ReqBody>) -> Self::Future {
         let mut req = req;
         req.extensions_mut().insert(self.pid);
+        req.extensions_mut().insert(self.peer_addr);
         self.inner.call(req)
     }
 }
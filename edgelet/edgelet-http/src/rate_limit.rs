@@ -0,0 +1,430 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either};
+use futures::{stream, Future, Stream};
+use hyper::header::RETRY_AFTER;
+use hyper::{Body, Request, Response};
+use log::{debug, warn};
+
+use edgelet_core::{AuthId, Module, ModuleRuntime, RateLimit, RateLimitSettings};
+
+use crate::route::{Handler, Parameters};
+use crate::{Error, ErrorKind, IntoResponse, Pid};
+
+/// How long the modules that processes belong to are trusted before the runtime is asked again
+/// about a process it didn't know.
+const PROCESS_MODULES_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps a token bucket for every endpoint and caller, sized according to the configured limits.
+///
+/// Callers are the modules named by their module token or that their process belongs to. Every
+/// caller that isn't a module, like the `iotedge` tool, shares one bucket per endpoint.
+///
+/// Clones share the same buckets.
+#[derive(Clone)]
+pub struct RateLimiter<M> {
+    settings: Arc<RateLimitSettings>,
+    buckets: Arc<Mutex<HashMap<(&'static str, Option<String>), TokenBucket>>>,
+    process_modules: ProcessModules<M>,
+}
+
+impl<M> RateLimiter<M> {
+    pub fn new(settings: RateLimitSettings, runtime: M) -> Self {
+        RateLimiter {
+            settings: Arc::new(settings),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+            process_modules: ProcessModules::new(runtime),
+        }
+    }
+
+    /// Wraps `handler` so that calls to it are limited according to the limit configured for
+    /// `endpoint` and the caller.
+    pub fn limit<H>(&self, endpoint: &'static str, handler: H) -> RateLimited<H, M>
+    where
+        M: Clone,
+    {
+        RateLimited {
+            endpoint,
+            limiter: self.clone(),
+            inner: Arc::new(handler),
+        }
+    }
+
+    /// Takes a token from the caller's bucket for the endpoint. If the bucket is empty, this
+    /// returns how long the caller has to wait before the next token is available.
+    fn acquire(
+        &self,
+        endpoint: &'static str,
+        caller: Option<String>,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let limit = match self
+            .settings
+            .limit(endpoint, caller.as_ref().map(AsRef::as_ref))
+        {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        self.buckets
+            .lock()
+            .expect("Unable to lock the rate limiter mutex")
+            .entry((endpoint, caller))
+            .or_insert_with(|| TokenBucket::new(limit, now))
+            .take(limit, now)
+    }
+}
+
+impl<M> RateLimiter<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    /// Identifies the module making the request: the module named in its token, the module the
+    /// process authentication identified, or the module the calling process runs in. Returns
+    /// `None` for callers that aren't modules.
+    fn caller(&self, req: &Request<Body>) -> impl Future<Item = Option<String>, Error = ()> {
+        if let Some(AuthId::Value(module_id)) = req.extensions().get::<AuthId>() {
+            return Either::A(future::ok(Some(module_id.to_string())));
+        }
+
+        match req.extensions().get::<Pid>() {
+            Some(Pid::Value(pid)) => Either::B(self.process_modules.module(*pid)),
+            _ => Either::A(future::ok(None)),
+        }
+    }
+}
+
+/// Remembers which module each process runs in, as reported by the runtime.
+#[derive(Clone)]
+struct ProcessModules<M> {
+    runtime: M,
+    modules: Arc<Mutex<(HashMap<i32, String>, Option<Instant>)>>,
+}
+
+impl<M> ProcessModules<M> {
+    fn new(runtime: M) -> Self {
+        ProcessModules {
+            runtime,
+            modules: Arc::new(Mutex::new((HashMap::new(), None))),
+        }
+    }
+}
+
+impl<M> ProcessModules<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    /// The module that the process `pid` runs in. Processes that aren't known yet make the
+    /// runtime list the processes of every module again, at most once per refresh interval.
+    fn module(&self, pid: i32) -> impl Future<Item = Option<String>, Error = ()> {
+        let now = Instant::now();
+        {
+            let modules = self
+                .modules
+                .lock()
+                .expect("Unable to lock the process modules mutex");
+            let (ref modules, refreshed) = *modules;
+            let fresh = refreshed.map_or(false, |refreshed| {
+                now.saturating_duration_since(refreshed) < PROCESS_MODULES_REFRESH_INTERVAL
+            });
+            if modules.contains_key(&pid) || fresh {
+                return Either::A(future::ok(modules.get(&pid).cloned()));
+            }
+        }
+
+        let cache = self.modules.clone();
+        let runtime = self.runtime.clone();
+        let refresh = self
+            .runtime
+            .list()
+            .map_err(|err| debug!("Could not list modules to identify a caller: {}", err))
+            .and_then(move |modules| {
+                let names: Vec<String> = modules
+                    .iter()
+                    .map(|module| module.name().to_string())
+                    .collect();
+                stream::iter_ok::<_, ()>(names)
+                    .and_then(move |name| {
+                        // modules that stop in the meantime have no processes to attribute
+                        runtime.top(&name).then(move |top| {
+                            Ok::<_, ()>(top.map(|top| (name, top.process_ids())).ok())
+                        })
+                    })
+                    .filter_map(|top| top)
+                    .collect()
+            })
+            .map(move |tops: Vec<(String, Vec<i32>)>| {
+                let mut modules = HashMap::new();
+                for (name, pids) in tops {
+                    for pid in pids {
+                        modules.insert(pid, name.clone());
+                    }
+                }
+                let module = modules.get(&pid).cloned();
+                *cache
+                    .lock()
+                    .expect("Unable to lock the process modules mutex") = (modules, Some(now));
+                module
+            });
+
+        Either::B(refresh)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        TokenBucket {
+            tokens: f64::from(limit.burst()),
+            last_refill: now,
+        }
+    }
+
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * limit.requests_per_second()).min(f64::from(limit.burst()));
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if limit.requests_per_second() > 0.0 {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / limit.requests_per_second(),
+            ))
+        } else {
+            Err(Duration::from_secs(u64::from(u32::max_value())))
+        }
+    }
+}
+
+pub struct RateLimited<H, M> {
+    endpoint: &'static str,
+    limiter: RateLimiter<M>,
+    inner: Arc<H>,
+}
+
+impl<H, M> Handler<Parameters> for RateLimited<H, M>
+where
+    H: Handler<Parameters> + Sync,
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+        if self.limiter.settings.is_empty() {
+            return self.inner.handle(req, params);
+        }
+
+        let endpoint = self.endpoint;
+        let limiter = self.limiter.clone();
+        let inner = self.inner.clone();
+
+        let response = self.limiter.caller(&req).then(move |caller| {
+            let caller = caller.unwrap_or(None);
+            match limiter.acquire(endpoint, caller.clone(), Instant::now()) {
+                Ok(()) => Either::A(inner.handle(req, params)),
+                Err(retry_after) => {
+                    match caller {
+                        Some(module) => {
+                            warn!("Rate limit for {} exceeded by module {}", endpoint, module)
+                        }
+                        None => warn!("Rate limit for {} exceeded", endpoint),
+                    }
+                    Either::B(future::ok(too_many_requests(endpoint, retry_after)))
+                }
+            }
+        });
+        Box::new(response)
+    }
+}
+
+fn too_many_requests(endpoint: &str, retry_after: Duration) -> Response<Body> {
+    // Retry-After is in whole seconds, so round up to not invite an early retry
+    let retry_after = if retry_after.subsec_nanos() > 0 {
+        retry_after.as_secs() + 1
+    } else {
+        retry_after.as_secs()
+    };
+
+    let mut response =
+        Error::from(ErrorKind::TooManyRequests(endpoint.to_string())).into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, retry_after.into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use failure::Fail;
+    use futures::Future;
+    use hyper::{Body, Request, Response, StatusCode};
+
+    use edgelet_core::{CallerRateLimits, MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, Fail)]
+    pub enum Error {
+        #[fail(display = "General error")]
+        General,
+    }
+
+    type Runtime = TestRuntime<Error, TestSettings>;
+
+    /// A runtime whose only module, `m1`, runs the process with the PID 1.
+    fn runtime() -> Runtime {
+        let module = TestModule::new(
+            "m1".to_string(),
+            TestConfig::new("microsoft/test-image".to_string()),
+            Ok(ModuleRuntimeState::default()),
+        );
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(Ok(module))
+    }
+
+    fn limiter(settings: RateLimitSettings) -> RateLimiter<Runtime> {
+        RateLimiter::new(settings, runtime())
+    }
+
+    fn limited(limit: RateLimit) -> RateLimitSettings {
+        let mut endpoints = HashMap::new();
+        endpoints.insert("limited".to_string(), limit);
+        RateLimitSettings::new(None, endpoints)
+    }
+
+    fn module(name: &str) -> Option<String> {
+        Some(name.to_string())
+    }
+
+    #[test]
+    fn burst_is_allowed_then_limited() {
+        let limiter = limiter(limited(RateLimit::new(1.0, 2)));
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert_eq!(
+            Err(Duration::from_secs(1)),
+            limiter.acquire("limited", module("m1"), now)
+        );
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time() {
+        let limiter = limiter(limited(RateLimit::new(2.0, 1)));
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert!(limiter.acquire("limited", module("m1"), now).is_err());
+        assert_eq!(
+            Ok(()),
+            limiter.acquire("limited", module("m1"), now + Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn callers_and_endpoints_are_limited_separately() {
+        let limiter = limiter(limited(RateLimit::new(1.0, 1)));
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m2"), now));
+        assert_eq!(Ok(()), limiter.acquire("limited", None, now));
+        assert_eq!(Ok(()), limiter.acquire("other", module("m1"), now));
+        assert_eq!(Ok(()), limiter.acquire("other", module("m1"), now));
+    }
+
+    #[test]
+    fn callers_get_their_own_limits() {
+        let settings = limited(RateLimit::new(1.0, 1)).with_caller(
+            "m1".to_string(),
+            CallerRateLimits::new(Some(RateLimit::new(1.0, 2)), HashMap::new()),
+        );
+        let limiter = limiter(settings);
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m1"), now));
+        assert!(limiter.acquire("limited", module("m1"), now).is_err());
+        assert_eq!(Ok(()), limiter.acquire("limited", module("m2"), now));
+        assert!(limiter.acquire("limited", module("m2"), now).is_err());
+    }
+
+    #[test]
+    fn caller_is_module_named_by_token() {
+        let limiter = limiter(RateLimitSettings::default());
+        let mut request = Request::default();
+        request.extensions_mut().insert(AuthId::Value("m2".into()));
+        request.extensions_mut().insert(Pid::Value(1));
+
+        assert_eq!(module("m2"), limiter.caller(&request).wait().unwrap());
+    }
+
+    #[test]
+    fn caller_is_module_the_process_runs_in() {
+        let limiter = limiter(RateLimitSettings::default());
+        let request = |pid| {
+            let mut request = Request::default();
+            request.extensions_mut().insert(AuthId::Any);
+            request.extensions_mut().insert(Pid::Value(pid));
+            request
+        };
+
+        assert_eq!(module("m1"), limiter.caller(&request(1)).wait().unwrap());
+        assert_eq!(None, limiter.caller(&request(2)).wait().unwrap());
+    }
+
+    #[test]
+    fn handler_responds_with_too_many_requests() {
+        let handler = limiter(limited(RateLimit::new(0.5, 1))).limit("limited", TestHandler);
+        let request = || {
+            let mut request = Request::default();
+            request.extensions_mut().insert(AuthId::Any);
+            request.extensions_mut().insert(Pid::Value(1));
+            request
+        };
+
+        let response = handler.handle(request(), Parameters::new()).wait().unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = handler.handle(request(), Parameters::new()).wait().unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!("2", response.headers().get(RETRY_AFTER).unwrap());
+    }
+
+    struct TestHandler;
+
+    impl Handler<Parameters> for TestHandler {
+        fn handle(
+            &self,
+            _req: Request<Body>,
+            _params: Parameters,
+        ) -> Box<dyn Future<Item = Response<Body>, Error = crate::Error> + Send> {
+            Box::new(future::ok(Response::new(Body::default())))
+        }
+    }
+}
@@ -5,8 +5,21 @@ macro_rules! router {
     ($($method:ident $ver:ident $runtime:ident $policy:expr => $path:expr => $handler:expr),+ $(,)*) => ({
         Router::from(
             $crate::route::RegexRoutesBuilder::default()
-            $(.$method(Version::$ver, $path, Authentication::new(Authorization::new($handler, $policy), $policy, $runtime.clone())))*
+            $(.$method($crate::route_version!($ver), $path, Authentication::new(Authorization::new($handler, $policy), $policy, $runtime.clone())))*
             .finish()
         )
     });
 }
+
+/// Maps the version column of `router!` to the version of the route. `Unversioned` routes are
+/// also served to requests without an `api-version`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! route_version {
+    (Unversioned) => {
+        None::<$crate::Version>
+    };
+    ($ver:ident) => {
+        Version::$ver
+    };
+}
@@ -49,10 +49,12 @@ pub type HandlerParamsPair<'a, P> = (&'a dyn Handler<P>, P);
 pub trait Recognizer {
     type Parameters: 'static;
 
+    /// Finds the handler for the request. Requests without an `api-version` are given a
+    /// `version` of `None` and only match routes that were added without one.
     fn recognize(
         &self,
         method: &Method,
-        version: Version,
+        version: Option<Version>,
         path: &str,
     ) -> Result<HandlerParamsPair<'_, Self::Parameters>, StatusCode>;
 }
@@ -60,39 +62,55 @@ pub trait Recognizer {
 pub trait Builder: Sized {
     type Recognizer: Recognizer;
 
-    fn route<S, H>(self, method: Method, version: Version, pattern: S, handler: H) -> Self
+    /// Adds a route served from the given API version onwards. Routes added with a version of
+    /// `None` are served to every request, including requests without an `api-version`.
+    fn route<V, S, H>(self, method: Method, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync;
 
     fn finish(self) -> Self::Recognizer;
 
-    fn get<S, H>(self, version: Version, pattern: S, handler: H) -> Self
+    fn get<V, S, H>(self, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
         self.route(Method::GET, version, pattern, handler)
     }
 
-    fn post<S, H>(self, version: Version, pattern: S, handler: H) -> Self
+    fn post<V, S, H>(self, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
         self.route(Method::POST, version, pattern, handler)
     }
 
-    fn put<S, H>(self, version: Version, pattern: S, handler: H) -> Self
+    fn put<V, S, H>(self, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
         self.route(Method::PUT, version, pattern, handler)
     }
 
-    fn delete<S, H>(self, version: Version, pattern: S, handler: H) -> Self
+    fn patch<V, S, H>(self, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
+        S: AsRef<str>,
+        H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
+    {
+        self.route(Method::PATCH, version, pattern, handler)
+    }
+
+    fn delete<V, S, H>(self, version: V, pattern: S, handler: H) -> Self
+    where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
@@ -165,25 +183,19 @@ where
             })
         };
 
-        match api_version {
-            Some(api_version) => {
-                let method = req.method().clone();
-                let path = req.uri().path().to_owned();
-                match self.inner.recognize(&method, api_version, &path) {
-                    Ok((handler, params)) => {
-                        Box::new(handler.handle(req, params).map_err(Fail::compat))
-                    }
-                    Err(code) => Box::new(future::ok(
-                        Response::builder()
-                            .status(code)
-                            .body(Body::empty())
-                            .expect("hyper::Response with empty body should not fail to build"),
-                    )),
-                }
-            }
-            None => Box::new(future::ok(
+        let method = req.method().clone();
+        let path = req.uri().path().to_owned();
+        match self.inner.recognize(&method, api_version, &path) {
+            Ok((handler, params)) => Box::new(handler.handle(req, params).map_err(Fail::compat)),
+            Err(_) if api_version.is_none() => Box::new(future::ok(
                 Error::from(ErrorKind::InvalidApiVersion(String::new())).into_response(),
             )),
+            Err(code) => Box::new(future::ok(
+                Response::builder()
+                    .status(code)
+                    .body(Body::empty())
+                    .expect("hyper::Response with empty body should not fail to build"),
+            )),
         }
     }
 }
@@ -67,7 +67,7 @@ impl Default for Parameters {
 struct RegexRoute {
     pattern: Regex,
     handler: Box<dyn Handler<Parameters> + Sync>,
-    version: Version,
+    version: Option<Version>,
 }
 
 #[derive(Default)]
@@ -78,8 +78,9 @@ pub struct RegexRoutesBuilder {
 impl Builder for RegexRoutesBuilder {
     type Recognizer = RegexRecognizer;
 
-    fn route<S, H>(mut self, method: Method, version: Version, pattern: S, handler: H) -> Self
+    fn route<V, S, H>(mut self, method: Method, version: V, pattern: S, handler: H) -> Self
     where
+        V: Into<Option<Version>>,
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
@@ -92,7 +93,7 @@ impl Builder for RegexRoutesBuilder {
             .push(RegexRoute {
                 pattern,
                 handler,
-                version,
+                version: version.into(),
             });
         self
     }
@@ -114,12 +115,17 @@ impl Recognizer for RegexRecognizer {
     fn recognize(
         &self,
         method: &Method,
-        api_version: Version,
+        api_version: Option<Version>,
         path: &str,
     ) -> Result<HandlerParamsPair<'_, Self::Parameters>, StatusCode> {
         let routes = self.routes.get(method).ok_or(StatusCode::NOT_FOUND)?;
         for route in routes {
-            if api_version >= route.version {
+            let served = match (api_version, route.version) {
+                (_, None) => true,
+                (Some(api_version), Some(version)) => api_version >= version,
+                (None, Some(_)) => false,
+            };
+            if served {
                 if let Some(params) = match_route(&route.pattern, path) {
                     return Ok((&*route.handler, params));
                 }
@@ -0,0 +1,1294 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A client for the `GetCACert` and `PKIOperation` transactions of the Simple Certificate
+//! Enrollment Protocol (SCEP, RFC 8894).
+//!
+//! A PKCS#10 request is encrypted to the CA (or its RA) and signed with a self-signed certificate
+//! of the requester's key, and the issued certificate comes back encrypted to that certificate.
+//! The openssl crate can't add the SCEP attributes to a signed PKCS#7 structure, so the messages
+//! are encoded with the DER helpers shared with the EST client. Only RSA keys are supported, since
+//! the key of each side is used to encrypt the messages it receives.
+
+use std::iter;
+use std::sync::Arc;
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future, IntoFuture, Stream};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Chunk, Method, Request, StatusCode, Uri};
+use log::debug;
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkey::{PKey, PKeyRef, Private};
+use openssl::rand::rand_bytes;
+use openssl::rsa::Padding;
+use openssl::sign::{Signer, Verifier};
+use openssl::symm::{self, Cipher};
+use openssl::x509::{X509NameRef, X509Req, X509};
+use url::Url;
+
+use crate::client::ClientImpl;
+use crate::der::{self, CertificateNames};
+use crate::error::{Error, ErrorKind};
+use crate::{MaybeProxyClient, PemCertificate};
+
+const CA_CERT_CONTENT_TYPE: &str = "application/x-x509-ca-cert";
+const PKI_MESSAGE_CONTENT_TYPE: &str = "application/x-pki-message";
+
+const MESSAGE_TYPE_CERT_REP: &[u8] = b"3";
+const MESSAGE_TYPE_PKCS_REQ: &[u8] = b"19";
+
+const PKI_STATUS_SUCCESS: &[u8] = b"0";
+const PKI_STATUS_FAILURE: &[u8] = b"2";
+const PKI_STATUS_PENDING: &[u8] = b"3";
+
+const NONCE_SIZE: usize = 16;
+
+/// 1.2.840.113549.1.1.1
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+/// 1.2.840.113549.1.1.11
+const OID_SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+/// 1.3.14.3.2.26
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+/// 2.16.840.1.101.3.4.2.1
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// 2.16.840.1.101.3.4.1.2
+const OID_AES_128_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02];
+/// 2.16.840.1.101.3.4.1.42
+const OID_AES_256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+/// 1.2.840.113549.3.7
+const OID_DES_EDE3_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+
+/// 1.2.840.113549.1.9.3
+const OID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+/// 1.2.840.113549.1.9.4
+const OID_MESSAGE_DIGEST: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+/// 1.2.840.113549.1.9.7
+const OID_CHALLENGE_PASSWORD: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x07];
+
+/// 2.16.840.1.113733.1.9.2
+const OID_MESSAGE_TYPE: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x02];
+/// 2.16.840.1.113733.1.9.3
+const OID_PKI_STATUS: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x03];
+/// 2.16.840.1.113733.1.9.4
+const OID_FAIL_INFO: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x04];
+/// 2.16.840.1.113733.1.9.5
+const OID_SENDER_NONCE: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x05];
+/// 2.16.840.1.113733.1.9.6
+const OID_RECIPIENT_NONCE: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x06];
+/// 2.16.840.1.113733.1.9.7
+const OID_TRANSACTION_ID: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x86, 0xf8, 0x45, 0x01, 0x09, 0x07];
+
+pub struct ScepClient<C> {
+    client: Arc<C>,
+    url: Url,
+    challenge_password: Option<String>,
+    ca_fingerprint: Option<String>,
+}
+
+impl ScepClient<MaybeProxyClient> {
+    /// Creates a client for the SCEP server at `url` (for example ending in `/scep` or
+    /// `/certsrv/mscep/mscep.dll`).
+    pub fn from_url(
+        url: Url,
+        trust_bundle: Option<PemCertificate>,
+        proxy_uri: Option<Uri>,
+    ) -> Result<Self, Error> {
+        let client = MaybeProxyClient::new(proxy_uri, None, trust_bundle)?;
+        Ok(ScepClient::new(client, url))
+    }
+}
+
+impl<C> ScepClient<C>
+where
+    C: ClientImpl,
+{
+    pub fn new(client: C, url: Url) -> Self {
+        ScepClient {
+            client: Arc::new(client),
+            url,
+            challenge_password: None,
+            ca_fingerprint: None,
+        }
+    }
+
+    /// Sets the password that the CA requires to issue certificates. It is added to each request
+    /// as its challengePassword attribute.
+    pub fn with_challenge_password(mut self, challenge_password: String) -> Self {
+        self.challenge_password = Some(challenge_password);
+        self
+    }
+
+    /// Only trusts the server if one of the certificates returned by `GetCACert` has this SHA-256
+    /// or SHA-1 fingerprint, written in hex with optional colons. Required for plain http URLs.
+    pub fn with_ca_fingerprint(mut self, ca_fingerprint: String) -> Self {
+        self.ca_fingerprint = Some(ca_fingerprint);
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Gets the certificate of the CA, followed by the certificate of its RA if it has one.
+    ///
+    /// The CA is the certificate with the configured fingerprint, or else the self-issued one,
+    /// which is only trusted because the server was reached over TLS. The other certificates
+    /// returned by the server are dropped unless the CA signed them, so that a man in the middle
+    /// can't have the request and its challenge password encrypted to an RA of its own.
+    pub fn get_ca_certificates(&self) -> impl Future<Item = Vec<X509>, Error = Error> + Send {
+        let ca_fingerprint = self.ca_fingerprint.clone();
+        if self.url.scheme() != "https" && ca_fingerprint.is_none() {
+            return future::Either::B(future::err(Error::from(ErrorKind::ScepInsecureUrl(
+                self.url.to_string(),
+            ))));
+        }
+
+        future::Either::A(
+            send(&*self.client, &self.url, Method::GET, "GetCACert", None)
+                .map_err(|err| Error::from(err.context(ErrorKind::ScepCaCertificates)))
+                .and_then(move |(content_type, body)| {
+                    let certs =
+                        parse_ca_certificates(content_type.as_ref().map(String::as_str), &body)?;
+                    trusted_certificates(certs, ca_fingerprint.as_ref().map(String::as_str))
+                }),
+        )
+    }
+
+    /// Requests a certificate for the DER-encoded PKCS#10 request `csr`, which must be signed
+    /// with the RSA key in the PEM `private_key`.
+    ///
+    /// The returned certificate contains the issued certificate followed by any other
+    /// certificates the server returned, and has `private_key` attached so that it can be used as
+    /// a TLS identity. A request that the CA leaves pending for manual approval fails with
+    /// `ErrorKind::ScepPending`, since polling for the certificate isn't supported.
+    pub fn enroll(
+        &self,
+        csr: &[u8],
+        private_key: Vec<u8>,
+    ) -> impl Future<Item = PemCertificate, Error = Error> + Send {
+        let request = PkiRequest::new(
+            csr,
+            private_key,
+            self.challenge_password.as_ref().map(String::as_str),
+        );
+
+        match request {
+            Ok(request) => {
+                let client = self.client.clone();
+                let url = self.url.clone();
+
+                future::Either::A(self.get_ca_certificates().and_then(move |ca_certs| {
+                    let message = request.message(&ca_certs);
+                    message.into_future().and_then(move |message| {
+                        send(&*client, &url, Method::POST, "PKIOperation", Some(message))
+                            .map_err(|err| Error::from(err.context(ErrorKind::ScepEnrollment)))
+                            .and_then(move |(_, body)| request.parse_response(&body, &ca_certs))
+                    })
+                }))
+            }
+            Err(err) => future::Either::B(future::err(err)),
+        }
+    }
+}
+
+/// Sends a SCEP operation, with the DER-encoded `message` as the body of POST requests, and
+/// returns the content type and body of the response.
+fn send<C>(
+    client: &C,
+    url: &Url,
+    method: Method,
+    operation: &str,
+    message: Option<Vec<u8>>,
+) -> impl Future<Item = (Option<String>, Chunk), Error = Error> + Send
+where
+    C: ClientImpl,
+{
+    let mut url = url.clone();
+    url.query_pairs_mut().append_pair("operation", operation);
+
+    let request = url
+        .as_str()
+        .parse::<Uri>()
+        .with_context(|_| ErrorKind::InvalidUrl(url.to_string()))
+        .map_err(Error::from)
+        .and_then(|uri| -> Result<_, Error> {
+            let mut request = Request::builder();
+            request.method(method).uri(uri);
+            let body = match message {
+                Some(message) => {
+                    request.header(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(PKI_MESSAGE_CONTENT_TYPE),
+                    );
+                    Body::from(message)
+                }
+                None => Body::empty(),
+            };
+            let request = request.body(body).context(ErrorKind::Http)?;
+            Ok(request)
+        });
+
+    match request {
+        Ok(request) => future::Either::A(
+            client
+                .call(request)
+                .then(|response| -> Result<_, Error> {
+                    let response = response.context(ErrorKind::Http)?;
+                    Ok(response)
+                })
+                .and_then(|response| {
+                    let (parts, body) = response.into_parts();
+                    body.concat2().then(move |body| {
+                        let body = body.context(ErrorKind::Http)?;
+                        if parts.status != StatusCode::OK {
+                            return Err(Error::http_with_error_response(parts.status, &*body));
+                        }
+
+                        let content_type = parts
+                            .headers
+                            .get(CONTENT_TYPE)
+                            .and_then(|value| value.to_str().ok())
+                            .map(ToString::to_string);
+                        Ok((content_type, body))
+                    })
+                }),
+        ),
+        Err(err) => future::Either::B(future::err(err)),
+    }
+}
+
+/// Parses the response of `GetCACert`, which is either a single DER-encoded certificate or a
+/// certs-only PKCS#7 structure with the CA and RA certificates.
+fn parse_ca_certificates(content_type: Option<&str>, body: &[u8]) -> Result<Vec<X509>, Error> {
+    let certs = if content_type.map_or(false, |value| value.starts_with(CA_CERT_CONTENT_TYPE)) {
+        vec![X509::from_der(body).context(ErrorKind::ScepCaCertificates)?]
+    } else {
+        der::pkcs7_certificates(body)
+            .ok_or(ErrorKind::ScepCaCertificates)?
+            .into_iter()
+            .map(|cert| {
+                X509::from_der(cert)
+                    .context(ErrorKind::ScepCaCertificates)
+                    .map_err(Error::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    if certs.is_empty() {
+        return Err(Error::from(ErrorKind::ScepCaCertificates));
+    }
+
+    Ok(certs)
+}
+
+/// Moves the CA to the front of `certs` and drops the certificates that it didn't sign.
+fn trusted_certificates(
+    mut certs: Vec<X509>,
+    ca_fingerprint: Option<&str>,
+) -> Result<Vec<X509>, Error> {
+    let position = match ca_fingerprint {
+        Some(ca_fingerprint) => find_fingerprint(&certs, ca_fingerprint)?,
+        None => {
+            let mut self_issued = None;
+            for (i, cert) in certs.iter().enumerate() {
+                let der = cert.to_der().context(ErrorKind::ScepCaCertificates)?;
+                let names = CertificateNames::parse(&der).ok_or(ErrorKind::ScepCaCertificates)?;
+                if names.is_self_issued() {
+                    self_issued = Some(i);
+                    break;
+                }
+            }
+            self_issued.unwrap_or(0)
+        }
+    };
+
+    let ca = certs.remove(position);
+    let ca_key = ca.public_key().context(ErrorKind::ScepCaCertificates)?;
+    let mut trusted = vec![ca];
+    for cert in certs {
+        if cert.verify(&ca_key).unwrap_or(false) {
+            trusted.push(cert);
+        } else {
+            debug!("Ignoring a SCEP server certificate that is not signed by the CA");
+        }
+    }
+    Ok(trusted)
+}
+
+/// Returns the position of the certificate in `certs` with the fingerprint `ca_fingerprint`.
+fn find_fingerprint(certs: &[X509], ca_fingerprint: &str) -> Result<usize, Error> {
+    let expected: String = ca_fingerprint
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let digest = match expected.len() {
+        40 => MessageDigest::sha1(),
+        64 => MessageDigest::sha256(),
+        _ => return Err(Error::from(ErrorKind::ScepCaFingerprint)),
+    };
+
+    for (i, cert) in certs.iter().enumerate() {
+        let cert = cert.to_der().context(ErrorKind::ScepCaCertificates)?;
+        let fingerprint = hash(digest, &cert).context(ErrorKind::ScepCaCertificates)?;
+        if hex(&fingerprint) == expected {
+            return Ok(i);
+        }
+    }
+
+    Err(Error::from(ErrorKind::ScepCaFingerprint))
+}
+
+/// Picks the certificate that requests are encrypted to out of the certificates returned by
+/// `get_ca_certificates`. That is the RA certificate if the server returned one, and the CA
+/// certificate otherwise.
+fn recipient(ca_certs: &[X509]) -> Result<&X509, Error> {
+    ca_certs
+        .get(1)
+        .or_else(|| ca_certs.first())
+        .ok_or_else(|| Error::from(ErrorKind::ScepEnrollment))
+}
+
+/// A `PKCSReq` message, and what is needed to read the server's response to it.
+struct PkiRequest {
+    csr: Vec<u8>,
+    key: PKey<Private>,
+    private_key: Vec<u8>,
+    signer: X509,
+    transaction_id: String,
+    sender_nonce: Vec<u8>,
+}
+
+impl PkiRequest {
+    fn new(
+        csr: &[u8],
+        private_key: Vec<u8>,
+        challenge_password: Option<&str>,
+    ) -> Result<Self, Error> {
+        let key = PKey::private_key_from_pem(&private_key).context(ErrorKind::ScepEnrollment)?;
+        if key.rsa().is_err() {
+            return Err(Error::from(ErrorKind::ScepUnsupportedKey));
+        }
+
+        let csr = match challenge_password {
+            Some(challenge_password) => with_challenge_password(csr, challenge_password, &key)?,
+            None => csr.to_vec(),
+        };
+
+        let req = X509Req::from_der(&csr).context(ErrorKind::ScepEnrollment)?;
+        let signer =
+            self_signed_certificate(req.subject_name(), &key).context(ErrorKind::ScepEnrollment)?;
+
+        // The transaction ID only has to be unique for the requester's key, so it is derived from
+        // the key as RFC 8894 recommends.
+        let public_key = key.public_key_to_der().context(ErrorKind::ScepEnrollment)?;
+        let transaction_id =
+            hex(&hash(MessageDigest::sha256(), &public_key).context(ErrorKind::ScepEnrollment)?);
+
+        let mut sender_nonce = vec![0; NONCE_SIZE];
+        rand_bytes(&mut sender_nonce).context(ErrorKind::ScepEnrollment)?;
+
+        Ok(PkiRequest {
+            csr,
+            key,
+            private_key,
+            signer,
+            transaction_id,
+            sender_nonce,
+        })
+    }
+
+    fn message(&self, ca_certs: &[X509]) -> Result<Vec<u8>, Error> {
+        let envelope = envelope(&self.csr, recipient(ca_certs)?)?;
+        let attributes = vec![
+            attribute(
+                OID_MESSAGE_TYPE,
+                &der::write(der::TAG_PRINTABLE_STRING, MESSAGE_TYPE_PKCS_REQ),
+            ),
+            attribute(
+                OID_TRANSACTION_ID,
+                &der::write(der::TAG_PRINTABLE_STRING, self.transaction_id.as_bytes()),
+            ),
+            attribute(
+                OID_SENDER_NONCE,
+                &der::write(der::TAG_OCTET_STRING, &self.sender_nonce),
+            ),
+        ];
+        sign(&envelope, attributes, &self.signer, &self.key)
+    }
+
+    /// Checks that the `CertRep` message `body` was signed by the CA or its RA and answers this
+    /// request, and returns the certificate issued in it.
+    fn parse_response(&self, body: &[u8], ca_certs: &[X509]) -> Result<PemCertificate, Error> {
+        let response = SignedData::parse(body).ok_or(ErrorKind::ScepResponse)?;
+        let (ca, ras) = ca_certs.split_first().ok_or(ErrorKind::ScepResponse)?;
+        response.verify(ca, ras)?;
+
+        let string = |oid: &[u8]| response.string_attribute(oid, der::TAG_PRINTABLE_STRING);
+        if string(OID_MESSAGE_TYPE) != Some(MESSAGE_TYPE_CERT_REP)
+            || string(OID_TRANSACTION_ID) != Some(self.transaction_id.as_bytes())
+            || response.string_attribute(OID_RECIPIENT_NONCE, der::TAG_OCTET_STRING)
+                != Some(&self.sender_nonce[..])
+        {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        }
+
+        let status = string(OID_PKI_STATUS).ok_or(ErrorKind::ScepResponse)?;
+        if status == PKI_STATUS_FAILURE {
+            let fail_info =
+                string(OID_FAIL_INFO).map_or_else(|| "unknown".to_string(), fail_info_name);
+            return Err(Error::from(ErrorKind::ScepFailure(fail_info)));
+        } else if status == PKI_STATUS_PENDING {
+            return Err(Error::from(ErrorKind::ScepPending(
+                self.transaction_id.clone(),
+            )));
+        } else if status != PKI_STATUS_SUCCESS {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        }
+
+        let envelope = response.content.ok_or(ErrorKind::ScepResponse)?;
+        let certs_only = decrypt(envelope, &self.signer, &self.key)?;
+        let public_key = self
+            .key
+            .public_key_to_der()
+            .context(ErrorKind::ScepResponse)?;
+
+        // the issued certificate goes first, whatever order the server sent the chain in
+        let mut chain = vec![];
+        let mut issued = false;
+        for cert in der::pkcs7_certificates(&certs_only).ok_or(ErrorKind::ScepResponse)? {
+            let cert = X509::from_der(cert).context(ErrorKind::ScepResponse)?;
+            let cert_public_key = cert
+                .public_key()
+                .and_then(|key| key.public_key_to_der())
+                .context(ErrorKind::ScepResponse)?;
+            if !issued && cert_public_key == public_key {
+                issued = true;
+                chain.insert(0, cert);
+            } else {
+                chain.push(cert);
+            }
+        }
+
+        if !issued {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        }
+
+        let mut pem = vec![];
+        for cert in chain {
+            pem.extend(cert.to_pem().context(ErrorKind::ScepResponse)?);
+        }
+
+        Ok(PemCertificate::new(
+            pem,
+            Some(self.private_key.clone()),
+            None,
+            None,
+        ))
+    }
+}
+
+fn fail_info_name(fail_info: &[u8]) -> String {
+    match fail_info {
+        b"0" => "badAlg".to_string(),
+        b"1" => "badMessageCheck".to_string(),
+        b"2" => "badRequest".to_string(),
+        b"3" => "badTime".to_string(),
+        b"4" => "badCertId".to_string(),
+        _ => String::from_utf8_lossy(fail_info).into_owned(),
+    }
+}
+
+/// Adds the challengePassword attribute to the DER-encoded PKCS#10 request `csr`, and signs it
+/// again with `key`.
+fn with_challenge_password(
+    csr: &[u8],
+    challenge_password: &str,
+    key: &PKeyRef<Private>,
+) -> Result<Vec<u8>, Error> {
+    // CertificationRequest ::= SEQUENCE {
+    //     certificationRequestInfo SEQUENCE { version INTEGER, subject Name,
+    //                                         subjectPKInfo SEQUENCE,
+    //                                         attributes [0] IMPLICIT SET OF Attribute },
+    //     signatureAlgorithm SEQUENCE, signature BIT STRING }
+    let request_info = || -> Option<_> {
+        let (_, csr, _) = der::read(csr, der::TAG_SEQUENCE)?;
+        let (_, request_info, _) = der::read(csr, der::TAG_SEQUENCE)?;
+        let (version, _, rest) = der::read(request_info, der::TAG_INTEGER)?;
+        let (subject, _, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (public_key, _, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, attributes, _) = der::read(rest, der::TAG_CONTEXT_0)?;
+        Some((version, subject, public_key, attributes))
+    };
+    let (version, subject, public_key, attributes) =
+        request_info().ok_or(ErrorKind::ScepEnrollment)?;
+
+    let password = attribute(
+        OID_CHALLENGE_PASSWORD,
+        &directory_string(challenge_password),
+    );
+    let attributes = der::write_all(der::TAG_CONTEXT_0, &[attributes, &password]);
+    let request_info = der::write_all(
+        der::TAG_SEQUENCE,
+        &[version, subject, public_key, &attributes],
+    );
+
+    let mut signer =
+        Signer::new(MessageDigest::sha256(), key).context(ErrorKind::ScepEnrollment)?;
+    signer
+        .update(&request_info)
+        .context(ErrorKind::ScepEnrollment)?;
+    // a BIT STRING starts with its number of unused bits
+    let mut signature = vec![0];
+    signature.extend(signer.sign_to_vec().context(ErrorKind::ScepEnrollment)?);
+
+    Ok(der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &request_info,
+            &der::algorithm(OID_SHA256_WITH_RSA),
+            &der::write(der::TAG_BIT_STRING, &signature),
+        ],
+    ))
+}
+
+/// Encodes `value` as a `PrintableString` if it only has characters that one allows, and as a
+/// `UTF8String` otherwise.
+fn directory_string(value: &str) -> Vec<u8> {
+    let printable = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || " '()+,-./:=?".contains(c));
+    let tag = if printable {
+        der::TAG_PRINTABLE_STRING
+    } else {
+        der::TAG_UTF8_STRING
+    };
+    der::write(tag, value.as_bytes())
+}
+
+/// The certificate that signs requests. SCEP requires one even though the requester doesn't have
+/// a certificate yet, so it is issued by the requester's own key for a day.
+fn self_signed_certificate(
+    subject: &X509NameRef,
+    key: &PKeyRef<Private>,
+) -> Result<X509, ErrorStack> {
+    let mut serial_number = [0; 16];
+    rand_bytes(&mut serial_number)?;
+    // keep the serial number positive
+    serial_number[0] &= 0x7f;
+    let serial_number = BigNum::from_slice(&serial_number)?.to_asn1_integer()?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial_number)?;
+    builder.set_subject_name(subject)?;
+    builder.set_issuer_name(subject)?;
+    builder.set_pubkey(key)?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(1)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.sign(key, MessageDigest::sha256())?;
+    Ok(builder.build())
+}
+
+/// Encodes a PKCS#9 `Attribute` with a single value.
+fn attribute(oid: &[u8], value: &[u8]) -> Vec<u8> {
+    der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_OID, oid),
+            &der::write(der::TAG_SET, value),
+        ],
+    )
+}
+
+/// Encodes a `ContentInfo` with the given content type.
+fn content_info(content_type: &[u8], content: &[u8]) -> Vec<u8> {
+    der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_OID, content_type),
+            &der::write(der::TAG_CONTEXT_0, content),
+        ],
+    )
+}
+
+/// Returns the content of a `ContentInfo` with the given content type.
+fn read_content_info<'a>(der: &'a [u8], content_type: &[u8]) -> Option<&'a [u8]> {
+    let (_, content_info, _) = der::read(der, der::TAG_SEQUENCE)?;
+    let (_, oid, content_info) = der::read(content_info, der::TAG_OID)?;
+    if oid != content_type {
+        return None;
+    }
+    let (_, content, _) = der::read(content_info, der::TAG_CONTEXT_0)?;
+    Some(content)
+}
+
+/// Signs `content` with SHA-256 and the given signed attributes, as PKCS#7 signedData that
+/// includes `cert`.
+fn sign(
+    content: &[u8],
+    mut attributes: Vec<Vec<u8>>,
+    cert: &X509,
+    key: &PKeyRef<Private>,
+) -> Result<Vec<u8>, Error> {
+    let cert = cert.to_der().context(ErrorKind::ScepEnrollment)?;
+    let signer = CertificateNames::parse(&cert).ok_or(ErrorKind::ScepEnrollment)?;
+
+    let digest = hash(MessageDigest::sha256(), content).context(ErrorKind::ScepEnrollment)?;
+    attributes.push(attribute(
+        OID_CONTENT_TYPE,
+        &der::write(der::TAG_OID, der::OID_DATA),
+    ));
+    attributes.push(attribute(
+        OID_MESSAGE_DIGEST,
+        &der::write(der::TAG_OCTET_STRING, &digest),
+    ));
+    // the attributes are a DER SET OF, whose elements are sorted by their encoding
+    attributes.sort();
+    let attributes = attributes.concat();
+
+    let mut signer_key =
+        Signer::new(MessageDigest::sha256(), key).context(ErrorKind::ScepEnrollment)?;
+    signer_key
+        .update(&der::write(der::TAG_SET, &attributes))
+        .context(ErrorKind::ScepEnrollment)?;
+    let signature = signer_key
+        .sign_to_vec()
+        .context(ErrorKind::ScepEnrollment)?;
+
+    let signer_info = der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_INTEGER, &[1]),
+            &signer.issuer_and_serial_number(),
+            &der::algorithm(OID_SHA256),
+            &der::write(der::TAG_CONTEXT_0, &attributes),
+            &der::algorithm(OID_RSA_ENCRYPTION),
+            &der::write(der::TAG_OCTET_STRING, &signature),
+        ],
+    );
+    let signed_data = der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_INTEGER, &[1]),
+            &der::write(der::TAG_SET, &der::algorithm(OID_SHA256)),
+            &content_info(der::OID_DATA, &der::write(der::TAG_OCTET_STRING, content)),
+            &der::write(der::TAG_CONTEXT_0, &cert),
+            &der::write(der::TAG_SET, &signer_info),
+        ],
+    );
+
+    Ok(content_info(der::OID_SIGNED_DATA, &signed_data))
+}
+
+/// The parts of a PKCS#7 signedData structure with a single signer that SCEP uses.
+struct SignedData<'a> {
+    content: Option<&'a [u8]>,
+    certificates: Vec<&'a [u8]>,
+    signer: &'a [u8],
+    digest_algorithm: &'a [u8],
+    attributes: &'a [u8],
+    signature: &'a [u8],
+}
+
+impl<'a> SignedData<'a> {
+    fn parse(der: &'a [u8]) -> Option<Self> {
+        // SignedData ::= SEQUENCE { version INTEGER, digestAlgorithms SET,
+        //                           contentInfo SEQUENCE { contentType OID,
+        //                                                  content [0] EXPLICIT OPTIONAL },
+        //                           certificates [0] IMPLICIT OPTIONAL,
+        //                           crls [1] IMPLICIT OPTIONAL, signerInfos SET }
+        let content = read_content_info(der, der::OID_SIGNED_DATA)?;
+        let (_, signed_data, _) = der::read(content, der::TAG_SEQUENCE)?;
+        let (_, _, rest) = der::read(signed_data, der::TAG_INTEGER)?;
+        let (_, _, rest) = der::read(rest, der::TAG_SET)?;
+        let (_, content_info, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, _, content_info) = der::read(content_info, der::TAG_OID)?;
+        let content = match der::read_optional(content_info, der::TAG_CONTEXT_0)?.0 {
+            Some(content) => Some(der::read(content, der::TAG_OCTET_STRING)?.1),
+            None => None,
+        };
+        let (certificates, rest) = der::read_optional(rest, der::TAG_CONTEXT_0)?;
+        let certificates = match certificates {
+            Some(certificates) => der::split_certificates(certificates)?,
+            None => vec![],
+        };
+        let (_, rest) = der::read_optional(rest, der::TAG_CONTEXT_1)?;
+        let (_, signer_infos, _) = der::read(rest, der::TAG_SET)?;
+
+        // SignerInfo ::= SEQUENCE { version INTEGER, issuerAndSerialNumber SEQUENCE,
+        //                           digestAlgorithm SEQUENCE,
+        //                           authenticatedAttributes [0] IMPLICIT,
+        //                           digestEncryptionAlgorithm SEQUENCE,
+        //                           encryptedDigest OCTET STRING, ... }
+        let (_, signer_info, _) = der::read(signer_infos, der::TAG_SEQUENCE)?;
+        let (_, _, rest) = der::read(signer_info, der::TAG_INTEGER)?;
+        let (signer, _, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, digest_algorithm, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, digest_algorithm, _) = der::read(digest_algorithm, der::TAG_OID)?;
+        let (_, attributes, rest) = der::read(rest, der::TAG_CONTEXT_0)?;
+        let (_, _, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, signature, _) = der::read(rest, der::TAG_OCTET_STRING)?;
+
+        Some(SignedData {
+            content,
+            certificates,
+            signer,
+            digest_algorithm,
+            attributes,
+            signature,
+        })
+    }
+
+    /// Returns the encoded value of the signed attribute with the given OID.
+    fn attribute(&self, oid: &[u8]) -> Option<&'a [u8]> {
+        let mut attributes = self.attributes;
+        while !attributes.is_empty() {
+            let (_, attribute, rest) = der::read(attributes, der::TAG_SEQUENCE)?;
+            let (_, attribute_oid, values) = der::read(attribute, der::TAG_OID)?;
+            if attribute_oid == oid {
+                let (_, values, _) = der::read(values, der::TAG_SET)?;
+                return Some(values);
+            }
+            attributes = rest;
+        }
+        None
+    }
+
+    /// Returns the contents of the value of the signed attribute with the given OID, if it is
+    /// encoded with `tag`.
+    fn string_attribute(&self, oid: &[u8], tag: u8) -> Option<&'a [u8]> {
+        let (_, value, _) = der::read(self.attribute(oid)?, tag)?;
+        Some(value)
+    }
+
+    /// Checks the content digest and the signature, which must be made by `ca` or by a
+    /// certificate that `ca` signed. The signer info identifies the certificate among `certs`
+    /// and the certificates included in the signed data.
+    fn verify(&self, ca: &X509, certs: &[X509]) -> Result<(), Error> {
+        let digest = if self.digest_algorithm == OID_SHA256 {
+            MessageDigest::sha256()
+        } else if self.digest_algorithm == OID_SHA1 {
+            MessageDigest::sha1()
+        } else {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        };
+
+        let included = self
+            .certificates
+            .iter()
+            .map(|cert| X509::from_der(cert).context(ErrorKind::ScepResponse))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ca_der = ca.to_der().context(ErrorKind::ScepResponse)?;
+        let mut signer = None;
+        for cert in iter::once(ca).chain(certs).chain(&included) {
+            let der = cert.to_der().context(ErrorKind::ScepResponse)?;
+            let names = CertificateNames::parse(&der).ok_or(ErrorKind::ScepResponse)?;
+            if names.issuer_and_serial_number() == self.signer {
+                signer = Some((cert, der));
+                break;
+            }
+        }
+        let (signer, signer_der) = signer.ok_or(ErrorKind::ScepResponse)?;
+        if signer_der != ca_der {
+            let ca_key = ca.public_key().context(ErrorKind::ScepResponse)?;
+            if !signer.verify(&ca_key).context(ErrorKind::ScepResponse)? {
+                return Err(Error::from(ErrorKind::ScepResponse));
+            }
+        }
+
+        let content_digest =
+            hash(digest, self.content.unwrap_or(&[])).context(ErrorKind::ScepResponse)?;
+        if self.string_attribute(OID_MESSAGE_DIGEST, der::TAG_OCTET_STRING)
+            != Some(&content_digest[..])
+        {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        }
+
+        let public_key = signer.public_key().context(ErrorKind::ScepResponse)?;
+        let mut verifier = Verifier::new(digest, &public_key).context(ErrorKind::ScepResponse)?;
+        verifier
+            .update(&der::write(der::TAG_SET, self.attributes))
+            .context(ErrorKind::ScepResponse)?;
+        if !verifier
+            .verify(self.signature)
+            .context(ErrorKind::ScepResponse)?
+        {
+            return Err(Error::from(ErrorKind::ScepResponse));
+        }
+
+        Ok(())
+    }
+}
+
+/// Encrypts `content` with AES-256-CBC under a random key, and encrypts that key to `recipient`,
+/// as PKCS#7 envelopedData.
+fn envelope(content: &[u8], recipient: &X509) -> Result<Vec<u8>, Error> {
+    let cipher = Cipher::aes_256_cbc();
+    let mut content_key = vec![0; cipher.key_len()];
+    let mut iv = vec![0; cipher.iv_len().unwrap_or(0)];
+    rand_bytes(&mut content_key).context(ErrorKind::ScepEnrollment)?;
+    rand_bytes(&mut iv).context(ErrorKind::ScepEnrollment)?;
+    let encrypted_content = symm::encrypt(cipher, &content_key, Some(&iv[..]), content)
+        .context(ErrorKind::ScepEnrollment)?;
+
+    let rsa = recipient
+        .public_key()
+        .and_then(|key| key.rsa())
+        .context(ErrorKind::ScepUnsupportedKey)?;
+    let mut encrypted_key = vec![0; rsa.size() as usize];
+    let len = rsa
+        .public_encrypt(&content_key, &mut encrypted_key, Padding::PKCS1)
+        .context(ErrorKind::ScepEnrollment)?;
+    encrypted_key.truncate(len);
+
+    let recipient = recipient.to_der().context(ErrorKind::ScepEnrollment)?;
+    let recipient = CertificateNames::parse(&recipient).ok_or(ErrorKind::ScepEnrollment)?;
+
+    // EnvelopedData ::= SEQUENCE { version INTEGER, recipientInfos SET OF RecipientInfo,
+    //                              encryptedContentInfo SEQUENCE }
+    let recipient_info = der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_INTEGER, &[0]),
+            &recipient.issuer_and_serial_number(),
+            &der::algorithm(OID_RSA_ENCRYPTION),
+            &der::write(der::TAG_OCTET_STRING, &encrypted_key),
+        ],
+    );
+    let encrypted_content_info = der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_OID, der::OID_DATA),
+            &der::write_all(
+                der::TAG_SEQUENCE,
+                &[
+                    &der::write(der::TAG_OID, OID_AES_256_CBC),
+                    &der::write(der::TAG_OCTET_STRING, &iv),
+                ],
+            ),
+            &der::write(der::TAG_CONTEXT_0_PRIMITIVE, &encrypted_content),
+        ],
+    );
+    let enveloped_data = der::write_all(
+        der::TAG_SEQUENCE,
+        &[
+            &der::write(der::TAG_INTEGER, &[0]),
+            &der::write(der::TAG_SET, &recipient_info),
+            &encrypted_content_info,
+        ],
+    );
+
+    Ok(content_info(der::OID_ENVELOPED_DATA, &enveloped_data))
+}
+
+/// Decrypts PKCS#7 envelopedData that was encrypted to `cert`, whose private key is `key`.
+fn decrypt(der: &[u8], cert: &X509, key: &PKeyRef<Private>) -> Result<Vec<u8>, Error> {
+    let envelope = EnvelopedData::parse(der).ok_or(ErrorKind::ScepResponse)?;
+
+    let cert = cert.to_der().context(ErrorKind::ScepResponse)?;
+    let recipient = CertificateNames::parse(&cert)
+        .ok_or(ErrorKind::ScepResponse)?
+        .issuer_and_serial_number();
+    let encrypted_key = envelope
+        .recipients
+        .iter()
+        .find(|(id, _)| *id == &recipient[..])
+        .map(|(_, encrypted_key)| *encrypted_key)
+        .ok_or(ErrorKind::ScepResponse)?;
+
+    let rsa = key.rsa().context(ErrorKind::ScepUnsupportedKey)?;
+    let mut content_key = vec![0; rsa.size() as usize];
+    let len = rsa
+        .private_decrypt(encrypted_key, &mut content_key, Padding::PKCS1)
+        .context(ErrorKind::ScepResponse)?;
+    content_key.truncate(len);
+
+    let cipher = if envelope.algorithm == OID_AES_256_CBC {
+        Cipher::aes_256_cbc()
+    } else if envelope.algorithm == OID_AES_128_CBC {
+        Cipher::aes_128_cbc()
+    } else if envelope.algorithm == OID_DES_EDE3_CBC {
+        Cipher::des_ede3_cbc()
+    } else {
+        return Err(Error::from(ErrorKind::ScepResponse));
+    };
+
+    let content = symm::decrypt(
+        cipher,
+        &content_key,
+        Some(envelope.iv),
+        &envelope.encrypted_content,
+    )
+    .context(ErrorKind::ScepResponse)?;
+    Ok(content)
+}
+
+/// The parts of a PKCS#7 envelopedData structure that are needed to decrypt it with an RSA key.
+struct EnvelopedData<'a> {
+    /// The `IssuerAndSerialNumber` and encrypted content key of each recipient
+    recipients: Vec<(&'a [u8], &'a [u8])>,
+    algorithm: &'a [u8],
+    iv: &'a [u8],
+    encrypted_content: Vec<u8>,
+}
+
+impl<'a> EnvelopedData<'a> {
+    fn parse(der: &'a [u8]) -> Option<Self> {
+        let content = read_content_info(der, der::OID_ENVELOPED_DATA)?;
+        let (_, enveloped_data, _) = der::read(content, der::TAG_SEQUENCE)?;
+        let (_, _, rest) = der::read(enveloped_data, der::TAG_INTEGER)?;
+        // CMS allows an originatorInfo [0] here
+        let (_, rest) = der::read_optional(rest, der::TAG_CONTEXT_0)?;
+        let (_, mut recipient_infos, rest) = der::read(rest, der::TAG_SET)?;
+
+        // RecipientInfo ::= SEQUENCE { version INTEGER, issuerAndSerialNumber SEQUENCE,
+        //                              keyEncryptionAlgorithm SEQUENCE,
+        //                              encryptedKey OCTET STRING }
+        let mut recipients = vec![];
+        while !recipient_infos.is_empty() {
+            let (_, recipient_info, next) = der::read(recipient_infos, der::TAG_SEQUENCE)?;
+            let (_, _, recipient_info) = der::read(recipient_info, der::TAG_INTEGER)?;
+            let (id, _, recipient_info) = der::read(recipient_info, der::TAG_SEQUENCE)?;
+            let (_, _, recipient_info) = der::read(recipient_info, der::TAG_SEQUENCE)?;
+            let (_, encrypted_key, _) = der::read(recipient_info, der::TAG_OCTET_STRING)?;
+            recipients.push((id, encrypted_key));
+            recipient_infos = next;
+        }
+
+        // EncryptedContentInfo ::= SEQUENCE { contentType OID,
+        //                                     contentEncryptionAlgorithm SEQUENCE,
+        //                                     encryptedContent [0] IMPLICIT OCTET STRING }
+        let (_, encrypted_content_info, _) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, _, rest) = der::read(encrypted_content_info, der::TAG_OID)?;
+        let (_, algorithm, rest) = der::read(rest, der::TAG_SEQUENCE)?;
+        let (_, algorithm, parameters) = der::read(algorithm, der::TAG_OID)?;
+        let (_, iv, _) = der::read(parameters, der::TAG_OCTET_STRING)?;
+
+        let encrypted_content = if rest.first() == Some(&der::TAG_CONTEXT_0) {
+            // the constructed form splits the content into several OCTET STRINGs
+            let (_, mut chunks, _) = der::read(rest, der::TAG_CONTEXT_0)?;
+            let mut encrypted_content = vec![];
+            while !chunks.is_empty() {
+                let (_, chunk, next) = der::read(chunks, der::TAG_OCTET_STRING)?;
+                encrypted_content.extend_from_slice(chunk);
+                chunks = next;
+            }
+            encrypted_content
+        } else {
+            let (_, encrypted_content, _) = der::read(rest, der::TAG_CONTEXT_0_PRIMITIVE)?;
+            encrypted_content.to_vec()
+        };
+
+        Some(EnvelopedData {
+            recipients,
+            algorithm,
+            iv,
+            encrypted_content,
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Response;
+    use openssl::nid::Nid;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509Name, X509NameBuilder};
+
+    fn name(common_name: &str) -> X509Name {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+            .unwrap();
+        name.build()
+    }
+
+    fn rsa_key() -> PKey<Private> {
+        PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+    }
+
+    fn ca() -> (X509, PKey<Private>) {
+        let key = rsa_key();
+        let cert = self_signed_certificate(&name("scep-ca"), &key).unwrap();
+        (cert, key)
+    }
+
+    fn csr() -> (Vec<u8>, Vec<u8>) {
+        let key = rsa_key();
+
+        let mut req = X509Req::builder().unwrap();
+        req.set_subject_name(&name("device")).unwrap();
+        req.set_pubkey(&key).unwrap();
+        req.sign(&key, MessageDigest::sha256()).unwrap();
+
+        (
+            req.build().to_der().unwrap(),
+            key.private_key_to_pem_pkcs8().unwrap(),
+        )
+    }
+
+    fn issue(req: &X509Req, ca_cert: &X509, ca_key: &PKeyRef<Private>) -> X509 {
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial_number = BigNum::from_u32(2).unwrap().to_asn1_integer().unwrap();
+        builder.set_serial_number(&serial_number).unwrap();
+        builder.set_subject_name(req.subject_name()).unwrap();
+        builder.set_issuer_name(ca_cert.subject_name()).unwrap();
+        builder.set_pubkey(&req.public_key().unwrap()).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(30).unwrap())
+            .unwrap();
+        builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /// A certificate for a new key, issued by `ca_cert`.
+    fn issue_for_key(
+        common_name: &str,
+        ca_cert: &X509,
+        ca_key: &PKeyRef<Private>,
+    ) -> (X509, PKey<Private>) {
+        let key = rsa_key();
+        let mut req = X509Req::builder().unwrap();
+        req.set_subject_name(&name(common_name)).unwrap();
+        req.set_pubkey(&key).unwrap();
+        req.sign(&key, MessageDigest::sha256()).unwrap();
+        (issue(&req.build(), ca_cert, ca_key), key)
+    }
+
+    fn certs_only(certs: &[&X509]) -> Vec<u8> {
+        let certs: Vec<Vec<u8>> = certs.iter().map(|cert| cert.to_der().unwrap()).collect();
+        let certs: Vec<&[u8]> = certs.iter().map(AsRef::as_ref).collect();
+        der::certs_only(&certs)
+    }
+
+    fn fingerprint(cert: &X509) -> String {
+        let fingerprint = hash(MessageDigest::sha256(), &cert.to_der().unwrap()).unwrap();
+        fingerprint
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// A SCEP server that issues certificates from `ca_cert`, or rejects every request with
+    /// badRequest if `reject` is set.
+    fn server(
+        ca_cert: X509,
+        ca_key: PKey<Private>,
+        reject: bool,
+    ) -> impl Fn(Request<Body>) -> Result<Response<Body>, hyper::Error> + Send + Sync {
+        move |req: Request<Body>| {
+            if req.uri().query() == Some("operation=GetCACert") {
+                assert_eq!(Method::GET, *req.method());
+                let mut response = Response::new(Body::from(ca_cert.to_der().unwrap()));
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(CA_CERT_CONTENT_TYPE));
+                return Ok(response);
+            }
+
+            assert_eq!(Method::POST, *req.method());
+            assert_eq!(Some("operation=PKIOperation"), req.uri().query());
+            assert_eq!(PKI_MESSAGE_CONTENT_TYPE, req.headers()[CONTENT_TYPE]);
+
+            let body = req.into_body().concat2().wait().unwrap();
+            let request = SignedData::parse(&body).unwrap();
+            let requester = X509::from_der(request.certificates[0]).unwrap();
+            request.verify(&requester, &[]).unwrap();
+            assert_eq!(
+                Some(MESSAGE_TYPE_PKCS_REQ),
+                request.string_attribute(OID_MESSAGE_TYPE, der::TAG_PRINTABLE_STRING)
+            );
+
+            let csr = decrypt(request.content.unwrap(), &ca_cert, &ca_key).unwrap();
+            assert!(csr.windows(7).any(|window| window == b"hunter2"));
+            let csr = X509Req::from_der(&csr).unwrap();
+
+            let transaction_id = request
+                .string_attribute(OID_TRANSACTION_ID, der::TAG_PRINTABLE_STRING)
+                .unwrap();
+            let sender_nonce = request
+                .string_attribute(OID_SENDER_NONCE, der::TAG_OCTET_STRING)
+                .unwrap();
+            let mut attributes = vec![
+                attribute(
+                    OID_MESSAGE_TYPE,
+                    &der::write(der::TAG_PRINTABLE_STRING, MESSAGE_TYPE_CERT_REP),
+                ),
+                attribute(
+                    OID_TRANSACTION_ID,
+                    &der::write(der::TAG_PRINTABLE_STRING, transaction_id),
+                ),
+                attribute(
+                    OID_RECIPIENT_NONCE,
+                    &der::write(der::TAG_OCTET_STRING, sender_nonce),
+                ),
+            ];
+
+            let content = if reject {
+                attributes.push(attribute(
+                    OID_PKI_STATUS,
+                    &der::write(der::TAG_PRINTABLE_STRING, PKI_STATUS_FAILURE),
+                ));
+                attributes.push(attribute(
+                    OID_FAIL_INFO,
+                    &der::write(der::TAG_PRINTABLE_STRING, b"2"),
+                ));
+                vec![]
+            } else {
+                attributes.push(attribute(
+                    OID_PKI_STATUS,
+                    &der::write(der::TAG_PRINTABLE_STRING, PKI_STATUS_SUCCESS),
+                ));
+                let issued = issue(&csr, &ca_cert, &ca_key);
+                envelope(&certs_only(&[&ca_cert, &issued]), &requester).unwrap()
+            };
+
+            let response = sign(&content, attributes, &ca_cert, &ca_key).unwrap();
+            Ok(Response::new(Body::from(response)))
+        }
+    }
+
+    fn common_name(cert: &X509) -> String {
+        cert.subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap()
+            .data()
+            .as_utf8()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn get_ca_certificates_checks_fingerprint() {
+        let (ca_cert, ca_key) = ca();
+        let url = Url::parse("https://scep.example.com/scep").unwrap();
+
+        let client = ScepClient::new(server(ca_cert.clone(), ca_key, false), url.clone())
+            .with_ca_fingerprint(fingerprint(&ca_cert));
+        let certs = client.get_ca_certificates().wait().unwrap();
+        assert_eq!(1, certs.len());
+        assert_eq!("scep-ca", common_name(&certs[0]));
+
+        let (other_ca_cert, other_ca_key) = ca();
+        let client = ScepClient::new(server(other_ca_cert, other_ca_key, false), url)
+            .with_ca_fingerprint(fingerprint(&ca_cert));
+        let err = client.get_ca_certificates().wait().err().unwrap();
+        assert_eq!(&ErrorKind::ScepCaFingerprint, err.kind());
+    }
+
+    #[test]
+    fn enroll_returns_issued_certificate() {
+        let (ca_cert, ca_key) = ca();
+        let (csr, key) = csr();
+
+        let client = ScepClient::new(
+            server(ca_cert, ca_key, false),
+            Url::parse("https://scep.example.com/scep").unwrap(),
+        )
+        .with_challenge_password("hunter2".to_string());
+        let cert = client.enroll(&csr, key).wait().unwrap();
+
+        let chain = X509::stack_from_pem(cert.get_certificate()).unwrap();
+        assert_eq!(2, chain.len());
+        assert_eq!("device", common_name(&chain[0]));
+        assert_eq!("scep-ca", common_name(&chain[1]));
+        assert!(cert.get_identity().is_ok());
+    }
+
+    #[test]
+    fn enroll_fails_when_request_is_rejected() {
+        let (ca_cert, ca_key) = ca();
+        let (csr, key) = csr();
+
+        let client = ScepClient::new(
+            server(ca_cert, ca_key, true),
+            Url::parse("https://scep.example.com/scep").unwrap(),
+        )
+        .with_challenge_password("hunter2".to_string());
+        let err = client.enroll(&csr, key).wait().unwrap_err();
+
+        assert_eq!(
+            &ErrorKind::ScepFailure("badRequest".to_string()),
+            err.kind()
+        );
+    }
+
+    #[test]
+    fn get_ca_certificates_drops_certificates_not_signed_by_ca() {
+        let (ca_cert, ca_key) = ca();
+        let (ra_cert, _) = issue_for_key("scep-ra", &ca_cert, &ca_key);
+        let (rogue_ca_cert, rogue_ca_key) = ca();
+        let (rogue_ra_cert, _) = issue_for_key("rogue-ra", &rogue_ca_cert, &rogue_ca_key);
+        let body = certs_only(&[&rogue_ra_cert, &ra_cert, &ca_cert]);
+        let server =
+            move |_: Request<Body>| Ok::<_, hyper::Error>(Response::new(Body::from(body.clone())));
+
+        let client = ScepClient::new(server, Url::parse("https://scep.example.com/scep").unwrap())
+            .with_ca_fingerprint(fingerprint(&ca_cert));
+        let certs = client.get_ca_certificates().wait().unwrap();
+
+        let names: Vec<String> = certs.iter().map(common_name).collect();
+        assert_eq!(vec!["scep-ca".to_string(), "scep-ra".to_string()], names);
+        assert_eq!("scep-ra", common_name(recipient(&certs).unwrap()));
+    }
+
+    #[test]
+    fn get_ca_certificates_requires_fingerprint_over_http() {
+        let (ca_cert, ca_key) = ca();
+        let url = Url::parse("http://scep.example.com/scep").unwrap();
+
+        let client = ScepClient::new(server(ca_cert.clone(), ca_key.clone(), false), url.clone());
+        let err = client.get_ca_certificates().wait().err().unwrap();
+        assert_eq!(&ErrorKind::ScepInsecureUrl(url.to_string()), err.kind());
+
+        let client = ScepClient::new(server(ca_cert.clone(), ca_key, false), url)
+            .with_ca_fingerprint(fingerprint(&ca_cert));
+        assert_eq!(1, client.get_ca_certificates().wait().unwrap().len());
+    }
+
+    #[test]
+    fn verify_requires_signer_signed_by_ca() {
+        let (ca_cert, ca_key) = ca();
+        let (ra_cert, ra_key) = issue_for_key("scep-ra", &ca_cert, &ca_key);
+        let (rogue_cert, rogue_key) = ca();
+        let signed =
+            |cert: &X509, key: &PKeyRef<Private>| sign(b"content", vec![], cert, key).unwrap();
+
+        let message = signed(&ca_cert, &ca_key);
+        SignedData::parse(&message)
+            .unwrap()
+            .verify(&ca_cert, &[])
+            .unwrap();
+
+        // the RA certificate is found among the certificates included in the message
+        let message = signed(&ra_cert, &ra_key);
+        SignedData::parse(&message)
+            .unwrap()
+            .verify(&ca_cert, &[])
+            .unwrap();
+
+        let message = signed(&rogue_cert, &rogue_key);
+        let err = SignedData::parse(&message)
+            .unwrap()
+            .verify(&ca_cert, &[rogue_cert.clone()])
+            .unwrap_err();
+        assert_eq!(&ErrorKind::ScepResponse, err.kind());
+    }
+}
@@ -0,0 +1,579 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Verification of the signatures of module images, published either in a TUF repository such as
+//! a Notary server, or in a sigstore Rekor transparency log.
+//!
+//! Both kinds of signature are ECDSA signatures over the SHA-256 digest of the image manifest, so
+//! the configured key must be an EC key. It is the only key that is trusted: the root metadata
+//! and delegations of the TUF repository aren't consulted, and neither are the inclusion proofs
+//! of Rekor entries.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use failure::{Fail, ResultExt};
+use futures::{future, Future, Stream};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Chunk, Method, Request, StatusCode, Uri};
+use openssl::bn::BigNum;
+use openssl::ec::EcKey;
+use openssl::ecdsa::EcdsaSig;
+use openssl::pkey::{PKey, Public};
+use openssl::x509::X509;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use url::Url;
+
+use edgelet_core::{SignatureSource, SignatureVerificationConfig};
+
+use crate::client::ClientImpl;
+use crate::error::{Error, ErrorKind};
+use crate::MaybeProxyClient;
+
+const SHA256_PREFIX: &str = "sha256:";
+const REKOR_INDEX_PATH: &str = "api/v1/index/retrieve";
+const REKOR_ENTRIES_PATH: &str = "api/v1/log/entries/retrieve";
+
+#[derive(Deserialize)]
+struct RekorEntry {
+    /// The base64-encoded JSON of the entry
+    body: String,
+}
+
+#[derive(Clone)]
+pub struct ImageSignatureVerifier<C> {
+    client: Arc<C>,
+}
+
+impl ImageSignatureVerifier<MaybeProxyClient> {
+    pub fn from_proxy(proxy_uri: Option<Uri>) -> Result<Self, Error> {
+        let client = MaybeProxyClient::new(proxy_uri, None, None)?;
+        Ok(ImageSignatureVerifier::new(client))
+    }
+}
+
+impl<C> ImageSignatureVerifier<C>
+where
+    C: 'static + ClientImpl,
+{
+    pub fn new(client: C) -> Self {
+        ImageSignatureVerifier {
+            client: Arc::new(client),
+        }
+    }
+
+    /// Verifies that `digest`, the `sha256:` digest of the manifest that `image` was pulled as,
+    /// was signed with the key in `config`. Fails with `ErrorKind::ImageSignatureInvalid` if the
+    /// source has no such signature.
+    ///
+    /// In a TUF repository, the signed digest is looked up by the tag of `image`. An image that
+    /// is only referenced by digest is accepted if any signed tag has that digest.
+    pub fn verify(
+        &self,
+        image: &str,
+        digest: &str,
+        config: &SignatureVerificationConfig,
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        let image = image.to_string();
+        let prepared = public_key(config.public_key())
+            .and_then(|key| Ok((key, parse_digest(&image, digest)?)));
+        let (key, digest_bytes) = match prepared {
+            Ok(prepared) => prepared,
+            Err(err) => return future::Either::B(future::err(err)),
+        };
+        let lookup_error = {
+            let image = image.clone();
+            move |err: Error| Error::from(err.context(ErrorKind::ImageSignatureLookup(image)))
+        };
+
+        let verified = match config.source() {
+            SignatureSource::Tuf(url) => {
+                let (repository, tag) = parse_reference(&image);
+                let path = format!("v2/{}/_trust/tuf/targets.json", repository);
+                let tag = tag.map(ToString::to_string);
+                let image = image.clone();
+
+                future::Either::A(
+                    send(&*self.client, url, Method::GET, &path, None)
+                        .map_err(lookup_error)
+                        .and_then(move |body| {
+                            let targets = serde_json::from_slice(&body)
+                                .context(ErrorKind::ImageSignatureLookup(image))?;
+                            Ok(verify_tuf_targets(
+                                &targets,
+                                tag.as_ref().map(String::as_str),
+                                &digest_bytes,
+                                &key,
+                                Utc::now(),
+                            ))
+                        }),
+                )
+            }
+            SignatureSource::Rekor(url) => {
+                let client = self.client.clone();
+                let url = url.clone();
+                let digest = digest.to_string();
+                let image = image.clone();
+                let index_request = json!({ "hash": digest }).to_string();
+
+                future::Either::B(
+                    send(
+                        &*client,
+                        &url,
+                        Method::POST,
+                        REKOR_INDEX_PATH,
+                        Some(index_request),
+                    )
+                    .map_err(lookup_error.clone())
+                    .and_then(move |body| {
+                        let entry_uuids: Vec<String> = serde_json::from_slice(&body)
+                            .context(ErrorKind::ImageSignatureLookup(image.clone()))?;
+                        Ok((image, entry_uuids))
+                    })
+                    .and_then(move |(image, entry_uuids)| {
+                        let entries_request = json!({ "entryUUIDs": entry_uuids }).to_string();
+                        send(
+                            &*client,
+                            &url,
+                            Method::POST,
+                            REKOR_ENTRIES_PATH,
+                            Some(entries_request),
+                        )
+                        .map_err(lookup_error)
+                        .and_then(move |body| {
+                            let entries: Vec<HashMap<String, RekorEntry>> =
+                                serde_json::from_slice(&body)
+                                    .context(ErrorKind::ImageSignatureLookup(image))?;
+                            Ok(entries.iter().flat_map(HashMap::values).any(|entry| {
+                                verify_rekor_entry(entry, &digest, &digest_bytes, &key)
+                            }))
+                        })
+                    }),
+                )
+            }
+        };
+
+        future::Either::A(verified.and_then(move |verified| {
+            if verified {
+                Ok(())
+            } else {
+                Err(Error::from(ErrorKind::ImageSignatureInvalid(image)))
+            }
+        }))
+    }
+}
+
+/// Reads the EC public key in `pem`, which is either a public key or a certificate for one.
+fn public_key(pem: &str) -> Result<EcKey<Public>, Error> {
+    let key = match PKey::public_key_from_pem(pem.as_bytes()) {
+        Ok(key) => key,
+        Err(_) => X509::from_pem(pem.as_bytes())
+            .and_then(|cert| cert.public_key())
+            .context(ErrorKind::ImageSignatureKey)?,
+    };
+    let key = key.ec_key().context(ErrorKind::ImageSignatureKey)?;
+    Ok(key)
+}
+
+fn parse_digest(image: &str, digest: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::from(ErrorKind::ImageSignatureLookup(image.to_string()));
+
+    if !digest.starts_with(SHA256_PREFIX) {
+        return Err(invalid());
+    }
+    let hex = &digest[SHA256_PREFIX.len()..];
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+/// Splits an image reference such as `registry:5000/repo/name:tag@sha256:...` into its
+/// repository and its tag, if it has one.
+pub fn parse_reference(image: &str) -> (&str, Option<&str>) {
+    let name = image.split('@').next().unwrap_or(image);
+    let tag_start = name.rfind(':').filter(|&i| !name[i..].contains('/'));
+    match tag_start {
+        Some(i) => (&name[..i], Some(&name[i + 1..])),
+        None if name.len() < image.len() => (name, None),
+        None => (name, Some("latest")),
+    }
+}
+
+/// Checks the `targets.json` metadata of a TUF repository. It must not have expired, must be
+/// signed with `key`, and must list `digest` as the SHA-256 hash of `tag`, or of any tag if there
+/// is none.
+fn verify_tuf_targets(
+    targets: &Value,
+    tag: Option<&str>,
+    digest: &[u8],
+    key: &EcKey<Public>,
+    now: DateTime<Utc>,
+) -> bool {
+    let signed = &targets["signed"];
+
+    let unexpired = signed["expires"]
+        .as_str()
+        .and_then(|expires| DateTime::parse_from_rfc3339(expires).ok())
+        .map_or(false, |expires| expires.with_timezone(&Utc) > now);
+
+    let mut canonical = String::new();
+    canonical_json(signed, &mut canonical);
+    let signed_digest = openssl::sha::sha256(canonical.as_bytes());
+    let signature_valid = targets["signatures"]
+        .as_array()
+        .map_or(false, |signatures| {
+            signatures.iter().any(|signature| {
+                signature["sig"]
+                    .as_str()
+                    .and_then(|sig| base64::decode(sig).ok())
+                    .map_or(false, |sig| verify_ecdsa(key, &signed_digest, &sig))
+            })
+        });
+
+    let expected_hash = base64::encode(digest);
+    let lists_digest = |target: &Value| target["hashes"]["sha256"] == expected_hash.as_str();
+    let digest_listed = match (tag, signed["targets"].as_object()) {
+        (Some(tag), Some(targets)) => targets.get(tag).map_or(false, lists_digest),
+        (None, Some(targets)) => targets.values().any(lists_digest),
+        (_, None) => false,
+    };
+
+    unexpired && signature_valid && digest_listed
+}
+
+/// Checks that a `hashedrekord` entry of a Rekor log is a signature of `digest` made with `key`.
+fn verify_rekor_entry(
+    entry: &RekorEntry,
+    digest: &str,
+    digest_bytes: &[u8],
+    key: &EcKey<Public>,
+) -> bool {
+    let body: Value = match base64::decode(&entry.body)
+        .ok()
+        .and_then(|body| serde_json::from_slice(&body).ok())
+    {
+        Some(body) => body,
+        None => return false,
+    };
+
+    let spec = &body["spec"];
+    let hash = &spec["data"]["hash"];
+    let hash_matches = hash["algorithm"] == "sha256"
+        && hash["value"].as_str().map_or(false, |value| {
+            value.eq_ignore_ascii_case(&digest[SHA256_PREFIX.len()..])
+        });
+    if body["kind"] != "hashedrekord" || !hash_matches {
+        return false;
+    }
+
+    spec["signature"]["content"]
+        .as_str()
+        .and_then(|sig| base64::decode(sig).ok())
+        .map_or(false, |sig| verify_ecdsa(key, digest_bytes, &sig))
+}
+
+/// Verifies an ECDSA signature over `digest`, either DER-encoded or, as TUF writes them, as the
+/// concatenation of `r` and `s`.
+fn verify_ecdsa(key: &EcKey<Public>, digest: &[u8], signature: &[u8]) -> bool {
+    let signature = if signature.len() == 64 {
+        BigNum::from_slice(&signature[..32])
+            .and_then(|r| Ok((r, BigNum::from_slice(&signature[32..])?)))
+            .and_then(|(r, s)| EcdsaSig::from_private_components(r, s))
+    } else {
+        EcdsaSig::from_der(signature)
+    };
+
+    signature
+        .and_then(|signature| signature.verify(digest, key))
+        .unwrap_or(false)
+}
+
+/// Writes `value` as canonical JSON, with sorted object keys and no whitespace, which is what TUF
+/// metadata is signed as.
+fn canonical_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+/// Sends a request to the path `path` under `base_url`, with `body` as its JSON body, and returns
+/// the body of the response.
+fn send<C>(
+    client: &C,
+    base_url: &Url,
+    method: Method,
+    path: &str,
+    body: Option<String>,
+) -> impl Future<Item = Chunk, Error = Error> + Send
+where
+    C: ClientImpl,
+{
+    // make sure the path is joined onto the base path instead of replacing its last segment
+    let mut base_url = base_url.clone();
+    if !base_url.path().ends_with('/') {
+        let base_path = format!("{}/", base_url.path());
+        base_url.set_path(&base_path);
+    }
+
+    let request = base_url
+        .join(path)
+        .with_context(|_| ErrorKind::UrlJoin(base_url.clone(), path.to_string()))
+        .map_err(Error::from)
+        .and_then(|url| -> Result<_, Error> {
+            let uri = url
+                .as_str()
+                .parse::<Uri>()
+                .with_context(|_| ErrorKind::InvalidUrl(url.to_string()))?;
+
+            let mut request = Request::builder();
+            request.method(method).uri(uri);
+            let body = match body {
+                Some(body) => {
+                    request.header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                    Body::from(body)
+                }
+                None => Body::empty(),
+            };
+            let request = request.body(body).context(ErrorKind::Http)?;
+            Ok(request)
+        });
+
+    match request {
+        Ok(request) => future::Either::A(
+            client
+                .call(request)
+                .then(|response| -> Result<_, Error> {
+                    let response = response.context(ErrorKind::Http)?;
+                    Ok(response)
+                })
+                .and_then(|response| {
+                    let (parts, body) = response.into_parts();
+                    body.concat2().then(move |body| {
+                        let body = body.context(ErrorKind::Http)?;
+                        if parts.status != StatusCode::OK {
+                            return Err(Error::http_with_error_response(parts.status, &*body));
+                        }
+                        Ok(body)
+                    })
+                }),
+        ),
+        Err(err) => future::Either::B(future::err(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Response;
+    use openssl::ec::EcGroup;
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+
+    const DIGEST: &str = "sha256:8f434346648f6b96df89dda901c5176b10a6d83961dd3c1ac88b59b2dc327aa4";
+
+    fn key() -> EcKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        EcKey::generate(&group).unwrap()
+    }
+
+    fn public_pem(key: &EcKey<Private>) -> String {
+        let key = PKey::from_ec_key(key.clone()).unwrap();
+        String::from_utf8(key.public_key_to_pem().unwrap()).unwrap()
+    }
+
+    fn config(source: SignatureSource, key: &EcKey<Private>) -> SignatureVerificationConfig {
+        SignatureVerificationConfig::new(source, public_pem(key))
+    }
+
+    /// Signs `digest` the way TUF does, with `r` and `s` concatenated.
+    fn tuf_signature(digest: &[u8], key: &EcKey<Private>) -> String {
+        let signature = EcdsaSig::sign(digest, key).unwrap();
+        let mut raw = vec![];
+        for component in &[signature.r(), signature.s()] {
+            let bytes = component.to_vec();
+            raw.extend(vec![0; 32 - bytes.len()]);
+            raw.extend(bytes);
+        }
+        base64::encode(&raw)
+    }
+
+    fn targets(tag: &str, digest: &str, key: &EcKey<Private>) -> Value {
+        let signed = json!({
+            "_type": "Targets",
+            "expires": "2999-01-01T00:00:00Z",
+            "targets": {
+                tag: {
+                    "hashes": { "sha256": base64::encode(&parse_digest("", digest).unwrap()) },
+                    "length": 1234,
+                },
+            },
+            "version": 3,
+        });
+        let mut canonical = String::new();
+        canonical_json(&signed, &mut canonical);
+        let signature = tuf_signature(&openssl::sha::sha256(canonical.as_bytes()), key);
+
+        json!({
+            "signed": signed,
+            "signatures": [{ "keyid": "abc", "method": "ecdsa", "sig": signature }],
+        })
+    }
+
+    fn tuf_server(
+        targets: Value,
+    ) -> impl Fn(Request<Body>) -> Result<Response<Body>, hyper::Error> + Send + Sync {
+        move |req| {
+            assert_eq!(
+                "/v2/registry.example.com/module1/_trust/tuf/targets.json",
+                req.uri().path()
+            );
+            Ok(Response::new(Body::from(targets.to_string())))
+        }
+    }
+
+    #[test]
+    fn parse_reference_splits_tag() {
+        assert_eq!(
+            ("registry:5000/repo/module1", Some("1.0")),
+            parse_reference("registry:5000/repo/module1:1.0")
+        );
+        assert_eq!(
+            ("registry:5000/module1", Some("latest")),
+            parse_reference("registry:5000/module1")
+        );
+        assert_eq!(
+            ("module1", None),
+            parse_reference(&format!("module1@{}", DIGEST))
+        );
+    }
+
+    #[test]
+    fn tuf_signature_of_tag_is_verified() {
+        let key = key();
+        let url = Url::parse("https://notary.example.com").unwrap();
+        let config = config(SignatureSource::Tuf(url), &key);
+        let verifier = ImageSignatureVerifier::new(tuf_server(targets("1.0", DIGEST, &key)));
+
+        verifier
+            .verify("registry.example.com/module1:1.0", DIGEST, &config)
+            .wait()
+            .unwrap();
+
+        let err = verifier
+            .verify("registry.example.com/module1:2.0", DIGEST, &config)
+            .wait()
+            .unwrap_err();
+        assert_eq!(
+            &ErrorKind::ImageSignatureInvalid("registry.example.com/module1:2.0".to_string()),
+            err.kind()
+        );
+    }
+
+    #[test]
+    fn tuf_signature_with_other_key_is_rejected() {
+        let url = Url::parse("https://notary.example.com").unwrap();
+        let config = config(SignatureSource::Tuf(url), &key());
+        let verifier = ImageSignatureVerifier::new(tuf_server(targets("1.0", DIGEST, &key())));
+
+        let err = verifier
+            .verify("registry.example.com/module1:1.0", DIGEST, &config)
+            .wait()
+            .unwrap_err();
+        assert_eq!(
+            &ErrorKind::ImageSignatureInvalid("registry.example.com/module1:1.0".to_string()),
+            err.kind()
+        );
+    }
+
+    #[test]
+    fn rekor_entry_for_digest_is_verified() {
+        let key = key();
+        let digest = parse_digest("", DIGEST).unwrap();
+        let signature = EcdsaSig::sign(&digest, &key).unwrap().to_der().unwrap();
+        let entry = json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": { "hash": { "algorithm": "sha256", "value": &DIGEST[7..] } },
+                "signature": {
+                    "content": base64::encode(&signature),
+                    "publicKey": { "content": base64::encode(&public_pem(&key)) },
+                },
+            },
+        });
+
+        let handler = move |req: Request<Body>| {
+            let path = req.uri().path().to_string();
+            let body = req.into_body().concat2().wait().unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+
+            let response = match path.as_str() {
+                "/api/v1/index/retrieve" => {
+                    assert_eq!(DIGEST, body["hash"]);
+                    json!(["uuid1"])
+                }
+                "/api/v1/log/entries/retrieve" => {
+                    assert_eq!(json!(["uuid1"]), body["entryUUIDs"]);
+                    json!([{ "uuid1": { "body": base64::encode(&entry.to_string()) } }])
+                }
+                path => panic!("unexpected request to {}", path),
+            };
+            Ok::<_, hyper::Error>(Response::new(Body::from(response.to_string())))
+        };
+
+        let url = Url::parse("https://rekor.example.com").unwrap();
+        let verifier = ImageSignatureVerifier::new(handler);
+
+        verifier
+            .verify(
+                "registry.example.com/module1:1.0",
+                DIGEST,
+                &config(SignatureSource::Rekor(url.clone()), &key),
+            )
+            .wait()
+            .unwrap();
+
+        let err = verifier
+            .verify(
+                "registry.example.com/module1:1.0",
+                DIGEST,
+                &config(SignatureSource::Rekor(url), &self::key()),
+            )
+            .wait()
+            .unwrap_err();
+        assert_eq!(
+            &ErrorKind::ImageSignatureInvalid("registry.example.com/module1:1.0".to_string()),
+            err.kind()
+        );
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use failure::ResultExt;
+use futures::future::{self, Either};
+use futures::Future;
+use hyper::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::{Body, Request, Response};
+
+use edgelet_core::crypto::Sign;
+use edgelet_core::module_token::validate_module_token;
+use edgelet_core::{AuthId, Authenticator, ModuleId};
+
+use crate::route::{Handler, Parameters};
+use crate::{Error, ErrorKind, IntoResponse};
+
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// Which modules may call an endpoint when management API calls are authorized with module
+/// tokens.
+#[derive(Clone, Copy, Debug)]
+pub enum TokenPolicy {
+    /// Any module with a valid token.
+    AnyModule,
+    /// The module named in the `name` path parameter, or the given module.
+    CallerOrModule(&'static str),
+    /// Only the given module.
+    Module(&'static str),
+}
+
+impl TokenPolicy {
+    pub fn authorize(self, name: Option<&str>, module_id: &ModuleId) -> bool {
+        let name = name.map(|n| n.trim_start_matches('$'));
+        match self {
+            TokenPolicy::AnyModule => true,
+            TokenPolicy::CallerOrModule(expected) => {
+                *module_id == expected || name.map_or(false, |name| *module_id == name)
+            }
+            TokenPolicy::Module(expected) => *module_id == expected,
+        }
+    }
+}
+
+/// Requires callers to present a module token in the `Authorization` header. Tokens are only
+/// checked if the authorizer was created with a key; otherwise every call is let through.
+///
+/// Modules that can't fetch a token, like the Edge Agent, can be exempted: calls from their
+/// processes are let through without one if the runtime authenticates the calling process as
+/// the module.
+#[derive(Clone)]
+pub struct TokenAuthorizer<K, M> {
+    key: Option<Arc<K>>,
+    exempt: Option<(&'static str, M)>,
+}
+
+impl<K, M> TokenAuthorizer<K, M> {
+    pub fn new(key: Option<K>) -> Self {
+        TokenAuthorizer {
+            key: key.map(Arc::new),
+            exempt: None,
+        }
+    }
+
+    pub fn with_exempt_module(mut self, module: &'static str, runtime: M) -> Self {
+        self.exempt = Some((module, runtime));
+        self
+    }
+
+    pub fn require<H>(&self, policy: TokenPolicy, handler: H) -> TokenAuthorization<H, K, M>
+    where
+        M: Clone,
+    {
+        TokenAuthorization {
+            policy,
+            key: self.key.clone(),
+            exempt: self.exempt.clone(),
+            inner: Arc::new(handler),
+        }
+    }
+}
+
+pub struct TokenAuthorization<H, K, M> {
+    policy: TokenPolicy,
+    key: Option<Arc<K>>,
+    exempt: Option<(&'static str, M)>,
+    inner: Arc<H>,
+}
+
+impl<H, K, M> TokenAuthorization<H, K, M>
+where
+    K: Sign,
+{
+    fn authorize(&self, key: &K, token: &str, params: &Parameters) -> Result<ModuleId, Error> {
+        let module_id =
+            validate_module_token(key, token, Utc::now()).context(ErrorKind::ModuleToken)?;
+
+        if self.policy.authorize(params.name("name"), &module_id) {
+            Ok(module_id)
+        } else {
+            Err(ErrorKind::ModuleNotAuthorized(module_id.to_string()).into())
+        }
+    }
+}
+
+impl<H, K, M> Handler<Parameters> for TokenAuthorization<H, K, M>
+where
+    H: Handler<Parameters> + Sync,
+    K: 'static + Sign + Send + Sync,
+    M: 'static + Authenticator<Request = Request<Body>> + Send + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+        let mut req = req;
+
+        let key = match self.key {
+            Some(ref key) => key,
+            None => return self.inner.handle(req, params),
+        };
+
+        if let Some(token) = bearer_token(&req) {
+            return match self.authorize(&**key, token, &params) {
+                Ok(module_id) => {
+                    // Calls are attributed to the module named in the token rather than to
+                    // whoever the process check identified.
+                    req.extensions_mut().insert(AuthId::Value(module_id));
+                    self.inner.handle(req, params)
+                }
+                Err(err) => Box::new(future::ok(unauthorized(err))),
+            };
+        }
+
+        let (module, runtime) = match self.exempt {
+            Some((module, ref runtime))
+                if self.policy.authorize(params.name("name"), &module.into()) =>
+            {
+                (module, runtime)
+            }
+            _ => return Box::new(future::ok(unauthorized(ErrorKind::ModuleToken.into()))),
+        };
+
+        // The runtime checks whether the calling process belongs to the module named in the
+        // request's module ID.
+        let previous = req.extensions_mut().insert(ModuleId::from(module));
+        let authenticate = runtime.authenticate(&req);
+        match previous {
+            Some(previous) => req.extensions_mut().insert(previous),
+            None => req.extensions_mut().remove::<ModuleId>(),
+        };
+
+        let inner = self.inner.clone();
+        let response = authenticate.then(move |auth_id| match auth_id {
+            Ok(AuthId::Value(ref module_id)) if *module_id == module => {
+                req.extensions_mut()
+                    .insert(AuthId::Value(module_id.clone()));
+                Either::A(inner.handle(req, params))
+            }
+            _ => Either::B(future::ok(unauthorized(ErrorKind::ModuleToken.into()))),
+        });
+        Box::new(response)
+    }
+}
+
+/// The token in the request's `Authorization: Bearer <token>` header, if any.
+fn bearer_token(req: &Request<Body>) -> Option<&str> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    if value.starts_with(BEARER_PREFIX) {
+        Some(&value[BEARER_PREFIX.len()..])
+    } else {
+        None
+    }
+}
+
+fn unauthorized(err: Error) -> Response<Body> {
+    let unauthenticated = *err.kind() == ErrorKind::ModuleToken;
+    let mut response = err.into_response();
+    if unauthenticated {
+        response.headers_mut().insert(
+            WWW_AUTHENTICATE,
+            "Bearer".parse().expect("valid header value"),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use futures::Stream;
+    use hyper::StatusCode;
+
+    use edgelet_core::crypto::MemoryKey;
+    use edgelet_core::module_token::create_module_token;
+
+    use super::*;
+
+    fn request(module: Option<&str>) -> Request<Body> {
+        let mut request = Request::default();
+        if let Some(module) = module {
+            let token = create_module_token(
+                &MemoryKey::new("key"),
+                module,
+                Utc::now() + Duration::hours(1),
+            )
+            .unwrap();
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        }
+        request
+    }
+
+    fn params(name: &str) -> Parameters {
+        Parameters::with_captures(vec![(Some("name".to_string()), name.to_string())])
+    }
+
+    fn handle(policy: TokenPolicy, req: Request<Body>, params: Parameters) -> Response<Body> {
+        TokenAuthorizer::new(Some(MemoryKey::new("key")))
+            .with_exempt_module("edgeAgent", TestAuthenticator(AuthId::None))
+            .require(policy, TestHandler)
+            .handle(req, params)
+            .wait()
+            .unwrap()
+    }
+
+    fn body(response: Response<Body>) -> String {
+        let body = response.into_body().concat2().wait().unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn disabled_authorizer_lets_calls_through() {
+        let response = TokenAuthorizer::<MemoryKey, TestAuthenticator>::new(None)
+            .require(TokenPolicy::Module("edgeAgent"), TestHandler)
+            .handle(request(None), Parameters::new())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("auth = none", body(response));
+    }
+
+    #[test]
+    fn missing_token_is_unauthorized() {
+        let response = handle(TokenPolicy::AnyModule, request(None), Parameters::new());
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert_eq!("Bearer", response.headers().get(WWW_AUTHENTICATE).unwrap());
+    }
+
+    #[test]
+    fn token_signed_with_other_key_is_unauthorized() {
+        let token = create_module_token(
+            &MemoryKey::new("other"),
+            "m1",
+            Utc::now() + Duration::hours(1),
+        )
+        .unwrap();
+        let mut req = Request::default();
+        req.headers_mut()
+            .insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+
+        let response = handle(TokenPolicy::AnyModule, req, Parameters::new());
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[test]
+    fn any_module_is_allowed() {
+        let response = handle(
+            TokenPolicy::AnyModule,
+            request(Some("m1")),
+            Parameters::new(),
+        );
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("auth = m1", body(response));
+    }
+
+    #[test]
+    fn module_may_only_act_on_itself() {
+        let policy = TokenPolicy::CallerOrModule("edgeAgent");
+
+        let response = handle(policy, request(Some("m1")), params("m1"));
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = handle(policy, request(Some("m1")), params("m2"));
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let response = handle(policy, request(Some("edgeAgent")), params("m2"));
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn only_named_module_is_allowed() {
+        let policy = TokenPolicy::Module("edgeAgent");
+
+        let response = handle(policy, request(Some("m1")), Parameters::new());
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let response = handle(policy, request(Some("edgeAgent")), Parameters::new());
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn exempt_module_is_allowed_without_token() {
+        let handle = |policy, auth_id| {
+            TokenAuthorizer::new(Some(MemoryKey::new("key")))
+                .with_exempt_module("edgeAgent", TestAuthenticator(auth_id))
+                .require(policy, TestHandler)
+                .handle(request(None), params("m1"))
+                .wait()
+                .unwrap()
+        };
+
+        let response = handle(
+            TokenPolicy::Module("edgeAgent"),
+            AuthId::Value("edgeAgent".into()),
+        );
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("auth = edgeAgent", body(response));
+
+        let response = handle(
+            TokenPolicy::CallerOrModule("edgeAgent"),
+            AuthId::Value("edgeAgent".into()),
+        );
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = handle(TokenPolicy::Module("edgeAgent"), AuthId::Value("m1".into()));
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        // connections that can't be attributed to a process aren't exempt
+        let response = handle(TokenPolicy::Module("edgeAgent"), AuthId::Any);
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[test]
+    fn exempt_module_is_not_allowed_by_other_policies() {
+        let response = TokenAuthorizer::new(Some(MemoryKey::new("key")))
+            .with_exempt_module(
+                "edgeAgent",
+                TestAuthenticator(AuthId::Value("edgeAgent".into())),
+            )
+            .require(TokenPolicy::Module("m1"), TestHandler)
+            .handle(request(None), Parameters::new())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[test]
+    fn other_authorization_schemes_are_unauthorized() {
+        let mut req = Request::default();
+        req.headers_mut()
+            .insert(AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+
+        let response = handle(TokenPolicy::AnyModule, req, Parameters::new());
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[derive(Clone)]
+    struct TestAuthenticator(AuthId);
+
+    impl Authenticator for TestAuthenticator {
+        type Error = edgelet_core::Error;
+        type Request = Request<Body>;
+        type AuthenticateFuture = future::FutureResult<AuthId, Self::Error>;
+
+        fn authenticate(&self, req: &Self::Request) -> Self::AuthenticateFuture {
+            // the authorizer names the module the process is expected to belong to
+            assert!(req.extensions().get::<ModuleId>().is_some());
+            future::ok(self.0.clone())
+        }
+    }
+
+    struct TestHandler;
+
+    impl Handler<Parameters> for TestHandler {
+        fn handle(
+            &self,
+            req: Request<Body>,
+            _params: Parameters,
+        ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+            let auth_id = req
+                .extensions()
+                .get::<AuthId>()
+                .cloned()
+                .unwrap_or_else(|| AuthId::None);
+            Box::new(future::ok(Response::new(
+                format!("auth = {}", auth_id).into(),
+            )))
+        }
+    }
+}
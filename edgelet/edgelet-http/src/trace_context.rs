@@ -0,0 +1,189 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! W3C Trace Context propagation, see <https://www.w3.org/TR/trace-context/>.
+
+use std::fmt;
+
+use hyper::header::HeaderValue;
+use hyper::HeaderMap;
+use openssl::rand::rand_bytes;
+
+pub const TRACEPARENT: &str = "traceparent";
+
+const VERSION: u8 = 0;
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// Identifies the span a request is handled in and the trace it belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    flags: u8,
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    pub fn new_root() -> Self {
+        let mut trace_id = [0; 16];
+        random_id(&mut trace_id);
+        let mut span_id = [0; 8];
+        random_id(&mut span_id);
+        TraceContext {
+            trace_id,
+            span_id,
+            flags: FLAG_SAMPLED,
+        }
+    }
+
+    /// Reads the context from the `traceparent` header. Returns `None` if the header is missing
+    /// or invalid, in which case the caller should start a new trace.
+    pub fn from_headers(headers: &HeaderMap<HeaderValue>) -> Option<Self> {
+        headers
+            .get(TRACEPARENT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceContext::parse)
+    }
+
+    /// Parses a `traceparent` header value, e.g.
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next().and_then(parse_hex::<[u8; 1]>)?[0];
+        let trace_id = parts.next().and_then(parse_hex::<[u8; 16]>)?;
+        let span_id = parts.next().and_then(parse_hex::<[u8; 8]>)?;
+        let flags = parts.next().and_then(parse_hex::<[u8; 1]>)?[0];
+
+        // Version 0 has exactly four parts, later versions may append more. Version 0xff is
+        // forbidden, as are all-zero IDs.
+        if version == 0xff
+            || (version == VERSION && parts.next().is_some())
+            || trace_id == [0; 16]
+            || span_id == [0; 8]
+        {
+            return None;
+        }
+
+        Some(TraceContext {
+            trace_id,
+            span_id,
+            flags,
+        })
+    }
+
+    /// Returns the context of a span started within this one. It belongs to the same trace.
+    pub fn child(&self) -> Self {
+        let mut span_id = [0; 8];
+        random_id(&mut span_id);
+        TraceContext {
+            trace_id: self.trace_id,
+            span_id,
+            flags: self.flags,
+        }
+    }
+
+    pub fn trace_id(&self) -> &[u8; 16] {
+        &self.trace_id
+    }
+
+    pub fn span_id(&self) -> &[u8; 8] {
+        &self.span_id
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+}
+
+/// Formats the context as a `traceparent` header value.
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}-{}-{}-{:02x}",
+            VERSION,
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            self.flags
+        )
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_id(id: &mut [u8]) {
+    rand_bytes(id).expect("Unable to generate a random trace ID");
+
+    // An all-zero ID is invalid
+    if id.iter().all(|b| *b == 0) {
+        id[id.len() - 1] = 1;
+    }
+}
+
+fn parse_hex<T>(value: &str) -> Option<T>
+where
+    T: Default + AsMut<[u8]>,
+{
+    let mut bytes = T::default();
+    let len = bytes.as_mut().len();
+    if value.len() != len * 2 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    for (i, byte) in bytes.as_mut().iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT_VALUE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse_round_trip() {
+        let context = TraceContext::parse(TRACEPARENT_VALUE).unwrap();
+
+        assert_eq!("4bf92f3577b34da6a3ce929d0e0e4736", hex(context.trace_id()));
+        assert_eq!("00f067aa0ba902b7", hex(context.span_id()));
+        assert!(context.sampled());
+        assert_eq!(TRACEPARENT_VALUE, context.to_string());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        for value in &[
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473z-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(None, TraceContext::parse(value), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn later_versions_may_have_more_parts() {
+        let value = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra";
+
+        assert!(TraceContext::parse(value).is_some());
+    }
+
+    #[test]
+    fn child_keeps_trace_id() {
+        let parent = TraceContext::parse(TRACEPARENT_VALUE).unwrap();
+
+        let child = parent.child();
+
+        assert_eq!(parent.trace_id(), child.trace_id());
+        assert_ne!(parent.span_id(), child.span_id());
+        assert_eq!(parent.sampled(), child.sampled());
+    }
+}
@@ -1,12 +1,16 @@
 // Copyright (c) Microsoft. All rights reserved.
 
-use failure::ResultExt;
-use futures::future;
+use std::io;
+use std::net::IpAddr;
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future};
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector;
 use hyper::{Body, Client as HyperClient, Error as HyperError, Request, Response, StatusCode, Uri};
 use hyper_proxy::{Intercept, Proxy, ProxyConnector};
-use hyper_tls::HttpsConnector;
-use native_tls::{Certificate as TlsCertificate, TlsConnector};
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use native_tls::{Certificate as TlsCertificate, TlsConnector, TlsStream};
 use openssl::x509::X509;
 use typed_headers::Credentials;
 use url::percent_encoding::percent_decode;
@@ -75,7 +79,7 @@ impl Config {
             let connector = builder.build().context(ErrorKind::Initialization)?;
             let mut http = HttpConnector::new(DNS_WORKER_THREADS);
             http.enforce_http(false);
-            let https_connector = HttpsConnector::from((http, connector));
+            let https_connector = SanValidatingConnector(HttpsConnector::from((http, connector)));
 
             match &self.proxy_uri {
                 None => Ok(Client::NoProxy(
@@ -147,10 +151,59 @@ fn uri_to_proxy(uri: Uri) -> Result<Proxy, Error> {
     Ok(proxy)
 }
 
+/// Checks the host name of the server certificate with `edgelet_core::validate_san` once the TLS
+/// handshake is done, so that connecting to a server whose certificate was issued for another
+/// host fails with an error that names the host.
+#[derive(Clone, Debug)]
+pub struct SanValidatingConnector(HttpsConnector<HttpConnector>);
+
+impl Connect for SanValidatingConnector {
+    type Transport = MaybeHttpsStream<<HttpConnector as Connect>::Transport>;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Self::Transport, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        let host = dst.host().to_string();
+
+        Box::new(self.0.connect(dst).and_then(move |(stream, connected)| {
+            if let MaybeHttpsStream::Https(tls_stream) = &stream {
+                validate_peer_san(tls_stream.get_ref(), &host)?;
+            }
+            Ok((stream, connected))
+        }))
+    }
+}
+
+fn validate_peer_san<S: io::Read + io::Write>(stream: &TlsStream<S>, host: &str) -> io::Result<()> {
+    // validate_san only checks DNS names, so IP addresses are left to the TLS library
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    let cert = stream
+        .peer_certificate()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} did not present a certificate", host),
+            )
+        })?;
+    let cert = cert
+        .to_der()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        .and_then(|der: Vec<u8>| {
+            X509::from_der(&der).map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        })?;
+
+    edgelet_core::validate_san(&cert, host)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.compat()))
+}
+
 #[derive(Clone, Debug)]
 pub enum Client {
-    NoProxy(HyperClient<HttpsConnector<HttpConnector>>),
-    Proxy(HyperClient<ProxyConnector<HttpsConnector<HttpConnector>>>),
+    NoProxy(HyperClient<SanValidatingConnector>),
+    Proxy(HyperClient<ProxyConnector<SanValidatingConnector>>),
     Null,
 }
 
@@ -203,7 +256,13 @@ impl ClientImpl for Client {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::Stream;
+    use hyper::server::conn::Http;
+    use hyper::service::service_fn_ok;
     use hyper::Uri;
+    use tokio::net::TcpListener;
+
+    use edgelet_test_utils::cert::{TestCa, TestCertUsage};
 
     // test that the client builder (Config) is wired up correctly to create the
     // right enum variants
@@ -259,6 +318,56 @@ mod tests {
         assert_eq!(&expected, proxy.headers().get("Authorization").unwrap());
     }
 
+    #[test]
+    fn client_accepts_server_certificate_valid_for_host() {
+        let ca = TestCa::new("root");
+        let server_cert = ca.issue("localhost", TestCertUsage::Server);
+        let identity = PemCertificate::new(
+            server_cert.get_full_certificate().to_vec(),
+            Some(server_cert.get_private_key().to_vec()),
+            None,
+            None,
+        )
+        .get_identity()
+        .unwrap();
+        let acceptor =
+            tokio_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+
+        let listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = listener
+            .incoming()
+            .take(1)
+            .map_err(|err| panic!("{}", err))
+            .for_each(move |stream| {
+                acceptor
+                    .accept(stream)
+                    .map_err(|err| panic!("{}", err))
+                    .and_then(|stream| {
+                        Http::new()
+                            .serve_connection(
+                                stream,
+                                service_fn_ok(|_| Response::new(Body::from("hello"))),
+                            )
+                            .map_err(|err| panic!("{}", err))
+                    })
+            });
+
+        let client = Client::configure()
+            .trust_bundle(PemCertificate::new(ca.cert_pem(), None, None, None))
+            .build()
+            .unwrap();
+        let request = Request::get(format!("https://localhost:{}/", port))
+            .body(Body::empty())
+            .unwrap();
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.spawn(server);
+        let response = runtime.block_on(client.call(request)).unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
     // TODO:
     // test that Client::Proxy and Client::NoProxy can actually be used to make
     // HTTPS requests with or without a proxy (respectively)
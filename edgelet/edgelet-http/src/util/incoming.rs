@@ -14,7 +14,7 @@ use tokio::net::{TcpListener, TcpStream};
 #[cfg(unix)]
 use tokio::prelude::*;
 #[cfg(unix)]
-use tokio_tls::{Accept, TlsAcceptor};
+use tokio_tls::Accept;
 #[cfg(unix)]
 use tokio_uds::UnixListener;
 #[cfg(windows)]
@@ -23,6 +23,8 @@ use tokio_uds_windows::UnixListener;
 #[cfg(unix)]
 use edgelet_utils::log_failure;
 
+#[cfg(unix)]
+use crate::certificate_manager::RenewingTlsAcceptor;
 use crate::util::{IncomingSocketAddr, StreamSelector};
 
 pub enum Incoming {
@@ -30,7 +32,7 @@ pub enum Incoming {
     #[cfg(unix)]
     Tls(
         TcpListener,
-        TlsAcceptor,
+        RenewingTlsAcceptor,
         Mutex<Vec<(Accept<TcpStream>, IncomingSocketAddr)>>,
     ),
     Unix(UnixListener),
@@ -2,7 +2,7 @@
 
 use std::fmt;
 use std::io::{self, Read, Write};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 #[cfg(unix)]
 use std::os::unix::net::SocketAddr as UnixSocketAddr;
 use std::path::Path;
@@ -156,6 +156,23 @@ impl fmt::Display for IncomingSocketAddr {
     }
 }
 
+/// The address of the peer a request came from. The server inserts it into the extensions of
+/// every request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PeerAddr {
+    Ip(IpAddr),
+    Unix,
+}
+
+impl From<&IncomingSocketAddr> for PeerAddr {
+    fn from(addr: &IncomingSocketAddr) -> Self {
+        match addr {
+            IncomingSocketAddr::Tcp(addr) => PeerAddr::Ip(addr.ip()),
+            IncomingSocketAddr::Unix(_) => PeerAddr::Unix,
+        }
+    }
+}
+
 pub fn socket_file_exists(path: &Path) -> bool {
     if cfg!(windows) {
         use std::fs;
@@ -6,11 +6,13 @@
 use std::io;
 
 use edgelet_http::UrlConnector;
-#[cfg(windows)]
-use edgelet_test_utils::run_pipe_server;
-use edgelet_test_utils::{run_tcp_server, run_uds_server};
+use edgelet_test_utils::{
+    run_pipe_server, run_tcp_server, run_uds_server, run_uds_server_with_shutdown,
+    SequentialHandler,
+};
 use futures::future;
 use futures::prelude::*;
+use futures::sync::oneshot;
 use hyper::{
     Body, Client, Error as HyperError, Method, Request, Response, StatusCode, Uri as HyperUri,
 };
@@ -61,6 +63,45 @@ fn tcp_get() {
     runtime.block_on(task).unwrap();
 }
 
+#[test]
+fn tcp_get_sequential_responses() {
+    let responses = SequentialHandler::new(vec![
+        (StatusCode::SERVICE_UNAVAILABLE, Body::empty()),
+        (StatusCode::OK, GET_RESPONSE.into()),
+    ]);
+    let (server, port) = run_tcp_server("127.0.0.1", responses.handler());
+    let server = server.map_err(|err| panic!(err));
+
+    let url = format!("http://localhost:{}", port);
+    let connector = UrlConnector::new(&Url::parse(&url).unwrap()).unwrap();
+
+    let client = Client::builder().build::<_, Body>(connector);
+    let second_client = client.clone();
+    let second_url = url.clone();
+    let task = client
+        .get(url.parse().unwrap())
+        .and_then(move |res| {
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, res.status());
+
+            // drain the body so that the connection can be reused for the second request
+            res.into_body()
+                .concat2()
+                .and_then(move |_| second_client.get(second_url.parse().unwrap()))
+        })
+        .and_then(|res| {
+            assert_eq!(StatusCode::OK, res.status());
+            res.into_body().concat2()
+        })
+        .map(|body| {
+            assert_eq!(GET_RESPONSE, &String::from_utf8_lossy(body.as_ref()));
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+    assert_eq!(0, responses.remaining());
+}
+
 #[test]
 #[cfg_attr(windows, ignore)] // TODO: remove when windows build servers are upgraded to RS5
 fn uds_get() {
@@ -93,17 +134,78 @@ fn uds_get() {
     runtime.block_on(task).unwrap();
 }
 
+#[test]
+#[cfg_attr(windows, ignore)] // TODO: remove when windows build servers are upgraded to RS5
+fn uds_get_with_shutdown() {
+    let dir = TempDir::new("uds").unwrap();
+    let file_path = dir.path().join("sock");
+    let file_path = file_path.to_str().unwrap();
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server = run_uds_server_with_shutdown(
+        &file_path,
+        |req| hello_handler(req).map_err(|err| io::Error::new(io::ErrorKind::Other, err)),
+        shutdown_rx.map_err(|_| ()),
+    );
+
+    let mut url = Url::from_file_path(file_path).unwrap();
+    url.set_scheme("unix").unwrap();
+    let connector = UrlConnector::new(&url).unwrap();
+
+    let client = Client::builder().build::<_, Body>(connector);
+    let task = client
+        .get(HyperlocalUri::new(&file_path, "/").into())
+        .and_then(|res| {
+            assert_eq!(StatusCode::OK, res.status());
+            res.into_body().concat2()
+        })
+        .map(|body| {
+            assert_eq!(GET_RESPONSE, &String::from_utf8_lossy(body.as_ref()));
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        .then(|result| {
+            shutdown_tx.send(()).unwrap();
+            result
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.block_on(server.join(task)).unwrap();
+    assert!(!dir.path().join("sock").exists());
+}
+
 #[cfg(windows)]
-fn make_path() -> String {
+fn make_path(_dir: &TempDir) -> String {
     format!(r"\\.\pipe\my-pipe-{}", rand::thread_rng().gen::<u64>())
 }
 
+// There are no named pipes on Unix, so `run_pipe_server` serves on a Unix domain socket
+#[cfg(unix)]
+fn make_path(dir: &TempDir) -> String {
+    dir.path().join("pipe").to_str().unwrap().to_string()
+}
+
 #[cfg(windows)]
 fn make_url(path: &str) -> String {
     format!("npipe:{}", path.replace("\\", "/"))
 }
 
+#[cfg(unix)]
+fn make_url(path: &str) -> String {
+    let mut url = Url::from_file_path(path).unwrap();
+    url.set_scheme("unix").unwrap();
+    url.into_string()
+}
+
 #[cfg(windows)]
+fn make_uri(url: &str) -> HyperUri {
+    PipeUri::new(url, "/").unwrap().into()
+}
+
+#[cfg(unix)]
+fn make_uri(url: &str) -> HyperUri {
+    HyperlocalUri::new(Url::parse(url).unwrap().path(), "/").into()
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn pipe_get_handler(_req: Request<Body>) -> impl Future<Item = Response<Body>, Error = io::Error> {
     let response = Response::builder()
@@ -117,10 +219,10 @@ fn pipe_get_handler(_req: Request<Body>) -> impl Future<Item = Response<Body>, E
     future::ok(response)
 }
 
-#[cfg(windows)]
 #[test]
 fn pipe_get() {
-    let path = make_path();
+    let dir = TempDir::new("pipe").unwrap();
+    let path = make_path(&dir);
     let url = make_url(&path);
 
     let server = run_pipe_server(path.into(), pipe_get_handler).map_err(|err| eprintln!("{}", err));
@@ -131,7 +233,7 @@ fn pipe_get() {
 
     // make a get request
     let task = client
-        .get(PipeUri::new(&url, "/").unwrap().into())
+        .get(make_uri(&url))
         .and_then(|res| {
             assert_eq!(StatusCode::OK, res.status());
             res.into_body().concat2()
@@ -230,7 +332,6 @@ fn uds_post() {
     runtime.block_on(task).unwrap();
 }
 
-#[cfg(windows)]
 fn pipe_post_handler(req: Request<Body>) -> impl Future<Item = Response<Body>, Error = io::Error> {
     req.into_body().concat2().then(|body| {
         let body = body.expect("couldn't read request body");
@@ -240,10 +341,10 @@ fn pipe_post_handler(req: Request<Body>) -> impl Future<Item = Response<Body>, E
     })
 }
 
-#[cfg(windows)]
 #[test]
 fn pipe_post() {
-    let path = make_path();
+    let dir = TempDir::new("pipe").unwrap();
+    let path = make_path(&dir);
     let url = make_url(&path);
 
     let server =
@@ -253,7 +354,7 @@ fn pipe_post() {
 
     let client = Client::builder().build::<_, Body>(connector);
 
-    let url: HyperUri = PipeUri::new(&url, "/").unwrap().into();
+    let url = make_uri(&url);
 
     // make a post request
     let mut req = Request::builder()
@@ -259,3 +259,35 @@ fn not_found_for_api_version() {
 
     assert_eq!(StatusCode::NOT_FOUND, response.status());
 }
+
+#[test]
+fn unversioned_route_is_served_without_api_version() {
+    let recognizer = RegexRoutesBuilder::default()
+        .get(None, "/route1/(?P<name>[^/]+)", route1)
+        .get(
+            Version::Version2018_06_28,
+            "/route2/(?P<name>[^/]+)",
+            route2,
+        )
+        .finish();
+    let router = Router::from(recognizer);
+    let mut service = router.new_service().wait().unwrap();
+
+    let request1 = Request::get("http://example.com/route1/thename")
+        .body(Body::default())
+        .unwrap();
+    let request2 = Request::get("http://example.com/route1/thename?api-version=2018-06-28")
+        .body(Body::default())
+        .unwrap();
+    let request3 = Request::get("http://example.com/route2/thename")
+        .body(Body::default())
+        .unwrap();
+
+    let response1 = service.call(request1).wait().unwrap();
+    let response2 = service.call(request2).wait().unwrap();
+    let response3 = service.call(request3).wait().unwrap();
+
+    assert_eq!(StatusCode::OK, response1.status());
+    assert_eq!(StatusCode::OK, response2.status());
+    assert_eq!(StatusCode::BAD_REQUEST, response3.status());
+}
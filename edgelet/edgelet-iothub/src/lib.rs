@@ -23,7 +23,9 @@ use percent_encoding::{define_encode_set, percent_encode, PATH_SEGMENT_ENCODE_SE
 use url::form_urlencoded::Serializer as UrlSerializer;
 
 use edgelet_core::crypto::{KeyIdentity, KeyStore, Sign, Signature, SignatureAlgorithm};
-use edgelet_core::{AuthType, Identity, IdentityManager, IdentityOperation, IdentitySpec};
+use edgelet_core::{
+    AuthType, GetModuleTwin, Identity, IdentityManager, IdentityOperation, IdentitySpec,
+};
 use edgelet_http::client::{ClientImpl, TokenSource};
 use iothubservice::{
     AuthMechanism, AuthType as HubAuthType, DeviceClient, ErrorKind as HubErrorKind, Module,
@@ -386,6 +388,42 @@ where
     }
 }
 
+impl<K, C, D> GetModuleTwin for HubIdentityManager<K, C, D>
+where
+    K: 'static + KeyStore + Send + Sync,
+    K::Key: AsRef<[u8]> + Clone + Send,
+    C: 'static + ClientImpl,
+    D: 'static + Sign + Clone + Send + Sync,
+{
+    type Error = Error;
+    type GetTwinFuture =
+        Box<dyn Future<Item = Option<serde_json::Value>, Error = Self::Error> + Send>;
+
+    fn get_twin(&self, module_id: &str) -> Self::GetTwinFuture {
+        let module_id = module_id.to_string();
+
+        Box::new(
+            self.state
+                .client
+                .get_module_twin(module_id.clone())
+                .then(|twin| match twin {
+                    Ok(twin) => Ok(Some(twin)),
+                    Err(err) => {
+                        if let HubErrorKind::GetModuleTwinWithReason(_, HubReason::ModuleNotFound) =
+                            err.kind()
+                        {
+                            Ok(None)
+                        } else {
+                            Err(Error::from(err.context(ErrorKind::IdentityOperation(
+                                IdentityOperation::GetTwin(module_id),
+                            ))))
+                        }
+                    }
+                }),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -839,6 +877,41 @@ mod tests {
         assert_eq!(None, hub_identity);
     }
 
+    #[test]
+    fn get_twin_module_not_found() {
+        let key_store = MemoryKeyStore::new();
+
+        let api_version = "2018-04-10".to_string();
+        let host_name = Url::parse("http://localhost").unwrap();
+
+        let handler = move |req: Request<Body>| {
+            assert_eq!(req.method(), &Method::GET);
+            assert_eq!(req.uri().path(), "/twins/d1/modules/m1");
+
+            let response = Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .expect("could not build hyper::Response");
+            Ok(response)
+        };
+        let token_source = SasTokenSource::new(
+            "hub".to_string(),
+            "device".to_string(),
+            MemoryKey::new("device"),
+        );
+        let client = Client::new(handler, Some(token_source), api_version, host_name).unwrap();
+        let device_client = DeviceClient::new(client, "d1".to_string()).unwrap();
+
+        let identity_manager = HubIdentityManager::new(key_store, device_client);
+        let task = identity_manager.get_twin("m1");
+
+        let twin = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(task)
+            .unwrap();
+        assert_eq!(None, twin);
+    }
+
     #[test]
     fn delete_succeeds() {
         let key_store = MemoryKeyStore::new();
@@ -14,9 +14,10 @@ use hyper::{Body, Chunk as HyperChunk, Request};
 use hyper_tls::HttpsConnector;
 
 use edgelet_core::{
-    AuthId, Authenticator, GetTrustBundle, LogOptions, MakeModuleRuntime, ModuleRegistry,
-    ModuleRuntime, ModuleRuntimeState, ModuleSpec, ProvisioningResult as CoreProvisioningResult,
-    RuntimeOperation, SystemInfo, SystemResources,
+    AuthId, Authenticator, GetTrustBundle, ImageSizeInfo, LogOptions, MakeModuleRuntime,
+    ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec,
+    ProvisioningResult as CoreProvisioningResult, PruneReport, RuntimeOperation, SystemInfo,
+    SystemResources, TopResult,
 };
 use edgelet_docker::DockerConfig;
 use kube_client::{get_config, Client as KubeClient, HttpClient, TokenSource, ValueToken};
@@ -162,7 +163,10 @@ where
     type SystemInfoFuture = Box<dyn Future<Item = SystemInfo, Error = Self::Error> + Send>;
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
+    type TopFuture = Box<dyn Future<Item = TopResult, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ImageSizeFuture = Box<dyn Future<Item = ImageSizeInfo, Error = Self::Error> + Send>;
+    type PruneImagesFuture = Box<dyn Future<Item = PruneReport, Error = Self::Error> + Send>;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         Box::new(create_module(self, module))
@@ -259,6 +263,11 @@ where
         )))
     }
 
+    fn top(&self, _id: &str) -> Self::TopFuture {
+        // TODO: add support for listing module processes on k8s
+        Box::new(future::ok(TopResult::default()))
+    }
+
     fn list(&self) -> Self::ListFuture {
         let result = self
             .client
@@ -303,6 +312,16 @@ where
     fn remove_all(&self) -> Self::RemoveAllFuture {
         Box::new(future::ok(()))
     }
+
+    fn image_size(&self, _image: &str) -> Self::ImageSizeFuture {
+        // TODO: add support for image sizes on k8s, where images are pulled by the nodes
+        Box::new(future::ok(ImageSizeInfo::default()))
+    }
+
+    fn prune_images(&self, _keep: &[String]) -> Self::PruneImagesFuture {
+        // the kubelet garbage collects the images of its node
+        Box::new(future::ok(PruneReport::default()))
+    }
 }
 
 impl<T, S> Authenticator for KubeModuleRuntime<T, S>
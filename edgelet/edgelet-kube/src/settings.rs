@@ -4,8 +4,9 @@ use std::path::Path;
 
 use config::{Config, Environment};
 use edgelet_core::{
-    Certificates, Connect, Listen, ModuleSpec, Provisioning, RuntimeSettings,
-    Settings as BaseSettings, WatchdogSettings,
+    Certificates, Connect, Listen, ManagementAuthSettings, MetricsSettings, ModuleSpec,
+    Pkcs11Settings, Provisioning, RateLimitSettings, RuntimeSettings, Settings as BaseSettings,
+    TracingSettings, WatchdogSettings,
 };
 use edgelet_docker::{DockerConfig, DEFAULTS};
 use edgelet_utils::YamlFileSource;
@@ -130,6 +131,30 @@ impl RuntimeSettings for Settings {
     fn watchdog(&self) -> &WatchdogSettings {
         self.base.watchdog()
     }
+
+    fn rate_limits(&self) -> &RateLimitSettings {
+        self.base.rate_limits()
+    }
+
+    fn management_auth(&self) -> &ManagementAuthSettings {
+        self.base.management_auth()
+    }
+
+    fn metrics(&self) -> &MetricsSettings {
+        self.base.metrics()
+    }
+
+    fn tracing(&self) -> &TracingSettings {
+        self.base.tracing()
+    }
+
+    fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+        self.base.pkcs11()
+    }
+
+    fn twin_cache_dir(&self) -> Option<&Path> {
+        self.base.twin_cache_dir()
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
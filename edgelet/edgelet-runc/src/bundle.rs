@@ -0,0 +1,707 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! The runtime config of a module's bundle, generated from its `createOptions` with Docker's
+//! semantics, and the files a bundle is made of.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use failure::ResultExt;
+use serde_json::{json, Value};
+
+use edgelet_core::{ModuleSpec, ResourceLimits, VolumeMount, VolumeSource};
+use edgelet_docker::DockerConfig;
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// The runtime config that `runc` reads.
+pub const CONFIG_FILE: &str = "config.json";
+/// The spec the module was created with, without its registry credentials.
+pub const MODULE_FILE: &str = "module.json";
+/// The stdout and stderr of the module.
+pub const LOG_FILE: &str = "container.log";
+pub const ROOTFS_DIR: &str = "rootfs";
+
+const OCI_VERSION: &str = "1.0.2";
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+pub const LABEL_KEY: &str = "net.azure-devices.edge.owner";
+pub const LABEL_VALUE: &str = "Microsoft.Azure.Devices.Edge.Agent";
+pub const CAPABILITIES_LABEL_KEY: &str = "net.azure-devices.edge.capabilities";
+const RUNTIME_LOG_LEVEL_KEY: &str = "RuntimeLogLevel";
+
+/// The capabilities that Docker grants containers that aren't privileged.
+const DEFAULT_CAPABILITIES: &[&str] = &[
+    "CAP_AUDIT_WRITE",
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_MKNOD",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_RAW",
+    "CAP_SETFCAP",
+    "CAP_SETGID",
+    "CAP_SETPCAP",
+    "CAP_SETUID",
+    "CAP_SYS_CHROOT",
+];
+
+const ALL_CAPABILITIES: &[&str] = &[
+    "CAP_AUDIT_CONTROL",
+    "CAP_AUDIT_READ",
+    "CAP_AUDIT_WRITE",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_KILL",
+    "CAP_LEASE",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_MAC_ADMIN",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MKNOD",
+    "CAP_NET_ADMIN",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_RAW",
+    "CAP_SETFCAP",
+    "CAP_SETGID",
+    "CAP_SETPCAP",
+    "CAP_SETUID",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_NICE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+];
+
+const MASKED_PATHS: &[&str] = &[
+    "/proc/acpi",
+    "/proc/asound",
+    "/proc/kcore",
+    "/proc/keys",
+    "/proc/latency_stats",
+    "/proc/sched_debug",
+    "/proc/scsi",
+    "/proc/timer_list",
+    "/proc/timer_stats",
+    "/sys/firmware",
+];
+
+const READONLY_PATHS: &[&str] = &[
+    "/proc/bus",
+    "/proc/fs",
+    "/proc/irq",
+    "/proc/sys",
+    "/proc/sysrq-trigger",
+];
+
+/// Generates the runtime config of `module`, whose image has the config `image_config` and has
+/// been unpacked into `rootfs`. Named volumes are directories under `volumes_dir`.
+///
+/// Containers share the network namespace of the host, so the network settings of the create
+/// options are ignored, and the host's `/etc/hosts` and `/etc/resolv.conf` are mounted into them.
+pub fn runtime_config(
+    module: &ModuleSpec<DockerConfig>,
+    image_config: &Value,
+    rootfs: &Path,
+    volumes_dir: &Path,
+) -> Result<Value> {
+    let name = module.name();
+    let create_options = serde_json::to_value(module.config().create_options())
+        .with_context(|_| ErrorKind::Bundle(name.to_string()))?;
+    let host_config = &create_options["HostConfig"];
+    let image_config = &image_config["config"];
+
+    let args = args(&create_options, image_config);
+    if args.is_empty() {
+        return Err(Error::from(ErrorKind::InvalidCreateOptions(format!(
+            "module {} has no command and its image has no entrypoint",
+            name
+        ))));
+    }
+
+    let user = string(&create_options["User"])
+        .or_else(|| string(&image_config["User"]))
+        .unwrap_or("");
+    let (uid, gid) = user_ids(rootfs, user).map_err(ErrorKind::InvalidCreateOptions)?;
+
+    let cwd = string(&create_options["WorkingDir"])
+        .or_else(|| string(&image_config["WorkingDir"]))
+        .unwrap_or("/");
+    let hostname = string(&create_options["Hostname"]).unwrap_or(name);
+
+    let privileged = host_config["Privileged"].as_bool().unwrap_or(false);
+    let capabilities = capabilities(
+        privileged,
+        &strings(&host_config["CapAdd"]).unwrap_or_default(),
+        &strings(&host_config["CapDrop"]).unwrap_or_default(),
+    );
+
+    let mut linux = json!({
+        "namespaces": [
+            { "type": "pid" },
+            { "type": "ipc" },
+            { "type": "uts" },
+            { "type": "mount" },
+        ],
+        "resources": resources(host_config, module.resource_limits()),
+    });
+    if !privileged {
+        linux["maskedPaths"] = json!(MASKED_PATHS);
+        linux["readonlyPaths"] = json!(READONLY_PATHS);
+    }
+
+    Ok(json!({
+        "ociVersion": OCI_VERSION,
+        "process": {
+            "terminal": false,
+            "user": { "uid": uid, "gid": gid },
+            "args": args,
+            "env": env(module, &create_options, image_config),
+            "cwd": cwd,
+            "capabilities": {
+                "bounding": capabilities,
+                "effective": capabilities,
+                "inheritable": capabilities,
+                "permitted": capabilities,
+            },
+            "noNewPrivileges": !privileged,
+        },
+        "root": {
+            "path": ROOTFS_DIR,
+            "readonly": host_config["ReadonlyRootfs"].as_bool().unwrap_or(false),
+        },
+        "hostname": hostname,
+        "mounts": mounts(host_config, module.volumes(), volumes_dir)?,
+        "annotations": annotations(module, &create_options)?,
+        "linux": linux,
+    }))
+}
+
+fn string(value: &Value) -> Option<&str> {
+    value.as_str().filter(|s| !s.is_empty())
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array().map(|values| {
+        values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(ToOwned::to_owned)
+            .collect()
+    })
+}
+
+fn args(create_options: &Value, image_config: &Value) -> Vec<String> {
+    // as with Docker, an entrypoint in the create options also replaces the command of the image
+    let (entrypoint, cmd) = match strings(&create_options["Entrypoint"]) {
+        Some(entrypoint) => (entrypoint, strings(&create_options["Cmd"])),
+        None => (
+            strings(&image_config["Entrypoint"]).unwrap_or_default(),
+            strings(&create_options["Cmd"]).or_else(|| strings(&image_config["Cmd"])),
+        ),
+    };
+    entrypoint
+        .into_iter()
+        .chain(cmd.unwrap_or_default())
+        .collect()
+}
+
+/// The environment of the image, overridden by the module's environment and log level, which
+/// are overridden in turn by the environment in the create options, as with Docker.
+fn env(
+    module: &ModuleSpec<DockerConfig>,
+    create_options: &Value,
+    image_config: &Value,
+) -> Vec<String> {
+    fn set(env: &mut Vec<(String, String)>, key: &str, value: &str) {
+        match env.iter_mut().find(|(k, _)| k == key) {
+            Some(var) => var.1 = value.to_string(),
+            None => env.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    fn set_all(env: &mut Vec<(String, String)>, vars: Option<Vec<String>>) {
+        for var in vars.unwrap_or_default() {
+            let mut tokens = var.splitn(2, '=');
+            if let Some(key) = tokens.next() {
+                set(env, key, tokens.next().unwrap_or(""));
+            }
+        }
+    }
+
+    let mut env = vec![];
+    set_all(&mut env, strings(&image_config["Env"]));
+
+    let mut module_env: Vec<_> = module.env().iter().collect();
+    module_env.sort();
+    for (key, value) in module_env {
+        set(&mut env, key, value);
+    }
+    if let Some(log_level) = module.log_level() {
+        set(&mut env, RUNTIME_LOG_LEVEL_KEY, log_level);
+    }
+
+    set_all(&mut env, strings(&create_options["Env"]));
+
+    if env.iter().all(|(key, _)| key != "PATH") {
+        set(&mut env, "PATH", DEFAULT_PATH);
+    }
+
+    env.into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+/// Resolves `user`, which is `user[:group]` with names or IDs, against the `/etc/passwd` and
+/// `/etc/group` of the image. Without a group, the user's primary group is used.
+fn user_ids(rootfs: &Path, user: &str) -> std::result::Result<(u32, u32), String> {
+    fn entries(rootfs: &Path, file: &str) -> Vec<Vec<String>> {
+        fs::read_to_string(rootfs.join("etc").join(file))
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.split(':').map(ToOwned::to_owned).collect::<Vec<_>>())
+            .filter(|fields| fields.len() > 3)
+            .collect()
+    }
+
+    let mut parts = user.splitn(2, ':');
+    let name = parts.next().unwrap_or("");
+    let group = parts.next();
+
+    let passwd = entries(rootfs, "passwd");
+    let entry = passwd
+        .iter()
+        .find(|fields| !name.is_empty() && (fields[0] == name || fields[2] == name));
+
+    let uid = match name.parse() {
+        Ok(uid) => uid,
+        Err(_) if name.is_empty() => 0,
+        Err(_) => entry
+            .and_then(|fields| fields[2].parse().ok())
+            .ok_or_else(|| format!("user {} is not in the image", name))?,
+    };
+
+    let gid = match group {
+        Some(group) => match group.parse() {
+            Ok(gid) => gid,
+            Err(_) => entries(rootfs, "group")
+                .iter()
+                .find(|fields| fields[0] == group)
+                .and_then(|fields| fields[2].parse().ok())
+                .ok_or_else(|| format!("group {} is not in the image", group))?,
+        },
+        None => entry.and_then(|fields| fields[3].parse().ok()).unwrap_or(0),
+    };
+
+    Ok((uid, gid))
+}
+
+fn capabilities(privileged: bool, add: &[String], drop: &[String]) -> Vec<String> {
+    fn normalize(capability: &str) -> String {
+        let capability = capability.to_uppercase();
+        if capability == "ALL" || capability.starts_with("CAP_") {
+            capability
+        } else {
+            format!("CAP_{}", capability)
+        }
+    }
+
+    let all = || ALL_CAPABILITIES.iter().map(ToString::to_string).collect();
+    let mut capabilities: Vec<String> = if privileged {
+        all()
+    } else {
+        DEFAULT_CAPABILITIES
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    };
+
+    for capability in add.iter().map(String::as_str).map(normalize) {
+        if capability == "ALL" {
+            capabilities = all();
+        } else if !capabilities.contains(&capability) {
+            capabilities.push(capability);
+        }
+    }
+    for capability in drop.iter().map(String::as_str).map(normalize) {
+        if capability == "ALL" {
+            capabilities.clear();
+        } else {
+            capabilities.retain(|c| *c != capability);
+        }
+    }
+
+    capabilities
+}
+
+fn resources(host_config: &Value, limits: &ResourceLimits) -> Value {
+    // resource limits on the spec take precedence over the ones in createOptions, and as with
+    // Docker, 0 means no limit
+    let cpu_shares = limits
+        .cpu_shares()
+        .or_else(|| host_config["CpuShares"].as_u64())
+        .filter(|&shares| shares > 0);
+    let memory = limits
+        .memory_bytes()
+        .or_else(|| host_config["Memory"].as_u64())
+        .filter(|&memory| memory > 0);
+    let memory_swap = limits
+        .memory_swap_bytes()
+        .or_else(|| host_config["MemorySwap"].as_i64())
+        .filter(|&swap| swap != 0);
+    let pids_limit = limits
+        .pids_limit()
+        .or_else(|| host_config["PidsLimit"].as_i64())
+        .filter(|&limit| limit > 0);
+
+    let mut resources = json!({});
+    if let Some(shares) = cpu_shares {
+        resources["cpu"] = json!({ "shares": shares });
+    }
+    if memory.is_some() || memory_swap.is_some() {
+        let mut memory_resources = json!({});
+        if let Some(memory) = memory {
+            memory_resources["limit"] = json!(memory);
+        }
+        if let Some(swap) = memory_swap {
+            memory_resources["swap"] = json!(swap);
+        }
+        resources["memory"] = memory_resources;
+    }
+    if let Some(limit) = pids_limit {
+        resources["pids"] = json!({ "limit": limit });
+    }
+    resources
+}
+
+fn mounts(host_config: &Value, volumes: &[VolumeMount], volumes_dir: &Path) -> Result<Vec<Value>> {
+    let mut mounts = vec![
+        json!({ "destination": "/proc", "type": "proc", "source": "proc" }),
+        json!({
+            "destination": "/dev",
+            "type": "tmpfs",
+            "source": "tmpfs",
+            "options": ["nosuid", "strictatime", "mode=755", "size=65536k"],
+        }),
+        json!({
+            "destination": "/dev/pts",
+            "type": "devpts",
+            "source": "devpts",
+            "options": ["nosuid", "noexec", "newinstance", "ptmxmode=0666", "mode=0620", "gid=5"],
+        }),
+        json!({
+            "destination": "/dev/shm",
+            "type": "tmpfs",
+            "source": "shm",
+            "options": ["nosuid", "noexec", "nodev", "mode=1777", "size=65536k"],
+        }),
+        json!({
+            "destination": "/dev/mqueue",
+            "type": "mqueue",
+            "source": "mqueue",
+            "options": ["nosuid", "noexec", "nodev"],
+        }),
+        json!({
+            "destination": "/sys",
+            "type": "sysfs",
+            "source": "sysfs",
+            "options": ["nosuid", "noexec", "nodev", "ro"],
+        }),
+        json!({
+            "destination": "/sys/fs/cgroup",
+            "type": "cgroup",
+            "source": "cgroup",
+            "options": ["nosuid", "noexec", "nodev", "relatime", "ro"],
+        }),
+        bind_mount(Path::new("/etc/hosts"), "/etc/hosts", true),
+        bind_mount(Path::new("/etc/resolv.conf"), "/etc/resolv.conf", true),
+    ];
+
+    for bind in strings(&host_config["Binds"]).unwrap_or_default() {
+        let mut parts = bind.splitn(3, ':');
+        match (parts.next(), parts.next()) {
+            (Some(source), Some(target)) => {
+                let read_only = parts
+                    .next()
+                    .map_or(false, |mode| mode.split(',').any(|option| option == "ro"));
+                mounts.push(bind_mount(Path::new(source), target, read_only));
+            }
+            _ => {
+                return Err(Error::from(ErrorKind::InvalidCreateOptions(format!(
+                    "bind {} has no target",
+                    bind
+                ))))
+            }
+        }
+    }
+
+    for mount in host_config["Mounts"]
+        .as_array()
+        .map_or(&[][..], |mounts| &mounts[..])
+    {
+        let target = string(&mount["Target"]).ok_or_else(|| {
+            ErrorKind::InvalidCreateOptions(format!("mount {} has no target", mount))
+        })?;
+        let source = string(&mount["Source"]).unwrap_or("");
+        let read_only = mount["ReadOnly"].as_bool().unwrap_or(false);
+        mounts.push(match string(&mount["Type"]).unwrap_or("volume") {
+            "bind" => bind_mount(Path::new(source), target, read_only),
+            "volume" => bind_mount(&volume_dir(volumes_dir, source)?, target, read_only),
+            "tmpfs" => tmpfs_mount(target, mount["TmpfsOptions"]["SizeBytes"].as_u64()),
+            type_ => {
+                return Err(Error::from(ErrorKind::InvalidCreateOptions(format!(
+                    "mounts of type {} are not supported",
+                    type_
+                ))))
+            }
+        });
+    }
+
+    for volume in volumes {
+        mounts.push(match volume.source() {
+            VolumeSource::NamedVolume(name) => bind_mount(
+                &volume_dir(volumes_dir, name)?,
+                volume.target(),
+                volume.read_only(),
+            ),
+            VolumeSource::HostPath(path) => bind_mount(path, volume.target(), volume.read_only()),
+            VolumeSource::Tmpfs { size_bytes } => tmpfs_mount(volume.target(), Some(*size_bytes)),
+        });
+    }
+
+    Ok(mounts)
+}
+
+/// The directory of a named volume, whose name must not take it out of `volumes_dir`.
+fn volume_dir(volumes_dir: &Path, name: &str) -> Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) => Ok(volumes_dir.join(name)),
+        _ => Err(Error::from(ErrorKind::InvalidCreateOptions(format!(
+            "invalid volume name {:?}",
+            name
+        )))),
+    }
+}
+
+fn bind_mount(source: &Path, target: &str, read_only: bool) -> Value {
+    json!({
+        "destination": target,
+        "type": "bind",
+        "source": source.to_string_lossy(),
+        "options": ["rbind", if read_only { "ro" } else { "rw" }],
+    })
+}
+
+fn tmpfs_mount(target: &str, size_bytes: Option<u64>) -> Value {
+    let mut options = vec!["nosuid".to_string(), "nodev".to_string()];
+    if let Some(size_bytes) = size_bytes.filter(|&size| size > 0) {
+        options.push(format!("size={}", size_bytes));
+    }
+    json!({
+        "destination": target,
+        "type": "tmpfs",
+        "source": "tmpfs",
+        "options": options,
+    })
+}
+
+/// The labels of the create options, and the ones Docker modules get, so that modules can be
+/// told apart when the bundles are read back.
+fn annotations(
+    module: &ModuleSpec<DockerConfig>,
+    create_options: &Value,
+) -> Result<HashMap<String, String>> {
+    let mut annotations: HashMap<String, String> =
+        serde_json::from_value(create_options["Labels"].clone()).unwrap_or_default();
+    annotations.insert(LABEL_KEY.to_string(), LABEL_VALUE.to_string());
+    if let Some(capabilities) = module.capabilities() {
+        let capabilities = serde_json::to_string(capabilities)
+            .with_context(|_| ErrorKind::Bundle(module.name().to_string()))?;
+        annotations.insert(CAPABILITIES_LABEL_KEY.to_string(), capabilities);
+    }
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use edgelet_core::ImagePullPolicy;
+    use tempfile::tempdir;
+
+    fn module(create_options: Value) -> ModuleSpec<DockerConfig> {
+        let config = DockerConfig::new(
+            "registry.example.com/module1:1.0".to_string(),
+            serde_json::from_value(create_options).unwrap(),
+            None,
+        )
+        .unwrap();
+        let env = vec![("KEY".to_string(), "module".to_string())]
+            .into_iter()
+            .collect();
+        ModuleSpec::new(
+            "module1".to_string(),
+            "docker".to_string(),
+            config,
+            env,
+            ImagePullPolicy::default(),
+        )
+        .unwrap()
+        .with_log_level(Some("debug".to_string()))
+    }
+
+    fn image_config() -> Value {
+        json!({
+            "config": {
+                "Entrypoint": ["/app"],
+                "Cmd": ["--default"],
+                "Env": ["PATH=/bin", "KEY=image", "IMAGE=1"],
+                "User": "app",
+                "WorkingDir": "/work",
+            }
+        })
+    }
+
+    fn rootfs() -> tempfile::TempDir {
+        let rootfs = tempdir().unwrap();
+        fs::create_dir(rootfs.path().join("etc")).unwrap();
+        fs::write(
+            rootfs.path().join("etc/passwd"),
+            "root:x:0:0:root:/root:/bin/sh\napp:x:1000:1001::/home/app:/bin/sh\n",
+        )
+        .unwrap();
+        fs::write(
+            rootfs.path().join("etc/group"),
+            "root:x:0:\nvideo:x:44:app\n",
+        )
+        .unwrap();
+        rootfs
+    }
+
+    #[test]
+    fn runtime_config_applies_image_and_create_options() {
+        let rootfs = rootfs();
+        let module = module(json!({
+            "Cmd": ["--verbose"],
+            "Env": ["KEY=create"],
+            "Labels": { "label1": "value1" },
+            "HostConfig": {
+                "Binds": ["/host/data:/data:ro"],
+                "CapAdd": ["NET_ADMIN"],
+                "CapDrop": ["CAP_MKNOD"],
+                "Memory": 1_048_576,
+                "Mounts": [{ "Type": "volume", "Source": "cache", "Target": "/cache" }],
+            },
+        }));
+
+        let config = runtime_config(
+            &module,
+            &image_config(),
+            rootfs.path(),
+            Path::new("/volumes"),
+        )
+        .unwrap();
+
+        assert_eq!(json!(["/app", "--verbose"]), config["process"]["args"]);
+        assert_eq!(
+            json!([
+                "PATH=/bin",
+                "KEY=create",
+                "IMAGE=1",
+                "RuntimeLogLevel=debug"
+            ]),
+            config["process"]["env"]
+        );
+        assert_eq!(
+            json!({ "uid": 1000, "gid": 1001 }),
+            config["process"]["user"]
+        );
+        assert_eq!("/work", config["process"]["cwd"]);
+        assert_eq!("module1", config["hostname"]);
+
+        let capabilities = config["process"]["capabilities"]["bounding"]
+            .as_array()
+            .unwrap();
+        assert!(capabilities.contains(&json!("CAP_NET_ADMIN")));
+        assert!(!capabilities.contains(&json!("CAP_MKNOD")));
+
+        let mounts = config["mounts"].as_array().unwrap();
+        assert!(mounts.contains(&json!({
+            "destination": "/data",
+            "type": "bind",
+            "source": "/host/data",
+            "options": ["rbind", "ro"],
+        })));
+        assert!(mounts.contains(&json!({
+            "destination": "/cache",
+            "type": "bind",
+            "source": "/volumes/cache",
+            "options": ["rbind", "rw"],
+        })));
+
+        assert_eq!(1_048_576, config["linux"]["resources"]["memory"]["limit"]);
+        assert_eq!("value1", config["annotations"]["label1"]);
+        assert_eq!(LABEL_VALUE, config["annotations"][LABEL_KEY]);
+        assert!(config["linux"]["maskedPaths"].is_array());
+    }
+
+    #[test]
+    fn entrypoint_in_create_options_replaces_image_command() {
+        let rootfs = rootfs();
+        let module = module(json!({ "Entrypoint": ["/bin/sh"], "User": "0:video" }));
+
+        let config = runtime_config(
+            &module,
+            &image_config(),
+            rootfs.path(),
+            Path::new("/volumes"),
+        )
+        .unwrap();
+
+        assert_eq!(json!(["/bin/sh"]), config["process"]["args"]);
+        assert_eq!(json!({ "uid": 0, "gid": 44 }), config["process"]["user"]);
+    }
+
+    #[test]
+    fn runtime_config_rejects_missing_user_and_volume_outside_volumes_dir() {
+        let rootfs = rootfs();
+
+        let module1 = module(json!({ "User": "nobody" }));
+        assert!(runtime_config(
+            &module1,
+            &image_config(),
+            rootfs.path(),
+            Path::new("/volumes")
+        )
+        .is_err());
+
+        let module2 = module(json!({
+            "HostConfig": { "Mounts": [{ "Type": "volume", "Source": "../etc", "Target": "/etc" }] },
+        }));
+        assert!(runtime_config(
+            &module2,
+            &image_config(),
+            rootfs.path(),
+            Path::new("/volumes")
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt;
+use std::fmt::Display;
+
+use failure::{Backtrace, Context, Fail};
+
+use edgelet_core::{
+    ModuleOperation, ModuleRuntimeErrorReason, RegistryOperation, RuntimeOperation,
+};
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub struct Error {
+    inner: Context<ErrorKind>,
+}
+
+#[derive(Debug, Fail)]
+pub enum ErrorKind {
+    #[fail(display = "Could not write the bundle of module {}", _0)]
+    Bundle(String),
+
+    #[fail(display = "Conflict with current operation")]
+    Conflict,
+
+    #[fail(display = "Image {} has not been pulled", _0)]
+    ImageNotPulled(String),
+
+    #[fail(display = "Could not read the pulled images in {}", _0)]
+    ImageRecords(String),
+
+    #[fail(display = "Could not verify the signature of image {}", _0)]
+    ImageSignature(String),
+
+    #[fail(display = "Could not initialize module runtime")]
+    Initialization,
+
+    #[fail(display = "Invalid create options: {}", _0)]
+    InvalidCreateOptions(String),
+
+    #[fail(display = "Invalid module name {:?}", _0)]
+    InvalidModuleName(String),
+
+    #[fail(display = "Invalid module type {:?}", _0)]
+    InvalidModuleType(String),
+
+    #[fail(display = "Invalid socket URI: {:?}", _0)]
+    InvalidSocketUri(String),
+
+    #[fail(display = "{}", _0)]
+    ModuleOperation(ModuleOperation),
+
+    #[fail(display = "{}", _0)]
+    NotFound(String),
+
+    #[fail(display = "Registry credentials are not supported without Docker")]
+    RegistryCredentials,
+
+    #[fail(display = "{}", _0)]
+    RegistryOperation(RegistryOperation),
+
+    #[fail(display = "runc failed: {}", _0)]
+    Runc(String),
+
+    #[fail(display = "{}", _0)]
+    RuntimeOperation(RuntimeOperation),
+}
+
+impl Fail for Error {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.inner.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        self.inner.get_context()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            inner: Context::new(kind),
+        }
+    }
+}
+
+impl From<Context<ErrorKind>> for Error {
+    fn from(inner: Context<ErrorKind>) -> Self {
+        Error { inner }
+    }
+}
+
+impl<'a> From<&'a Error> for ModuleRuntimeErrorReason {
+    fn from(err: &'a Error) -> Self {
+        // operations add their context to an `Error`, so the chain is searched rather than
+        // only its root cause
+        let not_found = Fail::iter_chain(err).any(|fail| {
+            let kind = fail
+                .downcast_ref::<Error>()
+                .map(Error::kind)
+                .or_else(|| fail.downcast_ref::<ErrorKind>());
+            match kind {
+                Some(ErrorKind::NotFound(_)) => true,
+                _ => false,
+            }
+        });
+
+        if not_found {
+            ModuleRuntimeErrorReason::NotFound
+        } else {
+            ModuleRuntimeErrorReason::Other
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A module runtime for devices that have `runc` but not the Docker daemon.
+//!
+//! Images are pulled straight from their registry, and every module is an OCI bundle under
+//! `{data_dir}/bundles/{module}` whose `config.json` is generated from the module's
+//! `createOptions`, so deployments written for Docker run unchanged. Containers share the network
+//! namespace of the host.
+
+#![cfg(target_os = "linux")]
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate,
+    clippy::too_many_lines,
+    clippy::use_self
+)]
+
+mod bundle;
+mod error;
+mod module;
+mod runc;
+mod runtime;
+mod settings;
+
+pub use error::{Error, ErrorKind};
+pub use module::RunCModule;
+pub use runtime::RunCModuleRuntime;
+pub use settings::{LoadSettingsError, RuncSettings, Settings};
@@ -0,0 +1,136 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::Fail;
+use futures_cpupool::{CpuFuture, CpuPool};
+
+use edgelet_core::{Module, ModuleOperation, ModuleRuntimeState, ModuleStatus, WorkloadCapability};
+use edgelet_docker::{DockerConfig, MODULE_TYPE};
+use edgelet_utils::ensure_not_empty_with_context;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::runc::{ContainerState, Runc};
+
+pub struct RunCModule {
+    runc: Runc,
+    pool: CpuPool,
+    name: String,
+    config: DockerConfig,
+    capabilities: Option<Vec<WorkloadCapability>>,
+}
+
+impl std::fmt::Debug for RunCModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunCModule")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl RunCModule {
+    /// `runc` is run on `pool` to get the state of the module.
+    pub fn new(runc: Runc, pool: CpuPool, name: String, config: DockerConfig) -> Result<Self> {
+        ensure_not_empty_with_context(&name, || ErrorKind::InvalidModuleName(name.clone()))?;
+
+        Ok(RunCModule {
+            runc,
+            pool,
+            name,
+            config,
+            capabilities: None,
+        })
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Option<Vec<WorkloadCapability>>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+}
+
+/// The runtime state of a module whose bundle exists, given the state of its container.
+///
+/// `runc` doesn't record exit codes or when processes started, so modules that aren't running
+/// have no exit code, and `started_at` is when the container was created.
+pub fn runtime_state(image_id: Option<&str>, state: Option<&ContainerState>) -> ModuleRuntimeState {
+    let (status, status_description) = match state {
+        Some(state) if state.is_running() => (ModuleStatus::Running, state.status.clone()),
+        Some(state) => (ModuleStatus::Stopped, state.status.clone()),
+        // the container is deleted when its module is stopped
+        None => (ModuleStatus::Stopped, "stopped".to_string()),
+    };
+    let running = state.filter(|state| state.is_running());
+
+    ModuleRuntimeState::default()
+        .with_status(status)
+        .with_status_description(Some(status_description))
+        .with_started_at(running.and_then(|state| state.created))
+        .with_image_id(image_id.map(ToOwned::to_owned))
+        .with_pid(running.map(|state| state.pid))
+}
+
+impl Module for RunCModule {
+    type Config = DockerConfig;
+    type Error = Error;
+    type RuntimeStateFuture = CpuFuture<ModuleRuntimeState, Self::Error>;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn type_(&self) -> &str {
+        MODULE_TYPE
+    }
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn runtime_state(&self) -> Self::RuntimeStateFuture {
+        let runc = self.runc.clone();
+        let name = self.name.clone();
+        let image_id = self.config.image_id().map(ToOwned::to_owned);
+        self.pool.spawn_fn(move || {
+            runc.state(&name)
+                .map(|state| runtime_state(image_id.as_ref().map(AsRef::as_ref), state.as_ref()))
+                .map_err(|err| {
+                    Error::from(
+                        err.context(ErrorKind::ModuleOperation(ModuleOperation::RuntimeState)),
+                    )
+                })
+        })
+    }
+
+    fn capabilities(&self) -> Option<&[WorkloadCapability]> {
+        self.capabilities.as_ref().map(AsRef::as_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(status: &str) -> ContainerState {
+        serde_json::from_value(serde_json::json!({
+            "status": status,
+            "pid": 1234,
+            "created": "2019-11-05T10:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn runtime_state_reports_pid_only_while_running() {
+        let running = runtime_state(Some("sha256:abc"), Some(&state("running")));
+        assert_eq!(&ModuleStatus::Running, running.status());
+        assert_eq!(Some(1234), running.pid());
+        assert_eq!(Some("sha256:abc"), running.image_id());
+        assert!(running.started_at().is_some());
+
+        let created = runtime_state(None, Some(&state("created")));
+        assert_eq!(&ModuleStatus::Stopped, created.status());
+        assert_eq!(None, created.pid());
+
+        let deleted = runtime_state(None, None);
+        assert_eq!(&ModuleStatus::Stopped, deleted.status());
+        assert_eq!(Some("stopped"), deleted.status_description());
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! The `runc` commands that the runtime uses. They are run as child processes, and each one is
+//! short-lived, since `runc` leaves a container running without it once it has been created.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use chrono::{DateTime, Utc};
+use failure::ResultExt;
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// The state of a container, as printed by `runc state`.
+#[derive(Clone, Debug, serde_derive::Deserialize)]
+pub struct ContainerState {
+    pub status: String,
+    #[serde(default)]
+    pub pid: i32,
+    pub created: Option<DateTime<Utc>>,
+}
+
+impl ContainerState {
+    pub fn is_running(&self) -> bool {
+        self.status == "running" || self.status == "paused"
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Runc {
+    binary: PathBuf,
+    root: PathBuf,
+}
+
+impl Runc {
+    pub fn new(binary: PathBuf, root: PathBuf) -> Self {
+        Runc { binary, root }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+        command.arg("--root").arg(&self.root);
+        command
+    }
+
+    /// Runs `runc` with `args` and returns its stdout.
+    fn run<I, S>(&self, args: I) -> Result<Vec<u8>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = self.command();
+        command.args(args).stdin(Stdio::null());
+        let output = command
+            .output()
+            .with_context(|_| ErrorKind::Runc(format!("could not run {:?}", command)))?;
+        check_status(&output)?;
+        Ok(output.stdout)
+    }
+
+    /// Creates container `id` from the bundle at `bundle`, without starting its process. The
+    /// process' stdout and stderr are written to `log`.
+    pub fn create(&self, id: &str, bundle: &Path, log: &File) -> Result<()> {
+        let stdout = log.try_clone().context(ErrorKind::Runc(
+            "could not open the log of the container".to_string(),
+        ))?;
+        let stderr = log.try_clone().context(ErrorKind::Runc(
+            "could not open the log of the container".to_string(),
+        ))?;
+
+        // runc's own errors go to the log too, since the container inherits its stderr
+        let mut command = self.command();
+        command
+            .arg("create")
+            .arg("--bundle")
+            .arg(bundle)
+            .arg(id)
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr);
+        let status = command
+            .status()
+            .with_context(|_| ErrorKind::Runc(format!("could not run {:?}", command)))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::Runc(format!(
+                "create exited with {}, see {}",
+                status,
+                bundle.join(crate::bundle::LOG_FILE).display()
+            ))))
+        }
+    }
+
+    pub fn start(&self, id: &str) -> Result<()> {
+        self.run(&["start", id]).map(|_| ())
+    }
+
+    /// Sends `signal` to the process of container `id`. With `all`, the signal is sent to every
+    /// process in the container instead.
+    pub fn kill(&self, id: &str, signal: &str, all: bool) -> Result<()> {
+        let mut args = vec!["kill"];
+        if all {
+            args.push("--all");
+        }
+        args.extend(&[id, signal]);
+        self.run(args).map(|_| ())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        self.run(&["delete", "--force", id]).map(|_| ())
+    }
+
+    /// The state of container `id`, or `None` if there is no such container.
+    pub fn state(&self, id: &str) -> Result<Option<ContainerState>> {
+        match self.run(&["state", id]) {
+            Ok(output) => {
+                let state = serde_json::from_slice(&output).context(ErrorKind::Runc(format!(
+                    "could not read the state of {}",
+                    id
+                )))?;
+                Ok(Some(state))
+            }
+            Err(ref err) if is_not_found(err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The host PIDs of the processes in container `id`.
+    pub fn ps(&self, id: &str) -> Result<Vec<i32>> {
+        let output = self.run(&["ps", "--format", "json", id])?;
+        let pids = serde_json::from_slice(&output).context(ErrorKind::Runc(format!(
+            "could not read the processes of {}",
+            id
+        )))?;
+        Ok(pids)
+    }
+}
+
+fn check_status(output: &Output) -> Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(Error::from(ErrorKind::Runc(error_message(stderr.trim()))))
+    }
+}
+
+/// `runc` prints its errors as log lines such as
+/// `time="..." level=error msg="container \"module1\" does not exist"`, so only the message is
+/// kept when there is one.
+fn error_message(stderr: &str) -> String {
+    stderr
+        .find("msg=\"")
+        .map(|i| &stderr[i + 5..])
+        .and_then(|msg| msg.rfind('"').map(|end| msg[..end].replace("\\\"", "\"")))
+        .unwrap_or_else(|| stderr.to_string())
+}
+
+fn is_not_found(err: &Error) -> bool {
+    match err.kind() {
+        ErrorKind::Runc(message) => message.contains("does not exist"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_reads_runc_output() {
+        let state: ContainerState = serde_json::from_str(
+            r#"{
+                "ociVersion": "1.0.2",
+                "id": "module1",
+                "pid": 1234,
+                "status": "running",
+                "bundle": "/var/lib/iotedge/runc/bundles/module1",
+                "rootfs": "/var/lib/iotedge/runc/bundles/module1/rootfs",
+                "created": "2019-11-05T10:00:00.123456789Z",
+                "owner": ""
+            }"#,
+        )
+        .unwrap();
+
+        assert!(state.is_running());
+        assert_eq!(1234, state.pid);
+        assert!(state.created.is_some());
+    }
+
+    #[test]
+    fn error_message_strips_log_fields() {
+        let message = error_message(
+            r#"time="2019-11-05T10:00:00Z" level=error msg="container \"module1\" does not exist""#,
+        );
+
+        assert_eq!(r#"container "module1" does not exist"#, message);
+        assert!(is_not_found(&Error::from(ErrorKind::Runc(message))));
+        assert_eq!("exit status 1", error_message("exit status 1"));
+    }
+}
@@ -0,0 +1,1166 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::convert::TryFrom;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use failure::{Fail, ResultExt};
+use futures::future::{self, Either, Loop};
+use futures::prelude::*;
+use futures::stream;
+use futures_cpupool::{Builder as CpuPoolBuilder, CpuFuture, CpuPool};
+use hyper::{Body, Chunk as HyperChunk, Request, Uri};
+use log::{debug, info, warn, Level};
+use serde::Serialize;
+use tokio::timer::Delay;
+
+use edgelet_core::{
+    AuthId, Authenticator, GetTrustBundle, ImageSizeInfo, LogOptions, LogTail, MakeModuleRuntime,
+    Module, ModuleId, ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec, PruneReport,
+    RegistryOperation, RuntimeOperation, SignatureVerificationConfig, SystemInfo as CoreSystemInfo,
+    SystemResources, TopResult,
+};
+use edgelet_docker::{DockerConfig, MODULE_TYPE};
+use edgelet_http::{ImageSignatureVerifier, MaybeProxyClient, OciImage, OciImagePuller, Pid};
+use edgelet_utils::{log_failure, parse_mem_available_kb};
+use provisioning::ProvisioningResult;
+
+use crate::bundle::{self, CONFIG_FILE, LOG_FILE, MODULE_FILE, ROOTFS_DIR};
+use crate::error::{Error, ErrorKind, Result};
+use crate::module::{runtime_state, RunCModule};
+use crate::runc::Runc;
+use crate::settings::Settings;
+
+const BUNDLES_DIR: &str = "bundles";
+const CACHE_DIR: &str = "cache";
+const IMAGES_DIR: &str = "images";
+const VOLUMES_DIR: &str = "volumes";
+
+/// How long modules get to exit after `SIGTERM` when no timeout is given, as with Docker.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `runc`, the unpacking of image layers and the other file system work of the runtime block, so
+/// they run on a pool of this many threads rather than on the reactor.
+const BLOCKING_THREADS: usize = 4;
+
+#[derive(Clone)]
+pub struct RunCModuleRuntime {
+    runc: Runc,
+    puller: OciImagePuller<MaybeProxyClient>,
+    data_dir: PathBuf,
+    started: Instant,
+    pool: CpuPool,
+}
+
+impl RunCModuleRuntime {
+    /// Runs `f` on the pool of blocking threads.
+    fn blocking<F, T>(&self, f: F) -> CpuFuture<T, Error>
+    where
+        F: FnOnce(&Self) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let runtime = self.clone();
+        self.pool.spawn_fn(move || f(&runtime))
+    }
+
+    fn bundle_dir(&self, name: &str) -> PathBuf {
+        self.data_dir.join(BUNDLES_DIR).join(name)
+    }
+
+    /// The record of a pulled image, named after the image reference.
+    fn image_file(&self, image: &str) -> PathBuf {
+        self.data_dir.join(IMAGES_DIR).join(format!(
+            "{}.json",
+            base64::encode_config(image, base64::URL_SAFE)
+        ))
+    }
+
+    fn existing_bundle_dir(&self, name: &str) -> Result<PathBuf> {
+        let bundle = self.bundle_dir(name);
+        if is_valid_name(name) && bundle.join(MODULE_FILE).exists() {
+            Ok(bundle)
+        } else {
+            Err(Error::from(ErrorKind::NotFound(format!(
+                "No such module: {}",
+                name
+            ))))
+        }
+    }
+
+    fn load_image(&self, image: &str) -> Result<OciImage> {
+        let file = File::open(self.image_file(image))
+            .with_context(|_| ErrorKind::ImageNotPulled(image.to_string()))?;
+        let image = serde_json::from_reader(file)
+            .with_context(|_| ErrorKind::ImageNotPulled(image.to_string()))?;
+        Ok(image)
+    }
+
+    fn load_module(&self, name: &str) -> Result<RunCModule> {
+        let bundle = self.existing_bundle_dir(name)?;
+        let file = File::open(bundle.join(MODULE_FILE))
+            .with_context(|_| ErrorKind::Bundle(name.to_string()))?;
+        let spec: ModuleSpec<DockerConfig> =
+            serde_json::from_reader(file).with_context(|_| ErrorKind::Bundle(name.to_string()))?;
+        let capabilities = spec.capabilities().map(ToOwned::to_owned);
+
+        Ok(RunCModule::new(
+            self.runc.clone(),
+            self.pool.clone(),
+            name.to_string(),
+            spec.config().clone(),
+        )?
+        .with_capabilities(capabilities))
+    }
+
+    fn create_bundle(&self, module: &ModuleSpec<DockerConfig>, image: &OciImage) -> Result<()> {
+        let name = module.name();
+        if !is_valid_name(name) {
+            return Err(Error::from(ErrorKind::InvalidModuleName(name.to_string())));
+        }
+
+        let bundle = self.bundle_dir(name);
+        if bundle.exists() {
+            return Err(Error::from(ErrorKind::Conflict));
+        }
+
+        let result = self
+            .write_bundle(module, image, &bundle)
+            .and_then(|()| self.create_container(name));
+        if result.is_err() {
+            if let Err(err) = fs::remove_dir_all(&bundle) {
+                warn!("Could not remove the bundle of module {}: {}", name, err);
+            }
+        }
+        result
+    }
+
+    fn write_bundle(
+        &self,
+        module: &ModuleSpec<DockerConfig>,
+        image: &OciImage,
+        bundle: &Path,
+    ) -> Result<()> {
+        let context = || ErrorKind::Bundle(module.name().to_string());
+
+        let rootfs = bundle.join(ROOTFS_DIR);
+        self.puller
+            .unpack(image, &rootfs)
+            .with_context(|_| context())?;
+
+        let volumes_dir = self.data_dir.join(VOLUMES_DIR);
+        let runtime_config = bundle::runtime_config(module, image.config(), &rootfs, &volumes_dir)?;
+
+        // like Docker, create the host directories of binds and volumes that don't exist yet
+        let mounts = runtime_config["mounts"].as_array().into_iter().flatten();
+        for mount in mounts.filter(|mount| mount["type"] == "bind") {
+            if let Some(source) = mount["source"].as_str().map(Path::new) {
+                if !source.exists() {
+                    fs::create_dir_all(source).with_context(|_| context())?;
+                }
+            }
+        }
+        write_json(&bundle.join(CONFIG_FILE), &runtime_config).with_context(|_| context())?;
+
+        // the spec is kept to list the module later, without its registry credentials
+        let config = DockerConfig::new(
+            module.config().image().to_string(),
+            module
+                .config()
+                .clone_create_options()
+                .with_context(|_| context())?,
+            None,
+        )
+        .with_context(|_| context())?
+        .with_image_id(image.digest().to_string());
+        let spec = module.clone().with_config(config);
+        write_json(&bundle.join(MODULE_FILE), &spec).with_context(|_| context())?;
+
+        Ok(())
+    }
+
+    /// Creates the container of module `name` from its bundle. Its output is appended to the log
+    /// of the module, so that it's kept when the module restarts.
+    fn create_container(&self, name: &str) -> Result<()> {
+        let bundle = self.bundle_dir(name);
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(bundle.join(LOG_FILE))
+            .with_context(|_| ErrorKind::Bundle(name.to_string()))?;
+        self.runc.create(name, &bundle, &log)
+    }
+
+    fn start_container(&self, name: &str) -> Result<()> {
+        self.existing_bundle_dir(name)?;
+
+        match self.runc.state(name)? {
+            Some(ref state) if state.is_running() => return Ok(()),
+            Some(ref state) if state.status == "created" => (),
+            // the process of a container can't be started again once it has exited
+            Some(_) => {
+                self.runc.delete(name)?;
+                self.create_container(name)?;
+            }
+            None => self.create_container(name)?,
+        }
+
+        self.runc.start(name)
+    }
+
+    /// Sends `SIGTERM` to module `name`, then `SIGKILL` to all its processes if it hasn't
+    /// exited after `timeout`, and deletes its container.
+    fn stop_container(
+        &self,
+        name: &str,
+        timeout: Duration,
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        let runtime = self.clone();
+        let name = name.to_string();
+
+        let signalled = self.blocking({
+            let name = name.clone();
+            move |runtime| {
+                runtime.existing_bundle_dir(&name)?;
+                let running = runtime
+                    .runc
+                    .state(&name)?
+                    .map_or(false, |state| state.is_running());
+                if running {
+                    runtime.runc.kill(&name, "SIGTERM", false)?;
+                }
+                Ok(running)
+            }
+        });
+
+        signalled
+            .and_then({
+                let runtime = runtime.clone();
+                let name = name.clone();
+                move |signalled| {
+                    if signalled {
+                        Either::A(runtime.wait_for_exit(name, timeout))
+                    } else {
+                        Either::B(future::ok(true))
+                    }
+                }
+            })
+            .and_then({
+                let runtime = runtime.clone();
+                let name = name.clone();
+                move |exited| {
+                    if exited {
+                        Either::B(future::ok(true))
+                    } else {
+                        debug!("Module {} didn't exit in time, killing it", name);
+                        let killed = runtime.blocking({
+                            let name = name.clone();
+                            move |runtime| runtime.runc.kill(&name, "SIGKILL", true)
+                        });
+                        Either::A(
+                            killed.and_then(move |()| runtime.wait_for_exit(name, STOP_TIMEOUT)),
+                        )
+                    }
+                }
+            })
+            .and_then(move |exited| {
+                runtime.blocking(move |runtime| {
+                    if !exited {
+                        return Err(Error::from(ErrorKind::Runc(format!(
+                            "module {} is still running after SIGKILL",
+                            name
+                        ))));
+                    }
+                    match runtime.runc.state(&name)? {
+                        Some(_) => runtime.runc.delete(&name),
+                        None => Ok(()),
+                    }
+                })
+            })
+    }
+
+    /// Resolves to whether the process of container `name` exited before `timeout`.
+    fn wait_for_exit(
+        &self,
+        name: String,
+        timeout: Duration,
+    ) -> impl Future<Item = bool, Error = Error> + Send {
+        let runtime = self.clone();
+        let deadline = Instant::now() + timeout;
+        future::loop_fn(name, move |name| {
+            let running = runtime.blocking({
+                let name = name.clone();
+                move |runtime| {
+                    Ok(runtime
+                        .runc
+                        .state(&name)?
+                        .map_or(false, |state| state.is_running()))
+                }
+            });
+
+            running.and_then(move |running| {
+                if !running {
+                    Either::A(future::ok(Loop::Break(true)))
+                } else if Instant::now() >= deadline {
+                    Either::A(future::ok(Loop::Break(false)))
+                } else {
+                    Either::B(
+                        Delay::new(Instant::now() + STATE_POLL_INTERVAL)
+                            .map(|()| Loop::Continue(name))
+                            .map_err(|err| {
+                                Error::from(err.context(ErrorKind::Runc(
+                                    "could not wait for the module to exit".to_string(),
+                                )))
+                            }),
+                    )
+                }
+            })
+        })
+    }
+
+    fn remove_module(&self, name: &str) -> Result<()> {
+        let bundle = self.existing_bundle_dir(name)?;
+        if self.runc.state(name)?.is_some() {
+            // this kills the processes of the container if they're still running
+            self.runc.delete(name)?;
+        }
+        fs::remove_dir_all(&bundle).with_context(|_| ErrorKind::Bundle(name.to_string()))?;
+        Ok(())
+    }
+
+    fn list_modules(&self) -> Result<Vec<RunCModule>> {
+        let bundles_dir = self.data_dir.join(BUNDLES_DIR);
+        let entries = match fs::read_dir(&bundles_dir) {
+            Ok(entries) => entries,
+            Err(_) if !bundles_dir.exists() => return Ok(vec![]),
+            Err(err) => {
+                return Err(Error::from(
+                    err.context(ErrorKind::Bundle(bundles_dir.display().to_string())),
+                ))
+            }
+        };
+
+        let mut modules = vec![];
+        for entry in entries {
+            let entry =
+                entry.with_context(|_| ErrorKind::Bundle(bundles_dir.display().to_string()))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            match self.load_module(&name) {
+                Ok(module) => modules.push(module),
+                // bundles that are still being written are skipped
+                Err(err) => debug!("Skipping bundle {}: {}", name, err),
+            }
+        }
+        modules.sort_by(|m1, m2| m1.name().cmp(m2.name()));
+        Ok(modules)
+    }
+
+    /// Removes the records of the pulled images that neither `keep` nor any module refers to,
+    /// and then the blobs in the cache that only those images used.
+    fn prune(&self, keep: &[String]) -> Result<PruneReport> {
+        let in_use: Vec<String> = self
+            .list_modules()?
+            .iter()
+            .map(|module| module.config().image().to_string())
+            .collect();
+
+        let images_dir = self.data_dir.join(IMAGES_DIR);
+        let records_error = || ErrorKind::ImageRecords(images_dir.display().to_string());
+        let entries = match fs::read_dir(&images_dir) {
+            Ok(entries) => entries,
+            Err(_) if !images_dir.exists() => return Ok(PruneReport::default()),
+            Err(err) => return Err(Error::from(err.context(records_error()))),
+        };
+
+        let mut kept = vec![];
+        let mut images_deleted = vec![];
+        for entry in entries {
+            let path = entry.with_context(|_| records_error())?.path();
+            let image = path
+                .file_stem()
+                .and_then(|stem| {
+                    base64::decode_config(stem.to_string_lossy().as_bytes(), base64::URL_SAFE).ok()
+                })
+                .and_then(|image| String::from_utf8(image).ok());
+            let image = match image {
+                Some(image) => image,
+                None => continue,
+            };
+
+            let oci_image = self.load_image(&image)?;
+            let used = keep
+                .iter()
+                .chain(&in_use)
+                .any(|reference| *reference == image || reference == oci_image.digest());
+            if used {
+                kept.push(oci_image);
+            } else {
+                debug!("Removing unused image {}", image);
+                fs::remove_file(&path).with_context(|_| records_error())?;
+                images_deleted.push(image);
+            }
+        }
+
+        let space_reclaimed = self
+            .puller
+            .remove_unused_blobs(&kept)
+            .with_context(|_| records_error())?;
+        Ok(PruneReport::new(images_deleted, space_reclaimed))
+    }
+
+    fn module_pids(&self, name: &str) -> Result<Vec<i32>> {
+        self.existing_bundle_dir(name)?;
+        match self.runc.state(name)? {
+            Some(ref state) if state.is_running() => self.runc.ps(name),
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn read_logs(&self, name: &str, tail: &LogTail) -> Result<Vec<u8>> {
+        let log = self.existing_bundle_dir(name)?.join(LOG_FILE);
+        let contents = match fs::read(&log) {
+            Ok(contents) => contents,
+            Err(_) if !log.exists() => vec![],
+            Err(err) => {
+                return Err(Error::from(
+                    err.context(ErrorKind::Bundle(name.to_string())),
+                ))
+            }
+        };
+        Ok(frame_logs(&contents, tail))
+    }
+}
+
+impl std::fmt::Debug for RunCModuleRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunCModuleRuntime")
+            .field("data_dir", &self.data_dir)
+            .finish()
+    }
+}
+
+/// Module names become the names of their bundle directories, so they're limited to the names
+/// that Docker allows for containers.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().map_or(false, char::is_alphanumeric)
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> std::result::Result<(), failure::Error> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, value)?;
+    Ok(())
+}
+
+/// Frames the lines of a module's log as stdout in Docker's multiplexed log format, since that's
+/// the format clients of the management API read logs in.
+fn frame_logs(log: &[u8], tail: &LogTail) -> Vec<u8> {
+    let mut lines: Vec<&[u8]> = log.split(|&b| b == b'\n').collect();
+    if lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let skip = match tail {
+        LogTail::All => 0,
+        LogTail::Num(num) => {
+            lines.len() - usize::try_from(*num).map_or(lines.len(), |num| num.min(lines.len()))
+        }
+    };
+
+    let mut framed = vec![];
+    for line in &lines[skip..] {
+        let len = u32::try_from(line.len() + 1).unwrap_or(std::u32::MAX);
+        framed.extend_from_slice(&[1, 0, 0, 0]);
+        framed.extend_from_slice(&len.to_be_bytes());
+        framed.extend_from_slice(line);
+        framed.push(b'\n');
+    }
+    framed
+}
+
+fn https_proxy_uri() -> Result<Option<Uri>> {
+    let proxy_uri = env::var("HTTPS_PROXY")
+        .or_else(|_| env::var("https_proxy"))
+        .ok()
+        .map(|proxy_uri| proxy_uri.parse::<Uri>().context(ErrorKind::Initialization))
+        .transpose()?;
+    Ok(proxy_uri)
+}
+
+fn verify_image_signature(
+    image: &str,
+    digest: &str,
+    config: &SignatureVerificationConfig,
+) -> impl Future<Item = (), Error = Error> + Send {
+    let signature_error = {
+        let image = image.to_string();
+        move |err: edgelet_http::Error| Error::from(err.context(ErrorKind::ImageSignature(image)))
+    };
+
+    let verifier = https_proxy_uri().and_then(|proxy_uri| {
+        ImageSignatureVerifier::from_proxy(proxy_uri).map_err(signature_error.clone())
+    });
+    match verifier {
+        Ok(verifier) => {
+            debug!("Verifying the signature of image {} ({})", image, digest);
+            Either::A(
+                verifier
+                    .verify(image, digest, config)
+                    .map_err(signature_error),
+            )
+        }
+        Err(err) => Either::B(future::err(err)),
+    }
+}
+
+fn operation_result<T>(result: Result<T>, context: ErrorKind) -> Result<T> {
+    result.map_err(|err| {
+        let err = Error::from(err.context(context));
+        log_failure(Level::Warn, &err);
+        err
+    })
+}
+
+impl ModuleRegistry for RunCModuleRuntime {
+    type Error = Error;
+    type PullFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error>>;
+    type Config = DockerConfig;
+
+    fn pull(&self, config: &Self::Config) -> Self::PullFuture {
+        let image = config.image().to_string();
+        info!("Pulling image {}...", image);
+
+        if config.auth().is_some() {
+            let err = Error::from(Error::from(ErrorKind::RegistryCredentials).context(
+                ErrorKind::RegistryOperation(RegistryOperation::PullImage(image)),
+            ));
+            log_failure(Level::Warn, &err);
+            return Box::new(future::err(err));
+        }
+
+        let image_file = self.image_file(&image);
+        Box::new(
+            self.puller
+                .fetch(&image)
+                .map_err(failure::Error::from)
+                .and_then(move |oci_image| write_json(&image_file, &oci_image))
+                .then(move |result| match result {
+                    Ok(()) => {
+                        info!("Successfully pulled image {}", image);
+                        Ok(())
+                    }
+                    Err(err) => {
+                        let err = Error::from(err.context(ErrorKind::RegistryOperation(
+                            RegistryOperation::PullImage(image),
+                        )));
+                        log_failure(Level::Warn, &err);
+                        Err(err)
+                    }
+                }),
+        )
+    }
+
+    fn remove(&self, name: &str) -> Self::RemoveFuture {
+        info!("Removing image {}...", name);
+
+        let image_file = self.image_file(name);
+        let result = match fs::remove_file(&image_file) {
+            Ok(()) => {
+                info!("Successfully removed image {}", name);
+                Ok(())
+            }
+            Err(_) if !image_file.exists() => Err(Error::from(ErrorKind::NotFound(format!(
+                "No such image: {}",
+                name
+            )))),
+            Err(err) => {
+                let err = Error::from(err.context(ErrorKind::RegistryOperation(
+                    RegistryOperation::RemoveImage(name.to_string()),
+                )));
+                log_failure(Level::Warn, &err);
+                Err(err)
+            }
+        };
+
+        Box::new(future::result(result))
+    }
+}
+
+impl MakeModuleRuntime for RunCModuleRuntime {
+    type Config = DockerConfig;
+    type Settings = Settings;
+    type ProvisioningResult = ProvisioningResult;
+    type ModuleRuntime = Self;
+    type Error = Error;
+    type Future = Box<dyn Future<Item = Self, Error = Self::Error> + Send>;
+
+    fn make_runtime(
+        settings: Settings,
+        _: ProvisioningResult,
+        _: impl GetTrustBundle,
+    ) -> Self::Future {
+        info!("Initializing module runtime...");
+
+        let runc_settings = settings.runc();
+        let data_dir = runc_settings.data_dir().to_path_buf();
+        let created = https_proxy_uri()
+            .and_then(|proxy_uri| {
+                for dir in &[
+                    runc_settings.root().to_path_buf(),
+                    data_dir.join(BUNDLES_DIR),
+                    data_dir.join(IMAGES_DIR),
+                    data_dir.join(VOLUMES_DIR),
+                ] {
+                    fs::create_dir_all(dir).context(ErrorKind::Initialization)?;
+                }
+
+                let puller = OciImagePuller::from_proxy(proxy_uri, data_dir.join(CACHE_DIR))
+                    .context(ErrorKind::Initialization)?;
+                Ok(RunCModuleRuntime {
+                    runc: Runc::new(
+                        runc_settings.binary().to_path_buf(),
+                        runc_settings.root().to_path_buf(),
+                    ),
+                    puller,
+                    data_dir,
+                    started: Instant::now(),
+                    pool: CpuPoolBuilder::new()
+                        .pool_size(BLOCKING_THREADS)
+                        .name_prefix("runc-")
+                        .create(),
+                })
+            })
+            .map(|runtime| {
+                info!("Successfully initialized module runtime");
+                runtime
+            })
+            .map_err(|err| {
+                let err =
+                    Error::from(err.context(ErrorKind::RuntimeOperation(RuntimeOperation::Init)));
+                log_failure(Level::Warn, &err);
+                err
+            });
+
+        Box::new(future::result(created))
+    }
+}
+
+impl ModuleRuntime for RunCModuleRuntime {
+    type Error = Error;
+    type Config = DockerConfig;
+    type Module = RunCModule;
+    type ModuleRegistry = Self;
+    type Chunk = Chunk;
+    type Logs = Logs;
+
+    type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type GetFuture =
+        Box<dyn Future<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
+    type ListFuture = Box<dyn Future<Item = Vec<Self::Module>, Error = Self::Error> + Send>;
+    type ListWithDetailsStream =
+        Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
+    type LogsFuture = Box<dyn Future<Item = Self::Logs, Error = Self::Error> + Send>;
+    type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type RestartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type StartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type StopFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type SystemInfoFuture = Box<dyn Future<Item = CoreSystemInfo, Error = Self::Error> + Send>;
+    type SystemResourcesFuture =
+        Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
+    type TopFuture = Box<dyn Future<Item = TopResult, Error = Self::Error> + Send>;
+    type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ImageSizeFuture = Box<dyn Future<Item = ImageSizeInfo, Error = Self::Error> + Send>;
+    type PruneImagesFuture = Box<dyn Future<Item = PruneReport, Error = Self::Error> + Send>;
+
+    fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
+        info!("Creating module {}...", module.name());
+
+        // we only want "docker" modules, since their createOptions are what bundles are made from
+        if module.type_() != MODULE_TYPE {
+            return Box::new(future::err(Error::from(ErrorKind::InvalidModuleType(
+                module.type_().to_string(),
+            ))));
+        }
+
+        let context = {
+            let name = module.name().to_string();
+            move || ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(name))
+        };
+
+        let image = match self.load_image(module.config().image()) {
+            Ok(image) => image,
+            Err(err) => return Box::new(future::result(operation_result(Err(err), context()))),
+        };
+
+        let verification = match module.image_signature_verification() {
+            Some(config) => Either::A(verify_image_signature(
+                module.config().image(),
+                image.digest(),
+                config,
+            )),
+            None => Either::B(future::ok(())),
+        };
+
+        let runtime = self.clone();
+        let result = verification
+            .and_then(move |()| {
+                runtime.blocking(move |runtime| {
+                    runtime.create_bundle(&module, &image)?;
+                    Ok(module)
+                })
+            })
+            .then(move |result| {
+                let module = operation_result(result, context())?;
+                info!("Successfully created module {}", module.name());
+                Ok(())
+            });
+
+        Box::new(result)
+    }
+
+    fn get(&self, id: &str) -> Self::GetFuture {
+        debug!("Getting module {}...", id);
+
+        let id = id.to_string();
+        let result = self
+            .blocking({
+                let id = id.clone();
+                move |runtime| {
+                    let module = runtime.load_module(&id)?;
+                    let state = runtime.runc.state(&id)?;
+                    let state = runtime_state(module.config().image_id(), state.as_ref());
+                    Ok((module, state))
+                }
+            })
+            .then(move |result| {
+                let result = operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::GetModule(id.clone())),
+                );
+                if result.is_ok() {
+                    debug!("Successfully got module {}", id);
+                }
+                result
+            });
+
+        Box::new(result)
+    }
+
+    fn start(&self, id: &str) -> Self::StartFuture {
+        info!("Starting module {}...", id);
+
+        let id = id.to_string();
+        let result = self
+            .blocking({
+                let id = id.clone();
+                move |runtime| runtime.start_container(&id)
+            })
+            .then(move |result| {
+                operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::StartModule(id.clone())),
+                )?;
+                info!("Successfully started module {}", id);
+                Ok(())
+            });
+
+        Box::new(result)
+    }
+
+    fn stop(&self, id: &str, wait_before_kill: Option<Duration>) -> Self::StopFuture {
+        info!("Stopping module {}...", id);
+
+        let id = id.to_string();
+        Box::new(
+            self.stop_container(&id, wait_before_kill.unwrap_or(STOP_TIMEOUT))
+                .then(move |result| {
+                    operation_result(
+                        result,
+                        ErrorKind::RuntimeOperation(RuntimeOperation::StopModule(id.clone())),
+                    )?;
+                    info!("Successfully stopped module {}", id);
+                    Ok(())
+                }),
+        )
+    }
+
+    fn restart(&self, id: &str) -> Self::RestartFuture {
+        info!("Restarting module {}...", id);
+
+        let runtime = self.clone();
+        let id = id.to_string();
+        Box::new(
+            self.stop_container(&id, STOP_TIMEOUT)
+                .and_then({
+                    let id = id.clone();
+                    move |()| runtime.blocking(move |runtime| runtime.start_container(&id))
+                })
+                .then(move |result| {
+                    operation_result(
+                        result,
+                        ErrorKind::RuntimeOperation(RuntimeOperation::RestartModule(id.clone())),
+                    )?;
+                    info!("Successfully restarted module {}", id);
+                    Ok(())
+                }),
+        )
+    }
+
+    fn remove(&self, id: &str) -> Self::RemoveFuture {
+        info!("Removing module {}...", id);
+
+        let id = id.to_string();
+        let result = self
+            .blocking({
+                let id = id.clone();
+                move |runtime| runtime.remove_module(&id)
+            })
+            .then(move |result| {
+                operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::RemoveModule(id.clone())),
+                )?;
+                info!("Successfully removed module {}", id);
+                Ok(())
+            });
+
+        Box::new(result)
+    }
+
+    fn system_info(&self) -> Self::SystemInfoFuture {
+        info!("Querying system info...");
+
+        let system_info =
+            CoreSystemInfo::new(env::consts::OS.to_string(), env::consts::ARCH.to_string());
+        info!("Successfully queried system info");
+
+        Box::new(future::ok(system_info))
+    }
+
+    fn system_resources(&self) -> Self::SystemResourcesFuture {
+        info!("Querying system resources...");
+
+        let mut info: libc::sysinfo = unsafe { mem::zeroed() };
+        let ret = unsafe { libc::sysinfo(&mut info) };
+        if ret != 0 {
+            let err = Error::from(std::io::Error::last_os_error().context(
+                ErrorKind::RuntimeOperation(RuntimeOperation::SystemResources),
+            ));
+            log_failure(Level::Warn, &err);
+            return Box::new(future::err(err));
+        }
+
+        let mem_unit = u64::from(info.mem_unit);
+        let total_memory = u64::from(info.totalram) * mem_unit;
+        let free_memory = u64::from(info.freeram) * mem_unit;
+        // The free memory doesn't count the caches the kernel can reclaim
+        let available_memory = fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| parse_mem_available_kb(&meminfo))
+            .map_or(free_memory, |available| available * 1024);
+        let cpu_cores = usize::try_from(unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) })
+            .unwrap_or_default();
+
+        // there is no daemon to report the usage of the CPU or the stats of modules
+        let resources = SystemResources::new(
+            u64::try_from(info.uptime).unwrap_or_default(),
+            self.started.elapsed().as_secs(),
+            -1.0,
+            total_memory - free_memory,
+            total_memory,
+            vec![],
+            "[]".to_string(),
+        )
+        .with_cpu_cores(cpu_cores)
+        .with_cpu_usage_percent(-1.0)
+        .with_memory_kb(total_memory / 1024, available_memory / 1024);
+
+        Box::new(future::ok(resources))
+    }
+
+    fn top(&self, id: &str) -> Self::TopFuture {
+        debug!("Top for module {}...", id);
+
+        let id = id.to_string();
+        let result = self
+            .blocking({
+                let id = id.clone();
+                move |runtime| runtime.module_pids(&id)
+            })
+            .then(move |result| {
+                let pids = operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::TopModule(id.clone())),
+                )?;
+                debug!("Successfully listed processes of module {}", id);
+                Ok(TopResult::new(
+                    vec!["PID".to_string()],
+                    pids.iter().map(|pid| vec![pid.to_string()]).collect(),
+                ))
+            });
+
+        Box::new(result)
+    }
+
+    fn list(&self) -> Self::ListFuture {
+        debug!("Listing modules...");
+
+        let result = self
+            .blocking(RunCModuleRuntime::list_modules)
+            .then(|result| {
+                let modules = operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::ListModules),
+                )?;
+                debug!("Successfully listed modules");
+                Ok(modules)
+            });
+
+        Box::new(result)
+    }
+
+    fn list_with_details(&self) -> Self::ListWithDetailsStream {
+        Box::new(
+            self.list()
+                .map(stream::iter_ok)
+                .flatten_stream()
+                .and_then(|module| module.runtime_state().map(|state| (module, state))),
+        )
+    }
+
+    /// Logs can't be followed or filtered by time, since `runc` only gives modules a file to
+    /// write their output to.
+    fn logs(&self, id: &str, options: &LogOptions) -> Self::LogsFuture {
+        info!("Getting logs for module {}...", id);
+
+        let id = id.to_string();
+        let tail = *options.tail();
+        let result = self
+            .blocking({
+                let id = id.clone();
+                move |runtime| runtime.read_logs(&id, &tail)
+            })
+            .then(move |result| {
+                let logs = operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::GetModuleLogs(id.clone())),
+                )?;
+                info!("Successfully got logs for module {}", id);
+                Ok(Logs(id, Body::from(logs)))
+            });
+
+        Box::new(result)
+    }
+
+    fn registry(&self) -> &Self::ModuleRegistry {
+        self
+    }
+
+    fn remove_all(&self) -> Self::RemoveAllFuture {
+        let self_for_remove = self.clone();
+        Box::new(self.list().and_then(move |list| {
+            let n = list.into_iter().map(move |c| {
+                <RunCModuleRuntime as ModuleRuntime>::remove(&self_for_remove, c.name())
+            });
+            future::join_all(n).map(|_| ())
+        }))
+    }
+
+    /// The uncompressed size is read by decompressing the layers of the image, since registries
+    /// only give the compressed size of layers.
+    fn image_size(&self, image: &str) -> Self::ImageSizeFuture {
+        debug!("Getting the size of image {}...", image);
+
+        let image = image.to_string();
+        let result = self.blocking(move |runtime| {
+            let context =
+                || ErrorKind::RuntimeOperation(RuntimeOperation::GetImageSize(image.clone()));
+            let result = match runtime.load_image(&image) {
+                Ok(oci_image) => runtime
+                    .puller
+                    .image_size(&oci_image)
+                    .map(|(compressed, uncompressed)| {
+                        ImageSizeInfo::new(compressed, uncompressed, oci_image.layers().len())
+                    })
+                    .map_err(|err| Error::from(err.context(context()))),
+                Err(err) => Err(Error::from(err.context(context()))),
+            };
+            match &result {
+                Ok(_) => debug!("Successfully got the size of image {}", image),
+                Err(err) => log_failure(Level::Warn, err),
+            }
+            result
+        });
+
+        Box::new(result)
+    }
+
+    /// The space reclaimed is the size of the blobs removed from the cache, which can be shared
+    /// between images.
+    fn prune_images(&self, keep: &[String]) -> Self::PruneImagesFuture {
+        info!("Pruning unused images...");
+
+        let keep = keep.to_vec();
+        let result = self
+            .blocking(move |runtime| runtime.prune(&keep))
+            .then(|result| {
+                let report = operation_result(
+                    result,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::PruneImages),
+                )?;
+                info!(
+                    "Successfully pruned {} images, reclaiming {} bytes",
+                    report.images_deleted().len(),
+                    report.space_reclaimed()
+                );
+                Ok(report)
+            });
+
+        Box::new(result)
+    }
+}
+
+impl Authenticator for RunCModuleRuntime {
+    type Error = Error;
+    type Request = Request<Body>;
+    type AuthenticateFuture = Box<dyn Future<Item = AuthId, Error = Self::Error> + Send>;
+
+    fn authenticate(&self, req: &Self::Request) -> Self::AuthenticateFuture {
+        let pid = req
+            .extensions()
+            .get::<Pid>()
+            .cloned()
+            .unwrap_or_else(|| Pid::None);
+        let expected_module_id = req.extensions().get::<ModuleId>().cloned();
+
+        match (pid, expected_module_id) {
+            (Pid::None, _) | (Pid::Value(_), None) => Box::new(future::ok(AuthId::None)),
+            (Pid::Any, _) => Box::new(future::ok(AuthId::Any)),
+            (Pid::Value(pid), Some(module_id)) => Box::new(self.blocking(move |runtime| {
+                // a module that doesn't exist or isn't running has no processes to match
+                match runtime.module_pids(&module_id.to_string()) {
+                    Ok(ref pids) if pids.contains(&pid) => Ok(AuthId::Value(module_id)),
+                    _ => {
+                        info!("Unable to find a module for caller pid: {}", pid);
+                        Ok(AuthId::None)
+                    }
+                }
+            })),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Logs(String, Body);
+
+impl Stream for Logs {
+    type Item = Chunk;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        match self.1.poll() {
+            Ok(Async::Ready(chunk)) => Ok(Async::Ready(chunk.map(Chunk))),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
+                RuntimeOperation::GetModuleLogs(self.0.clone()),
+            )))),
+        }
+    }
+}
+
+impl From<Logs> for Body {
+    fn from(logs: Logs) -> Self {
+        logs.1
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk(HyperChunk);
+
+impl AsRef<[u8]> for Chunk {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use docker::models::{AuthConfig, ContainerCreateBody};
+
+    fn runtime(data_dir: &Path) -> RunCModuleRuntime {
+        RunCModuleRuntime {
+            runc: Runc::new(PathBuf::from("runc"), data_dir.join("runc")),
+            puller: OciImagePuller::from_proxy(None, data_dir.join(CACHE_DIR)).unwrap(),
+            data_dir: data_dir.to_path_buf(),
+            started: Instant::now(),
+            pool: CpuPool::new(1),
+        }
+    }
+
+    #[test]
+    fn pull_with_registry_credentials_fails() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let config = DockerConfig::new(
+            "registry.example.com/module1:1.0".to_string(),
+            ContainerCreateBody::new(),
+            Some(
+                AuthConfig::new()
+                    .with_username("user".to_string())
+                    .with_password("password".to_string()),
+            ),
+        )
+        .unwrap();
+
+        let err = runtime(data_dir.path()).pull(&config).wait().unwrap_err();
+        match err.cause().and_then(|cause| cause.downcast_ref::<Error>()) {
+            Some(cause) => match cause.kind() {
+                ErrorKind::RegistryCredentials => (),
+                kind => panic!("Expected `RegistryCredentials` but got {:?}", kind),
+            },
+            None => panic!("Expected a `RegistryCredentials` cause"),
+        }
+    }
+
+    #[test]
+    fn modules_are_listed_on_the_blocking_pool() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let runtime = runtime(data_dir.path());
+
+        assert!(runtime.list().wait().unwrap().is_empty());
+        match runtime.get("module1").wait().unwrap_err().kind() {
+            ErrorKind::RuntimeOperation(RuntimeOperation::GetModule(name)) if name == "module1" => {
+            }
+            kind => panic!("Expected `GetModule` but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn frame_logs_frames_lines_as_stdout() {
+        let log = b"line1\nline2\nline3\n";
+
+        assert_eq!(
+            b"\x01\x00\x00\x00\x00\x00\x00\x06line2\n\x01\x00\x00\x00\x00\x00\x00\x06line3\n"
+                .to_vec(),
+            frame_logs(log, &LogTail::Num(2))
+        );
+        assert_eq!(3 * (8 + 6), frame_logs(log, &LogTail::All).len());
+        assert_eq!(3 * (8 + 6), frame_logs(log, &LogTail::Num(10)).len());
+        assert!(frame_logs(b"", &LogTail::All).is_empty());
+    }
+
+    #[test]
+    fn module_names_must_be_valid_directory_names() {
+        assert!(is_valid_name("edgeHub"));
+        assert!(is_valid_name("module-1.v2_a"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name(".."));
+        assert!(!is_valid_name("a/b"));
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::path::{Path, PathBuf};
+
+use config::{Config, Environment};
+use docker::models::HostConfig;
+use edgelet_core::{
+    Certificates, Connect, Listen, ManagementAuthSettings, MetricsSettings, ModuleSpec,
+    Pkcs11Settings, Provisioning, RateLimitSettings, RuntimeSettings, Settings as BaseSettings,
+    TracingSettings, UrlExt, WatchdogSettings,
+};
+use edgelet_docker::{DockerConfig, DEFAULTS};
+use edgelet_utils::YamlFileSource;
+use failure::{Context, Fail, ResultExt};
+
+use crate::error::{Error, ErrorKind};
+
+const UNIX_SCHEME: &str = "unix";
+
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct RuncSettings {
+    #[serde(default = "RuncSettings::default_binary")]
+    binary: PathBuf,
+    #[serde(default = "RuncSettings::default_root")]
+    root: PathBuf,
+    #[serde(default = "RuncSettings::default_data_dir")]
+    data_dir: PathBuf,
+}
+
+impl RuncSettings {
+    fn default_binary() -> PathBuf {
+        "runc".into()
+    }
+
+    fn default_root() -> PathBuf {
+        "/run/iotedge/runc".into()
+    }
+
+    fn default_data_dir() -> PathBuf {
+        "/var/lib/iotedge/runc".into()
+    }
+
+    /// The `runc` executable, looked up in `PATH` if it's a bare name.
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+
+    /// The directory where `runc` keeps the state of containers, passed to it as `--root`.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory with the bundles of the modules, the pulled images and the named volumes.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+}
+
+impl Default for RuncSettings {
+    fn default() -> Self {
+        RuncSettings {
+            binary: RuncSettings::default_binary(),
+            root: RuncSettings::default_root(),
+            data_dir: RuncSettings::default_data_dir(),
+        }
+    }
+}
+
+/// This struct is the same as the Settings type from the `edgelet_core` crate
+/// except that it also sets up the volume mounting of workload & management
+/// UDS sockets for the edge agent container, and has the settings of `runc`.
+#[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct Settings {
+    #[serde(flatten)]
+    base: BaseSettings<DockerConfig>,
+    #[serde(default)]
+    runc: RuncSettings,
+}
+
+impl Settings {
+    pub fn new(filename: &Path) -> Result<Self, LoadSettingsError> {
+        let mut config = Config::default();
+        config.merge(YamlFileSource::String(DEFAULTS))?;
+        config.merge(YamlFileSource::File(filename.into()))?;
+        config.merge(Environment::with_prefix("iotedge"))?;
+
+        let mut settings: Self = config.try_into()?;
+
+        agent_vol_mount(&mut settings)?;
+
+        Ok(settings)
+    }
+
+    pub fn runc(&self) -> &RuncSettings {
+        &self.runc
+    }
+}
+
+impl RuntimeSettings for Settings {
+    type Config = DockerConfig;
+
+    fn provisioning(&self) -> &Provisioning {
+        self.base.provisioning()
+    }
+
+    fn agent(&self) -> &ModuleSpec<DockerConfig> {
+        self.base.agent()
+    }
+
+    fn agent_mut(&mut self) -> &mut ModuleSpec<DockerConfig> {
+        self.base.agent_mut()
+    }
+
+    fn hostname(&self) -> &str {
+        self.base.hostname()
+    }
+
+    fn connect(&self) -> &Connect {
+        self.base.connect()
+    }
+
+    fn listen(&self) -> &Listen {
+        self.base.listen()
+    }
+
+    fn homedir(&self) -> &Path {
+        self.base.homedir()
+    }
+
+    fn certificates(&self) -> &Certificates {
+        self.base.certificates()
+    }
+
+    fn watchdog(&self) -> &WatchdogSettings {
+        self.base.watchdog()
+    }
+
+    fn rate_limits(&self) -> &RateLimitSettings {
+        self.base.rate_limits()
+    }
+
+    fn management_auth(&self) -> &ManagementAuthSettings {
+        self.base.management_auth()
+    }
+
+    fn metrics(&self) -> &MetricsSettings {
+        self.base.metrics()
+    }
+
+    fn tracing(&self) -> &TracingSettings {
+        self.base.tracing()
+    }
+
+    fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+        self.base.pkcs11()
+    }
+
+    fn twin_cache_dir(&self) -> Option<&Path> {
+        self.base.twin_cache_dir()
+    }
+}
+
+fn agent_vol_mount(settings: &mut Settings) -> Result<(), LoadSettingsError> {
+    let create_options = settings.agent().config().clone_create_options()?;
+    let host_config = create_options
+        .host_config()
+        .cloned()
+        .unwrap_or_else(HostConfig::new);
+    let mut binds = host_config.binds().map_or_else(Vec::new, ToOwned::to_owned);
+
+    // if the url is a domain socket URL then bind mount it into the container
+    for uri in &[
+        settings.connect().management_uri(),
+        settings.connect().workload_uri(),
+    ] {
+        if uri.scheme() == UNIX_SCHEME {
+            let path = uri
+                .to_uds_file_path()
+                .context(ErrorKind::InvalidSocketUri(uri.to_string()))?;
+            let path = path
+                .to_str()
+                .ok_or_else(|| ErrorKind::InvalidSocketUri(uri.to_string()))?
+                .to_string();
+            let bind = format!("{}:{}", &path, &path);
+            if !binds.contains(&bind) {
+                binds.push(bind);
+            }
+        }
+    }
+
+    if !binds.is_empty() {
+        let host_config = host_config.with_binds(binds);
+        let create_options = create_options.with_host_config(host_config);
+
+        settings
+            .agent_mut()
+            .config_mut()
+            .set_create_options(create_options);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "Could not load settings")]
+pub struct LoadSettingsError(#[cause] Context<Box<dyn std::fmt::Display + Send + Sync>>);
+
+impl From<config::ConfigError> for LoadSettingsError {
+    fn from(err: config::ConfigError) -> Self {
+        LoadSettingsError(Context::new(Box::new(err)))
+    }
+}
+
+impl From<edgelet_docker::Error> for LoadSettingsError {
+    fn from(err: edgelet_docker::Error) -> Self {
+        LoadSettingsError(Context::new(Box::new(err)))
+    }
+}
+
+impl From<Error> for LoadSettingsError {
+    fn from(err: Error) -> Self {
+        LoadSettingsError(Context::new(Box::new(err)))
+    }
+}
+
+impl From<Context<ErrorKind>> for LoadSettingsError {
+    fn from(inner: Context<ErrorKind>) -> Self {
+        From::from(Error::from(inner))
+    }
+}
+
+impl From<ErrorKind> for LoadSettingsError {
+    fn from(kind: ErrorKind) -> Self {
+        From::from(Error::from(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static RUNC_SETTINGS: &str = "test/sample_settings.yaml";
+
+    #[test]
+    fn settings_read_runc_section_and_mount_sockets() {
+        let settings = Settings::new(Path::new(RUNC_SETTINGS)).unwrap();
+
+        assert_eq!(Path::new("/usr/local/sbin/runc"), settings.runc().binary());
+        assert_eq!(Path::new("/run/iotedge/runc"), settings.runc().root());
+        assert_eq!(Path::new("/data/runc"), settings.runc().data_dir());
+
+        let create_options = settings.agent().config().create_options();
+        assert_eq!(
+            Some(
+                &[
+                    "/var/run/iotedge/mgmt.sock:/var/run/iotedge/mgmt.sock".to_string(),
+                    "/var/run/iotedge/workload.sock:/var/run/iotedge/workload.sock".to_string(),
+                ][..]
+            ),
+            create_options.host_config().and_then(HostConfig::binds)
+        );
+    }
+}
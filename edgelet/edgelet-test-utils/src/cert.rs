@@ -1,6 +1,19 @@
 // Copyright (c) Microsoft. All rights reserved.
 
-use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509Builder, X509Name, X509NameBuilder, X509};
+
 use edgelet_core::{Certificate, Error as CoreError, ErrorKind as CoreErrorKind, PrivateKey};
 
 #[derive(Clone, Debug, Default)]
@@ -97,3 +110,221 @@ impl Certificate for TestCert {
         }
     }
 }
+
+/// A PEM-encoded certificate chain and the private key of its leaf certificate.
+///
+/// The chain starts with the leaf certificate and is followed by its issuers, in the
+/// same order as they would be presented during a TLS handshake.
+#[derive(Clone)]
+pub struct PemCertificate {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl PemCertificate {
+    pub fn new(cert: Vec<u8>, key: Vec<u8>) -> Self {
+        PemCertificate { cert, key }
+    }
+
+    /// The leaf certificate followed by the rest of the chain.
+    pub fn get_full_certificate(&self) -> &[u8] {
+        &self.cert
+    }
+
+    /// The leaf certificate on its own.
+    pub fn get_leaf_certificate(&self) -> Vec<u8> {
+        self.certs()[0].to_pem().unwrap()
+    }
+
+    /// The issuers of the leaf certificate, without the leaf itself.
+    pub fn get_chain(&self) -> Vec<u8> {
+        self.certs()
+            .iter()
+            .skip(1)
+            .flat_map(|cert| cert.to_pem().unwrap())
+            .collect()
+    }
+
+    pub fn get_private_key(&self) -> &[u8] {
+        &self.key
+    }
+
+    fn certs(&self) -> Vec<X509> {
+        let certs = X509::stack_from_pem(&self.cert).expect("could not parse certificate chain");
+        assert!(!certs.is_empty(), "certificate chain is empty");
+        certs
+    }
+}
+
+impl fmt::Debug for PemCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // do not print the private key
+        f.debug_struct("PemCertificate")
+            .field("cert", &String::from_utf8_lossy(&self.cert))
+            .finish()
+    }
+}
+
+static NEXT_SERIAL_NUMBER: AtomicU32 = AtomicU32::new(1);
+
+/// What a certificate issued by `TestCa` may be used for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestCertUsage {
+    Server,
+    Client,
+}
+
+/// A self-signed certificate authority that issues certificates on the fly, so tests do
+/// not need pre-baked PEM files on disk.
+pub struct TestCa {
+    cert: X509,
+    key: PKey<Private>,
+}
+
+impl TestCa {
+    pub fn new(common_name: &str) -> Self {
+        let key = generate_key();
+
+        let mut builder = cert_builder(common_name, &key, &Asn1Time::days_from_now(365).unwrap());
+        builder.set_issuer_name(&name(common_name)).unwrap();
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .key_cert_sign()
+                    .crl_sign()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+        TestCa {
+            cert: builder.build(),
+            key,
+        }
+    }
+
+    /// The PEM-encoded CA certificate, to be used as a trust bundle.
+    pub fn cert_pem(&self) -> Vec<u8> {
+        self.cert.to_pem().unwrap()
+    }
+
+    /// Issues a certificate signed by this CA that is valid for 30 days. Server certificates are
+    /// valid for `localhost` and `127.0.0.1` in addition to `common_name`.
+    pub fn issue(&self, common_name: &str, usage: TestCertUsage) -> PemCertificate {
+        self.issue_valid_for(common_name, usage, Duration::days(30))
+    }
+
+    /// Like `issue`, but the certificate expires `validity` from now.
+    pub fn issue_valid_for(
+        &self,
+        common_name: &str,
+        usage: TestCertUsage,
+        validity: Duration,
+    ) -> PemCertificate {
+        self.issue_inner(common_name, usage, validity, None)
+    }
+
+    /// Issues a server certificate whose subjectAltName extension only has `dns_names`. The
+    /// certificate has no subjectAltName extension at all if `dns_names` is empty.
+    pub fn issue_with_dns_names(&self, common_name: &str, dns_names: &[&str]) -> PemCertificate {
+        self.issue_inner(
+            common_name,
+            TestCertUsage::Server,
+            Duration::days(30),
+            Some(dns_names),
+        )
+    }
+
+    fn issue_inner(
+        &self,
+        common_name: &str,
+        usage: TestCertUsage,
+        validity: Duration,
+        dns_names: Option<&[&str]>,
+    ) -> PemCertificate {
+        let key = generate_key();
+
+        let not_after = Asn1Time::from_unix((Utc::now() + validity).timestamp()).unwrap();
+        let mut builder = cert_builder(common_name, &key, &not_after);
+        builder.set_issuer_name(self.cert.subject_name()).unwrap();
+        builder
+            .append_extension(BasicConstraints::new().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .digital_signature()
+                    .key_encipherment()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        match usage {
+            TestCertUsage::Server => {
+                builder
+                    .append_extension(ExtendedKeyUsage::new().server_auth().build().unwrap())
+                    .unwrap();
+                let mut san = SubjectAlternativeName::new();
+                match dns_names {
+                    None => {
+                        san.dns(common_name).dns("localhost").ip("127.0.0.1");
+                    }
+                    Some(dns_names) => {
+                        for dns_name in dns_names {
+                            san.dns(dns_name);
+                        }
+                    }
+                }
+                if dns_names.map_or(true, |dns_names| !dns_names.is_empty()) {
+                    let san = san
+                        .build(&builder.x509v3_context(Some(&self.cert), None))
+                        .unwrap();
+                    builder.append_extension(san).unwrap();
+                }
+            }
+            TestCertUsage::Client => {
+                builder
+                    .append_extension(ExtendedKeyUsage::new().client_auth().build().unwrap())
+                    .unwrap();
+            }
+        }
+        builder.sign(&self.key, MessageDigest::sha256()).unwrap();
+
+        let mut cert = builder.build().to_pem().unwrap();
+        cert.extend(self.cert_pem());
+        PemCertificate::new(cert, key.private_key_to_pem_pkcs8().unwrap())
+    }
+}
+
+fn generate_key() -> PKey<Private> {
+    PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+}
+
+fn name(common_name: &str) -> X509Name {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", common_name).unwrap();
+    name.build()
+}
+
+fn cert_builder(common_name: &str, key: &PKey<Private>, not_after: &Asn1Time) -> X509Builder {
+    let serial_number = BigNum::from_u32(NEXT_SERIAL_NUMBER.fetch_add(1, Ordering::SeqCst))
+        .and_then(|serial_number| serial_number.to_asn1_integer())
+        .unwrap();
+
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_serial_number(&serial_number).unwrap();
+    builder.set_subject_name(&name(common_name)).unwrap();
+    builder.set_pubkey(key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder.set_not_after(not_after).unwrap();
+    builder
+}
@@ -1,9 +1,20 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
 use failure::Fail;
 use futures::future::{self, FutureResult, IntoFuture};
+use futures::{Future, Stream};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+
+use edgelet_core::{AuthType, GetModuleTwin, Identity, IdentityManager, IdentitySpec};
+
+use crate::web::{empty_response, json_response, run_uds_server, HttpMethod, RequestPath};
 
-use edgelet_core::{AuthType, Identity, IdentityManager, IdentitySpec};
+const IDENTITIES_PATH: &str = "/identities";
 
 #[derive(Clone, Copy, Debug, Fail)]
 pub enum Error {
@@ -66,6 +77,7 @@ impl Identity for TestIdentity {
 #[derive(Clone)]
 pub struct TestIdentityManager {
     identities: Vec<TestIdentity>,
+    twins: BTreeMap<String, Value>,
     gen_id_sentinel: u32,
     fail_list: bool,
     fail_get: bool,
@@ -76,6 +88,7 @@ impl TestIdentityManager {
     pub fn new(identities: Vec<TestIdentity>) -> Self {
         TestIdentityManager {
             identities,
+            twins: BTreeMap::new(),
             gen_id_sentinel: 0,
             fail_list: false,
             fail_get: true,
@@ -97,6 +110,11 @@ impl TestIdentityManager {
         self.fail_create = fail_create;
         self
     }
+
+    pub fn with_twin(mut self, module_id: &str, twin: Value) -> Self {
+        self.twins.insert(module_id.to_string(), twin);
+        self
+    }
 }
 
 impl IdentityManager for TestIdentityManager {
@@ -189,3 +207,144 @@ impl IdentityManager for TestIdentityManager {
             .into_future()
     }
 }
+
+impl GetModuleTwin for TestIdentityManager {
+    type Error = Error;
+    type GetTwinFuture = FutureResult<Option<Value>, Self::Error>;
+
+    fn get_twin(&self, module_id: &str) -> Self::GetTwinFuture {
+        if self.fail_get {
+            future::err(Error::General)
+        } else {
+            future::ok(self.twins.get(module_id).cloned())
+        }
+    }
+}
+
+/// A fake identity service served over a Unix domain socket.
+///
+/// By default it keeps an in-memory list of identities and implements
+/// `GET /identities`, `POST /identities/{name}` and `DELETE /identities/{name}` on top
+/// of it. Any route can be overridden with a fixed status and JSON payload through
+/// `with_response`.
+#[derive(Clone, Default)]
+pub struct TestIdentityClient {
+    identities: Arc<Mutex<Vec<TestIdentity>>>,
+    gen_id_sentinel: Arc<Mutex<u32>>,
+    responses: BTreeMap<(HttpMethod, RequestPath), (StatusCode, Value)>,
+}
+
+impl TestIdentityClient {
+    pub fn new(identities: Vec<TestIdentity>) -> Self {
+        TestIdentityClient {
+            identities: Arc::new(Mutex::new(identities)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_response(
+        mut self,
+        method: Method,
+        path: &str,
+        status: StatusCode,
+        body: Value,
+    ) -> Self {
+        self.responses.insert(
+            (HttpMethod(method), RequestPath(path.to_string())),
+            (status, body),
+        );
+        self
+    }
+
+    /// The identities currently known to the service, including those created or
+    /// deleted by clients.
+    pub fn identities(&self) -> Vec<TestIdentity> {
+        self.identities.lock().unwrap().clone()
+    }
+
+    pub fn run(&self, path: &str) -> impl Future<Item = (), Error = io::Error> {
+        let client = self.clone();
+        run_uds_server(path, move |req| client.handle(req))
+    }
+
+    fn handle(
+        &self,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = io::Error> + Send> {
+        let key = (
+            HttpMethod(req.method().clone()),
+            RequestPath(req.uri().path().to_string()),
+        );
+        if let Some((status, body)) = self.responses.get(&key) {
+            return Box::new(future::ok(json_response(*status, body)));
+        }
+
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        // an empty name means the collection itself, anything with a '/' in it is unknown
+        let name = match path.get(IDENTITIES_PATH.len()..) {
+            Some(rest) if path.starts_with(IDENTITIES_PATH) && rest.is_empty() => "",
+            Some(rest) if path.starts_with(IDENTITIES_PATH) && rest.starts_with('/') => &rest[1..],
+            _ => "/",
+        };
+
+        match (&method, name) {
+            (&Method::GET, "") => {
+                let identities = self.identities();
+                Box::new(future::ok(json_response(
+                    StatusCode::OK,
+                    &json!({ "identities": identities }),
+                )))
+            }
+
+            (&Method::POST, name) if !name.is_empty() && !name.contains('/') => {
+                let client = self.clone();
+                let name = name.to_string();
+                Box::new(
+                    req.into_body()
+                        .concat2()
+                        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+                        .map(move |body| client.create(name, &body)),
+                )
+            }
+
+            (&Method::DELETE, name) if !name.is_empty() && !name.contains('/') => {
+                let mut identities = self.identities.lock().unwrap();
+                let response = match identities.iter().position(|id| id.module_id() == name) {
+                    Some(index) => {
+                        identities.remove(index);
+                        empty_response(StatusCode::NO_CONTENT)
+                    }
+                    None => empty_response(StatusCode::NOT_FOUND),
+                };
+                Box::new(future::ok(response))
+            }
+
+            _ => Box::new(future::ok(empty_response(StatusCode::NOT_FOUND))),
+        }
+    }
+
+    fn create(&self, name: String, body: &[u8]) -> Response<Body> {
+        let managed_by = serde_json::from_slice::<Value>(body)
+            .ok()
+            .and_then(|spec| spec["managedBy"].as_str().map(ToString::to_string))
+            .unwrap_or_default();
+
+        let mut identities = self.identities.lock().unwrap();
+        if identities.iter().any(|id| id.module_id() == name) {
+            return empty_response(StatusCode::CONFLICT);
+        }
+
+        let mut gen_id_sentinel = self.gen_id_sentinel.lock().unwrap();
+        *gen_id_sentinel += 1;
+        let identity = TestIdentity::new(
+            &name,
+            &managed_by,
+            &format!("{}", *gen_id_sentinel),
+            AuthType::Sas,
+        );
+        identities.push(identity.clone());
+
+        json_response(StatusCode::OK, &json!(identity))
+    }
+}
@@ -17,10 +17,20 @@ pub mod identity;
 mod json_connector;
 pub mod module;
 pub mod web;
+pub mod workload;
 
+pub use crate::cert::{PemCertificate, TestCa, TestCertUsage};
 pub use crate::json_connector::{JsonConnector, StaticStream};
+pub use crate::web::run_h2_tls_tcp_server;
+pub use crate::web::run_pipe_server;
 pub use crate::web::run_tcp_server;
+pub use crate::web::run_tcp_server_on_random_port;
+pub use crate::web::run_tcp_server_returning_error;
+pub use crate::web::run_tcp_server_with_shutdown;
+pub use crate::web::run_tls_tcp_server;
+pub use crate::web::run_tls_tcp_server_with_mutual_auth;
+pub use crate::web::run_tls_tcp_server_with_test_ca;
 pub use crate::web::run_uds_server;
-
-#[cfg(windows)]
-pub use crate::web::run_pipe_server;
+pub use crate::web::run_uds_server_with_handler_state;
+pub use crate::web::run_uds_server_with_shutdown;
+pub use crate::web::{RecordedRequest, RecordingHandler, SequentialHandler, SlowServer};
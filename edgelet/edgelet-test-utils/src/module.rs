@@ -113,6 +113,30 @@ impl RuntimeSettings for TestSettings {
     fn watchdog(&self) -> &WatchdogSettings {
         unimplemented!()
     }
+
+    fn rate_limits(&self) -> &RateLimitSettings {
+        unimplemented!()
+    }
+
+    fn management_auth(&self) -> &ManagementAuthSettings {
+        unimplemented!()
+    }
+
+    fn metrics(&self) -> &MetricsSettings {
+        unimplemented!()
+    }
+
+    fn tracing(&self) -> &TracingSettings {
+        unimplemented!()
+    }
+
+    fn pkcs11(&self) -> Option<&Pkcs11Settings> {
+        unimplemented!()
+    }
+
+    fn twin_cache_dir(&self) -> Option<&Path> {
+        unimplemented!()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -121,6 +145,7 @@ pub struct TestModule<E, C> {
     config: C,
     state: Result<ModuleRuntimeState, E>,
     logs: TestBody<E>,
+    capabilities: Option<Vec<WorkloadCapability>>,
 }
 
 impl<E: Fail> TestModule<E, TestConfig> {
@@ -130,6 +155,7 @@ impl<E: Fail> TestModule<E, TestConfig> {
             config,
             state,
             logs: TestBody::default(),
+            capabilities: None,
         }
     }
 }
@@ -141,6 +167,7 @@ impl<E: Fail, C> TestModule<E, C> {
             config,
             state,
             logs: TestBody::default(),
+            capabilities: None,
         }
     }
 }
@@ -157,10 +184,18 @@ impl<E: Fail> TestModule<E, TestConfig> {
             config,
             state,
             logs: TestBody::new(logs),
+            capabilities: None,
         }
     }
 }
 
+impl<E, C> TestModule<E, C> {
+    pub fn with_capabilities(mut self, capabilities: Option<Vec<WorkloadCapability>>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+}
+
 impl<E: Clone + Fail, C> Module for TestModule<E, C> {
     type Config = C;
     type Error = E;
@@ -181,6 +216,10 @@ impl<E: Clone + Fail, C> Module for TestModule<E, C> {
     fn runtime_state(&self) -> Self::RuntimeStateFuture {
         self.state.clone().into_future()
     }
+
+    fn capabilities(&self) -> Option<&[WorkloadCapability]> {
+        self.capabilities.as_ref().map(AsRef::as_ref)
+    }
 }
 
 #[derive(Clone)]
@@ -338,7 +377,10 @@ where
     type StopFuture = FutureResult<(), Self::Error>;
     type SystemInfoFuture = FutureResult<SystemInfo, Self::Error>;
     type SystemResourcesFuture = FutureResult<SystemResources, Self::Error>;
+    type TopFuture = FutureResult<TopResult, Self::Error>;
     type RemoveAllFuture = FutureResult<(), Self::Error>;
+    type ImageSizeFuture = FutureResult<ImageSizeInfo, Self::Error>;
+    type PruneImagesFuture = FutureResult<PruneReport, Self::Error>;
 
     fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         match self.module.as_ref().unwrap() {
@@ -394,20 +436,35 @@ where
 
     fn system_resources(&self) -> Self::SystemResourcesFuture {
         match self.module.as_ref().unwrap() {
-            Ok(_) => future::ok(SystemResources::new(
-                595_023,
-                200,
-                0.25,
-                5000,
-                8000,
-                vec![DiskInfo::new(
-                    "test disk".to_owned(),
-                    10000,
-                    20000,
-                    "test system".to_owned(),
-                    "test type".to_owned(),
-                )],
-                "fake docker stats".to_owned(),
+            Ok(_) => future::ok(
+                SystemResources::new(
+                    595_023,
+                    200,
+                    0.25,
+                    5000,
+                    8000,
+                    vec![DiskInfo::new(
+                        "test disk".to_owned(),
+                        10000,
+                        20000,
+                        "test system".to_owned(),
+                        "test type".to_owned(),
+                    )],
+                    "fake docker stats".to_owned(),
+                )
+                .with_cpu_cores(4)
+                .with_cpu_usage_percent(25.0)
+                .with_memory_kb(8, 3),
+            ),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn top(&self, _id: &str) -> Self::TopFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(TopResult::new(
+                vec!["PID".to_string(), "CMD".to_string()],
+                vec![vec!["1".to_string(), "/bin/sh".to_string()]],
             )),
             Err(ref e) => future::err(e.clone()),
         }
@@ -444,4 +501,18 @@ where
     fn remove_all(&self) -> Self::RemoveAllFuture {
         future::ok(())
     }
+
+    fn image_size(&self, _image: &str) -> Self::ImageSizeFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(ImageSizeInfo::new(1024, 4096, 2)),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn prune_images(&self, _keep: &[String]) -> Self::PruneImagesFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(PruneReport::default()),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
 }
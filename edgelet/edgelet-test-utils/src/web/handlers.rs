@@ -0,0 +1,161 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, FutureResult};
+use futures::{Future, Stream};
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode, Uri};
+use tokio::timer::Delay;
+
+/// A request captured by `RecordingHandler`.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn body_str(&self) -> &str {
+        std::str::from_utf8(&self.body).expect("request body is not valid UTF-8")
+    }
+}
+
+/// Wraps a handler and records every request it receives before forwarding it.
+///
+/// Pass `handler()` to any of the `run_*_server` functions and call
+/// `recorded_requests()` after the exchange to assert on what the client sent.
+#[derive(Clone)]
+pub struct RecordingHandler<F> {
+    inner: F,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl<F, R, E> RecordingHandler<F>
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send + Sync,
+    R: 'static + Future<Item = Response<Body>, Error = E> + Send,
+    E: 'static,
+{
+    pub fn new(inner: F) -> Self {
+        RecordingHandler {
+            inner,
+            requests: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn recorded_requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn handler(
+        &self,
+    ) -> impl Fn(Request<Body>) -> Box<dyn Future<Item = Response<Body>, Error = E> + Send>
+           + Clone
+           + Send
+           + Sync {
+        let inner = self.inner.clone();
+        let requests = self.requests.clone();
+
+        move |req: Request<Body>| {
+            let inner = inner.clone();
+            let requests = requests.clone();
+            let (parts, body) = req.into_parts();
+
+            Box::new(body.concat2().then(move |body| {
+                let body = body.expect("could not read request body").to_vec();
+                requests.lock().unwrap().push(RecordedRequest {
+                    method: parts.method.clone(),
+                    uri: parts.uri.clone(),
+                    headers: parts.headers.clone(),
+                    body: body.clone(),
+                });
+                inner(Request::from_parts(parts, body.into()))
+            }))
+        }
+    }
+}
+
+/// Wraps a handler and delays every request by a fixed amount before forwarding it, for
+/// testing client-side timeouts.
+///
+/// The delay is driven by the tokio timer, so it does not block the runtime's thread.
+#[derive(Clone)]
+pub struct SlowServer<F> {
+    delay: Duration,
+    inner: F,
+}
+
+impl<F, R, E> SlowServer<F>
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send + Sync,
+    R: 'static + Future<Item = Response<Body>, Error = E> + Send,
+    E: 'static,
+{
+    pub fn new(delay: Duration, inner: F) -> Self {
+        SlowServer { delay, inner }
+    }
+
+    pub fn handler(
+        &self,
+    ) -> impl Fn(Request<Body>) -> Box<dyn Future<Item = Response<Body>, Error = E> + Send>
+           + Clone
+           + Send
+           + Sync {
+        let delay = self.delay;
+        let inner = self.inner.clone();
+
+        move |req: Request<Body>| {
+            let inner = inner.clone();
+            Box::new(Delay::new(Instant::now() + delay).then(move |result| {
+                result.expect("delay timer failed");
+                inner(req)
+            }))
+        }
+    }
+}
+
+/// Serves a fixed list of responses in order, one per request, e.g. to have the first
+/// call fail and the second succeed when testing retry logic.
+///
+/// Once the list is exhausted every further request gets a `500 Internal Server Error`.
+#[derive(Clone)]
+pub struct SequentialHandler {
+    responses: Arc<Mutex<VecDeque<(StatusCode, Body)>>>,
+}
+
+impl SequentialHandler {
+    pub fn new(responses: Vec<(StatusCode, Body)>) -> Self {
+        SequentialHandler {
+            responses: Arc::new(Mutex::new(responses.into_iter().collect())),
+        }
+    }
+
+    /// The number of configured responses that have not been served yet.
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    pub fn handler<E>(
+        &self,
+    ) -> impl Fn(Request<Body>) -> FutureResult<Response<Body>, E> + Clone + Send + Sync {
+        let responses = self.responses.clone();
+
+        move |_: Request<Body>| {
+            let (status, body) = responses.lock().unwrap().pop_front().unwrap_or_else(|| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "no more responses configured".into(),
+                )
+            });
+            let response = Response::builder()
+                .status(status)
+                .body(body)
+                .expect("could not build hyper::Response");
+            future::ok(response)
+        }
+    }
+}
@@ -1,8 +1,19 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+mod handlers;
+mod tls;
+#[cfg(unix)]
+mod unix;
 #[cfg(windows)]
 mod windows;
 
+pub use self::handlers::{RecordedRequest, RecordingHandler, SequentialHandler, SlowServer};
+pub use self::tls::{
+    run_h2_tls_tcp_server, run_tls_tcp_server, run_tls_tcp_server_with_mutual_auth,
+    run_tls_tcp_server_with_test_ca,
+};
+#[cfg(unix)]
+pub use self::unix::run_pipe_server;
 #[cfg(windows)]
 pub use self::windows::run_pipe_server;
 
@@ -13,13 +24,14 @@ use std::fs;
 use std::io;
 #[cfg(unix)]
 use std::os::unix::net::UnixListener as StdUnixListener;
+use std::sync::{Arc, Mutex};
 
 use futures::prelude::*;
 use hyper::body::Payload;
 use hyper::server::conn::Http;
 use hyper::service::service_fn;
 use hyper::Error as HyperError;
-use hyper::{self, Body, Method, Request, Response};
+use hyper::{self, Body, Method, Request, Response, StatusCode};
 #[cfg(unix)]
 use hyperlocal::server::{Http as UdsHttp, Incoming as UdsIncoming};
 #[cfg(windows)]
@@ -52,6 +64,38 @@ where
     (server, port)
 }
 
+/// Binds to `0.0.0.0` on a port chosen by the OS and returns that port together with the
+/// server future, so tests never need to hardcode a port number.
+pub fn run_tcp_server_on_random_port<F, R>(
+    handler: F,
+) -> (u16, impl Future<Item = (), Error = hyper::Error>)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    let (server, port) = run_tcp_server("0.0.0.0", handler);
+    (port, server)
+}
+
+/// Runs a TCP server on a random port that answers every request with `status_code` and
+/// `body`, for tests that only exercise a client's error handling.
+pub fn run_tcp_server_returning_error(
+    ip: &str,
+    status_code: u16,
+    body: &str,
+) -> (impl Future<Item = (), Error = hyper::Error>, u16) {
+    let status = StatusCode::from_u16(status_code).expect("invalid HTTP status code");
+    let body = body.to_string();
+
+    run_tcp_server(ip, move |_| {
+        let response = Response::builder()
+            .status(status)
+            .body(body.clone().into())
+            .expect("could not build hyper::Response");
+        futures::future::ok(response)
+    })
+}
+
 pub fn run_uds_server<F, R>(path: &str, handler: F) -> impl Future<Item = (), Error = io::Error>
 where
     F: 'static + Fn(Request<Body>) -> R + Clone + Send + Sync,
@@ -81,6 +125,89 @@ where
     })
 }
 
+/// Like `run_uds_server`, but the handler is also given mutable access to `state`.
+///
+/// The lock is held only while the handler builds its response future. The caller keeps
+/// its own clone of `state` to inspect it after the exchange, e.g. to assert on the number
+/// of requests received, or to serve different responses to successive requests.
+pub fn run_uds_server_with_handler_state<F, R, S>(
+    path: &str,
+    state: Arc<Mutex<S>>,
+    handler: F,
+) -> impl Future<Item = (), Error = io::Error>
+where
+    F: 'static + Fn(Request<Body>, &mut S) -> R + Clone + Send + Sync,
+    R: 'static + Future<Item = Response<Body>, Error = io::Error> + Send,
+    S: 'static + Send,
+{
+    run_uds_server(path, move |req| {
+        let mut state = state.lock().unwrap();
+        handler(req, &mut state)
+    })
+}
+
+/// Like `run_tcp_server`, but the returned future resolves once `shutdown` completes.
+///
+/// The listener is dropped when the server stops, so the port is released for
+/// subsequent tests.
+pub fn run_tcp_server_with_shutdown<F, R, S>(
+    ip: &str,
+    handler: F,
+    shutdown: S,
+) -> (impl Future<Item = (), Error = hyper::Error>, u16)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+    S: Future<Item = (), Error = ()>,
+{
+    let (server, port) = run_tcp_server(ip, handler);
+    let server = server
+        .select(shutdown.then(|_| Ok(())))
+        .map(|((), _)| ())
+        .map_err(|(err, _)| err);
+    (server, port)
+}
+
+/// Like `run_uds_server`, but the returned future resolves once `shutdown` completes.
+///
+/// The socket file is removed when the server stops so that the next test can bind
+/// the same path.
+pub fn run_uds_server_with_shutdown<F, R, S>(
+    path: &str,
+    handler: F,
+    shutdown: S,
+) -> impl Future<Item = (), Error = io::Error>
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send + Sync,
+    R: 'static + Future<Item = Response<Body>, Error = io::Error> + Send,
+    S: Future<Item = (), Error = ()>,
+{
+    let path = path.to_string();
+    run_uds_server(&path, handler)
+        .select(shutdown.then(|_| Ok(())))
+        .map(|((), _)| ())
+        .map_err(|(err, _)| err)
+        .then(move |result| {
+            fs::remove_file(&path).unwrap_or(());
+            result
+        })
+}
+
+pub(crate) fn json_response(status: StatusCode, body: &serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .expect("could not build hyper::Response")
+}
+
+pub(crate) fn empty_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("could not build hyper::Response")
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct RequestPath(pub String);
 
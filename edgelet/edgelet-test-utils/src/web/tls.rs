@@ -0,0 +1,188 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io;
+
+use futures::prelude::*;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{self, Body, Request, Response};
+use native_tls::{Identity, TlsAcceptor};
+use openssl::pkey::PKey;
+use openssl::ssl::{
+    select_next_proto, AlpnError, SslAcceptor, SslAcceptorBuilder, SslMethod, SslVerifyMode,
+};
+use openssl::x509::X509;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_openssl::SslAcceptorExt;
+
+use crate::cert::{PemCertificate, TestCa, TestCertUsage};
+
+/// ALPN protocol list, in wire format, containing only HTTP/2.
+const H2_ALPN: &[u8] = b"\x02h2";
+
+/// Serves HTTP over TLS on a random port, presenting `identity` as the server certificate.
+///
+/// Each accepted connection is driven by a full hyper `Http` connection, so clients get
+/// well-formed responses with proper header framing, keep-alive and chunked bodies.
+pub fn run_tls_tcp_server<F, R>(
+    ip: &str,
+    handler: F,
+    identity: Identity,
+) -> (impl Future<Item = (), Error = io::Error>, u16)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    let (listener, port) = bind(ip);
+    let acceptor = tokio_tls::TlsAcceptor::from(TlsAcceptor::new(identity).unwrap());
+
+    let incoming = listener.incoming().and_then(move |socket| {
+        acceptor.accept(socket).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to accept TLS connection: {}", e),
+            )
+        })
+    });
+    (serve(Http::new(), incoming, handler), port)
+}
+
+/// Serves HTTP over TLS on a random port and requires the client to present a certificate
+/// that chains up to one of the certificates in `trust_bundle`.
+///
+/// `server_cert` must contain the server certificate followed by its CA chain, and the
+/// matching private key.
+pub fn run_tls_tcp_server_with_mutual_auth<F, R>(
+    ip: &str,
+    handler: F,
+    server_cert: &PemCertificate,
+    trust_bundle: &[u8],
+) -> (impl Future<Item = (), Error = io::Error>, u16)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    let mut builder = acceptor_builder(server_cert);
+    for ca in X509::stack_from_pem(trust_bundle).unwrap() {
+        builder.cert_store_mut().add_cert(ca).unwrap();
+    }
+    builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    let acceptor = builder.build();
+
+    let (listener, port) = bind(ip);
+    let incoming = listener.incoming().and_then(move |socket| {
+        acceptor.accept_async(socket).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to accept TLS connection: {}", e),
+            )
+        })
+    });
+    (serve(Http::new(), incoming, handler), port)
+}
+
+/// Like `run_tls_tcp_server_with_mutual_auth`, but the server and client certificates are
+/// issued on the fly by `ca`.
+///
+/// Returns the client identity issued by `ca` alongside the server, so that the test can
+/// configure its client to present it.
+pub fn run_tls_tcp_server_with_test_ca<F, R>(
+    ip: &str,
+    handler: F,
+    ca: &TestCa,
+) -> (
+    impl Future<Item = (), Error = io::Error>,
+    u16,
+    PemCertificate,
+)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    let server_cert = ca.issue("localhost", TestCertUsage::Server);
+    let client_cert = ca.issue("client", TestCertUsage::Client);
+
+    let (server, port) =
+        run_tls_tcp_server_with_mutual_auth(ip, handler, &server_cert, &ca.cert_pem());
+    (server, port, client_cert)
+}
+
+/// Serves HTTP/2 over TLS on a random port.
+///
+/// The server advertises `h2` through ALPN and only speaks HTTP/2, so a client that fails
+/// to negotiate HTTP/2 will not get a response.
+pub fn run_h2_tls_tcp_server<F, R>(
+    ip: &str,
+    handler: F,
+    server_cert: &PemCertificate,
+) -> (impl Future<Item = (), Error = io::Error>, u16)
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    let mut builder = acceptor_builder(server_cert);
+    builder.set_alpn_select_callback(|_, client_protos| {
+        select_next_proto(H2_ALPN, client_protos).ok_or(AlpnError::NOACK)
+    });
+    let acceptor = builder.build();
+
+    let mut http = Http::new();
+    http.http2_only(true);
+
+    let (listener, port) = bind(ip);
+    let incoming = listener.incoming().and_then(move |socket| {
+        acceptor.accept_async(socket).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to accept TLS connection: {}", e),
+            )
+        })
+    });
+    (serve(http, incoming, handler), port)
+}
+
+fn acceptor_builder(server_cert: &PemCertificate) -> SslAcceptorBuilder {
+    let mut certs = X509::stack_from_pem(server_cert.get_full_certificate()).unwrap();
+    let key = PKey::private_key_from_pem(server_cert.get_private_key()).unwrap();
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key(&key).unwrap();
+    builder.set_certificate(&certs[0]).unwrap();
+    for cert in certs.drain(1..) {
+        builder.add_extra_chain_cert(cert).unwrap();
+    }
+    builder
+}
+
+fn bind(ip: &str) -> (TcpListener, u16) {
+    let addr = &format!("{}:0", ip).parse().unwrap();
+
+    // Bind a listener synchronously, so that the caller's client will not fail to connect
+    // regardless of when the asynchronous server accepts the connection
+    let listener = TcpListener::bind(addr).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    (listener, port)
+}
+
+fn serve<I, S, F, R>(
+    http: Http,
+    incoming: I,
+    handler: F,
+) -> impl Future<Item = (), Error = io::Error>
+where
+    I: Stream<Item = S, Error = io::Error>,
+    S: 'static + AsyncRead + AsyncWrite + Send,
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send,
+    R: 'static + Future<Item = Response<Body>, Error = hyper::Error> + Send,
+{
+    incoming.for_each(move |stream| {
+        http.serve_connection(stream, service_fn(handler.clone()))
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("failed to serve connection: {}", e),
+                )
+            })
+    })
+}
@@ -0,0 +1,25 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::ffi::OsString;
+use std::io;
+
+use futures::Future;
+use hyper::{Body, Request, Response};
+
+use super::run_uds_server;
+
+/// Unix counterpart of the Windows named pipe server.
+///
+/// There are no named pipes on Unix, so `addr` is treated as the path of a Unix domain
+/// socket. This keeps the public API identical on both platforms.
+pub fn run_pipe_server<F, R>(
+    addr: OsString,
+    handler: F,
+) -> impl Future<Item = (), Error = io::Error>
+where
+    F: 'static + Fn(Request<Body>) -> R + Clone + Send + Sync,
+    R: 'static + Future<Item = Response<Body>, Error = io::Error> + Send,
+{
+    let path = addr.into_string().expect("pipe path is not valid unicode");
+    run_uds_server(&path, handler)
+}
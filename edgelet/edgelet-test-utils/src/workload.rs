@@ -0,0 +1,153 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+
+use futures::future;
+use futures::{Future, Stream};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+
+use crate::web::{json_response, run_uds_server};
+
+/// The workload API operations that `TestWorkloadServer` can stub out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WorkloadOperation {
+    Sign,
+    Encrypt,
+    Decrypt,
+    IdentityCertificate,
+    ServerCertificate,
+    TrustBundle,
+}
+
+/// A request received by `TestWorkloadServer`, broken out of the URL and body.
+#[derive(Clone, Debug)]
+pub struct WorkloadRequest {
+    pub module_id: Option<String>,
+    pub generation_id: Option<String>,
+    pub body: Value,
+}
+
+pub type WorkloadHandler =
+    Arc<dyn Fn(WorkloadRequest) -> Result<Value, (StatusCode, String)> + Send + Sync>;
+
+/// A fake workload API served over a Unix domain socket.
+///
+/// Each operation is served by a closure that receives the parsed request and returns
+/// either the JSON response body or an error status and message. The error is returned
+/// to the client as an `ErrorResponse`. Operations without a handler respond with
+/// `404 Not Found`.
+#[derive(Clone, Default)]
+pub struct TestWorkloadServer {
+    handlers: BTreeMap<WorkloadOperation, WorkloadHandler>,
+}
+
+impl TestWorkloadServer {
+    pub fn new() -> Self {
+        TestWorkloadServer::default()
+    }
+
+    pub fn with_handler<F>(mut self, operation: WorkloadOperation, handler: F) -> Self
+    where
+        F: 'static + Fn(WorkloadRequest) -> Result<Value, (StatusCode, String)> + Send + Sync,
+    {
+        self.handlers.insert(operation, Arc::new(handler));
+        self
+    }
+
+    pub fn run(&self, path: &str) -> impl Future<Item = (), Error = io::Error> {
+        let server = self.clone();
+        run_uds_server(path, move |req| server.handle(req))
+    }
+
+    fn handle(
+        &self,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = io::Error> + Send> {
+        let route = parse_route(req.method(), req.uri().path());
+        let (operation, module_id, generation_id) = match route {
+            Some(route) => route,
+            None => {
+                return Box::new(future::ok(error_response(
+                    StatusCode::NOT_FOUND,
+                    "not found",
+                )))
+            }
+        };
+        let handler = match self.handlers.get(&operation) {
+            Some(handler) => handler.clone(),
+            None => {
+                return Box::new(future::ok(error_response(
+                    StatusCode::NOT_FOUND,
+                    &format!("no handler configured for {:?}", operation),
+                )))
+            }
+        };
+
+        Box::new(
+            req.into_body()
+                .concat2()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+                .map(move |body| {
+                    let body = if body.is_empty() {
+                        Value::Null
+                    } else {
+                        match serde_json::from_slice(&body) {
+                            Ok(body) => body,
+                            Err(err) => {
+                                return error_response(StatusCode::BAD_REQUEST, &err.to_string())
+                            }
+                        }
+                    };
+
+                    let request = WorkloadRequest {
+                        module_id,
+                        generation_id,
+                        body,
+                    };
+                    match handler(request) {
+                        Ok(body) => json_response(StatusCode::OK, &body),
+                        Err((status, message)) => error_response(status, &message),
+                    }
+                }),
+        )
+    }
+}
+
+/// Maps a request onto the workload API routes served by `iotedged`.
+fn parse_route(
+    method: &Method,
+    path: &str,
+) -> Option<(WorkloadOperation, Option<String>, Option<String>)> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let route = match (method, segments.as_slice()) {
+        (&Method::GET, ["trust-bundle"]) => (WorkloadOperation::TrustBundle, None, None),
+        (&Method::POST, ["modules", name, "certificate", "identity"]) => (
+            WorkloadOperation::IdentityCertificate,
+            Some(name.to_string()),
+            None,
+        ),
+        (&Method::POST, ["modules", name, "genid", genid, "certificate", "server"]) => (
+            WorkloadOperation::ServerCertificate,
+            Some(name.to_string()),
+            Some(genid.to_string()),
+        ),
+        (&Method::POST, ["modules", name, "genid", genid, operation]) => {
+            let operation = match *operation {
+                "sign" => WorkloadOperation::Sign,
+                "encrypt" => WorkloadOperation::Encrypt,
+                "decrypt" => WorkloadOperation::Decrypt,
+                _ => return None,
+            };
+            (operation, Some(name.to_string()), Some(genid.to_string()))
+        }
+        _ => return None,
+    };
+    Some(route)
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "message": message }))
+}
@@ -103,6 +103,19 @@ pub fn append_dns_san_entries(sans: &str, names: &[&str]) -> String {
     dns_sans
 }
 
+/// Reads the `MemAvailable` entry, in KiB, from the contents of `/proc/meminfo`. Returns `None`
+/// on kernels that don't report it.
+pub fn parse_mem_available_kb(meminfo: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let mut tokens = line.split_whitespace();
+        if tokens.next()? == "MemAvailable:" {
+            tokens.next()?.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,4 +257,14 @@ mod tests {
             append_dns_san_entries(&sanitized_labels, &["2019host", "   ", "2020host"])
         );
     }
+
+    #[test]
+    fn mem_available() {
+        let meminfo = "MemTotal:        8052960 kB\nMemFree:          548788 kB\nMemAvailable:    5295596 kB\nBuffers:          289888 kB\n";
+        assert_eq!(Some(5_295_596), parse_mem_available_kb(meminfo));
+        assert_eq!(
+            None,
+            parse_mem_available_kb("MemTotal: 8052960 kB\nMemFree: 548788 kB\n")
+        );
+    }
 }
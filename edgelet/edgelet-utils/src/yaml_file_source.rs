@@ -14,6 +14,9 @@ use yaml_rust::{Yaml, YamlLoader};
 /// We use this to parse our config.yaml instead of `config::File` because `config::File` lower-cases all field names that it reads.
 /// This causes issues with fields like `agent.config.createOptions` since the config crate returns `agent.config.createoptions`
 /// which the serde deserializer ignores.
+///
+/// `${VAR_NAME}` in string values is replaced with the value of the `VAR_NAME` environment variable, so that secrets can be
+/// injected without writing them into the file. It is an error for the variable to be unset. `$${` produces a literal `${`.
 #[derive(Clone, Debug)]
 pub enum YamlFileSource {
     File(PathBuf),
@@ -85,7 +88,11 @@ impl Source for YamlFileSource {
 /// The only difference is the fallback `_` case at the end.
 fn from_yaml_value(uri: Option<&String>, value: Yaml) -> Result<Value, ConfigError> {
     match value {
-        Yaml::String(value) => Ok(Value::new(uri, value)),
+        Yaml::String(value) => {
+            let value = substitute_env_vars(&value, |name| std::env::var(name).ok())
+                .map_err(|err| ConfigError::Foreign(Box::new(err)))?;
+            Ok(Value::new(uri, value))
+        }
         Yaml::Real(value) => {
             // TODO: Figure out in what cases this can fail?
             Ok(Value::new(
@@ -132,10 +139,46 @@ fn from_yaml_value(uri: Option<&String>, value: Yaml) -> Result<Value, ConfigErr
     }
 }
 
+/// Replaces every `${VAR_NAME}` in `value` with the result of `lookup(VAR_NAME)`.
+fn substitute_env_vars<F>(value: &str, lookup: F) -> Result<String, YamlFileSourceError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            // `$${` is an escaped `${`
+            result.push_str(&rest[..start]);
+            result.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        // Only the offset is reported, since the rest of the value may be a secret
+        let end = after.find('}').ok_or_else(|| {
+            YamlFileSourceError::UnterminatedVariable(value.len() - rest.len() + start)
+        })?;
+        let name = &after[..end];
+        let substitution = lookup(name)
+            .ok_or_else(|| YamlFileSourceError::UnsetEnvironmentVariable(name.to_string()))?;
+        result.push_str(&substitution);
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
 #[derive(Debug)]
 enum YamlFileSourceError {
     MoreThanOneDocument,
     UnrecognizedYamlValue(Yaml),
+    UnsetEnvironmentVariable(String),
+    UnterminatedVariable(usize),
 }
 
 impl std::fmt::Display for YamlFileSourceError {
@@ -147,8 +190,78 @@ impl std::fmt::Display for YamlFileSourceError {
             YamlFileSourceError::UnrecognizedYamlValue(value) => {
                 write!(f, "unrecognized YAML value {:?}", value)
             }
+            YamlFileSourceError::UnsetEnvironmentVariable(name) => write!(
+                f,
+                "environment variable {} is referenced in the config but is not set",
+                name
+            ),
+            YamlFileSourceError::UnterminatedVariable(offset) => write!(
+                f,
+                "unterminated ${{...}} at offset {} of a config value",
+                offset
+            ),
         }
     }
 }
 
 impl std::error::Error for YamlFileSourceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HUB" => Some("myhub.azure-devices.net".to_string()),
+            "KEY" => Some("QXp1cmUgSW9UIEVkZ2U=".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let value = substitute_env_vars("HostName=${HUB};SharedAccessKey=${KEY}", lookup).unwrap();
+
+        assert_eq!(
+            "HostName=myhub.azure-devices.net;SharedAccessKey=QXp1cmUgSW9UIEVkZ2U=",
+            value
+        );
+    }
+
+    #[test]
+    fn value_without_variables_is_unchanged() {
+        assert_eq!(
+            "unix:///var/run/iotedge/mgmt.sock",
+            substitute_env_vars("unix:///var/run/iotedge/mgmt.sock", lookup).unwrap()
+        );
+    }
+
+    #[test]
+    fn escaped_variable_is_literal() {
+        assert_eq!(
+            "${HUB} myhub.azure-devices.net",
+            substitute_env_vars("$${HUB} ${HUB}", lookup).unwrap()
+        );
+    }
+
+    #[test]
+    fn unset_variable_fails() {
+        let err = substitute_env_vars("${MISSING}", lookup).unwrap_err();
+
+        assert_eq!(
+            "environment variable MISSING is referenced in the config but is not set",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn unterminated_variable_fails() {
+        let err = substitute_env_vars("HostName=${HUB;SharedAccessKey=secret", lookup).unwrap_err();
+
+        match err {
+            YamlFileSourceError::UnterminatedVariable(offset) => assert_eq!(9, offset),
+            ref err => panic!("unexpected error {:?}", err),
+        }
+        assert!(!err.to_string().contains("secret"));
+    }
+}
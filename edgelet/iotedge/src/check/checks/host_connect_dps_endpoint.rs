@@ -1,6 +1,7 @@
 use std::net::TcpStream;
 
 use failure::{Context, ResultExt};
+use openssl::x509::X509;
 
 use edgelet_core::{self, ProvisioningType, RuntimeSettings};
 
@@ -84,14 +85,19 @@ pub fn resolve_and_tls_handshake(
     let stream = TcpStream::connect_timeout(&host_addr, std::time::Duration::from_secs(10))
         .with_context(|_| format!("Could not connect to {}", hostname_display))?;
 
-    let tls_connector = native_tls::TlsConnector::new().with_context(|_| {
-        format!(
-            "Could not connect to {} : could not create TLS connector",
-            hostname_display,
-        )
-    })?;
+    // The host name is validated below with `edgelet_core::validate_san` instead of by the TLS
+    // implementation, so that a mismatch is reported separately from other handshake errors.
+    let tls_connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_hostnames(true)
+        .build()
+        .with_context(|_| {
+            format!(
+                "Could not connect to {} : could not create TLS connector",
+                hostname_display,
+            )
+        })?;
 
-    let _ = tls_connector
+    let tls_stream = tls_connector
         .connect(tls_hostname, stream)
         .with_context(|_| {
             format!(
@@ -100,5 +106,24 @@ pub fn resolve_and_tls_handshake(
             )
         })?;
 
+    let server_cert = tls_stream
+        .peer_certificate()
+        .ok()
+        .and_then(|cert| cert)
+        .and_then(|cert| cert.to_der().ok())
+        .and_then(|der| X509::from_der(&der).ok())
+        .ok_or_else(|| {
+            Context::new(format!(
+                "Could not connect to {} : could not get the server certificate",
+                hostname_display,
+            ))
+        })?;
+    edgelet_core::validate_san(&server_cert, tls_hostname).with_context(|_| {
+        format!(
+            "Could not connect to {} : the server certificate is not valid for the host name",
+            hostname_display,
+        )
+    })?;
+
     Ok(())
 }
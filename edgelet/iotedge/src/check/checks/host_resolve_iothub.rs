@@ -0,0 +1,55 @@
+use std::net::ToSocketAddrs;
+
+use failure::{self, Context, ResultExt};
+
+use crate::check::{
+    checker::Checker, upstream_protocol_port::UpstreamProtocolPort, Check, CheckResult,
+};
+
+#[derive(Default, serde_derive::Serialize)]
+pub(crate) struct HostResolveIotHub {
+    iothub_hostname: Option<String>,
+    addresses: Vec<String>,
+}
+
+impl Checker for HostResolveIotHub {
+    fn id(&self) -> &'static str {
+        "host-resolve-iothub"
+    }
+    fn description(&self) -> &'static str {
+        "host can resolve the IoT Hub hostname"
+    }
+    fn execute(&mut self, check: &mut Check) -> CheckResult {
+        self.inner_execute(check)
+            .unwrap_or_else(CheckResult::Failed)
+    }
+    fn get_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+impl HostResolveIotHub {
+    fn inner_execute(&mut self, check: &mut Check) -> Result<CheckResult, failure::Error> {
+        let iothub_hostname = if let Some(iothub_hostname) = &check.iothub_hostname {
+            iothub_hostname
+        } else {
+            return Ok(CheckResult::Skipped);
+        };
+        self.iothub_hostname = Some(iothub_hostname.clone());
+
+        self.addresses = (&**iothub_hostname, UpstreamProtocolPort::Https.as_port())
+            .to_socket_addrs()
+            .with_context(|_| format!("Could not resolve {}", iothub_hostname))?
+            .map(|addr| addr.ip().to_string())
+            .collect();
+        if self.addresses.is_empty() {
+            return Err(Context::new(format!(
+                "{} did not resolve to any addresses",
+                iothub_hostname
+            ))
+            .into());
+        }
+
+        Ok(CheckResult::Ok)
+    }
+}
@@ -4,7 +4,10 @@ use std::path::PathBuf;
 
 use failure::{self, Context, ResultExt};
 
-use edgelet_core::{self, AttestationMethod, ManualAuthMethod, ProvisioningType, RuntimeSettings};
+use edgelet_core::{
+    self, parse_openssl_time, AttestationMethod, ManualAuthMethod, ProvisioningType,
+    RuntimeSettings,
+};
 
 use crate::check::{checker::Checker, Check, CheckResult};
 
@@ -83,18 +86,6 @@ pub(crate) struct CertificateValidity {
 
 impl CertificateValidity {
     pub(crate) fn parse(cert_name: String, cert_path: PathBuf) -> Result<Self, failure::Error> {
-        fn parse_openssl_time(
-            time: &openssl::asn1::Asn1TimeRef,
-        ) -> chrono::ParseResult<chrono::DateTime<chrono::Utc>> {
-            // openssl::asn1::Asn1TimeRef does not expose any way to convert the ASN1_TIME to a Rust-friendly type
-            //
-            // Its Display impl uses ASN1_TIME_print, so we convert it into a String and parse it back
-            // into a chrono::DateTime<chrono::Utc>
-            let time = time.to_string();
-            let time = chrono::NaiveDateTime::parse_from_str(&time, "%b %e %H:%M:%S %Y GMT")?;
-            Ok(chrono::DateTime::<chrono::Utc>::from_utc(time, chrono::Utc))
-        }
-
         let (not_after, not_before) = File::open(&cert_path)
             .map_err(failure::Error::from)
             .and_then(|mut device_ca_cert_file| {
@@ -123,6 +114,27 @@ impl CertificateValidity {
         })
     }
 
+    /// A one-line description of the validity period, e.g. for the support bundle.
+    pub(crate) fn summary(&self) -> String {
+        let now = chrono::Utc::now();
+        let status = if self.not_before > now {
+            "not yet valid".to_owned()
+        } else if self.not_after < now {
+            "expired".to_owned()
+        } else {
+            format!("expires in {} days", (self.not_after - now).num_days())
+        };
+
+        format!(
+            "{} at {}: valid from {} until {} ({})",
+            self.cert_name,
+            self.cert_path.display(),
+            self.not_before,
+            self.not_after,
+            status,
+        )
+    }
+
     fn to_check_result(&self) -> Result<CheckResult, failure::Error> {
         let cert_path_displayable = self.cert_path.display();
 
@@ -10,10 +10,12 @@ mod container_local_time;
 mod host_connect_dps_endpoint;
 mod host_connect_iothub;
 mod host_local_time;
+mod host_resolve_iothub;
 mod hostname;
 mod identity_certificate_expiry;
 mod iotedged_version;
 mod storage_mounted_from_host;
+mod valid_config;
 mod well_formed_config;
 mod well_formed_connection_string;
 mod windows_host_version;
@@ -30,10 +32,14 @@ pub(crate) use self::container_local_time::ContainerLocalTime;
 pub(crate) use self::host_connect_dps_endpoint::HostConnectDpsEndpoint;
 pub(crate) use self::host_connect_iothub::get_host_connect_iothub_tests;
 pub(crate) use self::host_local_time::HostLocalTime;
+pub(crate) use self::host_resolve_iothub::HostResolveIotHub;
 pub(crate) use self::hostname::Hostname;
-pub(crate) use self::identity_certificate_expiry::IdentityCertificateExpiry;
+pub(crate) use self::identity_certificate_expiry::{
+    CertificateValidity, IdentityCertificateExpiry,
+};
 pub(crate) use self::iotedged_version::IotedgedVersion;
 pub(crate) use self::storage_mounted_from_host::{EdgeAgentStorageMounted, EdgeHubStorageMounted};
+pub(crate) use self::valid_config::ValidConfig;
 pub(crate) use self::well_formed_config::WellFormedConfig;
 pub(crate) use self::well_formed_connection_string::WellFormedConnectionString;
 pub(crate) use self::windows_host_version::WindowsHostVersion;
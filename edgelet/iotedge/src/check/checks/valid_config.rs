@@ -0,0 +1,49 @@
+use failure::{self, Context};
+
+use crate::check::{checker::Checker, Check, CheckResult};
+
+#[derive(Default, serde_derive::Serialize)]
+pub(crate) struct ValidConfig {
+    errors: Vec<String>,
+}
+
+impl Checker for ValidConfig {
+    fn id(&self) -> &'static str {
+        "config-yaml-valid"
+    }
+    fn description(&self) -> &'static str {
+        "config.yaml settings are valid"
+    }
+    fn execute(&mut self, check: &mut Check) -> CheckResult {
+        self.inner_execute(check)
+            .unwrap_or_else(CheckResult::Failed)
+    }
+    fn get_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+impl ValidConfig {
+    fn inner_execute(&mut self, check: &mut Check) -> Result<CheckResult, failure::Error> {
+        let settings = if let Some(settings) = &check.settings {
+            settings
+        } else {
+            return Ok(CheckResult::Skipped);
+        };
+
+        self.errors = edgelet_core::validate_config(settings)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !self.errors.is_empty() {
+            return Err(Context::new(format!(
+                "The IoT Edge daemon will not start because of the following settings in {}:\n{}",
+                check.config_file.display(),
+                self.errors.join("\n"),
+            ))
+            .into());
+        }
+
+        Ok(CheckResult::Ok)
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! JUnit XML output of `iotedge check`, for the test result reporters of CI systems.
+//!
+//! Every section of checks is a `<testsuite>` and every check is a `<testcase>`. Errors are
+//! reported as failures, so that they fail the test run, while checks that were ignored or not
+//! run are reported as skipped. Warnings don't fail the test case, but are included in its
+//! `<system-out>`.
+
+use std::io::Write;
+
+use super::CheckResultSerializable;
+
+pub(super) struct TestCase<'a> {
+    pub(super) id: &'static str,
+    pub(super) description: &'static str,
+    /// `None` if the check was not run because an earlier check failed fatally.
+    pub(super) result: Option<&'a CheckResultSerializable>,
+}
+
+pub(super) struct TestSuite<'a> {
+    pub(super) name: &'a str,
+    pub(super) test_cases: Vec<TestCase<'a>>,
+}
+
+impl<'a> TestSuite<'a> {
+    fn failures(&self) -> usize {
+        self.test_cases
+            .iter()
+            .filter(|test_case| match test_case.result {
+                Some(CheckResultSerializable::Fatal { .. })
+                | Some(CheckResultSerializable::Error { .. }) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    fn skipped(&self) -> usize {
+        self.test_cases
+            .iter()
+            .filter(|test_case| match test_case.result {
+                None
+                | Some(CheckResultSerializable::Ignored)
+                | Some(CheckResultSerializable::Skipped) => true,
+                _ => false,
+            })
+            .count()
+    }
+}
+
+pub(super) fn write_junit(
+    writer: &mut impl Write,
+    test_suites: &[TestSuite<'_>],
+) -> std::io::Result<()> {
+    let tests: usize = test_suites.iter().map(|s| s.test_cases.len()).sum();
+    let failures: usize = test_suites.iter().map(TestSuite::failures).sum();
+    let skipped: usize = test_suites.iter().map(TestSuite::skipped).sum();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuites name="iotedge check" tests="{}" failures="{}" errors="0" skipped="{}">"#,
+        tests, failures, skipped,
+    )?;
+
+    for test_suite in test_suites {
+        writeln!(
+            writer,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="{}">"#,
+            escape(test_suite.name),
+            test_suite.test_cases.len(),
+            test_suite.failures(),
+            test_suite.skipped(),
+        )?;
+
+        for test_case in &test_suite.test_cases {
+            write!(
+                writer,
+                r#"    <testcase classname="{}" name="{}""#,
+                escape(test_suite.name),
+                escape(&format!("{} ({})", test_case.description, test_case.id)),
+            )?;
+
+            match test_case.result {
+                Some(CheckResultSerializable::Ok) => writeln!(writer, " />")?,
+                Some(CheckResultSerializable::Warning { details }) => {
+                    writeln!(writer, ">")?;
+                    writeln!(
+                        writer,
+                        "      <system-out>Warning: {}</system-out>",
+                        escape(&details.join("\n")),
+                    )?;
+                    writeln!(writer, "    </testcase>")?;
+                }
+                Some(CheckResultSerializable::Ignored) => {
+                    write_skipped(writer, "not applicable to this device")?
+                }
+                Some(CheckResultSerializable::Skipped) => {
+                    write_skipped(writer, "skipped because of previous failures")?
+                }
+                None => write_skipped(writer, "not run because of a previous fatal error")?,
+                Some(CheckResultSerializable::Fatal { details })
+                | Some(CheckResultSerializable::Error { details }) => {
+                    writeln!(writer, ">")?;
+                    writeln!(
+                        writer,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape(details.first().map_or("", String::as_str)),
+                        escape(&details.join("\n")),
+                    )?;
+                    writeln!(writer, "    </testcase>")?;
+                }
+            }
+        }
+
+        writeln!(writer, "  </testsuite>")?;
+    }
+
+    writeln!(writer, "</testsuites>")?;
+    Ok(())
+}
+
+fn write_skipped(writer: &mut impl Write, message: &str) -> std::io::Result<()> {
+    writeln!(writer, ">")?;
+    writeln!(writer, r#"      <skipped message="{}" />"#, escape(message))?;
+    writeln!(writer, "    </testcase>")
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn junit(test_suites: &[TestSuite<'_>]) -> String {
+        let mut output = vec![];
+        write_junit(&mut output, test_suites).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn errors_are_failures() {
+        let ok = CheckResultSerializable::Ok;
+        let error = CheckResultSerializable::Error {
+            details: vec![
+                "Could not open file config.yaml".to_owned(),
+                "permission denied".to_owned(),
+            ],
+        };
+        let ignored = CheckResultSerializable::Ignored;
+
+        let output = junit(&[TestSuite {
+            name: "Configuration checks",
+            test_cases: vec![
+                TestCase {
+                    id: "config-yaml-well-formed",
+                    description: "config.yaml is well-formed",
+                    result: Some(&ok),
+                },
+                TestCase {
+                    id: "connection-string",
+                    description: "config.yaml has well-formed connection string",
+                    result: Some(&error),
+                },
+                TestCase {
+                    id: "windows-host-version",
+                    description: "Windows host version is supported",
+                    result: Some(&ignored),
+                },
+                TestCase {
+                    id: "iotedged-version",
+                    description: "latest security daemon",
+                    result: None,
+                },
+            ],
+        }]);
+
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="iotedge check" tests="4" failures="1" errors="0" skipped="2">
+  <testsuite name="Configuration checks" tests="4" failures="1" errors="0" skipped="2">
+    <testcase classname="Configuration checks" name="config.yaml is well-formed (config-yaml-well-formed)" />
+    <testcase classname="Configuration checks" name="config.yaml has well-formed connection string (connection-string)">
+      <failure message="Could not open file config.yaml">Could not open file config.yaml&#10;permission denied</failure>
+    </testcase>
+    <testcase classname="Configuration checks" name="Windows host version is supported (windows-host-version)">
+      <skipped message="not applicable to this device" />
+    </testcase>
+    <testcase classname="Configuration checks" name="latest security daemon (iotedged-version)">
+      <skipped message="not run because of a previous fatal error" />
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+            output
+        );
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt; &amp; &apos;y&apos;",
+            escape(r#"<a href="x"> & 'y'"#)
+        );
+    }
+}
@@ -21,6 +21,8 @@ use crate::LatestVersions;
 mod additional_info;
 use self::additional_info::AdditionalInfo;
 
+mod junit;
+
 mod stdout;
 use self::stdout::Stdout;
 
@@ -30,6 +32,7 @@ mod checker;
 use checker::Checker;
 
 mod checks;
+pub(crate) use checks::CertificateValidity;
 use checks::*;
 
 pub struct Check {
@@ -58,6 +61,8 @@ pub struct Check {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OutputFormat {
     Json,
+    /// JUnit XML, for the test result reporters of CI systems.
+    JUnit,
     Text,
 }
 
@@ -223,6 +228,7 @@ impl Check {
                 "Configuration checks",
                 vec![
                     Box::new(WellFormedConfig::default()),
+                    Box::new(ValidConfig::default()),
                     Box::new(WellFormedConnectionString::default()),
                     Box::new(ContainerEngineInstalled::default()),
                     Box::new(WindowsHostVersion::default()),
@@ -244,6 +250,7 @@ impl Check {
             ("Connectivity checks", {
                 let mut tests: Vec<Box<dyn Checker>> = Vec::new();
                 tests.push(Box::new(HostConnectDpsEndpoint::default()));
+                tests.push(Box::new(HostResolveIotHub::default()));
                 tests.extend(get_host_connect_iothub_tests());
                 tests.extend(get_host_container_iothub_tests());
                 tests
@@ -251,6 +258,23 @@ impl Check {
         ]
     }
 
+    /// The checks run by `iotedge config check`. They only need config.yaml and the network, so
+    /// they can be run before the daemon is started.
+    pub fn config_check_ids() -> &'static [&'static str] {
+        &[
+            "config-yaml-well-formed",
+            "config-yaml-valid",
+            "connection-string",
+            "container-engine-uri",
+            "hostname",
+            "host-connect-dps-endpoint",
+            "host-resolve-iothub",
+            "host-connect-iothub-amqp",
+            "host-connect-iothub-https",
+            "host-connect-iothub-mqtt",
+        ]
+    }
+
     pub fn possible_ids() -> impl Iterator<Item = &'static str> {
         let result: Vec<&'static str> = Check::checks()
             .iter()
@@ -558,7 +582,7 @@ impl Check {
         if self.output_format == OutputFormat::Json {
             let check_results = CheckResultsSerializable {
                 additional_info: &self.additional_info,
-                checks,
+                checks: &checks,
             };
 
             if let Err(err) = serde_json::to_writer(std::io::stdout(), &check_results) {
@@ -569,6 +593,29 @@ impl Check {
             println!();
         }
 
+        if self.output_format == OutputFormat::JUnit {
+            let test_suites: Vec<_> = check_data
+                .iter()
+                .map(|(section_name, section_checks)| junit::TestSuite {
+                    name: section_name,
+                    test_cases: section_checks
+                        .iter()
+                        .filter(|check| !self.dont_run.contains(check.id()))
+                        .map(|check| junit::TestCase {
+                            id: check.id(),
+                            description: check.description(),
+                            result: checks.get(check.id()).map(|output| &output.result),
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            if let Err(err) = junit::write_junit(&mut std::io::stdout(), &test_suites) {
+                eprintln!("Could not write JUnit output: {}", err);
+                return Err(ErrorKind::Diagnostics.into());
+            }
+        }
+
         result
     }
 }
@@ -601,7 +648,7 @@ fn write_lines<'a>(
 #[derive(Debug, serde_derive::Serialize)]
 struct CheckResultsSerializable<'a> {
     additional_info: &'a AdditionalInfo,
-    checks: BTreeMap<&'static str, CheckOutputSerializable>,
+    checks: &'a BTreeMap<&'static str, CheckOutputSerializable>,
 }
 
 #[derive(Debug, serde_derive::Serialize)]
@@ -70,7 +70,7 @@ impl Stdout {
             }
         } else {
             match output_format {
-                super::OutputFormat::Json => Stdout::DefaultJson,
+                super::OutputFormat::Json | super::OutputFormat::JUnit => Stdout::DefaultJson,
                 super::OutputFormat::Text => Stdout::DefaultText,
             }
         }
@@ -0,0 +1,374 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Converts a v1 `config.toml` to the `config.yaml` read by this version of the daemon.
+//!
+//! The two formats mostly use the same tables and keys, so fields are carried over as they are
+//! unless they are listed in `RENAMED`. Fields that have no equivalent are dropped. The caller is
+//! told about every renamed and dropped field. Comments cannot be carried over, since neither
+//! format's parser keeps them.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use failure::ResultExt;
+use futures::future::{self, FutureResult};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// v1 fields that moved, as `(v1 path, v2 path)`. A path also matches the fields nested below it.
+const RENAMED: &[(&str, &str)] = &[
+    ("edge_ca.cert", "certificates.device_ca_cert"),
+    ("edge_ca.pk", "certificates.device_ca_pk"),
+    (
+        "provisioning.attestation.symmetric_key.value",
+        "provisioning.attestation.symmetric_key",
+    ),
+    (
+        "provisioning.connection_string",
+        "provisioning.device_connection_string",
+    ),
+    (
+        "provisioning.device_id",
+        "provisioning.authentication.device_id",
+    ),
+    ("provisioning.id_scope", "provisioning.scope_id"),
+    (
+        "provisioning.iothub_hostname",
+        "provisioning.authentication.iothub_hostname",
+    ),
+    ("trust_bundle_cert", "certificates.trusted_ca_certs"),
+];
+
+/// v1 fields that are unchanged in v2. A path also matches the fields nested below it.
+const UNCHANGED: &[&str] = &[
+    "agent",
+    "connect",
+    "homedir",
+    "hostname",
+    "listen",
+    "moby_runtime",
+    "provisioning.attestation",
+    "provisioning.authentication",
+    "provisioning.dynamic_reprovisioning",
+    "provisioning.endpoint",
+    "provisioning.global_endpoint",
+    "provisioning.source",
+    "watchdog",
+];
+
+pub struct ConfigImport<W> {
+    input: PathBuf,
+    output: PathBuf,
+    force: bool,
+    stdout: W,
+}
+
+impl<W> ConfigImport<W>
+where
+    W: Write,
+{
+    pub fn new(input: PathBuf, output: PathBuf, force: bool, stdout: W) -> Self {
+        ConfigImport {
+            input,
+            output,
+            force,
+            stdout,
+        }
+    }
+
+    fn execute_inner(mut self) -> Result<(), Error> {
+        if !self.force && self.output.exists() {
+            return Err(Error::from(ErrorKind::ImportConfig(format!(
+                "{} already exists. Use --force to overwrite it.",
+                self.output.display()
+            ))));
+        }
+
+        let v1 = fs::read_to_string(&self.input).with_context(|_| {
+            ErrorKind::ImportConfig(format!("could not read {}", self.input.display()))
+        })?;
+        let v1: Value = toml::from_str(&v1).with_context(|_| {
+            ErrorKind::ImportConfig(format!("{} is not valid TOML", self.input.display()))
+        })?;
+
+        let import = import(&v1);
+
+        let yaml = serde_yaml::to_string(&import.config)
+            .context(ErrorKind::ImportConfig("could not write YAML".to_string()))?;
+        let yaml = format!(
+            "# Imported from {} by 'iotedge config import'\n{}\n",
+            self.input.display(),
+            yaml
+        );
+        fs::write(&self.output, yaml).with_context(|_| {
+            ErrorKind::ImportConfig(format!("could not write {}", self.output.display()))
+        })?;
+
+        self.write_report(&import)
+            .context(ErrorKind::WriteToStdout)?;
+        Ok(())
+    }
+
+    fn write_report(&mut self, import: &Import) -> std::io::Result<()> {
+        writeln!(
+            self.stdout,
+            "Imported {} to {}",
+            self.input.display(),
+            self.output.display()
+        )?;
+
+        if !import.renamed.is_empty() {
+            writeln!(self.stdout)?;
+            writeln!(self.stdout, "Renamed fields:")?;
+            for (v1, v2) in &import.renamed {
+                writeln!(self.stdout, "~ {} -> {}", v1, v2)?;
+            }
+        }
+
+        if !import.dropped.is_empty() {
+            writeln!(self.stdout)?;
+            writeln!(self.stdout, "Dropped fields with no equivalent:")?;
+            for v1 in &import.dropped {
+                writeln!(self.stdout, "- {}", v1)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W> Command for ConfigImport<W>
+where
+    W: Write,
+{
+    type Future = FutureResult<(), Error>;
+
+    fn execute(self) -> Self::Future {
+        future::result(self.execute_inner())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Import {
+    config: Value,
+    renamed: Vec<(String, String)>,
+    dropped: Vec<String>,
+}
+
+fn import(v1: &Value) -> Import {
+    let mut import = Import {
+        config: Value::Object(Map::new()),
+        ..Import::default()
+    };
+
+    for (path, value) in leaves(v1) {
+        match v2_path(&path) {
+            Some(v2) => {
+                if v2 != path {
+                    import.renamed.push((path, v2.clone()));
+                }
+                insert(&mut import.config, &v2, value.clone());
+            }
+            None => import.dropped.push(path),
+        }
+    }
+
+    import
+}
+
+/// Returns the dotted paths of all the values in `value` that aren't tables.
+fn leaves(value: &Value) -> Vec<(String, &Value)> {
+    fn walk<'a>(prefix: &str, value: &'a Value, leaves: &mut Vec<(String, &'a Value)>) {
+        match value {
+            Value::Object(table) => {
+                for (key, value) in table {
+                    let path = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    walk(&path, value, leaves);
+                }
+            }
+            value => leaves.push((prefix.to_string(), value)),
+        }
+    }
+
+    let mut leaves = vec![];
+    walk("", value, &mut leaves);
+    leaves
+}
+
+fn v2_path(v1: &str) -> Option<String> {
+    let matches = |prefix: &str| v1 == prefix || v1.starts_with(&format!("{}.", prefix));
+
+    if let Some((from, to)) = RENAMED.iter().find(|(from, _)| matches(from)) {
+        return Some(format!("{}{}", to, &v1[from.len()..]));
+    }
+
+    if UNCHANGED.iter().any(|prefix| matches(prefix)) {
+        Some(v1.to_string())
+    } else {
+        None
+    }
+}
+
+fn insert(config: &mut Value, path: &str, value: Value) {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let last = keys.pop().expect("split always returns at least one value");
+
+    let mut table = config;
+    for key in keys {
+        let entry = table
+            .as_object_mut()
+            .expect("only tables are inserted before leaves")
+            .entry(key)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        table = entry;
+    }
+
+    table
+        .as_object_mut()
+        .expect("only tables are inserted before leaves")
+        .insert(last.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn import_str(v1: &str) -> Import {
+        import(&toml::from_str(v1).unwrap())
+    }
+
+    #[test]
+    fn manual_connection_string_is_imported() {
+        let import = import_str(
+            r#"
+hostname = "edgedevice"
+homedir = "/var/lib/iotedge"
+
+[provisioning]
+source = "manual"
+connection_string = "HostName=hub.azure-devices.net;DeviceId=d1;SharedAccessKey=a2V5"
+
+[agent]
+name = "edgeAgent"
+type = "docker"
+
+[agent.config]
+image = "mcr.microsoft.com/azureiotedge-agent:1.0"
+
+[connect]
+workload_uri = "unix:///var/run/iotedge/workload.sock"
+management_uri = "unix:///var/run/iotedge/mgmt.sock"
+"#,
+        );
+
+        assert_eq!(
+            json!({
+                "hostname": "edgedevice",
+                "homedir": "/var/lib/iotedge",
+                "provisioning": {
+                    "source": "manual",
+                    "device_connection_string": "HostName=hub.azure-devices.net;DeviceId=d1;SharedAccessKey=a2V5",
+                },
+                "agent": {
+                    "name": "edgeAgent",
+                    "type": "docker",
+                    "config": {
+                        "image": "mcr.microsoft.com/azureiotedge-agent:1.0",
+                    },
+                },
+                "connect": {
+                    "workload_uri": "unix:///var/run/iotedge/workload.sock",
+                    "management_uri": "unix:///var/run/iotedge/mgmt.sock",
+                },
+            }),
+            import.config
+        );
+        assert_eq!(
+            vec![(
+                "provisioning.connection_string".to_string(),
+                "provisioning.device_connection_string".to_string()
+            )],
+            import.renamed
+        );
+        assert!(import.dropped.is_empty());
+    }
+
+    #[test]
+    fn dps_symmetric_key_and_certificates_are_renamed() {
+        let import = import_str(
+            r#"
+trust_bundle_cert = "file:///etc/iotedge/trust-bundle.pem"
+
+[provisioning]
+source = "dps"
+global_endpoint = "https://global.azure-devices-provisioning.net"
+id_scope = "0ab1234C5D6"
+
+[provisioning.attestation]
+method = "symmetric_key"
+registration_id = "d1"
+symmetric_key = { value = "a2V5" }
+
+[edge_ca]
+cert = "file:///etc/iotedge/edge-ca.pem"
+pk = "file:///etc/iotedge/edge-ca.key.pem"
+"#,
+        );
+
+        assert_eq!("0ab1234C5D6", import.config["provisioning"]["scope_id"]);
+        assert_eq!(
+            "a2V5",
+            import.config["provisioning"]["attestation"]["symmetric_key"]
+        );
+        assert_eq!(
+            json!({
+                "device_ca_cert": "file:///etc/iotedge/edge-ca.pem",
+                "device_ca_pk": "file:///etc/iotedge/edge-ca.key.pem",
+                "trusted_ca_certs": "file:///etc/iotedge/trust-bundle.pem",
+            }),
+            import.config["certificates"]
+        );
+        assert_eq!(5, import.renamed.len());
+    }
+
+    #[test]
+    fn unknown_fields_are_dropped() {
+        let import = import_str(
+            r#"
+hostname = "edgedevice"
+parent_hostname = "parentdevice"
+
+[provisioning]
+source = "manual"
+auto_reprovisioning_mode = "Dynamic"
+"#,
+        );
+
+        assert_eq!(
+            json!({
+                "hostname": "edgedevice",
+                "provisioning": { "source": "manual" },
+            }),
+            import.config
+        );
+        assert_eq!(
+            vec![
+                "parent_hostname".to_string(),
+                "provisioning.auto_reprovisioning_mode".to_string()
+            ],
+            import.dropped
+        );
+    }
+}
@@ -30,6 +30,9 @@ pub enum ErrorKind {
     )]
     FetchLatestVersions(FetchLatestVersionsReason),
 
+    #[fail(display = "Could not import config: {}", _0)]
+    ImportConfig(String),
+
     #[fail(display = "Could not initialize tokio runtime")]
     InitializeTokio,
 
@@ -39,6 +42,9 @@ pub enum ErrorKind {
     #[fail(display = "A module runtime error occurred")]
     ModuleRuntime,
 
+    #[fail(display = "Could not provision the device: {}", _0)]
+    Provision(&'static str),
+
     #[fail(display = "Could not generate support bundle")]
     SupportBundle,
 
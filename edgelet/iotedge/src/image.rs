@@ -0,0 +1,122 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use failure::{Fail, ResultExt};
+use futures::Future;
+
+use edgelet_core::{Module, ModuleRuntime};
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Prints how much disk space a module image takes up.
+pub struct ImageSize<M, W> {
+    image: String,
+    runtime: M,
+    output: Arc<Mutex<W>>,
+}
+
+impl<M, W> ImageSize<M, W> {
+    pub fn new(image: String, runtime: M, output: W) -> Self {
+        ImageSize {
+            image,
+            runtime,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<M, W> Command for ImageSize<M, W>
+where
+    M: 'static + ModuleRuntime + Clone,
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let image = self.image.clone();
+        let write = self.output.clone();
+        let result = self
+            .runtime
+            .image_size(&image)
+            .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
+            .and_then(move |size| {
+                let mut w = write.lock().unwrap();
+                writeln!(
+                    w,
+                    "{}: {} bytes in {} layers ({} bytes with shared layers)",
+                    image,
+                    size.compressed_bytes(),
+                    size.layers(),
+                    size.uncompressed_bytes(),
+                )
+                .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
+
+/// Removes the images that no module uses. The images of the modules that are currently deployed
+/// are always kept, along with any images that are listed explicitly.
+pub struct ImagePrune<M, W> {
+    keep: Vec<String>,
+    runtime: M,
+    output: Arc<Mutex<W>>,
+}
+
+impl<M, W> ImagePrune<M, W> {
+    pub fn new(keep: Vec<String>, runtime: M, output: W) -> Self {
+        ImagePrune {
+            keep,
+            runtime,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<M, W> Command for ImagePrune<M, W>
+where
+    M: 'static + ModuleRuntime + Clone + Send,
+    M::Config: Display,
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let mut keep = self.keep;
+        let runtime = self.runtime.clone();
+        let write = self.output.clone();
+        let result = self
+            .runtime
+            .list()
+            .and_then(move |modules| {
+                keep.extend(
+                    modules
+                        .iter()
+                        .map(|module| module.config().to_string())
+                        .filter(|image| !image.is_empty()),
+                );
+                runtime.prune_images(&keep)
+            })
+            .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
+            .and_then(move |report| {
+                let mut w = write.lock().unwrap();
+                for image in report.images_deleted() {
+                    writeln!(w, "{}", image).context(ErrorKind::WriteToStdout)?;
+                }
+                writeln!(
+                    w,
+                    "Removed {} images, reclaiming {} bytes",
+                    report.images_deleted().len(),
+                    report.space_reclaimed(),
+                )
+                .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
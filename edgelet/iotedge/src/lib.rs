@@ -17,18 +17,24 @@ use futures::Future;
 use serde_derive::Deserialize;
 
 mod check;
+mod config_import;
 mod error;
+mod image;
 mod list;
 mod logs;
+mod provision;
 mod restart;
 mod support_bundle;
 mod unknown;
 mod version;
 
 pub use crate::check::{Check, OutputFormat};
+pub use crate::config_import::ConfigImport;
 pub use crate::error::{Error, ErrorKind, FetchLatestVersionsReason};
-pub use crate::list::List;
+pub use crate::image::{ImagePrune, ImageSize};
+pub use crate::list::{List, ListOutputFormat};
 pub use crate::logs::Logs;
+pub use crate::provision::Provision;
 pub use crate::restart::Restart;
 pub use crate::support_bundle::SupportBundle;
 pub use crate::unknown::Unknown;
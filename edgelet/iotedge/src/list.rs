@@ -8,6 +8,7 @@ use chrono::{Duration, Utc};
 use chrono_humanize::{Accuracy, HumanTime, Tense};
 use failure::{Fail, ResultExt};
 use futures::{Future, Stream};
+use serde::Serialize;
 use tabwriter::TabWriter;
 
 use edgelet_core::{Module, ModuleRuntime, ModuleRuntimeState, ModuleStatus};
@@ -17,17 +18,28 @@ use crate::Command;
 
 pub struct List<M, W> {
     runtime: M,
+    output_format: ListOutputFormat,
     output: Arc<Mutex<TabWriter<W>>>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListOutputFormat {
+    /// A JSON array of the modules, each in the format of the management API's `GET /modules`.
+    Json,
+    /// One JSON object per line, in the same format as `Json`.
+    JsonLines,
+    Text,
+}
+
 impl<M, W> List<M, W>
 where
     W: Write,
 {
-    pub fn new(runtime: M, output: W) -> Self {
+    pub fn new(runtime: M, output_format: ListOutputFormat, output: W) -> Self {
         let tab = TabWriter::new(output).minwidth(15);
         List {
             runtime,
+            output_format,
             output: Arc::new(Mutex::new(tab)),
         }
     }
@@ -36,13 +48,14 @@ where
 impl<M, W> Command for List<M, W>
 where
     M: 'static + ModuleRuntime + Clone,
-    M::Module: Clone,
+    M::Module: Clone + Serialize,
     M::Config: Display,
     W: 'static + Write + Send,
 {
     type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
 
     fn execute(self) -> Self::Future {
+        let output_format = self.output_format;
         let write = self.output.clone();
         let result = self
             .runtime
@@ -53,18 +66,21 @@ where
                 result.sort_by(|(mod1, _), (mod2, _)| mod1.name().cmp(mod2.name()));
 
                 let mut w = write.lock().unwrap();
-                writeln!(w, "NAME\tSTATUS\tDESCRIPTION\tCONFIG")
-                    .context(ErrorKind::WriteToStdout)?;
-                for (module, state) in result {
-                    writeln!(
-                        w,
-                        "{}\t{}\t{}\t{}",
-                        module.name(),
-                        state.status(),
-                        humanize_state(&state),
-                        module.config(),
-                    )
-                    .context(ErrorKind::WriteToStdout)?;
+                match output_format {
+                    ListOutputFormat::Json => {
+                        let modules: Vec<_> = result.iter().map(|(module, _)| module).collect();
+                        serde_json::to_writer(&mut *w, &modules)
+                            .context(ErrorKind::WriteToStdout)?;
+                        writeln!(w).context(ErrorKind::WriteToStdout)?;
+                    }
+                    ListOutputFormat::JsonLines => {
+                        for (module, _) in &result {
+                            serde_json::to_writer(&mut *w, module)
+                                .context(ErrorKind::WriteToStdout)?;
+                            writeln!(w).context(ErrorKind::WriteToStdout)?;
+                        }
+                    }
+                    ListOutputFormat::Text => write_table(&mut *w, result)?,
                 }
                 w.flush().context(ErrorKind::WriteToStdout)?;
                 Ok(())
@@ -73,7 +89,28 @@ where
     }
 }
 
-fn humanize_state(state: &ModuleRuntimeState) -> String {
+fn write_table<W, M>(w: &mut W, modules: Vec<(M, ModuleRuntimeState)>) -> Result<(), Error>
+where
+    W: Write,
+    M: Module,
+    M::Config: Display,
+{
+    writeln!(w, "NAME\tSTATUS\tDESCRIPTION\tCONFIG").context(ErrorKind::WriteToStdout)?;
+    for (module, state) in modules {
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}",
+            module.name(),
+            state.status(),
+            humanize_state(&state),
+            module.config(),
+        )
+        .context(ErrorKind::WriteToStdout)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn humanize_state(state: &ModuleRuntimeState) -> String {
     match *state.status() {
         ModuleStatus::Unknown => "Unknown".to_string(),
         ModuleStatus::Stopped => state.finished_at().map_or_else(
@@ -16,7 +16,7 @@ use failure::{Fail, ResultExt};
 use futures::Future;
 use url::Url;
 
-use edgelet_core::{LogOptions, LogTail};
+use edgelet_core::LogOptions;
 use edgelet_http_mgmt::ModuleClient;
 
 use iotedge::*;
@@ -83,6 +83,13 @@ fn run() -> Result<(), Error> {
         edgelet_core::version().replace("~", "-")
     );
 
+    let default_iotedged_path = if cfg!(windows) {
+        r"C:\Program Files\iotedge\iotedged.exe"
+    } else {
+        "/usr/bin/iotedged"
+    };
+    let default_ntp_server = "pool.ntp.org:123";
+
     let mut possible_check_id_values: Vec<_> = Check::possible_ids().collect();
     possible_check_id_values.sort();
 
@@ -151,9 +158,7 @@ fn run() -> Result<(), Error> {
                         .value_name("PATH_TO_IOTEDGED")
                         .help("Sets the path of the iotedged binary.")
                         .takes_value(true)
-                        .default_value(
-                            if cfg!(windows) { r"C:\Program Files\iotedge\iotedged.exe" } else { "/usr/bin/iotedged" }
-                        ),
+                        .default_value(default_iotedged_path),
                 )
                 .arg(
                     Arg::with_name("iothub-hostname")
@@ -168,16 +173,16 @@ fn run() -> Result<(), Error> {
                         .value_name("NTP_SERVER")
                         .help("Sets the NTP server to use when checking host local time.")
                         .takes_value(true)
-                        .default_value("pool.ntp.org:123"),
+                        .default_value(default_ntp_server),
                 )
                 .arg(
                     Arg::with_name("output")
                         .long("output")
                         .short("o")
                         .value_name("FORMAT")
-                        .help("Output format. Note that JSON output contains some additional information like OS name, OS version, disk space, etc.")
+                        .help("Output format. Note that JSON output contains some additional information like OS name, OS version, disk space, etc. JUnit output is a JUnit XML report for CI test result reporters.")
                         .takes_value(true)
-                        .possible_values(&["json", "text"])
+                        .possible_values(&["json", "junit", "text"])
                         .default_value("text"),
                 )
                 .arg(
@@ -196,7 +201,169 @@ fn run() -> Result<(), Error> {
                 ),
         )
         .subcommand(SubCommand::with_name("check-list").about("List the checks that are run for 'iotedge check'"))
-        .subcommand(SubCommand::with_name("list").about("List modules"))
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Manage the daemon configuration")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Check the configuration and connectivity of the device without starting the daemon")
+                        .arg(
+                            Arg::with_name("config-file")
+                                .short("c")
+                                .long("config-file")
+                                .value_name("FILE")
+                                .help("Sets daemon configuration file")
+                                .takes_value(true)
+                                .default_value_os(default_config_path.as_os_str()),
+                        )
+                        .arg(
+                            Arg::with_name("iothub-hostname")
+                                .long("iothub-hostname")
+                                .value_name("IOTHUB_HOSTNAME")
+                                .help("Sets the hostname of the Azure IoT Hub that this device would connect to. If using manual provisioning, this does not need to be specified.")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .value_name("FORMAT")
+                                .help("Output format.")
+                                .takes_value(true)
+                                .possible_values(&["json", "junit", "text"])
+                                .default_value("text"),
+                        )
+                        .arg(
+                            Arg::with_name("verbose")
+                                .long("verbose")
+                                .value_name("VERBOSE")
+                                .help("Increases verbosity of output.")
+                                .takes_value(false),
+                        )
+                        .arg(
+                            Arg::with_name("warnings-as-errors")
+                                .long("warnings-as-errors")
+                                .value_name("WARNINGS_AS_ERRORS")
+                                .help("Treats warnings as errors. Thus 'iotedge config check' will exit with non-zero code if it encounters warnings.")
+                                .takes_value(false),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Convert a v1 config.toml to config.yaml. Comments are not carried over.")
+                        .arg(
+                            Arg::with_name("input")
+                                .short("i")
+                                .long("input")
+                                .value_name("FILE")
+                                .help("Sets the config.toml to convert")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Sets the config.yaml to write")
+                                .takes_value(true)
+                                .default_value_os(default_config_path.as_os_str()),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Overwrites the output file if it already exists")
+                                .takes_value(false),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("image")
+                .about("Inspect module images")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("size")
+                        .about("Show how much disk space an image takes up")
+                        .arg(
+                            Arg::with_name("IMAGE")
+                                .help("Sets the image to inspect, as in a module's config")
+                                .required(true)
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("prune")
+                        .about("Remove the images that no module uses")
+                        .arg(
+                            Arg::with_name("keep")
+                                .long("keep")
+                                .value_name("IMAGE")
+                                .help("Sets an image to keep in addition to the images of the deployed modules. Can be repeated.")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List modules")
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("FORMAT")
+                        .help("Output format. 'json' and 'jsonl' list the modules in the format of the management API, as a JSON array or as one JSON object per line.")
+                        .takes_value(true)
+                        .possible_values(&["json", "jsonl", "text"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("provision")
+                .about("Register the device through a DPS symmetric key enrollment group and write its connection string to config.yaml")
+                .arg(
+                    Arg::with_name("config-file")
+                        .short("c")
+                        .long("config-file")
+                        .value_name("FILE")
+                        .help("Sets daemon configuration file")
+                        .takes_value(true)
+                        .default_value_os(default_config_path.as_os_str()),
+                )
+                .arg(
+                    Arg::with_name("dps-endpoint")
+                        .long("dps-endpoint")
+                        .value_name("URL")
+                        .help("Sets the DPS global endpoint")
+                        .takes_value(true)
+                        .default_value("https://global.azure-devices-provisioning.net"),
+                )
+                .arg(
+                    Arg::with_name("scope-id")
+                        .long("scope-id")
+                        .value_name("SCOPE_ID")
+                        .help("Sets the ID scope of the DPS instance")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("registration-id")
+                        .long("registration-id")
+                        .value_name("REGISTRATION_ID")
+                        .help("Sets the registration ID of the device in the enrollment group")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("enrollment-key")
+                        .long("enrollment-key")
+                        .value_name("KEY")
+                        .help("Sets the primary or secondary key of the enrollment group. If not specified, it is read from stdin.")
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("restart")
                 .about("Restart a module")
@@ -244,12 +411,20 @@ fn run() -> Result<(), Error> {
                 .about("Bundles troubleshooting information")
                 .arg(
                     Arg::with_name("output")
-                        .help("Path of output file")
+                        .help("Path of output file [default: support_bundle_<local time>.zip]")
                         .long("output")
                         .short("o")
                         .takes_value(true)
-                        .value_name("FILENAME")
-                        .default_value("support_bundle.zip"),
+                        .value_name("FILENAME"),
+                )
+                .arg(
+                    Arg::with_name("config-file")
+                        .short("c")
+                        .long("config-file")
+                        .value_name("FILE")
+                        .help("Sets daemon configuration file")
+                        .takes_value(true)
+                        .default_value_os(default_config_path.as_os_str()),
                 )
                 .arg(
                     Arg::with_name("since")
@@ -259,6 +434,14 @@ fn run() -> Result<(), Error> {
                         .value_name("DURATION or TIMESTAMP")
                         .default_value("1 day"),
                 )
+                .arg(
+                    Arg::with_name("tail")
+                        .help("Number of lines to include from the end of each module's log")
+                        .long("tail")
+                        .takes_value(true)
+                        .value_name("NUM")
+                        .default_value("1000"),
+                )
                 .arg(
                     Arg::with_name("include-edge-runtime-only")
                         .help("Only include logs from Microsoft-owned Edge modules")
@@ -329,6 +512,7 @@ fn run() -> Result<(), Error> {
                 args.value_of("output")
                     .map(|arg| match arg {
                         "json" => OutputFormat::Json,
+                        "junit" => OutputFormat::JUnit,
                         "text" => OutputFormat::Text,
                         _ => unreachable!(),
                     })
@@ -339,7 +523,117 @@ fn run() -> Result<(), Error> {
             .and_then(Command::execute),
         ),
         ("check-list", _) => Check::print_list(),
-        ("list", _) => tokio_runtime.block_on(List::new(runtime()?, io::stdout()).execute()),
+        ("config", Some(args)) => match args.subcommand() {
+            ("check", Some(args)) => {
+                let dont_run = Check::possible_ids()
+                    .filter(|id| !Check::config_check_ids().contains(id))
+                    .map(ToOwned::to_owned)
+                    .collect();
+                tokio_runtime.block_on(
+                    Check::new(
+                        args.value_of_os("config-file")
+                            .expect("arg has a default value")
+                            .to_os_string()
+                            .into(),
+                        default_container_engine_config_path.to_path_buf(),
+                        default_diagnostics_image_name.clone(),
+                        dont_run,
+                        // The iotedged version check isn't run, so there's no need to look up
+                        // the latest version
+                        Some(edgelet_core::version().to_owned()),
+                        default_iotedged_path.into(),
+                        args.value_of("iothub-hostname").map(ToOwned::to_owned),
+                        default_ntp_server.to_owned(),
+                        args.value_of("output")
+                            .map(|arg| match arg {
+                                "json" => OutputFormat::Json,
+                                "junit" => OutputFormat::JUnit,
+                                "text" => OutputFormat::Text,
+                                _ => unreachable!(),
+                            })
+                            .expect("arg has a default value"),
+                        args.is_present("verbose"),
+                        args.is_present("warnings-as-errors"),
+                    )
+                    .and_then(Command::execute),
+                )
+            }
+            ("import", Some(args)) => tokio_runtime.block_on(
+                ConfigImport::new(
+                    args.value_of_os("input")
+                        .expect("arg is required")
+                        .to_os_string()
+                        .into(),
+                    args.value_of_os("output")
+                        .expect("arg has a default value")
+                        .to_os_string()
+                        .into(),
+                    args.is_present("force"),
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+        },
+        ("image", Some(args)) => match args.subcommand() {
+            ("size", Some(args)) => tokio_runtime.block_on(
+                ImageSize::new(
+                    args.value_of("IMAGE").expect("arg is required").to_string(),
+                    runtime()?,
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            ("prune", Some(args)) => tokio_runtime.block_on(
+                ImagePrune::new(
+                    args.values_of("keep")
+                        .into_iter()
+                        .flatten()
+                        .map(ToOwned::to_owned)
+                        .collect(),
+                    runtime()?,
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+        },
+        ("list", Some(args)) => {
+            let output_format = args
+                .value_of("output")
+                .map(|arg| match arg {
+                    "json" => ListOutputFormat::Json,
+                    "jsonl" => ListOutputFormat::JsonLines,
+                    "text" => ListOutputFormat::Text,
+                    _ => unreachable!(),
+                })
+                .expect("arg has a default value");
+            tokio_runtime.block_on(List::new(runtime()?, output_format, io::stdout()).execute())
+        }
+        ("provision", Some(args)) => {
+            let dps_endpoint = Url::parse(
+                args.value_of("dps-endpoint")
+                    .expect("arg has a default value"),
+            )
+            .context(ErrorKind::Provision("--dps-endpoint is not a valid URL"))?;
+            tokio_runtime.block_on(
+                Provision::new(
+                    dps_endpoint,
+                    args.value_of("scope-id")
+                        .expect("arg is required")
+                        .to_string(),
+                    args.value_of("registration-id")
+                        .expect("arg is required")
+                        .to_string(),
+                    args.value_of("enrollment-key").map(ToOwned::to_owned),
+                    args.value_of_os("config-file")
+                        .expect("arg has a default value")
+                        .to_os_string()
+                        .into(),
+                )
+                .execute(),
+            )
+        }
         ("restart", Some(args)) => tokio_runtime.block_on(
             Restart::new(
                 args.value_of("MODULE").unwrap().to_string(),
@@ -371,15 +665,35 @@ fn run() -> Result<(), Error> {
             tokio_runtime.block_on(Logs::new(id, options, runtime()?).execute())
         }
         ("support-bundle", Some(args)) => {
-            let location = args.value_of_os("output").expect("arg has a default value");
+            let location = args.value_of_os("output").map_or_else(
+                || {
+                    format!(
+                        "support_bundle_{}.zip",
+                        Local::now().format("%Y-%m-%d_%H-%M-%S")
+                    )
+                    .into()
+                },
+                ToOwned::to_owned,
+            );
+            let config_file = args
+                .value_of_os("config-file")
+                .expect("arg has a default value");
             let since = args
                 .value_of("since")
                 .map(|s| parse_since(s))
                 .transpose()?
                 .expect("arg has a default value");
+            let tail = args
+                .value_of("tail")
+                .map(str::parse)
+                .transpose()
+                .map_err(|err: edgelet_core::Error| {
+                    Error::from(err.context(ErrorKind::BadTailParameter))
+                })?
+                .expect("arg has a default value");
             let options = LogOptions::new()
                 .with_follow(false)
-                .with_tail(LogTail::All)
+                .with_tail(tail)
                 .with_since(since);
             let include_ms_only = args.is_present("include-edge-runtime-only");
             let verbose = !args.is_present("quiet");
@@ -387,10 +701,11 @@ fn run() -> Result<(), Error> {
             tokio_runtime.block_on(
                 SupportBundle::new(
                     options,
-                    location.to_owned(),
+                    location,
                     include_ms_only,
                     verbose,
                     iothub_hostname,
+                    config_file.into(),
                     runtime()?,
                 )
                 .execute(),
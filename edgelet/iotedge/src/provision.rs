@@ -0,0 +1,231 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future};
+use regex::{NoExpand, Regex};
+use url::Url;
+
+use dps::registration::{DpsAuthKind, DpsClient};
+use dps::DPS_API_VERSION;
+use edgelet_core::crypto::{
+    Activate, KeyIdentity, MemoryKey, MemoryKeyStore, Sign, Signature, SignatureAlgorithm,
+};
+use edgelet_http::client::Client;
+use edgelet_http::MaybeProxyClient;
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Registers the device with DPS through a symmetric key enrollment group, and switches
+/// config.yaml to manual provisioning with the connection string of the assigned device.
+pub struct Provision {
+    dps_endpoint: Url,
+    scope_id: String,
+    registration_id: String,
+    enrollment_key: Option<String>,
+    config_file: PathBuf,
+}
+
+impl Provision {
+    /// If `enrollment_key` is `None`, it is read from stdin so that it doesn't end up in the
+    /// shell history.
+    pub fn new(
+        dps_endpoint: Url,
+        scope_id: String,
+        registration_id: String,
+        enrollment_key: Option<String>,
+        config_file: PathBuf,
+    ) -> Self {
+        Provision {
+            dps_endpoint,
+            scope_id,
+            registration_id,
+            enrollment_key,
+            config_file,
+        }
+    }
+
+    fn register(self) -> Result<impl Future<Item = (), Error = Error> + Send, Error> {
+        let enrollment_key = match self.enrollment_key {
+            Some(ref enrollment_key) => enrollment_key.clone(),
+            None => prompt("Enrollment group key: ").context(ErrorKind::Provision(
+                "could not read the enrollment group key",
+            ))?,
+        };
+        let device_key = derive_device_key(&enrollment_key, &self.registration_id)?;
+
+        let mut key_store = MemoryKeyStore::new();
+        key_store
+            .activate_identity_key(KeyIdentity::Device, "primary".to_string(), &device_key)
+            .context(ErrorKind::Provision("could not load the device key"))?;
+
+        let proxy = std::env::var("HTTPS_PROXY")
+            .ok()
+            .or_else(|| std::env::var("https_proxy").ok())
+            .map(|proxy| proxy.parse::<hyper::Uri>())
+            .transpose()
+            .context(ErrorKind::Provision("HTTPS_PROXY is not a valid URI"))?;
+        let hyper_client = MaybeProxyClient::new(proxy, None, None)
+            .context(ErrorKind::Provision("could not create HTTP client"))?;
+        let client = Client::new(
+            hyper_client,
+            None,
+            DPS_API_VERSION.to_string(),
+            self.dps_endpoint.clone(),
+        )
+        .context(ErrorKind::Provision("could not create DPS client"))?;
+        let dps = DpsClient::new(
+            client,
+            self.scope_id.clone(),
+            self.registration_id.clone(),
+            DpsAuthKind::SymmetricKey,
+            key_store,
+        )
+        .context(ErrorKind::Provision("could not create DPS client"))?;
+
+        let config_file = self.config_file;
+        let registration = dps
+            .register()
+            .map_err(|err| Error::from(err.context(ErrorKind::Provision("DPS registration failed"))))
+            .and_then(move |(device_id, hub_name, _)| {
+                let connection_string = format!(
+                    "HostName={};DeviceId={};SharedAccessKey={}",
+                    hub_name,
+                    device_id,
+                    base64::encode(&device_key)
+                );
+
+                println!("Registered device {} in IoT Hub {}", device_id, hub_name);
+                write_connection_string(&config_file, &connection_string)?;
+                println!(
+                    "Wrote the device connection string to {}. Restart the IoT Edge daemon to use it.",
+                    config_file.display()
+                );
+                Ok(())
+            });
+        Ok(registration)
+    }
+}
+
+impl Command for Provision {
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        match self.register() {
+            Ok(registration) => Box::new(registration),
+            Err(err) => Box::new(future::err(err)),
+        }
+    }
+}
+
+/// Derives the key of a device in a symmetric key enrollment group, which is the HMAC-SHA256 of
+/// the registration ID keyed with the group key.
+fn derive_device_key(enrollment_key: &str, registration_id: &str) -> Result<Vec<u8>, Error> {
+    let enrollment_key = base64::decode(enrollment_key.trim()).context(ErrorKind::Provision(
+        "the enrollment group key is not valid base64",
+    ))?;
+    let device_key = MemoryKey::new(enrollment_key)
+        .sign(SignatureAlgorithm::HMACSHA256, registration_id.as_bytes())
+        .context(ErrorKind::Provision("could not derive the device key"))?;
+    Ok(device_key.as_bytes().to_vec())
+}
+
+fn write_connection_string(config_file: &Path, connection_string: &str) -> Result<(), Error> {
+    let config = fs::read_to_string(config_file)
+        .context(ErrorKind::Provision("could not read config.yaml"))?;
+    let config = set_device_connection_string(&config, connection_string).ok_or_else(|| {
+        Error::from(ErrorKind::Provision(
+            "config.yaml is not set up for manual provisioning with a device connection string. \
+             Set provisioning.source to \"manual\" and uncomment provisioning.device_connection_string, \
+             then try again.",
+        ))
+    })?;
+    fs::write(config_file, config).context(ErrorKind::Provision("could not write config.yaml"))?;
+    Ok(())
+}
+
+/// Replaces the value of `provisioning.device_connection_string` in the text of config.yaml, so
+/// that everything else in the file, comments included, is left as is. Returns `None` if the file
+/// doesn't use manual provisioning with a connection string.
+fn set_device_connection_string(config: &str, connection_string: &str) -> Option<String> {
+    let source =
+        Regex::new(r#"(?m)^[ \t]+source:[ \t]*"?manual"?[ \t]*$"#).expect("regex is valid");
+    let device_connection_string =
+        Regex::new(r#"(?m)^([ \t]+device_connection_string:).*$"#).expect("regex is valid");
+
+    if !source.is_match(config) || device_connection_string.find_iter(config).count() != 1 {
+        return None;
+    }
+
+    let replacement = format!(
+        "{} \"{}\"",
+        device_connection_string
+            .captures(config)
+            .expect("match was found")
+            .get(1)
+            .expect("group 1 always participates in the match")
+            .as_str(),
+        connection_string
+    );
+    Some(
+        device_connection_string
+            .replace(config, NoExpand(&replacement))
+            .into_owned(),
+    )
+}
+
+fn prompt(message: &str) -> io::Result<String> {
+    print!("{}", message);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_key_is_derived_from_registration_id() {
+        // Computed with
+        //   echo -n device1 | openssl dgst -sha256 -mac HMAC -macopt key:key -binary | base64
+        let key = derive_device_key("a2V5", "device1").unwrap();
+
+        assert_eq!(
+            "cm27PgwyiW8609W2sN1V/5kiz6eBJ2S8BKGWK1Rxx5I=",
+            base64::encode(&key)
+        );
+    }
+
+    #[test]
+    fn invalid_enrollment_key_fails() {
+        derive_device_key("not base64!", "device1").unwrap_err();
+    }
+
+    #[test]
+    fn connection_string_replaces_placeholder() {
+        let config = "provisioning:\n  source: \"manual\"\n  device_connection_string: \"<ADD DEVICE CONNECTION STRING HERE>\"\n\n# provisioning:\n#   device_connection_string: \"\"\n";
+
+        let config =
+            set_device_connection_string(config, "HostName=h;DeviceId=d;SharedAccessKey=k")
+                .unwrap();
+
+        assert_eq!(
+            "provisioning:\n  source: \"manual\"\n  device_connection_string: \"HostName=h;DeviceId=d;SharedAccessKey=k\"\n\n# provisioning:\n#   device_connection_string: \"\"\n",
+            config
+        );
+    }
+
+    #[test]
+    fn dps_config_is_not_changed() {
+        let config = "provisioning:\n  source: \"dps\"\n  global_endpoint: \"https://global.azure-devices-provisioning.net\"\n";
+
+        assert_eq!(None, set_device_connection_string(config, "HostName=h"));
+    }
+}
@@ -3,19 +3,26 @@
 use std::env;
 use std::error::Error as StdError;
 use std::ffi::OsString;
-use std::fs::File;
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::process::Command as ShellCommand;
 
 use chrono::{DateTime, Local, NaiveDateTime, Utc};
 use failure::Fail;
 use futures::{Future, Stream};
+use regex::Regex;
 use tokio::prelude::*;
 use zip;
 
-use edgelet_core::{LogOptions, LogTail, Module, ModuleRuntime};
+use edgelet_core::{
+    AttestationMethod, LogOptions, LogTail, ManualAuthMethod, Module, ModuleRuntime,
+    ProvisioningType, RuntimeSettings,
+};
+use edgelet_docker::Settings;
 
+use crate::check::CertificateValidity;
 use crate::error::{Error, ErrorKind};
+use crate::list::humanize_state;
 use crate::logs::pull_logs;
 use crate::Command;
 
@@ -26,6 +33,7 @@ pub struct SupportBundle<M> {
     include_ms_only: bool,
     verbose: bool,
     iothub_hostname: Option<String>,
+    config_file: PathBuf,
 }
 
 struct BundleState<M> {
@@ -35,6 +43,10 @@ struct BundleState<M> {
     include_ms_only: bool,
     verbose: bool,
     iothub_hostname: Option<String>,
+    config_file: PathBuf,
+    /// Whether the daemon could be reached the last time the modules were listed. If it couldn't,
+    /// module logs are read from docker directly.
+    runtime_available: bool,
     file_options: zip::write::FileOptions,
     zip_writer: zip::ZipWriter<File>,
 }
@@ -48,12 +60,16 @@ where
     fn execute(self) -> Self::Future {
         println!("Making support bundle");
         let result = future::result(self.make_state())
+            .and_then(SupportBundle::write_module_list_to_file)
             .and_then(SupportBundle::write_all_logs)
             .and_then(SupportBundle::write_edgelet_log_to_file)
             .and_then(SupportBundle::write_docker_log_to_file)
             .and_then(SupportBundle::write_check_to_file)
             .and_then(SupportBundle::write_all_inspects)
             .and_then(SupportBundle::write_all_network_inspects)
+            .and_then(SupportBundle::write_config_to_file)
+            .and_then(SupportBundle::write_certificates_to_file)
+            .and_then(SupportBundle::write_system_info_to_file)
             .map(|state| {
                 println!(
                     "Created support bundle at {}",
@@ -79,6 +95,7 @@ where
         include_ms_only: bool,
         verbose: bool,
         iothub_hostname: Option<String>,
+        config_file: PathBuf,
         runtime: M,
     ) -> Self {
         SupportBundle {
@@ -88,6 +105,7 @@ where
             include_ms_only,
             verbose,
             iothub_hostname,
+            config_file,
         }
     }
 
@@ -108,11 +126,62 @@ where
             include_ms_only: self.include_ms_only,
             verbose: self.verbose,
             iothub_hostname: self.iothub_hostname,
+            config_file: self.config_file,
+            runtime_available: true,
             file_options,
             zip_writer,
         })
     }
 
+    fn write_module_list_to_file(
+        state: BundleState<M>,
+    ) -> impl Future<Item = BundleState<M>, Error = Error> {
+        state.print_verbose("Listing modules");
+
+        state
+            .runtime
+            .list_with_details()
+            .collect()
+            .then(|modules| -> Result<_, Error> {
+            let mut state = state;
+            let (file_name, output) = match modules {
+                Ok(mut modules) => {
+                    modules.sort_by(|(mod1, _), (mod2, _)| mod1.name().cmp(mod2.name()));
+
+                    let mut output = "NAME\tSTATUS\tDESCRIPTION\n".to_owned();
+                    for (module, module_state) in modules {
+                        output.push_str(&format!(
+                            "{}\t{}\t{}\n",
+                            module.name(),
+                            module_state.status(),
+                            humanize_state(&module_state),
+                        ));
+                    }
+                    ("modules.txt", output.into_bytes())
+                }
+                Err(err) => {
+                    println!(
+                        "Could not list modules with the IoT Edge daemon, listing the containers with docker instead.\nError message: {}",
+                        err
+                    );
+                    let mut ps = docker_command();
+                    ps.args(&["ps", "-a"]);
+                    match ps.output() {
+                        Ok(result) if result.status.success() => {
+                            ("modules_docker.txt", result.stdout)
+                        }
+                        Ok(result) => ("modules_err.txt", result.stderr),
+                        Err(err) => ("modules_err.txt", err.to_string().into_bytes()),
+                    }
+                }
+            };
+
+            state.write_file(file_name, &output)?;
+            state.print_verbose("Wrote module list to file");
+            Ok(state)
+        })
+    }
+
     fn write_all_logs(state: BundleState<M>) -> impl Future<Item = BundleState<M>, Error = Error> {
         /* Print status */
         if state.verbose {
@@ -162,21 +231,53 @@ where
     ) -> impl Future<Item = (Vec<String>, BundleState<M>), Error = Error> {
         const MS_MODULES: &[&str] = &["edgeAgent", "edgeHub"];
 
-        let include_ms_only = state.include_ms_only;
-
         state
             .runtime
             .list_with_details()
-            .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
             .map(|(module, _s)| module.name().to_owned())
-            .filter(move |name| !include_ms_only || MS_MODULES.iter().any(|ms| ms == name))
             .collect()
-            .map(|names| (names, state))
+            .then(|names| -> Result<_, Error> {
+                let mut state = state;
+                let names = match names {
+                    Ok(names) => {
+                        state.runtime_available = true;
+                        names
+                    }
+                    Err(err) => {
+                        println!(
+                            "Could not list modules with the IoT Edge daemon, reading them from docker instead.\nError message: {}",
+                            err
+                        );
+                        state.runtime_available = false;
+                        docker_container_names()
+                    }
+                };
+
+                let include_ms_only = state.include_ms_only;
+                let names: Vec<String> = names
+                    .into_iter()
+                    .filter(|name| !include_ms_only || MS_MODULES.iter().any(|ms| ms == name))
+                    .collect();
+                Ok((names, state))
+            })
     }
 
     fn write_log_to_file(
         state: BundleState<M>,
         module_name: String,
+    ) -> impl Future<Item = BundleState<M>, Error = Error> {
+        if state.runtime_available {
+            future::Either::A(SupportBundle::write_runtime_log_to_file(state, module_name))
+        } else {
+            future::Either::B(future::result(
+                SupportBundle::write_docker_module_log_to_file(state, &module_name),
+            ))
+        }
+    }
+
+    fn write_runtime_log_to_file(
+        state: BundleState<M>,
+        module_name: String,
     ) -> impl Future<Item = BundleState<M>, Error = Error> {
         state.print_verbose(&format!("Writing {} logs to file", module_name));
         let BundleState {
@@ -186,6 +287,8 @@ where
             include_ms_only,
             verbose,
             iothub_hostname,
+            config_file,
+            runtime_available,
             file_options,
             mut zip_writer,
         } = state;
@@ -204,6 +307,8 @@ where
                         include_ms_only,
                         verbose,
                         iothub_hostname,
+                        config_file,
+                        runtime_available,
                         file_options,
                         zip_writer: zw,
                     };
@@ -213,6 +318,40 @@ where
             })
     }
 
+    /// Used when the daemon isn't running, since the logs of the module containers can still be
+    /// read from docker.
+    fn write_docker_module_log_to_file(
+        mut state: BundleState<M>,
+        module_name: &str,
+    ) -> Result<BundleState<M>, Error> {
+        state.print_verbose(&format!("Writing {} logs to file from docker", module_name));
+
+        let mut logs = docker_command();
+        logs.arg("logs")
+            .args(&["--since", &state.log_options.since().to_string()]);
+        if let LogTail::Num(tail) = state.log_options.tail() {
+            logs.args(&["--tail", &tail.to_string()]);
+        }
+        logs.arg(module_name);
+
+        let (file_name, output) = match logs.output() {
+            // docker passes on the container's stdout and stderr as they are
+            Ok(ref result) if result.status.success() => (
+                format!("logs/{}_log.txt", module_name),
+                [&result.stdout[..], &result.stderr[..]].concat(),
+            ),
+            Ok(result) => (format!("logs/{}_err.txt", module_name), result.stderr),
+            Err(err) => (
+                format!("logs/{}_err_docker.txt", module_name),
+                err.to_string().into_bytes(),
+            ),
+        };
+
+        state.write_file(&file_name, &output)?;
+        state.print_verbose(&format!("Wrote {} logs to file", module_name));
+        Ok(state)
+    }
+
     fn write_edgelet_log_to_file(mut state: BundleState<M>) -> Result<BundleState<M>, Error> {
         state.print_verbose("Getting system logs for iotedged");
         let since_time: DateTime<Utc> = DateTime::from_utc(
@@ -480,9 +619,92 @@ where
         state.print_verbose(&format!("Got docker network inspect for {}", network_name));
         Ok(state)
     }
+
+    fn write_config_to_file(mut state: BundleState<M>) -> Result<BundleState<M>, Error> {
+        state.print_verbose(&format!(
+            "Copying {} without secrets",
+            state.config_file.display()
+        ));
+
+        let (file_name, output) = match fs::read_to_string(&state.config_file) {
+            Ok(config) => ("config.yaml", redact_config(&config)),
+            Err(err) => {
+                println!(
+                    "Could not read {}. Including error in bundle.\nError message: {}",
+                    state.config_file.display(),
+                    err
+                );
+                ("config_err.txt", err.to_string())
+            }
+        };
+
+        state.write_file(file_name, output.as_bytes())?;
+        state.print_verbose("Wrote config to file");
+        Ok(state)
+    }
+
+    fn write_certificates_to_file(mut state: BundleState<M>) -> Result<BundleState<M>, Error> {
+        state.print_verbose("Reading certificate expiry");
+
+        let output = match Settings::new(&state.config_file) {
+            Ok(settings) => certificates(&settings)
+                .into_iter()
+                .map(
+                    |(name, path)| match CertificateValidity::parse(name, path) {
+                        Ok(validity) => validity.summary(),
+                        Err(err) => err.to_string(),
+                    },
+                )
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(err) => format!("Could not read {}: {}", state.config_file.display(), err),
+        };
+
+        state.write_file("certificates.txt", output.as_bytes())?;
+        state.print_verbose("Wrote certificate expiry to file");
+        Ok(state)
+    }
+
+    fn write_system_info_to_file(mut state: BundleState<M>) -> Result<BundleState<M>, Error> {
+        state.print_verbose("Getting system information");
+
+        let mut output = String::new();
+
+        #[cfg(unix)]
+        {
+            output.push_str(&command_output(ShellCommand::new("uname").arg("-a")));
+            output.push_str(
+                &fs::read_to_string("/etc/os-release")
+                    .unwrap_or_else(|err| format!("Could not read /etc/os-release: {}\n", err)),
+            );
+        }
+
+        #[cfg(windows)]
+        output.push_str(&command_output(
+            ShellCommand::new("cmd").args(&["/c", "ver"]),
+        ));
+
+        output.push('\n');
+        output.push_str(&command_output(docker_command().arg("version")));
+
+        state.write_file("system_info.txt", output.as_bytes())?;
+        state.print_verbose("Wrote system information to file");
+        Ok(state)
+    }
 }
 
 impl<M> BundleState<M> {
+    fn write_file(&mut self, file_name: &str, contents: &[u8]) -> Result<(), Error> {
+        self.zip_writer
+            .start_file_from_path(&Path::new(file_name), self.file_options)
+            .map_err(|err| Error::from(err.context(ErrorKind::SupportBundle)))?;
+
+        self.zip_writer
+            .write_all(contents)
+            .map_err(|err| Error::from(err.context(ErrorKind::SupportBundle)))?;
+
+        Ok(())
+    }
     fn print_verbose(&self, message: &str) {
         if self.verbose {
             println!("{}", message);
@@ -490,6 +712,115 @@ impl<M> BundleState<M> {
     }
 }
 
+fn docker_command() -> ShellCommand {
+    let mut command = ShellCommand::new("docker");
+
+    /***
+     * Note: just like inspect, this assumes using windows containers on a windows machine.
+     */
+    #[cfg(windows)]
+    command.args(&["-H", "npipe:////./pipe/iotedge_moby_engine"]);
+
+    command
+}
+
+fn docker_container_names() -> Vec<String> {
+    let mut ps = docker_command();
+    ps.args(&["ps", "-a", "--format", "{{.Names}}"]);
+
+    match ps.output() {
+        Ok(ref result) if result.status.success() => String::from_utf8_lossy(&result.stdout)
+            .lines()
+            .map(String::from)
+            .collect(),
+        Ok(result) => {
+            println!(
+                "Could not list containers: {}",
+                String::from_utf8_lossy(&result.stderr)
+            );
+            vec![]
+        }
+        Err(err) => {
+            println!("Could not list containers: {}", err);
+            vec![]
+        }
+    }
+}
+
+/// Runs `command` and returns its output, or the error if it couldn't be run.
+fn command_output(command: &mut ShellCommand) -> String {
+    match command.output() {
+        Ok(result) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&result.stdout),
+            String::from_utf8_lossy(&result.stderr)
+        ),
+        Err(err) => format!("Could not run {:?}: {}\n", command, err),
+    }
+}
+
+/// Masks the device connection string, symmetric keys, shared access keys, the SCEP challenge
+/// password and the PKCS#11 and FIDO2 PINs in the text of config.yaml.
+fn redact_config(config: &str) -> String {
+    let secret_setting =
+        Regex::new(r#"(?m)^([ \t]*(?:device_connection_string|symmetric_key|scep_challenge_password|pin):)[ \t]*("[^"\r\n]*"|'[^'\r\n]*'|[^ \t#\r\n]*)"#)
+            .expect("regex is valid");
+    let shared_access_key = Regex::new(r#"SharedAccessKey=[^;"'\s]*"#).expect("regex is valid");
+
+    let config = secret_setting.replace_all(config, r#"$1 "<redacted>""#);
+    shared_access_key
+        .replace_all(&config, "SharedAccessKey=<redacted>")
+        .into_owned()
+}
+
+/// The certificates named in config.yaml, and those created by the daemon in its home directory.
+fn certificates(settings: &Settings) -> Vec<(String, PathBuf)> {
+    let mut certificates = vec![];
+
+    if let Some(device_cert) = settings.certificates().device_cert() {
+        if let Ok(path) = device_cert.device_ca_cert() {
+            certificates.push(("Device CA certificate".to_owned(), path));
+        }
+        if let Ok(path) = device_cert.trusted_ca_certs() {
+            certificates.push(("Trusted CA certificates".to_owned(), path));
+        }
+    }
+
+    match settings.provisioning().provisioning_type() {
+        ProvisioningType::Manual(manual) => {
+            if let ManualAuthMethod::X509(x509) = manual.authentication_method() {
+                if let Ok(path) = x509.identity_cert() {
+                    certificates.push((
+                        "Manual authentication identity certificate".to_owned(),
+                        path,
+                    ));
+                }
+            }
+        }
+        ProvisioningType::Dps(dps) => {
+            if let AttestationMethod::X509(x509) = dps.attestation() {
+                if let Ok(path) = x509.identity_cert() {
+                    certificates.push(("DPS identity certificate".to_owned(), path));
+                }
+            }
+        }
+        ProvisioningType::External(_) => (),
+    }
+
+    if let Ok(entries) = fs::read_dir(settings.homedir().join("hsm").join("certs")) {
+        let mut generated: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "pem"))
+            .collect();
+        generated.sort();
+        for path in generated {
+            certificates.push(("Certificate created by iotedged".to_owned(), path));
+        }
+    }
+
+    certificates
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -524,6 +855,12 @@ mod tests {
             .to_str()
             .unwrap()
             .to_owned();
+        let config_file = tmp_dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            "provisioning:\n  source: \"manual\"\n  device_connection_string: \"HostName=h;DeviceId=d;SharedAccessKey=a2V5\"\n",
+        )
+        .unwrap();
 
         let bundle = SupportBundle::new(
             LogOptions::default(),
@@ -531,6 +868,7 @@ mod tests {
             false,
             false,
             None,
+            config_file.clone(),
             runtime,
         );
 
@@ -592,6 +930,17 @@ mod tests {
         // expect check
         File::open(PathBuf::from(&extract_path).join("check.json")).unwrap();
 
+        // expect module list, system info and certificate expiry
+        let modules = fs::read_to_string(PathBuf::from(&extract_path).join("modules.txt")).unwrap();
+        assert!(modules.contains(module_name));
+        File::open(PathBuf::from(&extract_path).join("system_info.txt")).unwrap();
+        File::open(PathBuf::from(&extract_path).join("certificates.txt")).unwrap();
+
+        // expect config without secrets
+        let config = fs::read_to_string(PathBuf::from(&extract_path).join("config.yaml")).unwrap();
+        assert!(config.contains("source: \"manual\""));
+        assert!(!config.contains("a2V5"));
+
         // expect network inspect
         let network_in_inspect = Regex::new(r".*\.json").unwrap();
         assert!(fs::read_dir(PathBuf::from(&extract_path).join("network"))
@@ -634,12 +983,14 @@ mod tests {
             .to_str()
             .unwrap()
             .to_owned();
+        let config_file = tmp_dir.path().join("config.yaml");
         let bundle = SupportBundle::new(
             LogOptions::default(),
             OsString::from(file_path.to_owned()),
             false,
             true,
             None,
+            config_file.clone(),
             runtime,
         );
 
@@ -661,6 +1012,7 @@ mod tests {
             false,
             true,
             None,
+            config_file,
             runtime,
         );
 
@@ -685,6 +1037,7 @@ mod tests {
             .to_str()
             .unwrap()
             .to_owned();
+        let config_file = tmp_dir.path().join("config.yaml");
 
         let bundle = SupportBundle::new(
             LogOptions::default(),
@@ -692,6 +1045,7 @@ mod tests {
             false,
             true,
             None,
+            config_file,
             runtime,
         );
 
@@ -700,6 +1054,44 @@ mod tests {
         File::open(file_path).unwrap();
     }
 
+    #[test]
+    fn redact_config_masks_secrets() {
+        let config = r##"provisioning:
+  source: "dps"
+  global_endpoint: "https://global.azure-devices-provisioning.net"
+  attestation:
+    method: "symmetric_key"
+    symmetric_key: "a2V5" # the group key
+    pin: 1234
+# provisioning:
+#   device_connection_string: "HostName=h;DeviceId=d;SharedAccessKey=a2V5"
+certificates:
+  scep_url: "https://scep.example.com/scep"
+  scep_challenge_password: 'hunter2'
+pkcs11:
+  pin: "5678"
+"##;
+
+        assert_eq!(
+            r##"provisioning:
+  source: "dps"
+  global_endpoint: "https://global.azure-devices-provisioning.net"
+  attestation:
+    method: "symmetric_key"
+    symmetric_key: "<redacted>" # the group key
+    pin: "<redacted>"
+# provisioning:
+#   device_connection_string: "HostName=h;DeviceId=d;SharedAccessKey=<redacted>"
+certificates:
+  scep_url: "https://scep.example.com/scep"
+  scep_challenge_password: "<redacted>"
+pkcs11:
+  pin: "<redacted>"
+"##,
+            redact_config(config)
+        );
+    }
+
     fn make_runtime(module_name: &str) -> TestRuntime<Error, TestSettings> {
         let logs = vec![
             &[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0d, b'R', b'o'][..],
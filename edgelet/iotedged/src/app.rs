@@ -1,17 +1,21 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::ffi::{OsStr, OsString};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{crate_authors, crate_description, crate_name, App, Arg};
 use failure::ResultExt;
-use log::info;
+use log::{error, info};
 
-use edgelet_core;
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+use edgelet_core::RuntimeType;
+use edgelet_core::{self, RuntimeSettings};
 #[cfg(feature = "runtime-docker")]
 use edgelet_docker::Settings;
 #[cfg(feature = "runtime-kubernetes")]
 use edgelet_kube::Settings;
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+use edgelet_utils::YamlFileSource;
 
 use crate::error::{Error, ErrorKind, InitializeErrorReason};
 use crate::logging;
@@ -47,7 +51,16 @@ fn create_app(default_config_file: &OsStr) -> App<'_, '_> {
     }
 }
 
-fn init_common(running_as_windows_service: bool) -> Result<Settings, Error> {
+/// The settings of the module runtime that the config file selects with `runtime`.
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+pub enum Runtime {
+    Docker(Settings),
+    Podman(Settings),
+    Runc(edgelet_runc::Settings),
+}
+
+/// Initializes logging and returns the path of the config file.
+fn init_common(running_as_windows_service: bool) -> PathBuf {
     let default_config_file = if cfg!(windows) {
         let program_data: PathBuf =
             std::env::var_os("PROGRAMDATA").map_or_else(|| r"C:\ProgramData".into(), Into::into);
@@ -87,22 +100,101 @@ fn init_common(running_as_windows_service: bool) -> Result<Settings, Error> {
 
     info!("Using config file: {}", config_file.display());
 
-    let settings = Settings::new(&config_file)
-        .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
+    config_file
+}
+
+fn validate_settings<S>(settings: S) -> Result<S, Error>
+where
+    S: RuntimeSettings,
+{
+    // Report every problem at once rather than failing on them one startup at a time
+    let errors = edgelet_core::validate_config(&settings);
+    if !errors.is_empty() {
+        for err in &errors {
+            error!("Invalid setting {}", err);
+        }
+        return Err(Error::from(ErrorKind::Initialize(
+            InitializeErrorReason::InvalidSettings,
+        )));
+    }
 
     Ok(settings)
 }
 
+/// Reads which module runtime the config file at `config_file` selects, so that the daemon knows
+/// which settings to load. Docker is used if the file doesn't select one.
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+fn runtime_type(config_file: &Path) -> Result<RuntimeType, config::ConfigError> {
+    let mut config = config::Config::default();
+    config.merge(YamlFileSource::File(config_file.into()))?;
+    config.merge(config::Environment::with_prefix("iotedge"))?;
+
+    match config.get("runtime") {
+        Ok(runtime_type) => Ok(runtime_type),
+        Err(config::ConfigError::NotFound(_)) => Ok(RuntimeType::default()),
+        Err(err) => Err(err),
+    }
+}
+
+fn load_settings(config_file: &Path) -> Result<Settings, Error> {
+    let settings = Settings::new(config_file)
+        .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
+    validate_settings(settings)
+}
+
+#[cfg(not(all(target_os = "linux", feature = "runtime-docker")))]
 pub fn init() -> Result<Settings, Error> {
-    init_common(false)
+    load_settings(&init_common(false))
+}
+
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+pub fn init() -> Result<Runtime, Error> {
+    let config_file = init_common(false);
+
+    let runtime_type = runtime_type(&config_file)
+        .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
+    match runtime_type {
+        RuntimeType::Docker => Ok(Runtime::Docker(load_settings(&config_file)?)),
+        RuntimeType::Podman => {
+            info!("Using the Podman module runtime");
+            Ok(Runtime::Podman(load_settings(&config_file)?))
+        }
+        RuntimeType::Runc => {
+            info!("Using the runc module runtime");
+
+            let settings = edgelet_runc::Settings::new(&config_file)
+                .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
+            Ok(Runtime::Runc(validate_settings(settings)?))
+        }
+    }
 }
 
 #[cfg(windows)]
 pub fn init_win_svc() -> Result<Settings, Error> {
-    init_common(true)
+    load_settings(&init_common(true))
 }
 
 #[cfg(windows)]
 pub fn init_win_svc_logging() {
     logging::init_win_log();
 }
+
+#[cfg(all(test, target_os = "linux", feature = "runtime-docker"))]
+mod tests {
+    use super::*;
+
+    static DOCKER_SETTINGS: &str = "test/linux/sample_settings1.yaml";
+    static RUNC_SETTINGS: &str = "../edgelet-runc/test/sample_settings.yaml";
+
+    #[test]
+    fn runtime_type_defaults_to_docker() {
+        assert_eq!(
+            RuntimeType::Docker,
+            runtime_type(Path::new(DOCKER_SETTINGS)).unwrap()
+        );
+        assert_eq!(
+            RuntimeType::Runc,
+            runtime_type(Path::new(RUNC_SETTINGS)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use failure::{Fail, ResultExt};
+use futures::{Future, Stream};
+use log::{info, warn};
+use tokio::timer::Interval;
+
+use edgelet_core::crypto::{Certificate, CreateCertificate, GetIssuerAlias, IOTEDGED_CA_ALIAS};
+use edgelet_core::{CertificateIssuer, CertificateProperties, CertificateType, Certificates};
+
+use crate::error::{Error, ErrorKind, InitializeErrorReason};
+
+const IOTEDGED_COMMONNAME: &str = "iotedged workload ca";
+
+// 5 mins
+const IOTEDGED_MIN_EXPIRATION_DURATION: i64 = 5 * 60;
+
+/// How often `schedule_renewal` checks whether the Edge CA needs to be regenerated.
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Issues the Edge CA certificate from the device CA. The Edge CA signs all workload
+/// certificates.
+///
+/// Without a configured validity, the Edge CA is valid for as long as the device CA, and is only
+/// regenerated when the daemon is reconfigured. Otherwise it is regenerated once less than
+/// `renewal_threshold_percent` of its validity remains.
+#[derive(Clone)]
+pub struct EdgeCaManager<C> {
+    crypto: C,
+    validity_in_secs: Option<u64>,
+    renewal_threshold_percent: u8,
+}
+
+impl<C> EdgeCaManager<C>
+where
+    C: CreateCertificate + GetIssuerAlias,
+{
+    pub fn new(crypto: C, certificates: &Certificates) -> Self {
+        EdgeCaManager {
+            crypto,
+            validity_in_secs: certificates.edge_ca_validity_seconds(),
+            renewal_threshold_percent: certificates.renewal_threshold_percent(),
+        }
+    }
+
+    /// Issues the Edge CA, unless a valid one that doesn't need to be regenerated yet exists.
+    pub fn prepare(&self) -> Result<(), Error> {
+        if self.needs_renewal(Utc::now())? {
+            self.regenerate()
+        } else {
+            self.issue()
+        }
+    }
+
+    /// Replaces the Edge CA with a newly issued one.
+    pub fn regenerate(&self) -> Result<(), Error> {
+        // The certificate is destroyed first, since creating a certificate with the alias of an
+        // existing one returns the existing certificate.
+        self.crypto
+            .destroy_certificate(IOTEDGED_CA_ALIAS.to_string())
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::DestroyWorkloadCa,
+            ))?;
+        self.issue()
+    }
+
+    /// Checks every hour whether the Edge CA needs to be regenerated, and if so, regenerates it.
+    /// Workload certificates issued afterwards are signed by the new Edge CA.
+    ///
+    /// The returned future only completes if the timer fails. A failed renewal is retried at the
+    /// next check.
+    pub fn schedule_renewal(self) -> impl Future<Item = (), Error = Error> {
+        Interval::new(
+            Instant::now() + RENEWAL_CHECK_INTERVAL,
+            RENEWAL_CHECK_INTERVAL,
+        )
+        .map_err(|err| Error::from(err.context(ErrorKind::EdgeCaRenewal)))
+        .for_each(move |_| {
+            let renewal = self.needs_renewal(Utc::now()).and_then(|renew| {
+                if renew {
+                    self.regenerate()?;
+                    info!("Regenerated the Edge CA certificate");
+                }
+                Ok(())
+            });
+            if let Err(err) = renewal {
+                warn!("Could not regenerate the Edge CA certificate: {}", err);
+            }
+            Ok(())
+        })
+    }
+
+    fn issue(&self) -> Result<(), Error> {
+        let issuer_validity = self.issuer_validity()?;
+        info!("Edge issuer CA expiration date: {:?}", issuer_validity);
+
+        let diff = issuer_validity.timestamp() - Utc::now().timestamp();
+
+        if diff > IOTEDGED_MIN_EXPIRATION_DURATION {
+            #[allow(clippy::cast_sign_loss)]
+            let issuer_remaining = diff as u64;
+            let validity = self
+                .validity_in_secs
+                .map_or(issuer_remaining, |validity| validity.min(issuer_remaining));
+
+            let edgelet_ca_props = CertificateProperties::new(
+                validity,
+                IOTEDGED_COMMONNAME.to_string(),
+                CertificateType::Ca,
+                IOTEDGED_CA_ALIAS.to_string(),
+            )
+            .with_issuer(CertificateIssuer::DeviceCa);
+
+            self.crypto
+                .create_certificate(&edgelet_ca_props)
+                .context(ErrorKind::Initialize(
+                    InitializeErrorReason::PrepareWorkloadCa,
+                ))?;
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::Initialize(
+                InitializeErrorReason::IssuerCAExpiration,
+            )))
+        }
+    }
+
+    fn needs_renewal(&self, now: DateTime<Utc>) -> Result<bool, Error> {
+        let validity_in_secs = match self.validity_in_secs {
+            Some(validity_in_secs) => validity_in_secs,
+            None => return Ok(false),
+        };
+
+        let edge_ca_validity = match self
+            .crypto
+            .get_certificate(IOTEDGED_CA_ALIAS.to_string())
+            .and_then(|cert| cert.get_valid_to())
+        {
+            Ok(valid_to) => valid_to,
+            // There is no Edge CA to keep.
+            Err(_) => return Ok(true),
+        };
+
+        Ok(needs_renewal(
+            edge_ca_validity,
+            self.issuer_validity()?,
+            validity_in_secs,
+            self.renewal_threshold_percent,
+            now,
+        ))
+    }
+
+    fn issuer_validity(&self) -> Result<DateTime<Utc>, Error> {
+        let issuer_alias = self
+            .crypto
+            .get_issuer_alias(CertificateIssuer::DeviceCa)
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::PrepareWorkloadCa,
+            ))?;
+
+        let issuer_ca =
+            self.crypto
+                .get_certificate(issuer_alias)
+                .context(ErrorKind::Initialize(
+                    InitializeErrorReason::PrepareWorkloadCa,
+                ))?;
+
+        let issuer_validity = issuer_ca.get_valid_to().context(ErrorKind::Initialize(
+            InitializeErrorReason::PrepareWorkloadCa,
+        ))?;
+        Ok(issuer_validity)
+    }
+}
+
+/// An Edge CA can't outlive the device CA, so one that already expires with the device CA isn't
+/// regenerated, since the new one wouldn't be valid for any longer.
+fn needs_renewal(
+    edge_ca_validity: DateTime<Utc>,
+    issuer_validity: DateTime<Utc>,
+    validity_in_secs: u64,
+    renewal_threshold_percent: u8,
+    now: DateTime<Utc>,
+) -> bool {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let remaining = (edge_ca_validity - now).num_seconds().max(0) as u64;
+    let expires_before_issuer = issuer_validity - edge_ca_validity
+        > chrono::Duration::from_std(RENEWAL_CHECK_INTERVAL)
+            .expect("renewal check interval is out of range");
+
+    expires_before_issuer
+        && remaining * 100 < validity_in_secs * u64::from(renewal_threshold_percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Duration;
+
+    #[test]
+    fn edge_ca_is_renewed_below_threshold() {
+        let now = Utc::now();
+        let issuer_validity = now + Duration::days(365);
+
+        assert!(!needs_renewal(
+            now + Duration::days(30),
+            issuer_validity,
+            90 * 86_400,
+            20,
+            now
+        ));
+        assert!(needs_renewal(
+            now + Duration::days(10),
+            issuer_validity,
+            90 * 86_400,
+            20,
+            now
+        ));
+        assert!(needs_renewal(
+            now - Duration::days(1),
+            issuer_validity,
+            90 * 86_400,
+            20,
+            now
+        ));
+    }
+
+    #[test]
+    fn edge_ca_expiring_with_issuer_is_not_renewed() {
+        let now = Utc::now();
+        let issuer_validity = now + Duration::days(10);
+
+        assert!(!needs_renewal(
+            issuer_validity,
+            issuer_validity,
+            90 * 86_400,
+            20,
+            now
+        ));
+    }
+}
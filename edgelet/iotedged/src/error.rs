@@ -31,6 +31,9 @@ pub enum ErrorKind {
     #[fail(display = "The device has been de-provisioned")]
     DeviceDeprovisioned,
 
+    #[fail(display = "The Edge CA renewal timer encountered a failure.")]
+    EdgeCaRenewal,
+
     #[fail(display = "The daemon could not start up successfully: {}", _0)]
     Initialize(InitializeErrorReason),
 
@@ -150,7 +153,8 @@ impl From<&ErrorKind> for i32 {
             ErrorKind::Initialize(InitializeErrorReason::InvalidDeviceConfig) => 150,
             ErrorKind::Initialize(InitializeErrorReason::InvalidHubConfig) => 151,
             ErrorKind::InvalidSignedToken => 152,
-            ErrorKind::Initialize(InitializeErrorReason::LoadSettings) => 153,
+            ErrorKind::Initialize(InitializeErrorReason::LoadSettings)
+            | ErrorKind::Initialize(InitializeErrorReason::InvalidSettings) => 153,
             ErrorKind::DeviceDeprovisioned => 154,
             _ => 1,
         }
@@ -159,6 +163,7 @@ impl From<&ErrorKind> for i32 {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InitializeErrorReason {
+    CertificateEnrollment,
     CertificateSettings,
     CreateCertificateManager,
     CreateMasterEncryptionKey,
@@ -166,10 +171,12 @@ pub enum InitializeErrorReason {
     CreateCacheDirectory,
     CreateTlsCertificate,
     DestroyWorkloadCa,
+    DeviceCaCertificateChain,
     DeviceClient,
     DpsProvisioningClient,
     EdgeRuntime,
     ExternalProvisioningClient(ExternalProvisioningErrorReason),
+    Fido2NotSupported,
     Hsm,
     HttpClient,
     HybridAuthDirCreate,
@@ -180,20 +187,25 @@ pub enum InitializeErrorReason {
     HybridAuthKeySign,
     IncompatibleHsmVersion,
     IdentityCertificateSettings,
+    ImageUpdateChecker,
     InvalidDeviceCertCredentials,
     InvalidDeviceConfig,
     InvalidHubConfig,
     InvalidProxyUri,
+    InvalidSettings,
     IssuerCAExpiration,
     LoadSettings,
     ManagementService,
     ManualProvisioningClient,
     ModuleRuntime,
+    Pkcs11,
+    Pkcs11NotSupported,
     PrepareWorkloadCa,
     #[cfg(windows)]
     RegisterWindowsService,
     RemoveExistingModules,
     SaveSettings,
+    SecretStore,
     #[cfg(windows)]
     StartWindowsService,
     Tokio,
@@ -217,6 +229,11 @@ pub enum ExternalProvisioningErrorReason {
 impl fmt::Display for InitializeErrorReason {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            InitializeErrorReason::CertificateEnrollment => write!(
+                f,
+                "Could not create the client for the certificate enrollment server"
+            ),
+
             InitializeErrorReason::CertificateSettings => {
                 write!(f, "Could not configure Edge gateway certificates")
             }
@@ -245,6 +262,11 @@ impl fmt::Display for InitializeErrorReason {
                 write!(f, "Could not destroy workload CA certificate")
             }
 
+            InitializeErrorReason::DeviceCaCertificateChain => write!(
+                f,
+                "The device CA certificate chain is not trusted by the configured trusted CA certificates"
+            ),
+
             InitializeErrorReason::DeviceClient => write!(f, "Could not initialize device client"),
 
             InitializeErrorReason::DpsProvisioningClient => {
@@ -259,6 +281,11 @@ impl fmt::Display for InitializeErrorReason {
                 x
             ),
 
+            InitializeErrorReason::Fido2NotSupported => write!(
+                f,
+                "FIDO2 attestation is not supported by this build of iotedged; rebuild it with the fido2 feature"
+            ),
+
             InitializeErrorReason::Hsm => write!(f, "Could not initialize HSM"),
 
             InitializeErrorReason::HttpClient => write!(f, "Could not initialize HTTP client"),
@@ -296,6 +323,10 @@ impl fmt::Display for InitializeErrorReason {
                 write!(f, "Could not configure Edge X.509 identity certificate")
             }
 
+            InitializeErrorReason::ImageUpdateChecker => {
+                write!(f, "Could not initialize the image update checker")
+            }
+
             InitializeErrorReason::InvalidDeviceCertCredentials => {
                 write!(f, "Invalid identity certificate")
             }
@@ -310,6 +341,10 @@ impl fmt::Display for InitializeErrorReason {
 
             InitializeErrorReason::InvalidProxyUri => write!(f, "Invalid proxy URI"),
 
+            InitializeErrorReason::InvalidSettings => {
+                write!(f, "Invalid settings were provided in the configuration file")
+            }
+
             InitializeErrorReason::IssuerCAExpiration => {
                 write!(f, "Edge device CA has expired or is near expiration")
             }
@@ -328,6 +363,15 @@ impl fmt::Display for InitializeErrorReason {
                 write!(f, "Could not initialize module runtime")
             }
 
+            InitializeErrorReason::Pkcs11 => {
+                write!(f, "Could not initialize the PKCS#11 device key")
+            }
+
+            InitializeErrorReason::Pkcs11NotSupported => write!(
+                f,
+                "PKCS#11 is not supported by this build of iotedged; rebuild it with the pkcs11 feature"
+            ),
+
             InitializeErrorReason::PrepareWorkloadCa => {
                 write!(f, "Could not prepare workload CA certificate")
             }
@@ -343,6 +387,8 @@ impl fmt::Display for InitializeErrorReason {
 
             InitializeErrorReason::SaveSettings => write!(f, "Could not save settings file"),
 
+            InitializeErrorReason::SecretStore => write!(f, "Could not open the secret store"),
+
             #[cfg(windows)]
             InitializeErrorReason::StartWindowsService => {
                 write!(f, "Could not start as Windows Service")
@@ -14,6 +14,7 @@
 )]
 
 pub mod app;
+mod edge_ca;
 mod error;
 pub mod logging;
 pub mod signal;
@@ -33,6 +34,7 @@ use std::fs::{DirBuilder, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use failure::{Context, Fail, ResultExt};
 use futures::future::{Either, IntoFuture};
@@ -40,7 +42,7 @@ use futures::sync::oneshot::{self, Receiver};
 use futures::{future, Future, Stream};
 use hyper::server::conn::Http;
 use hyper::{Body, Request, Uri};
-use log::{debug, info, Level};
+use log::{debug, info, warn, Level};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use sha2::{Digest, Sha256};
@@ -51,25 +53,35 @@ use edgelet_core::crypto::{
     Activate, CreateCertificate, Decrypt, DerivedKeyStore, Encrypt, GetDeviceIdentityCertificate,
     GetHsmVersion, GetIssuerAlias, GetTrustBundle, KeyIdentity, KeyStore, MakeRandom,
     MasterEncryptionKey, MemoryKey, MemoryKeyStore, Sign, Signature, SignatureAlgorithm,
-    IOTEDGED_CA_ALIAS,
 };
+use edgelet_core::module_token::MODULE_TOKEN_KEY_NAME;
 use edgelet_core::watchdog::Watchdog;
+#[cfg(feature = "fido2")]
+use edgelet_core::Fido2AttestationInfo;
 use edgelet_core::{
-    AttestationMethod, Authenticator, Certificate, CertificateIssuer, CertificateProperties,
-    CertificateType, Dps, MakeModuleRuntime, ManualAuthMethod, Module, ModuleRuntime,
-    ModuleRuntimeErrorReason, ModuleSpec, ProvisioningResult as CoreProvisioningResult,
-    ProvisioningType, RuntimeSettings, SymmetricKeyAttestationInfo, TpmAttestationInfo,
-    WorkloadConfig, X509AttestationInfo,
+    validate_certificate_chain_pem, AttestationMethod, Authenticator, Certificate,
+    CertificateExpiryMonitor, CertificateIssuer, CertificateProperties, CertificateType,
+    Certificates, CrlChecker, Dps, Error as CoreError, ErrorKind as CoreErrorKind, EventLog,
+    MakeModuleRuntime, ManualAuthMethod, Module, ModuleRuntime, ModuleRuntimeErrorReason,
+    ModuleSpec, ProvisioningResult as CoreProvisioningResult, ProvisioningType, RuntimeSettings,
+    SecretStore, SymmetricKeyAttestationInfo, TpmAttestationInfo, TwinCache, WorkloadConfig,
+    X509AttestationInfo,
 };
+use edgelet_docker::{DockerConfig, ImageUpdateChecker};
 use edgelet_hsm::tpm::{TpmKey, TpmKeyStore};
 use edgelet_hsm::{Crypto, HsmLock, X509};
+#[cfg(feature = "pkcs11")]
+use edgelet_hsm::{ErrorKind as HsmErrorKind, Pkcs11Config, Pkcs11KeyStore, Pkcs11Library};
 use edgelet_http::certificate_manager::CertificateManager;
 use edgelet_http::client::{Client as HttpClient, ClientImpl};
 use edgelet_http::logging::LoggingService;
-use edgelet_http::{HyperExt, MaybeProxyClient, PemCertificate, TlsAcceptorParams, API_VERSION};
+use edgelet_http::{
+    EstClient, HyperExt, MaybeProxyClient, PemCertificate, ScepClient, TlsAcceptorParams,
+    API_VERSION,
+};
 use edgelet_http_external_provisioning::ExternalProvisioningClient;
-use edgelet_http_mgmt::ManagementService;
-use edgelet_http_workload::WorkloadService;
+use edgelet_http_mgmt::{ManagementService, ModuleSpecs};
+use edgelet_http_workload::{CertificateEnrollment, WorkloadService};
 use edgelet_iothub::{HubIdentityManager, SasTokenSource};
 use edgelet_utils::log_failure;
 pub use error::{Error, ErrorKind, InitializeErrorReason};
@@ -81,7 +93,10 @@ use provisioning::provisioning::{
     DpsTpmProvisioning, DpsX509Provisioning, ExternalProvisioning, ManualProvisioning, Provision,
     ProvisioningResult, ReprovisioningStatus,
 };
+#[cfg(feature = "fido2")]
+use provisioning::{Fido2Provisioning, HidAuthenticator};
 
+use crate::edge_ca::EdgeCaManager;
 use crate::error::ExternalProvisioningErrorReason;
 use crate::workload::WorkloadData;
 
@@ -157,6 +172,18 @@ const EDGE_PROVISIONING_BACKUP_FILENAME: &str = "provisioning_backup.json";
 /// This is the name of the settings backup file
 const EDGE_SETTINGS_STATE_FILENAME: &str = "settings_state";
 
+/// This is the name of the file the workload API's secret store is persisted to
+const EDGE_SECRET_STORE_FILENAME: &str = "secrets.json";
+
+/// This is the name of the directory module twins are cached in, unless `twin_cache_dir` is set
+const EDGE_TWIN_CACHE_DIRNAME: &str = "twins";
+
+/// Certificates in the trust bundle that expire within this many seconds are warned about
+const CERTIFICATE_EXPIRY_WARNING_THRESHOLD_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// This is the name of the directory the specs of modules are saved in
+const EDGE_MODULE_SPECS_DIRNAME: &str = "module_specs";
+
 /// This is the name of the hybrid id subdirectory that will
 /// contain the hybrid key and other related files
 const EDGE_HYBRID_IDENTITY_SUBDIR: &str = "hybrid_id";
@@ -203,10 +230,7 @@ const DEVICE_IDENTITY_CERT_PATH_ENV_KEY: &str = "IOTEDGE_DEVICE_IDENTITY_CERT";
 /// This is used for both DPS attestation and manual authentication modes.
 const DEVICE_IDENTITY_KEY_PATH_ENV_KEY: &str = "IOTEDGE_DEVICE_IDENTITY_PK";
 
-const IOTEDGED_COMMONNAME: &str = "iotedged workload ca";
 const IOTEDGED_TLS_COMMONNAME: &str = "iotedged";
-// 5 mins
-const IOTEDGED_MIN_EXPIRATION_DURATION: i64 = 5 * 60;
 // 2 hours
 const IOTEDGE_ID_CERT_MAX_DURATION_SECS: i64 = 2 * 3600;
 // 90 days
@@ -242,7 +266,9 @@ struct IdentityCertificateData {
 
 impl<M> Main<M>
 where
-    M: MakeModuleRuntime<ProvisioningResult = ProvisioningResult> + Send + 'static,
+    M: MakeModuleRuntime<Config = DockerConfig, ProvisioningResult = ProvisioningResult>
+        + Send
+        + 'static,
     M::ModuleRuntime: 'static + Authenticator<Request = Request<Body>> + Clone + Send + Sync,
     <<M::ModuleRuntime as ModuleRuntime>::Module as Module>::Config:
         Clone + DeserializeOwned + Serialize,
@@ -301,6 +327,8 @@ where
         ))?;
         info!("Finished initializing hsm.");
 
+        init_pkcs11_device_key(&settings, hsm_lock.clone())?;
+
         let (hyper_client, device_cert_identity_data) = prepare_httpclient_and_identity_data(
             hsm_lock.clone(),
             &settings,
@@ -362,6 +390,7 @@ where
                     $provisioning_result.device_id().to_string(),
                     IOTEDGE_ID_CERT_MAX_DURATION_SECS,
                     IOTEDGE_SERVER_CERT_MAX_DURATION_SECS,
+                    settings.certificates().ct_log_url().cloned(),
                 );
                 // This "do-while" loop runs until a StartApiReturnStatus::Shutdown
                 // is received. If the TLS cert needs a restart, we will loop again.
@@ -592,6 +621,33 @@ where
                             dps_symmetric_key,
                         );
                     }
+                    #[cfg(feature = "fido2")]
+                    AttestationMethod::Fido2(ref fido2_info) => {
+                        info!("Starting provisioning edge device via FIDO2 attestation...");
+                        let dps_fido2 =
+                            dps_fido2_provision_init(&dps, hyper_client.clone(), fido2_info)?;
+                        let (key_store, provisioning_result, root_key) = dps_fido2_provision(
+                            dps_path,
+                            &mut tokio_runtime,
+                            MemoryKeyStore::new(),
+                            &dps_fido2,
+                        )?;
+
+                        start_edgelet!(
+                            key_store,
+                            provisioning_result,
+                            root_key,
+                            force_module_reprovision,
+                            None,
+                            dps_fido2,
+                        );
+                    }
+                    #[cfg(not(feature = "fido2"))]
+                    AttestationMethod::Fido2(_) => {
+                        return Err(Error::from(ErrorKind::Initialize(
+                            InitializeErrorReason::Fido2NotSupported,
+                        )));
+                    }
                     AttestationMethod::X509(ref x509_info) => {
                         info!("Starting provisioning edge device via X509 provisioning...");
                         let id_data = device_cert_identity_data.ok_or_else(|| {
@@ -765,14 +821,14 @@ where
             info!("Transparent gateway certificates not found, operating in quick start mode...")
         }
         Some(&c) => {
-            let path = c.device_ca_cert().context(ErrorKind::Initialize(
+            let device_ca_cert_path = c.device_ca_cert().context(ErrorKind::Initialize(
                 InitializeErrorReason::CertificateSettings,
             ))?;
             info!(
                 "Configuring the Device CA certificate using {:?}.",
-                path.as_os_str()
+                device_ca_cert_path.as_os_str()
             );
-            env::set_var(DEVICE_CA_CERT_KEY, path);
+            env::set_var(DEVICE_CA_CERT_KEY, &device_ca_cert_path);
 
             let path = c.device_ca_pk().context(ErrorKind::Initialize(
                 InitializeErrorReason::CertificateSettings,
@@ -790,7 +846,22 @@ where
                 "Configuring the trusted CA certificates using {:?}.",
                 path.as_os_str()
             );
-            env::set_var(TRUSTED_CA_CERTS_KEY, path);
+            env::set_var(TRUSTED_CA_CERTS_KEY, &path);
+
+            let device_ca_chain = fs::read(&device_ca_cert_path).context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateSettings,
+            ))?;
+            let trust_bundle = fs::read(&path).context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateSettings,
+            ))?;
+            validate_certificate_chain_pem(
+                &device_ca_chain,
+                &trust_bundle,
+                &CrlChecker::new(fetch_crl),
+            )
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::DeviceCaCertificateChain,
+            ))?;
         }
     };
 
@@ -868,6 +939,12 @@ where
                 ))?;
                 env::set_var(DEVICE_IDENTITY_KEY_PATH_ENV_KEY, path.as_os_str());
             }
+            AttestationMethod::Fido2(ref fido2_info) => {
+                env::set_var(
+                    DPS_REGISTRATION_ID_ENV_KEY,
+                    fido2_info.registration_id().to_string(),
+                );
+            }
         },
     }
 
@@ -875,6 +952,103 @@ where
     Ok(())
 }
 
+/// Downloads a CRL from its distribution point, over http or https and through the proxy in
+/// `HTTPS_PROXY`, if any.
+fn fetch_crl(url: &Url) -> Result<Vec<u8>, CoreError> {
+    let request = Request::get(url.as_str())
+        .body(Body::empty())
+        .context(CoreErrorKind::CertificateRevocationCheck)?;
+    let client = MaybeProxyClient::new(
+        get_proxy_uri(None).context(CoreErrorKind::CertificateRevocationCheck)?,
+        None,
+        None,
+    )
+    .context(CoreErrorKind::CertificateRevocationCheck)?;
+
+    // This runs during startup, before the main runtime starts running futures.
+    let mut runtime = tokio::runtime::current_thread::Runtime::new()
+        .context(CoreErrorKind::CertificateRevocationCheck)?;
+    let (status, body) = runtime
+        .block_on(client.call(request).and_then(|response| {
+            let status = response.status();
+            response
+                .into_body()
+                .concat2()
+                .map(move |body| (status, body))
+        }))
+        .context(CoreErrorKind::CertificateRevocationCheck)?;
+
+    if status.is_success() {
+        Ok(body.to_vec())
+    } else {
+        warn!("Downloading the CRL at {} failed with {}", url, status);
+        Err(CoreError::from(CoreErrorKind::CertificateRevocationCheck))
+    }
+}
+
+/// Opens the PKCS#11 token from the settings, if any, and generates the device's key pair on it
+/// if it doesn't have one yet.
+#[cfg(feature = "pkcs11")]
+fn init_pkcs11_device_key<S>(settings: &S, hsm_lock: Arc<HsmLock>) -> Result<(), Error>
+where
+    S: RuntimeSettings,
+{
+    let pkcs11 = match settings.pkcs11() {
+        Some(pkcs11) => pkcs11,
+        None => return Ok(()),
+    };
+
+    info!(
+        "Initializing PKCS#11 token {} with {}...",
+        pkcs11.token_label(),
+        pkcs11.lib_path().display()
+    );
+    let library = Pkcs11Library::load(pkcs11.lib_path(), hsm_lock)
+        .context(ErrorKind::Initialize(InitializeErrorReason::Pkcs11))?;
+    let config = Pkcs11Config::new(
+        pkcs11.lib_path().to_path_buf(),
+        pkcs11.token_label().to_string(),
+        pkcs11.pin().to_string(),
+    );
+    let key_store = Pkcs11KeyStore::open(&library, &config)
+        .context(ErrorKind::Initialize(InitializeErrorReason::Pkcs11))?;
+
+    match key_store.get(pkcs11.key_label()) {
+        Ok(_) => info!("Found device key {} on the token.", pkcs11.key_label()),
+        Err(ref err) if *err.kind() == HsmErrorKind::Pkcs11KeyNotFound => {
+            info!(
+                "Generating device key {} on the token...",
+                pkcs11.key_label()
+            );
+            key_store
+                .generate_key_pair(pkcs11.key_label())
+                .context(ErrorKind::Initialize(InitializeErrorReason::Pkcs11))?;
+        }
+        Err(err) => {
+            return Err(Error::from(
+                err.context(ErrorKind::Initialize(InitializeErrorReason::Pkcs11)),
+            ))
+        }
+    }
+    info!("Finished initializing PKCS#11 token.");
+
+    Ok(())
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn init_pkcs11_device_key<S>(settings: &S, _hsm_lock: Arc<HsmLock>) -> Result<(), Error>
+where
+    S: RuntimeSettings,
+{
+    if settings.pkcs11().is_some() {
+        return Err(Error::from(ErrorKind::Initialize(
+            InitializeErrorReason::Pkcs11NotSupported,
+        )));
+    }
+
+    Ok(())
+}
+
 fn prepare_httpclient_and_identity_data<S>(
     hsm_lock: Arc<HsmLock>,
     settings: &S,
@@ -985,67 +1159,6 @@ pub fn get_proxy_uri(https_proxy: Option<String>) -> Result<Option<Uri>, Error>
     Ok(proxy_uri)
 }
 
-fn prepare_workload_ca<C>(crypto: &C) -> Result<(), Error>
-where
-    C: CreateCertificate + GetIssuerAlias,
-{
-    let issuer_alias = crypto
-        .get_issuer_alias(CertificateIssuer::DeviceCa)
-        .context(ErrorKind::Initialize(
-            InitializeErrorReason::PrepareWorkloadCa,
-        ))?;
-
-    let issuer_ca = crypto
-        .get_certificate(issuer_alias)
-        .context(ErrorKind::Initialize(
-            InitializeErrorReason::PrepareWorkloadCa,
-        ))?;
-
-    let issuer_validity = issuer_ca.get_valid_to().context(ErrorKind::Initialize(
-        InitializeErrorReason::PrepareWorkloadCa,
-    ))?;
-
-    info!("Edge issuer CA expiration date: {:?}", issuer_validity);
-
-    let now = chrono::Utc::now();
-
-    let diff = issuer_validity.timestamp() - now.timestamp();
-
-    if diff > IOTEDGED_MIN_EXPIRATION_DURATION {
-        #[allow(clippy::cast_sign_loss)]
-        let edgelet_ca_props = CertificateProperties::new(
-            diff as u64,
-            IOTEDGED_COMMONNAME.to_string(),
-            CertificateType::Ca,
-            IOTEDGED_CA_ALIAS.to_string(),
-        )
-        .with_issuer(CertificateIssuer::DeviceCa);
-
-        crypto
-            .create_certificate(&edgelet_ca_props)
-            .context(ErrorKind::Initialize(
-                InitializeErrorReason::PrepareWorkloadCa,
-            ))?;
-        Ok(())
-    } else {
-        Err(Error::from(ErrorKind::Initialize(
-            InitializeErrorReason::IssuerCAExpiration,
-        )))
-    }
-}
-
-fn destroy_workload_ca<C>(crypto: &C) -> Result<(), Error>
-where
-    C: CreateCertificate,
-{
-    crypto
-        .destroy_certificate(IOTEDGED_CA_ALIAS.to_string())
-        .context(ErrorKind::Initialize(
-            InitializeErrorReason::DestroyWorkloadCa,
-        ))?;
-    Ok(())
-}
-
 fn prepare_master_hybrid_identity_key<S, C>(
     settings: &S,
     crypto: &C,
@@ -1273,7 +1386,7 @@ fn check_settings_state<M, C>(
 where
     M: MakeModuleRuntime + 'static,
     M::Settings: Serialize,
-    C: CreateCertificate + GetIssuerAlias + MasterEncryptionKey,
+    C: CreateCertificate + GetIssuerAlias + MasterEncryptionKey + Clone,
 {
     info!("Detecting if configuration file has changed...");
     let path = subdir.join(filename);
@@ -1285,8 +1398,9 @@ where
     } else {
         info!("No change to configuration file detected.");
 
+        let edge_ca = EdgeCaManager::new(crypto.clone(), settings.certificates());
         #[allow(clippy::single_match_else)]
-        match prepare_workload_ca(crypto) {
+        match edge_ca.prepare() {
             Ok(()) => info!("Obtaining workload CA succeeded."),
             Err(_) => {
                 reconfig_reqd = true;
@@ -1355,7 +1469,7 @@ fn reconfigure<M, C>(
 where
     M: MakeModuleRuntime + 'static,
     M::Settings: Serialize,
-    C: CreateCertificate + GetIssuerAlias + MasterEncryptionKey,
+    C: CreateCertificate + GetIssuerAlias + MasterEncryptionKey + Clone,
 {
     // Remove all edge containers and destroy the cache (settings and dps backup)
     info!("Removing all modules...");
@@ -1380,8 +1494,7 @@ where
         ))?;
 
     // regenerate the workload CA certificate
-    destroy_workload_ca(crypto)?;
-    prepare_workload_ca(crypto)?;
+    EdgeCaManager::new(crypto.clone(), settings.certificates()).regenerate()?;
     let mut file =
         File::create(path).context(ErrorKind::Initialize(InitializeErrorReason::SaveSettings))?;
     let digest = compute_settings_digest(settings, id_cert_thumbprint)
@@ -1411,6 +1524,7 @@ where
     C: CreateCertificate
         + Decrypt
         + Encrypt
+        + GetIssuerAlias
         + GetTrustBundle
         + MasterEncryptionKey
         + Clone
@@ -1419,7 +1533,7 @@ where
         + 'static,
     W: WorkloadConfig + Clone + Send + Sync + 'static,
     M::ModuleRuntime: Authenticator<Request = Request<Body>> + Send + Sync + Clone + 'static,
-    M: MakeModuleRuntime + 'static,
+    M: MakeModuleRuntime<Config = DockerConfig> + 'static,
     <<M::ModuleRuntime as ModuleRuntime>::Module as Module>::Config:
         Clone + DeserializeOwned + Serialize,
     M::Settings: 'static,
@@ -1464,10 +1578,15 @@ where
     let expiration_timer = if settings.listen().management_uri().scheme() == "https"
         || settings.listen().workload_uri().scheme() == "https"
     {
+        // The certificate is renewed in the background and the listeners pick up the renewed
+        // one, so the expiration timer only restarts them if the renewals keep failing
+        let renewal = cert_manager.schedule_renewal();
         Either::A(
             cert_manager
                 .schedule_expiration_timer(move || restart_tx.send(()))
-                .map_err(|err| {
+                .select(renewal)
+                .map(|_| ())
+                .map_err(|(err, _)| {
                     Error::from(err.context(ErrorKind::CertificateExpirationManagement))
                 }),
         )
@@ -1477,10 +1596,57 @@ where
 
     let cert_manager = Arc::new(cert_manager);
 
+    let edge_ca_renewal = EdgeCaManager::new(crypto.clone(), settings.certificates())
+        .schedule_renewal()
+        .map_err(|err| warn!("The Edge CA renewal timer failed: {}", err));
+    tokio_runtime.spawn(edge_ca_renewal);
+
+    // Module lifecycle events recorded by the watchdog and served by the management API.
+    let event_log = EventLog::default();
+
+    // The image update checker stops when the services do, so that restarting them doesn't leave
+    // a second checker running.
+    let (image_updates_tx, image_updates_rx) = oneshot::channel();
+    let image_updates =
+        ImageUpdateChecker::from_proxy(runtime.clone(), event_log.clone(), get_proxy_uri(None)?)
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::ImageUpdateChecker,
+            ))?
+            .run_until(image_updates_rx.then(|_| Ok(())));
+    tokio_runtime.spawn(image_updates);
+
+    // So does the certificate expiry monitor.
+    let (expiry_monitor_tx, expiry_monitor_rx) = oneshot::channel();
+    let expiry_monitor = CertificateExpiryMonitor::new(
+        crypto.clone(),
+        Duration::from_secs(CERTIFICATE_EXPIRY_WARNING_THRESHOLD_SECS),
+    )
+    .run_until(expiry_monitor_rx.then(|_| Ok(())));
+    tokio_runtime.spawn(expiry_monitor);
+
+    // Module tokens presented to the management API are signed with a key derived from the
+    // device key. Without a key, the management API doesn't check tokens.
+    let token_key = if settings.management_auth().enabled() {
+        let key = key_store
+            .get(&KeyIdentity::Device, MODULE_TOKEN_KEY_NAME)
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::ManagementService,
+            ))?;
+        Some(key)
+    } else {
+        None
+    };
+
+    let module_specs =
+        ModuleSpecs::new().with_dir(settings.homedir().join(EDGE_MODULE_SPECS_DIRNAME));
+
     let mgmt = start_management::<_, _, _, M>(
         settings,
         runtime,
         &id_man,
+        &event_log,
+        &module_specs,
+        token_key,
         mgmt_rx,
         cert_manager.clone(),
         mgmt_stop_and_reprovision_tx,
@@ -1500,6 +1666,8 @@ where
     let edge_rt = start_runtime::<_, _, M>(
         runtime.clone(),
         &id_man,
+        &event_log,
+        &module_specs,
         &hub_name,
         &device_id,
         &settings,
@@ -1584,6 +1752,8 @@ where
             Err(err) => Err(err),
         });
     let (restart_code, should_reprovision) = tokio_runtime.block_on(services)?;
+    image_updates_tx.send(()).unwrap_or(());
+    expiry_monitor_tx.send(()).unwrap_or(());
     Ok((restart_code, should_reprovision))
 }
 
@@ -1875,6 +2045,72 @@ where
     tokio_runtime.block_on(provision)
 }
 
+#[cfg(feature = "fido2")]
+fn dps_fido2_provision_init<HC>(
+    provisioning: &Dps,
+    hyper_client: HC,
+    fido2_info: &Fido2AttestationInfo,
+) -> Result<Fido2Provisioning<HC, HidAuthenticator>, Error>
+where
+    HC: 'static + ClientImpl,
+{
+    // The relying party defaults to the enrollment service the attestation is submitted to.
+    let relying_party_id = match fido2_info.relying_party_id() {
+        Some(relying_party_id) => relying_party_id.to_string(),
+        None => provisioning
+            .global_endpoint()
+            .host_str()
+            .ok_or_else(|| ErrorKind::Initialize(InitializeErrorReason::DpsProvisioningClient))?
+            .to_string(),
+    };
+
+    let dps = Fido2Provisioning::new(
+        hyper_client,
+        provisioning.global_endpoint().clone(),
+        provisioning.scope_id().to_string(),
+        fido2_info.registration_id().to_string(),
+        relying_party_id,
+        HidAuthenticator::new(fido2_info.pin().map(ToString::to_string)),
+        DPS_API_VERSION.to_string(),
+    )
+    .context(ErrorKind::Initialize(
+        InitializeErrorReason::DpsProvisioningClient,
+    ))?;
+    Ok(dps)
+}
+
+#[cfg(feature = "fido2")]
+fn dps_fido2_provision<HC>(
+    backup_path: PathBuf,
+    tokio_runtime: &mut tokio::runtime::Runtime,
+    memory_hsm: MemoryKeyStore,
+    dps: &Fido2Provisioning<HC, HidAuthenticator>,
+) -> Result<(DerivedKeyStore<MemoryKey>, ProvisioningResult, MemoryKey), Error>
+where
+    HC: 'static + ClientImpl,
+{
+    let provision_with_file_backup = BackupProvisioning::new(dps, backup_path);
+
+    let provision =
+        provision_with_file_backup
+            .provision(memory_hsm.clone())
+            .map_err(|err| {
+                Error::from(err.context(ErrorKind::Initialize(
+                    InitializeErrorReason::DpsProvisioningClient,
+                )))
+            })
+            .and_then(move |prov_result| {
+                info!("Successful FIDO2 provisioning.");
+                let k = memory_hsm.get(&KeyIdentity::Device, "primary").context(
+                    ErrorKind::Initialize(InitializeErrorReason::DpsProvisioningClient),
+                )?;
+                let derived_key_store = DerivedKeyStore::new(k.clone());
+                Ok((derived_key_store, prov_result, k))
+            });
+
+    tokio_runtime.block_on(provision)
+}
+
 fn dps_tpm_provision_init<HC>(
     provisioning: &Dps,
     hyper_client: HC,
@@ -1956,9 +2192,12 @@ where
     tokio_runtime.block_on(provision)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn start_runtime<K, HC, M>(
     runtime: M::ModuleRuntime,
     id_man: &HubIdentityManager<DerivedKeyStore<K>, HC, K>,
+    event_log: &EventLog,
+    module_specs: &ModuleSpecs,
     hostname: &str,
     device_id: &str,
     settings: &M::Settings,
@@ -1983,9 +2222,14 @@ where
         env,
         spec.image_pull_policy(),
     )
-    .context(ErrorKind::Initialize(InitializeErrorReason::EdgeRuntime))?;
-
-    let watchdog = Watchdog::new(runtime, id_man.clone(), settings.watchdog().max_retries());
+    .context(ErrorKind::Initialize(InitializeErrorReason::EdgeRuntime))?
+    .with_resource_limits(*spec.resource_limits())
+    .with_restart_policy(*spec.restart_policy())
+    .with_log_level(spec.log_level().map(ToOwned::to_owned));
+    module_specs.insert_runtime_spec(&spec);
+
+    let watchdog = Watchdog::new(runtime, id_man.clone(), settings.watchdog().max_retries())
+        .with_event_log(event_log.clone());
     let runtime_future = watchdog
         .run_until(spec, EDGE_RUNTIME_MODULEID, shutdown.map_err(|_| ()))
         .map_err(Error::from);
@@ -2043,10 +2287,14 @@ where
     env
 }
 
+#[allow(clippy::too_many_arguments)]
 fn start_management<C, K, HC, M>(
     settings: &M::Settings,
     runtime: &M::ModuleRuntime,
     id_man: &HubIdentityManager<DerivedKeyStore<K>, HC, K>,
+    event_log: &EventLog,
+    module_specs: &ModuleSpecs,
+    token_key: Option<MemoryKey>,
     shutdown: Receiver<()>,
     cert_manager: Arc<CertificateManager<C>>,
     initiate_shutdown_and_reprovision: mpsc::UnboundedSender<()>,
@@ -2067,29 +2315,44 @@ where
     let label = "mgmt".to_string();
     let url = settings.listen().management_uri().clone();
     let min_protocol_version = settings.listen().min_tls_version();
+    let twin_cache = TwinCache::new(settings.twin_cache_dir().map_or_else(
+        || settings.homedir().join(EDGE_TWIN_CACHE_DIRNAME),
+        Path::to_path_buf,
+    ));
 
-    ManagementService::new(runtime, id_man, initiate_shutdown_and_reprovision)
-        .then(move |service| -> Result<_, Error> {
-            let service = service.context(ErrorKind::Initialize(
-                InitializeErrorReason::ManagementService,
-            ))?;
-            let service = LoggingService::new(label, service);
+    ManagementService::new(
+        runtime,
+        id_man,
+        event_log,
+        token_key,
+        settings.rate_limits(),
+        settings.metrics(),
+        settings.tracing(),
+        &twin_cache,
+        module_specs,
+        initiate_shutdown_and_reprovision,
+    )
+    .then(move |service| -> Result<_, Error> {
+        let service = service.context(ErrorKind::Initialize(
+            InitializeErrorReason::ManagementService,
+        ))?;
+        let service = LoggingService::new(label, service);
 
-            let tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
+        let tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
 
-            let run = Http::new()
-                .bind_url(url.clone(), service, Some(tls_params))
-                .map_err(|err| {
-                    err.context(ErrorKind::Initialize(
-                        InitializeErrorReason::ManagementService,
-                    ))
-                })?
-                .run_until(shutdown.map_err(|_| ()))
-                .map_err(|err| Error::from(err.context(ErrorKind::ManagementService)));
-            info!("Listening on {} with 1 thread for management API.", url);
-            Ok(run)
-        })
-        .flatten()
+        let run = Http::new()
+            .bind_url(url.clone(), service, Some(tls_params))
+            .map_err(|err| {
+                err.context(ErrorKind::Initialize(
+                    InitializeErrorReason::ManagementService,
+                ))
+            })?
+            .run_until(shutdown.map_err(|_| ()))
+            .map_err(|err| Error::from(err.context(ErrorKind::ManagementService)));
+        info!("Listening on {} with 1 thread for management API.", url);
+        Ok(run)
+    })
+    .flatten()
 }
 
 fn start_workload<K, C, CE, W, M>(
@@ -2129,28 +2392,119 @@ where
     let url = settings.listen().workload_uri().clone();
     let min_protocol_version = settings.listen().min_tls_version();
 
-    WorkloadService::new(key_store, crypto.clone(), runtime, config)
-        .then(move |service| -> Result<_, Error> {
-            let service = service.context(ErrorKind::Initialize(
-                InitializeErrorReason::WorkloadService,
+    let secret_store = match SecretStore::new(
+        crypto.clone(),
+        Path::new(&settings.homedir()).join(EDGE_SECRET_STORE_FILENAME),
+    ) {
+        Ok(secret_store) => secret_store,
+        Err(err) => {
+            return Either::B(future::err(Error::from(
+                err.context(ErrorKind::Initialize(InitializeErrorReason::SecretStore)),
+            )))
+        }
+    };
+
+    let enrollment = match certificate_enrollment(settings.certificates()) {
+        Ok(enrollment) => enrollment,
+        Err(err) => return Either::B(future::err(err)),
+    };
+
+    let run = WorkloadService::new(
+        key_store,
+        crypto.clone(),
+        runtime,
+        config,
+        settings.management_auth(),
+        &secret_store,
+        enrollment,
+    )
+    .then(move |service| -> Result<_, Error> {
+        let service = service.context(ErrorKind::Initialize(
+            InitializeErrorReason::WorkloadService,
+        ))?;
+        let service = LoggingService::new(label, service);
+
+        let tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
+
+        let run = Http::new()
+            .bind_url(url.clone(), service, Some(tls_params))
+            .map_err(|err| {
+                err.context(ErrorKind::Initialize(
+                    InitializeErrorReason::WorkloadService,
+                ))
+            })?
+            .run_until(shutdown.map_err(|_| ()))
+            .map_err(|err| Error::from(err.context(ErrorKind::WorkloadService)));
+        info!("Listening on {} with 1 thread for workload API.", url);
+        Ok(run)
+    })
+    .flatten();
+
+    Either::A(run)
+}
+
+/// Creates the client for the certificate authority that issues workload certificates instead of
+/// the HSM, if one is configured.
+fn certificate_enrollment(
+    certificates: &Certificates,
+) -> Result<Option<CertificateEnrollment>, Error> {
+    if let Some(scep_url) = certificates.scep_url() {
+        let mut client = ScepClient::from_url(scep_url.clone(), None, get_proxy_uri(None)?)
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateEnrollment,
             ))?;
-            let service = LoggingService::new(label, service);
+        if let Some(challenge_password) = certificates.scep_challenge_password() {
+            client = client.with_challenge_password(challenge_password.to_string());
+        }
+        if let Some(ca_fingerprint) = certificates.scep_ca_fingerprint() {
+            client = client.with_ca_fingerprint(ca_fingerprint.to_string());
+        }
+        info!(
+            "Workload certificates will be issued by the SCEP server at {}",
+            scep_url
+        );
 
-            let tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
+        return Ok(Some(CertificateEnrollment::scep(client)));
+    }
 
-            let run = Http::new()
-                .bind_url(url.clone(), service, Some(tls_params))
-                .map_err(|err| {
-                    err.context(ErrorKind::Initialize(
-                        InitializeErrorReason::WorkloadService,
-                    ))
-                })?
-                .run_until(shutdown.map_err(|_| ()))
-                .map_err(|err| Error::from(err.context(ErrorKind::WorkloadService)));
-            info!("Listening on {} with 1 thread for workload API.", url);
-            Ok(run)
-        })
-        .flatten()
+    let est_url = match certificates.est_url() {
+        Some(est_url) => est_url,
+        None => return Ok(None),
+    };
+
+    let read = |path: Result<Option<PathBuf>, CoreError>| -> Result<Vec<u8>, Error> {
+        let path = path
+            .context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateEnrollment,
+            ))?
+            .ok_or_else(|| ErrorKind::Initialize(InitializeErrorReason::CertificateEnrollment))?;
+        let contents = fs::read(path).context(ErrorKind::Initialize(
+            InitializeErrorReason::CertificateEnrollment,
+        ))?;
+        Ok(contents)
+    };
+    let identity_certificate = PemCertificate::new(
+        read(certificates.est_identity_cert())?,
+        Some(read(certificates.est_identity_pk())?),
+        None,
+        None,
+    );
+
+    let client = EstClient::from_identity(
+        est_url.clone(),
+        identity_certificate,
+        None,
+        get_proxy_uri(None)?,
+    )
+    .context(ErrorKind::Initialize(
+        InitializeErrorReason::CertificateEnrollment,
+    ))?;
+    info!(
+        "Workload certificates will be issued by the EST server at {}",
+        est_url
+    );
+
+    Ok(Some(CertificateEnrollment::est(client)))
 }
 
 #[cfg(test)]
@@ -2243,6 +2597,7 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
     struct TestCrypto {
         use_expired_ca: bool,
         fail_device_ca_alias: bool,
@@ -1,14 +1,16 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::env;
-use std::io::Write;
+use std::io::{self, Write};
 
 #[cfg(target_os = "windows")]
 use clap::crate_name;
 
 use edgelet_utils::log_failure;
 use env_logger;
-use log::{Level, LevelFilter};
+use env_logger::fmt::Formatter;
+use log::{Level, LevelFilter, Record};
+use serde_json::json;
 #[cfg(target_os = "windows")]
 use win_logger::EventLogger;
 
@@ -17,45 +19,67 @@ use crate::error::Error;
 #[cfg(target_os = "windows")]
 const IOTEDGED_SERVICE_NAME: &str = crate_name!();
 const ENV_LOG: &str = "IOTEDGE_LOG";
+const ENV_LOG_FORMAT: &str = "IOTEDGE_LOG_FORMAT";
 
 pub fn init() {
-    env_logger::Builder::new()
-        .format(|fmt, record| {
-            let level = match record.level() {
-                Level::Trace => "TRCE",
-                Level::Debug => "DBUG",
-                Level::Info => "INFO",
-                Level::Warn => "WARN",
-                Level::Error => "ERR!",
-            };
-            let timestamp = fmt.timestamp();
+    let mut builder = env_logger::Builder::new();
+    if env::var(ENV_LOG_FORMAT).map_or(false, |format| format.eq_ignore_ascii_case("json")) {
+        builder.format(format_json);
+    } else {
+        builder.format(format_text);
+    }
 
-            if record.level() >= Level::Debug {
-                writeln!(
-                    fmt,
-                    "<{}>{} [{}] - [{}] {}",
-                    syslog_level(record.level()),
-                    timestamp,
-                    level,
-                    record.target(),
-                    record.args()
-                )
-            } else {
-                writeln!(
-                    fmt,
-                    "<{}>{} [{}] - {}",
-                    syslog_level(record.level()),
-                    timestamp,
-                    level,
-                    record.args()
-                )
-            }
-        })
+    builder
         .filter_level(LevelFilter::Info)
         .parse(&env::var(ENV_LOG).unwrap_or_default())
         .init();
 }
 
+fn format_text(fmt: &mut Formatter, record: &Record<'_>) -> io::Result<()> {
+    let level = match record.level() {
+        Level::Trace => "TRCE",
+        Level::Debug => "DBUG",
+        Level::Info => "INFO",
+        Level::Warn => "WARN",
+        Level::Error => "ERR!",
+    };
+    let timestamp = fmt.timestamp();
+
+    if record.level() >= Level::Debug {
+        writeln!(
+            fmt,
+            "<{}>{} [{}] - [{}] {}",
+            syslog_level(record.level()),
+            timestamp,
+            level,
+            record.target(),
+            record.args()
+        )
+    } else {
+        writeln!(
+            fmt,
+            "<{}>{} [{}] - {}",
+            syslog_level(record.level()),
+            timestamp,
+            level,
+            record.args()
+        )
+    }
+}
+
+/// Writes the record as a single line of JSON so that log collectors don't have to parse the
+/// text format. `module_id` is the Rust module that logged the line.
+fn format_json(fmt: &mut Formatter, record: &Record<'_>) -> io::Result<()> {
+    let line = json!({
+        "timestamp": fmt.timestamp().to_string(),
+        "level": record.level().to_string(),
+        "module_id": record.module_path(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    writeln!(fmt, "{}", line)
+}
+
 #[cfg(target_os = "windows")]
 pub fn init_win_log() {
     let mut min_log_level = "info".to_string();
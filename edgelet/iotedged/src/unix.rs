@@ -12,6 +12,7 @@ type ModuleRuntime = edgelet_kube::KubeModuleRuntime<
     kube_client::HttpClient<hyper_tls::HttpsConnector<hyper::client::HttpConnector>, hyper::Body>,
 >;
 
+#[cfg(not(all(target_os = "linux", feature = "runtime-docker")))]
 pub fn run() -> Result<(), Error> {
     let settings = app::init()?;
     let main = super::Main::<ModuleRuntime>::new(settings);
@@ -19,3 +20,21 @@ pub fn run() -> Result<(), Error> {
     main.run_until(signal::shutdown)?;
     Ok(())
 }
+
+#[cfg(all(target_os = "linux", feature = "runtime-docker"))]
+pub fn run() -> Result<(), Error> {
+    match app::init()? {
+        app::Runtime::Docker(settings) => {
+            super::Main::<ModuleRuntime>::new(settings).run_until(signal::shutdown)?
+        }
+        app::Runtime::Podman(settings) => {
+            super::Main::<edgelet_docker::PodmanModuleRuntime>::new(settings)
+                .run_until(signal::shutdown)?
+        }
+        app::Runtime::Runc(settings) => {
+            super::Main::<edgelet_runc::RunCModuleRuntime>::new(settings)
+                .run_until(signal::shutdown)?
+        }
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@
 
 use edgelet_core::{CertificateType, WorkloadConfig};
 use std::sync::Arc;
+use url::Url;
 
 #[derive(Debug, Clone)]
 struct WorkloadConfigData {
@@ -9,6 +10,7 @@ struct WorkloadConfigData {
     device_id: String,
     id_cert_max_duration: i64,
     srv_cert_max_duration: i64,
+    ct_log_url: Option<Url>,
 }
 
 impl WorkloadConfigData {
@@ -17,12 +19,14 @@ impl WorkloadConfigData {
         device_id: String,
         id_cert_max_duration: i64,
         srv_cert_max_duration: i64,
+        ct_log_url: Option<Url>,
     ) -> Self {
         WorkloadConfigData {
             iot_hub_name,
             device_id,
             id_cert_max_duration,
             srv_cert_max_duration,
+            ct_log_url,
         }
     }
 
@@ -41,6 +45,10 @@ impl WorkloadConfigData {
     pub fn server_cert_max(&self) -> i64 {
         self.srv_cert_max_duration
     }
+
+    pub fn ct_log_url(&self) -> Option<&Url> {
+        self.ct_log_url.as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,12 +62,14 @@ impl WorkloadData {
         device_id: String,
         id_cert_max_duration: i64,
         srv_cert_max_duration: i64,
+        ct_log_url: Option<Url>,
     ) -> Self {
         let w = WorkloadConfigData::new(
             iot_hub_name,
             device_id,
             id_cert_max_duration,
             srv_cert_max_duration,
+            ct_log_url,
         );
         WorkloadData { data: Arc::new(w) }
     }
@@ -81,4 +91,8 @@ impl WorkloadConfig for WorkloadData {
             _ => 0,
         }
     }
+
+    fn ct_log_url(&self) -> Option<&Url> {
+        self.data.ct_log_url()
+    }
 }
@@ -5,6 +5,7 @@ use futures::future::{self, Either};
 use futures::Future;
 use hyper::{Method, StatusCode};
 use percent_encoding::{define_encode_set, percent_encode, PercentEncode, PATH_SEGMENT_ENCODE_SET};
+use serde_json::Value;
 
 use edgelet_http::client::{Client, ClientImpl, TokenSource};
 use edgelet_http::error::ErrorKind as HttpErrorKind;
@@ -158,6 +159,51 @@ where
         }
     }
 
+    pub fn get_module_twin(&self, module_id: String) -> impl Future<Item = Value, Error = Error> {
+        if module_id.trim().is_empty() {
+            Either::B(future::err(Error::from(
+                ErrorKind::GetModuleTwinWithReason(module_id, ModuleOperationReason::EmptyModuleId),
+            )))
+        } else {
+            let res = self
+                .client
+                .request::<(), Value>(
+                    Method::GET,
+                    &format!(
+                        "/twins/{}/modules/{}",
+                        url_encode(&self.device_id),
+                        url_encode(&module_id)
+                    ),
+                    None,
+                    None,
+                    false,
+                )
+                .then(|twin| match twin {
+                    Ok(Some(twin)) => Ok(twin),
+
+                    Ok(None) => Err(Error::from(ErrorKind::GetModuleTwinWithReason(
+                        module_id,
+                        ModuleOperationReason::EmptyResponse,
+                    ))),
+
+                    Err(err) => Err({
+                        if let HttpErrorKind::HttpWithErrorResponse(StatusCode::NOT_FOUND, _) =
+                            err.kind()
+                        {
+                            Error::from(ErrorKind::GetModuleTwinWithReason(
+                                module_id,
+                                ModuleOperationReason::ModuleNotFound,
+                            ))
+                        } else {
+                            Error::from(err.context(ErrorKind::GetModuleTwin(module_id)))
+                        }
+                    }),
+                });
+
+            Either::A(res)
+        }
+    }
+
     pub fn list_modules(&self) -> impl Future<Item = Vec<Module>, Error = Error> {
         self.client
             .request::<(), Vec<Module>>(
@@ -742,4 +788,78 @@ mod tests {
             .block_on(task)
             .unwrap();
     }
+
+    #[test]
+    fn module_twin_get_request() {
+        let api_version = "2018-04-10".to_string();
+        let host_name = Url::parse("http://localhost").unwrap();
+        let twin = serde_json::json!({
+            "deviceId": "d1",
+            "moduleId": "m1",
+            "properties": {
+                "desired": { "$version": 2, "interval": 10 },
+                "reported": { "$version": 5 }
+            }
+        });
+        let expected_twin = twin.clone();
+
+        let handler = move |req: Request<Body>| {
+            assert_eq!(req.method(), &Method::GET);
+            assert_eq!(req.uri().path(), "/twins/d1/modules/m1");
+
+            let mut response = Response::new(twin.to_string().into());
+            response
+                .headers_mut()
+                .typed_insert(&ContentType(mime::APPLICATION_JSON));
+            Ok(response)
+        };
+        let client = Client::new(handler, Some(NullTokenSource), api_version, host_name).unwrap();
+
+        let device_client = DeviceClient::new(client, "d1".to_string()).unwrap();
+        let task = device_client
+            .get_module_twin("m1".to_string())
+            .then(|twin| {
+                assert_eq!(expected_twin, twin.unwrap());
+                Ok::<_, Error>(())
+            });
+
+        tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(task)
+            .unwrap();
+    }
+
+    #[test]
+    fn module_twin_get_not_found() {
+        let api_version = "2018-04-10".to_string();
+        let host_name = Url::parse("http://localhost").unwrap();
+
+        let handler = move |_req: Request<Body>| {
+            let response = Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .expect("could not build hyper::Response");
+            Ok(response)
+        };
+        let client = Client::new(handler, Some(NullTokenSource), api_version, host_name).unwrap();
+
+        let device_client = DeviceClient::new(client, "d1".to_string()).unwrap();
+        let task = device_client
+            .get_module_twin("m1".to_string())
+            .then(|twin| {
+                assert_eq!(
+                    ErrorKind::GetModuleTwinWithReason(
+                        "m1".to_string(),
+                        ModuleOperationReason::ModuleNotFound
+                    ),
+                    *twin.unwrap_err().kind()
+                );
+                Ok::<_, Error>(())
+            });
+
+        tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(task)
+            .unwrap();
+    }
 }
@@ -25,6 +25,12 @@ pub enum ErrorKind {
     #[fail(display = "Could not get module {}: {}", _0, _1)]
     GetModuleWithReason(String, ModuleOperationReason),
 
+    #[fail(display = "Could not get the twin of module {}", _0)]
+    GetModuleTwin(String),
+
+    #[fail(display = "Could not get the twin of module {}: {}", _0, _1)]
+    GetModuleTwinWithReason(String, ModuleOperationReason),
+
     #[fail(display = "IoT Hub service error: [{}] {}", _0, _1)]
     HubService(StatusCode, String),
 
@@ -6,6 +6,7 @@ use hyper;
 pub struct APIClient {
     device_actions_api: Box<dyn crate::apis::DeviceActionsApi>,
     identity_api: Box<dyn crate::apis::IdentityApi>,
+    image_api: Box<dyn crate::apis::ImageApi>,
     module_api: Box<dyn crate::apis::ModuleApi>,
     system_information_api: Box<dyn crate::apis::SystemInformationApi>,
 }
@@ -22,6 +23,7 @@ impl APIClient {
                 configuration.clone(),
             )),
             identity_api: Box::new(crate::apis::IdentityApiClient::new(configuration.clone())),
+            image_api: Box::new(crate::apis::ImageApiClient::new(configuration.clone())),
             module_api: Box::new(crate::apis::ModuleApiClient::new(configuration.clone())),
             system_information_api: Box::new(crate::apis::SystemInformationApiClient::new(
                 configuration,
@@ -37,6 +39,10 @@ impl APIClient {
         self.identity_api.as_ref()
     }
 
+    pub fn image_api(&self) -> &dyn crate::apis::ImageApi {
+        self.image_api.as_ref()
+    }
+
     pub fn module_api(&self) -> &dyn crate::apis::ModuleApi {
         self.module_api.as_ref()
     }
@@ -52,6 +52,8 @@ mod device_actions_api;
 pub use self::device_actions_api::{DeviceActionsApi, DeviceActionsApiClient};
 mod identity_api;
 pub use self::identity_api::{IdentityApi, IdentityApiClient};
+mod image_api;
+pub use self::image_api::{ImageApi, ImageApiClient};
 mod module_api;
 pub use self::module_api::{ModuleApi, ModuleApiClient};
 mod system_information_api;
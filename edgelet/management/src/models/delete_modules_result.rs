@@ -0,0 +1,53 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteModulesResult {
+    #[serde(rename = "succeeded")]
+    succeeded: Vec<String>,
+    #[serde(rename = "failed")]
+    failed: Vec<crate::models::ModuleFailure>,
+}
+
+impl DeleteModulesResult {
+    pub fn new(succeeded: Vec<String>, failed: Vec<crate::models::ModuleFailure>) -> Self {
+        DeleteModulesResult { succeeded, failed }
+    }
+
+    pub fn set_succeeded(&mut self, succeeded: Vec<String>) {
+        self.succeeded = succeeded;
+    }
+
+    pub fn with_succeeded(mut self, succeeded: Vec<String>) -> Self {
+        self.succeeded = succeeded;
+        self
+    }
+
+    pub fn succeeded(&self) -> &[String] {
+        &self.succeeded
+    }
+
+    pub fn set_failed(&mut self, failed: Vec<crate::models::ModuleFailure>) {
+        self.failed = failed;
+    }
+
+    pub fn with_failed(mut self, failed: Vec<crate::models::ModuleFailure>) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    pub fn failed(&self) -> &[crate::models::ModuleFailure] {
+        &self.failed
+    }
+}
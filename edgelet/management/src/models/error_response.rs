@@ -14,13 +14,38 @@ use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
+    #[serde(rename = "code", skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
     #[serde(rename = "message")]
     message: String,
+    #[serde(rename = "details", skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
 }
 
 impl ErrorResponse {
     pub fn new(message: String) -> Self {
-        ErrorResponse { message }
+        ErrorResponse {
+            code: None,
+            message,
+            details: None,
+        }
+    }
+
+    pub fn set_code(&mut self, code: String) {
+        self.code = Some(code);
+    }
+
+    pub fn with_code(mut self, code: String) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_code(&mut self) {
+        self.code = None;
     }
 
     pub fn set_message(&mut self, message: String) {
@@ -35,4 +60,21 @@ impl ErrorResponse {
     pub fn message(&self) -> &String {
         &self.message
     }
+
+    pub fn set_details(&mut self, details: Value) {
+        self.details = Some(details);
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        self.details.as_ref()
+    }
+
+    pub fn reset_details(&mut self) {
+        self.details = None;
+    }
 }
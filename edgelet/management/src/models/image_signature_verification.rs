@@ -0,0 +1,72 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageSignatureVerification {
+    #[serde(rename = "type")]
+    _type: String,
+    #[serde(rename = "url")]
+    url: String,
+    #[serde(rename = "publicKey")]
+    public_key: String,
+}
+
+impl ImageSignatureVerification {
+    pub fn new(_type: String, url: String, public_key: String) -> Self {
+        ImageSignatureVerification {
+            _type,
+            url,
+            public_key,
+        }
+    }
+
+    pub fn set_type(&mut self, _type: String) {
+        self._type = _type;
+    }
+
+    pub fn with_type(mut self, _type: String) -> Self {
+        self._type = _type;
+        self
+    }
+
+    pub fn _type(&self) -> &String {
+        &self._type
+    }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    pub fn with_url(mut self, url: String) -> Self {
+        self.url = url;
+        self
+    }
+
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    pub fn set_public_key(&mut self, public_key: String) {
+        self.public_key = public_key;
+    }
+
+    pub fn with_public_key(mut self, public_key: String) -> Self {
+        self.public_key = public_key;
+        self
+    }
+
+    pub fn public_key(&self) -> &String {
+        &self.public_key
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2019-11-05
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageSize {
+    /// The size of the image's own layers.
+    #[serde(rename = "compressed_bytes")]
+    compressed_bytes: i64,
+    /// The size of the image together with the layers it shares with its parent images.
+    #[serde(rename = "uncompressed_bytes")]
+    uncompressed_bytes: i64,
+    #[serde(rename = "layers")]
+    layers: i32,
+}
+
+impl ImageSize {
+    pub fn new(compressed_bytes: i64, uncompressed_bytes: i64, layers: i32) -> Self {
+        ImageSize {
+            compressed_bytes,
+            uncompressed_bytes,
+            layers,
+        }
+    }
+
+    pub fn set_compressed_bytes(&mut self, compressed_bytes: i64) {
+        self.compressed_bytes = compressed_bytes;
+    }
+
+    pub fn with_compressed_bytes(mut self, compressed_bytes: i64) -> Self {
+        self.compressed_bytes = compressed_bytes;
+        self
+    }
+
+    pub fn compressed_bytes(&self) -> i64 {
+        self.compressed_bytes
+    }
+
+    pub fn set_uncompressed_bytes(&mut self, uncompressed_bytes: i64) {
+        self.uncompressed_bytes = uncompressed_bytes;
+    }
+
+    pub fn with_uncompressed_bytes(mut self, uncompressed_bytes: i64) -> Self {
+        self.uncompressed_bytes = uncompressed_bytes;
+        self
+    }
+
+    pub fn uncompressed_bytes(&self) -> i64 {
+        self.uncompressed_bytes
+    }
+
+    pub fn set_layers(&mut self, layers: i32) {
+        self.layers = layers;
+    }
+
+    pub fn with_layers(mut self, layers: i32) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn layers(&self) -> i32 {
+        self.layers
+    }
+}
@@ -1,5 +1,7 @@
 mod config;
 pub use self::config::Config;
+mod delete_modules_result;
+pub use self::delete_modules_result::DeleteModulesResult;
 mod env_var;
 pub use self::env_var::EnvVar;
 mod error_response;
@@ -8,6 +10,10 @@ mod exit_status;
 pub use self::exit_status::ExitStatus;
 mod identity;
 pub use self::identity::Identity;
+mod image_size;
+pub use self::image_size::ImageSize;
+mod image_signature_verification;
+pub use self::image_signature_verification::ImageSignatureVerification;
 mod identity_list;
 pub use self::identity_list::IdentityList;
 mod identity_spec;
@@ -16,16 +22,24 @@ mod update_identity;
 pub use self::update_identity::UpdateIdentity;
 mod module_details;
 pub use self::module_details::ModuleDetails;
+mod module_failure;
+pub use self::module_failure::ModuleFailure;
 mod module_list;
 pub use self::module_list::ModuleList;
 mod module_spec;
 pub use self::module_spec::ModuleSpec;
+mod prune_images_result;
+pub use self::prune_images_result::PruneImagesResult;
+mod registry_auth;
+pub use self::registry_auth::RegistryAuth;
 mod runtime_status;
 pub use self::runtime_status::RuntimeStatus;
 mod status;
 pub use self::status::Status;
 mod system_info;
 pub use self::system_info::SystemInfo;
+mod volume;
+pub use self::volume::Volume;
 
 // TODO(farcaller): sort out files
 pub struct File;
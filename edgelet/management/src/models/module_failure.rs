@@ -0,0 +1,53 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleFailure {
+    #[serde(rename = "id")]
+    id: String,
+    #[serde(rename = "error")]
+    error: String,
+}
+
+impl ModuleFailure {
+    pub fn new(id: String, error: String) -> Self {
+        ModuleFailure { id, error }
+    }
+
+    pub fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = error;
+    }
+
+    pub fn with_error(mut self, error: String) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn error(&self) -> &String {
+        &self.error
+    }
+}
@@ -23,6 +23,19 @@ pub struct ModuleSpec {
     config: crate::models::Config,
     #[serde(rename = "imagePullPolicy", skip_serializing_if = "Option::is_none")]
     image_pull_policy: Option<String>,
+    #[serde(rename = "logLevel", skip_serializing_if = "Option::is_none")]
+    log_level: Option<String>,
+    #[serde(rename = "capabilities", skip_serializing_if = "Option::is_none")]
+    capabilities: Option<Vec<String>>,
+    #[serde(rename = "volumes", skip_serializing_if = "Option::is_none")]
+    volumes: Option<Vec<crate::models::Volume>>,
+    #[serde(
+        rename = "imageSignatureVerification",
+        skip_serializing_if = "Option::is_none"
+    )]
+    image_signature_verification: Option<crate::models::ImageSignatureVerification>,
+    #[serde(rename = "registryAuth", skip_serializing_if = "Option::is_none")]
+    registry_auth: Option<crate::models::RegistryAuth>,
 }
 
 impl ModuleSpec {
@@ -32,6 +45,11 @@ impl ModuleSpec {
             type_,
             config,
             image_pull_policy: None,
+            log_level: None,
+            capabilities: None,
+            volumes: None,
+            image_signature_verification: None,
+            registry_auth: None,
         }
     }
 
@@ -90,4 +108,97 @@ impl ModuleSpec {
     pub fn reset_image_pull_policy(&mut self) {
         self.image_pull_policy = None;
     }
+
+    pub fn set_log_level(&mut self, log_level: String) {
+        self.log_level = Some(log_level);
+    }
+
+    pub fn with_log_level(mut self, log_level: String) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+
+    pub fn log_level(&self) -> Option<&str> {
+        self.log_level.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_log_level(&mut self) {
+        self.log_level = None;
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Vec<String>) {
+        self.capabilities = Some(capabilities);
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Vec<String>) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    pub fn capabilities(&self) -> Option<&[String]> {
+        self.capabilities.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_capabilities(&mut self) {
+        self.capabilities = None;
+    }
+
+    pub fn set_volumes(&mut self, volumes: Vec<crate::models::Volume>) {
+        self.volumes = Some(volumes);
+    }
+
+    pub fn with_volumes(mut self, volumes: Vec<crate::models::Volume>) -> Self {
+        self.volumes = Some(volumes);
+        self
+    }
+
+    pub fn volumes(&self) -> Option<&[crate::models::Volume]> {
+        self.volumes.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_volumes(&mut self) {
+        self.volumes = None;
+    }
+
+    pub fn set_image_signature_verification(
+        &mut self,
+        image_signature_verification: crate::models::ImageSignatureVerification,
+    ) {
+        self.image_signature_verification = Some(image_signature_verification);
+    }
+
+    pub fn with_image_signature_verification(
+        mut self,
+        image_signature_verification: crate::models::ImageSignatureVerification,
+    ) -> Self {
+        self.image_signature_verification = Some(image_signature_verification);
+        self
+    }
+
+    pub fn image_signature_verification(
+        &self,
+    ) -> Option<&crate::models::ImageSignatureVerification> {
+        self.image_signature_verification.as_ref()
+    }
+
+    pub fn reset_image_signature_verification(&mut self) {
+        self.image_signature_verification = None;
+    }
+
+    pub fn set_registry_auth(&mut self, registry_auth: crate::models::RegistryAuth) {
+        self.registry_auth = Some(registry_auth);
+    }
+
+    pub fn with_registry_auth(mut self, registry_auth: crate::models::RegistryAuth) -> Self {
+        self.registry_auth = Some(registry_auth);
+        self
+    }
+
+    pub fn registry_auth(&self) -> Option<&crate::models::RegistryAuth> {
+        self.registry_auth.as_ref()
+    }
+
+    pub fn reset_registry_auth(&mut self) {
+        self.registry_auth = None;
+    }
 }
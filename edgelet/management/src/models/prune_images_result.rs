@@ -0,0 +1,57 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2019-11-05
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PruneImagesResult {
+    #[serde(rename = "images_deleted")]
+    images_deleted: Vec<String>,
+    /// The sum of the sizes of the removed images, as reported by the runtime.
+    #[serde(rename = "space_reclaimed")]
+    space_reclaimed: i64,
+}
+
+impl PruneImagesResult {
+    pub fn new(images_deleted: Vec<String>, space_reclaimed: i64) -> Self {
+        PruneImagesResult {
+            images_deleted,
+            space_reclaimed,
+        }
+    }
+
+    pub fn set_images_deleted(&mut self, images_deleted: Vec<String>) {
+        self.images_deleted = images_deleted;
+    }
+
+    pub fn with_images_deleted(mut self, images_deleted: Vec<String>) -> Self {
+        self.images_deleted = images_deleted;
+        self
+    }
+
+    pub fn images_deleted(&self) -> &[String] {
+        &self.images_deleted
+    }
+
+    pub fn set_space_reclaimed(&mut self, space_reclaimed: i64) {
+        self.space_reclaimed = space_reclaimed;
+    }
+
+    pub fn with_space_reclaimed(mut self, space_reclaimed: i64) -> Self {
+        self.space_reclaimed = space_reclaimed;
+        self
+    }
+
+    pub fn space_reclaimed(&self) -> i64 {
+        self.space_reclaimed
+    }
+}
@@ -0,0 +1,85 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use std::fmt;
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistryAuth {
+    #[serde(rename = "server")]
+    server: String,
+    #[serde(rename = "username")]
+    username: String,
+    #[serde(rename = "password")]
+    password: String,
+}
+
+impl RegistryAuth {
+    pub fn new(server: String, username: String, password: String) -> Self {
+        RegistryAuth {
+            server,
+            username,
+            password,
+        }
+    }
+
+    pub fn set_server(&mut self, server: String) {
+        self.server = server;
+    }
+
+    pub fn with_server(mut self, server: String) -> Self {
+        self.server = server;
+        self
+    }
+
+    pub fn server(&self) -> &String {
+        &self.server
+    }
+
+    pub fn set_username(&mut self, username: String) {
+        self.username = username;
+    }
+
+    pub fn with_username(mut self, username: String) -> Self {
+        self.username = username;
+        self
+    }
+
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+
+    pub fn set_password(&mut self, password: String) {
+        self.password = password;
+    }
+
+    pub fn with_password(mut self, password: String) -> Self {
+        self.password = password;
+        self
+    }
+
+    pub fn password(&self) -> &String {
+        &self.password
+    }
+}
+
+// The password is left out so that module specs can be logged.
+impl fmt::Debug for RegistryAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryAuth")
+            .field("server", &self.server)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
@@ -0,0 +1,116 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Volume {
+    #[serde(rename = "type")]
+    _type: String,
+    #[serde(rename = "source", skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(rename = "target")]
+    target: String,
+    #[serde(rename = "readOnly", skip_serializing_if = "Option::is_none")]
+    read_only: Option<bool>,
+    #[serde(rename = "sizeBytes", skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+}
+
+impl Volume {
+    pub fn new(_type: String, target: String) -> Self {
+        Volume {
+            _type,
+            source: None,
+            target,
+            read_only: None,
+            size_bytes: None,
+        }
+    }
+
+    pub fn set_type(&mut self, _type: String) {
+        self._type = _type;
+    }
+
+    pub fn with_type(mut self, _type: String) -> Self {
+        self._type = _type;
+        self
+    }
+
+    pub fn _type(&self) -> &String {
+        &self._type
+    }
+
+    pub fn set_source(&mut self, source: String) {
+        self.source = Some(source);
+    }
+
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_source(&mut self) {
+        self.source = None;
+    }
+
+    pub fn set_target(&mut self, target: String) {
+        self.target = target;
+    }
+
+    pub fn with_target(mut self, target: String) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn target(&self) -> &String {
+        &self.target
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = Some(read_only);
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = Some(read_only);
+        self
+    }
+
+    pub fn read_only(&self) -> Option<bool> {
+        self.read_only
+    }
+
+    pub fn reset_read_only(&mut self) {
+        self.read_only = None;
+    }
+
+    pub fn set_size_bytes(&mut self, size_bytes: u64) {
+        self.size_bytes = Some(size_bytes);
+    }
+
+    pub fn with_size_bytes(mut self, size_bytes: u64) -> Self {
+        self.size_bytes = Some(size_bytes);
+        self
+    }
+
+    pub fn size_bytes(&self) -> Option<u64> {
+        self.size_bytes
+    }
+
+    pub fn reset_size_bytes(&mut self) {
+        self.size_bytes = None;
+    }
+}
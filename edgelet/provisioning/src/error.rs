@@ -24,6 +24,12 @@ pub enum ErrorKind {
     #[fail(display = "Failure during external provisioning. {}", _0)]
     ExternalProvisioning(ExternalProvisioningErrorReason),
 
+    #[fail(display = "Could not create a credential on the FIDO2 authenticator")]
+    Fido2Attestation,
+
+    #[fail(display = "Could not enroll the FIDO2 attestation")]
+    Fido2Enrollment,
+
     #[fail(display = "Invalid value specified for provisioning status")]
     InvalidProvisioningStatus,
 
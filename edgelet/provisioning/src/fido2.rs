@@ -0,0 +1,365 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Experimental provisioning with the attestation of a FIDO2 authenticator, such as a hardware
+//! security key.
+//!
+//! DPS doesn't accept FIDO2 attestation, so the DPS global endpoint must be an enrollment service
+//! that implements these two operations:
+//!
+//! - `POST {scope_id}/registrations/{registration_id}/fido2/challenge` returns a base64-encoded
+//!   challenge as `{"challenge": "..."}`.
+//! - `POST {scope_id}/registrations/{registration_id}/fido2/attestation` takes the attestation
+//!   statement of a credential created for that challenge, and returns the device it assigned
+//!   with a base64-encoded symmetric key for it, as
+//!   `{"deviceId": "...", "assignedHub": "...", "symmetricKey": "...", "substatus": "..."}`.
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use ctap_hid_fido2::{Cfg, FidoKeyHidFactory};
+use failure::{Fail, ResultExt};
+use futures::Future;
+use hyper::Method;
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use dps::registration::DpsTokenSource;
+use edgelet_core::crypto::{Activate, KeyIdentity, MemoryKey, MemoryKeyStore};
+use edgelet_http::client::{Client as HttpClient, ClientImpl};
+
+use crate::error::{Error, ErrorKind};
+use crate::provisioning::{Provision, ProvisioningResult, ReprovisioningStatus};
+
+/// The attestation statement of a credential that an authenticator created.
+#[derive(Clone, Debug)]
+pub struct Fido2Attestation {
+    fmt: String,
+    auth_data: Vec<u8>,
+    alg: i32,
+    sig: Vec<u8>,
+    x5c: Vec<Vec<u8>>,
+}
+
+impl Fido2Attestation {
+    pub fn new(fmt: String, auth_data: Vec<u8>, alg: i32, sig: Vec<u8>, x5c: Vec<Vec<u8>>) -> Self {
+        Fido2Attestation {
+            fmt,
+            auth_data,
+            alg,
+            sig,
+            x5c,
+        }
+    }
+}
+
+pub trait Authenticator {
+    /// Creates a credential for the relying party `rp_id`, and returns its attestation over
+    /// `challenge`. This blocks until the user confirms their presence on the authenticator.
+    fn make_credential(&self, rp_id: &str, challenge: &[u8]) -> Result<Fido2Attestation, Error>;
+}
+
+/// The first FIDO2 authenticator connected over USB HID.
+pub struct HidAuthenticator {
+    pin: Option<String>,
+}
+
+impl HidAuthenticator {
+    pub fn new(pin: Option<String>) -> Self {
+        HidAuthenticator { pin }
+    }
+}
+
+impl Authenticator for HidAuthenticator {
+    fn make_credential(&self, rp_id: &str, challenge: &[u8]) -> Result<Fido2Attestation, Error> {
+        let device = FidoKeyHidFactory::create(&Cfg::init()).map_err(authenticator_error)?;
+        let attestation = device
+            .make_credential(rp_id, challenge, self.pin.as_ref().map(AsRef::as_ref))
+            .map_err(authenticator_error)?;
+
+        Ok(Fido2Attestation::new(
+            attestation.fmt,
+            attestation.auth_data,
+            attestation.attstmt_alg,
+            attestation.attstmt_sig,
+            attestation.attstmt_x5c,
+        ))
+    }
+}
+
+/// The authenticator library's errors don't implement `Fail`, so only their message is kept.
+fn authenticator_error<E: Display>(err: E) -> Error {
+    Error::from(failure::err_msg(err.to_string()).context(ErrorKind::Fido2Attestation))
+}
+
+#[derive(Serialize)]
+struct ChallengeRequest {
+    #[serde(rename = "registrationId")]
+    registration_id: String,
+}
+
+#[derive(Deserialize)]
+struct ChallengeResponse {
+    challenge: String,
+}
+
+#[derive(Serialize)]
+struct AttestationRequest {
+    #[serde(rename = "registrationId")]
+    registration_id: String,
+    fmt: String,
+    #[serde(rename = "authData")]
+    auth_data: String,
+    #[serde(rename = "attStmt")]
+    att_stmt: AttestationStatement,
+}
+
+#[derive(Serialize)]
+struct AttestationStatement {
+    alg: i32,
+    sig: String,
+    x5c: Vec<String>,
+}
+
+impl AttestationRequest {
+    fn new(registration_id: String, attestation: Fido2Attestation) -> Self {
+        AttestationRequest {
+            registration_id,
+            fmt: attestation.fmt,
+            auth_data: base64::encode(&attestation.auth_data),
+            att_stmt: AttestationStatement {
+                alg: attestation.alg,
+                sig: base64::encode(&attestation.sig),
+                x5c: attestation.x5c.iter().map(base64::encode).collect(),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EnrollmentResponse {
+    #[serde(rename = "deviceId")]
+    device_id: String,
+    #[serde(rename = "assignedHub")]
+    assigned_hub: String,
+    #[serde(rename = "symmetricKey")]
+    symmetric_key: String,
+    substatus: Option<String>,
+}
+
+pub struct Fido2Provisioning<C, A>
+where
+    C: ClientImpl,
+{
+    client: HttpClient<C, DpsTokenSource<MemoryKey>>,
+    scope_id: String,
+    registration_id: String,
+    relying_party_id: String,
+    authenticator: Arc<A>,
+}
+
+impl<C, A> Fido2Provisioning<C, A>
+where
+    C: ClientImpl,
+{
+    pub fn new(
+        client_impl: C,
+        endpoint: Url,
+        scope_id: String,
+        registration_id: String,
+        relying_party_id: String,
+        authenticator: A,
+        api_version: String,
+    ) -> Result<Self, Error> {
+        let client = HttpClient::new(
+            client_impl,
+            None as Option<DpsTokenSource<MemoryKey>>,
+            api_version,
+            endpoint,
+        )
+        .context(ErrorKind::DpsInitialization)?;
+        Ok(Fido2Provisioning {
+            client,
+            scope_id,
+            registration_id,
+            relying_party_id,
+            authenticator: Arc::new(authenticator),
+        })
+    }
+
+    fn path(&self, operation: &str) -> String {
+        format!(
+            "{}/registrations/{}/fido2/{}",
+            self.scope_id, self.registration_id, operation
+        )
+    }
+}
+
+impl<C, A> Provision for Fido2Provisioning<C, A>
+where
+    C: 'static + ClientImpl,
+    A: 'static + Authenticator + Send + Sync,
+{
+    type Hsm = MemoryKeyStore;
+
+    fn provision(
+        &self,
+        mut key_activator: Self::Hsm,
+    ) -> Box<dyn Future<Item = ProvisioningResult, Error = Error> + Send> {
+        let client = self.client.clone();
+        let attestation_path = self.path("attestation");
+        let registration_id = self.registration_id.clone();
+        let relying_party_id = self.relying_party_id.clone();
+        let authenticator = self.authenticator.clone();
+
+        let challenge = ChallengeRequest {
+            registration_id: self.registration_id.clone(),
+        };
+        let provisioning = self
+            .client
+            .request::<_, ChallengeResponse>(
+                Method::POST,
+                &self.path("challenge"),
+                None,
+                Some(challenge),
+                false,
+            )
+            .map_err(|err| Error::from(err.context(ErrorKind::Fido2Enrollment)))
+            .and_then(move |response| {
+                let response = response.ok_or(ErrorKind::Fido2Enrollment)?;
+                let challenge =
+                    base64::decode(&response.challenge).context(ErrorKind::Fido2Enrollment)?;
+
+                info!("Creating a FIDO2 credential, confirm presence on the authenticator...");
+                let attestation = authenticator.make_credential(&relying_party_id, &challenge)?;
+                Ok(AttestationRequest::new(registration_id, attestation))
+            })
+            .and_then(move |request| {
+                client
+                    .request::<_, EnrollmentResponse>(
+                        Method::POST,
+                        &attestation_path,
+                        None,
+                        Some(request),
+                        false,
+                    )
+                    .map_err(|err| Error::from(err.context(ErrorKind::Fido2Enrollment)))
+            })
+            .and_then(move |response| {
+                let response = response.ok_or(ErrorKind::Fido2Enrollment)?;
+                let key =
+                    base64::decode(&response.symmetric_key).context(ErrorKind::Fido2Enrollment)?;
+                key_activator
+                    .activate_identity_key(KeyIdentity::Device, "primary".to_string(), key)
+                    .context(ErrorKind::Fido2Enrollment)?;
+
+                info!(
+                    "FIDO2 enrollment assigned device \"{}\" in hub \"{}\"",
+                    response.device_id, response.assigned_hub
+                );
+                let reconfigure = response.substatus.map_or_else(
+                    || ReprovisioningStatus::InitialAssignment,
+                    |s| ReprovisioningStatus::from(s.as_ref()),
+                );
+                Ok(ProvisioningResult::new(
+                    &response.device_id,
+                    &response.assigned_hub,
+                    None,
+                    reconfigure,
+                    None,
+                ))
+            });
+
+        Box::new(provisioning)
+    }
+
+    fn reprovision(&self) -> Box<dyn Future<Item = (), Error = Error> + Send> {
+        // No reprovision action is needed, as for the DPS provisioning modes.
+        Box::new(futures::future::ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use edgelet_core::crypto::KeyStore;
+    use edgelet_core::ProvisioningResult as CoreProvisioningResult;
+    use futures::Stream;
+    use hyper::{Body, Request, Response};
+    use serde_json::{json, Value};
+    use tokio::runtime::current_thread::Runtime;
+
+    struct TestAuthenticator;
+
+    impl Authenticator for TestAuthenticator {
+        fn make_credential(
+            &self,
+            rp_id: &str,
+            challenge: &[u8],
+        ) -> Result<Fido2Attestation, Error> {
+            assert_eq!("enroll.example.com", rp_id);
+            Ok(Fido2Attestation::new(
+                "packed".to_string(),
+                challenge.to_vec(),
+                -7,
+                b"signature".to_vec(),
+                vec![b"certificate".to_vec()],
+            ))
+        }
+    }
+
+    #[test]
+    fn provision_submits_attestation_and_activates_key() {
+        let handler = |req: Request<Body>| {
+            let path = req.uri().path().to_string();
+            let body = req.into_body().concat2().wait().unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!("reg1", body["registrationId"]);
+
+            let response = match path.as_str() {
+                "/scope1/registrations/reg1/fido2/challenge" => json!({
+                    "challenge": base64::encode("challenge"),
+                }),
+                "/scope1/registrations/reg1/fido2/attestation" => {
+                    assert_eq!("packed", body["fmt"]);
+                    assert_eq!(base64::encode("challenge"), body["authData"]);
+                    assert_eq!(-7, body["attStmt"]["alg"]);
+                    assert_eq!(base64::encode("certificate"), body["attStmt"]["x5c"][0]);
+                    json!({
+                        "deviceId": "device1",
+                        "assignedHub": "hub1.azure-devices.net",
+                        "symmetricKey": base64::encode("key"),
+                    })
+                }
+                path => panic!("unexpected request to {}", path),
+            };
+            Ok::<_, hyper::Error>(Response::new(Body::from(response.to_string())))
+        };
+
+        let provisioning = Fido2Provisioning::new(
+            handler,
+            Url::parse("https://enroll.example.com").unwrap(),
+            "scope1".to_string(),
+            "reg1".to_string(),
+            "enroll.example.com".to_string(),
+            TestAuthenticator,
+            "2018-11-01".to_string(),
+        )
+        .unwrap();
+        let key_store = MemoryKeyStore::new();
+
+        let result = Runtime::new()
+            .unwrap()
+            .block_on(provisioning.provision(key_store.clone()))
+            .unwrap();
+
+        assert_eq!("device1", result.device_id());
+        assert_eq!("hub1.azure-devices.net", result.hub_name());
+        assert_eq!(
+            ReprovisioningStatus::InitialAssignment,
+            result.reconfigure()
+        );
+        assert!(key_store.get(&KeyIdentity::Device, "primary").is_ok());
+    }
+}
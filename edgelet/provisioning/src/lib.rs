@@ -11,9 +11,13 @@
 )]
 
 pub mod error;
+#[cfg(feature = "fido2")]
+pub mod fido2;
 pub mod provisioning;
 
 pub use crate::error::Error;
+#[cfg(feature = "fido2")]
+pub use crate::fido2::{Authenticator, Fido2Attestation, Fido2Provisioning, HidAuthenticator};
 pub use crate::provisioning::{
     AuthType, BackupProvisioning, Credentials, DpsSymmetricKeyProvisioning, DpsTpmProvisioning,
     DpsX509Provisioning, Provision, ProvisioningResult, ProvisioningStatus, ReprovisioningStatus,
@@ -12,8 +12,14 @@ mod error_response;
 pub use self::error_response::ErrorResponse;
 mod identity_certificate_request;
 pub use self::identity_certificate_request::IdentityCertificateRequest;
+mod module_token_response;
+pub use self::module_token_response::ModuleTokenResponse;
 mod private_key;
 pub use self::private_key::PrivateKey;
+mod rotate_secret_response;
+pub use self::rotate_secret_response::RotateSecretResponse;
+mod secret;
+pub use self::secret::Secret;
 mod server_certificate_request;
 pub use self::server_certificate_request::ServerCertificateRequest;
 mod sign_request;
@@ -0,0 +1,39 @@
+/*
+ * IoT Edge Module Workload API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateSecretResponse {
+    /// The key version the secret is now encrypted under.
+    #[serde(rename = "keyVersion")]
+    key_version: i32,
+}
+
+impl RotateSecretResponse {
+    pub fn new(key_version: i32) -> Self {
+        RotateSecretResponse { key_version }
+    }
+
+    pub fn set_key_version(&mut self, key_version: i32) {
+        self.key_version = key_version;
+    }
+
+    pub fn with_key_version(mut self, key_version: i32) -> Self {
+        self.key_version = key_version;
+        self
+    }
+
+    pub fn key_version(&self) -> i32 {
+        self.key_version
+    }
+}